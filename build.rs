@@ -37,13 +37,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_server(true)
         .out_dir("src/grpc/protobuf")
-        .compile_with_config(config, &["src/grpc/protobuf/uniffle.proto"], &["."])?;
+        .compile_with_config(
+            config,
+            &[
+                "src/grpc/protobuf/uniffle.proto",
+                "src/grpc/protobuf/health.proto",
+            ],
+            &["."],
+        )?;
 
     // rename the generated filename to uniffle.rs
     rename_file(
         "src/grpc/protobuf/rss.common.rs",
         "src/grpc/protobuf/uniffle.rs",
     );
+    // rename the generated filename of the standard grpc health service to health.rs
+    rename_file(
+        "src/grpc/protobuf/grpc.health.v1.rs",
+        "src/grpc/protobuf/health.rs",
+    );
 
     Ok(())
 }
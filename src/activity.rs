@@ -0,0 +1,315 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The hourly activity log of the last 7 days for the capacity planning, which is
+//! independent of the prometheus retention and is persisted to survive the restarts.
+
+use crate::app::AppManagerRef;
+use crate::runtime::manager::RuntimeManager;
+use crate::util;
+use crate::util::now_timestamp_as_sec;
+use anyhow::Result;
+use await_tree::InstrumentAwait;
+use log::{error, info};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::SeqCst;
+use std::time::Duration;
+
+pub const ACTIVITY_SLOT_NUM: u64 = 168;
+const SECONDS_PER_HOUR: u64 = 3600;
+
+const ACTIVITY_SAMPLE_INTERVAL_SEC: u64 = 10;
+const ACTIVITY_FLUSH_INTERVAL_SEC: u64 = 300;
+
+pub static ACTIVITY_LOG: Lazy<ActivityLog> = Lazy::new(|| ActivityLog::new());
+
+#[derive(Default)]
+struct ActivitySlot {
+    // the hours since epoch, the slot is reset once the hour is changed
+    hour: AtomicU64,
+    written_bytes: AtomicU64,
+    read_bytes: AtomicU64,
+    peak_memory_used: AtomicU64,
+    // stored as the bits of f64. The order of bits is consistent with the non-negative f64.
+    peak_disk_used_ratio: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HourlyActivity {
+    pub hour_timestamp_sec: u64,
+    pub written_bytes: u64,
+    pub read_bytes: u64,
+    pub peak_memory_used: u64,
+    pub peak_disk_used_ratio: f64,
+}
+
+pub struct ActivityLog {
+    slots: Vec<ActivitySlot>,
+    // refreshed by the background sampler, to select the slot cheaply in the hot path
+    cached_hour: AtomicU64,
+}
+
+impl ActivityLog {
+    fn new() -> Self {
+        Self {
+            slots: (0..ACTIVITY_SLOT_NUM).map(|_| Default::default()).collect(),
+            cached_hour: AtomicU64::new(now_timestamp_as_sec() / SECONDS_PER_HOUR),
+        }
+    }
+
+    fn slot(&self, hour: u64) -> &ActivitySlot {
+        let slot = &self.slots[(hour % ACTIVITY_SLOT_NUM) as usize];
+        let previous = slot.hour.load(SeqCst);
+        if previous != hour
+            && slot
+                .hour
+                .compare_exchange(previous, hour, SeqCst, SeqCst)
+                .is_ok()
+        {
+            slot.written_bytes.store(0, SeqCst);
+            slot.read_bytes.store(0, SeqCst);
+            slot.peak_memory_used.store(0, SeqCst);
+            slot.peak_disk_used_ratio.store(0, SeqCst);
+        }
+        slot
+    }
+
+    /// Refreshes the cached hour by the given timestamp.
+    pub fn tick(&self, timestamp_sec: u64) {
+        self.cached_hour
+            .store(timestamp_sec / SECONDS_PER_HOUR, SeqCst);
+    }
+
+    pub fn record_written(&self, bytes: u64) {
+        self.slot(self.cached_hour.load(SeqCst))
+            .written_bytes
+            .fetch_add(bytes, SeqCst);
+    }
+
+    pub fn record_read(&self, bytes: u64) {
+        self.slot(self.cached_hour.load(SeqCst))
+            .read_bytes
+            .fetch_add(bytes, SeqCst);
+    }
+
+    pub fn record_usage(&self, memory_used: u64, disk_used_ratio: f64) {
+        let slot = self.slot(self.cached_hour.load(SeqCst));
+        slot.peak_memory_used.fetch_max(memory_used, SeqCst);
+        slot.peak_disk_used_ratio
+            .fetch_max(disk_used_ratio.max(0f64).to_bits(), SeqCst);
+    }
+
+    /// Dumps the activities of the last 168 hours in the ascending order of hour.
+    pub fn dump(&self) -> Vec<HourlyActivity> {
+        let current_hour = self.cached_hour.load(SeqCst);
+        let mut activities: Vec<HourlyActivity> = self
+            .slots
+            .iter()
+            .filter(|slot| {
+                let hour = slot.hour.load(SeqCst);
+                hour != 0 && hour <= current_hour && current_hour - hour < ACTIVITY_SLOT_NUM
+            })
+            .map(|slot| HourlyActivity {
+                hour_timestamp_sec: slot.hour.load(SeqCst) * SECONDS_PER_HOUR,
+                written_bytes: slot.written_bytes.load(SeqCst),
+                read_bytes: slot.read_bytes.load(SeqCst),
+                peak_memory_used: slot.peak_memory_used.load(SeqCst),
+                peak_disk_used_ratio: f64::from_bits(slot.peak_disk_used_ratio.load(SeqCst)),
+            })
+            .collect();
+        activities.sort_by_key(|activity| activity.hour_timestamp_sec);
+        activities
+    }
+
+    /// Restores the persisted activities, the ones out of the window are ignored.
+    fn restore(&self, activities: Vec<HourlyActivity>) {
+        let current_hour = self.cached_hour.load(SeqCst);
+        for activity in activities {
+            let hour = activity.hour_timestamp_sec / SECONDS_PER_HOUR;
+            if hour > current_hour || current_hour - hour >= ACTIVITY_SLOT_NUM {
+                continue;
+            }
+            let slot = self.slot(hour);
+            slot.written_bytes.fetch_add(activity.written_bytes, SeqCst);
+            slot.read_bytes.fetch_add(activity.read_bytes, SeqCst);
+            slot.peak_memory_used
+                .fetch_max(activity.peak_memory_used, SeqCst);
+            slot.peak_disk_used_ratio
+                .fetch_max(activity.peak_disk_used_ratio.max(0f64).to_bits(), SeqCst);
+        }
+    }
+
+    pub fn load(&self, path: &str) -> Result<()> {
+        if !Path::new(path).exists() {
+            return Ok(());
+        }
+        let activities: Vec<HourlyActivity> = serde_json::from_slice(&std::fs::read(path)?)?;
+        self.restore(activities);
+        Ok(())
+    }
+
+    pub fn flush(&self, path: &str) -> Result<()> {
+        util::write_atomically(path, &serde_json::to_vec(&self.dump())?)?;
+        Ok(())
+    }
+
+    /// Starts the background sampler of the memory and disk usage, which also flushes the
+    /// activities to the given path periodically.
+    pub fn start(
+        &'static self,
+        path: Option<String>,
+        runtime_manager: &RuntimeManager,
+        app_manager_ref: &AppManagerRef,
+    ) {
+        if let Some(path) = path.as_ref() {
+            if let Err(err) = self.load(path) {
+                error!(
+                    "Errors on loading the activity log from: {}. err: {}",
+                    path, err
+                );
+            }
+        }
+        let app_manager_ref = app_manager_ref.clone();
+        runtime_manager
+            .default_runtime
+            .spawn_with_await_tree("Activity log sampler", async move {
+                info!("Starting the activity log sampler...");
+                let mut elapsed = 0;
+                loop {
+                    tokio::time::sleep(Duration::from_secs(ACTIVITY_SAMPLE_INTERVAL_SEC))
+                        .instrument_await("sleeping")
+                        .await;
+                    self.tick(now_timestamp_as_sec());
+
+                    let memory_used = match app_manager_ref.store_memory_snapshot().await {
+                        Ok(snapshot) => snapshot.used().max(0) as u64,
+                        _ => 0,
+                    };
+                    let disk_used_ratio = app_manager_ref
+                        .store_localfile_stat()
+                        .map(|stat| {
+                            stat.stats
+                                .iter()
+                                .map(|disk| disk.used_ratio)
+                                .fold(0f64, f64::max)
+                        })
+                        .unwrap_or(0f64);
+                    self.record_usage(memory_used, disk_used_ratio);
+
+                    elapsed += ACTIVITY_SAMPLE_INTERVAL_SEC;
+                    if elapsed < ACTIVITY_FLUSH_INTERVAL_SEC {
+                        continue;
+                    }
+                    elapsed = 0;
+                    if let Some(path) = path.as_ref() {
+                        if let Err(err) = self.flush(path) {
+                            error!(
+                                "Errors on flushing the activity log to: {}. err: {}",
+                                path, err
+                            );
+                        }
+                    }
+                }
+            });
+    }
+}
+
+pub fn to_csv(activities: &[HourlyActivity]) -> String {
+    let mut csv = String::from(
+        "hour_timestamp_sec,written_bytes,read_bytes,peak_memory_used,peak_disk_used_ratio\n",
+    );
+    for activity in activities {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{}",
+            activity.hour_timestamp_sec,
+            activity.written_bytes,
+            activity.read_bytes,
+            activity.peak_memory_used,
+            activity.peak_disk_used_ratio
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use crate::activity::{to_csv, ActivityLog, ACTIVITY_SLOT_NUM, SECONDS_PER_HOUR};
+
+    #[test]
+    fn test_activity_log() -> anyhow::Result<()> {
+        let log = ActivityLog::new();
+        let base = 1_700_000_000 / SECONDS_PER_HOUR * SECONDS_PER_HOUR;
+
+        // case1: the activities across the hour boundary
+        log.tick(base + SECONDS_PER_HOUR - 1);
+        log.record_written(10);
+        log.record_read(5);
+        log.record_usage(100, 0.2);
+        log.record_usage(50, 0.5);
+        log.tick(base + SECONDS_PER_HOUR);
+        log.record_written(20);
+
+        let activities = log.dump();
+        assert_eq!(2, activities.len());
+        assert_eq!(base, activities[0].hour_timestamp_sec);
+        assert_eq!(10, activities[0].written_bytes);
+        assert_eq!(5, activities[0].read_bytes);
+        assert_eq!(100, activities[0].peak_memory_used);
+        assert_eq!(0.5, activities[0].peak_disk_used_ratio);
+        assert_eq!(base + SECONDS_PER_HOUR, activities[1].hour_timestamp_sec);
+        assert_eq!(20, activities[1].written_bytes);
+
+        // case2: the ring wraps after 168 hours, and the slot of the first hour is reused
+        log.tick(base + ACTIVITY_SLOT_NUM * SECONDS_PER_HOUR);
+        log.record_written(30);
+        let activities = log.dump();
+        assert_eq!(2, activities.len());
+        assert_eq!(base + SECONDS_PER_HOUR, activities[0].hour_timestamp_sec);
+        assert_eq!(
+            base + ACTIVITY_SLOT_NUM * SECONDS_PER_HOUR,
+            activities[1].hour_timestamp_sec
+        );
+        assert_eq!(30, activities[1].written_bytes);
+        assert_eq!(0, activities[1].read_bytes);
+
+        // case3: csv
+        let csv = to_csv(&activities[1..]);
+        assert_eq!(
+            format!(
+                "hour_timestamp_sec,written_bytes,read_bytes,peak_memory_used,peak_disk_used_ratio\n{},30,0,0,0\n",
+                base + ACTIVITY_SLOT_NUM * SECONDS_PER_HOUR
+            ),
+            csv
+        );
+
+        // case4: restore from the flushed file
+        let temp_dir = tempdir::TempDir::new("test_activity_log")?;
+        let path = format!("{}/activity.json", temp_dir.path().to_str().unwrap());
+        log.flush(&path)?;
+        let restored = ActivityLog::new();
+        restored.tick(base + ACTIVITY_SLOT_NUM * SECONDS_PER_HOUR);
+        restored.load(&path)?;
+        assert_eq!(activities, restored.dump());
+
+        Ok(())
+    }
+}
@@ -15,28 +15,41 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::config::{Config, StorageType};
+use crate::activity::ACTIVITY_LOG;
+use crate::chaos::{FaultOperation, FAULT_INJECTOR};
+use crate::config::{Config, DiskPlacementMode, StorageType};
 use crate::error::WorkerError;
 use crate::metric::{
-    BLOCK_ID_NUMBER, GAUGE_APP_NUMBER, GAUGE_HUGE_PARTITION_NUMBER, GAUGE_PARTITION_NUMBER,
-    GAUGE_TOPN_APP_RESIDENT_BYTES, PURGE_FAILED_COUNTER, RESIDENT_BYTES, TOTAL_APP_FLUSHED_BYTES,
-    TOTAL_APP_NUMBER, TOTAL_HUGE_PARTITION_NUMBER, TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED,
+    BLOCK_ID_NUMBER, GAUGE_APP_EXPECTED_SIZE_PROGRESS, GAUGE_APP_NUMBER,
+    GAUGE_DUPLICATE_BLOCK_REPORTS, GAUGE_HUGE_PARTITION_NUMBER, GAUGE_PARTITION_NUMBER,
+    GAUGE_RESIDENT_BYTES_BY_TIER, GAUGE_TOPN_APP_RESIDENT_BYTES, PURGE_FAILED_COUNTER,
+    RESIDENT_BYTES, TOTAL_APP_FLUSHED_BYTES, TOTAL_APP_NUMBER, TOTAL_APP_QUOTA_HARD_LIMIT_REJECTED,
+    TOTAL_APP_QUOTA_SOFT_LIMIT_EXCEEDED, TOTAL_APP_WRITE_RATE_LIMITED_BYTES,
+    TOTAL_EXPIRED_SPILLED_FILE_RECLAIMED_BYTES, TOTAL_HUGE_PARTITION_NUMBER,
+    TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED, TOTAL_PARTITIONS_BEYOND_APP_LIMIT,
     TOTAL_PARTITION_NUMBER, TOTAL_READ_DATA, TOTAL_READ_DATA_FROM_LOCALFILE,
     TOTAL_READ_DATA_FROM_MEMORY, TOTAL_READ_INDEX_FROM_LOCALFILE, TOTAL_RECEIVED_DATA,
-    TOTAL_REQUIRE_BUFFER_FAILED,
+    TOTAL_REPORTED_BLOCK_ID_COUNT_MISMATCH, TOTAL_REQUIRE_BUFFER_FAILED,
+    TOTAL_WRITE_EXCEEDS_RESERVATION,
 };
 
 use crate::readable_size::ReadableSize;
+use crate::replication::Replicator;
 use crate::runtime::manager::RuntimeManager;
 use crate::store::hybrid::HybridStore;
-use crate::store::{Block, RequireBufferResponse, ResponseData, ResponseDataIndex, Store};
-use crate::util::{now_timestamp_as_millis, now_timestamp_as_sec};
+use crate::store::{
+    Block, PartitionFlushState, PartitionLocation, RequireBufferResponse, ResponseData,
+    ResponseDataIndex, Store,
+};
+use crate::util::{now_monotonic_as_sec, now_timestamp_as_millis};
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
 use croaring::{JvmLegacy, Treemap};
 
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 
 use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -45,16 +58,19 @@ use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::str::FromStr;
 
+use crate::app_meta_snapshot::{AppMetaSnapshot, MetadataSnapshot, PartitionMetaSnapshot};
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::block_id_manager::{get_block_id_manager, BlockIdManager};
 use crate::config_reconfigure::{ByteString, ConfRef, ReconfigurableConfManager};
 use crate::constant::ALL_LABEL;
-use crate::grpc::protobuf::uniffle::{BlockIdLayout, RemoteStorage};
+use crate::grpc::protobuf::uniffle::{BlockIdLayout, RemoteStorage, RemoteStorageConfItem};
 use crate::historical_apps::HistoricalAppStatistics;
 use crate::id_layout::IdLayout;
 use crate::storage::HybridStorage;
 use crate::store::local::LocalfileStoreStat;
+use crate::store::localfile::{IndexRepairOutcome, RebalanceProgress};
 use crate::store::mem::capacity::CapacitySnapshot;
+use crate::timeline::{TimelineCategory, TIMELINE};
 use crate::util;
 use await_tree::InstrumentAwait;
 use crossbeam::epoch::Atomic;
@@ -63,9 +79,9 @@ use parking_lot::RwLock;
 use prometheus::core::Collector;
 use prometheus::proto::MetricType::GAUGE;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::Instrument;
 
 pub static SHUFFLE_SERVER_ID: OnceLock<String> = OnceLock::new();
@@ -73,7 +89,7 @@ pub static SHUFFLE_SERVER_IP: OnceLock<String> = OnceLock::new();
 
 pub static APP_MANAGER_REF: OnceCell<AppManagerRef> = OnceCell::new();
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DataDistribution {
     NORMAL,
     #[allow(non_camel_case_types)]
@@ -82,11 +98,23 @@ pub enum DataDistribution {
 
 pub const MAX_CONCURRENCY_PER_PARTITION_TO_WRITE: i32 = 20;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct AppConfigOptions {
+    // the user submitting the app
+    pub user: String,
     pub data_distribution: DataDistribution,
     pub max_concurrency_per_partition_to_write: i32,
     pub remote_storage_config_option: Option<RemoteStorageConfig>,
+    // verify the per-partition crc aggregates of client and server side when committing
+    pub consistency_check_enable: bool,
+    // the hint of the total shuffle bytes of this app from the client side
+    pub expected_total_bytes: Option<u64>,
+    // the per-app storage quota overriding the global app_config
+    pub quota_soft_limit: Option<u64>,
+    pub quota_hard_limit: Option<u64>,
+    // the disk placement mode overriding the localfile store config, sticky after registry
+    pub disk_placement_mode: Option<DiskPlacementMode>,
 }
 
 impl AppConfigOptions {
@@ -96,9 +124,15 @@ impl AppConfigOptions {
         remote_storage_config_option: Option<RemoteStorageConfig>,
     ) -> Self {
         Self {
+            user: "".to_string(),
             data_distribution,
             max_concurrency_per_partition_to_write,
             remote_storage_config_option,
+            consistency_check_enable: false,
+            expected_total_bytes: None,
+            quota_soft_limit: None,
+            quota_hard_limit: None,
+            disk_placement_mode: None,
         }
     }
 }
@@ -106,16 +140,22 @@ impl AppConfigOptions {
 impl Default for AppConfigOptions {
     fn default() -> Self {
         AppConfigOptions {
+            user: "".to_string(),
             data_distribution: DataDistribution::LOCAL_ORDER,
             max_concurrency_per_partition_to_write: 20,
             remote_storage_config_option: None,
+            consistency_check_enable: false,
+            expected_total_bytes: None,
+            quota_soft_limit: None,
+            quota_hard_limit: None,
+            disk_placement_mode: None,
         }
     }
 }
 
 // =============================================================
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct RemoteStorageConfig {
     pub root: String,
     pub configs: HashMap<String, String>,
@@ -136,6 +176,19 @@ impl From<RemoteStorage> for RemoteStorageConfig {
     }
 }
 
+impl From<RemoteStorageConfig> for RemoteStorage {
+    fn from(remote_conf: RemoteStorageConfig) -> Self {
+        Self {
+            path: remote_conf.root,
+            remote_storage_conf: remote_conf
+                .configs
+                .into_iter()
+                .map(|(key, value)| RemoteStorageConfItem { key, value })
+                .collect(),
+        }
+    }
+}
+
 // =============================================================
 
 pub struct App {
@@ -144,7 +197,11 @@ pub struct App {
     latest_heartbeat_time: AtomicU64,
     store: Arc<HybridStore>,
 
-    memory_capacity: u64,
+    // the remote storage config could be rotated for the expired credentials
+    remote_storage_config: RwLock<Option<RemoteStorageConfig>>,
+
+    // the typed limits parsed once on registration
+    limits: AppLimits,
 
     // partition limitation
     partition_limit_enable: bool,
@@ -152,7 +209,7 @@ pub struct App {
     partition_limit_mem_backpressure_ratio: ConfRef<f64>,
 
     total_received_data_size: AtomicU64,
-    total_resident_data_size: AtomicU64,
+    total_resident_data_size: Arc<AtomicU64>,
 
     // when exceeding the partition-limit-threshold, it will be marked as huge partition
     huge_partition_number: AtomicU64,
@@ -171,6 +228,139 @@ pub struct App {
 
     // reconfiguration manager
     reconf_manager: ReconfigurableConfManager,
+
+    // per-app write rate limit
+    write_rate_limiter: Option<AppWriteRateLimiter>,
+
+    // per-app storage quota
+    quota_soft_limit_exceeded: AtomicBool,
+
+    write_reservation_check_enable: bool,
+
+    // key: (shuffle_id, partition_id). only for the consistency check
+    partition_crc_aggregates: DashMap<(i32, i32), Arc<PartitionCrcAggregate>>,
+
+    // the tracker of the in-progress purge
+    purge_tracker: parking_lot::Mutex<Option<PurgeProgressTracker>>,
+
+    // shared with the app manager, the writing is rejected when it's true
+    read_only: Arc<AtomicBool>,
+
+    // the registered shuffle ids, whose number is limited by the max_shuffles
+    registered_shuffles: DashMap<i32, ()>,
+    max_shuffles: Option<usize>,
+
+    // the soft cap of the partition_meta_infos entries, and whether to reject beyond it
+    max_partitions: Option<usize>,
+    reject_partitions_beyond_max: bool,
+
+    // forwarding the accepted blocks to the peer server
+    replicator: Option<Replicator>,
+}
+
+/// The order-independent sum of the blocks' crc of one partition. The expected one is
+/// reported by the client side, and the actual one is maintained by the stored blocks.
+#[derive(Default)]
+struct PartitionCrcAggregate {
+    expected: AtomicI64,
+    actual: AtomicI64,
+}
+
+fn crc_aggregate<'a>(blocks: impl Iterator<Item = &'a Block>) -> i64 {
+    blocks.fold(0i64, |acc, block| acc.wrapping_add(block.crc))
+}
+
+const APP_WRITE_RATE_LIMITER_REFILL_INTERVAL_MS: u64 = 100;
+
+// the ratio of the already present ids in one report to be warned
+const DUPLICATE_BLOCK_IDS_WARN_RATIO: f64 = 0.5;
+
+/// The typed size limits of the app, which are parsed and validated once on the registration,
+/// so the checks in the writing path never parse the raw strings. The reconfigurable
+/// thresholds are held by the [`ConfRef`], whose typed value is only replaced on change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppLimits {
+    pub memory_capacity: u64,
+    pub write_rate_limit: Option<u64>,
+    // the per-app options override the global app_config
+    pub quota_soft_limit: Option<u64>,
+    pub quota_hard_limit: Option<u64>,
+}
+
+impl AppLimits {
+    pub fn parse(config: &Config, options: &AppConfigOptions) -> Result<Self, WorkerError> {
+        let parse = |key: &str, raw: &str| {
+            util::try_parse_raw_to_bytesize(raw)
+                .map_err(|err| WorkerError::INVALID_APP_CONFIG(format!("{}. err: {}", key, err)))
+        };
+        let parse_option = |key: &str, raw: &Option<String>| match raw {
+            Some(raw) => parse(key, raw).map(Some),
+            _ => Ok(None),
+        };
+        let memory_capacity = match &config.memory_store {
+            Some(memory_store) => parse("memory_store.capacity", &memory_store.capacity)?,
+            _ => 0,
+        };
+        let app_config = &config.app_config;
+        Ok(Self {
+            memory_capacity,
+            write_rate_limit: parse_option(
+                "app_config.app_write_rate_limit",
+                &app_config.app_write_rate_limit,
+            )?,
+            quota_soft_limit: match options.quota_soft_limit {
+                Some(limit) => Some(limit),
+                _ => parse_option(
+                    "app_config.app_quota_soft_limit",
+                    &app_config.app_quota_soft_limit,
+                )?,
+            },
+            quota_hard_limit: match options.quota_hard_limit {
+                Some(limit) => Some(limit),
+                _ => parse_option(
+                    "app_config.app_quota_hard_limit",
+                    &app_config.app_quota_hard_limit,
+                )?,
+            },
+        })
+    }
+}
+
+/// The token bucket of the app write bytes, whose capacity is the bytes of one second.
+/// And the tokens will be refilled by the background task of [`AppManager`].
+struct AppWriteRateLimiter {
+    capacity: u64,
+    tokens: AtomicU64,
+}
+
+impl AppWriteRateLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            capacity: bytes_per_sec,
+            tokens: AtomicU64::new(bytes_per_sec),
+        }
+    }
+
+    // the full bucket always allows the acquirement to avoid the hang of huge size requirement.
+    fn try_acquire(&self, amount: u64) -> bool {
+        self.tokens
+            .fetch_update(SeqCst, SeqCst, |tokens| {
+                if tokens >= amount {
+                    Some(tokens - amount)
+                } else if tokens >= self.capacity {
+                    Some(0)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    fn refill(&self, amount: u64) {
+        let _ = self.tokens.fetch_update(SeqCst, SeqCst, |tokens| {
+            Some((tokens + amount).min(self.capacity))
+        });
+    }
 }
 
 #[derive(Clone)]
@@ -243,7 +433,26 @@ impl App {
         runtime_manager: RuntimeManager,
         config: &Config,
         reconf_manager: &ReconfigurableConfManager,
-    ) -> Self {
+        read_only: Arc<AtomicBool>,
+        replicator: Option<Replicator>,
+    ) -> Result<Self, WorkerError> {
+        // fast fail on the malformed config before registering into the store
+        let limits = AppLimits::parse(config, &config_options)?;
+        let invalid_conf = |err: anyhow::Error| WorkerError::INVALID_APP_CONFIG(err.to_string());
+
+        let partition_limit_enable = config.app_config.partition_limit_enable;
+        let partition_limit_threshold: ConfRef<ByteString> = reconf_manager
+            .register("app_config.partition_limit_threshold")
+            .map_err(invalid_conf)?;
+        let partition_limit_mem_backpressure_ratio: ConfRef<f64> = reconf_manager
+            .register("app_config.partition_limit_memory_backpressure_ratio")
+            .map_err(invalid_conf)?;
+
+        let partition_split_enable = config.app_config.partition_split_enable;
+        let partition_split_threshold: ConfRef<ByteString> = reconf_manager
+            .register("app_config.partition_split_threshold")
+            .map_err(invalid_conf)?;
+
         // todo: should throw exception if register failed.
         let copy_app_id = app_id.to_string();
         let app_options = config_options.clone();
@@ -257,35 +466,27 @@ impl App {
             _ => {}
         }
 
-        let memory_capacity =
-            util::parse_raw_to_bytesize(&config.memory_store.as_ref().unwrap().capacity);
-
-        let partition_limit_enable = config.app_config.partition_limit_enable;
-        let partition_limit_threshold: ConfRef<ByteString> = reconf_manager
-            .register("app_config.partition_limit_threshold")
-            .unwrap();
-        let partition_limit_mem_backpressure_ratio: ConfRef<f64> = reconf_manager
-            .register("app_config.partition_limit_memory_backpressure_ratio")
-            .unwrap();
-
-        let partition_split_enable = config.app_config.partition_split_enable;
-        let partition_split_threshold: ConfRef<ByteString> = reconf_manager
-            .register("app_config.partition_split_threshold")
-            .unwrap();
-
         let block_id_manager = get_block_id_manager(&config.app_config.block_id_manager_type);
 
+        let write_rate_limiter = limits.write_rate_limit.map(AppWriteRateLimiter::new);
+
         info!("App=[{}]. block_manager_type: {}. partition_limit/threshold/ratio: {}/{}/{}. partition_split/threshold: {}/{}",
                 &app_id, &config.app_config.block_id_manager_type,
                 partition_limit_enable, partition_limit_threshold.get(), partition_limit_mem_backpressure_ratio.get(),
                 partition_split_enable, partition_split_threshold.get());
 
-        App {
+        if let Some(replicator) = replicator.as_ref() {
+            replicator.register_app(&app_id, &config_options);
+        }
+
+        let remote_storage_config = config_options.remote_storage_config_option.clone();
+        Ok(App {
             app_id,
             app_config_options: config_options,
-            latest_heartbeat_time: AtomicU64::new(now_timestamp_as_sec()),
+            latest_heartbeat_time: AtomicU64::new(now_monotonic_as_sec()),
             store,
-            memory_capacity,
+            remote_storage_config: RwLock::new(remote_storage_config),
+            limits,
             partition_limit_enable,
             partition_limit_threshold,
             partition_limit_mem_backpressure_ratio,
@@ -298,6 +499,117 @@ impl App {
             partition_split_enable,
             partition_split_threshold,
             reconf_manager: reconf_manager.clone(),
+            write_rate_limiter,
+            quota_soft_limit_exceeded: AtomicBool::new(false),
+            write_reservation_check_enable: config.app_config.write_reservation_check_enable,
+            partition_crc_aggregates: DashMap::new(),
+            purge_tracker: Default::default(),
+            read_only,
+            registered_shuffles: DashMap::new(),
+            max_shuffles: config.app_config.max_shuffles_per_app,
+            max_partitions: config.app_config.max_partitions_per_app,
+            reject_partitions_beyond_max: config.app_config.reject_partitions_beyond_max,
+            replicator,
+        })
+    }
+
+    pub fn is_consistency_check_enabled(&self) -> bool {
+        self.app_config_options.consistency_check_enable
+    }
+
+    fn get_crc_aggregate(&self, uid: &PartitionedUId) -> Arc<PartitionCrcAggregate> {
+        self.partition_crc_aggregates
+            .entry((uid.shuffle_id, uid.partition_id))
+            .or_insert_with(|| Default::default())
+            .clone()
+    }
+
+    /// Accumulates the crc aggregate reported by the client for the inserted blocks.
+    pub fn add_expected_crc_aggregate(&self, uid: &PartitionedUId, aggregate: i64) {
+        if self.is_consistency_check_enabled() {
+            self.get_crc_aggregate(uid)
+                .expected
+                .fetch_add(aggregate, SeqCst);
+        }
+    }
+
+    /// Removes the dropped blocks from the server side crc aggregate, which is invoked
+    /// when the blocks are lost, like the spill event is discarded.
+    pub fn sub_dropped_crc_aggregate<'a>(
+        &self,
+        uid: &PartitionedUId,
+        blocks: impl Iterator<Item = &'a Block>,
+    ) {
+        if self.is_consistency_check_enabled() {
+            self.get_crc_aggregate(uid)
+                .actual
+                .fetch_sub(crc_aggregate(blocks), SeqCst);
+        }
+    }
+
+    /// Returns the sorted partition ids whose client and server side crc aggregates diverge.
+    pub fn get_divergent_partitions(&self, shuffle_id: i32) -> Vec<i32> {
+        let mut partition_ids: Vec<i32> = self
+            .partition_crc_aggregates
+            .iter()
+            .filter(|entry| entry.key().0 == shuffle_id)
+            .filter(|entry| {
+                entry.value().expected.load(SeqCst) != entry.value().actual.load(SeqCst)
+            })
+            .map(|entry| entry.key().1)
+            .collect();
+        partition_ids.sort();
+        partition_ids
+    }
+
+    /// Checks the app storage usage with the incoming bytes against the quota. The resident
+    /// size covers both the memory and the flushed data, which is released on purge.
+    fn check_quota(&self, incoming_bytes: u64) -> Result<(), WorkerError> {
+        let usage = self.total_resident_data_size() + incoming_bytes;
+        if let Some(hard_limit) = self.limits.quota_hard_limit {
+            if usage > hard_limit {
+                TOTAL_APP_QUOTA_HARD_LIMIT_REJECTED.inc();
+                return Err(WorkerError::APP_QUOTA_EXCEEDED);
+            }
+        }
+        if let Some(soft_limit) = self.limits.quota_soft_limit {
+            let exceeded = usage > soft_limit;
+            let previously_exceeded = self.quota_soft_limit_exceeded.swap(exceeded, SeqCst);
+            if exceeded && !previously_exceeded {
+                TOTAL_APP_QUOTA_SOFT_LIMIT_EXCEEDED.inc();
+                warn!(
+                    "App=[{}] storage usage:[{}] exceeds the soft quota:[{}]",
+                    &self.app_id, usage, soft_limit
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_quota_soft_limit_exceeded(&self) -> bool {
+        self.quota_soft_limit_exceeded.load(SeqCst)
+    }
+
+    /// Checks the size of the data written against the ticket doesn't exceed the reserved
+    /// size released from the ticket, otherwise the client over-commits the memory.
+    pub fn check_write_reservation(
+        &self,
+        reserved_len: i64,
+        written_len: i64,
+    ) -> Result<(), WorkerError> {
+        if self.write_reservation_check_enable && written_len > reserved_len {
+            TOTAL_WRITE_EXCEEDS_RESERVATION.inc();
+            return Err(WorkerError::WRITE_EXCEEDS_RESERVATION(
+                written_len,
+                reserved_len,
+            ));
+        }
+        Ok(())
+    }
+
+    fn refill_write_rate_limiter(&self, interval: Duration) {
+        if let Some(limiter) = &self.write_rate_limiter {
+            limiter.refill(limiter.capacity * interval.as_millis() as u64 / 1000);
         }
     }
 
@@ -317,32 +629,129 @@ impl App {
         self.latest_heartbeat_time.load(SeqCst)
     }
 
+    pub fn heartbeat_age_sec(&self) -> u64 {
+        now_monotonic_as_sec().saturating_sub(self.get_latest_heartbeat_time())
+    }
+
+    pub fn is_local_order(&self) -> bool {
+        matches!(
+            self.app_config_options.data_distribution,
+            DataDistribution::LOCAL_ORDER
+        )
+    }
+
+    pub fn remote_storage_root(&self) -> Option<String> {
+        self.remote_storage_config
+            .read()
+            .as_ref()
+            .map(|conf| conf.root.to_string())
+    }
+
+    /// Swaps the remote storage config, like the refreshed delegation tokens of
+    /// the long-running app, and the remote store will rebuild its client with it.
+    pub fn update_remote_storage_config(&self, new: RemoteStorageConfig) -> Result<()> {
+        let mut conf = self.remote_storage_config.write();
+        self.store
+            .update_remote_storage_config(&self.app_id, new.clone())?;
+        info!(
+            "The remote storage config of app:[{}] has been updated. root: {}",
+            &self.app_id, &new.root
+        );
+        *conf = Some(new);
+        Ok(())
+    }
+
     pub fn heartbeat(&self) -> Result<()> {
-        let timestamp = now_timestamp_as_sec();
+        let timestamp = now_monotonic_as_sec();
         self.latest_heartbeat_time.store(timestamp, SeqCst);
         Ok(())
     }
 
     pub fn register_shuffle(&self, shuffle_id: i32) -> Result<()> {
         self.heartbeat()?;
+        if let Some(max_shuffles) = self.max_shuffles {
+            if !self.registered_shuffles.contains_key(&shuffle_id)
+                && self.registered_shuffles.len() >= max_shuffles
+            {
+                return Err(
+                    WorkerError::TOO_MANY_SHUFFLES(self.app_id.to_string(), max_shuffles).into(),
+                );
+            }
+        }
+        self.registered_shuffles.insert(shuffle_id, ());
+        Ok(())
+    }
+
+    fn check_partition_limit(&self, uid: &PartitionedUId) -> Result<(), WorkerError> {
+        let max_partitions = match self.max_partitions {
+            Some(max_partitions) => max_partitions,
+            _ => return Ok(()),
+        };
+        let key = (uid.shuffle_id, uid.partition_id);
+        let tracked = self.partition_meta_infos.len();
+        if tracked < max_partitions || self.partition_meta_infos.contains_key(&key) {
+            return Ok(());
+        }
+        TOTAL_PARTITIONS_BEYOND_APP_LIMIT.inc();
+        if self.reject_partitions_beyond_max {
+            return Err(WorkerError::TOO_MANY_PARTITIONS(
+                self.app_id.to_string(),
+                max_partitions,
+            ));
+        }
+        if tracked == max_partitions {
+            warn!(
+                "The tracked partitions of app:[{}] exceed the limit: {}",
+                &self.app_id, max_partitions
+            );
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), WorkerError> {
+        if self.read_only.load(SeqCst) {
+            return Err(WorkerError::READ_ONLY_MODE);
+        }
         Ok(())
     }
 
-    pub async fn insert(&self, ctx: WritingViewContext) -> Result<i32, WorkerError> {
+    pub async fn insert(&self, mut ctx: WritingViewContext) -> Result<i32, WorkerError> {
         self.heartbeat()?;
+        self.check_writable()?;
 
         let len: u64 = ctx.data_size;
+        self.check_quota(len)?;
+        self.check_partition_limit(&ctx.uid)?;
         TOTAL_RECEIVED_DATA.inc_by(len);
+        ACTIVITY_LOG.record_written(len);
 
         // add the partition size into the meta
         self.inc_partition_size(&ctx.uid, len)?;
+        ctx.huge_partition = self.is_huge_partition(&ctx.uid)?;
 
         self.total_received_data_size.fetch_add(len, SeqCst);
         self.total_resident_data_size.fetch_add(len, SeqCst);
 
         RESIDENT_BYTES.add(len as i64);
 
+        let crc_aggregate_option = if self.is_consistency_check_enabled() {
+            Some((ctx.uid.clone(), crc_aggregate(ctx.data_blocks.iter())))
+        } else {
+            None
+        };
+        let replication_option = self
+            .replicator
+            .as_ref()
+            .map(|replicator| (replicator, ctx.uid.clone(), ctx.data_blocks.clone()));
         self.store.insert(ctx).await?;
+        if let Some((uid, aggregate)) = crc_aggregate_option {
+            self.get_crc_aggregate(&uid)
+                .actual
+                .fetch_add(aggregate, SeqCst);
+        }
+        if let Some((replicator, uid, blocks)) = replication_option {
+            replicator.replicate(uid, blocks, len);
+        }
         Ok(len as i32)
     }
 
@@ -356,11 +765,13 @@ impl App {
                     let length = local_data.data.len() as u64;
                     TOTAL_READ_DATA_FROM_LOCALFILE.inc_by(length);
                     TOTAL_READ_DATA.inc_by(length);
+                    ACTIVITY_LOG.record_read(length);
                 }
                 ResponseData::Mem(mem_data) => {
                     let length = mem_data.data.len() as u64;
                     TOTAL_READ_DATA_FROM_MEMORY.inc_by(length);
                     TOTAL_READ_DATA.inc_by(length);
+                    ACTIVITY_LOG.record_read(length);
                 }
             };
 
@@ -381,6 +792,7 @@ impl App {
                     let len = local_data.index_data.len();
                     TOTAL_READ_INDEX_FROM_LOCALFILE.inc_by(len as u64);
                     TOTAL_READ_DATA.inc_by(len as u64);
+                    ACTIVITY_LOG.record_read(len as u64);
                 }
                 _ => {}
             };
@@ -429,6 +841,20 @@ impl App {
             .inc();
     }
 
+    /// Releases the huge partitions removed by the shuffle purge or the partition deletion.
+    fn sub_removed_huge_partitions(&self, number: u64) {
+        if number == 0 {
+            return;
+        }
+        self.huge_partition_number.fetch_sub(number, SeqCst);
+        GAUGE_HUGE_PARTITION_NUMBER
+            .with_label_values(&[ALL_LABEL])
+            .sub(number as i64);
+        GAUGE_HUGE_PARTITION_NUMBER
+            .with_label_values(&[self.app_id.as_str()])
+            .sub(number as i64);
+    }
+
     fn sub_huge_partition_metric(&self) {
         let number = self.huge_partition_number.load(SeqCst);
         if number > 0 {
@@ -450,7 +876,7 @@ impl App {
             return Ok(false);
         }
         let ratio = self.partition_limit_mem_backpressure_ratio.get();
-        let threshold = (self.memory_capacity as f64 * ratio) as u64;
+        let threshold = (self.limits.memory_capacity as f64 * ratio) as u64;
         let used = self.store.get_memory_buffer_size(uid).await?;
 
         if used > threshold {
@@ -478,6 +904,10 @@ impl App {
         ctx: RequireBufferContext,
     ) -> Result<RequireBufferResponse, WorkerError> {
         self.heartbeat()?;
+        self.check_writable().map_err(|err| {
+            TOTAL_REQUIRE_BUFFER_FAILED.inc();
+            err
+        })?;
 
         let app_id = &ctx.uid.app_id;
         let shuffle_id = &ctx.uid.shuffle_id;
@@ -491,7 +921,7 @@ impl App {
             if self.partition_split_enable
                 && self
                     .get_partition_meta(&puid)
-                    .is_split(&puid, self.partition_split_threshold.get().into())?
+                    .is_split(&puid, self.partition_split_threshold.get().as_u64())?
             {
                 partition_split_candidates.insert(*partition_id);
                 split_hit = true;
@@ -506,8 +936,42 @@ impl App {
             }
         }
 
+        FAULT_INJECTOR
+            .inject(StorageType::MEMORY, None, FaultOperation::REQUIRE_BUFFER)
+            .await
+            .map_err(|err| {
+                TOTAL_REQUIRE_BUFFER_FAILED.inc();
+                err
+            })?;
+
+        self.store.check_memory_backpressure().map_err(|err| {
+            TOTAL_REQUIRE_BUFFER_FAILED.inc();
+            err
+        })?;
+
+        let required_size = ctx.size.max(0) as u64;
+        self.check_quota(required_size).map_err(|err| {
+            TOTAL_REQUIRE_BUFFER_FAILED.inc();
+            err
+        })?;
+        if let Some(limiter) = &self.write_rate_limiter {
+            if !limiter.try_acquire(required_size) {
+                TOTAL_REQUIRE_BUFFER_FAILED.inc();
+                TOTAL_APP_WRITE_RATE_LIMITED_BYTES.inc_by(required_size);
+                TIMELINE.record(
+                    TimelineCategory::RATE_LIMIT,
+                    format!("app: {} write rate limited", &self.app_id),
+                );
+                return Err(WorkerError::APP_WRITE_RATE_LIMITED);
+            }
+        }
+
         let mut required = self.store.require_buffer(ctx).await.map_err(|err| {
             TOTAL_REQUIRE_BUFFER_FAILED.inc();
+            // give back the tokens due to no data will be written
+            if let Some(limiter) = &self.write_rate_limiter {
+                limiter.refill(required_size);
+            }
             err
         })?;
         required.split_partitions = partition_split_candidates
@@ -537,6 +1001,146 @@ impl App {
         partitioned_meta.clone()
     }
 
+    pub async fn get_partition_flush_state(
+        &self,
+        uid: &PartitionedUId,
+    ) -> Result<PartitionFlushState> {
+        self.store.get_partition_flush_state(uid).await
+    }
+
+    pub async fn get_partition_location(&self, uid: &PartitionedUId) -> Result<PartitionLocation> {
+        self.store.get_partition_location(uid).await
+    }
+
+    /// Dumps the metadata to be persisted, which is restored after the worker restarts.
+    pub async fn snapshot(&self) -> Result<AppMetaSnapshot> {
+        let keys: Vec<(i32, i32)> = self
+            .partition_meta_infos
+            .iter()
+            .map(|entry| *entry.key())
+            .collect();
+        // the bitmaps are dumped firstly, so that the block ids of the data flushed later
+        // won't be missing in the snapshot
+        let mut block_ids = self.block_id_manager.snapshot()?;
+        let mut partitions = vec![];
+        for (shuffle_id, partition_id) in keys {
+            let meta = match self.partition_meta_infos.get(&(shuffle_id, partition_id)) {
+                Some(meta) => meta.clone(),
+                _ => continue,
+            };
+            let (total_size, is_huge_partition, is_split) = {
+                let inner = meta.inner.read();
+                (inner.total_size, inner.is_huge_partition, inner.is_split)
+            };
+            let uid = PartitionedUId::from(self.app_id.to_string(), shuffle_id, partition_id);
+            let localfile = self.store.get_restorable_localfile_partition(&uid).await?;
+            let crc_aggregate = self
+                .partition_crc_aggregates
+                .get(&(shuffle_id, partition_id))
+                .map(|x| x.value().clone());
+            // the memory data is lost after the restart, whose crc and block ids are excluded
+            let buffer = self.store.get_memory_buffer(&uid).await.ok();
+            let crc_aggregate = match crc_aggregate {
+                Some(aggregate) => {
+                    let expected = aggregate.expected.load(SeqCst);
+                    let actual = aggregate.actual.load(SeqCst);
+                    let buffered = buffer.as_ref().map_or(0, |x| x.crc_aggregate());
+                    Some((expected, actual.wrapping_sub(buffered)))
+                }
+                _ => None,
+            };
+            if let Some(buffer) = buffer {
+                let memory_block_ids = buffer.block_ids();
+                for block_ids in block_ids.iter_mut().filter(|x| x.shuffle_id == shuffle_id) {
+                    block_ids.exclude(partition_id, &memory_block_ids)?;
+                }
+            }
+            partitions.push(PartitionMetaSnapshot {
+                shuffle_id,
+                partition_id,
+                total_size,
+                is_huge_partition,
+                is_split,
+                localfile,
+                crc_aggregate,
+            });
+        }
+        Ok(AppMetaSnapshot {
+            app_id: self.app_id.to_string(),
+            partitions,
+            block_ids,
+            options: self.app_config_options.clone(),
+        })
+    }
+
+    /// Restores the metadata persisted before the restart. Only the flushed localfile
+    /// data is counted as resident, since the memory data has been lost.
+    fn restore(&self, snapshot: AppMetaSnapshot) -> Result<()> {
+        for partition in snapshot.partitions {
+            let uid = PartitionedUId::from(
+                self.app_id.to_string(),
+                partition.shuffle_id,
+                partition.partition_id,
+            );
+            self.registered_shuffles.insert(partition.shuffle_id, ());
+            let mut meta = self.get_partition_meta(&uid);
+            {
+                let mut inner = meta.inner.write();
+                inner.total_size = partition.total_size;
+                inner.is_split = partition.is_split;
+            }
+            if partition.is_huge_partition {
+                meta.mark_as_huge_partition();
+                self.add_huge_partition_metric();
+            }
+            let mut is_data_restored = true;
+            if let Some((disk_root, offset)) = partition.localfile {
+                match self
+                    .store
+                    .restore_localfile_partition(&uid, &disk_root, offset)
+                {
+                    Ok(_) => {
+                        self.total_received_data_size
+                            .fetch_add(offset as u64, SeqCst);
+                        self.total_resident_data_size
+                            .fetch_add(offset as u64, SeqCst);
+                        RESIDENT_BYTES.add(offset);
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Errors on restoring the localfile partition: {:?}. err: {:?}",
+                            &uid, err
+                        );
+                        is_data_restored = false;
+                    }
+                }
+            }
+            if let Some((expected, actual)) = partition.crc_aggregate {
+                // the unrestored data is lost, which is reported by the divergence
+                let aggregate = self.get_crc_aggregate(&uid);
+                aggregate.expected.store(expected, SeqCst);
+                if is_data_restored {
+                    aggregate.actual.store(actual, SeqCst);
+                }
+            }
+        }
+        let number = self.block_id_manager.restore(snapshot.block_ids)?;
+        BLOCK_ID_NUMBER.add(number as i64);
+        Ok(())
+    }
+
+    /// The received size of every partition in the shuffle, the largest comes first.
+    pub fn partition_sizes(&self, shuffle_id: i32) -> Vec<(i32, u64)> {
+        let mut sizes: Vec<(i32, u64)> = self
+            .partition_meta_infos
+            .iter()
+            .filter(|entry| entry.key().0 == shuffle_id)
+            .map(|entry| (entry.key().1, entry.value().get_size().unwrap_or(0)))
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        sizes
+    }
+
     pub fn inc_partition_size(&self, uid: &PartitionedUId, size: u64) -> Result<()> {
         let mut partitioned_meta = self.get_partition_meta(&uid);
         partitioned_meta.inc_size(size as i32)
@@ -547,13 +1151,66 @@ impl App {
         self.block_id_manager.get_multi_block_ids(ctx).await
     }
 
+    pub async fn get_block_ids_batch(
+        &self,
+        ctx: GetMultiBlockIdsContext,
+    ) -> Result<HashMap<i32, Bytes>> {
+        self.heartbeat()?;
+        self.block_id_manager.get_block_ids_batch(ctx).await
+    }
+
     pub async fn report_multi_block_ids(&self, ctx: ReportMultiBlockIdsContext) -> Result<()> {
         self.heartbeat()?;
-        let number = self.block_id_manager.report_multi_block_ids(ctx).await?;
-        BLOCK_ID_NUMBER.add(number as i64);
+        let shuffle_id = ctx.shuffle_id;
+        let replication_option = self
+            .replicator
+            .as_ref()
+            .map(|replicator| (replicator, ctx.block_ids.clone()));
+        let reported = self.block_id_manager.report_multi_block_ids(ctx).await?;
+        if let Some((replicator, block_ids)) = replication_option {
+            replicator.report_block_ids(&self.app_id, shuffle_id, block_ids);
+        }
+        BLOCK_ID_NUMBER.add(reported.reported as i64);
+
+        let duplicated = reported.duplicated();
+        if duplicated > 0 {
+            GAUGE_DUPLICATE_BLOCK_REPORTS.add(duplicated as i64);
+            if duplicated as f64 > reported.reported as f64 * DUPLICATE_BLOCK_IDS_WARN_RATIO {
+                warn!(
+                    "High duplicate ratio of the reported block ids({}/{}) for app:{}. shuffle_id:{}",
+                    duplicated, reported.reported, &self.app_id, shuffle_id
+                );
+            }
+        }
         Ok(())
     }
 
+    /// Compare the client side expected cumulative block ids number with the reported
+    /// bitmap's cardinality. Returns the mismatched partitions with the delta of
+    /// (expected - actual), which could be used by client to resend the lost reports.
+    pub fn check_reported_block_ids_count(
+        &self,
+        shuffle_id: i32,
+        expected_counts: &HashMap<i32, i64>,
+    ) -> Result<HashMap<i32, i64>> {
+        let mut deltas = HashMap::new();
+        for (partition_id, expected) in expected_counts {
+            let actual = self
+                .block_id_manager
+                .get_cardinality(shuffle_id, *partition_id)?;
+            let delta = *expected - actual as i64;
+            if delta != 0 {
+                warn!(
+                    "Reported block ids number is not consistent with the expected for app: {}, shuffle_id: {}, partition_id: {}. expected/actual: {}/{}",
+                    &self.app_id, shuffle_id, partition_id, expected, actual
+                );
+                TOTAL_REPORTED_BLOCK_ID_COUNT_MISMATCH.inc();
+                deltas.insert(*partition_id, delta);
+            }
+        }
+        Ok(deltas)
+    }
+
     pub async fn dump_all_huge_partitions_size(&self) -> Result<Vec<u64>> {
         let mut records = vec![];
         let view = self.partition_meta_infos.clone().into_read_only();
@@ -569,13 +1226,45 @@ impl App {
 
     pub async fn purge(&self, reason: &PurgeReason) -> Result<()> {
         let (app_id, shuffle_id) = reason.extract();
-        let removed_size = self.store.purge(&PurgeDataContext::new(reason)).await?;
+
+        // the resident size is corrected incrementally by the purged batches reported from
+        // the stores, and the rest unreported size is corrected after the store purge.
+        let resident_data_size = self.total_resident_data_size.clone();
+        let tracker = PurgeProgressTracker::new(move |batch: &PurgeBatch| {
+            resident_data_size.fetch_sub(batch.removed_bytes as u64, SeqCst);
+            RESIDENT_BYTES.sub(batch.removed_bytes);
+        });
+        let _ = self.purge_tracker.lock().replace(tracker.clone());
+        let purged = self
+            .store
+            .purge(&PurgeDataContext::with_progress_tracker(
+                reason,
+                tracker.clone(),
+            ))
+            .await;
+        let _ = self.purge_tracker.lock().take();
+        let removed_size = purged?;
+        if removed_size.max(0) as u64 >= TIMELINE.purge_size_threshold() {
+            TIMELINE.record(
+                TimelineCategory::PURGE,
+                format!("{:?} removed {} bytes", reason, removed_size),
+            );
+        }
+        // the reported progress may be ahead of the removed size returned by the store
+        let unreported_size = (removed_size.max(0) as u64)
+            .saturating_sub(tracker.progress().removed_bytes.max(0) as u64);
         self.total_resident_data_size
-            .fetch_sub(removed_size as u64, SeqCst);
+            .fetch_sub(unreported_size, SeqCst);
 
-        RESIDENT_BYTES.sub(removed_size);
+        RESIDENT_BYTES.sub(unreported_size as i64);
+
+        if let Some(replicator) = self.replicator.as_ref() {
+            replicator.purge(&app_id, shuffle_id);
+        }
 
         if let Some(shuffle_id) = shuffle_id {
+            self.registered_shuffles.remove(&shuffle_id);
+
             // shuffle level bitmap deletion
             let purged_number = self.block_id_manager.purge_block_ids(shuffle_id).await?;
             BLOCK_ID_NUMBER.sub(purged_number as i64);
@@ -588,29 +1277,72 @@ impl App {
                     deletion_keys.push(key);
                 }
             }
-            GAUGE_PARTITION_NUMBER.sub(deletion_keys.len() as i64);
+            self.partition_crc_aggregates
+                .retain(|key, _| key.0 != shuffle_id);
+            // the partition meta of the shuffle is dropped, otherwise it's leaked for the apps
+            // registering and unregistering many shuffles.
+            let mut removed_cnt = 0;
             let mut huge_partition_cnt = 0;
             for deletion_key in deletion_keys {
                 if let Some(meta) = self.partition_meta_infos.remove(deletion_key) {
+                    removed_cnt += 1;
                     if meta.1.is_huge_partition() {
                         huge_partition_cnt += 1;
                     }
                 }
             }
-            GAUGE_HUGE_PARTITION_NUMBER
-                .with_label_values(&vec![ALL_LABEL])
-                .sub(huge_partition_cnt as i64);
+            GAUGE_PARTITION_NUMBER.sub(removed_cnt);
+            self.sub_removed_huge_partitions(huge_partition_cnt);
         } else {
             // app level deletion
             GAUGE_PARTITION_NUMBER.sub(self.partition_meta_infos.len() as i64);
             self.sub_huge_partition_metric();
 
             BLOCK_ID_NUMBER.sub(self.block_id_manager.get_blocks_number()? as i64);
+
+            if self.expected_total_bytes().is_some() {
+                let _ = GAUGE_APP_EXPECTED_SIZE_PROGRESS.remove_label_values(&[&self.app_id]);
+            }
         }
 
         Ok(())
     }
 
+    /// Deletes the data of the single partition from all the stores, and returns the removed bytes.
+    /// The block ids are retained, which are purged along with the shuffle.
+    pub async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64> {
+        let key = (uid.shuffle_id, uid.partition_id);
+        let removed_size = self.store.delete_partition(uid).await?;
+        self.total_resident_data_size
+            .fetch_sub(removed_size as u64, SeqCst);
+        RESIDENT_BYTES.sub(removed_size);
+
+        self.partition_crc_aggregates.remove(&key);
+        if let Some((_, meta)) = self.partition_meta_infos.remove(&key) {
+            GAUGE_PARTITION_NUMBER.dec();
+            if meta.is_huge_partition() {
+                self.sub_removed_huge_partitions(1);
+            }
+        }
+        Ok(removed_size)
+    }
+
+    /// The progress of the in-progress purge, which is None if no purge is running.
+    pub fn purge_progress(&self) -> Option<PurgeProgress> {
+        self.purge_tracker.lock().as_ref().map(|x| x.progress())
+    }
+
+    /// Stops the in-progress purge between the batches. Returns false if no purge is running.
+    pub fn cancel_purge(&self) -> bool {
+        match self.purge_tracker.lock().as_ref() {
+            Some(tracker) => {
+                tracker.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn total_received_data_size(&self) -> u64 {
         self.total_received_data_size.load(SeqCst)
     }
@@ -618,6 +1350,32 @@ impl App {
     pub fn total_resident_data_size(&self) -> u64 {
         self.total_resident_data_size.load(SeqCst)
     }
+
+    pub fn expected_total_bytes(&self) -> Option<u64> {
+        self.app_config_options.expected_total_bytes
+    }
+
+    /// The expected but not yet received bytes, which decays as the real data arrives.
+    pub fn outstanding_expected_bytes(&self) -> u64 {
+        self.expected_total_bytes()
+            .map(|expected| expected.saturating_sub(self.total_received_data_size()))
+            .unwrap_or(0)
+    }
+
+    /// The ratio of the received bytes to the expected total bytes.
+    pub fn expected_size_progress(&self) -> Option<f64> {
+        self.expected_total_bytes()
+            .filter(|expected| *expected > 0)
+            .map(|expected| self.total_received_data_size() as f64 / expected as f64)
+    }
+
+    pub fn update_expected_size_progress_metric(&self) {
+        if let Some(progress) = self.expected_size_progress() {
+            GAUGE_APP_EXPECTED_SIZE_PROGRESS
+                .with_label_values(&[&self.app_id])
+                .set(progress);
+        }
+    }
 }
 
 #[allow(non_camel_case_types)]
@@ -649,13 +1407,110 @@ impl PurgeReason {
 #[derive(Debug, Clone)]
 pub struct PurgeDataContext {
     pub purge_reason: PurgeReason,
+    pub progress_tracker: Option<PurgeProgressTracker>,
 }
 
 impl PurgeDataContext {
     pub fn new(reason: &PurgeReason) -> PurgeDataContext {
         PurgeDataContext {
             purge_reason: reason.clone(),
+            progress_tracker: None,
+        }
+    }
+
+    pub fn with_progress_tracker(
+        reason: &PurgeReason,
+        tracker: PurgeProgressTracker,
+    ) -> PurgeDataContext {
+        PurgeDataContext {
+            purge_reason: reason.clone(),
+            progress_tracker: Some(tracker),
+        }
+    }
+
+    /// Reports the purged batch, which should be invoked by the store after every batch
+    /// deletion.
+    pub fn report_batch(&self, batch: PurgeBatch) {
+        if let Some(tracker) = self.progress_tracker.as_ref() {
+            tracker.report(batch);
+        }
+    }
+
+    /// Whether the purge has been cancelled, the store should stop between the batches.
+    pub fn is_cancelled(&self) -> bool {
+        self.progress_tracker
+            .as_ref()
+            .map_or(false, |tracker| tracker.is_cancelled())
+    }
+}
+
+/// The purged data of one batch, which is mostly one shuffle.
+#[derive(Debug, Clone, Default)]
+pub struct PurgeBatch {
+    pub shuffle_id: Option<i32>,
+    pub removed_bytes: i64,
+    pub removed_files: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PurgeProgress {
+    pub removed_bytes: i64,
+    pub removed_files: u64,
+    pub current_shuffle_id: Option<i32>,
+    pub cancelled: bool,
+}
+
+/// Accumulates the purged batches reported by the stores and notifies the listener
+/// for every batch.
+#[derive(Clone)]
+pub struct PurgeProgressTracker {
+    progress: Arc<parking_lot::Mutex<PurgeProgress>>,
+    cancelled: Arc<AtomicBool>,
+    listener: Arc<dyn Fn(&PurgeBatch) + Send + Sync>,
+}
+
+impl PurgeProgressTracker {
+    pub fn new<F>(listener: F) -> Self
+    where
+        F: Fn(&PurgeBatch) + Send + Sync + 'static,
+    {
+        Self {
+            progress: Default::default(),
+            cancelled: Default::default(),
+            listener: Arc::new(listener),
+        }
+    }
+
+    pub fn report(&self, batch: PurgeBatch) {
+        {
+            let mut progress = self.progress.lock();
+            progress.removed_bytes += batch.removed_bytes;
+            progress.removed_files += batch.removed_files;
+            progress.current_shuffle_id = batch.shuffle_id;
         }
+        (self.listener)(&batch);
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(SeqCst)
+    }
+
+    pub fn progress(&self) -> PurgeProgress {
+        let mut progress = self.progress.lock().clone();
+        progress.cancelled = self.is_cancelled();
+        progress
+    }
+}
+
+impl std::fmt::Debug for PurgeProgressTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PurgeProgressTracker")
+            .field("progress", &self.progress())
+            .finish()
     }
 }
 
@@ -704,6 +1559,8 @@ pub struct WritingViewContext {
     pub uid: PartitionedUId,
     pub data_blocks: Vec<Block>,
     pub data_size: u64,
+    // tagged by the app, the huge partition buffer will be spilled to cold store directly
+    pub huge_partition: bool,
 }
 
 impl WritingViewContext {
@@ -713,6 +1570,7 @@ impl WritingViewContext {
             uid,
             data_blocks,
             data_size: 0,
+            huge_partition: false,
         }
     }
 
@@ -722,6 +1580,7 @@ impl WritingViewContext {
             uid,
             data_blocks,
             data_size,
+            huge_partition: false,
         }
     }
 
@@ -731,6 +1590,7 @@ impl WritingViewContext {
             uid,
             data_blocks,
             data_size: len,
+            huge_partition: false,
         }
     }
 }
@@ -740,10 +1600,28 @@ pub struct ReadingViewContext {
     pub uid: PartitionedUId,
     pub reading_options: ReadingOptions,
     pub serialized_expected_task_ids_bitmap: Option<Treemap>,
+    // only the block of the latest task attempt is returned for the duplicate block ids,
+    // which avoids the superseded data written by the speculative attempts.
+    pub latest_attempt_only: bool,
+    // all the writes up to this sequence of the partition must be visible for this reading,
+    // which is returned by the previous insert.
+    pub min_write_sequence: Option<u64>,
+    // the reading is given up once exceeding this deadline, which is unlimited if None.
+    pub deadline: Option<Instant>,
 }
 
 pub struct ReadingIndexViewContext {
     pub partition_id: PartitionedUId,
+    // only read the index entries in this range rather than the whole index file
+    pub entry_range: Option<IndexEntryRange>,
+    pub deadline: Option<Instant>,
+}
+
+/// The range of the index entries, and every entry is [`crate::store::index_codec::INDEX_BLOCK_SIZE`] bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntryRange {
+    pub start_entry: u64,
+    pub max_entries: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -787,6 +1665,9 @@ pub enum ReadingOptions {
     MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(i64, i64),
     #[allow(non_camel_case_types)]
     FILE_OFFSET_AND_LEN(i64, i64),
+    // the fast path only reads the memory, the NOT_IN_MEMORY will be returned if absent.
+    #[allow(non_camel_case_types)]
+    MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE(i64, i64),
 }
 
 // ==========================================================
@@ -808,7 +1689,15 @@ pub struct AppManager {
     config: Config,
     runtime_manager: RuntimeManager,
     historical_app_statistics: Option<HistoricalAppStatistics>,
+    pub(crate) replicator: Option<Replicator>,
     reconf_manager: ReconfigurableConfManager,
+    // the maintenance mode that freezes the writing while still serving the reading
+    read_only: Arc<AtomicBool>,
+    // key: app_id. the apps whose app level purge is in flight, which have been removed from
+    // the apps and are kept here to observe or cancel the purge.
+    purging_apps: DashMap<String, Arc<App>>,
+    // the new apps are refused under the decommissioning, while the known apps keep working
+    decommissioning: AtomicBool,
 }
 
 impl AppManager {
@@ -829,6 +1718,11 @@ impl AppManager {
                 None
             };
 
+        let replicator = config
+            .replication
+            .as_ref()
+            .map(|conf| Replicator::new(conf, &runtime_manager));
+
         let manager = AppManager {
             apps: DashMap::new(),
             receiver,
@@ -838,7 +1732,11 @@ impl AppManager {
             config,
             runtime_manager: runtime_manager.clone(),
             historical_app_statistics,
+            replicator,
             reconf_manager: reconf_manager.clone(),
+            read_only: Default::default(),
+            purging_apps: DashMap::new(),
+            decommissioning: Default::default(),
         };
         manager
     }
@@ -859,7 +1757,9 @@ impl AppManager {
         ));
         let app_manager_ref_cloned = app_ref.clone();
 
-        runtime_manager.default_runtime.spawn_with_await_tree("App heartbeat checker", async move {
+        runtime_manager.default_runtime.spawn_with_await_tree(
+            "App heartbeat checker",
+            async move {
                 info!("Starting app heartbeat checker...");
                 loop {
                     // task1: find out heartbeat timeout apps
@@ -867,33 +1767,25 @@ impl AppManager {
                         .instrument_await("sleeping for 10s...")
                         .await;
 
-                    for item in app_manager_ref_cloned.apps.iter() {
-                        let (key, app) = item.pair();
-                        let last_time = app.get_latest_heartbeat_time();
-                        let current = now_timestamp_as_sec();
-
-                        if current - last_time
-                            > (app_manager_ref_cloned.app_heartbeat_timeout_min * 60) as u64
+                    let current = now_monotonic_as_sec();
+                    for app_id in app_manager_ref_cloned.find_heartbeat_timeout_apps(current) {
+                        if app_manager_ref_cloned
+                            .sender
+                            .send(PurgeEvent {
+                                reason: PurgeReason::APP_LEVEL_HEARTBEAT_TIMEOUT(app_id.clone()),
+                            })
+                            .await
+                            .is_err()
                         {
-                            info!("Detected app:{:?} heartbeat timeout. now: {:?}, latest heartbeat: {:?}. timeout threshold: {:?}(min)",
-                            key, current, last_time, app_manager_ref_cloned.app_heartbeat_timeout_min);
-                            if app_manager_ref_cloned
-                                .sender
-                                .send(PurgeEvent {
-                                    reason: PurgeReason::APP_LEVEL_HEARTBEAT_TIMEOUT(key.clone()),
-                                })
-                                .await
-                                .is_err()
-                            {
-                                error!(
+                            error!(
                                 "Errors on sending purge event when app: {} heartbeat timeout",
-                                key
+                                app_id
                             );
-                            }
                         }
                     }
                 }
-        });
+            },
+        );
 
         // calculate topN app shuffle data size
         let app_manager_ref = app_ref.clone();
@@ -925,9 +1817,93 @@ impl AppManager {
                             .with_label_values(&[&app.app_id])
                             .set(apps[idx].total_resident_data_size() as i64);
                     }
+
+                    for app in apps {
+                        app.update_expected_size_progress_metric();
+                    }
+
+                    for (tier, bytes) in app_manager_ref.store_resident_bytes_by_tier().await {
+                        GAUGE_RESIDENT_BYTES_BY_TIER
+                            .with_label_values(&[&format!("{:?}", tier)])
+                            .set(bytes as i64);
+                    }
                 }
             });
 
+        if app_ref.config.app_config.app_write_rate_limit.is_some() {
+            let app_manager_ref = app_ref.clone();
+            runtime_manager.default_runtime.spawn_with_await_tree(
+                "App write rate limiter refill",
+                async move {
+                    info!("Starting refilling the app write rate limiter...");
+                    let interval = Duration::from_millis(APP_WRITE_RATE_LIMITER_REFILL_INTERVAL_MS);
+                    loop {
+                        tokio::time::sleep(interval)
+                            .instrument_await("sleeping...")
+                            .await;
+                        for app in app_manager_ref.apps.iter() {
+                            app.refill_write_rate_limiter(interval);
+                        }
+                    }
+                },
+            );
+        }
+
+        let spilled_file_max_age_sec = app_ref
+            .config
+            .localfile_store
+            .as_ref()
+            .and_then(|conf| conf.spilled_file_max_age_sec);
+        if let Some(max_age_sec) = spilled_file_max_age_sec {
+            let app_manager_ref = app_ref.clone();
+            runtime_manager.default_runtime.spawn_with_await_tree(
+                "Expired spilled files janitor",
+                async move {
+                    info!(
+                        "Starting reclaiming the spilled files older than {}(s)...",
+                        max_age_sec
+                    );
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(60))
+                            .instrument_await("sleeping for 60s...")
+                            .await;
+                        if let Err(err) = app_manager_ref.reclaim_expired_spilled_files(max_age_sec)
+                        {
+                            error!("Errors on reclaiming expired spilled files. err: {:?}", err);
+                        }
+                    }
+                },
+            );
+        }
+
+        if let Some(conf) = app_ref.config.metadata_snapshot.clone() {
+            if let Err(err) = app_ref.restore_metadata() {
+                error!(
+                    "Errors on restoring the apps from the metadata snapshot: {}. err: {:?}",
+                    &conf.path, err
+                );
+            }
+            let app_manager_ref = app_ref.clone();
+            runtime_manager.default_runtime.spawn_with_await_tree(
+                "App metadata snapshotter",
+                async move {
+                    info!("Starting snapshotting the apps metadata...");
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(conf.interval_sec))
+                            .instrument_await("sleeping...")
+                            .await;
+                        if let Err(err) = app_manager_ref
+                            .snapshot_metadata()
+                            .instrument_await("snapshotting the apps metadata")
+                            .await
+                        {
+                            error!("Errors on snapshotting the apps metadata. err: {:?}", err);
+                        }
+                    }
+                },
+            );
+        }
+
         let app_manager_cloned = app_ref.clone();
         runtime_manager
             .default_runtime
@@ -954,6 +1930,107 @@ impl AppManager {
         app_ref
     }
 
+    /// Persists the metadata of all the apps into the snapshot file.
+    pub async fn snapshot_metadata(&self) -> Result<usize> {
+        let path = match self.config.metadata_snapshot.as_ref() {
+            Some(conf) => conf.path.to_string(),
+            _ => return Ok(0),
+        };
+        let apps: Vec<Arc<App>> = self.apps.iter().map(|x| x.value().clone()).collect();
+        let mut snapshot = MetadataSnapshot {
+            timestamp: now_timestamp_as_millis(),
+            apps: vec![],
+        };
+        for app in apps {
+            if self.purging_apps.contains_key(&app.app_id) {
+                continue;
+            }
+            snapshot.apps.push(app.snapshot().await?);
+        }
+        let number = snapshot.apps.len();
+        snapshot.write_to(&path)?;
+        Ok(number)
+    }
+
+    /// Restores the apps from the snapshot file, which is invoked on startup.
+    pub fn restore_metadata(&self) -> Result<usize> {
+        let path = match self.config.metadata_snapshot.as_ref() {
+            Some(conf) => conf.path.to_string(),
+            _ => return Ok(0),
+        };
+        let snapshot = match MetadataSnapshot::read_from(&path)? {
+            Some(snapshot) => snapshot,
+            _ => return Ok(0),
+        };
+        let mut number = 0;
+        for app_snapshot in snapshot.apps {
+            let app_id = app_snapshot.app_id.to_string();
+            if self.apps.contains_key(&app_id) {
+                continue;
+            }
+            let app = App::from(
+                app_id.to_string(),
+                app_snapshot.options.clone(),
+                self.store.clone(),
+                self.runtime_manager.clone(),
+                &self.config,
+                &self.reconf_manager,
+                self.read_only.clone(),
+                self.replicator.clone(),
+            )?;
+            if let Err(err) = app.restore(app_snapshot) {
+                warn!(
+                    "Skipping the app: {} on restoring the metadata snapshot. err: {:?}",
+                    &app_id, err
+                );
+                continue;
+            }
+            TOTAL_APP_NUMBER.inc();
+            GAUGE_APP_NUMBER.inc();
+            self.apps.insert(app_id, Arc::new(app));
+            number += 1;
+        }
+        info!(
+            "Restored {} apps from the metadata snapshot: {}",
+            number, &path
+        );
+        Ok(number)
+    }
+
+    /// Deletes the spilled files older than the max age, while the files of apps that
+    /// still keep heartbeat within this age are always retained.
+    fn reclaim_expired_spilled_files(&self, max_age_sec: u64) -> Result<u64> {
+        let reclaimed =
+            self.store
+                .reclaim_expired_localfiles(Duration::from_secs(max_age_sec), |app_id| {
+                    self.apps
+                        .get(app_id)
+                        .map_or(false, |app| app.heartbeat_age_sec() < max_age_sec)
+                })?;
+        if reclaimed > 0 {
+            info!("Reclaimed {} bytes of expired spilled files", reclaimed);
+            TOTAL_EXPIRED_SPILLED_FILE_RECLAIMED_BYTES.inc_by(reclaimed);
+        }
+        Ok(reclaimed)
+    }
+
+    /// Finds out the heartbeat timeout apps by the monotonic seconds, which never jump
+    /// with the wall clock.
+    fn find_heartbeat_timeout_apps(&self, current: u64) -> Vec<String> {
+        let timeout_sec = (self.app_heartbeat_timeout_min * 60) as u64;
+        let mut timeout_apps = vec![];
+        for item in self.apps.iter() {
+            let (key, app) = item.pair();
+            let last_time = app.get_latest_heartbeat_time();
+            if current.saturating_sub(last_time) > timeout_sec {
+                info!("Detected app:{:?} heartbeat timeout. now: {:?}, latest heartbeat: {:?}. timeout threshold: {:?}(min)",
+                    key, current, last_time, self.app_heartbeat_timeout_min);
+                timeout_apps.push(key.clone());
+            }
+        }
+        timeout_apps
+    }
+
     pub fn get_historical_statistics(&self) -> Option<&HistoricalAppStatistics> {
         self.historical_app_statistics.as_ref()
     }
@@ -962,14 +2039,84 @@ impl AppManager {
         self.apps.contains_key(app_id)
     }
 
+    /// Toggles the read only mode. The buffered data is still spilled under this mode.
+    pub fn set_read_only(&self, read_only: bool) {
+        if self.read_only.swap(read_only, SeqCst) != read_only {
+            info!("The read only mode has been set to {}", read_only);
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(SeqCst)
+    }
+
+    pub fn set_decommissioning(&self, decommissioning: bool) {
+        if self.decommissioning.swap(decommissioning, SeqCst) != decommissioning {
+            info!("The decommissioning has been set to {}", decommissioning);
+        }
+    }
+
+    pub fn is_decommissioning(&self) -> bool {
+        self.decommissioning.load(SeqCst)
+    }
+
     pub async fn store_is_healthy(&self) -> Result<bool> {
         self.store.is_healthy().await
     }
 
+    pub async fn store_drain(&self, timeout: Duration) -> Result<()> {
+        self.store.drain(timeout).await
+    }
+
+    /// Flushes the in-memory data of the app into the persistent stores without deleting it.
+    pub async fn store_flush_app(&self, app_id: &str) -> Result<u64> {
+        if !self.app_is_exist(app_id) {
+            return Err(anyhow!("No such app: {}", app_id));
+        }
+        self.store.flush_app(app_id).await
+    }
+
+    /// Trigger the localfile disks rebalance job in background, whose progress could be
+    /// fetched by the [`AppManager::store_localfile_rebalance_progress`]
+    pub fn store_rebalance_localfile_disks(&self, skew_threshold: f64) -> Result<()> {
+        if self.store.localfile_rebalance_progress()?.running {
+            return Err(anyhow!("The disk rebalance job is running"));
+        }
+        let store = self.store.clone();
+        self.runtime_manager.default_runtime.spawn_with_await_tree(
+            "Localfile disks rebalance",
+            async move {
+                match store.rebalance_localfile_disks(skew_threshold).await {
+                    Ok(progress) => info!(
+                        "Finished localfile disks rebalance. moved partitions: {}",
+                        progress.outcomes.len()
+                    ),
+                    Err(err) => error!("Errors on localfile disks rebalance. err: {:?}", err),
+                }
+            },
+        );
+        Ok(())
+    }
+
+    pub fn store_localfile_rebalance_progress(&self) -> Result<RebalanceProgress> {
+        self.store.localfile_rebalance_progress()
+    }
+
+    pub async fn store_repair_localfile_index(
+        &self,
+        uid: &PartitionedUId,
+    ) -> Result<IndexRepairOutcome> {
+        self.store.repair_localfile_index(uid).await
+    }
+
     pub async fn store_memory_snapshot(&self) -> Result<CapacitySnapshot> {
         self.store.mem_snapshot()
     }
 
+    pub async fn store_resident_bytes_by_tier(&self) -> HashMap<StorageType, u64> {
+        self.store.resident_bytes_by_tier().await
+    }
+
     pub fn store_localfile_stat(&self) -> Result<LocalfileStoreStat> {
         self.store.localfile_stat()
     }
@@ -980,37 +2127,85 @@ impl AppManager {
 
     async fn purge_app_data(&self, reason: &PurgeReason) -> Result<()> {
         let (app_id, shuffle_id_option) = reason.extract();
-        let app = self.get_app(&app_id).ok_or(anyhow!(format!(
-            "App:{} don't exist when purging data, this should not happen",
-            &app_id
-        )))?;
-        if shuffle_id_option.is_none() {
-            self.apps.remove(&app_id);
-
-            GAUGE_APP_NUMBER.dec();
-            let _ = GAUGE_TOPN_APP_RESIDENT_BYTES.remove_label_values(&[&app_id]);
-
-            let _ = TOTAL_APP_FLUSHED_BYTES.remove_label_values(&[
-                app_id.as_str(),
-                format!("{:?}", StorageType::LOCALFILE).as_str(),
-            ]);
-            let _ = TOTAL_APP_FLUSHED_BYTES.remove_label_values(&[
-                app_id.as_str(),
-                format!("{:?}", StorageType::HDFS).as_str(),
-            ]);
-
-            // record into the historical app list
-            if let Some(historical_manager) = self.historical_app_statistics.as_ref() {
-                info!(
-                    "Saving timeout app into the historical list.. app_id: {}",
-                    app_id.as_str()
-                );
-                historical_manager
-                    .save(&app)
-                    .instrument_await("Saving to historical app list...")
-                    .await?;
+        if shuffle_id_option.is_some() {
+            let app = self.get_app(&app_id).ok_or(anyhow!(format!(
+                "App:{} don't exist when purging data, this should not happen",
+                &app_id
+            )))?;
+            return app.purge(reason).await;
+        }
+
+        // the app is marked as purging under the same entry lock with the registering,
+        // so that the registering of this app is serialized with the whole purge.
+        let app = match self.apps.entry(app_id.clone()) {
+            Entry::Occupied(entry) => {
+                self.purging_apps
+                    .insert(app_id.clone(), entry.get().clone());
+                entry.remove()
+            }
+            Entry::Vacant(_) => {
+                return Err(anyhow!(format!(
+                    "App:{} don't exist when purging data, this should not happen",
+                    &app_id
+                )))
+            }
+        };
+        let purged = self.purge_removed_app(&app_id, &app, reason).await;
+        if let Err(e) = &purged {
+            if let Some(WorkerError::PURGE_CANCELLED(_)) = e.downcast_ref::<WorkerError>() {
+                // the cancelled app is kept to track the rest data, which will be purged again.
+                // This is done before unmarking the purging to not race with the registering.
+                if let Entry::Vacant(entry) = self.apps.entry(app_id.clone()) {
+                    warn!(
+                        "The purge of app:[{}] is cancelled, and the app is kept",
+                        &app_id
+                    );
+                    entry.insert(app);
+                    GAUGE_APP_NUMBER.inc();
+                }
             }
         }
+        self.purging_apps.remove(&app_id);
+        purged
+    }
+
+    /// The progress of the in-progress purge of the app, including the app level purge
+    /// whose app has been removed from the registered apps.
+    pub fn purge_progress(&self, app_id: &str) -> Option<PurgeProgress> {
+        if let Some(app) = self.purging_apps.get(app_id) {
+            return app.purge_progress();
+        }
+        self.get_app(app_id).and_then(|app| app.purge_progress())
+    }
+
+    /// Cancels the in-progress purge of the app. Returns false if no purge is running.
+    pub fn cancel_purge(&self, app_id: &str) -> bool {
+        if let Some(app) = self.purging_apps.get(app_id) {
+            return app.cancel_purge();
+        }
+        self.get_app(app_id).map_or(false, |app| app.cancel_purge())
+    }
+
+    async fn purge_removed_app(&self, app_id: &str, app: &App, reason: &PurgeReason) -> Result<()> {
+        GAUGE_APP_NUMBER.dec();
+        let _ = GAUGE_TOPN_APP_RESIDENT_BYTES.remove_label_values(&[app_id]);
+
+        let _ = TOTAL_APP_FLUSHED_BYTES
+            .remove_label_values(&[app_id, format!("{:?}", StorageType::LOCALFILE).as_str()]);
+        let _ = TOTAL_APP_FLUSHED_BYTES
+            .remove_label_values(&[app_id, format!("{:?}", StorageType::HDFS).as_str()]);
+
+        // record into the historical app list
+        if let Some(historical_manager) = self.historical_app_statistics.as_ref() {
+            info!(
+                "Saving timeout app into the historical list.. app_id: {}",
+                app_id
+            );
+            historical_manager
+                .save(app)
+                .instrument_await("Saving to historical app list...")
+                .await?;
+        }
         app.purge(reason).await?;
         Ok(())
     }
@@ -1019,10 +2214,55 @@ impl AppManager {
         self.apps.get(app_id).map(|v| v.value().clone())
     }
 
+    /// The absolute deadline of the reading request, taking the earlier one between the
+    /// client timeout and the configured default.
+    pub fn read_deadline(&self, client_timeout: Option<Duration>) -> Option<Instant> {
+        let default_timeout = self
+            .config
+            .app_config
+            .read_deadline_ms
+            .map(Duration::from_millis);
+        let timeout = match (client_timeout, default_timeout) {
+            (Some(client), Some(default)) => Some(client.min(default)),
+            (client, default) => client.or(default),
+        };
+        timeout.map(|timeout| Instant::now() + timeout)
+    }
+
     pub fn get_alive_app_number(&self) -> usize {
         self.apps.len()
     }
 
+    /// The sum of the expected but not yet received bytes of all the apps.
+    pub fn committed_expected_bytes(&self) -> u64 {
+        self.apps
+            .iter()
+            .map(|app| app.outstanding_expected_bytes())
+            .sum()
+    }
+
+    /// Denies the new app whose expected total bytes can't fit into the free capacity
+    /// multiplied by the overcommit ratio, with the committed bytes of other apps considered.
+    fn check_expected_size_admission(&self, expected_total_bytes: u64) -> Result<()> {
+        let free_capacity = match self.store.free_capacity()? {
+            Some(free) => free,
+            // the unbounded cold store is used
+            _ => return Ok(()),
+        };
+        let admission_capacity = (free_capacity as f64
+            * self.config.app_config.app_expected_size_overcommit_ratio)
+            as u64;
+        let committed = self.committed_expected_bytes();
+        if committed.saturating_add(expected_total_bytes) > admission_capacity {
+            return Err(WorkerError::APP_EXPECTED_SIZE_EXCEEDS_CAPACITY(
+                expected_total_bytes,
+                admission_capacity.saturating_sub(committed),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     pub fn register(
         &self,
         app_id: String,
@@ -1034,23 +2274,40 @@ impl AppManager {
             app_id.clone(),
             shuffle_id
         );
-        let app_ref = self
-            .apps
-            .entry(app_id.clone())
-            .or_insert_with(|| {
-                TOTAL_APP_NUMBER.inc();
-                GAUGE_APP_NUMBER.inc();
-
-                Arc::new(App::from(
+        if self.is_read_only() {
+            return Err(WorkerError::READ_ONLY_MODE.into());
+        }
+        if let Some(expected_total_bytes) = app_config_options.expected_total_bytes {
+            if !self.apps.contains_key(&app_id) {
+                self.check_expected_size_admission(expected_total_bytes)?;
+            }
+        }
+        let app_ref = match self.apps.entry(app_id.clone()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                if self.is_decommissioning() {
+                    return Err(WorkerError::SERVER_DECOMMISSIONING(app_id).into());
+                }
+                if self.config.app_config.register_purge_serialization_enable
+                    && self.purging_apps.contains_key(&app_id)
+                {
+                    return Err(WorkerError::APP_IS_PURGING(app_id).into());
+                }
+                let app = App::from(
                     app_id,
                     app_config_options,
                     self.store.clone(),
                     self.runtime_manager.clone(),
                     &self.config,
                     &self.reconf_manager,
-                ))
-            })
-            .clone();
+                    self.read_only.clone(),
+                    self.replicator.clone(),
+                )?;
+                TOTAL_APP_NUMBER.inc();
+                GAUGE_APP_NUMBER.inc();
+                entry.insert(Arc::new(app)).clone()
+            }
+        };
         app_ref.register_shuffle(shuffle_id)
     }
 
@@ -1106,24 +2363,37 @@ impl PartitionedUId {
 #[cfg(test)]
 pub(crate) mod test {
     use crate::app::{
-        AppManager, GetBlocksContext, GetMultiBlockIdsContext, PartitionedUId, PurgeReason,
-        ReadingOptions, ReadingViewContext, ReportBlocksContext, ReportMultiBlockIdsContext,
-        RequireBufferContext, WritingViewContext,
+        AppConfigOptions, AppManager, DataDistribution, GetBlocksContext, GetMultiBlockIdsContext,
+        PartitionedUId, PurgeReason, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
+        RemoteStorageConfig, ReportBlocksContext, ReportMultiBlockIdsContext, RequireBufferContext,
+        WritingViewContext,
+    };
+    use crate::chaos::{FaultMode, FaultOperation, FaultSpec, FaultTarget, FAULT_INJECTOR};
+    use crate::config::{
+        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, MetadataSnapshotConfig,
+        SpillCandidateSelectPolicy, StorageType,
     };
-    use crate::config::{Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig};
     use crate::config_reconfigure::ReconfigurableConfManager;
     use crate::error::WorkerError;
     use crate::id_layout::{to_layout, IdLayout, DEFAULT_BLOCK_ID_LAYOUT};
+    use crate::metric::{
+        GAUGE_APP_EXPECTED_SIZE_PROGRESS, GAUGE_HUGE_PARTITION_NUMBER,
+        TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL,
+    };
     use crate::runtime::manager::RuntimeManager;
     use crate::storage::StorageService;
-    use crate::store::{Block, ResponseData};
+    use crate::store::{Block, ResponseData, ResponseDataIndex};
+    use crate::util::now_monotonic_as_sec;
     use bytes::Bytes;
     use crc32fast::hash;
     use croaring::{JvmLegacy, Treemap};
     use dashmap::DashMap;
     use parking_lot::RwLock;
     use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::Ordering::SeqCst;
     use std::sync::Arc;
+    use std::time::Duration;
 
     #[test]
     fn test_uid_hash() {
@@ -1188,6 +2458,10 @@ pub(crate) mod test {
                 buffer_ticket_timeout_sec: 1,
                 buffer_ticket_check_interval_sec: 1,
                 dashmap_shard_amount: 16,
+                max_segments_per_response: 100000,
+                buffer_max_initial_batches: 64,
+                buffer_shrink_ratio: 0.25,
+                read_crc_verify_enable: false,
             }),
         );
         let _ = std::mem::replace(
@@ -1203,6 +2477,16 @@ pub(crate) mod test {
                 sensitive_watermark_spill_enable: false,
                 async_watermark_spill_trigger_enable: false,
                 async_watermark_spill_trigger_interval_ms: 0,
+                shutdown_drain_timeout_sec: 0,
+                memory_spill_candidate_select_policy: SpillCandidateSelectPolicy::LARGEST_FIRST,
+                spill_retry_max_attempts: 3,
+                spill_retry_backoff_base_millis: 100,
+                spill_retry_queue_capacity: 1024,
+                spill_retry_max_age_sec: 600,
+                spill_dead_letter_dir: None,
+                spilled_read_retry_window_ms: 1000,
+                spilled_read_retry_max_attempts: 5,
+                require_buffer_reject_ratio: None,
             },
         );
         let mut app_config = &mut config.app_config;
@@ -1242,92 +2526,1123 @@ pub(crate) mod test {
     }
 
     #[test]
-    fn app_put_get_purge_test() {
-        let app_id = "app_put_get_purge_test-----id";
-
+    fn app_huge_partition_direct_cold_spill_test() -> anyhow::Result<()> {
+        let app_id = "app_huge_partition_direct_cold_spill_test";
         let runtime_manager: RuntimeManager = Default::default();
-        let config = mock_config();
+
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        // the watermark spill is never triggered
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+
         let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
         let storage = StorageService::init(&runtime_manager, &config);
         let app_manager_ref =
             AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
-        app_manager_ref
-            .register(app_id.clone().into(), 1, Default::default())
-            .unwrap();
-
-        if let Some(app) = app_manager_ref.get_app("app_id".into()) {
-            let writing_ctx = mock_writing_context(&app_id, 1, 0, 2, 20);
-
-            // case1: put
-            let f = app.insert(writing_ctx);
-            if runtime_manager.wait(f).is_err() {
-                panic!()
-            }
-
-            let reading_ctx = ReadingViewContext {
-                uid: Default::default(),
-                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
-                serialized_expected_task_ids_bitmap: Default::default(),
-            };
-
-            // case2: get
-            let f = app.select(reading_ctx);
-            let result = runtime_manager.wait(f);
-            if result.is_err() {
-                panic!()
-            }
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
 
-            match result.unwrap() {
-                ResponseData::Mem(data) => {
-                    assert_eq!(2, data.shuffle_data_block_segments.len());
-                }
-                _ => todo!(),
-            }
+        let direct_spill_num = TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL.get();
 
-            // check the data size
-            assert_eq!(40, app.total_received_data_size());
-            assert_eq!(40, app.total_resident_data_size());
+        // the normal partition is kept in memory
+        let normal_uid = PartitionedUId::from(app_id.to_string(), 1, 1);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 1, 1, 5)))?;
 
-            // case3: purge
-            runtime_manager
-                .wait(
-                    app_manager_ref.purge_app_data(&PurgeReason::APP_LEVEL_HEARTBEAT_TIMEOUT(
-                        app_id.to_owned(),
-                    )),
-                )
-                .expect("");
+        // the huge partition is spilled directly without waiting the watermark
+        let huge_uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 10)))?;
+        assert!(app.is_huge_partition(&huge_uid)?);
+        assert!(TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL.get() > direct_spill_num);
 
-            assert_eq!(false, app_manager_ref.get_app(app_id).is_none());
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            app_manager_ref.store.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(app_manager_ref.store.get_memory_buffer_size(&huge_uid))
+                    .unwrap()
+                    == 0
+        });
+        assert_eq!(
+            5,
+            runtime_manager.wait(app_manager_ref.store.get_memory_buffer_size(&normal_uid))?
+        );
+        let location = runtime_manager.wait(app.get_partition_location(&huge_uid))?;
+        assert!(location.localfile_disk_root.is_some());
 
-            // check the data size again after the data has been removed
-            assert_eq!(40, app.total_received_data_size());
-            assert_eq!(0, app.total_resident_data_size());
-        }
+        Ok(())
     }
 
     #[test]
-    fn app_manager_test() {
-        let config = mock_config();
+    fn app_write_rate_limit_test() {
+        let app_id = "app_write_rate_limit_test";
         let runtime_manager: RuntimeManager = Default::default();
-        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
-        let storage = StorageService::init(&runtime_manager, &config);
-        let app_manager_ref =
-            AppManager::get_ref(Default::default(), config, &storage, &reconf_manager).clone();
-
-        app_manager_ref
-            .register("app_id".into(), 1, Default::default())
-            .unwrap();
-        if let Some(app) = app_manager_ref.get_app("app_id".into()) {
-            assert_eq!("app_id", app.app_id);
-        }
-    }
 
-    #[test]
-    fn test_get_or_put_block_ids() {
-        let app_id = "test_get_or_put_block_ids-----id".to_string();
+        let mut config = mock_config();
+        config.app_config.app_write_rate_limit = Some("1000B".to_string());
 
-        let runtime_manager: RuntimeManager = Default::default();
-        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref
+            .register(app_id.clone().into(), 1, Default::default())
+            .unwrap();
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+
+        let ctx = |size: i64| RequireBufferContext {
+            uid: PartitionedUId {
+                app_id: app_id.to_string(),
+                shuffle_id: 1,
+                partition_id: 0,
+            },
+            size,
+            partition_ids: vec![0],
+        };
+
+        // case1: exhaust the bucket
+        assert!(runtime_manager.wait(app.require_buffer(ctx(1000))).is_ok());
+        match runtime_manager.wait(app.require_buffer(ctx(500))) {
+            Err(WorkerError::APP_WRITE_RATE_LIMITED) => {}
+            _ => panic!(),
+        }
+
+        // case2: succeed after refilling
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| runtime_manager.wait(app.require_buffer(ctx(500))).is_ok());
+    }
+
+    #[test]
+    fn app_memory_backpressure_test() -> anyhow::Result<()> {
+        let app_id = "app_memory_backpressure_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.hybrid_store.require_buffer_reject_ratio = Some(0.5);
+        let capacity = 1024 * 1024;
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let ctx = |size: i64| RequireBufferContext {
+            uid: PartitionedUId::from(app_id.to_string(), 1, 0),
+            size,
+            partition_ids: vec![0],
+        };
+
+        // case1: the memory is filled past the ratio
+        let ticket = runtime_manager.wait(app.require_buffer(ctx(600 * 1024)))?;
+        match runtime_manager.wait(app.require_buffer(ctx(1024))) {
+            Err(WorkerError::MEMORY_BACKPRESSURE(used, total)) => {
+                assert_eq!(600 * 1024, used);
+                assert_eq!(capacity, total);
+            }
+            _ => panic!("the buffer requirement should be rejected by the memory backpressure"),
+        }
+
+        // case2: accepted after the memory is released
+        runtime_manager.wait(app.release_ticket(ticket.ticket_id))?;
+        assert!(runtime_manager.wait(app.require_buffer(ctx(1024))).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_quota_test() -> anyhow::Result<()> {
+        let app_id = "app_quota_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.app_config.app_quota_soft_limit = Some("40B".to_string());
+        config.app_config.app_quota_hard_limit = Some("1000B".to_string());
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+
+        // the per-app options override the global hard limit
+        let mut options = AppConfigOptions::default();
+        options.quota_hard_limit = Some(80);
+        app_manager_ref.register(app_id.to_string(), 1, options)?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+
+        let ctx = |size: i64| RequireBufferContext {
+            uid: PartitionedUId::from(app_id.to_string(), 1, 0),
+            size,
+            partition_ids: vec![0],
+        };
+
+        // case1: crossing the soft limit only marks it
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 20)))?;
+        assert!(!app.is_quota_soft_limit_exceeded());
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 20)))?;
+        assert!(app.is_quota_soft_limit_exceeded());
+        assert_eq!(60, app.total_resident_data_size());
+
+        // case2: crossing the hard limit rejects the writing
+        match runtime_manager.wait(app.require_buffer(ctx(40))) {
+            Err(WorkerError::APP_QUOTA_EXCEEDED) => {}
+            _ => panic!(),
+        }
+        match runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 20))) {
+            Err(WorkerError::APP_QUOTA_EXCEEDED) => {}
+            _ => panic!(),
+        }
+        assert_eq!(60, app.total_resident_data_size());
+        assert!(runtime_manager.wait(app.require_buffer(ctx(20))).is_ok());
+
+        // case3: the usage is released after the shuffle level purge
+        runtime_manager.wait(app.purge(&PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(
+            app_id.to_string(),
+            1,
+        )))?;
+        assert_eq!(0, app.total_resident_data_size());
+        assert!(runtime_manager.wait(app.require_buffer(ctx(40))).is_ok());
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 2, 0, 1, 20)))?;
+        assert!(!app.is_quota_soft_limit_exceeded());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_shuffle_purge_partition_meta_test() -> anyhow::Result<()> {
+        let app_id = "app_shuffle_purge_partition_meta_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "30B".to_string();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        // the shuffle 1 has 2 partitions and the huge one, the shuffle 2 has 1 partition
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 20)))?;
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 1, 1, 10)))?;
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 2, 0, 1, 10)))?;
+        assert!(app.is_huge_partition(&PartitionedUId::from(app_id.to_string(), 1, 0))?);
+        assert_eq!(3, app.partition_number());
+        assert_eq!(1, app.huge_partition_number());
+        assert_eq!(60, app.total_resident_data_size());
+
+        runtime_manager.wait(app.purge(&PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(
+            app_id.to_string(),
+            1,
+        )))?;
+        assert_eq!(1, app.partition_number());
+        assert_eq!(0, app.huge_partition_number());
+        assert_eq!(
+            0,
+            GAUGE_HUGE_PARTITION_NUMBER
+                .with_label_values(&[app_id])
+                .get()
+        );
+        assert_eq!(10, app.total_resident_data_size());
+        assert_eq!(vec![(0, 10)], app.partition_sizes(2));
+        assert!(app.partition_sizes(1).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_read_only_mode_test() -> anyhow::Result<()> {
+        let app_id = "app_read_only_mode_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_single_buffer_max_spill_size = Some("30B".to_string());
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 20)))?;
+        runtime_manager.wait(app.report_multi_block_ids(ReportMultiBlockIdsContext {
+            shuffle_id: 1,
+            block_ids: HashMap::from([(0, vec![0, 1])]),
+        }))?;
+
+        app_manager_ref.set_read_only(true);
+        assert!(app_manager_ref.is_read_only());
+
+        // case1: the writing is rejected
+        match runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 20))) {
+            Err(WorkerError::READ_ONLY_MODE) => {}
+            _ => panic!(),
+        }
+        let require_ctx = RequireBufferContext {
+            uid: uid.clone(),
+            size: 20,
+            partition_ids: vec![0],
+        };
+        match runtime_manager.wait(app.require_buffer(require_ctx)) {
+            Err(WorkerError::READ_ONLY_MODE) => {}
+            _ => panic!(),
+        }
+        assert!(app_manager_ref
+            .register(app_id.to_string(), 2, Default::default())
+            .is_err());
+
+        // case2: the pending spill triggered before is still finished
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            app_manager_ref.store.get_in_flight_size().unwrap() == 0
+                && !runtime_manager
+                    .wait(app.get_partition_location(&uid))
+                    .unwrap()
+                    .memory
+        });
+        let location = runtime_manager.wait(app.get_partition_location(&uid))?;
+        assert!(location.localfile_disk_root.is_some());
+
+        // case3: the reading is still served
+        match runtime_manager.wait(app.list_index(ReadingIndexViewContext {
+            partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
+        }))? {
+            ResponseDataIndex::Local(index) => assert_eq!(40, index.data_file_len),
+            _ => panic!(),
+        }
+        let block_ids = runtime_manager.wait(app.get_multi_block_ids(GetMultiBlockIdsContext {
+            shuffle_id: 1,
+            partition_ids: vec![0],
+            layout: to_layout(None),
+        }))?;
+        assert!(!block_ids.is_empty());
+
+        // case4: the writing is accepted after cancelling
+        app_manager_ref.set_read_only(false);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 20)))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_write_reservation_check_test() -> anyhow::Result<()> {
+        let app_id = "app_write_reservation_check_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.app_config.write_reservation_check_enable = true;
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+
+        let require_ctx = RequireBufferContext {
+            uid: PartitionedUId::from(app_id.to_string(), 1, 0),
+            size: 20,
+            partition_ids: vec![0],
+        };
+        let ticket_id = runtime_manager
+            .wait(app.require_buffer(require_ctx))?
+            .ticket_id;
+        let reserved_len = runtime_manager.wait(app.release_ticket(ticket_id))?;
+        assert_eq!(20, reserved_len);
+
+        // the larger writing is rejected
+        let ctx = mock_writing_context(app_id, 1, 0, 2, 20);
+        match app.check_write_reservation(reserved_len, ctx.data_size as i64) {
+            Err(WorkerError::WRITE_EXCEEDS_RESERVATION(40, 20)) => {}
+            _ => panic!(),
+        }
+        assert!(app.check_write_reservation(reserved_len, 20).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_limits_parsed_once_test() -> anyhow::Result<()> {
+        let app_id = "app_limits_parsed_once_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        // case1: the malformed strings are rejected on registration
+        let mut config = mock_config();
+        config.app_config.app_quota_hard_limit = Some("10WRONG".to_string());
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        let err = app_manager_ref
+            .register(app_id.to_string(), 1, Default::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("app_config.app_quota_hard_limit"));
+        assert!(app_manager_ref.get_app(app_id.as_ref()).is_none());
+
+        // case2: the writing path goes with the parsed limits
+        let mut config = mock_config();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_split_enable = true;
+        config.app_config.app_quota_hard_limit = Some("1M".to_string());
+        config.app_config.app_write_rate_limit = Some("1M".to_string());
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+        assert_eq!(Some(1000 * 1000), app.limits.quota_hard_limit);
+
+        for _ in 0..5 {
+            let require_ctx = RequireBufferContext {
+                uid: PartitionedUId::from(app_id.to_string(), 1, 0),
+                size: 20,
+                partition_ids: vec![0],
+            };
+            runtime_manager.wait(app.require_buffer(require_ctx))?;
+            runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 20)))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_consistency_check_test() -> anyhow::Result<()> {
+        let app_id = "app_consistency_check_test";
+        let temp_dir = tempdir::TempDir::new("app_consistency_check_test")?;
+
+        // the huge partition is spilled to the localfile directly
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.hybrid_store.spill_retry_max_attempts = 1;
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+        config.metadata_snapshot = Some(MetadataSnapshotConfig {
+            path: format!("{}/snapshot.json", temp_dir.path().to_str().unwrap()),
+            interval_sec: 600,
+        });
+        let disk_root = config.localfile_store.as_ref().unwrap().data_paths[0].clone();
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref = AppManager::get_ref(
+            runtime_manager.clone(),
+            config.clone(),
+            &storage,
+            &reconf_manager,
+        );
+        storage.with_app_manager(&app_manager_ref);
+
+        let mut options = AppConfigOptions::default();
+        options.consistency_check_enable = true;
+        app_manager_ref.register(app_id.into(), 1, options)?;
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+        assert!(app.is_consistency_check_enabled());
+
+        let insert = |partition_id: i32| -> anyhow::Result<()> {
+            let mut ctx = mock_writing_context(app_id, 1, partition_id, 2, 20);
+            for (idx, block) in ctx.data_blocks.iter_mut().enumerate() {
+                block.crc = (partition_id as i64 + 1) * 1000 + idx as i64;
+            }
+            let expected = ctx.data_blocks.iter().map(|block| block.crc).sum::<i64>();
+            app.add_expected_crc_aggregate(&ctx.uid, expected);
+            runtime_manager.wait(app.insert(ctx))?;
+            Ok(())
+        };
+        let is_spill_finished = |partition_id: i32| {
+            let uid = PartitionedUId::from(app_id.to_string(), 1, partition_id);
+            storage.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(storage.get_memory_buffer_size(&uid))
+                    .unwrap()
+                    == 0
+        };
+
+        // case1: all the blocks are flushed
+        insert(0)?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| is_spill_finished(0));
+        assert!(app.get_divergent_partitions(1).is_empty());
+
+        // case2: the blocks of partition 1 are dropped by the failed spill
+        FAULT_INJECTOR.enable(true);
+        let fault_id = FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(disk_root),
+                store_type: None,
+                operation: Some(FaultOperation::APPEND),
+            },
+            mode: FaultMode::ERROR("EIO".to_string()),
+            probability: 1.0,
+            ttl_sec: 60,
+        })?;
+        insert(1)?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| is_spill_finished(1));
+        FAULT_INJECTOR.remove(fault_id);
+        assert_eq!(vec![1], app.get_divergent_partitions(1));
+
+        // case3: the aggregates survive the restart
+        assert_eq!(
+            1,
+            runtime_manager.wait(app_manager_ref.snapshot_metadata())?
+        );
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        assert!(app.is_consistency_check_enabled());
+        assert_eq!(vec![1], app.get_divergent_partitions(1));
+
+        // case4: the aggregates are cleared after the shuffle is purged
+        runtime_manager.wait(app.purge(&PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(
+            app_id.to_string(),
+            1,
+        )))?;
+        assert!(app.get_divergent_partitions(1).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_purge_cancelled_test() -> anyhow::Result<()> {
+        let app_id = "app_purge_cancelled_test";
+
+        // the huge partition is spilled to the localfile directly
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+        config.localfile_store.as_mut().unwrap().purge_parallelism = 1;
+        let disk_root = config.localfile_store.as_ref().unwrap().data_paths[0].clone();
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref = AppManager::get_ref(
+            runtime_manager.clone(),
+            config.clone(),
+            &storage,
+            &reconf_manager,
+        );
+        storage.with_app_manager(&app_manager_ref);
+
+        app_manager_ref.register(app_id.into(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        let shuffle_number = 3;
+        for shuffle_id in 0..shuffle_number {
+            runtime_manager.wait(app.insert(mock_writing_context(app_id, shuffle_id, 0, 2, 20)))?;
+        }
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            storage.get_in_flight_size().unwrap() == 0
+                && (0..shuffle_number).all(|shuffle_id| {
+                    let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, 0);
+                    runtime_manager
+                        .wait(storage.get_memory_buffer_size(&uid))
+                        .unwrap()
+                        == 0
+                })
+        });
+        assert!(app_manager_ref.purge_progress(app_id).is_none());
+        assert!(!app_manager_ref.cancel_purge(app_id));
+
+        // the slow deletion leaves the time window to cancel the purge
+        FAULT_INJECTOR.enable(true);
+        let fault_id = FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(disk_root.clone()),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::DELETE),
+            },
+            mode: FaultMode::LATENCY_MS(500),
+            probability: 1.0,
+            ttl_sec: 60,
+        })?;
+        let manager = app_manager_ref.clone();
+        let runtime = runtime_manager.clone();
+        let purging = std::thread::spawn(move || {
+            runtime.wait(
+                manager.purge_app_data(&PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(
+                    app_id.to_string(),
+                )),
+            )
+        });
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| app_manager_ref.purge_progress(app_id).is_some());
+        assert!(app_manager_ref.cancel_purge(app_id));
+        let purged = purging.join().unwrap();
+        FAULT_INJECTOR.remove(fault_id);
+        match purged {
+            Err(e) => match e.downcast_ref::<WorkerError>() {
+                Some(WorkerError::PURGE_CANCELLED(_)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+            _ => panic!("the cancelled purge should fail"),
+        }
+
+        // the app is kept with the rest files, which are purged again
+        assert!(app_manager_ref.get_app(app_id).is_some());
+        assert!(Path::new(&format!("{}/{}/{}", &disk_root, app_id, shuffle_number - 1)).exists());
+        runtime_manager.wait(app_manager_ref.purge_app_data(
+            &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+        ))?;
+        assert!(app_manager_ref.get_app(app_id).is_none());
+        assert!(!Path::new(&format!("{}/{}", &disk_root, app_id)).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_expected_size_admission_test() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.app_config.app_expected_size_overcommit_ratio = 1.0;
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        // the memory only store with 1M capacity
+        let capacity = 1024 * 1024;
+
+        let options = |expected_total_bytes: Option<u64>| {
+            let mut options = AppConfigOptions::default();
+            options.expected_total_bytes = expected_total_bytes;
+            options
+        };
+        let is_denied = |result: anyhow::Result<()>| match result {
+            Err(e) => matches!(
+                e.downcast_ref::<WorkerError>(),
+                Some(WorkerError::APP_EXPECTED_SIZE_EXCEEDS_CAPACITY(_, _))
+            ),
+            _ => false,
+        };
+
+        // case1: the apps fit into the capacity
+        app_manager_ref.register("app-1".into(), 1, options(Some(600 * 1024)))?;
+        assert!(is_denied(app_manager_ref.register(
+            "app-2".into(),
+            1,
+            options(Some(capacity - 600 * 1024 + 1))
+        )));
+        app_manager_ref.register("app-2".into(), 1, options(Some(capacity - 600 * 1024)))?;
+        assert_eq!(capacity, app_manager_ref.committed_expected_bytes());
+
+        // case2: the apps without hint and the registered app are not checked
+        app_manager_ref.register("app-3".into(), 1, options(None))?;
+        app_manager_ref.register("app-1".into(), 2, options(Some(capacity)))?;
+        assert_eq!(3, app_manager_ref.get_alive_app_number());
+
+        // case3: the committed bytes decay as the real data arrives
+        let app = app_manager_ref.get_app("app-1").unwrap();
+        runtime_manager.wait(app.insert(mock_writing_context("app-1", 1, 0, 2, 20)))?;
+        assert_eq!(capacity - 40, app_manager_ref.committed_expected_bytes());
+        assert!(is_denied(app_manager_ref.register(
+            "app-4".into(),
+            1,
+            options(Some(41))
+        )));
+        app_manager_ref.register("app-4".into(), 1, options(Some(40)))?;
+
+        // case4: the progress gauge tracks the received/expected
+        let progress = 40f64 / (600 * 1024) as f64;
+        assert_eq!(Some(progress), app.expected_size_progress());
+        app.update_expected_size_progress_metric();
+        assert_eq!(
+            progress,
+            GAUGE_APP_EXPECTED_SIZE_PROGRESS
+                .with_label_values(&["app-1"])
+                .get()
+        );
+        assert_eq!(
+            None,
+            app_manager_ref
+                .get_app("app-3")
+                .unwrap()
+                .expected_size_progress()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_heartbeat_timeout_test() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let timeout_sec = (config.app_config.app_heartbeat_timeout_min * 60) as u64;
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        for idx in 0..3 {
+            app_manager_ref.register(
+                format!("app_heartbeat_timeout_test-{}", idx),
+                1,
+                Default::default(),
+            )?;
+        }
+        let last_time = now_monotonic_as_sec();
+        for idx in 0..3 {
+            app_manager_ref
+                .get_app(&format!("app_heartbeat_timeout_test-{}", idx))
+                .unwrap()
+                .latest_heartbeat_time
+                .store(last_time, SeqCst);
+        }
+
+        // case1: the normal interval
+        assert!(app_manager_ref
+            .find_heartbeat_timeout_apps(last_time + 1)
+            .is_empty());
+
+        // case2: the real timeout
+        let timeout_apps =
+            app_manager_ref.find_heartbeat_timeout_apps(last_time + timeout_sec + 10);
+        assert_eq!(3, timeout_apps.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_put_get_purge_test() {
+        let app_id = "app_put_get_purge_test-----id";
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref
+            .register(app_id.clone().into(), 1, Default::default())
+            .unwrap();
+
+        if let Some(app) = app_manager_ref.get_app("app_id".into()) {
+            let writing_ctx = mock_writing_context(&app_id, 1, 0, 2, 20);
+
+            // case1: put
+            let f = app.insert(writing_ctx);
+            if runtime_manager.wait(f).is_err() {
+                panic!()
+            }
+
+            let reading_ctx = ReadingViewContext {
+                uid: Default::default(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            };
+
+            // case2: get
+            let f = app.select(reading_ctx);
+            let result = runtime_manager.wait(f);
+            if result.is_err() {
+                panic!()
+            }
+
+            match result.unwrap() {
+                ResponseData::Mem(data) => {
+                    assert_eq!(2, data.shuffle_data_block_segments.len());
+                }
+                _ => todo!(),
+            }
+
+            // check the data size
+            assert_eq!(40, app.total_received_data_size());
+            assert_eq!(40, app.total_resident_data_size());
+
+            // case3: purge
+            runtime_manager
+                .wait(
+                    app_manager_ref.purge_app_data(&PurgeReason::APP_LEVEL_HEARTBEAT_TIMEOUT(
+                        app_id.to_owned(),
+                    )),
+                )
+                .expect("");
+
+            assert_eq!(false, app_manager_ref.get_app(app_id).is_none());
+
+            // check the data size again after the data has been removed
+            assert_eq!(40, app.total_received_data_size());
+            assert_eq!(0, app.total_resident_data_size());
+        }
+    }
+
+    #[test]
+    fn app_register_unregister_race_test() -> anyhow::Result<()> {
+        let app_id = "app_register_unregister_race_test";
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+
+        // case1: the registering is rejected while the app purge is in flight
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let (_, purging_app) = app_manager_ref.apps.remove(app_id).unwrap();
+        app_manager_ref
+            .purging_apps
+            .insert(app_id.to_string(), purging_app);
+        match app_manager_ref.register(app_id.to_string(), 1, Default::default()) {
+            Err(e) => match e.downcast_ref::<WorkerError>() {
+                Some(WorkerError::APP_IS_PURGING(_)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+            _ => panic!("the registering should be rejected"),
+        }
+        assert!(app_manager_ref.get_app(app_id).is_none());
+        app_manager_ref.purging_apps.remove(app_id);
+
+        // case2: the interleaved registering and purging always end up with the consistent state
+        for _ in 0..50 {
+            if app_manager_ref.get_app(app_id).is_none() {
+                app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+                let app = app_manager_ref.get_app(app_id).unwrap();
+                runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 10)))?;
+            }
+
+            let manager = app_manager_ref.clone();
+            let runtime = runtime_manager.clone();
+            let register = std::thread::spawn(move || -> anyhow::Result<()> {
+                loop {
+                    match manager.register(app_id.to_string(), 1, Default::default()) {
+                        Ok(_) => break,
+                        Err(e) => match e.downcast_ref::<WorkerError>() {
+                            Some(WorkerError::APP_IS_PURGING(_)) => std::thread::yield_now(),
+                            _ => return Err(e),
+                        },
+                    }
+                }
+                // only the clean app created after the purge is written
+                if let Some(app) = manager.get_app(app_id) {
+                    if app.total_received_data_size() == 0 {
+                        runtime.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 10)))?;
+                    }
+                }
+                Ok(())
+            });
+            runtime_manager.wait(app_manager_ref.purge_app_data(
+                &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            ))?;
+            register.join().unwrap()?;
+
+            // the purged buffer is absent
+            let stored = runtime_manager
+                .wait(app_manager_ref.store.get_memory_buffer_size(&uid))
+                .unwrap_or(0);
+            match app_manager_ref.get_app(app_id) {
+                Some(app) => assert_eq!(app.total_resident_data_size(), stored),
+                None => assert_eq!(0, stored),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn max_shuffles_per_app_test() -> anyhow::Result<()> {
+        let app_id = "max_shuffles_per_app_test";
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.app_config.max_shuffles_per_app = Some(2);
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        app_manager_ref.register(app_id.to_string(), 2, Default::default())?;
+        // the re-registering of the existing shuffle is allowed
+        app_manager_ref.register(app_id.to_string(), 2, Default::default())?;
+
+        // case1: rejected beyond the limit
+        match app_manager_ref.register(app_id.to_string(), 3, Default::default()) {
+            Err(e) => match e.downcast_ref::<WorkerError>() {
+                Some(WorkerError::TOO_MANY_SHUFFLES(_, 2)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+            _ => panic!("the registering should be rejected"),
+        }
+
+        // case2: accepted after the shuffle is unregistered
+        runtime_manager.wait(app_manager_ref.purge_app_data(
+            &PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string(), 1),
+        ))?;
+        app_manager_ref.register(app_id.to_string(), 3, Default::default())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_remote_storage_config_test() -> anyhow::Result<()> {
+        let app_id = "update_remote_storage_config_test";
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+
+        let remote_storage_config = |root: &str| RemoteStorageConfig {
+            root: root.to_string(),
+            configs: HashMap::from([("token".to_string(), root.to_string())]),
+        };
+        app_manager_ref.register(
+            app_id.to_string(),
+            1,
+            AppConfigOptions::new(
+                DataDistribution::NORMAL,
+                20,
+                Some(remote_storage_config("memory://bucket/old")),
+            ),
+        )?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        assert_eq!(
+            Some("memory://bucket/old".to_string()),
+            app.remote_storage_root()
+        );
+
+        app.update_remote_storage_config(remote_storage_config("memory://bucket/new"))?;
+        assert_eq!(
+            Some("memory://bucket/new".to_string()),
+            app.remote_storage_root()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn max_partitions_per_app_test() -> anyhow::Result<()> {
+        let app_id = "max_partitions_per_app_test";
+        let runtime_manager: RuntimeManager = Default::default();
+        let create_app = |reject: bool| -> anyhow::Result<Arc<App>> {
+            let mut config = mock_config();
+            config.app_config.max_partitions_per_app = Some(2);
+            config.app_config.reject_partitions_beyond_max = reject;
+            let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+            let storage = StorageService::init(&runtime_manager, &config);
+            let app_manager_ref =
+                AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+            app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+            Ok(app_manager_ref.get_app(app_id).unwrap())
+        };
+
+        // case1: the partitions beyond the soft cap are accepted and counted
+        let app = create_app(false)?;
+        let beyond = TOTAL_PARTITIONS_BEYOND_APP_LIMIT.get();
+        for partition_id in 0..3 {
+            runtime_manager.wait(app.insert(mock_writing_context(
+                app_id,
+                1,
+                partition_id,
+                1,
+                10,
+            )))?;
+        }
+        assert_eq!(3, app.partition_number());
+        assert!(TOTAL_PARTITIONS_BEYOND_APP_LIMIT.get() > beyond);
+
+        // case2: the new partitions beyond the cap are rejected
+        let app = create_app(true)?;
+        for partition_id in 0..2 {
+            runtime_manager.wait(app.insert(mock_writing_context(
+                app_id,
+                1,
+                partition_id,
+                1,
+                10,
+            )))?;
+        }
+        match runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 2, 1, 10))) {
+            Err(WorkerError::TOO_MANY_PARTITIONS(_, 2)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // the tracked partitions are still writable
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 10)))?;
+        assert_eq!(2, app.partition_number());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_metadata_snapshot_restore_test() -> anyhow::Result<()> {
+        let app_id = "app_metadata_snapshot_restore_test";
+        let temp_dir = tempdir::TempDir::new("app_metadata_snapshot_restore_test")?;
+
+        // the huge partition is spilled to the localfile directly
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+        config.metadata_snapshot = Some(MetadataSnapshotConfig {
+            path: format!("{}/snapshot.json", temp_dir.path().to_str().unwrap()),
+            interval_sec: 600,
+        });
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref = AppManager::get_ref(
+            runtime_manager.clone(),
+            config.clone(),
+            &storage,
+            &reconf_manager,
+        );
+        let options = AppConfigOptions {
+            user: "user".to_string(),
+            consistency_check_enable: true,
+            quota_hard_limit: Some(1024),
+            ..Default::default()
+        };
+        app_manager_ref.register(app_id.to_string(), 1, options.clone())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 12)))?;
+        // the small partition is kept in memory, which is lost after the restart
+        let mut writing_ctx = mock_writing_context(app_id, 1, 1, 1, 5);
+        writing_ctx.data_blocks[0].block_id = 2;
+        runtime_manager.wait(app.insert(writing_ctx))?;
+        runtime_manager.wait(app.report_multi_block_ids(ReportMultiBlockIdsContext::new(
+            1,
+            HashMap::from([(0, vec![0, 1]), (1, vec![2])]),
+        )))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            storage.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(storage.get_memory_buffer_size(&uid))
+                    .unwrap()
+                    == 0
+        });
+        assert_eq!(
+            5,
+            runtime_manager.wait(storage.get_memory_buffer_size(&PartitionedUId::from(
+                app_id.to_string(),
+                1,
+                1
+            )))?
+        );
+        assert_eq!(
+            1,
+            runtime_manager.wait(app_manager_ref.snapshot_metadata())?
+        );
+
+        // simulate the restart with the same disks, the apps are restored from the snapshot
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        assert_eq!(options, app.app_config_options);
+        assert_eq!(24, app.total_resident_data_size());
+        assert!(app.is_huge_partition(&uid)?);
+
+        let data = runtime_manager
+            .wait(app.select(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, 24),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_local();
+        assert_eq!(24, data.len());
+
+        let block_ids = runtime_manager.wait(app.get_multi_block_ids(GetMultiBlockIdsContext {
+            shuffle_id: 1,
+            partition_ids: vec![0],
+            layout: to_layout(None),
+        }))?;
+        assert_eq!(
+            2,
+            Treemap::deserialize::<JvmLegacy>(&block_ids).cardinality()
+        );
+
+        // the block ids of the memory data are excluded
+        let block_ids = runtime_manager.wait(app.get_multi_block_ids(GetMultiBlockIdsContext {
+            shuffle_id: 1,
+            partition_ids: vec![1],
+            layout: to_layout(None),
+        }))?;
+        assert_eq!(
+            0,
+            Treemap::deserialize::<JvmLegacy>(&block_ids).cardinality()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_partition_sizes_test() -> anyhow::Result<()> {
+        let app_id = "app_partition_sizes_test";
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        app_manager_ref.register(app_id.to_string(), 2, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 1, 10)))?;
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 1, 3, 10)))?;
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 2, 2, 10)))?;
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 2, 0, 5, 10)))?;
+
+        assert_eq!(vec![(1, 30), (2, 20), (0, 10)], app.partition_sizes(1));
+        assert_eq!(vec![(0, 50)], app.partition_sizes(2));
+        assert!(app.partition_sizes(3).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_manager_test() {
+        let config = mock_config();
+        let runtime_manager: RuntimeManager = Default::default();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(Default::default(), config, &storage, &reconf_manager).clone();
+
+        app_manager_ref
+            .register("app_id".into(), 1, Default::default())
+            .unwrap();
+        if let Some(app) = app_manager_ref.get_app("app_id".into()) {
+            assert_eq!("app_id", app.app_id);
+        }
+    }
+
+    #[test]
+    fn test_get_or_put_block_ids() {
+        let app_id = "test_get_or_put_block_ids-----id".to_string();
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
         let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
         let storage = StorageService::init(&runtime_manager, &config);
         let app_manager_ref =
@@ -1378,6 +3693,50 @@ pub(crate) mod test {
         assert_eq!(deserialized, Treemap::from_iter(vec![block_id_3 as u64]));
     }
 
+    #[test]
+    fn test_check_reported_block_ids_count() {
+        let app_id = "test_check_reported_block_ids_count-----id".to_string();
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        app_manager_ref
+            .register(app_id.clone().into(), 1, Default::default())
+            .unwrap();
+        let app = app_manager_ref.get_app(app_id.as_ref()).unwrap();
+
+        let report = |block_ids: Vec<i64>| {
+            let mut hashmap = HashMap::new();
+            hashmap.insert(10, block_ids);
+            runtime_manager
+                .wait(app.report_multi_block_ids(ReportMultiBlockIdsContext::new(1, hashmap)))
+                .unwrap();
+        };
+        let block_ids: Vec<i64> = (0..3)
+            .map(|idx| DEFAULT_BLOCK_ID_LAYOUT.get_block_id(idx, 10, idx))
+            .collect();
+
+        // case1: the first report is lost, the second report carries the cumulative count
+        report(vec![block_ids[2]]);
+        let expected = HashMap::from([(10, 3i64)]);
+        let deltas = app.check_reported_block_ids_count(1, &expected).unwrap();
+        assert_eq!(Some(&2), deltas.get(&10));
+
+        // case2: re-report the missing block ids, and it should converge
+        report(vec![block_ids[0], block_ids[1], block_ids[2]]);
+        let deltas = app.check_reported_block_ids_count(1, &expected).unwrap();
+        assert!(deltas.is_empty());
+
+        // case3: no expected count, no check
+        let deltas = app
+            .check_reported_block_ids_count(1, &HashMap::new())
+            .unwrap();
+        assert!(deltas.is_empty());
+    }
+
     #[test]
     fn test_dashmap_values() {
         let dashmap = DashMap::new();
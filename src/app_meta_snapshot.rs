@@ -0,0 +1,116 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The snapshot of the apps metadata, which is persisted into the local file periodically
+//! and restored on startup. Only the data spilled into the localfile store survives the
+//! restart, the memory data is lost.
+
+use crate::app::AppConfigOptions;
+use crate::block_id_manager::BlockIdsSnapshot;
+use crate::util;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetadataSnapshot {
+    pub timestamp: u128,
+    pub apps: Vec<AppMetaSnapshot>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AppMetaSnapshot {
+    pub app_id: String,
+    pub partitions: Vec<PartitionMetaSnapshot>,
+    // only the block ids of the flushed data, the memory ones are excluded
+    pub block_ids: Vec<BlockIdsSnapshot>,
+    #[serde(default)]
+    pub options: AppConfigOptions,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PartitionMetaSnapshot {
+    pub shuffle_id: i32,
+    pub partition_id: i32,
+    pub total_size: u64,
+    pub is_huge_partition: bool,
+    pub is_split: bool,
+    // the disk root and the committed data offset of the flushed partition
+    pub localfile: Option<(String, i64)>,
+    // the client and the server side crc aggregates of the consistency check
+    #[serde(default)]
+    pub crc_aggregate: Option<(i64, i64)>,
+}
+
+impl MetadataSnapshot {
+    pub fn write_to(&self, path: &str) -> Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        util::write_atomically(path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Returns None if no snapshot has been persisted.
+    pub fn read_from(path: &str) -> Result<Option<MetadataSnapshot>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(path)?;
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::AppConfigOptions;
+    use crate::app_meta_snapshot::{AppMetaSnapshot, MetadataSnapshot, PartitionMetaSnapshot};
+
+    #[test]
+    fn test_write_and_read() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_metadata_snapshot")?;
+        let path = format!("{}/meta/snapshot.json", temp_dir.path().to_str().unwrap());
+        assert_eq!(None, MetadataSnapshot::read_from(&path)?);
+
+        let snapshot = MetadataSnapshot {
+            timestamp: 1,
+            apps: vec![AppMetaSnapshot {
+                app_id: "app".to_string(),
+                partitions: vec![PartitionMetaSnapshot {
+                    shuffle_id: 1,
+                    partition_id: 2,
+                    total_size: 10,
+                    is_huge_partition: false,
+                    is_split: false,
+                    localfile: Some(("/data1".to_string(), 10)),
+                    crc_aggregate: Some((100, 90)),
+                }],
+                block_ids: vec![],
+                options: AppConfigOptions {
+                    user: "user".to_string(),
+                    consistency_check_enable: true,
+                    quota_hard_limit: Some(100),
+                    ..Default::default()
+                },
+            }],
+        };
+        snapshot.write_to(&path)?;
+        assert_eq!(Some(snapshot), MetadataSnapshot::read_from(&path)?);
+
+        Ok(())
+    }
+}
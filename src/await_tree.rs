@@ -18,8 +18,10 @@
 use await_tree::{Registry, TreeRoot};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use serde::Serialize;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 type AwaitTreeRegistryRef = Arc<Mutex<Registry<u64>>>;
 
@@ -48,4 +50,132 @@ impl AwaitTreeInner {
     pub fn get_inner(&self) -> AwaitTreeRegistryRef {
         self.inner.clone()
     }
+
+    /// Renders all the registered actors sorted by the actor id.
+    pub fn dump(&self) -> Vec<ActorTree> {
+        let registry = self.inner.lock();
+        let mut raw_trees: Vec<(u64, String)> = registry
+            .iter()
+            .map(|(id, tree)| (*id, format!("{}", tree)))
+            .collect();
+        drop(registry);
+
+        raw_trees.sort_by_key(|kv| kv.0);
+        raw_trees
+            .into_iter()
+            .map(|(actor_id, tree)| ActorTree::from(actor_id, tree))
+            .collect()
+    }
+}
+
+/// The rendered await-tree of one actor, whose elapsed time is the root span's.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActorTree {
+    pub actor_id: u64,
+    pub elapsed_ms: Option<u64>,
+    pub spans: Vec<SpanView>,
+    #[serde(skip)]
+    pub tree: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SpanView {
+    pub depth: usize,
+    pub span: String,
+    pub elapsed_ms: Option<u64>,
+}
+
+impl ActorTree {
+    fn from(actor_id: u64, tree: String) -> Self {
+        let spans: Vec<SpanView> = tree
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(SpanView::parse)
+            .collect();
+        let elapsed_ms = spans.first().and_then(|span| span.elapsed_ms);
+        Self {
+            actor_id,
+            elapsed_ms,
+            spans,
+            tree,
+        }
+    }
+}
+
+impl SpanView {
+    /// Parses the rendered line like `  span [1.234s]  <== current`, and the line without
+    /// the elapsed time is kept as it is.
+    fn parse(line: &str) -> Self {
+        let trimmed = line.trim_start_matches(' ');
+        let depth = (line.len() - trimmed.len()) / 2;
+        let content = trimmed.trim_end_matches("  <== current");
+        let parsed = content.rfind(" [").and_then(|idx| {
+            let elapsed = content[idx + 2..]
+                .strip_suffix(']')?
+                .trim_start_matches("!!! ");
+            Some((idx, parse_elapsed(elapsed)?))
+        });
+        match parsed {
+            Some((idx, elapsed)) => Self {
+                depth,
+                span: content[..idx].to_string(),
+                elapsed_ms: Some(elapsed.as_millis() as u64),
+            },
+            _ => Self {
+                depth,
+                span: content.to_string(),
+                elapsed_ms: None,
+            },
+        }
+    }
+}
+
+/// Parses the debug format of the duration, like `1.234s`, `5.000ms`.
+fn parse_elapsed(raw: &str) -> Option<Duration> {
+    let (value, unit_nanos) = if let Some(value) = raw.strip_suffix("ns") {
+        (value, 1.0)
+    } else if let Some(value) = raw.strip_suffix("µs") {
+        (value, 1e3)
+    } else if let Some(value) = raw.strip_suffix("ms") {
+        (value, 1e6)
+    } else if let Some(value) = raw.strip_suffix('s') {
+        (value, 1e9)
+    } else {
+        return None;
+    };
+    let value: f64 = value.parse().ok()?;
+    Some(Duration::from_nanos((value * unit_nanos).round() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::await_tree::{parse_elapsed, SpanView};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_span() {
+        assert_eq!(
+            Some(Duration::from_millis(1969123)),
+            parse_elapsed("1969.123s")
+        );
+        assert_eq!(Some(Duration::from_micros(5500)), parse_elapsed("5.500ms"));
+        assert_eq!(None, parse_elapsed("unknown"));
+
+        assert_eq!(
+            SpanView {
+                depth: 1,
+                span: "inserting data".to_string(),
+                elapsed_ms: Some(1969123),
+            },
+            SpanView::parse("  inserting data [!!! 1969.123s]  <== current")
+        );
+        assert_eq!(
+            SpanView {
+                depth: 0,
+                span: "[Detached 1]".to_string(),
+                elapsed_ms: None,
+            },
+            SpanView::parse("[Detached 1]")
+        );
+    }
 }
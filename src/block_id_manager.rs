@@ -1,13 +1,14 @@
 use crate::app::{GetMultiBlockIdsContext, ReportMultiBlockIdsContext};
 use crate::block_id_manager::BlockIdManagerType::DEFAULT;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
 use croaring::{JvmLegacy, Treemap};
 use dashmap::DashMap;
+use log::warn;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
 use std::sync::Arc;
@@ -16,9 +17,87 @@ use std::sync::Arc;
 #[async_trait]
 pub trait BlockIdManager: Send + Sync {
     async fn get_multi_block_ids(&self, ctx: GetMultiBlockIdsContext) -> Result<Bytes>;
-    async fn report_multi_block_ids(&self, ctx: ReportMultiBlockIdsContext) -> Result<u64>;
+    // the serialized bitmap of every requested partition, the missing partition will be
+    // returned as the empty bitmap.
+    async fn get_block_ids_batch(
+        &self,
+        ctx: GetMultiBlockIdsContext,
+    ) -> Result<HashMap<i32, Bytes>>;
+    async fn report_multi_block_ids(
+        &self,
+        ctx: ReportMultiBlockIdsContext,
+    ) -> Result<ReportedBlockIds>;
     async fn purge_block_ids(&self, shuffle_id: i32) -> Result<u64>;
     fn get_blocks_number(&self) -> Result<u64>;
+    // the distinct block ids number of the partition, which will not serialize the bitmap.
+    fn get_cardinality(&self, shuffle_id: i32, partition_id: i32) -> Result<u64>;
+    // dump all the bitmaps to be persisted, which are restored after the worker restarts.
+    fn snapshot(&self) -> Result<Vec<BlockIdsSnapshot>>;
+    fn restore(&self, snapshots: Vec<BlockIdsSnapshot>) -> Result<u64>;
+}
+
+/// The reported block ids number, and the newly added ones of them. The already present
+/// ids come from the retried reports of the speculative tasks, or the double write bug.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReportedBlockIds {
+    pub reported: u64,
+    pub newly_added: u64,
+}
+
+impl ReportedBlockIds {
+    pub fn duplicated(&self) -> u64 {
+        self.reported - self.newly_added
+    }
+}
+
+/// The persisted bitmap of the partition, or of the whole shuffle when the partition_id
+/// is None.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockIdsSnapshot {
+    pub shuffle_id: i32,
+    pub partition_id: Option<i32>,
+    pub serialized_bitmap: Vec<u8>,
+    // key: partition_id. only for the shuffle level bitmap
+    pub partition_cardinality: Vec<(i32, u64)>,
+}
+
+impl BlockIdsSnapshot {
+    fn deserialize_bitmap(&self) -> Result<Treemap> {
+        Treemap::try_deserialize::<JvmLegacy>(&self.serialized_bitmap).ok_or_else(|| {
+            anyhow!(
+                "Corrupted block ids bitmap of shuffle: {}, partition: {:?}",
+                self.shuffle_id,
+                self.partition_id
+            )
+        })
+    }
+
+    /// Excludes the block ids of the partition, which are still in memory and will be lost
+    /// after the restart.
+    pub fn exclude(&mut self, partition_id: i32, block_ids: &[i64]) -> Result<()> {
+        if block_ids.is_empty() || self.partition_id.map_or(false, |x| x != partition_id) {
+            return Ok(());
+        }
+        let mut treemap = self.deserialize_bitmap()?;
+        let mut excluded = 0;
+        for block_id in block_ids {
+            let block_id = *block_id as u64;
+            if treemap.contains(block_id) {
+                treemap.remove(block_id);
+                excluded += 1;
+            }
+        }
+        if excluded == 0 {
+            return Ok(());
+        }
+        self.serialized_bitmap = treemap.serialize::<JvmLegacy>();
+        for (pid, cardinality) in self.partition_cardinality.iter_mut() {
+            if *pid == partition_id {
+                *cardinality = cardinality.saturating_sub(excluded);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, strum_macros::Display)]
@@ -43,6 +122,8 @@ pub fn get_block_id_manager(b_type: &BlockIdManagerType) -> Arc<Box<dyn BlockIdM
 pub struct PartitionedBlockIdManager {
     block_id_bitmap: DashMap<i32, Arc<RwLock<Treemap>>>,
     number: AtomicU64,
+    // key: (shuffle_id, partition_id), val: the distinct block ids number
+    partition_cardinality: DashMap<(i32, i32), u64>,
 }
 
 #[async_trait]
@@ -68,7 +149,39 @@ impl BlockIdManager for PartitionedBlockIdManager {
         Ok(Bytes::from(retrieved.serialize::<JvmLegacy>()))
     }
 
-    async fn report_multi_block_ids(&self, ctx: ReportMultiBlockIdsContext) -> Result<u64> {
+    async fn get_block_ids_batch(
+        &self,
+        ctx: GetMultiBlockIdsContext,
+    ) -> Result<HashMap<i32, Bytes>> {
+        let block_id_layout = &ctx.layout;
+        let mut retrieved: HashMap<i32, Treemap> = ctx
+            .partition_ids
+            .iter()
+            .map(|pid| (*pid, Treemap::new()))
+            .collect();
+
+        // all partitions share the one bitmap of shuffle, so iterate it only once.
+        if let Some(treemap) = self.block_id_bitmap.get(&ctx.shuffle_id) {
+            let treemap = treemap.clone();
+            let treemap = treemap.read();
+            for element in treemap.iter() {
+                let partition_id = block_id_layout.get_partition_id(element as i64) as i32;
+                if let Some(bitmap) = retrieved.get_mut(&partition_id) {
+                    bitmap.add(element);
+                }
+            }
+        }
+
+        Ok(retrieved
+            .into_iter()
+            .map(|(pid, bitmap)| (pid, Bytes::from(bitmap.serialize::<JvmLegacy>())))
+            .collect())
+    }
+
+    async fn report_multi_block_ids(
+        &self,
+        ctx: ReportMultiBlockIdsContext,
+    ) -> Result<ReportedBlockIds> {
         let shuffle_id = &ctx.shuffle_id;
         let treemap = self
             .block_id_bitmap
@@ -78,14 +191,25 @@ impl BlockIdManager for PartitionedBlockIdManager {
         let partitioned_block_ids = ctx.block_ids;
         let mut treemap = treemap.write();
         let mut number = 0;
-        for (_, block_ids) in partitioned_block_ids {
+        let mut newly_added = 0;
+        for (pid, block_ids) in partitioned_block_ids {
             number += block_ids.len();
+            let before = treemap.cardinality();
             for block_id in block_ids {
                 treemap.add(block_id as u64);
             }
+            let added = treemap.cardinality() - before;
+            newly_added += added;
+            *self
+                .partition_cardinality
+                .entry((*shuffle_id, pid))
+                .or_insert(0) += added;
         }
         self.number.fetch_add(number as u64, SeqCst);
-        Ok(number as u64)
+        Ok(ReportedBlockIds {
+            reported: number as u64,
+            newly_added,
+        })
     }
 
     async fn purge_block_ids(&self, shuffle_id: i32) -> Result<u64> {
@@ -95,6 +219,8 @@ impl BlockIdManager for PartitionedBlockIdManager {
             let purged = map.cardinality();
             self.number.fetch_sub(purged, SeqCst);
         }
+        self.partition_cardinality
+            .retain(|(v_shuffle_id, _), _| *v_shuffle_id != shuffle_id);
         Ok(purged as u64)
     }
 
@@ -102,6 +228,62 @@ impl BlockIdManager for PartitionedBlockIdManager {
         let number = self.number.load(SeqCst);
         Ok(number)
     }
+
+    fn get_cardinality(&self, shuffle_id: i32, partition_id: i32) -> Result<u64> {
+        Ok(self
+            .partition_cardinality
+            .get(&(shuffle_id, partition_id))
+            .map_or(0, |x| *x))
+    }
+
+    fn snapshot(&self) -> Result<Vec<BlockIdsSnapshot>> {
+        let mut snapshots = vec![];
+        for entry in self.block_id_bitmap.iter() {
+            let shuffle_id = *entry.key();
+            let partition_cardinality = self
+                .partition_cardinality
+                .iter()
+                .filter(|x| x.key().0 == shuffle_id)
+                .map(|x| (x.key().1, *x.value()))
+                .collect();
+            snapshots.push(BlockIdsSnapshot {
+                shuffle_id,
+                partition_id: None,
+                serialized_bitmap: entry.value().read().serialize::<JvmLegacy>(),
+                partition_cardinality,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    fn restore(&self, snapshots: Vec<BlockIdsSnapshot>) -> Result<u64> {
+        let mut number = 0;
+        for snapshot in snapshots {
+            if snapshot.partition_id.is_some() {
+                warn!(
+                    "Skipping the partition level block ids snapshot of shuffle: {}, which is not restored by the partitioned block id manager",
+                    snapshot.shuffle_id
+                );
+                continue;
+            }
+            let treemap = match snapshot.deserialize_bitmap() {
+                Ok(treemap) => treemap,
+                Err(err) => {
+                    warn!("Skipping the block ids snapshot. err: {:?}", err);
+                    continue;
+                }
+            };
+            number += treemap.cardinality();
+            self.block_id_bitmap
+                .insert(snapshot.shuffle_id, Arc::new(RwLock::new(treemap)));
+            for (partition_id, cardinality) in snapshot.partition_cardinality {
+                self.partition_cardinality
+                    .insert((snapshot.shuffle_id, partition_id), cardinality);
+            }
+        }
+        self.number.fetch_add(number, SeqCst);
+        Ok(number)
+    }
 }
 
 #[derive(Default)]
@@ -127,10 +309,37 @@ impl BlockIdManager for DefaultBlockIdManager {
         Ok(Bytes::from(treemap.serialize::<JvmLegacy>()))
     }
 
-    async fn report_multi_block_ids(&self, ctx: ReportMultiBlockIdsContext) -> Result<u64> {
+    async fn get_block_ids_batch(
+        &self,
+        ctx: GetMultiBlockIdsContext,
+    ) -> Result<HashMap<i32, Bytes>> {
+        let shuffle_id = ctx.shuffle_id;
+        let partition_ids: HashSet<i32> = HashSet::from_iter(ctx.partition_ids);
+        let futures = partition_ids.into_iter().map(|pid| async move {
+            let serialized = match self.block_id_bitmap.get(&(shuffle_id, pid)) {
+                Some(bitmap) => {
+                    let bitmap = bitmap.clone();
+                    let bitmap = bitmap.read();
+                    bitmap.serialize::<JvmLegacy>()
+                }
+                _ => Treemap::new().serialize::<JvmLegacy>(),
+            };
+            (pid, Bytes::from(serialized))
+        });
+        Ok(futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .collect())
+    }
+
+    async fn report_multi_block_ids(
+        &self,
+        ctx: ReportMultiBlockIdsContext,
+    ) -> Result<ReportedBlockIds> {
         let shuffle_id = ctx.shuffle_id;
         let partitioned_block_ids = ctx.block_ids;
         let mut number = 0;
+        let mut newly_added = 0;
         for (pid, block_ids) in partitioned_block_ids {
             number += block_ids.len();
             let treemap = self
@@ -139,12 +348,17 @@ impl BlockIdManager for DefaultBlockIdManager {
                 .or_insert_with(|| Arc::new(RwLock::new(Treemap::new())))
                 .clone();
             let mut treemap = treemap.write();
+            let before = treemap.cardinality();
             for block_id in block_ids {
                 treemap.add(block_id as u64);
             }
+            newly_added += treemap.cardinality() - before;
         }
         self.number.fetch_add(number as u64, SeqCst);
-        Ok(number as u64)
+        Ok(ReportedBlockIds {
+            reported: number as u64,
+            newly_added,
+        })
     }
 
     async fn purge_block_ids(&self, shuffle_id: i32) -> Result<u64> {
@@ -170,12 +384,64 @@ impl BlockIdManager for DefaultBlockIdManager {
     fn get_blocks_number(&self) -> Result<u64> {
         Ok(self.number.load(SeqCst))
     }
+
+    fn get_cardinality(&self, shuffle_id: i32, partition_id: i32) -> Result<u64> {
+        match self.block_id_bitmap.get(&(shuffle_id, partition_id)) {
+            Some(bitmap) => Ok(bitmap.read().cardinality()),
+            _ => Ok(0),
+        }
+    }
+
+    fn snapshot(&self) -> Result<Vec<BlockIdsSnapshot>> {
+        Ok(self
+            .block_id_bitmap
+            .iter()
+            .map(|entry| BlockIdsSnapshot {
+                shuffle_id: entry.key().0,
+                partition_id: Some(entry.key().1),
+                serialized_bitmap: entry.value().read().serialize::<JvmLegacy>(),
+                partition_cardinality: vec![],
+            })
+            .collect())
+    }
+
+    fn restore(&self, snapshots: Vec<BlockIdsSnapshot>) -> Result<u64> {
+        let mut number = 0;
+        for snapshot in snapshots {
+            let partition_id = match snapshot.partition_id {
+                Some(partition_id) => partition_id,
+                _ => {
+                    warn!(
+                        "Skipping the shuffle level block ids snapshot of shuffle: {}, which is not restored by the default block id manager",
+                        snapshot.shuffle_id
+                    );
+                    continue;
+                }
+            };
+            let treemap = match snapshot.deserialize_bitmap() {
+                Ok(treemap) => treemap,
+                Err(err) => {
+                    warn!("Skipping the block ids snapshot. err: {:?}", err);
+                    continue;
+                }
+            };
+            number += treemap.cardinality();
+            self.block_id_bitmap.insert(
+                (snapshot.shuffle_id, partition_id),
+                Arc::new(RwLock::new(treemap)),
+            );
+        }
+        self.number.fetch_add(number, SeqCst);
+        Ok(number)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::app::{GetMultiBlockIdsContext, ReportMultiBlockIdsContext};
-    use crate::block_id_manager::{get_block_id_manager, BlockIdManager, BlockIdManagerType};
+    use crate::block_id_manager::{
+        get_block_id_manager, BlockIdManager, BlockIdManagerType, ReportedBlockIds,
+    };
     use crate::id_layout::{to_layout, DEFAULT_BLOCK_ID_LAYOUT};
     use anyhow::Result;
     use croaring::{JvmLegacy, Treemap};
@@ -203,6 +469,8 @@ mod tests {
             })
             .await?;
         assert_eq!(100 * 20, manager.get_blocks_number()?);
+        assert_eq!(20, manager.get_cardinality(shuffle_id, 1)?);
+        assert_eq!(0, manager.get_cardinality(shuffle_id, 100)?);
 
         // get by one partition
         for partition_id in 0..100 {
@@ -244,9 +512,129 @@ mod tests {
             }
         }
 
+        // get by batch, the partition 100 has no reported block
+        let batch = manager
+            .get_block_ids_batch(GetMultiBlockIdsContext {
+                shuffle_id,
+                partition_ids: vec![1, 2, 3, 100],
+                layout: to_layout(None),
+            })
+            .await?;
+        assert_eq!(4, batch.len());
+        for partition_id in [1, 2, 3] {
+            let deserialized = Treemap::deserialize::<JvmLegacy>(batch.get(&partition_id).unwrap());
+            assert_eq!(20, deserialized.cardinality());
+            for block_id in deserialized.iter() {
+                assert_eq!(
+                    partition_id,
+                    layout.get_partition_id(block_id as i64) as i32
+                );
+            }
+        }
+        let deserialized = Treemap::deserialize::<JvmLegacy>(batch.get(&100).unwrap());
+        assert_eq!(0, deserialized.cardinality());
+
         // purge
         manager.purge_block_ids(shuffle_id).await?;
         assert_eq!(0, manager.get_blocks_number()?);
+        assert_eq!(0, manager.get_cardinality(shuffle_id, 1)?);
+
+        Ok(())
+    }
+
+    async fn test_snapshot_restore(manager_type: BlockIdManagerType) -> Result<()> {
+        let shuffle_id = 1;
+        let mut partitioned_block_ids = HashMap::new();
+        for pid in 0..3 {
+            let block_ids = (0..10)
+                .map(|idx| DEFAULT_BLOCK_ID_LAYOUT.get_block_id(idx, pid, idx))
+                .collect();
+            partitioned_block_ids.insert(pid as i32, block_ids);
+        }
+        let manager = get_block_id_manager(&manager_type);
+        manager
+            .report_multi_block_ids(ReportMultiBlockIdsContext {
+                shuffle_id,
+                block_ids: partitioned_block_ids,
+            })
+            .await?;
+
+        let restored = get_block_id_manager(&manager_type);
+        assert_eq!(30, restored.restore(manager.snapshot()?)?);
+        assert_eq!(30, restored.get_blocks_number()?);
+        assert_eq!(10, restored.get_cardinality(shuffle_id, 1)?);
+        let gotten = restored
+            .get_multi_block_ids(GetMultiBlockIdsContext {
+                shuffle_id,
+                partition_ids: vec![2],
+                layout: to_layout(None),
+            })
+            .await?;
+        assert_eq!(10, Treemap::deserialize::<JvmLegacy>(&gotten).cardinality());
+
+        // the truncated bitmap is skipped rather than panicking
+        let mut snapshots = manager.snapshot()?;
+        for snapshot in snapshots.iter_mut() {
+            snapshot.serialized_bitmap.truncate(3);
+        }
+        let restored = get_block_id_manager(&manager_type);
+        assert_eq!(0, restored.restore(snapshots)?);
+        assert_eq!(0, restored.get_blocks_number()?);
+
+        // the snapshot of the other block id manager type is skipped
+        let other_type = match manager_type {
+            BlockIdManagerType::DEFAULT => BlockIdManagerType::PARTITIONED,
+            BlockIdManagerType::PARTITIONED => BlockIdManagerType::DEFAULT,
+        };
+        let restored = get_block_id_manager(&other_type);
+        assert_eq!(0, restored.restore(manager.snapshot()?)?);
+
+        // the excluded block ids of the partition are not restored
+        let mut snapshots = manager.snapshot()?;
+        let excluded: Vec<i64> = (0..4)
+            .map(|idx| DEFAULT_BLOCK_ID_LAYOUT.get_block_id(idx, 1, idx))
+            .collect();
+        for snapshot in snapshots.iter_mut() {
+            snapshot.exclude(1, &excluded)?;
+        }
+        let restored = get_block_id_manager(&manager_type);
+        assert_eq!(26, restored.restore(snapshots)?);
+        assert_eq!(6, restored.get_cardinality(shuffle_id, 1)?);
+        assert_eq!(10, restored.get_cardinality(shuffle_id, 2)?);
+
+        Ok(())
+    }
+
+    async fn test_duplicated_report(manager_type: BlockIdManagerType) -> Result<()> {
+        let manager = get_block_id_manager(&manager_type);
+        let report = || {
+            manager.report_multi_block_ids(ReportMultiBlockIdsContext {
+                shuffle_id: 1,
+                block_ids: HashMap::from([(0, vec![1, 2, 2, 3])]),
+            })
+        };
+
+        let reported = report().await?;
+        assert_eq!(
+            ReportedBlockIds {
+                reported: 4,
+                newly_added: 3
+            },
+            reported
+        );
+        assert_eq!(1, reported.duplicated());
+
+        // all the ids are already present on the retried report
+        let reported = report().await?;
+        assert_eq!(
+            ReportedBlockIds {
+                reported: 4,
+                newly_added: 0
+            },
+            reported
+        );
+        assert_eq!(4, reported.duplicated());
+        assert_eq!(3, manager.get_cardinality(1, 0)?);
 
         Ok(())
     }
@@ -255,6 +643,10 @@ mod tests {
     async fn test() -> Result<()> {
         test_block_id_manager(get_block_id_manager(&BlockIdManagerType::DEFAULT)).await?;
         test_block_id_manager(get_block_id_manager(&BlockIdManagerType::PARTITIONED)).await?;
+        test_snapshot_restore(BlockIdManagerType::DEFAULT).await?;
+        test_snapshot_restore(BlockIdManagerType::PARTITIONED).await?;
+        test_duplicated_report(BlockIdManagerType::DEFAULT).await?;
+        test_duplicated_report(BlockIdManagerType::PARTITIONED).await?;
 
         Ok(())
     }
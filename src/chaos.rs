@@ -0,0 +1,303 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The fault injection for the resilience drills. It is inert unless the
+//! `fault_injection_enable` is set and the faults are registered via the admin http api.
+
+use crate::config::StorageType;
+use crate::error::WorkerError;
+use crate::metric::TOTAL_INJECTED_FAULTS;
+use crate::util;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub static FAULT_INJECTOR: Lazy<FaultInjector> = Lazy::new(|| FaultInjector::new());
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum FaultOperation {
+    APPEND,
+    READ,
+    WRITE,
+    DELETE,
+    REQUIRE_BUFFER,
+}
+
+/// The component that the fault is injected into. The unset field matches all.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct FaultTarget {
+    pub disk_root: Option<String>,
+    pub store_type: Option<StorageType>,
+    pub operation: Option<FaultOperation>,
+}
+
+impl FaultTarget {
+    fn matches(
+        &self,
+        store_type: StorageType,
+        disk_root: Option<&str>,
+        operation: FaultOperation,
+    ) -> bool {
+        if let Some(expected) = self.store_type {
+            if expected != store_type {
+                return false;
+            }
+        }
+        if let Some(expected) = self.operation {
+            if expected != operation {
+                return false;
+            }
+        }
+        match self.disk_root.as_ref() {
+            Some(expected) => disk_root == Some(expected.as_str()),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum FaultMode {
+    /// fail the operation with the given error code
+    ERROR(String),
+    /// delay the operation with the given milliseconds
+    LATENCY_MS(u64),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FaultSpec {
+    pub target: FaultTarget,
+    pub mode: FaultMode,
+    #[serde(default = "as_default_probability")]
+    pub probability: f64,
+    pub ttl_sec: u64,
+}
+
+fn as_default_probability() -> f64 {
+    1.0
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActiveFault {
+    pub id: u64,
+    pub spec: FaultSpec,
+    pub expired_timestamp_ms: u64,
+}
+
+impl ActiveFault {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expired_timestamp_ms <= now
+    }
+}
+
+pub struct FaultInjector {
+    enabled: AtomicBool,
+    id_generator: AtomicU64,
+
+    // the faults are replaced as a whole snapshot, so the hot path only clones the Arc.
+    faults: RwLock<Arc<Vec<ActiveFault>>>,
+    has_faults: AtomicBool,
+}
+
+impl FaultInjector {
+    fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            id_generator: AtomicU64::new(0),
+            faults: RwLock::new(Arc::new(vec![])),
+            has_faults: AtomicBool::new(false),
+        }
+    }
+
+    pub fn enable(&self, enabled: bool) {
+        if enabled {
+            info!("The fault injection has been enabled");
+        }
+        self.enabled.store(enabled, SeqCst);
+        if !enabled {
+            self.update(|faults| faults.clear());
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(SeqCst)
+    }
+
+    fn update<F: FnOnce(&mut Vec<ActiveFault>)>(&self, f: F) {
+        let mut snapshot = self.faults.write();
+        let mut faults = snapshot.as_ref().clone();
+        let now = util::now_timestamp_as_millis() as u64;
+        faults.retain(|fault| !fault.is_expired(now));
+        f(&mut faults);
+        self.has_faults.store(!faults.is_empty(), SeqCst);
+        *snapshot = Arc::new(faults);
+    }
+
+    pub fn register(&self, spec: FaultSpec) -> Result<u64, WorkerError> {
+        if !self.is_enabled() {
+            return Err(WorkerError::FAULT_INJECTION_DISABLED);
+        }
+        if !(0.0..=1.0).contains(&spec.probability) {
+            return Err(WorkerError::INVALID_FAULT_SPEC(format!(
+                "probability: {} should be in [0, 1]",
+                spec.probability
+            )));
+        }
+        let id = self.id_generator.fetch_add(1, SeqCst);
+        let expired_timestamp_ms = util::now_timestamp_as_millis() as u64 + spec.ttl_sec * 1000;
+        info!("Registering the fault. id: {}. spec: {:?}", id, &spec);
+        self.update(|faults| {
+            faults.push(ActiveFault {
+                id,
+                spec,
+                expired_timestamp_ms,
+            })
+        });
+        Ok(id)
+    }
+
+    pub fn remove(&self, id: u64) -> bool {
+        let mut removed = false;
+        self.update(|faults| {
+            let len = faults.len();
+            faults.retain(|fault| fault.id != id);
+            removed = len != faults.len();
+        });
+        if removed {
+            info!("The fault: {} has been removed", id);
+        }
+        removed
+    }
+
+    pub fn list(&self) -> Vec<ActiveFault> {
+        self.update(|_| {});
+        self.faults.read().as_ref().clone()
+    }
+
+    /// Injects the matched active faults into the operation. The latency faults will
+    /// delay it and the error faults will fail it.
+    pub async fn inject(
+        &self,
+        store_type: StorageType,
+        disk_root: Option<&str>,
+        operation: FaultOperation,
+    ) -> Result<(), WorkerError> {
+        if !self.has_faults.load(SeqCst) {
+            return Ok(());
+        }
+        let snapshot = self.faults.read().clone();
+        let now = util::now_timestamp_as_millis() as u64;
+        for fault in snapshot.iter() {
+            if fault.is_expired(now) || !fault.spec.target.matches(store_type, disk_root, operation)
+            {
+                continue;
+            }
+            if rand::random::<f64>() >= fault.spec.probability {
+                continue;
+            }
+            TOTAL_INJECTED_FAULTS
+                .with_label_values(&[&format!("{:?}", operation)])
+                .inc();
+            warn!(
+                "Injecting the fault: {} into the operation: {:?} of {:?}. disk: {:?}. mode: {:?}",
+                fault.id, operation, store_type, disk_root, &fault.spec.mode
+            );
+            match &fault.spec.mode {
+                FaultMode::LATENCY_MS(millis) => {
+                    tokio::time::sleep(Duration::from_millis(*millis)).await;
+                }
+                FaultMode::ERROR(code) => {
+                    return Err(WorkerError::INJECTED_FAULT(format!(
+                        "fault: {}, code: {}",
+                        fault.id, code
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chaos::{FaultMode, FaultOperation, FaultSpec, FaultTarget, FAULT_INJECTOR};
+    use crate::config::StorageType;
+    use crate::error::WorkerError;
+    use std::time::{Duration, Instant};
+
+    fn spec(disk_root: &str, mode: FaultMode, probability: f64) -> FaultSpec {
+        FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(disk_root.to_string()),
+                store_type: None,
+                operation: Some(FaultOperation::READ),
+            },
+            mode,
+            probability,
+            ttl_sec: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection() -> anyhow::Result<()> {
+        let root = "/chaos-test-root";
+        FAULT_INJECTOR.enable(true);
+
+        // case1: invalid probability
+        match FAULT_INJECTOR.register(spec(root, FaultMode::LATENCY_MS(1), 1.5)) {
+            Err(WorkerError::INVALID_FAULT_SPEC(_)) => {}
+            _ => panic!(),
+        }
+
+        // case2: the zero probability fault never be injected
+        let id = FAULT_INJECTOR.register(spec(root, FaultMode::ERROR("EIO".to_string()), 0.0))?;
+        for _ in 0..100 {
+            FAULT_INJECTOR
+                .inject(StorageType::LOCALFILE, Some(root), FaultOperation::READ)
+                .await?;
+        }
+        assert!(FAULT_INJECTOR.remove(id));
+        assert!(!FAULT_INJECTOR.remove(id));
+
+        // case3: latency
+        let id = FAULT_INJECTOR.register(spec(root, FaultMode::LATENCY_MS(200), 1.0))?;
+        let start = Instant::now();
+        FAULT_INJECTOR
+            .inject(StorageType::LOCALFILE, Some(root), FaultOperation::READ)
+            .await?;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // the unmatched operation and disk are not affected
+        let start = Instant::now();
+        FAULT_INJECTOR
+            .inject(StorageType::LOCALFILE, Some(root), FaultOperation::APPEND)
+            .await?;
+        FAULT_INJECTOR
+            .inject(StorageType::LOCALFILE, Some("/other"), FaultOperation::READ)
+            .await?;
+        assert!(start.elapsed() < Duration::from_millis(200));
+        assert!(FAULT_INJECTOR.remove(id));
+
+        Ok(())
+    }
+}
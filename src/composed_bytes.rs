@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Result};
 use bytes::{Bytes, BytesMut};
 
 /// To compose multi Bytes into one for zero copy.
@@ -15,10 +16,13 @@ impl ComposedBytes {
         }
     }
 
-    pub fn from(all: Vec<Bytes>, total_size: usize) -> ComposedBytes {
+    /// The length is always the sum of the component lengths rather than the caller
+    /// declared size, which may differ from the real bytes.
+    pub fn from(all: Vec<Bytes>) -> ComposedBytes {
+        let total_len = all.iter().map(|x| x.len()).sum();
         Self {
             composed: all,
-            total_len: total_size,
+            total_len,
         }
     }
 
@@ -36,6 +40,48 @@ impl ComposedBytes {
         bytes_mut.freeze()
     }
 
+    /// Slices the range without copy when it falls inside a single component,
+    /// otherwise only the spanned components will be copied.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Bytes> {
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= self.total_len)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Out of bounds. offset: {}, len: {}, total len: {}",
+                    offset,
+                    len,
+                    self.total_len
+                )
+            })?;
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+
+        let mut bytes_mut: Option<BytesMut> = None;
+        let mut component_start = 0;
+        for component in self.composed.iter() {
+            let component_end = component_start + component.len();
+            if component_end <= offset {
+                component_start = component_end;
+                continue;
+            }
+            let start = offset.max(component_start) - component_start;
+            let stop = end.min(component_end) - component_start;
+            if bytes_mut.is_none() && end <= component_end {
+                return Ok(component.slice(start..stop));
+            }
+            bytes_mut
+                .get_or_insert_with(|| BytesMut::with_capacity(len))
+                .extend_from_slice(&component[start..stop]);
+            if end <= component_end {
+                break;
+            }
+            component_start = component_end;
+        }
+        Ok(bytes_mut.map(|x| x.freeze()).unwrap_or_default())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Bytes> + '_ {
         self.composed.iter()
     }
@@ -67,5 +113,43 @@ mod test {
 
         let data = composed.freeze();
         assert_eq!(b"helloworld", data.as_ref());
+        assert_eq!(composed.len(), data.len());
+
+        let composed = ComposedBytes::from(vec![
+            Bytes::copy_from_slice(b"hello"),
+            Bytes::new(),
+            Bytes::copy_from_slice(b"world!"),
+        ]);
+        assert_eq!(11, composed.len());
+        assert_eq!(composed.len(), composed.freeze().len());
+    }
+
+    #[test]
+    fn test_slice() {
+        let hello = Bytes::copy_from_slice(b"hello");
+        let composed = ComposedBytes::from(vec![
+            hello.clone(),
+            Bytes::copy_from_slice(b"world"),
+            Bytes::copy_from_slice(b"!"),
+        ]);
+
+        // case1: inside a single component without copy
+        let data = composed.slice(1, 3).unwrap();
+        assert_eq!(b"ell", data.as_ref());
+        assert_eq!(unsafe { hello.as_ptr().add(1) }, data.as_ptr());
+        assert_eq!(b"world", composed.slice(5, 5).unwrap().as_ref());
+
+        // case2: spanning the multiple components
+        assert_eq!(b"loworld!", composed.slice(3, 8).unwrap().as_ref());
+        assert_eq!(
+            composed.freeze(),
+            composed.slice(0, composed.len()).unwrap()
+        );
+
+        // case3: out of bounds
+        assert!(composed.slice(9, 3).is_err());
+        assert!(composed.slice(12, 0).is_err());
+        assert!(composed.slice(usize::MAX, 2).is_err());
+        assert_eq!(0, composed.slice(11, 0).unwrap().len());
     }
 }
@@ -17,6 +17,7 @@
 
 use crate::block_id_manager::BlockIdManagerType;
 use crate::store::ResponseDataIndex::Local;
+use crate::util;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -33,6 +34,35 @@ pub struct MemoryStoreConfig {
 
     #[serde(default = "as_default_dashmap_shard_amount")]
     pub dashmap_shard_amount: usize,
+
+    // to avoid the giant response assembly, the client should page with smaller read size
+    #[serde(default = "as_default_max_segments_per_response")]
+    pub max_segments_per_response: usize,
+
+    // the partition buffer starts empty and is pre-sized with the batch number of its
+    // previous spill, which is capped by this bound.
+    #[serde(default = "as_default_buffer_max_initial_batches")]
+    pub buffer_max_initial_batches: usize,
+    // the excess capacity is released once the used is below this fraction of capacity.
+    #[serde(default = "as_default_buffer_shrink_ratio")]
+    pub buffer_shrink_ratio: f64,
+
+    // recompute the crc of the blocks read from memory to catch the corrupted buffer,
+    // which costs extra cpu. default is false!
+    #[serde(default = "bool::default")]
+    pub read_crc_verify_enable: bool,
+}
+
+fn as_default_buffer_max_initial_batches() -> usize {
+    64
+}
+
+fn as_default_buffer_shrink_ratio() -> f64 {
+    0.25
+}
+
+fn as_default_max_segments_per_response() -> usize {
+    100000
 }
 
 fn as_default_buffer_ticket_timeout_check_interval_sec() -> i64 {
@@ -54,6 +84,10 @@ impl MemoryStoreConfig {
             buffer_ticket_timeout_sec: as_default_buffer_ticket_timeout_sec(),
             buffer_ticket_check_interval_sec: as_default_buffer_ticket_timeout_check_interval_sec(),
             dashmap_shard_amount: as_default_dashmap_shard_amount(),
+            max_segments_per_response: as_default_max_segments_per_response(),
+            buffer_max_initial_batches: as_default_buffer_max_initial_batches(),
+            buffer_shrink_ratio: as_default_buffer_shrink_ratio(),
+            read_crc_verify_enable: false,
         }
     }
 
@@ -63,6 +97,10 @@ impl MemoryStoreConfig {
             buffer_ticket_timeout_sec,
             buffer_ticket_check_interval_sec: as_default_buffer_ticket_timeout_check_interval_sec(),
             dashmap_shard_amount: as_default_dashmap_shard_amount(),
+            max_segments_per_response: as_default_max_segments_per_response(),
+            buffer_max_initial_batches: as_default_buffer_max_initial_batches(),
+            buffer_shrink_ratio: as_default_buffer_shrink_ratio(),
+            read_crc_verify_enable: false,
         }
     }
 }
@@ -136,7 +174,188 @@ pub struct LocalfileStoreConfig {
     #[serde(default = "bool::default")]
     pub index_consistency_detection_enable: bool,
 
+    // recompute the crc of the blocks read from the data file, which costs extra cpu.
+    // default is false!
+    #[serde(default = "bool::default")]
+    pub read_crc_verify_enable: bool,
+    // the max block crc entries of all the partitions cached for the read crc verification
+    #[serde(default = "as_default_read_crc_cache_entries")]
+    pub read_crc_cache_entries: usize,
+
+    // the composed bytes will be written by the vectored io rather than the buf writer,
+    // which avoids the extra copy.
+    #[serde(default = "as_default_composed_bytes_vectored_write_enable")]
+    pub composed_bytes_vectored_write_enable: bool,
+
+    // the identical blocks spilled into the different partitions of the same shuffle
+    // will be only stored once on disk. default is false!
+    #[serde(default = "bool::default")]
+    pub spill_dedup_enable: bool,
+
+    // compress every spilled block independently, so that the reads only decompress the
+    // blocks covering the requested range. it's ignored when the spill dedup is enabled.
+    // default is disabled.
+    pub spill_compression: Option<CompressionType>,
+
     pub io_limiter: Option<IoLimiterConfig>,
+
+    // prefetch the following data of the sequential reads into the cache. default is disabled.
+    pub read_ahead: Option<ReadAheadConfig>,
+
+    // the spilled data/index files older than this age will be deleted by the background
+    // janitor, unless the owner app still keeps heartbeat. default is disabled.
+    pub spilled_file_max_age_sec: Option<u64>,
+
+    // fsync the parent dir once the data/index file is created, otherwise the new file may
+    // be lost on the crash for some filesystems even if its content has been synced.
+    // default is false!
+    #[serde(default = "bool::default")]
+    pub parent_dir_sync_enable: bool,
+
+    // evacuate the partitions from the corrupted disks to the other healthy disks, and
+    // also for the unhealthy disks if they are unhealthy beyond the duration.
+    // default is false!
+    #[serde(default = "bool::default")]
+    pub disk_evacuation_enable: bool,
+    pub disk_evacuation_unhealthy_duration_sec: Option<u64>,
+
+    // the default placement of the partitions to disks, which could be overridden by app.
+    #[serde(default = "as_default_disk_placement_mode")]
+    pub disk_placement_mode: DiskPlacementMode,
+    // the number of contiguous partitions of one shuffle placed on the same disk
+    // under the partition-range placement mode.
+    #[serde(default = "as_default_disk_placement_partition_range_size")]
+    pub disk_placement_partition_range_size: usize,
+
+    // the capacity of every disk reserved exclusively for the index files, that the data
+    // appends are refused firstly when the disk is nearly full. default is disabled.
+    pub disk_index_reserved_capacity: Option<String>,
+
+    // the number of shuffle dirs deleted concurrently on purging the app.
+    // default is 1, that the shuffles are deleted one by one.
+    #[serde(default = "as_default_purge_parallelism")]
+    pub purge_parallelism: usize,
+
+    // use the io_uring backend for the disk reading and writing, which requires the io-uring
+    // feature. It falls back to the sync io if the kernel doesn't support. default is disabled.
+    pub io_uring: Option<IoUringConfig>,
+
+    // rewrite the partitions built from many small appends to drop the duplicate and dangling
+    // index entries with their bytes. default is disabled.
+    pub compaction: Option<CompactionConfig>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum DiskPlacementMode {
+    // every partition is hashed to the disk independently
+    #[serde(rename = "hash")]
+    HASH,
+    // the contiguous partition ranges of one shuffle are placed on the same disk, and the
+    // ranges are spread across the disks in turn, to improve the shuffle purge locality.
+    #[serde(rename = "partition-range")]
+    PARTITION_RANGE,
+    // the new partition is placed on the healthy disk with the least in-flight io operations,
+    // and then the least used ratio.
+    #[serde(rename = "least-io-pressure")]
+    LEAST_IO_PRESSURE,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum CompressionType {
+    #[serde(rename = "lz4")]
+    LZ4,
+}
+
+fn as_default_disk_placement_mode() -> DiskPlacementMode {
+    DiskPlacementMode::HASH
+}
+
+fn as_default_disk_placement_partition_range_size() -> usize {
+    16
+}
+
+fn as_default_purge_parallelism() -> usize {
+    1
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReadAheadConfig {
+    // the total bytes of the cached pages
+    pub capacity: String,
+    // the prefetched bytes after every read, which is also the page size of the cache
+    pub window_size: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct IoUringConfig {
+    // the submission queue size of the ring of every disk
+    #[serde(default = "as_default_io_uring_entries")]
+    pub entries: u32,
+    // the pre-registered aligned buffers of every disk used by the direct io
+    #[serde(default = "as_default_io_uring_registered_buffer_number")]
+    pub registered_buffer_number: usize,
+    #[serde(default = "as_default_io_uring_registered_buffer_size")]
+    pub registered_buffer_size: String,
+}
+
+impl Default for IoUringConfig {
+    fn default() -> Self {
+        IoUringConfig {
+            entries: as_default_io_uring_entries(),
+            registered_buffer_number: as_default_io_uring_registered_buffer_number(),
+            registered_buffer_size: as_default_io_uring_registered_buffer_size(),
+        }
+    }
+}
+
+fn as_default_io_uring_entries() -> u32 {
+    256
+}
+
+fn as_default_io_uring_registered_buffer_number() -> usize {
+    16
+}
+
+fn as_default_io_uring_registered_buffer_size() -> String {
+    "4M".to_owned()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CompactionConfig {
+    // the partition is compacted once its new index entries since the last compaction
+    // exceed this threshold
+    #[serde(default = "as_default_compaction_index_entry_threshold")]
+    pub index_entry_threshold: u64,
+    // the partition is compacted after it has been neither written nor read for this duration,
+    // so that the readers holding the fetched index won't see the changed offsets
+    #[serde(default = "as_default_compaction_idle_sec")]
+    pub idle_sec: u64,
+    #[serde(default = "as_default_compaction_check_interval_sec")]
+    pub check_interval_sec: u64,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        CompactionConfig {
+            index_entry_threshold: as_default_compaction_index_entry_threshold(),
+            idle_sec: as_default_compaction_idle_sec(),
+            check_interval_sec: as_default_compaction_check_interval_sec(),
+        }
+    }
+}
+
+fn as_default_compaction_index_entry_threshold() -> u64 {
+    10000
+}
+
+fn as_default_compaction_idle_sec() -> u64 {
+    10 * 60
+}
+
+fn as_default_compaction_check_interval_sec() -> u64 {
+    60
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -156,6 +375,10 @@ fn as_default_io_duration_threshold_sec() -> usize {
     5 * 60
 }
 
+fn as_default_read_crc_cache_entries() -> usize {
+    1024 * 1024
+}
+
 fn as_default_direct_io_enable() -> bool {
     false
 }
@@ -167,6 +390,10 @@ fn as_default_direct_io_append_enable() -> bool {
     true
 }
 
+fn as_default_composed_bytes_vectored_write_enable() -> bool {
+    true
+}
+
 fn as_default_disk_healthy_check_interval_sec() -> u64 {
     60
 }
@@ -199,7 +426,23 @@ impl LocalfileStoreConfig {
             direct_io_append_enable: as_default_direct_io_append_enable(),
             io_duration_threshold_sec: as_default_io_duration_threshold_sec(),
             index_consistency_detection_enable: false,
+            read_crc_verify_enable: false,
+            read_crc_cache_entries: as_default_read_crc_cache_entries(),
+            composed_bytes_vectored_write_enable: as_default_composed_bytes_vectored_write_enable(),
+            spill_dedup_enable: false,
+            spill_compression: None,
             io_limiter: None,
+            read_ahead: None,
+            spilled_file_max_age_sec: None,
+            parent_dir_sync_enable: false,
+            disk_evacuation_enable: false,
+            disk_evacuation_unhealthy_duration_sec: None,
+            disk_placement_mode: as_default_disk_placement_mode(),
+            disk_placement_partition_range_size: as_default_disk_placement_partition_range_size(),
+            disk_index_reserved_capacity: None,
+            purge_parallelism: as_default_purge_parallelism(),
+            io_uring: None,
+            compaction: None,
         }
     }
 }
@@ -215,6 +458,9 @@ pub struct RuntimeConfig {
     pub http_thread_num: usize,
     pub default_thread_num: usize,
     pub dispatch_thread_num: usize,
+
+    // refuse to start when the runtime sizing analysis has any warning
+    pub strict_sizing_check_enable: bool,
 }
 
 impl Default for RuntimeConfig {
@@ -226,6 +472,7 @@ impl Default for RuntimeConfig {
             http_thread_num: 2,
             default_thread_num: 10,
             dispatch_thread_num: 100,
+            strict_sizing_check_enable: false,
         }
     }
 }
@@ -268,12 +515,94 @@ pub struct HybridStoreConfig {
     pub async_watermark_spill_trigger_enable: bool,
     #[serde(default = "as_default_async_watermark_spill_trigger_interval_ms")]
     pub async_watermark_spill_trigger_interval_ms: u64,
+
+    // the max waiting time to drain the in-memory data into persistent stores on shutdown
+    #[serde(default = "as_default_shutdown_drain_timeout_sec")]
+    pub shutdown_drain_timeout_sec: u64,
+
+    // the order to pick up the partitions to spill when the watermark is reached
+    #[serde(default = "as_default_memory_spill_candidate_select_policy")]
+    pub memory_spill_candidate_select_policy: SpillCandidateSelectPolicy,
+
+    // the failed spill event is retried with the exponential backoff from the base,
+    // and is written into the dead letter dir after the max attempts.
+    #[serde(default = "as_default_spill_retry_max_attempts")]
+    pub spill_retry_max_attempts: u32,
+    #[serde(default = "as_default_spill_retry_backoff_base_millis")]
+    pub spill_retry_backoff_base_millis: u64,
+    // the max number of the failed spill events waiting for the retry, the overflowed
+    // ones are written into the dead letter dir directly.
+    #[serde(default = "as_default_spill_retry_queue_capacity")]
+    pub spill_retry_queue_capacity: u64,
+    // the failed spill event older than this will be dropped to release the memory
+    #[serde(default = "as_default_spill_retry_max_age_sec")]
+    pub spill_retry_max_age_sec: u64,
+    // the local dir to keep the data of the spill events exceeding the max retry attempts,
+    // the data will be dropped if not set.
+    pub spill_dead_letter_dir: Option<String>,
+
+    // the empty read of the partition spilled within the window is retried with the max
+    // attempts, to avoid the false miss in the memory to disk handoff. 0 means disabled.
+    #[serde(default = "as_default_spilled_read_retry_window_ms")]
+    pub spilled_read_retry_window_ms: u64,
+    #[serde(default = "as_default_spilled_read_retry_max_attempts")]
+    pub spilled_read_retry_max_attempts: u32,
+
+    // the buffer requirement is rejected to back off the clients when the used ratio of
+    // the memory store exceeds this, that the spill can't keep up. default is disabled.
+    pub require_buffer_reject_ratio: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum SpillCandidateSelectPolicy {
+    // the partitions in the iteration order of memory buffers, without any sorting
+    #[serde(rename = "fifo")]
+    FIFO,
+    // the partition with the largest staging size first, which frees the most memory per spill
+    #[serde(rename = "largest-first")]
+    LARGEST_FIRST,
+    // the partition with the earliest staging data first
+    #[serde(rename = "oldest-first")]
+    OLDEST_FIRST,
+}
+
+fn as_default_memory_spill_candidate_select_policy() -> SpillCandidateSelectPolicy {
+    SpillCandidateSelectPolicy::LARGEST_FIRST
+}
+
+fn as_default_spill_retry_max_attempts() -> u32 {
+    3
+}
+
+fn as_default_spill_retry_backoff_base_millis() -> u64 {
+    100
+}
+
+fn as_default_spill_retry_queue_capacity() -> u64 {
+    1024
+}
+
+fn as_default_spill_retry_max_age_sec() -> u64 {
+    600
+}
+
+fn as_default_spilled_read_retry_window_ms() -> u64 {
+    1000
+}
+
+fn as_default_spilled_read_retry_max_attempts() -> u32 {
+    5
 }
 
 fn as_default_async_watermark_spill_trigger_interval_ms() -> u64 {
     500
 }
 
+fn as_default_shutdown_drain_timeout_sec() -> u64 {
+    60
+}
+
 fn as_default_async_watermark_spill_trigger_enable() -> bool {
     false
 }
@@ -317,6 +646,16 @@ impl HybridStoreConfig {
             async_watermark_spill_trigger_enable: as_default_async_watermark_spill_trigger_enable(),
             async_watermark_spill_trigger_interval_ms:
                 as_default_async_watermark_spill_trigger_interval_ms(),
+            shutdown_drain_timeout_sec: as_default_shutdown_drain_timeout_sec(),
+            memory_spill_candidate_select_policy: as_default_memory_spill_candidate_select_policy(),
+            spill_retry_max_attempts: as_default_spill_retry_max_attempts(),
+            spill_retry_backoff_base_millis: as_default_spill_retry_backoff_base_millis(),
+            spill_retry_queue_capacity: as_default_spill_retry_queue_capacity(),
+            spill_retry_max_age_sec: as_default_spill_retry_max_age_sec(),
+            spill_dead_letter_dir: None,
+            spilled_read_retry_window_ms: as_default_spilled_read_retry_window_ms(),
+            spilled_read_retry_max_attempts: as_default_spilled_read_retry_max_attempts(),
+            require_buffer_reject_ratio: None,
         }
     }
 }
@@ -336,6 +675,16 @@ impl Default for HybridStoreConfig {
             async_watermark_spill_trigger_enable: as_default_async_watermark_spill_trigger_enable(),
             async_watermark_spill_trigger_interval_ms:
                 as_default_async_watermark_spill_trigger_interval_ms(),
+            shutdown_drain_timeout_sec: as_default_shutdown_drain_timeout_sec(),
+            memory_spill_candidate_select_policy: as_default_memory_spill_candidate_select_policy(),
+            spill_retry_max_attempts: as_default_spill_retry_max_attempts(),
+            spill_retry_backoff_base_millis: as_default_spill_retry_backoff_base_millis(),
+            spill_retry_queue_capacity: as_default_spill_retry_queue_capacity(),
+            spill_retry_max_age_sec: as_default_spill_retry_max_age_sec(),
+            spill_dead_letter_dir: None,
+            spilled_read_retry_window_ms: as_default_spilled_read_retry_window_ms(),
+            spilled_read_retry_max_attempts: as_default_spilled_read_retry_max_attempts(),
+            require_buffer_reject_ratio: None,
         }
     }
 }
@@ -364,6 +713,23 @@ pub struct Config {
     #[serde(default = "as_default_grpc_port")]
     pub grpc_port: i32,
     pub urpc_port: Option<i32>,
+    // the host which the urpc server binds to, default is 0.0.0.0
+    pub urpc_bind_host: Option<String>,
+    // the urpc connection is closed if no frame arrives within this time, default is 600s
+    pub urpc_connection_idle_timeout_sec: Option<u64>,
+    // the max concurrent connections of one urpc listener, default is 40000
+    pub urpc_max_connections: Option<usize>,
+    // the in-flight urpc requests are drained within this period on shutdown, default is 10s
+    pub urpc_shutdown_grace_period_sec: Option<u64>,
+    // merging the rapid tiny local reads of one partition from the same connection
+    pub urpc_read_coalesce: Option<ReadCoalesceConfig>,
+    // the urpc listener exited with errors is restarted at most this times, default is 10
+    pub urpc_listener_max_restarts: Option<usize>,
+    // the backoff before restarting the failed urpc listener, default is 5s
+    pub urpc_listener_restart_backoff_sec: Option<u64>,
+    // the restarts are counted from zero again once the urpc listener has been running
+    // healthy for this period, default is 600s
+    pub urpc_listener_healthy_reset_sec: Option<u64>,
 
     pub coordinator_quorum: Vec<String>,
     pub tags: Option<Vec<String>>,
@@ -379,14 +745,68 @@ pub struct Config {
 
     pub tracing: Option<TracingConfig>,
 
+    // forwarding the accepted data to the peer server asynchronously for the recovery
+    pub replication: Option<ReplicationConfig>,
+
+    // persisting the apps metadata periodically, which is restored on startup to serve
+    // the reading of the spilled data after the restart
+    pub metadata_snapshot: Option<MetadataSnapshotConfig>,
+
     #[serde(default = "as_default_health_service_config")]
     pub health_service_config: HealthServiceConfig,
 
     #[serde(default = "as_default_heartbeat_interval_seconds")]
     pub heartbeat_interval_seconds: u32,
+
+    #[serde(default = "as_default_heartbeat_pressure_config")]
+    pub heartbeat_pressure_config: HeartbeatPressureConfig,
+
+    // the faults could be injected via the admin http api only when enabled.
+    // default is false!
+    #[serde(default = "bool::default")]
+    pub fault_injection_enable: bool,
+
+    // the file to persist the hourly activity log, which is in memory only if not set.
+    pub activity_log_path: Option<String>,
+
+    // the file to persist the operational event timeline, which is in memory only if not set.
+    pub timeline_path: Option<String>,
+    // the app purge removing more bytes than this threshold is recorded into the timeline.
+    // default is 1G
+    pub timeline_purge_size_threshold: Option<String>,
+}
+
+// =========================================================
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HeartbeatPressureConfig {
+    #[serde(default = "as_default_heartbeat_pressure_enable")]
+    pub enable: bool,
+    // the number of in-flight spill events that is regarded as the full spill backlog
+    #[serde(default = "as_default_spill_backlog_max_event_num")]
+    pub spill_backlog_max_event_num: u64,
+}
+
+fn as_default_heartbeat_pressure_enable() -> bool {
+    true
+}
+
+fn as_default_spill_backlog_max_event_num() -> u64 {
+    1000
+}
+
+impl Default for HeartbeatPressureConfig {
+    fn default() -> Self {
+        Self {
+            enable: as_default_heartbeat_pressure_enable(),
+            spill_backlog_max_event_num: as_default_spill_backlog_max_event_num(),
+        }
+    }
 }
 
 // ====
+fn as_default_heartbeat_pressure_config() -> HeartbeatPressureConfig {
+    Default::default()
+}
 fn as_default_heartbeat_interval_seconds() -> u32 {
     2
 }
@@ -441,6 +861,49 @@ pub struct AppConfig {
 
     #[serde(default = "as_default_partition_split_threshold")]
     pub partition_split_threshold: String,
+
+    // for the per-app write rate limit mechanism, like "100M" means 100MB per second.
+    pub app_write_rate_limit: Option<String>,
+
+    // the apps with the expected total bytes hint are denied when the committed bytes
+    // exceed the free capacity multiplied by this ratio.
+    #[serde(default = "as_default_app_expected_size_overcommit_ratio")]
+    pub app_expected_size_overcommit_ratio: f64,
+
+    // for the per-app storage quota mechanism, like "100G". Exceeding the soft limit only
+    // logs, while exceeding the hard limit rejects the writing.
+    pub app_quota_soft_limit: Option<String>,
+    pub app_quota_hard_limit: Option<String>,
+
+    // reject the writing whose size exceeds the reserved size of the required buffer ticket
+    #[serde(default = "bool::default")]
+    pub write_reservation_check_enable: bool,
+
+    // serialize the app registering with the in-flight app purging. The registering of a
+    // purging app is rejected until the purge finishes, and then a clean app is created.
+    #[serde(default = "as_default_register_purge_serialization_enable")]
+    pub register_purge_serialization_enable: bool,
+
+    // the max number of the registered shuffles of one app, which is unlimited if not set.
+    pub max_shuffles_per_app: Option<usize>,
+
+    // the soft cap of the tracked partitions of one app, which is unlimited if not set.
+    // The new partitions beyond it are only counted by the metric unless the reject is enabled.
+    pub max_partitions_per_app: Option<usize>,
+    #[serde(default = "bool::default")]
+    pub reject_partitions_beyond_max: bool,
+
+    // the default deadline of one reading request, which is unlimited if not set.
+    // The earlier one is taken if the client also carries the deadline, like the grpc-timeout.
+    pub read_deadline_ms: Option<u64>,
+}
+
+fn as_default_register_purge_serialization_enable() -> bool {
+    true
+}
+
+fn as_default_app_expected_size_overcommit_ratio() -> f64 {
+    1.5
 }
 
 fn as_default_partition_limit_memory_backpressure_ratio() -> f64 {
@@ -479,6 +942,16 @@ fn as_default_app_config() -> AppConfig {
         historical_apps_record_enable: false,
         partition_split_enable: false,
         partition_split_threshold: as_default_partition_split_threshold(),
+        app_write_rate_limit: None,
+        app_expected_size_overcommit_ratio: as_default_app_expected_size_overcommit_ratio(),
+        app_quota_soft_limit: None,
+        app_quota_hard_limit: None,
+        write_reservation_check_enable: false,
+        register_purge_serialization_enable: as_default_register_purge_serialization_enable(),
+        max_shuffles_per_app: None,
+        max_partitions_per_app: None,
+        reject_partitions_beyond_max: false,
+        read_deadline_ms: None,
     }
 }
 
@@ -493,6 +966,79 @@ pub struct TracingConfig {
     pub jaeger_service_name: String,
 }
 
+// =========================================================
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReadCoalesceConfig {
+    // the local read not larger than this is regarded as the tiny one
+    #[serde(default = "as_default_tiny_read_size")]
+    pub tiny_read_size: String,
+
+    // the tiny read is expanded to this size, whose rest is cached for the following reads
+    #[serde(default = "as_default_merged_read_size")]
+    pub merged_read_size: String,
+
+    // the cached data is only served within this window after being read
+    #[serde(default = "as_default_read_coalesce_window_ms")]
+    pub window_ms: u64,
+}
+
+fn as_default_tiny_read_size() -> String {
+    "64K".to_string()
+}
+
+fn as_default_merged_read_size() -> String {
+    "4M".to_string()
+}
+
+fn as_default_read_coalesce_window_ms() -> u64 {
+    100
+}
+
+// =========================================================
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReplicationConfig {
+    // the grpc address of the peer shuffle server, like "host:port"
+    pub peer_address: String,
+
+    // the max number of the writings waiting to be forwarded, the overflowed
+    // ones are dropped without blocking the local writing.
+    #[serde(default = "as_default_replication_queue_depth")]
+    pub queue_depth: usize,
+
+    #[serde(default = "as_default_replication_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+
+    // the interval of keeping the replicated apps alive on the peer
+    #[serde(default = "as_default_replication_heartbeat_interval_sec")]
+    pub heartbeat_interval_sec: u64,
+}
+
+fn as_default_replication_queue_depth() -> usize {
+    1024
+}
+
+fn as_default_replication_flush_interval_ms() -> u64 {
+    100
+}
+
+fn as_default_replication_heartbeat_interval_sec() -> u64 {
+    60
+}
+
+// =========================================================
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MetadataSnapshotConfig {
+    // the local file path of the apps metadata snapshot
+    pub path: String,
+
+    #[serde(default = "as_default_metadata_snapshot_interval_sec")]
+    pub interval_sec: u64,
+}
+
+fn as_default_metadata_snapshot_interval_sec() -> u64 {
+    60
+}
+
 // =========================================================
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct MetricsConfig {
@@ -502,6 +1048,9 @@ pub struct MetricsConfig {
     pub push_interval_sec: u32,
 
     pub labels: Option<HashMap<String, String>>,
+
+    // refresh the read/write amplification gauges in this interval. default is disabled.
+    pub amplification_refresh_interval_sec: Option<u64>,
 }
 
 fn as_default_push_interval_sec() -> u32 {
@@ -584,6 +1133,27 @@ impl Config {
         toml::from_str(&file_content).unwrap()
     }
 
+    /// Validates the size strings consumed by the app, so the malformed ones are rejected
+    /// on the config loading or updating instead of panicking in the writing path.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(memory_store) = &self.memory_store {
+            util::try_parse_raw_to_bytesize(&memory_store.capacity)?;
+        }
+        let app_config = &self.app_config;
+        util::try_parse_raw_to_bytesize(&app_config.partition_limit_threshold)?;
+        util::try_parse_raw_to_bytesize(&app_config.partition_split_threshold)?;
+        for limit in [
+            &app_config.app_write_rate_limit,
+            &app_config.app_quota_soft_limit,
+            &app_config.app_quota_hard_limit,
+        ] {
+            if let Some(limit) = limit {
+                util::try_parse_raw_to_bytesize(limit)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn create_from_env() -> Config {
         let path = match std::env::var(CONFIG_FILE_PATH_KEY) {
             Ok(val) => val,
@@ -644,6 +1214,20 @@ mod test {
     use crate::readable_size::ReadableSize;
     use std::str::FromStr;
 
+    #[test]
+    fn validate_test() {
+        let mut config = Config::create_simple_config();
+        assert!(config.validate().is_ok());
+
+        config.app_config.partition_split_threshold = "1WRONG".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("1WRONG"));
+
+        config.app_config.partition_split_threshold = "1G".to_string();
+        config.app_config.app_quota_soft_limit = Some("-1".to_string());
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn storage_type_test() {
         let stype = StorageType::MEMORY_LOCALFILE;
@@ -1,8 +1,8 @@
 use crate::config::Config;
 use crate::runtime::{Runtime, RuntimeRef};
+use crate::timeline::{TimelineCategory, TIMELINE};
 use crate::util;
 use anyhow::{anyhow, Result};
-use bytesize::ByteSize;
 use clap::builder::Str;
 use crossbeam_utils::atomic::AtomicCell;
 use dashmap::DashMap;
@@ -100,22 +100,24 @@ impl ReconfigurableConfManager {
         }
 
         info!("Register reconfiguration key for [{}]", key);
-        let val = self.conf_state.get(key).unwrap().clone();
+        let raw_val = self.conf_state.get(key).unwrap().clone();
         // fast fail on any parsing failure
-        let val: T = serde_json::from_value(val)?;
+        let val: T = serde_json::from_value(raw_val.clone())?;
         let conf_ref = ConfRef {
             manager: self.clone(),
             key: key.to_string(),
             value: RwLock::new(val),
             last_update_timestamp: AtomicU64::new(util::now_timestamp_as_sec()),
             refresh_interval: 1,
-            lock: Default::default(),
+            lock: Mutex::new(raw_val),
         };
         Ok(conf_ref)
     }
 
     fn reload(&self, path: &str) -> Result<()> {
         let config_struct = Config::from(path);
+        // reject the whole update on any malformed value rather than fallback silently
+        config_struct.validate()?;
         let new_state = Self::to_internal_state(&config_struct)?;
 
         for (k, v) in new_state {
@@ -123,6 +125,10 @@ impl ReconfigurableConfManager {
                 // Only numeric val could be refreshed.
                 if *val_ref != v {
                     warn!("Updated [{}] from {:?} to {:?}", &k, &val_ref, &v);
+                    TIMELINE.record(
+                        TimelineCategory::CONFIG_CHANGE,
+                        format!("{}: {} -> {}", &k, &*val_ref, &v),
+                    );
                     *val_ref = v;
                 }
             }
@@ -169,7 +175,7 @@ impl<'de> Deserialize<'de> for ByteString {
         D: Deserializer<'de>,
     {
         let raw = String::deserialize(deserializer)?;
-        let val = raw.parse::<ByteSize>().map_err(serde::de::Error::custom)?.0;
+        let val = util::try_parse_raw_to_bytesize(&raw).map_err(serde::de::Error::custom)?;
         Ok(ByteString {
             val: raw,
             parsed_val: val,
@@ -179,7 +185,7 @@ impl<'de> Deserialize<'de> for ByteString {
 
 impl Into<u64> for ByteString {
     fn into(self) -> u64 {
-        util::parse_raw_to_bytesize(&self.val)
+        self.parsed_val
     }
 }
 
@@ -190,7 +196,8 @@ pub struct ConfRef<T> {
     last_update_timestamp: AtomicU64,
     refresh_interval: u64,
 
-    lock: Mutex<()>,
+    // the raw value of the latest refresh, only the changed one is deserialized again
+    lock: Mutex<Value>,
 }
 
 impl<T> ConfRef<T>
@@ -198,16 +205,19 @@ where
     T: DeserializeOwned + Clone,
 {
     pub fn get(&self) -> T {
-        if self.lock.try_lock().is_some() {
+        if let Some(mut raw_val) = self.lock.try_lock() {
             let now_sec = util::now_timestamp_as_sec();
             let last = self.last_update_timestamp.load(Ordering::Relaxed);
             if now_sec - last > self.refresh_interval {
                 if let Some(val) = self.manager.conf_state.get(&self.key) {
-                    if let Ok(val) = serde_json::from_value::<T>(val.clone()) {
-                        let mut internal_val = self.value.write();
-                        *internal_val = val;
-                    } else {
-                        // fallback
+                    if *val != *raw_val {
+                        if let Ok(parsed_val) = serde_json::from_value::<T>(val.clone()) {
+                            let mut internal_val = self.value.write();
+                            *internal_val = parsed_val;
+                        } else {
+                            // fallback
+                        }
+                        *raw_val = val.clone();
                     }
                 }
                 self.last_update_timestamp.store(now_sec, Ordering::Relaxed);
@@ -398,6 +408,10 @@ mod tests {
         "#;
         write_conf_into_file(target_conf_file.to_owned(), toml_str.to_string())?;
 
+        // the malformed update is rejected as a whole
+        let err = reconf_manager.reload(&target_conf_file).unwrap_err();
+        assert!(err.to_string().contains("1024WRONG"));
+
         thread::sleep(Duration::from_millis(2000));
         // fallback due to the incorrect conf options
         assert_eq!(100, reconf_ref_2.get());
@@ -1,4 +1,5 @@
 use crate::metric::DEADLOCK_SIGNAL;
+use crate::timeline::TIMELINE;
 use log::{error, info, warn};
 use once_cell::sync::Lazy;
 use std::sync::atomic::AtomicBool;
@@ -23,6 +24,7 @@ pub fn detect_deadlock() {
                 error!("{:#?}", t.backtrace());
             }
         }
+        error!("{}", TIMELINE.diagnostic_section());
         DEADLOCK_SIGNAL.set(1);
         DEADLOCK_TAG.store(true, SeqCst);
     });
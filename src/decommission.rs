@@ -1,5 +1,6 @@
 use crate::app::AppManagerRef;
 use crate::grpc::protobuf::uniffle::ServerStatus;
+use crate::timeline::{TimelineCategory, TIMELINE};
 use crate::util;
 use libc::{send, stat};
 use log::{info, warn};
@@ -46,6 +47,16 @@ impl DecommissionManager {
 
     pub fn as_state(&self, state: DecommissionState) {
         let mut internal_state = self.state.write();
+        if *internal_state != state {
+            TIMELINE.record(
+                TimelineCategory::DECOMMISSION,
+                format!("{:?} -> {:?}", &*internal_state, &state),
+            );
+        }
+        self.app_manager_ref.set_decommissioning(matches!(
+            state,
+            DecommissionState::DECOMMISSIONING | DecommissionState::DECOMMISSIONED
+        ));
         *internal_state = state;
         self.state_time.store(util::now_timestamp_as_sec(), SeqCst);
     }
@@ -101,6 +112,7 @@ mod tests {
     use crate::app::AppManager;
     use crate::config_reconfigure::ReconfigurableConfManager;
     use crate::decommission::{DecommissionManager, DecommissionState};
+    use crate::error::WorkerError;
     use crate::grpc::protobuf::uniffle::ServerStatus;
     use crate::runtime::manager::RuntimeManager;
     use crate::storage::StorageService;
@@ -146,4 +158,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_refuse_new_apps_under_decommissioning() -> Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager).clone();
+        let decommission_manager = DecommissionManager::new(&app_manager_ref);
+
+        let known_app_id = "test_refuse_new_apps-known";
+        app_manager_ref.register(known_app_id.to_string(), 0, Default::default())?;
+
+        // case1: the new app is refused, and the known app still works
+        decommission_manager.as_state(DecommissionState::DECOMMISSIONING);
+        assert!(app_manager_ref.is_decommissioning());
+        match app_manager_ref.register(
+            "test_refuse_new_apps-new".to_string(),
+            0,
+            Default::default(),
+        ) {
+            Err(err) => match err.downcast_ref::<WorkerError>() {
+                Some(WorkerError::SERVER_DECOMMISSIONING(_)) => {}
+                _ => panic!("unexpected error: {:?}", err),
+            },
+            _ => panic!("the new app should be refused"),
+        }
+        app_manager_ref.register(known_app_id.to_string(), 1, Default::default())?;
+
+        // case2: the new app is accepted after cancelling
+        decommission_manager.as_state(DecommissionState::CANCEL_DECOMMISSION);
+        assert!(!app_manager_ref.is_decommissioning());
+        app_manager_ref.register(
+            "test_refuse_new_apps-new".to_string(),
+            0,
+            Default::default(),
+        )?;
+
+        Ok(())
+    }
 }
@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::constant::StatusCode;
 use anyhow::Error;
 use std::string::FromUtf8Error;
 
@@ -49,15 +50,42 @@ pub enum WorkerError {
     #[error("Local disk:[{0}] owned by current partition has been corrupted")]
     LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(String),
 
+    #[error("The remaining capacity of local disk:[{0}] is reserved for the index files")]
+    LOCAL_DISK_RESERVED_FOR_INDEX(String),
+
     #[error("No enough memory to be allocated.")]
     NO_ENOUGH_MEMORY_TO_BE_ALLOCATED,
 
     #[error("The memory usage is limited by huge partition mechanism")]
     MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION,
 
+    #[error("The memory is under pressure, used: {0}, capacity: {1}")]
+    MEMORY_BACKPRESSURE(i64, i64),
+
+    #[error("The app write rate exceeds the limit")]
+    APP_WRITE_RATE_LIMITED,
+
+    #[error("The app storage usage exceeds the hard quota")]
+    APP_QUOTA_EXCEEDED,
+
+    #[error("The writing is rejected under the read only mode")]
+    READ_ONLY_MODE,
+
+    #[error("Invalid app config. {0}")]
+    INVALID_APP_CONFIG(String),
+
+    #[error("The written size: {0} exceeds the reserved size: {1} of the ticket")]
+    WRITE_EXCEEDS_RESERVATION(i64, i64),
+
     #[error("Http request failed. {0}")]
     HTTP_SERVICE_ERROR(String),
 
+    #[error("Too many segments: {0} exceeding the limit: {1} in one response. Please page the reading with the smaller read buffer size")]
+    TOO_MANY_SEGMENTS_PER_RESPONSE(usize, usize),
+
+    #[error("The partition data is not in memory")]
+    NOT_IN_MEMORY,
+
     #[error("Ticket id: {0} not exist")]
     TICKET_ID_NOT_EXIST(i64),
 
@@ -73,6 +101,12 @@ pub enum WorkerError {
     #[error("Spill event has been retried exceed the max reject for app: {0}")]
     SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(String),
 
+    #[error("Spill event has been retried exceed the max age for app: {0}")]
+    SPILL_EVENT_EXPIRED(String),
+
+    #[error("Spill event could not be retried due to the full retry queue for app: {0}")]
+    SPILL_RETRY_QUEUE_FULL(String),
+
     #[error("urpc stream is incomplete")]
     STREAM_INCOMPLETE,
 
@@ -97,6 +131,59 @@ pub enum WorkerError {
     #[error("HDFS has been unhealthy.")]
     HDFS_UNHEALTHY,
 
+    #[error("The spill target dir is missing. error: {0}")]
+    SPILL_TARGET_DIR_MISSING(anyhow::Error),
+
+    #[error("The app: {0} has been purged when spilling")]
+    SPILL_APP_PURGED(String),
+
+    #[error("The spill target disk is full. error: {0}")]
+    SPILL_DISK_FULL(anyhow::Error),
+
+    #[error("Store is draining and rejects the new data on shutdown")]
+    STORE_DRAINING,
+
+    #[error("The write sequence: {0} is not visible, the latest visible sequence is {1}")]
+    WRITE_SEQUENCE_NOT_VISIBLE(u64, u64),
+
+    #[error("The app: {0} is being purged, and the registering should be retried later")]
+    APP_IS_PURGING(String),
+
+    #[error("The purge of app: {0} has been cancelled, and the rest data is kept")]
+    PURGE_CANCELLED(String),
+
+    #[error("The new app: {0} is refused under the decommissioning")]
+    SERVER_DECOMMISSIONING(String),
+
+    #[error("The registered shuffle number of app: {0} exceeds the limit: {1}")]
+    TOO_MANY_SHUFFLES(String, usize),
+
+    #[error("The tracked partition number of app: {0} exceeds the limit: {1}")]
+    TOO_MANY_PARTITIONS(String, usize),
+
+    #[error("The expected total bytes: {0} of app exceeds the admission capacity: {1}")]
+    APP_EXPECTED_SIZE_EXCEEDS_CAPACITY(u64, u64),
+
+    #[error("Injected fault. {0}")]
+    INJECTED_FAULT(String),
+
+    #[error("The fault injection is disabled")]
+    FAULT_INJECTION_DISABLED,
+
+    #[error("Invalid fault spec. {0}")]
+    INVALID_FAULT_SPEC(String),
+
+    #[error("Data crc mismatch of block_id: {block_id} at offset: {offset}. expected crc: {expected}, actual crc: {actual}")]
+    DATA_CRC_MISMATCH {
+        block_id: i64,
+        offset: i64,
+        expected: i64,
+        actual: i64,
+    },
+
+    #[error("The reading exceeds the deadline of the request")]
+    DEADLINE_EXCEEDED,
+
     #[error("future execution timeout. error: {0}")]
     FUTURE_EXEC_TIMEOUT(anyhow::Error),
 
@@ -107,6 +194,16 @@ pub enum WorkerError {
     Other(#[from] anyhow::Error),
 }
 
+impl WorkerError {
+    /// The status code responded to the client for the failed reading.
+    pub fn reading_status_code(&self) -> StatusCode {
+        match self {
+            WorkerError::DEADLINE_EXCEEDED => StatusCode::TIMEOUT,
+            _ => StatusCode::INTERNAL_ERROR,
+        }
+    }
+}
+
 impl From<AcquireError> for WorkerError {
     fn from(error: AcquireError) -> Self {
         WorkerError::Other(Error::new(error))
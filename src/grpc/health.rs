@@ -0,0 +1,197 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::grpc::protobuf::health::health_check_response::ServingStatus;
+use crate::grpc::protobuf::health::health_server::Health;
+use crate::grpc::protobuf::health::{HealthCheckRequest, HealthCheckResponse};
+use crate::health_service::HealthService;
+use log::warn;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// The full name of the shuffle service, which is checked by the grpc health probes
+/// besides the empty name representing the whole server.
+pub const SHUFFLE_SERVICE_NAME: &str = "rss.common.ShuffleServer";
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The standard `grpc.health.v1.Health` service, whose serving status is derived from the
+/// same health checking reported to the coordinator by heartbeat.
+#[derive(Clone)]
+pub struct GrpcHealthService {
+    health_service: HealthService,
+}
+
+impl GrpcHealthService {
+    pub fn new(health_service: &HealthService) -> Self {
+        Self {
+            health_service: health_service.clone(),
+        }
+    }
+
+    async fn serving_status(health_service: &HealthService, service: &str) -> ServingStatus {
+        if !service.is_empty() && service != SHUFFLE_SERVICE_NAME {
+            return ServingStatus::ServiceUnknown;
+        }
+        match health_service.is_healthy().await {
+            Ok(true) => ServingStatus::Serving,
+            Ok(false) => ServingStatus::NotServing,
+            Err(err) => {
+                warn!(
+                    "Errors on checking the health for grpc probe. err: {:?}",
+                    err
+                );
+                ServingStatus::NotServing
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Health for GrpcHealthService {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        let status = Self::serving_status(&self.health_service, &service).await;
+        if status == ServingStatus::ServiceUnknown {
+            return Err(Status::not_found(format!("Unknown service: {}", service)));
+        }
+        Ok(Response::new(HealthCheckResponse {
+            status: status.into(),
+        }))
+    }
+
+    type WatchStream = ReceiverStream<Result<HealthCheckResponse, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let health_service = self.health_service.clone();
+        let (tx, rx) = mpsc::channel(1);
+
+        // the status is sent at first and then only sent on changes, until the client goes away.
+        tokio::spawn(async move {
+            let mut last_status = None;
+            loop {
+                let status = Self::serving_status(&health_service, &service).await;
+                if last_status != Some(status) {
+                    let response = HealthCheckResponse {
+                        status: status.into(),
+                    };
+                    if tx.send(Ok(response)).await.is_err() {
+                        return;
+                    }
+                    last_status = Some(status);
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(WATCH_INTERVAL) => {}
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::test::mock_config;
+    use crate::app::AppManager;
+    use crate::config_reconfigure::ReconfigurableConfManager;
+    use crate::deadlock::DEADLOCK_TAG;
+    use crate::grpc::health::{GrpcHealthService, SHUFFLE_SERVICE_NAME};
+    use crate::grpc::protobuf::health::health_check_response::ServingStatus;
+    use crate::grpc::protobuf::health::health_client::HealthClient;
+    use crate::grpc::protobuf::health::health_server::HealthServer;
+    use crate::grpc::protobuf::health::HealthCheckRequest;
+    use crate::health_service::HealthService;
+    use crate::runtime::manager::RuntimeManager;
+    use crate::storage::StorageService;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[tokio::test]
+    async fn test_grpc_health_check() -> anyhow::Result<()> {
+        DEADLOCK_TAG.store(false, SeqCst);
+
+        let mut config = mock_config();
+        config.health_service_config.alive_app_number_max_limit = Some(1);
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref = AppManager::get_ref(
+            Default::default(),
+            config.clone(),
+            &storage,
+            &reconf_manager,
+        )
+        .clone();
+        let health_service =
+            HealthService::new(&app_manager_ref, &storage, &config.health_service_config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(HealthServer::new(GrpcHealthService::new(&health_service)))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = HealthClient::connect(format!("http://{}", addr)).await?;
+        let check = |service: &str| HealthCheckRequest {
+            service: service.to_string(),
+        };
+
+        // case1: serving
+        let resp = client.check(check("")).await?.into_inner();
+        assert_eq!(ServingStatus::Serving as i32, resp.status);
+        let resp = client
+            .check(check(SHUFFLE_SERVICE_NAME))
+            .await?
+            .into_inner();
+        assert_eq!(ServingStatus::Serving as i32, resp.status);
+
+        // case2: unknown service
+        let status = client.check(check("unknown")).await.unwrap_err();
+        assert_eq!(tonic::Code::NotFound, status.code());
+
+        // case3: not serving when exceeding the alive app number limit
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        app_manager_ref.register("app_2".to_string(), 1, Default::default())?;
+        let resp = client.check(check("")).await?.into_inner();
+        assert_eq!(ServingStatus::NotServing as i32, resp.status);
+
+        // case4: serving again after the app is purged
+        app_manager_ref.unregister_app("app_2".to_string()).await?;
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| app_manager_ref.get_alive_app_number() == 1);
+        let resp = client.check(check("")).await?.into_inner();
+        assert_eq!(ServingStatus::Serving as i32, resp.status);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+pub mod health;
 pub mod layer;
 pub mod protobuf;
 pub mod service;
@@ -17,3 +17,5 @@
 
 #[rustfmt::skip]
 pub mod uniffle;
+#[rustfmt::skip]
+pub mod health;
@@ -17,7 +17,7 @@
 
 use crate::app::{
     AppConfigOptions, AppManagerRef, DataDistribution, GetBlocksContext, GetMultiBlockIdsContext,
-    PartitionedUId, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
+    IndexEntryRange, PartitionedUId, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
     RemoteStorageConfig, ReportBlocksContext, ReportMultiBlockIdsContext, RequireBufferContext,
     WritingViewContext,
 };
@@ -28,11 +28,14 @@ use crate::grpc::protobuf::uniffle::shuffle_server_internal_server::ShuffleServe
 use crate::grpc::protobuf::uniffle::shuffle_server_server::ShuffleServer;
 use crate::grpc::protobuf::uniffle::{
     AppHeartBeatRequest, AppHeartBeatResponse, CancelDecommissionRequest,
-    CancelDecommissionResponse, DecommissionRequest, DecommissionResponse, FinishShuffleRequest,
-    FinishShuffleResponse, GetLocalShuffleDataRequest, GetLocalShuffleDataResponse,
-    GetLocalShuffleIndexRequest, GetLocalShuffleIndexResponse, GetMemoryShuffleDataRequest,
-    GetMemoryShuffleDataResponse, GetShuffleResultForMultiPartRequest,
-    GetShuffleResultForMultiPartResponse, GetShuffleResultRequest, GetShuffleResultResponse,
+    CancelDecommissionResponse, DataDistribution as ProtoDataDistribution, DecommissionRequest,
+    DecommissionResponse, FinishShuffleRequest, FinishShuffleResponse, GetLocalShuffleDataRequest,
+    GetLocalShuffleDataResponse, GetLocalShuffleIndexRequest, GetLocalShuffleIndexResponse,
+    GetMemoryShuffleDataRequest, GetMemoryShuffleDataResponse, GetPartitionFlushStateRequest,
+    GetPartitionFlushStateResponse, GetPartitionLocationRequest, GetPartitionLocationResponse,
+    GetShuffleResultBatchRequest, GetShuffleResultBatchResponse,
+    GetShuffleResultForMultiPartRequest, GetShuffleResultForMultiPartResponse,
+    GetShuffleResultRequest, GetShuffleResultResponse, PartitionToBlockCountDelta,
     ReportShuffleResultRequest, ReportShuffleResultResponse, RequireBufferRequest,
     RequireBufferResponse, SendShuffleDataRequest, SendShuffleDataResponse, ShuffleCommitRequest,
     ShuffleCommitResponse, ShuffleRegisterRequest, ShuffleRegisterResponse,
@@ -48,7 +51,7 @@ use crate::metric::{
     GRPC_SEND_DATA_PROCESS_TIME, GRPC_SEND_DATA_TRANSPORT_TIME,
 };
 use crate::reject::RejectionPolicyGateway;
-use crate::store::{PartitionedData, ResponseDataIndex};
+use crate::store::{group_blocks_by_partition, ResponseDataIndex};
 use crate::util;
 use await_tree::InstrumentAwait;
 use bytes::Bytes;
@@ -57,7 +60,9 @@ use fastrace::future::FutureExt;
 use fastrace::trace;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
+use std::time::Duration;
 use tokio::time::Instant;
+use tonic::metadata::MetadataMap;
 use tonic::{Request, Response, Status};
 
 /// Use the maximum value for HTTP/2 connection window size to avoid deadlock among multiplexed
@@ -67,6 +72,26 @@ pub const MAX_CONNECTION_WINDOW_SIZE: u32 = (1 << 31) - 1;
 /// as we don't rely on this for back-pressure.
 pub const STREAM_WINDOW_SIZE: u32 = 32 * 1024 * 1024; // 32 MB
 
+/// Parses the client timeout from the `grpc-timeout` header, which is the integer value
+/// followed by the unit, like `100m` means 100 milliseconds.
+fn parse_grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let raw = metadata.get("grpc-timeout")?.to_str().ok()?;
+    if raw.len() < 2 {
+        return None;
+    }
+    let (value, unit) = raw.split_at(raw.len() - 1);
+    let value: u64 = value.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 60 * 60)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
 pub struct DefaultShuffleServer {
     app_manager_ref: AppManagerRef,
     rejection_policy_gateway: RejectionPolicyGateway,
@@ -124,13 +149,22 @@ impl ShuffleServer for DefaultShuffleServer {
         // todo: fast fail when hdfs is enabled but empty remote storage info.
         let remote_storage_info = inner.remote_storage.map(|x| RemoteStorageConfig::from(x));
         // todo: add more options: huge_partition_threshold. and so on...
-        let app_config_option = AppConfigOptions::new(
-            DataDistribution::LOCAL_ORDER,
+        let data_distribution = match inner.shuffle_data_distribution() {
+            ProtoDataDistribution::Normal => DataDistribution::NORMAL,
+            ProtoDataDistribution::LocalOrder => DataDistribution::LOCAL_ORDER,
+        };
+        let mut app_config_option = AppConfigOptions::new(
+            data_distribution,
             inner.max_concurrency_per_partition_to_write,
             remote_storage_info,
         );
+        app_config_option.user = inner.user.to_string();
+        app_config_option.consistency_check_enable = inner.consistency_check_enabled;
+        if inner.expected_total_bytes > 0 {
+            app_config_option.expected_total_bytes = Some(inner.expected_total_bytes as u64);
+        }
 
-        let status = match self.app_manager_ref.register(
+        let (status, ret_msg) = match self.app_manager_ref.register(
             inner.app_id.clone(),
             inner.shuffle_id,
             app_config_option,
@@ -140,14 +174,22 @@ impl ShuffleServer for DefaultShuffleServer {
                     "Errors on registering for app:{:?}, shuffle:{:?}. error:{:#?}",
                     &inner.app_id, &inner.shuffle_id, e
                 );
-                StatusCode::INTERNAL_ERROR
+                match e.downcast_ref::<WorkerError>() {
+                    Some(WorkerError::APP_EXPECTED_SIZE_EXCEEDS_CAPACITY(_, _))
+                    | Some(WorkerError::READ_ONLY_MODE)
+                    | Some(WorkerError::APP_IS_PURGING(_))
+                    | Some(WorkerError::SERVER_DECOMMISSIONING(_))
+                    | Some(WorkerError::TOO_MANY_SHUFFLES(_, _)) => {
+                        (StatusCode::ACCESS_DENIED, e.to_string())
+                    }
+                    _ => (StatusCode::INTERNAL_ERROR, "".to_string()),
+                }
             }
-            _ => StatusCode::SUCCESS,
-        }
-        .into();
+            _ => (StatusCode::SUCCESS, "".to_string()),
+        };
         Ok(Response::new(ShuffleRegisterResponse {
-            status,
-            ret_msg: "".to_string(),
+            status: status.into(),
+            ret_msg,
         }))
     }
 
@@ -273,13 +315,30 @@ impl ShuffleServer for DefaultShuffleServer {
         }
         let required_len_with_ticket = release_result.unwrap();
 
-        let mut blocks_map = HashMap::new();
-        for shuffle_data in req.shuffle_data {
-            let data: PartitionedData = shuffle_data.into();
-            let partition_id = data.partition_id;
-            let data_blocks = data.blocks;
-            let blocks = blocks_map.entry(partition_id).or_insert_with(|| vec![]);
-            blocks.extend(data_blocks);
+        let mut crc_aggregates: HashMap<i32, i64> = HashMap::new();
+        for data in req.shuffle_data.iter() {
+            if let Some(aggregate) = data.crc_aggregate {
+                let value = crc_aggregates.entry(data.partition_id).or_insert(0);
+                *value = value.wrapping_add(aggregate);
+            }
+        }
+        let blocks_map = group_blocks_by_partition(req.shuffle_data);
+
+        let written_len: i64 = blocks_map
+            .values()
+            .flat_map(|blocks| blocks.iter())
+            .map(|block| block.length as i64)
+            .sum();
+        if let Err(err) = app.check_write_reservation(required_len_with_ticket, written_len) {
+            warn!(
+                "Rejecting the data of app: {} with ticketId: {}. err: {}",
+                &app_id, ticket_id, err
+            );
+            let _ = app.dec_allocated_from_budget(required_len_with_ticket);
+            return Ok(Response::new(SendShuffleDataResponse {
+                status: StatusCode::INTERNAL_ERROR.into(),
+                ret_msg: err.to_string(),
+            }));
         }
 
         let mut inserted_failure_occurs = false;
@@ -299,9 +358,14 @@ impl ShuffleServer for DefaultShuffleServer {
                 shuffle_id,
                 partition_id,
             };
-            let ctx = WritingViewContext::new(uid, blocks);
+            let ctx = WritingViewContext::new(uid.clone(), blocks);
             let app_ref = app.clone();
             let inserted = app_ref.insert(ctx).instrument_await(await_tree_msg).await;
+            if inserted.is_ok() {
+                if let Some(aggregate) = crc_aggregates.get(&partition_id) {
+                    app_ref.add_expected_crc_aggregate(&uid, *aggregate);
+                }
+            }
 
             if inserted.is_err() {
                 let err = format!(
@@ -353,12 +417,19 @@ impl ShuffleServer for DefaultShuffleServer {
         request: Request<GetLocalShuffleIndexRequest>,
     ) -> Result<Response<GetLocalShuffleIndexResponse>, Status> {
         let start = tokio::time::Instant::now();
+        let deadline = self
+            .app_manager_ref
+            .read_deadline(parse_grpc_timeout(request.metadata()));
         let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
         let partition_id = req.partition_id;
         let _partition_num = req.partition_num;
         let _partition_per_range = req.partition_num_per_range;
+        let entry_range = req.max_entries.map(|max_entries| IndexEntryRange {
+            start_entry: req.start_entry.max(0) as u64,
+            max_entries: max_entries.max(0) as u64,
+        });
 
         let app_option = self.app_manager_ref.get_app(&app_id);
 
@@ -370,6 +441,7 @@ impl ShuffleServer for DefaultShuffleServer {
                 ret_msg: "App not found".to_string(),
                 data_file_len: 0,
                 storage_ids: vec![],
+                index_len: 0,
             }));
         }
 
@@ -379,6 +451,8 @@ impl ShuffleServer for DefaultShuffleServer {
         let data_index_wrapper = app
             .list_index(ReadingIndexViewContext {
                 partition_id: partition_id.clone(),
+                entry_range,
+                deadline,
             })
             .instrument_await(format!(
                 "get index from localfile. uid: {:?}",
@@ -386,18 +460,18 @@ impl ShuffleServer for DefaultShuffleServer {
             ))
             .await;
 
-        if data_index_wrapper.is_err() {
-            let error_msg = data_index_wrapper.err();
+        if let Err(err) = &data_index_wrapper {
             error!(
                 "Errors on getting localfile data index for app:[{}], error: {:?}",
-                &app_id, error_msg
+                &app_id, err
             );
             return Ok(Response::new(GetLocalShuffleIndexResponse {
                 index_data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", error_msg),
+                status: err.reading_status_code().into(),
+                ret_msg: format!("{:?}", err),
                 data_file_len: 0,
                 storage_ids: vec![],
+                index_len: 0,
             }));
         }
 
@@ -414,6 +488,7 @@ impl ShuffleServer for DefaultShuffleServer {
                     ret_msg: "".to_string(),
                     data_file_len: data_index.data_file_len,
                     storage_ids: vec![],
+                    index_len: data_index.index_len,
                 }))
             }
         }
@@ -425,6 +500,9 @@ impl ShuffleServer for DefaultShuffleServer {
     ) -> Result<Response<GetLocalShuffleDataResponse>, Status> {
         let start = tokio::time::Instant::now();
         let timer = GRPC_GET_LOCALFILE_DATA_PROCESS_TIME.start_timer();
+        let deadline = self
+            .app_manager_ref
+            .read_deadline(parse_grpc_timeout(request.metadata()));
         let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
@@ -443,6 +521,7 @@ impl ShuffleServer for DefaultShuffleServer {
                 data: Default::default(),
                 status: StatusCode::NO_REGISTER.into(),
                 ret_msg: "No such app in this shuffle server".to_string(),
+                has_more_data: false,
             }));
         }
 
@@ -457,6 +536,9 @@ impl ShuffleServer for DefaultShuffleServer {
                 uid: partition_id.clone(),
                 reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(req.offset, req.length as i64),
                 serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline,
             })
             .instrument_await(format!(
                 "select data from localfile. uid: {:?}",
@@ -464,16 +546,15 @@ impl ShuffleServer for DefaultShuffleServer {
             ))
             .await;
 
-        if data_fetched_result.is_err() {
-            let err_msg = data_fetched_result.err();
+        if let Err(err) = &data_fetched_result {
             error!(
                 "Errors on getting localfile index for app:[{}], error: {:?}",
-                &app_id, err_msg
+                &app_id, err
             );
             return Ok(Response::new(GetLocalShuffleDataResponse {
                 data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", err_msg),
+                status: err.reading_status_code().into(),
+                ret_msg: format!("{:?}", err),
             }));
         }
 
@@ -496,6 +577,9 @@ impl ShuffleServer for DefaultShuffleServer {
         request: Request<GetMemoryShuffleDataRequest>,
     ) -> Result<Response<GetMemoryShuffleDataResponse>, Status> {
         let timer = GRPC_GET_MEMORY_DATA_PROCESS_TIME.start_timer();
+        let deadline = self
+            .app_manager_ref
+            .read_deadline(parse_grpc_timeout(request.metadata()));
         let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
@@ -548,21 +632,24 @@ impl ShuffleServer for DefaultShuffleServer {
                     req.read_buffer_size as i64,
                 ),
                 serialized_expected_task_ids_bitmap,
+                latest_attempt_only: req.latest_attempt_only,
+                min_write_sequence: None,
+                deadline,
             })
             .instrument_await(format!("select data from memory. uid: {:?}", &partition_id))
             .await;
 
-        if data_fetched_result.is_err() {
-            let error_msg = data_fetched_result.err();
+        if let Err(err) = &data_fetched_result {
             error!(
                 "Errors on getting data from memory for [{}], error: {:?}",
-                &app_id, error_msg
+                &app_id, err
             );
             return Ok(Response::new(GetMemoryShuffleDataResponse {
                 shuffle_data_block_segments: vec![],
                 data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", error_msg),
+                status: err.reading_status_code().into(),
+                ret_msg: format!("{:?}", err),
+                has_more_data: false,
             }));
         }
 
@@ -582,18 +669,55 @@ impl ShuffleServer for DefaultShuffleServer {
             data: bytes,
             status: StatusCode::SUCCESS.into(),
             ret_msg: "".to_string(),
+            has_more_data: data.has_more,
         }))
     }
 
     async fn commit_shuffle_task(
         &self,
-        _request: Request<ShuffleCommitRequest>,
+        request: Request<ShuffleCommitRequest>,
     ) -> Result<Response<ShuffleCommitResponse>, Status> {
-        warn!("It has not been supported of committing shuffle data");
+        let req = request.into_inner();
+        let app_id = req.app_id;
+        let shuffle_id = req.shuffle_id;
+
+        let app = match self.app_manager_ref.get_app(&app_id) {
+            Some(app) if app.is_consistency_check_enabled() => app,
+            Some(_) => {
+                warn!("It has not been supported of committing shuffle data");
+                return Ok(Response::new(ShuffleCommitResponse {
+                    commit_count: 0,
+                    status: StatusCode::INTERNAL_ERROR.into(),
+                    ret_msg: "Not supported".to_string(),
+                    divergent_partition_ids: vec![],
+                }));
+            }
+            _ => {
+                return Ok(Response::new(ShuffleCommitResponse {
+                    commit_count: 0,
+                    status: StatusCode::NO_REGISTER.into(),
+                    ret_msg: "No such app in this shuffle server".to_string(),
+                    divergent_partition_ids: vec![],
+                }));
+            }
+        };
+
+        let divergent_partition_ids = app.get_divergent_partitions(shuffle_id);
+        let (status, ret_msg) = if divergent_partition_ids.is_empty() {
+            (StatusCode::SUCCESS, "".to_string())
+        } else {
+            let msg = format!(
+                "The crc aggregates of partitions: {:?} diverge for app: {}, shuffle: {}",
+                &divergent_partition_ids, &app_id, shuffle_id
+            );
+            error!("{}", &msg);
+            (StatusCode::INTERNAL_ERROR, msg)
+        };
         Ok(Response::new(ShuffleCommitResponse {
             commit_count: 0,
-            status: StatusCode::INTERNAL_ERROR.into(),
-            ret_msg: "Not supported".to_string(),
+            status: status.into(),
+            ret_msg,
+            divergent_partition_ids,
         }))
     }
 
@@ -611,29 +735,59 @@ impl ShuffleServer for DefaultShuffleServer {
             return Ok(Response::new(ReportShuffleResultResponse {
                 status: StatusCode::NO_REGISTER.into(),
                 ret_msg: "No such app in this shuffle server".to_string(),
+                block_count_deltas: vec![],
             }));
         }
         let app = app.unwrap();
         let mut block_ids = HashMap::new();
+        let mut expected_counts = HashMap::new();
         for partition_to_block_id in partition_to_block_ids {
+            if let Some(expected) = partition_to_block_id.expected_total_count {
+                expected_counts.insert(partition_to_block_id.partition_id, expected);
+            }
             block_ids.insert(
                 partition_to_block_id.partition_id,
                 partition_to_block_id.block_ids,
             );
         }
-        match app
+        if let Err(e) = app
             .report_multi_block_ids(ReportMultiBlockIdsContext::new(shuffle_id, block_ids))
             .await
         {
-            Err(e) => Ok(Response::new(ReportShuffleResultResponse {
+            return Ok(Response::new(ReportShuffleResultResponse {
                 status: StatusCode::INTERNAL_ERROR.into(),
                 ret_msg: e.to_string(),
-            })),
-            _ => Ok(Response::new(ReportShuffleResultResponse {
-                status: StatusCode::SUCCESS.into(),
-                ret_msg: "".to_string(),
-            })),
+                block_count_deltas: vec![],
+            }));
         }
+
+        // the count check is a soft warning, it will not fail the report.
+        let block_count_deltas =
+            match app.check_reported_block_ids_count(shuffle_id, &expected_counts) {
+                Ok(deltas) => deltas
+                    .into_iter()
+                    .map(|(partition_id, delta)| PartitionToBlockCountDelta {
+                        partition_id,
+                        delta,
+                    })
+                    .collect(),
+                Err(e) => {
+                    warn!(
+                        "Errors on checking the reported block ids count for app: {}. err: {:?}",
+                        &app_id, e
+                    );
+                    vec![]
+                }
+            };
+        let ret_msg = match block_count_deltas.is_empty() {
+            true => "".to_string(),
+            _ => "Reported block ids number is not consistent with the expected".to_string(),
+        };
+        Ok(Response::new(ReportShuffleResultResponse {
+            status: StatusCode::SUCCESS.into(),
+            ret_msg,
+            block_count_deltas,
+        }))
     }
 
     async fn get_shuffle_result(
@@ -743,6 +897,64 @@ impl ShuffleServer for DefaultShuffleServer {
         }
     }
 
+    async fn get_shuffle_result_batch(
+        &self,
+        request: Request<GetShuffleResultBatchRequest>,
+    ) -> Result<Response<GetShuffleResultBatchResponse>, Status> {
+        let req = request.into_inner();
+        let app_id = req.app_id;
+        let shuffle_id = req.shuffle_id;
+        let layout = req.block_id_layout;
+        let mut partitions = req.partitions;
+        for range in req.partition_ranges {
+            partitions.extend(range.start..=range.end);
+        }
+
+        let app = self.app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            warn!(
+                "The app of {:?} has not been registered or been removed that should not happen!",
+                &app_id
+            );
+            return Ok(Response::new(GetShuffleResultBatchResponse {
+                status: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in this shuffle server".to_string(),
+                serialized_bitmaps: Default::default(),
+            }));
+        }
+        let app = app.unwrap();
+        let ctx = GetMultiBlockIdsContext {
+            shuffle_id,
+            partition_ids: partitions,
+            layout: to_layout(layout),
+        };
+        match app
+            .get_block_ids_batch(ctx)
+            .instrument_await(format!(
+                "getting the batch block_id bitmaps for app[{}]/shuffle_id[{}]",
+                &app_id, shuffle_id
+            ))
+            .await
+        {
+            Err(e) => {
+                error!(
+                    "Errors on getting shuffle block ids by batch way of app:[{}], error: {:?}",
+                    &app_id, &e
+                );
+                Ok(Response::new(GetShuffleResultBatchResponse {
+                    status: StatusCode::INTERNAL_ERROR.into(),
+                    ret_msg: format!("{:?}", &e),
+                    serialized_bitmaps: Default::default(),
+                }))
+            }
+            Ok(data) => Ok(Response::new(GetShuffleResultBatchResponse {
+                status: 0,
+                ret_msg: "".to_string(),
+                serialized_bitmaps: data,
+            })),
+        }
+    }
+
     async fn finish_shuffle(
         &self,
         _request: Request<FinishShuffleRequest>,
@@ -841,4 +1053,111 @@ impl ShuffleServer for DefaultShuffleServer {
             ret_msg: "".to_string(),
         }))
     }
+
+    async fn get_partition_flush_state(
+        &self,
+        request: Request<GetPartitionFlushStateRequest>,
+    ) -> Result<Response<GetPartitionFlushStateResponse>, Status> {
+        let req = request.into_inner();
+        let app_id = req.app_id;
+
+        let app = self.app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            return Ok(Response::new(GetPartitionFlushStateResponse {
+                status: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in this shuffle server".to_string(),
+                pending_spill_event_num: 0,
+                memory_buffered_bytes: 0,
+                last_flushed_offset: 0,
+            }));
+        }
+
+        let uid = PartitionedUId::from(app_id, req.shuffle_id, req.partition_id);
+        match app
+            .unwrap()
+            .get_partition_flush_state(&uid)
+            .instrument_await(format!("getting partition flush state. uid: {:?}", &uid))
+            .await
+        {
+            Ok(state) => Ok(Response::new(GetPartitionFlushStateResponse {
+                status: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+                pending_spill_event_num: state.pending_spill_event_num as i64,
+                memory_buffered_bytes: state.memory_buffered_bytes as i64,
+                last_flushed_offset: state.last_flushed_offset,
+            })),
+            Err(err) => Ok(Response::new(GetPartitionFlushStateResponse {
+                status: StatusCode::INTERNAL_ERROR.into(),
+                ret_msg: format!("{:?}", err),
+                pending_spill_event_num: 0,
+                memory_buffered_bytes: 0,
+                last_flushed_offset: 0,
+            })),
+        }
+    }
+
+    async fn get_partition_location(
+        &self,
+        request: Request<GetPartitionLocationRequest>,
+    ) -> Result<Response<GetPartitionLocationResponse>, Status> {
+        let req = request.into_inner();
+        let app_id = req.app_id;
+
+        let app = self.app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            return Ok(Response::new(GetPartitionLocationResponse {
+                status: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in this shuffle server".to_string(),
+                memory: false,
+                localfile_disk_root: "".to_string(),
+                hdfs: false,
+            }));
+        }
+
+        let uid = PartitionedUId::from(app_id, req.shuffle_id, req.partition_id);
+        match app
+            .unwrap()
+            .get_partition_location(&uid)
+            .instrument_await(format!("getting partition location. uid: {:?}", &uid))
+            .await
+        {
+            Ok(location) => Ok(Response::new(GetPartitionLocationResponse {
+                status: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+                memory: location.memory,
+                localfile_disk_root: location.localfile_disk_root.unwrap_or_default(),
+                hdfs: location.hdfs,
+            })),
+            Err(err) => Ok(Response::new(GetPartitionLocationResponse {
+                status: StatusCode::INTERNAL_ERROR.into(),
+                ret_msg: format!("{:?}", err),
+                memory: false,
+                localfile_disk_root: "".to_string(),
+                hdfs: false,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::grpc::service::parse_grpc_timeout;
+    use std::time::Duration;
+    use tonic::metadata::MetadataMap;
+
+    #[test]
+    fn parse_grpc_timeout_test() {
+        let timeout = |raw: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert("grpc-timeout", raw.parse().unwrap());
+            parse_grpc_timeout(&metadata)
+        };
+        assert_eq!(Some(Duration::from_millis(100)), timeout("100m"));
+        assert_eq!(Some(Duration::from_secs(120)), timeout("2M"));
+        assert_eq!(Some(Duration::from_secs(3)), timeout("3S"));
+        assert_eq!(Some(Duration::from_micros(5)), timeout("5u"));
+        assert_eq!(None, timeout("5x"));
+        assert_eq!(None, timeout("m"));
+        assert_eq!(None, parse_grpc_timeout(&MetadataMap::new()));
+    }
 }
@@ -4,6 +4,7 @@ use crate::deadlock::DEADLOCK_TAG;
 use crate::mem_allocator::ALLOCATOR;
 use crate::panic_hook::PANIC_TAG;
 use crate::storage::HybridStorage;
+use crate::timeline::{TimelineCategory, TIMELINE};
 use crate::util;
 use anyhow::Result;
 use dashmap::DashMap;
@@ -119,6 +120,13 @@ impl HealthService {
                     "The health state from checker of [disk used ratio] changes from [{}] to [{}]",
                     prev_stat, localfile_stat
                 );
+                TIMELINE.record(
+                    TimelineCategory::SERVICE_HEALTH,
+                    format!(
+                        "[disk used ratio] healthy: {} -> {}",
+                        prev_stat, localfile_stat
+                    ),
+                );
                 self.health_stat.s_1.store(!prev_stat, SeqCst);
             }
             if !localfile_stat {
@@ -133,6 +141,10 @@ impl HealthService {
                 "The health state from checker of [hybrid store health] changes from [{}] to [{}]",
                 prev_stat, stat
             );
+            TIMELINE.record(
+                TimelineCategory::SERVICE_HEALTH,
+                format!("[hybrid store health] healthy: {} -> {}", prev_stat, stat),
+            );
             self.health_stat.s_2.store(!prev_stat, SeqCst);
         }
         if !stat {
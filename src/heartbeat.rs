@@ -1,18 +1,106 @@
 use crate::app::{AppManagerRef, SHUFFLE_SERVER_ID, SHUFFLE_SERVER_IP};
-use crate::config::Config;
+use crate::config::{Config, HeartbeatPressureConfig};
 use crate::decommission::DecommissionManager;
 use crate::grpc::protobuf::uniffle::coordinator_server_client::CoordinatorServerClient;
-use crate::grpc::protobuf::uniffle::{ShuffleServerHeartBeatRequest, ShuffleServerId};
+use crate::grpc::protobuf::uniffle::storage_info::{StorageMedia, StorageStatus};
+use crate::grpc::protobuf::uniffle::{ShuffleServerHeartBeatRequest, ShuffleServerId, StorageInfo};
 use crate::health_service::HealthService;
 use crate::metric::SERVICE_IS_HEALTHY;
 use crate::runtime::manager::RuntimeManager;
+use crate::store::local::LocalfileStoreStat;
+use crate::store::mem::capacity::CapacitySnapshot;
+use crate::subsystem::{SubsystemTask, COORDINATOR_HEARTBEAT, SUBSYSTEM_MANAGER};
+use crate::timeline::{TimelineCategory, TIMELINE};
 use await_tree::InstrumentAwait;
 use log::{error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::Receiver;
 use tonic::transport::Channel;
 
 const DEFAULT_SHUFFLE_SERVER_TAG: &str = "ss_v4";
 
+/// The load pressure reported to the coordinator, every ratio is in the range of [0, 1].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LoadPressure {
+    pub memory_used_ratio: f64,
+    pub disk_used_ratio: f64,
+    pub spill_backlog_ratio: f64,
+}
+
+impl LoadPressure {
+    pub fn compute(
+        memory_snapshot: &CapacitySnapshot,
+        memory_spill_event_num: u64,
+        localfile_stat: &LocalfileStoreStat,
+        conf: &HeartbeatPressureConfig,
+    ) -> Self {
+        let memory_used_ratio = if memory_snapshot.capacity() <= 0 {
+            0f64
+        } else {
+            (memory_snapshot.used() + memory_snapshot.allocated()) as f64
+                / memory_snapshot.capacity() as f64
+        };
+        let disk_used_ratio = localfile_stat
+            .stats
+            .iter()
+            .map(|stat| stat.used_ratio)
+            .fold(0f64, f64::max);
+        let spill_backlog_ratio = if conf.spill_backlog_max_event_num == 0 {
+            0f64
+        } else {
+            memory_spill_event_num as f64 / conf.spill_backlog_max_event_num as f64
+        };
+        Self {
+            memory_used_ratio: memory_used_ratio.clamp(0f64, 1f64),
+            disk_used_ratio: disk_used_ratio.clamp(0f64, 1f64),
+            spill_backlog_ratio: spill_backlog_ratio.clamp(0f64, 1f64),
+        }
+    }
+
+    /// The score is decided by the most pressured resource.
+    pub fn score(&self) -> f64 {
+        self.memory_used_ratio
+            .max(self.disk_used_ratio)
+            .max(self.spill_backlog_ratio)
+    }
+
+    fn fill(&self, request: &mut ShuffleServerHeartBeatRequest) {
+        request.memory_used_ratio = Some(self.memory_used_ratio);
+        request.disk_used_ratio = Some(self.disk_used_ratio);
+        request.spill_backlog_ratio = Some(self.spill_backlog_ratio);
+        request.pressure_score = Some(self.score());
+    }
+}
+
+/// The per disk capacity and usage reported to the coordinator, keyed by the disk root.
+fn storage_info(localfile_stat: &LocalfileStoreStat) -> HashMap<String, StorageInfo> {
+    localfile_stat
+        .stats
+        .iter()
+        .map(|stat| {
+            let status = if stat.healthy {
+                StorageStatus::Normal
+            } else {
+                StorageStatus::Unhealthy
+            };
+            let info = StorageInfo {
+                mount_point: stat.root.clone(),
+                storage_media: StorageMedia::StorageTypeUnknown.into(),
+                capacity: stat.capacity as i64,
+                used_bytes: stat.used as i64,
+                writing_speed1_m: 0,
+                writing_speed5_m: 0,
+                writing_speed1_h: 0,
+                num_of_writing_failures: 0,
+                status: status.into(),
+            };
+            (stat.root.clone(), info)
+        })
+        .collect()
+}
+
 pub struct HeartbeatTask;
 
 impl HeartbeatTask {
@@ -35,6 +123,7 @@ impl HeartbeatTask {
         let urpc_port = config.urpc_port.unwrap_or(0);
 
         let interval_seconds = config.heartbeat_interval_seconds;
+        let pressure_conf = config.heartbeat_pressure_config.clone();
 
         let ip = SHUFFLE_SERVER_IP.get().unwrap().to_string();
         let id = SHUFFLE_SERVER_ID.get().unwrap().to_string();
@@ -47,9 +136,16 @@ impl HeartbeatTask {
             netty_port: urpc_port,
         };
 
-        runtime_manager.default_runtime.spawn_with_await_tree(
-            "Coordinator heartbeat task",
-            async move {
+        // the heartbeat could be stopped/restarted individually by the admin api
+        let starter = move |mut shutdown: Receiver<()>| {
+            let app_manager = app_manager.clone();
+            let health_service = health_service.clone();
+            let decommission_manager = decommission_manager.clone();
+            let coordinator_quorum = coordinator_quorum.clone();
+            let tags = tags.clone();
+            let pressure_conf = pressure_conf.clone();
+            let shuffle_server_id = shuffle_server_id.clone();
+            let task = async move {
                 let mut multi_coordinator_clients: Vec<CoordinatorServerClient<Channel>> =
                     futures::future::try_join_all(coordinator_quorum.iter().map(|quorum| {
                         CoordinatorServerClient::connect(format!("http://{}", quorum))
@@ -57,6 +153,7 @@ impl HeartbeatTask {
                     .await
                     .unwrap();
 
+                let mut spill_backlog_alarmed = false;
                 loop {
                     tokio::time::sleep(Duration::from_secs(interval_seconds as u64))
                         .instrument_await("sleeping")
@@ -75,10 +172,29 @@ impl HeartbeatTask {
                         .unwrap_or((0, 0, 0).into());
                     let memory_spill_event_num =
                         app_manager.store_memory_spill_event_num().unwrap_or(0) as i32;
+                    let spill_backlog_full = pressure_conf.spill_backlog_max_event_num > 0
+                        && memory_spill_event_num as u64
+                            >= pressure_conf.spill_backlog_max_event_num;
+                    if spill_backlog_full != spill_backlog_alarmed {
+                        spill_backlog_alarmed = spill_backlog_full;
+                        TIMELINE.record(
+                            TimelineCategory::SPILL_BACKLOG,
+                            format!(
+                                "the spill backlog is {}. spill event num: {}",
+                                if spill_backlog_full {
+                                    "full"
+                                } else {
+                                    "recovered"
+                                },
+                                memory_spill_event_num
+                            ),
+                        );
+                    }
 
                     let decommission_state = decommission_manager.get_server_status();
+                    let localfile_stat = app_manager.store_localfile_stat().unwrap_or_default();
 
-                    let heartbeat_req = ShuffleServerHeartBeatRequest {
+                    let mut heartbeat_req = ShuffleServerHeartBeatRequest {
                         server_id: Some(shuffle_server_id.clone()),
                         used_memory: memory_snapshot.used(),
                         pre_allocated_memory: memory_snapshot.allocated(),
@@ -87,8 +203,26 @@ impl HeartbeatTask {
                         tags: all_tags,
                         is_healthy: Some(healthy),
                         status: decommission_state.into(),
-                        storage_info: Default::default(),
+                        storage_info: storage_info(&localfile_stat),
+                        memory_used_ratio: None,
+                        disk_used_ratio: None,
+                        spill_backlog_ratio: None,
+                        pressure_score: None,
+                        committed_expected_bytes: Some(
+                            app_manager.committed_expected_bytes() as i64
+                        ),
+                        memory_capacity: Some(memory_snapshot.capacity()),
+                        alive_app_number: Some(app_manager.get_alive_app_number() as i32),
                     };
+                    if pressure_conf.enable {
+                        LoadPressure::compute(
+                            &memory_snapshot,
+                            memory_spill_event_num as u64,
+                            &localfile_stat,
+                            &pressure_conf,
+                        )
+                        .fill(&mut heartbeat_req);
+                    }
 
                     // It must use the 0..len to avoid borrow check in loop.
                     for idx in 0..multi_coordinator_clients.len() {
@@ -107,7 +241,104 @@ impl HeartbeatTask {
                         }
                     }
                 }
-            },
-        );
+            };
+            let handle = runtime_manager.default_runtime.spawn_with_await_tree(
+                "Coordinator heartbeat task",
+                async move {
+                    tokio::select! {
+                        _ = task => {}
+                        _ = shutdown.recv() => {
+                            info!("The coordinator heartbeat task has been stopped");
+                        }
+                    }
+                },
+            );
+            Ok(vec![SubsystemTask::Async(handle)])
+        };
+        let _ = SUBSYSTEM_MANAGER.register(COORDINATOR_HEARTBEAT, Arc::new(starter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::HeartbeatPressureConfig;
+    use crate::grpc::protobuf::uniffle::storage_info::StorageStatus;
+    use crate::grpc::protobuf::uniffle::ShuffleServerHeartBeatRequest;
+    use crate::heartbeat::{storage_info, LoadPressure};
+    use crate::store::local::{DiskStat, LocalfileStoreStat};
+
+    #[test]
+    fn test_load_pressure() {
+        let conf = HeartbeatPressureConfig {
+            enable: true,
+            spill_backlog_max_event_num: 100,
+        };
+        let localfile_stat = LocalfileStoreStat {
+            stats: vec![
+                DiskStat {
+                    root: "/data1".to_string(),
+                    used_ratio: 0.2,
+                    capacity: 100,
+                    used: 20,
+                    healthy: true,
+                },
+                DiskStat {
+                    root: "/data2".to_string(),
+                    used_ratio: 0.4,
+                    capacity: 100,
+                    used: 40,
+                    healthy: false,
+                },
+            ],
+        };
+
+        // case1: low pressure
+        let pressure = LoadPressure::compute(&(100, 10, 10).into(), 10, &localfile_stat, &conf);
+        assert_eq!(0.2, pressure.memory_used_ratio);
+        assert_eq!(0.4, pressure.disk_used_ratio);
+        assert_eq!(0.1, pressure.spill_backlog_ratio);
+        assert_eq!(0.4, pressure.score());
+
+        // case2: high pressure of the spill backlog should be reflected in the heartbeat payload
+        let pressure = LoadPressure::compute(&(100, 40, 55).into(), 500, &localfile_stat, &conf);
+        let mut request = ShuffleServerHeartBeatRequest::default();
+        pressure.fill(&mut request);
+        assert_eq!(Some(0.95), request.memory_used_ratio);
+        assert_eq!(Some(0.4), request.disk_used_ratio);
+        assert_eq!(Some(1.0), request.spill_backlog_ratio);
+        assert_eq!(Some(1.0), request.pressure_score);
+    }
+
+    #[test]
+    fn test_storage_info() {
+        let localfile_stat = LocalfileStoreStat {
+            stats: vec![
+                DiskStat {
+                    root: "/data1".to_string(),
+                    used_ratio: 0.2,
+                    capacity: 100,
+                    used: 20,
+                    healthy: true,
+                },
+                DiskStat {
+                    root: "/data2".to_string(),
+                    used_ratio: 0.4,
+                    capacity: 100,
+                    used: 40,
+                    healthy: false,
+                },
+            ],
+        };
+        let infos = storage_info(&localfile_stat);
+        assert_eq!(2, infos.len());
+
+        let info = infos.get("/data1").unwrap();
+        assert_eq!(100, info.capacity);
+        assert_eq!(20, info.used_bytes);
+        assert_eq!(StorageStatus::Normal as i32, info.status);
+
+        let info = infos.get("/data2").unwrap();
+        assert_eq!(40, info.used_bytes);
+        assert_eq!(StorageStatus::Unhealthy as i32, info.status);
     }
 }
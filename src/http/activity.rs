@@ -0,0 +1,42 @@
+use crate::activity::{to_csv, ACTIVITY_LOG};
+use crate::http::Handler;
+use poem::error::InternalServerError;
+use poem::{handler, Request, RouteMethod};
+use serde::Deserialize;
+
+#[derive(Default)]
+pub struct ActivityHandler;
+
+impl Handler for ActivityHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(activity_handler)
+    }
+
+    /// request with /debug/activity?format=json (default)
+    /// or /debug/activity?format=csv
+    fn get_route_path(&self) -> String {
+        "/debug/activity".to_string()
+    }
+}
+
+#[derive(Deserialize)]
+#[allow(non_camel_case_types)]
+enum ActivityFormat {
+    json,
+    csv,
+}
+
+#[derive(Deserialize)]
+struct ActivityParam {
+    format: Option<ActivityFormat>,
+}
+
+#[handler]
+fn activity_handler(req: &Request) -> poem::Result<String> {
+    let params = req.params::<ActivityParam>()?;
+    let activities = ACTIVITY_LOG.dump();
+    match params.format.unwrap_or(ActivityFormat::json) {
+        ActivityFormat::json => serde_json::to_string(&activities).map_err(InternalServerError),
+        ActivityFormat::csv => Ok(to_csv(&activities)),
+    }
+}
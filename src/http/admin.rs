@@ -1,9 +1,13 @@
+use crate::app::{PartitionedUId, APP_MANAGER_REF};
 use crate::decommission::{DecommissionState, DECOMMISSION_MANAGER_REF};
 use crate::http::Handler;
 use anyhow::Result;
 use clap::builder::Str;
+use poem::error::InternalServerError;
 use poem::{handler, Request, RouteMethod};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_REBALANCE_SKEW_THRESHOLD: f64 = 0.1;
 
 #[derive(Default)]
 pub struct AdminHandler;
@@ -14,6 +18,13 @@ impl Handler for AdminHandler {
     }
 
     /// request with /admin?operation=DECOMMISSION
+    /// or /admin?operation=PARTITION_LOCATION&app_id=xxx&shuffle_id=0&partition_id=0
+    /// or /admin?operation=PARTITION_SIZES&app_id=xxx&shuffle_id=0
+    /// or /admin?operation=REBALANCE_DISKS&skew_threshold=0.1
+    /// or /admin?operation=REPAIR_INDEX&app_id=xxx&shuffle_id=0&partition_id=0
+    /// or /admin?operation=READ_ONLY
+    /// or /admin?operation=PURGE_PROGRESS&app_id=xxx
+    /// or /admin?operation=CANCEL_PURGE&app_id=xxx
     fn get_route_path(&self) -> String {
         "/admin".to_string()
     }
@@ -23,15 +34,34 @@ impl Handler for AdminHandler {
 enum Operation {
     DECOMMISSION,
     CANCEL_DECOMMISSION,
+    PARTITION_LOCATION,
+    PARTITION_SIZES,
+    REBALANCE_DISKS,
+    REBALANCE_DISKS_PROGRESS,
+    REPAIR_INDEX,
+    READ_ONLY,
+    CANCEL_READ_ONLY,
+    PURGE_PROGRESS,
+    CANCEL_PURGE,
 }
 
 #[derive(Deserialize)]
 struct OperationParam {
     operation: Operation,
+    app_id: Option<String>,
+    shuffle_id: Option<i32>,
+    partition_id: Option<i32>,
+    skew_threshold: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct PartitionSize {
+    partition_id: i32,
+    size: u64,
 }
 
 #[handler]
-fn adminHandler(req: &Request) -> poem::Result<String> {
+async fn adminHandler(req: &Request) -> poem::Result<String> {
     let params = req.params::<OperationParam>()?;
     let decom_manager_ref = DECOMMISSION_MANAGER_REF.get().unwrap();
 
@@ -42,6 +72,95 @@ fn adminHandler(req: &Request) -> poem::Result<String> {
         Operation::CANCEL_DECOMMISSION => {
             decom_manager_ref.as_state(DecommissionState::CANCEL_DECOMMISSION);
         }
+        Operation::PARTITION_LOCATION => {
+            let (app_id, shuffle_id, partition_id) =
+                match (params.app_id, params.shuffle_id, params.partition_id) {
+                    (Some(app_id), Some(shuffle_id), Some(partition_id)) => {
+                        (app_id, shuffle_id, partition_id)
+                    }
+                    _ => {
+                        return Ok(
+                            "app_id, shuffle_id and partition_id must be specified".to_string()
+                        )
+                    }
+                };
+            let app = match APP_MANAGER_REF.get().unwrap().get_app(&app_id) {
+                Some(app) => app,
+                _ => return Ok(format!("No such app: {}", app_id)),
+            };
+            let uid = PartitionedUId::from(app_id, shuffle_id, partition_id);
+            let location = app.get_partition_location(&uid).await?;
+            return serde_json::to_string(&location).map_err(|e| InternalServerError(e));
+        }
+        Operation::PARTITION_SIZES => {
+            let (app_id, shuffle_id) = match (params.app_id, params.shuffle_id) {
+                (Some(app_id), Some(shuffle_id)) => (app_id, shuffle_id),
+                _ => return Ok("app_id and shuffle_id must be specified".to_string()),
+            };
+            let app = match APP_MANAGER_REF.get().unwrap().get_app(&app_id) {
+                Some(app) => app,
+                _ => return Ok(format!("No such app: {}", app_id)),
+            };
+            let sizes: Vec<PartitionSize> = app
+                .partition_sizes(shuffle_id)
+                .into_iter()
+                .map(|(partition_id, size)| PartitionSize { partition_id, size })
+                .collect();
+            return serde_json::to_string(&sizes).map_err(|e| InternalServerError(e));
+        }
+        Operation::REBALANCE_DISKS => {
+            let skew_threshold = params
+                .skew_threshold
+                .unwrap_or(DEFAULT_REBALANCE_SKEW_THRESHOLD);
+            APP_MANAGER_REF
+                .get()
+                .unwrap()
+                .store_rebalance_localfile_disks(skew_threshold)?;
+        }
+        Operation::REBALANCE_DISKS_PROGRESS => {
+            let progress = APP_MANAGER_REF
+                .get()
+                .unwrap()
+                .store_localfile_rebalance_progress()?;
+            return serde_json::to_string(&progress).map_err(|e| InternalServerError(e));
+        }
+        Operation::REPAIR_INDEX => {
+            let uid = match (params.app_id, params.shuffle_id, params.partition_id) {
+                (Some(app_id), Some(shuffle_id), Some(partition_id)) => {
+                    PartitionedUId::from(app_id, shuffle_id, partition_id)
+                }
+                _ => return Ok("app_id, shuffle_id and partition_id must be specified".to_string()),
+            };
+            let outcome = APP_MANAGER_REF
+                .get()
+                .unwrap()
+                .store_repair_localfile_index(&uid)
+                .await?;
+            return serde_json::to_string(&outcome).map_err(|e| InternalServerError(e));
+        }
+        Operation::READ_ONLY => {
+            APP_MANAGER_REF.get().unwrap().set_read_only(true);
+        }
+        Operation::CANCEL_READ_ONLY => {
+            APP_MANAGER_REF.get().unwrap().set_read_only(false);
+        }
+        Operation::PURGE_PROGRESS => {
+            let app_id = match params.app_id {
+                Some(app_id) => app_id,
+                _ => return Ok("app_id must be specified".to_string()),
+            };
+            let progress = APP_MANAGER_REF.get().unwrap().purge_progress(&app_id);
+            return serde_json::to_string(&progress).map_err(|e| InternalServerError(e));
+        }
+        Operation::CANCEL_PURGE => {
+            let app_id = match params.app_id {
+                Some(app_id) => app_id,
+                _ => return Ok("app_id must be specified".to_string()),
+            };
+            if !APP_MANAGER_REF.get().unwrap().cancel_purge(&app_id) {
+                return Ok(format!("No running purge of app: {}", app_id));
+            }
+        }
     }
 
     Ok("Done".to_string())
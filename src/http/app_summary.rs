@@ -0,0 +1,176 @@
+use crate::app::{App, APP_MANAGER_REF};
+use crate::http::Handler;
+use poem::web::Json;
+use poem::{handler, Request, RouteMethod};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+#[derive(Default)]
+pub struct AppSummaryHandler;
+
+impl Handler for AppSummaryHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(app_summary_handler)
+    }
+
+    /// request with /admin/apps?page=0&size=50&sort=resident_bytes
+    fn get_route_path(&self) -> String {
+        "/admin/apps".to_string()
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+enum SortKey {
+    app_id,
+    registry_timestamp,
+    heartbeat_age_sec,
+    partition_number,
+    huge_partition_number,
+    received_bytes,
+    resident_bytes,
+}
+
+#[derive(Deserialize)]
+struct AppSummaryParam {
+    page: Option<usize>,
+    size: Option<usize>,
+    sort: Option<SortKey>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+struct AppSummary {
+    app_id: String,
+    registry_timestamp: u128,
+    heartbeat_age_sec: u64,
+    partition_number: usize,
+    huge_partition_number: u64,
+    received_bytes: u64,
+    resident_bytes: u64,
+    remote_storage_root: Option<String>,
+}
+
+impl From<&Arc<App>> for AppSummary {
+    fn from(app: &Arc<App>) -> Self {
+        Self {
+            app_id: app.app_id.to_string(),
+            registry_timestamp: app.registry_timestamp,
+            heartbeat_age_sec: app.heartbeat_age_sec(),
+            partition_number: app.partition_number(),
+            huge_partition_number: app.huge_partition_number(),
+            received_bytes: app.total_received_data_size(),
+            resident_bytes: app.total_resident_data_size(),
+            remote_storage_root: app.remote_storage_root(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AppSummaryPage {
+    total: usize,
+    page: usize,
+    size: usize,
+    apps: Vec<AppSummary>,
+}
+
+/// Sorts the apps by the given key, the app_id is in the ascending order and the others
+/// are in the descending order to make the heaviest apps come first.
+fn paginate(
+    mut summaries: Vec<AppSummary>,
+    sort: SortKey,
+    page: usize,
+    size: usize,
+) -> AppSummaryPage {
+    match sort {
+        SortKey::app_id => summaries.sort_by(|a, b| a.app_id.cmp(&b.app_id)),
+        SortKey::registry_timestamp => {
+            summaries.sort_by(|a, b| b.registry_timestamp.cmp(&a.registry_timestamp))
+        }
+        SortKey::heartbeat_age_sec => {
+            summaries.sort_by(|a, b| b.heartbeat_age_sec.cmp(&a.heartbeat_age_sec))
+        }
+        SortKey::partition_number => {
+            summaries.sort_by(|a, b| b.partition_number.cmp(&a.partition_number))
+        }
+        SortKey::huge_partition_number => {
+            summaries.sort_by(|a, b| b.huge_partition_number.cmp(&a.huge_partition_number))
+        }
+        SortKey::received_bytes => {
+            summaries.sort_by(|a, b| b.received_bytes.cmp(&a.received_bytes))
+        }
+        SortKey::resident_bytes => {
+            summaries.sort_by(|a, b| b.resident_bytes.cmp(&a.resident_bytes))
+        }
+    }
+    let total = summaries.len();
+    let apps = summaries
+        .into_iter()
+        .skip(page.saturating_mul(size))
+        .take(size)
+        .collect();
+    AppSummaryPage {
+        total,
+        page,
+        size,
+        apps,
+    }
+}
+
+#[handler]
+fn app_summary_handler(req: &Request) -> poem::Result<Json<AppSummaryPage>> {
+    let params = req.params::<AppSummaryParam>()?;
+    let summaries = APP_MANAGER_REF
+        .get()
+        .unwrap()
+        .apps
+        .iter()
+        .map(|entry| AppSummary::from(entry.value()))
+        .collect();
+    Ok(Json(paginate(
+        summaries,
+        params.sort.unwrap_or(SortKey::resident_bytes),
+        params.page.unwrap_or(0),
+        params.size.unwrap_or(DEFAULT_PAGE_SIZE),
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::app_summary::{paginate, AppSummary, SortKey};
+
+    fn summary(app_id: &str, resident_bytes: u64) -> AppSummary {
+        AppSummary {
+            app_id: app_id.to_string(),
+            registry_timestamp: 0,
+            heartbeat_age_sec: 0,
+            partition_number: 0,
+            huge_partition_number: 0,
+            received_bytes: 0,
+            resident_bytes,
+            remote_storage_root: None,
+        }
+    }
+
+    #[test]
+    fn test_paginate() {
+        let summaries: Vec<AppSummary> = (0..5)
+            .map(|idx| summary(&format!("app-{}", idx), idx * 10))
+            .collect();
+
+        let page = paginate(summaries.clone(), SortKey::resident_bytes, 0, 2);
+        assert_eq!(5, page.total);
+        let ids: Vec<&str> = page.apps.iter().map(|app| app.app_id.as_str()).collect();
+        assert_eq!(vec!["app-4", "app-3"], ids);
+
+        let page = paginate(summaries.clone(), SortKey::app_id, 2, 2);
+        let ids: Vec<&str> = page.apps.iter().map(|app| app.app_id.as_str()).collect();
+        assert_eq!(vec!["app-4"], ids);
+
+        // out of range
+        let page = paginate(summaries, SortKey::app_id, 10, 2);
+        assert_eq!(5, page.total);
+        assert!(page.apps.is_empty());
+    }
+}
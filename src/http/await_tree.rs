@@ -15,10 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::await_tree::AWAIT_TREE_REGISTRY;
+use crate::await_tree::{ActorTree, AWAIT_TREE_REGISTRY};
 use crate::http::Handler;
 use poem::endpoint::make;
-use poem::{get, RouteMethod};
+use poem::error::InternalServerError;
+use poem::{get, handler, Request, RouteMethod};
+use serde::Deserialize;
 
 pub struct AwaitTreeHandler {}
 
@@ -30,27 +32,142 @@ impl Default for AwaitTreeHandler {
 
 impl Handler for AwaitTreeHandler {
     fn get_route_method(&self) -> RouteMethod {
-        get(make(|_| async {
-            let registry_cloned = AWAIT_TREE_REGISTRY.clone().get_inner();
-            let registry = registry_cloned.lock();
-            let mut sorted_list: Vec<(u64, String)> = vec![];
-            for (v, tree) in registry.iter() {
-                let raw_tree = format!("{}", tree);
-                sorted_list.push((*v, raw_tree));
-            }
-            drop(registry);
-
-            let mut dynamic_string = String::new();
-            sorted_list.sort_by_key(|kv| kv.0);
-            for (_, raw_tree) in sorted_list {
-                dynamic_string.push_str(raw_tree.as_str());
-                dynamic_string.push('\n');
-            }
-            dynamic_string
-        }))
+        get(make(|_| async { to_text(&AWAIT_TREE_REGISTRY.dump()) }))
     }
 
     fn get_route_path(&self) -> String {
         "/await-tree".to_string()
     }
 }
+
+#[derive(Default)]
+pub struct AwaitTreeDebugHandler;
+
+impl Handler for AwaitTreeDebugHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(await_tree_debug_handler)
+    }
+
+    /// request with /debug/await_tree?format=text(default)|json&actor=xxx&min_duration_ms=xxx
+    /// and all the params are optional
+    fn get_route_path(&self) -> String {
+        "/debug/await_tree".to_string()
+    }
+}
+
+#[derive(Deserialize)]
+#[allow(non_camel_case_types)]
+enum AwaitTreeFormat {
+    text,
+    json,
+}
+
+#[derive(Deserialize)]
+struct AwaitTreeParam {
+    format: Option<AwaitTreeFormat>,
+    // the substring of the actor's root span
+    actor: Option<String>,
+    // the actors whose root span has not elapsed so long are filtered out
+    min_duration_ms: Option<u64>,
+}
+
+fn to_text(trees: &[ActorTree]) -> String {
+    let mut dynamic_string = String::new();
+    for tree in trees {
+        dynamic_string.push_str(tree.tree.as_str());
+        dynamic_string.push('\n');
+    }
+    dynamic_string
+}
+
+fn filter(trees: Vec<ActorTree>, params: &AwaitTreeParam) -> Vec<ActorTree> {
+    trees
+        .into_iter()
+        .filter(|tree| match params.actor.as_ref() {
+            Some(actor) => tree
+                .spans
+                .first()
+                .map_or(false, |root| root.span.contains(actor.as_str())),
+            _ => true,
+        })
+        .filter(|tree| match params.min_duration_ms {
+            Some(min_duration_ms) => tree.elapsed_ms.unwrap_or(0) >= min_duration_ms,
+            _ => true,
+        })
+        .collect()
+}
+
+#[handler]
+fn await_tree_debug_handler(req: &Request) -> poem::Result<String> {
+    let params = req.params::<AwaitTreeParam>()?;
+    let trees = filter(AWAIT_TREE_REGISTRY.dump(), &params);
+    match params.format.as_ref().unwrap_or(&AwaitTreeFormat::text) {
+        AwaitTreeFormat::text => Ok(to_text(&trees)),
+        AwaitTreeFormat::json => serde_json::to_string(&trees).map_err(InternalServerError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::await_tree::AWAIT_TREE_REGISTRY;
+    use crate::http::await_tree::AwaitTreeDebugHandler;
+    use crate::http::Handler;
+    use await_tree::InstrumentAwait;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_router() -> anyhow::Result<()> {
+        let root = AWAIT_TREE_REGISTRY
+            .register("http_mock_actor for the await-tree debug".to_string())
+            .await;
+        let actor = tokio::spawn(root.instrument(async {
+            tokio::time::sleep(Duration::from_secs(600))
+                .instrument_await("http_mock_actor sleeping")
+                .await
+        }));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let handler = AwaitTreeDebugHandler::default();
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        // the text with the per-span elapsed time
+        let resp = cli
+            .get("/debug/await_tree?actor=http_mock_actor")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await?;
+        assert!(body.contains("http_mock_actor for the await-tree debug ["));
+        assert!(body.contains("http_mock_actor sleeping ["));
+
+        // the json
+        let resp = cli
+            .get("/debug/await_tree?actor=http_mock_actor&format=json&min_duration_ms=50")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await?;
+        let trees: serde_json::Value = serde_json::from_str(&body)?;
+        let trees = trees.as_array().unwrap();
+        assert_eq!(1, trees.len());
+        assert!(trees[0]["elapsed_ms"].as_u64().unwrap() >= 50);
+        assert_eq!(
+            "http_mock_actor sleeping",
+            trees[0]["spans"][1]["span"].as_str().unwrap()
+        );
+
+        // filtered out by the duration
+        let resp = cli
+            .get("/debug/await_tree?actor=http_mock_actor&min_duration_ms=600000")
+            .send()
+            .await;
+        let body = resp.0.into_body().into_string().await?;
+        assert!(body.is_empty());
+
+        actor.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::chaos::{ActiveFault, FaultSpec, FAULT_INJECTOR};
+use crate::http::Handler;
+use poem::error::BadRequest;
+use poem::web::Json;
+use poem::{handler, Request, RouteMethod};
+use serde::Deserialize;
+
+#[derive(Default)]
+pub struct FaultsHandler;
+
+impl Handler for FaultsHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new()
+            .get(list_faults)
+            .post(register_fault)
+            .delete(remove_fault)
+    }
+
+    /// GET to list the active faults
+    /// or POST with the json body of fault spec to register
+    /// or DELETE with /admin/faults?id=0 to remove
+    fn get_route_path(&self) -> String {
+        "/admin/faults".to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct FaultIdParam {
+    id: u64,
+}
+
+#[handler]
+fn list_faults() -> Json<Vec<ActiveFault>> {
+    Json(FAULT_INJECTOR.list())
+}
+
+#[handler]
+fn register_fault(Json(spec): Json<FaultSpec>) -> poem::Result<String> {
+    let id = FAULT_INJECTOR.register(spec).map_err(BadRequest)?;
+    Ok(id.to_string())
+}
+
+#[handler]
+fn remove_fault(req: &Request) -> poem::Result<String> {
+    let params = req.params::<FaultIdParam>()?;
+    if FAULT_INJECTOR.remove(params.id) {
+        Ok("Done".to_string())
+    } else {
+        Ok(format!("No such fault: {}", params.id))
+    }
+}
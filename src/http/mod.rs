@@ -15,27 +15,39 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod activity;
 mod admin;
+mod app_summary;
 mod apps;
 mod await_tree;
+mod faults;
 mod historical_apps;
 mod http_service;
 mod metrics;
 mod profile_cpu;
 mod profile_heap;
+mod runtimes;
+mod subsystems;
+mod timeline;
 
 use crate::config::Config;
-use crate::http::await_tree::AwaitTreeHandler;
+use crate::http::await_tree::{AwaitTreeDebugHandler, AwaitTreeHandler};
 use crate::http::http_service::PoemHTTPServer;
 use crate::http::metrics::MetricsHTTPHandler;
 use crate::http::profile_cpu::ProfileCpuHandler;
 use crate::runtime::manager::RuntimeManager;
 
 use crate::app::AppManagerRef;
+use crate::http::activity::ActivityHandler;
 use crate::http::admin::AdminHandler;
+use crate::http::app_summary::AppSummaryHandler;
 use crate::http::apps::AppsHandler;
+use crate::http::faults::FaultsHandler;
 use crate::http::historical_apps::HistoricalAppsHandler;
 use crate::http::profile_heap::ProfileHeapHandler;
+use crate::http::runtimes::RuntimesHandler;
+use crate::http::subsystems::{SubsystemControlHandler, SubsystemsHandler};
+use crate::http::timeline::TimelineHandler;
 use log::info;
 use poem::RouteMethod;
 use serde::{Deserialize, Serialize};
@@ -72,9 +84,17 @@ fn new_server() -> Box<PoemHTTPServer> {
 
     server.register_handler(MetricsHTTPHandler::default());
     server.register_handler(AwaitTreeHandler::default());
+    server.register_handler(AwaitTreeDebugHandler::default());
     server.register_handler(AppsHandler::default());
     server.register_handler(HistoricalAppsHandler::default());
     server.register_handler(AdminHandler::default());
+    server.register_handler(FaultsHandler::default());
+    server.register_handler(ActivityHandler::default());
+    server.register_handler(TimelineHandler::default());
+    server.register_handler(AppSummaryHandler::default());
+    server.register_handler(RuntimesHandler::default());
+    server.register_handler(SubsystemsHandler::default());
+    server.register_handler(SubsystemControlHandler::default());
 
     Box::new(server)
 }
@@ -0,0 +1,22 @@
+use crate::http::Handler;
+use crate::runtime::analyzer::RUNTIME_SIZING_REPORT;
+use poem::error::InternalServerError;
+use poem::{handler, RouteMethod};
+
+#[derive(Default)]
+pub struct RuntimesHandler;
+
+impl Handler for RuntimesHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(runtimes_handler)
+    }
+
+    fn get_route_path(&self) -> String {
+        "/debug/runtimes".to_string()
+    }
+}
+
+#[handler]
+fn runtimes_handler() -> poem::Result<String> {
+    serde_json::to_string(&RUNTIME_SIZING_REPORT.get()).map_err(InternalServerError)
+}
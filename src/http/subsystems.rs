@@ -0,0 +1,208 @@
+use crate::http::Handler;
+use crate::subsystem::{SubsystemAction, SubsystemState, SUBSYSTEM_MANAGER};
+use anyhow::anyhow;
+use poem::error::{BadRequest, InternalServerError};
+use poem::web::{Json, Path};
+use poem::{handler, RouteMethod};
+
+#[derive(Default)]
+pub struct SubsystemsHandler;
+
+impl Handler for SubsystemsHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(list_subsystems)
+    }
+
+    /// GET to list the states of all the controllable subsystems
+    fn get_route_path(&self) -> String {
+        "/admin/subsystems".to_string()
+    }
+}
+
+#[derive(Default)]
+pub struct SubsystemControlHandler;
+
+impl Handler for SubsystemControlHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().post(control_subsystem)
+    }
+
+    /// POST with /admin/subsystems/urpc_listener/restart, the action is one of start/stop/restart
+    fn get_route_path(&self) -> String {
+        "/admin/subsystems/:name/:action".to_string()
+    }
+}
+
+fn parse_action(action: &str) -> anyhow::Result<SubsystemAction> {
+    match action.to_uppercase().as_str() {
+        "START" => Ok(SubsystemAction::START),
+        "STOP" => Ok(SubsystemAction::STOP),
+        "RESTART" => Ok(SubsystemAction::RESTART),
+        _ => Err(anyhow!("Unknown subsystem action: {}", action)),
+    }
+}
+
+#[handler]
+fn list_subsystems() -> Json<Vec<SubsystemState>> {
+    Json(SUBSYSTEM_MANAGER.states())
+}
+
+#[handler]
+async fn control_subsystem(
+    Path((name, action)): Path<(String, String)>,
+) -> poem::Result<Json<SubsystemState>> {
+    let action = parse_action(&action).map_err(BadRequest)?;
+    let state = SUBSYSTEM_MANAGER
+        .apply(&name, action)
+        .await
+        .map_err(InternalServerError)?;
+    Ok(Json(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::test::mock_config;
+    use crate::app::AppManager;
+    use crate::config_reconfigure::ReconfigurableConfManager;
+    use crate::http::subsystems::{SubsystemControlHandler, SubsystemsHandler};
+    use crate::http::Handler;
+    use crate::rpc::start_urpc_listeners;
+    use crate::runtime::manager::RuntimeManager;
+    use crate::storage::StorageService;
+    use crate::subsystem::{SubsystemTask, SUBSYSTEM_MANAGER};
+    use crate::urpc::server::UrpcServerOptions;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::net::TcpStream;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_router() -> anyhow::Result<()> {
+        let alive = Arc::new(AtomicBool::new(false));
+        let alive_ref = alive.clone();
+        SUBSYSTEM_MANAGER.register(
+            "http_mock_subsystem",
+            Arc::new(move |mut rx| {
+                alive_ref.store(true, SeqCst);
+                let alive_ref = alive_ref.clone();
+                let task = std::thread::spawn(move || {
+                    let _ = rx.blocking_recv();
+                    alive_ref.store(false, SeqCst);
+                });
+                Ok(vec![SubsystemTask::Thread(task)])
+            }),
+        )?;
+
+        let list_handler = SubsystemsHandler::default();
+        let control_handler = SubsystemControlHandler::default();
+        let app = Route::new()
+            .at(
+                list_handler.get_route_path(),
+                list_handler.get_route_method(),
+            )
+            .at(
+                control_handler.get_route_path(),
+                control_handler.get_route_method(),
+            );
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/admin/subsystems").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await?;
+        assert!(body.contains("http_mock_subsystem"));
+
+        let resp = cli
+            .post("/admin/subsystems/http_mock_subsystem/stop")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        // the stopping returns after the subsystem task exits
+        assert!(!alive.load(SeqCst));
+
+        let resp = cli
+            .post("/admin/subsystems/http_mock_subsystem/RESTART")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await?;
+        assert!(body.contains("\"running\":true"));
+        assert!(body.contains("\"restart_count\":1"));
+        assert!(alive.load(SeqCst));
+
+        // the unknown action and subsystem are rejected
+        let resp = cli
+            .post("/admin/subsystems/http_mock_subsystem/pause")
+            .send()
+            .await;
+        resp.assert_status(poem::http::StatusCode::BAD_REQUEST);
+        let resp = cli.post("/admin/subsystems/store/stop").send().await;
+        resp.assert_status(poem::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_urpc_listener_stop_and_restart() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let options = UrpcServerOptions {
+            max_connections: 10,
+            idle_timeout: Duration::from_secs(600),
+            shutdown_grace_period: Duration::from_secs(1),
+            read_coalesce: None,
+            accept_max_backoff: Duration::from_secs(64),
+            listener_max_restarts: 10,
+            listener_restart_backoff: Duration::from_secs(5),
+            listener_healthy_reset: Duration::from_secs(600),
+        };
+        let (process_tx, _) = broadcast::channel(1);
+        SUBSYSTEM_MANAGER.register(
+            "http_mock_urpc_listener",
+            Arc::new(move |subsystem_rx| {
+                start_urpc_listeners(
+                    addr,
+                    options,
+                    process_tx.subscribe(),
+                    subsystem_rx,
+                    app_manager_ref.clone(),
+                )
+            }),
+        )?;
+        assert!(TcpStream::connect(addr).is_ok());
+
+        let control_handler = SubsystemControlHandler::default();
+        let cli = TestClient::new(Route::new().at(
+            control_handler.get_route_path(),
+            control_handler.get_route_method(),
+        ));
+
+        // the listeners are closed once the stopping returns
+        let resp = runtime_manager.wait(
+            cli.post("/admin/subsystems/http_mock_urpc_listener/stop")
+                .send(),
+        );
+        resp.assert_status_is_ok();
+        assert!(TcpStream::connect(addr).is_err());
+
+        let resp = runtime_manager.wait(
+            cli.post("/admin/subsystems/http_mock_urpc_listener/restart")
+                .send(),
+        );
+        resp.assert_status_is_ok();
+        let body = runtime_manager.wait(resp.0.into_body().into_string())?;
+        assert!(body.contains("\"running\":true"));
+        assert!(TcpStream::connect(addr).is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use crate::http::Handler;
+use crate::timeline::{TimelineCategory, TIMELINE};
+use poem::error::{BadRequest, InternalServerError};
+use poem::{handler, Request, RouteMethod};
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Default)]
+pub struct TimelineHandler;
+
+impl Handler for TimelineHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(timeline_handler)
+    }
+
+    /// request with /debug/timeline?start_sec=xxx&end_sec=xxx&categories=PURGE,DISK_HEALTH
+    /// and all the params are optional
+    fn get_route_path(&self) -> String {
+        "/debug/timeline".to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct TimelineParam {
+    start_sec: Option<u64>,
+    end_sec: Option<u64>,
+    categories: Option<String>,
+}
+
+#[handler]
+fn timeline_handler(req: &Request) -> poem::Result<String> {
+    let params = req.params::<TimelineParam>()?;
+    let categories = match params.categories.as_ref() {
+        Some(raw) => raw
+            .split(',')
+            .filter(|category| !category.is_empty())
+            .map(|category| TimelineCategory::from_str(category.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BadRequest)?,
+        _ => vec![],
+    };
+    let events = TIMELINE.query(params.start_sec, params.end_sec, &categories);
+    serde_json::to_string(&events).map_err(InternalServerError)
+}
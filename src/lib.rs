@@ -18,7 +18,9 @@
 #![allow(dead_code, unused)]
 #![feature(impl_trait_in_assoc_type)]
 
+pub mod activity;
 pub mod app;
+pub mod app_meta_snapshot;
 pub mod await_tree;
 pub mod common;
 mod composed_bytes;
@@ -32,6 +34,7 @@ pub mod log_service;
 mod mem_allocator;
 pub mod metric;
 pub mod readable_size;
+pub mod replication;
 pub mod rpc;
 pub mod runtime;
 pub mod signal;
@@ -46,9 +49,11 @@ mod kerberos;
 mod reject;
 pub mod semaphore_with_index;
 pub mod storage;
+pub mod timeline;
 
 pub mod bits;
 pub mod block_id_manager;
+pub mod chaos;
 pub mod histogram;
 pub mod id_layout;
 pub mod lazy_initializer;
@@ -59,6 +64,7 @@ pub mod decommission;
 pub mod disk_explorer;
 pub mod historical_apps;
 pub mod panic_hook;
+pub mod subsystem;
 
 use crate::app::{AppManager, AppManagerRef};
 use crate::common::init_global_variable;
@@ -70,6 +76,7 @@ use crate::grpc::protobuf::uniffle::{
     GetShuffleResultRequest, PartitionToBlockIds, ReportShuffleResultRequest, RequireBufferRequest,
     SendShuffleDataRequest, ShuffleBlock, ShuffleData, ShuffleRegisterRequest,
 };
+use crate::health_service::HealthService;
 use crate::http::{HTTPServer, HttpMonitorService};
 use crate::id_layout::{IdLayout, DEFAULT_BLOCK_ID_LAYOUT};
 use crate::metric::MetricService;
@@ -106,12 +113,15 @@ pub async fn start_uniffle_worker(config: config::Config) -> Result<AppManagerRe
     let app_manager_ref_cloned = app_manager_ref.clone();
     let rm_cloned = runtime_manager.clone();
     let decommission_manager = DecommissionManager::new(&app_manager_ref);
+    let health_service =
+        HealthService::new(&app_manager_ref, &storage, &config.health_service_config);
     runtime_manager.default_runtime.spawn(async move {
         DefaultRpcService {}.start(
             &config,
             rm_cloned,
             app_manager_ref_cloned,
             &decommission_manager,
+            &health_service,
         )
     });
 
@@ -139,6 +149,8 @@ pub async fn write_read_for_one_time(mut client: ShuffleServerClient<Channel>) -
             user: "".to_string(),
             shuffle_data_distribution: 1,
             max_concurrency_per_partition_to_write: 10,
+            consistency_check_enabled: false,
+            expected_total_bytes: 0,
         })
         .await?
         .into_inner();
@@ -184,6 +196,7 @@ pub async fn write_read_for_one_time(mut client: ShuffleServerClient<Channel>) -
                         data: Bytes::copy_from_slice(data),
                         task_attempt_id: 0,
                     }],
+                    crc_aggregate: None,
                 }],
                 timestamp: 0,
                 stage_attempt_number: 0,
@@ -205,6 +218,7 @@ pub async fn write_read_for_one_time(mut client: ShuffleServerClient<Channel>) -
                 partition_to_block_ids: vec![PartitionToBlockIds {
                     partition_id: idx,
                     block_ids: vec![block_id],
+                    expected_total_count: None,
                 }],
             })
             .await?;
@@ -248,6 +262,7 @@ pub async fn write_read_for_one_time(mut client: ShuffleServerClient<Channel>) -
                 read_buffer_size: 10000000,
                 timestamp: 0,
                 serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
             })
             .await?;
         let response = response_data.into_inner();
@@ -268,6 +283,8 @@ pub async fn write_read_for_one_time(mut client: ShuffleServerClient<Channel>) -
                 partition_id: idx,
                 partition_num_per_range: 1,
                 partition_num: 0,
+                start_entry: 0,
+                max_entries: None,
             })
             .await?;
 
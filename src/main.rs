@@ -18,7 +18,9 @@
 #![allow(dead_code, unused)]
 #![feature(impl_trait_in_assoc_type)]
 
+use crate::activity::ACTIVITY_LOG;
 use crate::app::{AppManager, APP_MANAGER_REF};
+use crate::chaos::FAULT_INJECTOR;
 use crate::common::init_global_variable;
 use crate::config::Config;
 use crate::health_service::HealthService;
@@ -38,8 +40,10 @@ use crate::metric::MetricService;
 use crate::panic_hook::set_panic_hook;
 use crate::readable_size::ReadableSize;
 use crate::rpc::DefaultRpcService;
+use crate::runtime::analyzer::{RuntimeSizingReport, RUNTIME_SIZING_REPORT};
 use crate::runtime::manager::RuntimeManager;
 use crate::storage::StorageService;
+use crate::timeline::TIMELINE;
 use crate::tracing::FastraceWrapper;
 use anyhow::Result;
 use clap::builder::Str;
@@ -49,7 +53,9 @@ use std::str::FromStr;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+pub mod activity;
 pub mod app;
+pub mod app_meta_snapshot;
 mod await_tree;
 pub mod common;
 pub mod composed_bytes;
@@ -75,11 +81,13 @@ mod logforth_service;
 
 pub mod bits;
 pub mod block_id_manager;
+pub mod chaos;
 pub mod histogram;
 mod mem_allocator;
 mod metric;
 mod readable_size;
 pub mod reject;
+pub mod replication;
 pub mod rpc;
 pub mod runtime;
 pub mod semaphore_with_index;
@@ -97,6 +105,8 @@ pub mod historical_apps;
 
 pub mod config_reconfigure;
 pub mod panic_hook;
+pub mod subsystem;
+pub mod timeline;
 
 const MAX_MEMORY_ALLOCATION_SIZE_ENV_KEY: &str = "MAX_MEMORY_ALLOCATION_LIMIT_SIZE";
 
@@ -112,6 +122,7 @@ fn main() -> Result<()> {
 
     let args = Args::parse();
     let config = Config::from(&args.config);
+    config.validate()?;
 
     #[cfg(not(feature = "logforth"))]
     let _guard = LogService::init(&config.log);
@@ -131,9 +142,14 @@ fn main() -> Result<()> {
     set_panic_hook();
 
     init_global_variable(&config);
+    FAULT_INJECTOR.enable(config.fault_injection_enable);
 
     info!("The specified config show as follows: \n {:#?}", config);
 
+    let runtime_sizing_report = RuntimeSizingReport::analyze(&config);
+    runtime_sizing_report.check()?;
+    let _ = RUNTIME_SIZING_REPORT.set(runtime_sizing_report);
+
     let runtime_manager = RuntimeManager::from(config.runtime_config.clone());
 
     // init the reconfigurableConfManager
@@ -160,6 +176,16 @@ fn main() -> Result<()> {
     let _ = DECOMMISSION_MANAGER_REF.set(decommission_manager.clone());
 
     MetricService::init(&config, runtime_manager.clone());
+    ACTIVITY_LOG.start(
+        config.activity_log_path.clone(),
+        &runtime_manager,
+        &app_manager_ref,
+    );
+    TIMELINE.start(
+        config.timeline_path.clone(),
+        config.timeline_purge_size_threshold.clone(),
+        &runtime_manager,
+    );
     FastraceWrapper::init(config.clone());
     HeartbeatTask::run(
         &config,
@@ -175,6 +201,7 @@ fn main() -> Result<()> {
         runtime_manager,
         app_manager_ref,
         &decommission_manager,
+        &health_service,
     )?;
 
     Ok(())
@@ -23,9 +23,11 @@ use crate::mem_allocator::ALLOCATOR;
 use crate::panic_hook::PANIC_TAG;
 use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
+use crate::subsystem::{SubsystemTask, METRICS_REPORTER, SUBSYSTEM_MANAGER};
 use await_tree::InstrumentAwait;
 use log::{error, info};
 use once_cell::sync::Lazy;
+use prometheus::core::Collector;
 use prometheus::{
     histogram_opts, labels, register_gauge_vec, register_histogram_vec,
     register_histogram_vec_with_registry, register_int_counter_vec, register_int_gauge,
@@ -33,7 +35,9 @@ use prometheus::{
     IntCounterVec, IntGauge, IntGaugeVec, Registry,
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::Receiver;
 
 const DEFAULT_BUCKETS: &[f64] = &[
     0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 60.0, 80.0,
@@ -61,6 +65,19 @@ pub static BLOCK_ID_NUMBER: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new("block_id_number", "block_id_number").expect("metric should be created")
 });
 
+pub static GAUGE_DUPLICATE_BLOCK_REPORTS: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new("duplicate_block_reports", "duplicate_block_reports")
+        .expect("metric should be created")
+});
+
+pub static TOTAL_REPORTED_BLOCK_ID_COUNT_MISMATCH: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_reported_block_id_count_mismatch",
+        "total_reported_block_id_count_mismatch",
+    )
+    .expect("metric should be created")
+});
+
 pub static ALIGNMENT_BUFFER_POOL_READ_ACQUIRE_MISS: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "alignment_buffer_pool_read_acquire_miss",
@@ -107,6 +124,14 @@ pub static TOTAL_READ_DATA_FROM_MEMORY: Lazy<IntCounter> = Lazy::new(|| {
         .expect("metric should be created")
 });
 
+pub static TOTAL_READ_DEADLINE_EXCEEDED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_read_deadline_exceeded",
+        "Reading given up by the exceeded deadline",
+    )
+    .expect("metric should be created")
+});
+
 pub static TOTAL_READ_DATA_FROM_LOCALFILE: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_read_data_from_localfile",
@@ -143,6 +168,14 @@ pub static GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES: Lazy<IntGauge> = Lazy::new(|| {
     .expect("")
 });
 
+pub static GAUGE_MEMORY_BUFFER_SLACK_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "memory_buffer_slack_bytes",
+        "the allocated but unused capacity bytes of memory buffers",
+    )
+    .expect("")
+});
+
 pub static GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_OF_HUGE_PARTITION: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new(
         "memory_spill_in_flight_bytes_of_huge_partition",
@@ -287,6 +320,15 @@ pub static LOCALFILE_DISK_DELETE_OPERATION_DURATION: Lazy<HistogramVec> = Lazy::
     opts
 });
 
+pub static TOTAL_INJECTED_FAULTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_injected_faults",
+        "total injected faults",
+        &["operation"]
+    )
+    .unwrap()
+});
+
 pub static TOTAL_LOCAL_DISK_APPEND_OPERATION_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
         "localfile_disk_append_operation_counter",
@@ -345,6 +387,28 @@ pub static URPC_REQUEST_PARSING_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
     urpc_latency
 });
 
+pub static URPC_COMMAND_PROCESSING_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    let opts = histogram_opts!(
+        "urpc_command_processing_latency",
+        "uRPC command processing latency",
+        Vec::from(DEFAULT_BUCKETS as &'static [f64])
+    );
+    register_histogram_vec_with_registry!(opts, &["command"], REGISTRY).unwrap()
+});
+
+pub static URPC_COMMAND_IN_FLIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "urpc_command_in_flight",
+        "urpc_command_in_flight",
+        &["command"]
+    )
+    .unwrap()
+});
+
+pub static URPC_COMMAND_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!("urpc_command_errors", "urpc_command_errors", &["command"]).unwrap()
+});
+
 pub static URPC_SEND_DATA_TRANSPORT_TIME: Lazy<Histogram> = Lazy::new(|| {
     let opts = HistogramOpts::new("urpc_send_data_transport_time", "none")
         .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
@@ -364,10 +428,48 @@ pub static URPC_GET_LOCALFILE_DATA_TRANSPORT_TIME: Lazy<Histogram> = Lazy::new(|
 pub static URPC_CONNECTION_NUMBER: Lazy<IntGauge> =
     Lazy::new(|| IntGauge::new("urpc_connection_number", "urpc_connection_number").expect(""));
 
+pub static URPC_CONNECTIONS_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new("urpc_connections_in_flight", "urpc_connections_in_flight").expect("")
+});
+
+pub static URPC_CONNECTION_IDLE_TIMEOUT_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "urpc_connection_idle_timeout_count",
+        "urpc_connection_idle_timeout_count",
+    )
+    .expect("metric should be created")
+});
+
+pub static URPC_LISTENER_FAILURE_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new("urpc_listener_failure_count", "urpc_listener_failure_count")
+        .expect("metric should be created")
+});
+
+pub static URPC_LISTENER_RESTART_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new("urpc_listener_restart_count", "urpc_listener_restart_count")
+        .expect("metric should be created")
+});
+
+pub static URPC_COALESCED_READ_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "urpc_coalesced_read_count",
+        "the tiny local reads served from the merged read of the connection",
+    )
+    .expect("metric should be created")
+});
+
 pub static PURGE_FAILED_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("purge_failed_count", "purge_failed_count").expect("metric should be created")
 });
 
+pub static TOTAL_EXPIRED_SPILLED_FILE_RECLAIMED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_expired_spilled_file_reclaimed_bytes",
+        "total_expired_spilled_file_reclaimed_bytes",
+    )
+    .expect("metric should be created")
+});
+
 pub static DEADLOCK_SIGNAL: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new("deadlock_signal", "deadlock_signal").expect("metric should be created")
 });
@@ -386,6 +488,78 @@ pub static TOTAL_LOCALFILE_USED: Lazy<IntCounter> = Lazy::new(|| {
         .expect("metric should be created")
 });
 
+pub static TOTAL_LOCALFILE_DEDUP_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_dedup_bytes",
+        "Total localfile bytes saved by the spill dedup",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_COMPRESSION_SAVED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compression_saved_bytes",
+        "Total localfile bytes saved by the spill chunk compression",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_decompressed_chunks",
+        "Total compressed chunks decompressed by the localfile reads",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compactions",
+        "Total localfile partitions rewritten by the compaction",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTION_ABORTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compaction_aborted",
+        "Total localfile compactions aborted by the concurrent purge",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTION_RECLAIMED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compaction_reclaimed_bytes",
+        "Total localfile data and index bytes reclaimed by the compaction",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_INDEX_TRUNCATED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_index_truncated",
+        "Total index views truncated to the consistent entries of data file",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_LOCALFILE_DATA_CRC_MISMATCH: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_data_crc_mismatch",
+        "Total crc mismatched blocks read from localfile",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_MEMORY_DATA_CRC_MISMATCH: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_memory_data_crc_mismatch",
+        "Total crc mismatched blocks read from memory",
+    )
+    .expect("metric should be created")
+});
+
 pub static TOTAL_HDFS_USED: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("total_hdfs_used", "Total hdfs used").expect("metric should be created")
 });
@@ -477,6 +651,34 @@ pub static TOTAL_REQUIRE_BUFFER_FAILED: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("total_require_buffer_failed", "total_require_buffer_failed")
         .expect("metrics should be created")
 });
+pub static TOTAL_APP_WRITE_RATE_LIMITED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_app_write_rate_limited_bytes",
+        "total_app_write_rate_limited_bytes",
+    )
+    .expect("metrics should be created")
+});
+pub static TOTAL_APP_QUOTA_SOFT_LIMIT_EXCEEDED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_app_quota_soft_limit_exceeded",
+        "total_app_quota_soft_limit_exceeded",
+    )
+    .expect("metrics should be created")
+});
+pub static TOTAL_APP_QUOTA_HARD_LIMIT_REJECTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_app_quota_hard_limit_rejected",
+        "total_app_quota_hard_limit_rejected",
+    )
+    .expect("metrics should be created")
+});
+pub static TOTAL_WRITE_EXCEEDS_RESERVATION: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_write_exceeds_reservation",
+        "total_write_exceeds_reservation",
+    )
+    .expect("metrics should be created")
+});
 pub static TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_huge_partition_require_buffer_failed",
@@ -485,6 +687,30 @@ pub static TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED: Lazy<IntCounter> = Lazy::
     .expect("metrics should be created")
 });
 
+pub static GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "localfile_read_ahead_cache_bytes",
+        "the cached bytes of the localfile read-ahead",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_READ_AHEAD_HIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_read_ahead_hit",
+        "total localfile reads served by the read-ahead cache",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_READ_AHEAD_MISS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_read_ahead_miss",
+        "total localfile reads missing the read-ahead cache",
+    )
+    .expect("")
+});
+
 pub static GAUGE_LOCAL_DISK_CAPACITY: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "local_disk_capacity",
@@ -503,6 +729,15 @@ pub static GAUGE_LOCAL_DISK_SERVICE_USED: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "local_disk_evacuation_remaining_bytes",
+        "the remaining bytes to be evacuated from the failing disk",
+        &["root"]
+    )
+    .unwrap()
+});
+
 pub static GAUGE_LOCAL_DISK_SERVICE_USED_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
     register_gauge_vec!(
         "local_disk_service_used_ratio",
@@ -563,6 +798,15 @@ pub static GAUGE_RUNTIME_IDLE_THREAD_NUM: Lazy<IntGaugeVec> = Lazy::new(|| {
 pub static RESIDENT_BYTES: Lazy<IntGauge> =
     Lazy::new(|| IntGauge::new("resident_bytes", "resident_bytes").unwrap());
 
+pub static GAUGE_RESIDENT_BYTES_BY_TIER: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "resident_bytes_by_tier",
+        "resident bytes of every storage tier",
+        &["tier"]
+    )
+    .unwrap()
+});
+
 pub static GAUGE_TOPN_APP_RESIDENT_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "topN_app_resident_bytes",
@@ -572,6 +816,15 @@ pub static GAUGE_TOPN_APP_RESIDENT_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static GAUGE_APP_EXPECTED_SIZE_PROGRESS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "app_expected_size_progress",
+        "the ratio of received bytes to the expected total bytes of app",
+        &["app_id"]
+    )
+    .unwrap()
+});
+
 pub static TOTAL_APP_FLUSHED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
         "app_flushed_bytes",
@@ -626,6 +879,46 @@ pub static GAUGE_GRPC_REQUEST_QUEUE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new("grpc_request_number", "current service request queue size").unwrap()
 });
 
+pub static REPLICATION_LAG_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "replication_lag_bytes",
+        "the bytes accepted but not acknowledged by the replication peer",
+    )
+    .expect("")
+});
+
+pub static TOTAL_REPLICATED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_replicated_bytes",
+        "total bytes acknowledged by the replication peer",
+    )
+    .expect("")
+});
+
+pub static TOTAL_REPLICATION_DROPPED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_replication_dropped_bytes",
+        "total bytes dropped without being replicated",
+    )
+    .expect("")
+});
+
+pub static TOTAL_SPILL_DATA_DROPPED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_spill_data_dropped_bytes",
+        "total bytes of the dropped spill events without the dead letter",
+    )
+    .expect("")
+});
+
+pub static TOTAL_SPILL_EVENTS_DEAD_LETTERED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_spill_events_dead_lettered",
+        "total spill events written into the dead letter dir",
+    )
+    .expect("")
+});
+
 pub static TOTAL_SPILL_EVENTS_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_spill_events_dropped",
@@ -634,6 +927,27 @@ pub static TOTAL_SPILL_EVENTS_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
     .expect("")
 });
 
+pub static TOTAL_PARTITIONS_BEYOND_APP_LIMIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_partitions_beyond_app_limit",
+        "total number of the new partitions beyond the per-app partition limit",
+    )
+    .expect("metric should be created")
+});
+
+pub static TOTAL_SPILLED_READ_RETRY: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new("total_spilled_read_retry", "total_spilled_read_retry")
+        .expect("metric should be created")
+});
+
+pub static TOTAL_SPILL_EVENTS_CANCELLED_BY_PURGE: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_spill_events_cancelled_by_purge",
+        "total pending spill events cancelled by the app or shuffle purge",
+    )
+    .expect("")
+});
+
 pub static TOTAL_SPILL_EVENTS_DROPPED_WITH_APP_NOT_FOUND: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_spill_events_dropped_with_app_not_found",
@@ -642,6 +956,38 @@ pub static TOTAL_SPILL_EVENTS_DROPPED_WITH_APP_NOT_FOUND: Lazy<IntCounter> = Laz
     .expect("")
 });
 
+pub static TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_huge_partition_direct_cold_spill",
+        "total huge partition spill number directly to cold store without watermark",
+    )
+    .expect("")
+});
+
+pub static TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_huge_partition_direct_cold_spill_bytes",
+        "total huge partition spill bytes directly to cold store without watermark",
+    )
+    .expect("")
+});
+
+pub static TOTAL_WATERMARK_SPILL_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_watermark_spill_bytes",
+        "total memory spill bytes triggered by the watermark",
+    )
+    .expect("")
+});
+
+pub static TOTAL_SPILL_BUFFER_RELEASE_FAILED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_spill_buffer_release_failed",
+        "total spill buffer release failed number of the existing app",
+    )
+    .expect("")
+});
+
 pub static TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_localfile_detected_in_consistency",
@@ -650,6 +996,43 @@ pub static TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY: Lazy<IntCounter> = Lazy::new
     .expect("")
 });
 
+// the bytes written into the local disks / the received bytes
+pub static WRITE_AMPLIFICATION: Lazy<Gauge> = Lazy::new(|| {
+    Gauge::new("write_amplification", "write_amplification").expect("metric should be created")
+});
+
+// the bytes read from the local disks / the served bytes
+pub static READ_AMPLIFICATION: Lazy<Gauge> = Lazy::new(|| {
+    Gauge::new("read_amplification", "read_amplification").expect("metric should be created")
+});
+
+/// The ratio of the disk operated bytes of all the roots to the bytes at the service
+/// side, which is 0 if nothing has been served.
+fn amplification(disk_bytes: &IntCounterVec, served_bytes: &IntCounter) -> f64 {
+    let served = served_bytes.get();
+    if served == 0 {
+        return 0.0;
+    }
+    let disk: u64 = disk_bytes
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .map(|metric| metric.get_counter().get_value() as u64)
+        .sum();
+    disk as f64 / served as f64
+}
+
+pub fn refresh_amplification() {
+    WRITE_AMPLIFICATION.set(amplification(
+        &TOTAL_LOCAL_DISK_APPEND_OPERATION_BYTES_COUNTER,
+        &TOTAL_RECEIVED_DATA,
+    ));
+    READ_AMPLIFICATION.set(amplification(
+        &TOTAL_LOCAL_DISK_READ_OPERATION_BYTES_COUNTER,
+        &TOTAL_READ_DATA,
+    ));
+}
+
 // total timeout tickets
 pub static TOTAL_EVICT_TIMEOUT_TICKETS_NUM: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
@@ -748,9 +1131,18 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(BLOCK_ID_NUMBER.clone()))
         .expect("block_id_number must be registered");
+    REGISTRY
+        .register(Box::new(GAUGE_DUPLICATE_BLOCK_REPORTS.clone()))
+        .expect("duplicate_block_reports must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_REPORTED_BLOCK_ID_COUNT_MISMATCH.clone()))
+        .expect("total_reported_block_id_count_mismatch must be registered");
     REGISTRY
         .register(Box::new(PURGE_FAILED_COUNTER.clone()))
         .expect("purge_failed_count must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_EXPIRED_SPILLED_FILE_RECLAIMED_BYTES.clone()))
+        .expect("total_expired_spilled_file_reclaimed_bytes must be registered");
 
     REGISTRY
         .register(Box::new(ALIGNMENT_BUFFER_POOL_ACQUIRED_MISS.clone()))
@@ -793,6 +1185,9 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES.clone()))
         .expect("");
+    REGISTRY
+        .register(Box::new(GAUGE_MEMORY_BUFFER_SLACK_BYTES.clone()))
+        .expect("");
     REGISTRY
         .register(Box::new(
             GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_OF_HUGE_PARTITION.clone(),
@@ -837,6 +1232,21 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(TOTAL_SPILL_EVENTS_DROPPED.clone()))
         .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_SPILL_DATA_DROPPED_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(REPLICATION_LAG_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_REPLICATED_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_REPLICATION_DROPPED_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_SPILL_EVENTS_DEAD_LETTERED.clone()))
+        .expect("");
 
     REGISTRY
         .register(Box::new(
@@ -844,9 +1254,50 @@ fn register_custom_metrics() {
         ))
         .expect("");
 
+    REGISTRY
+        .register(Box::new(TOTAL_SPILLED_READ_RETRY.clone()))
+        .expect("total_spilled_read_retry must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_PARTITIONS_BEYOND_APP_LIMIT.clone()))
+        .expect("total_partitions_beyond_app_limit must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_SPILL_EVENTS_CANCELLED_BY_PURGE.clone()))
+        .expect("");
+
+    REGISTRY
+        .register(Box::new(TOTAL_SPILL_BUFFER_RELEASE_FAILED.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(
+            TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL_BYTES.clone(),
+        ))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_WATERMARK_SPILL_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_READ_AHEAD_HIT.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_READ_AHEAD_MISS.clone()))
+        .expect("");
+
     REGISTRY
         .register(Box::new(GAUGE_TOPN_APP_RESIDENT_BYTES.clone()))
         .expect("");
+    REGISTRY
+        .register(Box::new(GAUGE_RESIDENT_BYTES_BY_TIER.clone()))
+        .expect("resident_bytes_by_tier must be registered");
+
+    REGISTRY
+        .register(Box::new(GAUGE_APP_EXPECTED_SIZE_PROGRESS.clone()))
+        .expect("");
 
     REGISTRY
         .register(Box::new(TOTAL_APP_FLUSHED_BYTES.clone()))
@@ -864,6 +1315,10 @@ fn register_custom_metrics() {
         .register(Box::new(TOTAL_READ_DATA_FROM_MEMORY.clone()))
         .expect("total_read_data must be registered");
 
+    REGISTRY
+        .register(Box::new(TOTAL_READ_DEADLINE_EXCEEDED.clone()))
+        .expect("total_read_deadline_exceeded must be registered");
+
     REGISTRY
         .register(Box::new(GAUGE_LOCAL_DISK_CAPACITY.clone()))
         .expect("");
@@ -880,6 +1335,12 @@ fn register_custom_metrics() {
         .register(Box::new(GAUGE_LOCAL_DISK_SERVICE_USED.clone()))
         .expect("");
 
+    REGISTRY
+        .register(Box::new(
+            GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES.clone(),
+        ))
+        .expect("");
+
     REGISTRY
         .register(Box::new(GAUGE_LOCAL_DISK_SERVICE_USED_RATIO.clone()))
         .expect("");
@@ -908,6 +1369,33 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(TOTAL_LOCALFILE_USED.clone()))
         .expect("total_localfile_used must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_DEDUP_BYTES.clone()))
+        .expect("total_localfile_dedup_bytes must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_COMPRESSION_SAVED_BYTES.clone()))
+        .expect("total_localfile_compression_saved_bytes must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.clone()))
+        .expect("total_localfile_decompressed_chunks must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_COMPACTIONS.clone()))
+        .expect("total_localfile_compactions must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_COMPACTION_ABORTED.clone()))
+        .expect("total_localfile_compaction_aborted must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_COMPACTION_RECLAIMED_BYTES.clone()))
+        .expect("total_localfile_compaction_reclaimed_bytes must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_DATA_CRC_MISMATCH.clone()))
+        .expect("total_localfile_data_crc_mismatch must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_MEMORY_DATA_CRC_MISMATCH.clone()))
+        .expect("total_memory_data_crc_mismatch must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_LOCALFILE_INDEX_TRUNCATED.clone()))
+        .expect("total_localfile_index_truncated must be registered");
     REGISTRY
         .register(Box::new(TOTAL_HDFS_USED.clone()))
         .expect("total_hdfs_used must be registered");
@@ -936,6 +1424,18 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(TOTAL_REQUIRE_BUFFER_FAILED.clone()))
         .expect("total_require_buffer_failed must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_APP_WRITE_RATE_LIMITED_BYTES.clone()))
+        .expect("total_app_write_rate_limited_bytes must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_APP_QUOTA_SOFT_LIMIT_EXCEEDED.clone()))
+        .expect("total_app_quota_soft_limit_exceeded must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_APP_QUOTA_HARD_LIMIT_REJECTED.clone()))
+        .expect("total_app_quota_hard_limit_rejected must be registered");
+    REGISTRY
+        .register(Box::new(TOTAL_WRITE_EXCEEDS_RESERVATION.clone()))
+        .expect("total_write_exceeds_reservation must be registered");
     REGISTRY
         .register(Box::new(TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED.clone()))
         .expect("total_huge_partition_require_buffer_failed must be registered");
@@ -1006,12 +1506,39 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(URPC_CONNECTION_NUMBER.clone()))
         .expect("");
+    REGISTRY
+        .register(Box::new(URPC_CONNECTION_IDLE_TIMEOUT_COUNTER.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_LISTENER_FAILURE_COUNTER.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_LISTENER_RESTART_COUNTER.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_COALESCED_READ_COUNTER.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_CONNECTIONS_IN_FLIGHT.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_COMMAND_IN_FLIGHT.clone()))
+        .expect("");
+    REGISTRY
+        .register(Box::new(URPC_COMMAND_ERRORS.clone()))
+        .expect("");
     REGISTRY
         .register(Box::new(TOTAL_EVICT_TIMEOUT_TICKETS_NUM.clone()))
         .expect("");
     REGISTRY
         .register(Box::new(TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY.clone()))
         .expect("");
+    REGISTRY
+        .register(Box::new(WRITE_AMPLIFICATION.clone()))
+        .expect("write_amplification must be registered");
+    REGISTRY
+        .register(Box::new(READ_AMPLIFICATION.clone()))
+        .expect("read_amplification must be registered");
 }
 
 const JOB_NAME: &str = "uniffle-worker";
@@ -1030,12 +1557,28 @@ impl MetricService {
 
         let cfg = config.metrics.clone().unwrap();
 
-        let push_gateway_endpoint = cfg.push_gateway_endpoint;
-        if let Some(ref _endpoint) = push_gateway_endpoint {
-            let push_interval_sec = cfg.push_interval_sec;
+        if let Some(interval_sec) = cfg.amplification_refresh_interval_sec {
             runtime_manager.default_runtime.spawn_with_await_tree(
-                "Metric prometheus reporter",
+                "Metric amplification refresher",
                 async move {
+                    info!("Starting the read/write amplification refresher...");
+                    loop {
+                        refresh_amplification();
+                        tokio::time::sleep(Duration::from_secs(interval_sec))
+                            .instrument_await("sleeping")
+                            .await;
+                    }
+                },
+            );
+        }
+
+        let push_gateway_endpoint = cfg.push_gateway_endpoint.clone();
+        if let Some(ref _endpoint) = push_gateway_endpoint {
+            let push_interval_sec = cfg.push_interval_sec;
+            let starter = move |mut shutdown: Receiver<()>| {
+                let cfg = cfg.clone();
+                let push_gateway_endpoint = push_gateway_endpoint.clone();
+                let task = async move {
                     info!("Starting prometheus metrics exporter...");
                     loop {
                         tokio::time::sleep(Duration::from_secs(push_interval_sec as u64))
@@ -1078,8 +1621,46 @@ impl MetricService {
                             error!("Errors on pushing metrics. {:?}", pushed_result.err());
                         }
                     }
-                },
-            );
+                };
+                let handle = runtime_manager.default_runtime.spawn_with_await_tree(
+                    "Metric prometheus reporter",
+                    async move {
+                        tokio::select! {
+                            _ = task => {}
+                            _ = shutdown.recv() => {
+                                info!("The prometheus metrics exporter has been stopped");
+                            }
+                        }
+                    },
+                );
+                Ok(vec![SubsystemTask::Async(handle)])
+            };
+            let _ = SUBSYSTEM_MANAGER.register(METRICS_REPORTER, Arc::new(starter));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::metric::amplification;
+    use prometheus::{IntCounter, IntCounterVec, Opts};
+
+    #[test]
+    fn test_amplification() {
+        let disk_bytes =
+            IntCounterVec::new(Opts::new("disk_bytes", "disk_bytes"), &["root"]).unwrap();
+        let served_bytes = IntCounter::new("served_bytes", "served_bytes").unwrap();
+
+        // nothing served
+        disk_bytes.with_label_values(&["/data1"]).inc_by(100);
+        assert_eq!(0.0, amplification(&disk_bytes, &served_bytes));
+
+        // the disk bytes of all the roots are summed up
+        disk_bytes.with_label_values(&["/data2"]).inc_by(150);
+        served_bytes.inc_by(200);
+        assert_eq!(1.25, amplification(&disk_bytes, &served_bytes));
+
+        served_bytes.inc_by(300);
+        assert_eq!(0.5, amplification(&disk_bytes, &served_bytes));
+    }
+}
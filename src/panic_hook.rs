@@ -1,5 +1,6 @@
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::metric::PANIC_SIGNAL;
+use crate::timeline::TIMELINE;
 use backtrace::Backtrace;
 use log::error;
 use once_cell::sync::Lazy;
@@ -16,23 +17,25 @@ pub fn set_panic_hook() {
     panic::set_hook(Box::new(move |panic| {
         let backtrace = Backtrace::new();
         let backtrace = format!("{backtrace:?}");
+        let timeline = TIMELINE.diagnostic_section();
         if let Some(location) = panic.location() {
             error!(
                 "[Panic] ============================================================\
-                \nmessage: {}\n backtrace: {}\n file: {}. line: {}. column: {}\n\
+                \nmessage: {}\n backtrace: {}\n file: {}. line: {}. column: {}\n {}\
                 ====================================================================",
                 panic,
                 backtrace,
                 location.file(),
                 location.line(),
-                location.column()
+                location.column(),
+                timeline
             );
         } else {
             error!(
                 "[Panic] ============================================================\
-                \nmessage: {}\n backtrace: {}\n\
+                \nmessage: {}\n backtrace: {}\n {}\
                 ====================================================================",
-                panic, backtrace,
+                panic, backtrace, timeline,
             );
         }
 
@@ -0,0 +1,715 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The server side replication, which forwards the accepted blocks and the reported block
+//! ids to the peer shuffle server asynchronously, and keeps the replicated apps alive there.
+//! The reading is always served locally, and the replica is only for the recovery when this
+//! server dies.
+
+use crate::app::{AppConfigOptions, DataDistribution, PartitionedUId};
+use crate::config::ReplicationConfig;
+use crate::constant::StatusCode;
+use crate::grpc::protobuf::uniffle::shuffle_server_client::ShuffleServerClient;
+use crate::grpc::protobuf::uniffle::{
+    AppHeartBeatRequest, DataDistribution as ProtoDataDistribution, PartitionToBlockIds,
+    ReportShuffleResultRequest, RequireBufferRequest, SendShuffleDataRequest, ShuffleBlock,
+    ShuffleData, ShuffleRegisterRequest,
+};
+use crate::metric::{
+    REPLICATION_LAG_BYTES, TOTAL_REPLICATED_BYTES, TOTAL_REPLICATION_DROPPED_BYTES,
+};
+use crate::runtime::manager::RuntimeManager;
+use crate::store::Block;
+use anyhow::{anyhow, Result};
+use await_tree::InstrumentAwait;
+use dashmap::DashMap;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tonic::transport::Channel;
+
+// the bytes of one forwarding request, which keeps the require_size within i32
+const MAX_FORWARD_BATCH_BYTES: u64 = 64 * 1024 * 1024;
+const PEER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct ReplicationItem {
+    uid: PartitionedUId,
+    blocks: Vec<Block>,
+    size: u64,
+}
+
+enum ReplicationEvent {
+    Data(ReplicationItem),
+    // the block ids reported by the client, which are forwarded after the preceding data
+    BlockIds {
+        app_id: String,
+        shuffle_id: i32,
+        block_ids: HashMap<i32, Vec<i64>>,
+    },
+}
+
+/// The bytes of one partition enqueued to be replicated and acknowledged by the peer.
+#[derive(Default)]
+pub struct ReplicationWatermark {
+    pub enqueued_bytes: AtomicU64,
+    pub acked_bytes: AtomicU64,
+}
+
+// key: app_id, val: the watermarks keyed by (shuffle_id, partition_id)
+type Watermarks = DashMap<String, DashMap<(i32, i32), Arc<ReplicationWatermark>>>;
+
+#[derive(Clone)]
+pub struct Replicator {
+    sender: mpsc::Sender<ReplicationEvent>,
+    watermarks: Arc<Watermarks>,
+    // key: app_id, val: the options to register the app on the peer. The apps are kept
+    // alive on the peer until they are purged here.
+    app_options: Arc<DashMap<String, AppConfigOptions>>,
+}
+
+impl Replicator {
+    pub fn new(conf: &ReplicationConfig, runtime_manager: &RuntimeManager) -> Self {
+        let (sender, receiver) = mpsc::channel(conf.queue_depth);
+        let replicator = Replicator {
+            sender,
+            watermarks: Default::default(),
+            app_options: Default::default(),
+        };
+        info!(
+            "Replication to the peer: {} has been initialized.",
+            &conf.peer_address
+        );
+
+        let forwarder = Forwarder {
+            peer_address: conf.peer_address.to_string(),
+            client: None,
+            registered: HashSet::new(),
+            watermarks: replicator.watermarks.clone(),
+            app_options: replicator.app_options.clone(),
+        };
+        let interval = Duration::from_millis(conf.flush_interval_ms);
+        let heartbeat_interval = Duration::from_secs(conf.heartbeat_interval_sec.max(1));
+        let batch_limit = conf.queue_depth;
+        runtime_manager.default_runtime.spawn_with_await_tree(
+            "Replication forwarder",
+            async move {
+                forwarder
+                    .run(receiver, interval, heartbeat_interval, batch_limit)
+                    .await
+            },
+        );
+
+        replicator
+    }
+
+    /// Enqueues the accepted blocks without blocking, which are dropped when the queue is full.
+    pub fn replicate(&self, uid: PartitionedUId, blocks: Vec<Block>, size: u64) {
+        let watermark = self
+            .watermarks
+            .entry(uid.app_id.to_string())
+            .or_default()
+            .entry((uid.shuffle_id, uid.partition_id))
+            .or_default()
+            .clone();
+        match self
+            .sender
+            .try_send(ReplicationEvent::Data(ReplicationItem {
+                uid,
+                blocks,
+                size,
+            })) {
+            Ok(_) => {
+                watermark.enqueued_bytes.fetch_add(size, SeqCst);
+                REPLICATION_LAG_BYTES.add(size as i64);
+            }
+            Err(_) => {
+                TOTAL_REPLICATION_DROPPED_BYTES.inc_by(size);
+            }
+        }
+    }
+
+    /// Records the app options, which are used to register the app on the peer.
+    pub fn register_app(&self, app_id: &str, options: &AppConfigOptions) {
+        self.app_options.insert(app_id.to_string(), options.clone());
+    }
+
+    /// Enqueues the reported block ids without blocking, which are dropped when the queue is full.
+    pub fn report_block_ids(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        block_ids: HashMap<i32, Vec<i64>>,
+    ) {
+        let event = ReplicationEvent::BlockIds {
+            app_id: app_id.to_string(),
+            shuffle_id,
+            block_ids,
+        };
+        if self.sender.try_send(event).is_err() {
+            warn!(
+                "Dropped the replicated block ids of app: {}, shuffle: {} due to the full queue",
+                app_id, shuffle_id
+            );
+        }
+    }
+
+    /// Returns the enqueued and acknowledged bytes of the partition.
+    pub fn get_watermark(&self, uid: &PartitionedUId) -> Option<(u64, u64)> {
+        let partitions = self.watermarks.get(&uid.app_id)?;
+        let watermark = partitions.get(&(uid.shuffle_id, uid.partition_id))?;
+        Some((
+            watermark.enqueued_bytes.load(SeqCst),
+            watermark.acked_bytes.load(SeqCst),
+        ))
+    }
+
+    pub fn purge(&self, app_id: &str, shuffle_id: Option<i32>) {
+        match shuffle_id {
+            Some(shuffle_id) => {
+                if let Some(partitions) = self.watermarks.get(app_id) {
+                    partitions.retain(|key, _| key.0 != shuffle_id);
+                }
+            }
+            _ => {
+                self.watermarks.remove(app_id);
+                self.app_options.remove(app_id);
+            }
+        }
+    }
+}
+
+struct Forwarder {
+    peer_address: String,
+    client: Option<ShuffleServerClient<Channel>>,
+    // the (app_id, shuffle_id) having been registered on the peer
+    registered: HashSet<(String, i32)>,
+    watermarks: Arc<Watermarks>,
+    app_options: Arc<DashMap<String, AppConfigOptions>>,
+}
+
+impl Forwarder {
+    async fn run(
+        mut self,
+        mut receiver: mpsc::Receiver<ReplicationEvent>,
+        interval: Duration,
+        heartbeat_interval: Duration,
+        batch_limit: usize,
+    ) {
+        info!("Starting the replication forwarder...");
+        let mut heartbeat_ticker = tokio::time::interval(heartbeat_interval);
+        // the batch is flushed by the ticker rather than sleeping, to not delay the heartbeat
+        let mut flush_ticker = tokio::time::interval(interval);
+        flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut events = vec![];
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Some(event) => {
+                        events.push(event);
+                        if events.len() < batch_limit {
+                            continue;
+                        }
+                    }
+                    _ => return,
+                },
+                _ = flush_ticker.tick() => {
+                    if events.is_empty() {
+                        continue;
+                    }
+                }
+                _ = heartbeat_ticker.tick() => {
+                    self.heartbeat()
+                        .instrument_await("heartbeating the replicated apps")
+                        .await;
+                    continue;
+                }
+            }
+            self.flush(std::mem::take(&mut events))
+                .instrument_await("forwarding the batch")
+                .await;
+        }
+    }
+
+    async fn flush(&mut self, events: Vec<ReplicationEvent>) {
+        let mut groups: HashMap<(String, i32), Vec<ReplicationItem>> = HashMap::new();
+        let mut reports = vec![];
+        for event in events {
+            match event {
+                ReplicationEvent::Data(item) => groups
+                    .entry((item.uid.app_id.to_string(), item.uid.shuffle_id))
+                    .or_default()
+                    .push(item),
+                ReplicationEvent::BlockIds {
+                    app_id,
+                    shuffle_id,
+                    block_ids,
+                } => reports.push((app_id, shuffle_id, block_ids)),
+            }
+        }
+        // the shuffles whose data failed to be forwarded in this batch
+        let mut failed = HashSet::new();
+        for ((app_id, shuffle_id), items) in groups {
+            for batch in split_by_bytes(items, MAX_FORWARD_BATCH_BYTES) {
+                let size: u64 = batch.iter().map(|item| item.size).sum();
+                let result = if failed.contains(&(app_id.to_string(), shuffle_id)) {
+                    Err(anyhow!("The preceding batch has failed"))
+                } else {
+                    self.forward(&app_id, shuffle_id, &batch)
+                        .instrument_await(format!(
+                            "forwarding {} bytes of app: {}, shuffle: {}",
+                            size, &app_id, shuffle_id
+                        ))
+                        .await
+                };
+                match result {
+                    Ok(_) => {
+                        TOTAL_REPLICATED_BYTES.inc_by(size);
+                        self.ack(&batch);
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Errors on replicating {} bytes of app: {}, shuffle: {} to the peer: {}. err: {:#?}",
+                            size, &app_id, shuffle_id, &self.peer_address, err
+                        );
+                        TOTAL_REPLICATION_DROPPED_BYTES.inc_by(size);
+                        if failed.insert((app_id.to_string(), shuffle_id)) {
+                            // reconnect and register again in the next round
+                            self.client = None;
+                            self.registered.remove(&(app_id.to_string(), shuffle_id));
+                        }
+                    }
+                }
+                REPLICATION_LAG_BYTES.sub(size as i64);
+            }
+        }
+        // the block ids are reported after the data of the same batch has been forwarded,
+        // and skipped when the data is missing on the peer
+        for (app_id, shuffle_id, block_ids) in reports {
+            if failed.contains(&(app_id.to_string(), shuffle_id)) {
+                warn!(
+                    "Skipping the replicated block ids of app: {}, shuffle: {} due to the failed data",
+                    &app_id, shuffle_id
+                );
+                continue;
+            }
+            if let Err(err) = self
+                .forward_block_ids(&app_id, shuffle_id, block_ids)
+                .instrument_await(format!(
+                    "forwarding block ids of app: {}, shuffle: {}",
+                    &app_id, shuffle_id
+                ))
+                .await
+            {
+                warn!(
+                    "Errors on replicating block ids of app: {}, shuffle: {} to the peer: {}. err: {:#?}",
+                    &app_id, shuffle_id, &self.peer_address, err
+                );
+                self.client = None;
+                self.registered.remove(&(app_id, shuffle_id));
+            }
+        }
+    }
+
+    /// Keeps the replicated apps alive on the peer, and forgets the apps purged locally.
+    async fn heartbeat(&mut self) {
+        let app_options = self.app_options.clone();
+        self.registered
+            .retain(|(app_id, _)| app_options.contains_key(app_id));
+        let app_ids: HashSet<String> = self
+            .registered
+            .iter()
+            .map(|(app_id, _)| app_id.to_string())
+            .collect();
+        for app_id in app_ids {
+            let heartbeat = async {
+                let response = self
+                    .get_client()
+                    .await?
+                    .app_heartbeat(AppHeartBeatRequest {
+                        app_id: app_id.to_string(),
+                    })
+                    .await?
+                    .into_inner();
+                if response.status != StatusCode::SUCCESS as i32 {
+                    return Err(anyhow!(
+                        "Errors on heartbeat. status: {}. msg: {}",
+                        response.status,
+                        response.ret_msg
+                    ));
+                }
+                Ok(())
+            };
+            if let Err(err) = heartbeat.await {
+                warn!(
+                    "Errors on heartbeating app: {} to the peer: {}. err: {:#?}",
+                    &app_id, &self.peer_address, err
+                );
+                // register again in the next round
+                self.client = None;
+                self.registered.retain(|(v_app_id, _)| v_app_id != &app_id);
+            }
+        }
+    }
+
+    fn ack(&self, items: &[ReplicationItem]) {
+        for item in items {
+            let uid = &item.uid;
+            if let Some(partitions) = self.watermarks.get(&uid.app_id) {
+                if let Some(watermark) = partitions.get(&(uid.shuffle_id, uid.partition_id)) {
+                    watermark.acked_bytes.fetch_add(item.size, SeqCst);
+                }
+            }
+        }
+    }
+
+    async fn get_client(&mut self) -> Result<&mut ShuffleServerClient<Channel>> {
+        if self.client.is_none() {
+            let client = tokio::time::timeout(
+                PEER_CONNECT_TIMEOUT,
+                ShuffleServerClient::connect(format!("http://{}", &self.peer_address)),
+            )
+            .await
+            .map_err(|_| anyhow!("Timeout on connecting to the peer"))??;
+            self.client = Some(client);
+        }
+        Ok(self.client.as_mut().unwrap())
+    }
+
+    /// Registers the shuffle on the peer with the same options of the local app.
+    async fn register(&mut self, app_id: &str, shuffle_id: i32) -> Result<()> {
+        let key = (app_id.to_string(), shuffle_id);
+        if self.registered.contains(&key) {
+            return Ok(());
+        }
+        let options = match self.app_options.get(app_id) {
+            Some(options) => options.clone(),
+            _ => return Err(anyhow!("The app: {} has been purged", app_id)),
+        };
+        let data_distribution = match options.data_distribution {
+            DataDistribution::NORMAL => ProtoDataDistribution::Normal,
+            DataDistribution::LOCAL_ORDER => ProtoDataDistribution::LocalOrder,
+        };
+        let response = self
+            .get_client()
+            .await?
+            .register_shuffle(ShuffleRegisterRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_ranges: vec![],
+                remote_storage: options.remote_storage_config_option.map(|x| x.into()),
+                user: options.user,
+                shuffle_data_distribution: data_distribution.into(),
+                max_concurrency_per_partition_to_write: options
+                    .max_concurrency_per_partition_to_write,
+                consistency_check_enabled: options.consistency_check_enable,
+                expected_total_bytes: options.expected_total_bytes.unwrap_or(0) as i64,
+            })
+            .await?
+            .into_inner();
+        if response.status != StatusCode::SUCCESS as i32 {
+            return Err(anyhow!(
+                "Errors on registering. status: {}. msg: {}",
+                response.status,
+                response.ret_msg
+            ));
+        }
+        self.registered.insert(key);
+        Ok(())
+    }
+
+    async fn forward_block_ids(
+        &mut self,
+        app_id: &str,
+        shuffle_id: i32,
+        block_ids: HashMap<i32, Vec<i64>>,
+    ) -> Result<()> {
+        self.register(app_id, shuffle_id).await?;
+        let response = self
+            .get_client()
+            .await?
+            .report_shuffle_result(ReportShuffleResultRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                task_attempt_id: 0,
+                bitmap_num: 1,
+                partition_to_block_ids: block_ids
+                    .into_iter()
+                    .map(|(partition_id, block_ids)| PartitionToBlockIds {
+                        partition_id,
+                        block_ids,
+                        expected_total_count: None,
+                    })
+                    .collect(),
+            })
+            .await?
+            .into_inner();
+        if response.status != StatusCode::SUCCESS as i32 {
+            return Err(anyhow!(
+                "Errors on reporting block ids. status: {}. msg: {}",
+                response.status,
+                response.ret_msg
+            ));
+        }
+        Ok(())
+    }
+
+    async fn forward(
+        &mut self,
+        app_id: &str,
+        shuffle_id: i32,
+        items: &[ReplicationItem],
+    ) -> Result<()> {
+        self.register(app_id, shuffle_id).await?;
+        let client = self.get_client().await?;
+
+        let size: u64 = items.iter().map(|item| item.size).sum();
+        let ticket = client
+            .require_buffer(RequireBufferRequest {
+                require_size: i32::try_from(size)?,
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_ids: vec![],
+            })
+            .await?
+            .into_inner();
+        if ticket.status != StatusCode::SUCCESS as i32 {
+            return Err(anyhow!(
+                "Errors on requiring buffer. status: {}. msg: {}",
+                ticket.status,
+                ticket.ret_msg
+            ));
+        }
+
+        let mut partitions: HashMap<i32, Vec<ShuffleBlock>> = HashMap::new();
+        for item in items {
+            let blocks = partitions.entry(item.uid.partition_id).or_default();
+            for block in &item.blocks {
+                blocks.push(ShuffleBlock {
+                    block_id: block.block_id,
+                    length: block.length,
+                    uncompress_length: block.uncompress_length,
+                    crc: block.crc,
+                    data: block.data.clone(),
+                    task_attempt_id: block.task_attempt_id,
+                });
+            }
+        }
+        let response = client
+            .send_shuffle_data(SendShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                require_buffer_id: ticket.require_buffer_id,
+                shuffle_data: partitions
+                    .into_iter()
+                    .map(|(partition_id, blocks)| ShuffleData {
+                        partition_id,
+                        block: blocks,
+                        crc_aggregate: None,
+                    })
+                    .collect(),
+                timestamp: 0,
+                stage_attempt_number: 0,
+            })
+            .await?
+            .into_inner();
+        if response.status != StatusCode::SUCCESS as i32 {
+            return Err(anyhow!(
+                "Errors on sending data. status: {}. msg: {}",
+                response.status,
+                response.ret_msg
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Splits the items into the batches of the limited bytes, while the item larger than the
+/// limit is in its own batch.
+fn split_by_bytes(items: Vec<ReplicationItem>, limit: u64) -> Vec<Vec<ReplicationItem>> {
+    let mut batches = vec![];
+    let mut batch = vec![];
+    let mut batch_size = 0;
+    for item in items {
+        if !batch.is_empty() && batch_size + item.size > limit {
+            batches.push(std::mem::take(&mut batch));
+            batch_size = 0;
+        }
+        batch_size += item.size;
+        batch.push(item);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::test::{mock_config, mock_writing_context};
+    use crate::app::{
+        AppConfigOptions, AppManager, DataDistribution, GetMultiBlockIdsContext, PartitionedUId,
+        ReadingOptions, ReadingViewContext, ReportMultiBlockIdsContext,
+    };
+    use crate::config::ReplicationConfig;
+    use crate::config_reconfigure::ReconfigurableConfManager;
+    use crate::decommission::DecommissionManager;
+    use crate::grpc::protobuf::uniffle::shuffle_server_server::ShuffleServerServer;
+    use crate::grpc::service::DefaultShuffleServer;
+    use crate::id_layout::to_layout;
+    use crate::reject::RejectionPolicyGateway;
+    use crate::replication::{split_by_bytes, ReplicationItem};
+    use crate::runtime::manager::RuntimeManager;
+    use crate::storage::StorageService;
+    use crate::store::ResponseData;
+    use croaring::{JvmLegacy, Treemap};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[test]
+    fn test_split_by_bytes() {
+        let items = [10, 20, 100, 5, 5]
+            .into_iter()
+            .map(|size| ReplicationItem {
+                uid: PartitionedUId::from("app".to_string(), 1, 0),
+                blocks: vec![],
+                size,
+            })
+            .collect();
+        let batches: Vec<Vec<u64>> = split_by_bytes(items, 30)
+            .into_iter()
+            .map(|batch| batch.iter().map(|item| item.size).collect())
+            .collect();
+        assert_eq!(vec![vec![10, 20], vec![100], vec![5, 5]], batches);
+    }
+
+    #[test]
+    fn test_replication() -> anyhow::Result<()> {
+        let app_id = "test_replication";
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+
+        // the peer server
+        let peer_runtime_manager: RuntimeManager = Default::default();
+        let peer_config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&peer_config, None)?;
+        let peer_storage = StorageService::init(&peer_runtime_manager, &peer_config);
+        let peer_app_manager_ref = AppManager::get_ref(
+            peer_runtime_manager.clone(),
+            peer_config.clone(),
+            &peer_storage,
+            &reconf_manager,
+        );
+        let shuffle_server = DefaultShuffleServer::from(
+            peer_app_manager_ref.clone(),
+            &RejectionPolicyGateway::new(&peer_app_manager_ref, &peer_config),
+            &DecommissionManager::new(&peer_app_manager_ref),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let peer_addr = listener.local_addr()?;
+        peer_runtime_manager.default_runtime.spawn(async move {
+            Server::builder()
+                .add_service(ShuffleServerServer::new(shuffle_server))
+                .serve_with_incoming(TcpListenerStream::new(
+                    TcpListener::from_std(listener).unwrap(),
+                ))
+                .await
+        });
+
+        // the primary server replicating to the peer
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.replication = Some(ReplicationConfig {
+            peer_address: peer_addr.to_string(),
+            queue_depth: 16,
+            flush_interval_ms: 10,
+            heartbeat_interval_sec: 1,
+        });
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        // the app options are registered on the peer as same as the primary
+        let options = AppConfigOptions::new(DataDistribution::NORMAL, 10, None);
+        app_manager_ref.register(app_id.to_string(), 1, options)?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 10)))?;
+        runtime_manager.wait(app.report_multi_block_ids(ReportMultiBlockIdsContext::new(
+            1,
+            HashMap::from([(0, vec![0, 1])]),
+        )))?;
+
+        // the data and block ids written into the primary are readable on the peer
+        let get_peer_block_ids = || -> anyhow::Result<u64> {
+            let peer_app = match peer_app_manager_ref.get_app(app_id) {
+                Some(app) => app,
+                _ => return Ok(0),
+            };
+            let block_ids = peer_runtime_manager.wait(peer_app.get_multi_block_ids(
+                GetMultiBlockIdsContext {
+                    shuffle_id: 1,
+                    partition_ids: vec![0],
+                    layout: to_layout(None),
+                },
+            ))?;
+            Ok(Treemap::deserialize::<JvmLegacy>(&block_ids).cardinality())
+        };
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| get_peer_block_ids().unwrap_or(0) == 2);
+        let peer_app = peer_app_manager_ref.get_app(app_id).unwrap();
+        assert_eq!(20, peer_app.total_received_data_size());
+        assert!(!peer_app.is_local_order());
+
+        // the peer app is kept alive by the heartbeat without any writing or reading
+        std::thread::sleep(Duration::from_secs(3));
+        assert!(peer_app.heartbeat_age_sec() < 3);
+
+        let data = peer_runtime_manager.wait(peer_app.select(ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+            serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
+        }))?;
+        match data {
+            ResponseData::Mem(data) => {
+                assert_eq!(2, data.shuffle_data_block_segments.len());
+                assert_eq!(20, data.data.len());
+            }
+            _ => panic!("the replicated data should be in memory"),
+        }
+
+        // the local data is kept and the watermark is acknowledged
+        assert_eq!(
+            20,
+            runtime_manager.wait(storage.get_memory_buffer_size(&uid))?
+        );
+        let replicator = app_manager_ref.replicator.as_ref().unwrap();
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| replicator.get_watermark(&uid) == Some((20, 20)));
+
+        Ok(())
+    }
+}
@@ -2,24 +2,32 @@ use crate::app::AppManagerRef;
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::config::Config;
 use crate::decommission::DecommissionManager;
+use crate::grpc::health::GrpcHealthService;
 use crate::grpc::layer::awaittree::AwaitTreeMiddlewareLayer;
 use crate::grpc::layer::metric::MetricsMiddlewareLayer;
 use crate::grpc::layer::tracing::TracingMiddleWareLayer;
+use crate::grpc::protobuf::health::health_server::HealthServer;
 use crate::grpc::protobuf::uniffle::shuffle_server_server::ShuffleServerServer;
 use crate::grpc::service::{DefaultShuffleServer, MAX_CONNECTION_WINDOW_SIZE, STREAM_WINDOW_SIZE};
+use crate::health_service::HealthService;
 use crate::metric::GRPC_LATENCY_TIME_SEC;
 use crate::reject::RejectionPolicyGateway;
 use crate::runtime::manager::RuntimeManager;
 use crate::signal::details::graceful_wait_for_signal;
+use crate::subsystem::{SubsystemTask, SUBSYSTEM_MANAGER, URPC_LISTENER};
 use crate::urpc;
+use crate::urpc::read_coalescer::ReadCoalesceOptions;
+use crate::urpc::server::UrpcServerOptions;
 use crate::util::is_port_used;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::{debug, error, info};
 use once_cell::sync::Lazy;
 use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::{Receiver, Sender};
@@ -34,6 +42,15 @@ pub static GRPC_PARALLELISM: Lazy<NonZeroUsize> = Lazy::new(|| {
     })
 });
 
+const DEFAULT_URPC_BIND_HOST: &str = "0.0.0.0";
+const DEFAULT_URPC_CONNECTION_IDLE_TIMEOUT_SEC: u64 = 600;
+const DEFAULT_URPC_MAX_CONNECTIONS: usize = 40000;
+const DEFAULT_URPC_SHUTDOWN_GRACE_PERIOD_SEC: u64 = 10;
+const DEFAULT_URPC_LISTENER_MAX_RESTARTS: usize = 10;
+const DEFAULT_URPC_LISTENER_RESTART_BACKOFF_SEC: u64 = 5;
+const DEFAULT_URPC_LISTENER_HEALTHY_RESET_SEC: u64 = 600;
+const URPC_ACCEPT_MAX_BACKOFF: Duration = Duration::from_secs(64);
+
 pub static URPC_PARALLELISM: Lazy<NonZeroUsize> = Lazy::new(|| {
     let available_cores = std::thread::available_parallelism().unwrap();
     std::env::var("URPC_PARALLELISM").map_or(available_cores, |v| {
@@ -61,33 +78,66 @@ impl DefaultRpcService {
         app_manager_ref: AppManagerRef,
         rejection_gateway: &RejectionPolicyGateway,
     ) -> Result<()> {
-        let urpc_port = config.urpc_port.unwrap();
-        info!("Starting urpc server with port:[{}] ......", urpc_port);
-
-        for _ in 0..URPC_PARALLELISM.get() {
-            let rx = tx.subscribe();
-            async fn shutdown(mut rx: Receiver<()>) -> Result<()> {
-                if let Err(err) = rx.recv().await {
-                    error!("Errors on stopping the urpc service, err: {:?}.", err);
-                } else {
-                    debug!("urpc service has been graceful stopped.");
-                }
-                Ok(())
-            }
-
-            let app_manager = app_manager_ref.clone();
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), urpc_port as u16);
-
-            std::thread::spawn(move || {
-                tokio::runtime::Builder::new_current_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap()
-                    .block_on(urpc_serve(addr, shutdown(rx), app_manager));
-            });
-        }
+        let addr = urpc_bind_addr(config)?;
+        let idle_timeout = Duration::from_secs(
+            config
+                .urpc_connection_idle_timeout_sec
+                .unwrap_or(DEFAULT_URPC_CONNECTION_IDLE_TIMEOUT_SEC),
+        );
+        let max_connections = config
+            .urpc_max_connections
+            .unwrap_or(DEFAULT_URPC_MAX_CONNECTIONS);
+        let shutdown_grace_period = Duration::from_secs(
+            config
+                .urpc_shutdown_grace_period_sec
+                .unwrap_or(DEFAULT_URPC_SHUTDOWN_GRACE_PERIOD_SEC),
+        );
+        let read_coalesce = match config.urpc_read_coalesce.as_ref() {
+            Some(conf) => Some(ReadCoalesceOptions::parse(conf)?),
+            _ => None,
+        };
+        let listener_max_restarts = config
+            .urpc_listener_max_restarts
+            .unwrap_or(DEFAULT_URPC_LISTENER_MAX_RESTARTS);
+        let listener_restart_backoff = Duration::from_secs(
+            config
+                .urpc_listener_restart_backoff_sec
+                .unwrap_or(DEFAULT_URPC_LISTENER_RESTART_BACKOFF_SEC),
+        );
+        let listener_healthy_reset = Duration::from_secs(
+            config
+                .urpc_listener_healthy_reset_sec
+                .unwrap_or(DEFAULT_URPC_LISTENER_HEALTHY_RESET_SEC),
+        );
+        let options = UrpcServerOptions {
+            max_connections,
+            idle_timeout,
+            shutdown_grace_period,
+            read_coalesce,
+            accept_max_backoff: URPC_ACCEPT_MAX_BACKOFF,
+            listener_max_restarts,
+            listener_restart_backoff,
+            listener_healthy_reset,
+        };
+        info!(
+            "Starting urpc server with address:[{}], options:[{:?}] ......",
+            addr, options
+        );
 
-        Ok(())
+        // the urpc listener could be stopped/restarted individually by the admin api,
+        // which is also stopped along with the process shutdown.
+        SUBSYSTEM_MANAGER.register(
+            URPC_LISTENER,
+            Arc::new(move |subsystem_rx| {
+                start_urpc_listeners(
+                    addr,
+                    options,
+                    tx.subscribe(),
+                    subsystem_rx,
+                    app_manager_ref.clone(),
+                )
+            }),
+        )
     }
 
     fn start_grpc(
@@ -97,6 +147,7 @@ impl DefaultRpcService {
         app_manager_ref: AppManagerRef,
         rejection_gateway: &RejectionPolicyGateway,
         decommission_manager: &DecommissionManager,
+        health_service: &HealthService,
     ) -> Result<()> {
         let grpc_port = config.grpc_port;
 
@@ -115,6 +166,7 @@ impl DefaultRpcService {
             let service = ShuffleServerServer::new(shuffle_server)
                 .max_decoding_message_size(usize::MAX)
                 .max_encoding_message_size(usize::MAX);
+            let health = HealthServer::new(GrpcHealthService::new(health_service));
             let service_tx = tx.subscribe();
 
             // every std::thread to bound the tokio thread to eliminate thread context switch.
@@ -130,7 +182,7 @@ impl DefaultRpcService {
                     .enable_all()
                     .build()
                     .unwrap()
-                    .block_on(grpc_serve(service, addr, service_tx));
+                    .block_on(grpc_serve(service, health, addr, service_tx));
             });
         }
 
@@ -143,6 +195,7 @@ impl DefaultRpcService {
         runtime_manager: RuntimeManager,
         app_manager_ref: AppManagerRef,
         decommission_manager: &DecommissionManager,
+        health_service: &HealthService,
     ) -> Result<()> {
         let rejection_gateway = RejectionPolicyGateway::new(&app_manager_ref, config);
 
@@ -160,6 +213,7 @@ impl DefaultRpcService {
             app_manager_ref.clone(),
             &rejection_gateway,
             decommission_manager,
+            health_service,
         )?;
 
         let urpc_port = config.urpc_port;
@@ -177,35 +231,98 @@ impl DefaultRpcService {
             )?;
         }
 
+        // Once the shutdown signal is broadcast, the buffered data will be drained into
+        // the persistent stores before the process exit.
+        let mut drain_rx = tx.subscribe();
+        let drain_timeout = Duration::from_secs(config.hybrid_store.shutdown_drain_timeout_sec);
+        let drain_handle = runtime_manager.default_runtime.spawn_with_await_tree(
+            "Draining the store on shutdown",
+            async move {
+                if let Err(err) = drain_rx.recv().await {
+                    error!("Errors on receiving the shutdown signal. err: {:?}", err);
+                    return;
+                }
+                info!("Draining the store on shutdown...");
+                if let Err(err) = app_manager_ref.store_drain(drain_timeout).await {
+                    error!("Errors on draining the store. err: {:?}", err);
+                }
+            },
+        );
+
         graceful_wait_for_signal(tx);
+        let _ = runtime_manager.wait(drain_handle);
 
         Ok(())
     }
 }
 
-async fn urpc_serve(addr: SocketAddr, shutdown: impl Future, app_manager_ref: AppManagerRef) {
-    let sock = socket2::Socket::new(
-        match addr {
-            SocketAddr::V4(_) => socket2::Domain::IPV4,
-            SocketAddr::V6(_) => socket2::Domain::IPV6,
-        },
-        socket2::Type::STREAM,
-        None,
-    )
-    .unwrap();
+fn urpc_bind_addr(config: &Config) -> Result<SocketAddr> {
+    let host = config
+        .urpc_bind_host
+        .as_deref()
+        .unwrap_or(DEFAULT_URPC_BIND_HOST);
+    let ip: IpAddr = host
+        .parse()
+        .map_err(|err| anyhow!("Illegal urpc bind host: {}. err: {}", host, err))?;
+    Ok(SocketAddr::new(ip, config.urpc_port.unwrap() as u16))
+}
 
-    sock.set_reuse_address(true).unwrap();
-    sock.set_reuse_port(true).unwrap();
-    sock.set_nonblocking(true).unwrap();
-    sock.bind(&addr.into()).unwrap();
-    sock.listen(8192).unwrap();
+pub(crate) fn start_urpc_listeners(
+    addr: SocketAddr,
+    options: UrpcServerOptions,
+    process_rx: Receiver<()>,
+    subsystem_rx: Receiver<()>,
+    app_manager_ref: AppManagerRef,
+) -> Result<Vec<SubsystemTask>> {
+    async fn shutdown(mut process_rx: Receiver<()>, mut subsystem_rx: Receiver<()>) {
+        tokio::select! {
+            res = process_rx.recv() => {
+                if let Err(err) = res {
+                    error!("Errors on stopping the urpc service, err: {:?}.", err);
+                } else {
+                    debug!("urpc service has been graceful stopped.");
+                }
+            }
+            _ = subsystem_rx.recv() => {
+                info!("urpc listener has been stopped by the subsystem manager.");
+            }
+        }
+    }
 
-    let listener = TcpListener::from_std(sock.into()).unwrap();
-    let _ = urpc::server::run(listener, shutdown, app_manager_ref).await;
+    // binding all the sockets in advance to fail fast for the restarting
+    let mut listeners = vec![];
+    for _ in 0..URPC_PARALLELISM.get() {
+        listeners.push(urpc::server::bind(addr)?);
+    }
+    let mut tasks = vec![];
+    for listener in listeners {
+        let shutdown = shutdown(process_rx.resubscribe(), subsystem_rx.resubscribe());
+        let app_manager = app_manager_ref.clone();
+        let task = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(urpc_serve(listener, options, shutdown, app_manager));
+        });
+        tasks.push(SubsystemTask::Thread(task));
+    }
+    Ok(tasks)
+}
+
+async fn urpc_serve(
+    listener: std::net::TcpListener,
+    options: UrpcServerOptions,
+    shutdown: impl Future,
+    app_manager_ref: AppManagerRef,
+) {
+    let listener = TcpListener::from_std(listener).unwrap();
+    let _ = urpc::server::run(listener, options, shutdown, app_manager_ref).await;
 }
 
 async fn grpc_serve(
     service: ShuffleServerServer<DefaultShuffleServer>,
+    health: HealthServer<GrpcHealthService>,
     addr: SocketAddr,
     mut rx: broadcast::Receiver<()>,
 ) {
@@ -237,6 +354,7 @@ async fn grpc_serve(
             AWAIT_TREE_REGISTRY.clone(),
         )))
         .add_service(service)
+        .add_service(health)
         .serve_with_incoming_shutdown(incoming, async {
             if let Err(err) = rx.recv().await {
                 error!("Errors on stopping the GRPC service, err: {:?}.", err);
@@ -247,3 +365,39 @@ async fn grpc_serve(
         .await
         .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::rpc::urpc_bind_addr;
+    use crate::urpc::server::bind;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    #[test]
+    fn test_urpc_bind_addr() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.urpc_port = Some(21100);
+
+        // case1: all the interfaces by default
+        let addr = urpc_bind_addr(&config)?;
+        assert_eq!("0.0.0.0:21100", addr.to_string());
+
+        // case2: loopback only when explicitly set
+        config.urpc_bind_host = Some("127.0.0.1".to_string());
+        assert!(urpc_bind_addr(&config)?.ip().is_loopback());
+
+        // case3: illegal host
+        config.urpc_bind_host = Some("localhost:1".to_string());
+        assert!(urpc_bind_addr(&config).is_err());
+
+        // case4: the bound local addr is non-loopback
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        let listener = bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        assert!(!local_addr.ip().is_loopback());
+        assert!(local_addr.ip().is_unspecified());
+        assert_ne!(0, local_addr.port());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::{Config, RuntimeConfig, StorageType};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// The sizing report of the startup, which is exposed by the `/debug/runtimes`
+pub static RUNTIME_SIZING_REPORT: OnceLock<RuntimeSizingReport> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSizingWarning {
+    pub runtime: String,
+    pub threads: usize,
+    pub suggested_threads: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSizingReport {
+    pub runtime_config: RuntimeConfig,
+    pub disk_number: usize,
+    pub warnings: Vec<RuntimeSizingWarning>,
+}
+
+impl RuntimeSizingReport {
+    /// Analyzes the runtime thread numbers against the number of disks. Because every
+    /// localfile read/append occupies one runtime thread, the threads fewer than the disks
+    /// guarantee some disks are starved.
+    pub fn analyze(config: &Config) -> Self {
+        let runtime_config = &config.runtime_config;
+        let disk_number = match config.localfile_store.as_ref() {
+            Some(localfile) if StorageType::contains_localfile(&config.store_type) => {
+                localfile.data_paths.len()
+            }
+            _ => 0,
+        };
+
+        let mut warnings = vec![];
+        let mut check = |runtime: &str, threads: usize, required: usize, reason: &str| {
+            if threads < required {
+                warnings.push(RuntimeSizingWarning {
+                    runtime: runtime.to_string(),
+                    threads,
+                    suggested_threads: required,
+                    message: format!(
+                        "The {} has {} threads that is less than {}, {}. Suggest: >= {}",
+                        runtime, threads, required, reason, required
+                    ),
+                });
+            }
+        };
+
+        let disk_reason = |threads: usize| {
+            format!(
+                "only {} of {} disks could be accessed concurrently",
+                threads, disk_number
+            )
+        };
+        check(
+            "localfile_write_runtime",
+            runtime_config.localfile_write_thread_num,
+            disk_number.max(1),
+            disk_reason(runtime_config.localfile_write_thread_num).as_str(),
+        );
+        check(
+            "read_runtime",
+            runtime_config.read_thread_num,
+            disk_number.max(1),
+            disk_reason(runtime_config.read_thread_num).as_str(),
+        );
+        check(
+            "hdfs_write_runtime",
+            runtime_config.hdfs_write_thread_num,
+            1,
+            "no thread is available",
+        );
+        check(
+            "default_runtime",
+            runtime_config.default_thread_num,
+            1,
+            "no thread is available",
+        );
+        check(
+            "dispatch_runtime",
+            runtime_config.dispatch_thread_num,
+            1,
+            "no thread is available",
+        );
+        check(
+            "http_runtime",
+            runtime_config.http_thread_num,
+            1,
+            "no thread is available",
+        );
+
+        Self {
+            runtime_config: runtime_config.clone(),
+            disk_number,
+            warnings,
+        }
+    }
+
+    /// Logs the warnings, and fails under the strict mode if any warning exists.
+    pub fn check(&self) -> Result<()> {
+        if self.warnings.is_empty() {
+            info!(
+                "The runtime sizing is consistent with {} disks",
+                self.disk_number
+            );
+            return Ok(());
+        }
+        for warning in &self.warnings {
+            warn!("[Runtime sizing] {}", &warning.message);
+        }
+        if self.runtime_config.strict_sizing_check_enable {
+            return Err(anyhow!(
+                "The runtime sizing check failed with {} warnings under the strict mode",
+                self.warnings.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Config, LocalfileStoreConfig, StorageType};
+    use crate::runtime::analyzer::RuntimeSizingReport;
+
+    fn config_with_disks(disk_number: usize) -> Config {
+        let mut config = Config::default();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.localfile_store = Some(LocalfileStoreConfig::new(
+            (0..disk_number)
+                .map(|idx| format!("/data{}", idx))
+                .collect(),
+        ));
+        config
+    }
+
+    #[test]
+    fn test_analyze() -> anyhow::Result<()> {
+        // case1: the default runtime config is enough
+        let report = RuntimeSizingReport::analyze(&config_with_disks(12));
+        assert!(report.warnings.is_empty());
+        report.check()?;
+
+        // case2: the memory only store is not limited by disks
+        let mut config = config_with_disks(12);
+        config.store_type = StorageType::MEMORY;
+        config.runtime_config.localfile_write_thread_num = 2;
+        assert!(RuntimeSizingReport::analyze(&config).warnings.is_empty());
+
+        // case3: the write runtime starves the disks
+        let mut config = config_with_disks(12);
+        config.runtime_config.localfile_write_thread_num = 2;
+        config.runtime_config.read_thread_num = 4;
+        let report = RuntimeSizingReport::analyze(&config);
+        assert_eq!(2, report.warnings.len());
+        assert_eq!("localfile_write_runtime", report.warnings[0].runtime);
+        assert_eq!(2, report.warnings[0].threads);
+        assert_eq!(12, report.warnings[0].suggested_threads);
+        assert_eq!("read_runtime", report.warnings[1].runtime);
+        assert_eq!(12, report.warnings[1].suggested_threads);
+        report.check()?;
+
+        // case4: the zero threads
+        let mut config = config_with_disks(1);
+        config.runtime_config.default_thread_num = 0;
+        let report = RuntimeSizingReport::analyze(&config);
+        assert_eq!(1, report.warnings.len());
+        assert_eq!("default_runtime", report.warnings[0].runtime);
+
+        // case5: the strict mode refuses to start
+        let mut config = config_with_disks(12);
+        config.runtime_config.localfile_write_thread_num = 2;
+        config.runtime_config.strict_sizing_check_enable = true;
+        assert!(RuntimeSizingReport::analyze(&config).check().is_err());
+
+        Ok(())
+    }
+}
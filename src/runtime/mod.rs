@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod analyzer;
 pub mod manager;
 mod metrics;
 
@@ -117,6 +118,10 @@ impl<T> JoinHandle<T> {
     pub fn abort(&self) {
         self.inner.abort();
     }
+
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
 }
 
 impl<T> Future for JoinHandle<T> {
@@ -0,0 +1,145 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::composed_bytes::ComposedBytes;
+use crate::config::CompressionType;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+
+/// The pluggable codec to compress the spilled chunks independently.
+pub trait Codec: Send + Sync {
+    fn compress(&self, data: &[u8]) -> Result<Bytes>;
+
+    fn decompress(&self, data: &[u8], uncompressed_len: usize) -> Result<Bytes>;
+}
+
+pub struct Lz4Codec;
+
+impl Codec for Lz4Codec {
+    fn compress(&self, data: &[u8]) -> Result<Bytes> {
+        Ok(Bytes::from(lz4_flex::block::compress(data)))
+    }
+
+    fn decompress(&self, data: &[u8], uncompressed_len: usize) -> Result<Bytes> {
+        let data = lz4_flex::block::decompress(data, uncompressed_len)
+            .map_err(|e| anyhow!("Errors on lz4 decompressing. err: {}", e))?;
+        Ok(Bytes::from(data))
+    }
+}
+
+pub fn get_codec(compression_type: CompressionType) -> Box<dyn Codec> {
+    match compression_type {
+        CompressionType::LZ4 => Box::new(Lz4Codec),
+    }
+}
+
+/// Maps a range of the partition's logical data file to the compressed chunk on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedChunk {
+    pub logical_offset: i64,
+    pub length: i64,
+    pub physical_offset: i64,
+    pub physical_length: i64,
+}
+
+/// Compresses every component of the composed bytes as an independent chunk, so that
+/// the chunks could be decompressed without the others. The components are the blocks
+/// of the spilled partition, which keeps the chunks aligned to the index segments.
+pub fn compress_chunks(
+    codec: &dyn Codec,
+    data: &ComposedBytes,
+    logical_offset: i64,
+    physical_offset: i64,
+) -> Result<(ComposedBytes, Vec<CompressedChunk>)> {
+    let mut compressed = ComposedBytes::new();
+    let mut chunks = vec![];
+    let mut logical_offset = logical_offset;
+    let mut physical_offset = physical_offset;
+    for component in data.iter().filter(|x| !x.is_empty()) {
+        let chunk = codec.compress(component)?;
+        chunks.push(CompressedChunk {
+            logical_offset,
+            length: component.len() as i64,
+            physical_offset,
+            physical_length: chunk.len() as i64,
+        });
+        logical_offset += component.len() as i64;
+        physical_offset += chunk.len() as i64;
+        compressed.put(chunk);
+    }
+    Ok((compressed, chunks))
+}
+
+/// The chunks overlapping the logical range, which are sorted by the logical offset.
+pub fn overlapped_chunks(chunks: &[CompressedChunk], offset: i64, len: i64) -> &[CompressedChunk] {
+    let end = offset + len;
+    let start_idx = chunks.partition_point(|x| x.logical_offset + x.length <= offset);
+    let end_idx = chunks.partition_point(|x| x.logical_offset < end);
+    &chunks[start_idx..end_idx.max(start_idx)]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::composed_bytes::ComposedBytes;
+    use crate::config::CompressionType;
+    use crate::store::compression::{compress_chunks, get_codec, overlapped_chunks};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_compress_chunks() -> anyhow::Result<()> {
+        let codec = get_codec(CompressionType::LZ4);
+        let data = ComposedBytes::from(vec![
+            Bytes::from(vec![b'a'; 100]),
+            Bytes::new(),
+            Bytes::from(vec![b'b'; 200]),
+            Bytes::from(vec![b'c'; 50]),
+        ]);
+        let (compressed, chunks) = compress_chunks(codec.as_ref(), &data, 10, 4)?;
+        assert_eq!(3, chunks.len());
+        assert!(compressed.len() < data.len());
+        assert_eq!(
+            vec![(10, 100), (110, 200), (310, 50)],
+            chunks
+                .iter()
+                .map(|x| (x.logical_offset, x.length))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(4, chunks[0].physical_offset);
+        assert_eq!(
+            compressed.len() as i64,
+            chunks.iter().map(|x| x.physical_length).sum::<i64>()
+        );
+
+        // every chunk could be decompressed independently
+        let chunk = &chunks[1];
+        let raw = compressed.slice(
+            (chunk.physical_offset - 4) as usize,
+            chunk.physical_length as usize,
+        )?;
+        let decompressed = codec.decompress(&raw, chunk.length as usize)?;
+        assert_eq!(vec![b'b'; 200], decompressed);
+
+        // the overlapped chunks
+        assert_eq!(&chunks[1..2], overlapped_chunks(&chunks, 110, 200));
+        assert_eq!(&chunks[0..2], overlapped_chunks(&chunks, 100, 20));
+        assert_eq!(&chunks[2..], overlapped_chunks(&chunks, 320, 100));
+        assert!(overlapped_chunks(&chunks, 400, 10).is_empty());
+        assert!(overlapped_chunks(&chunks, 0, 10).is_empty());
+
+        Ok(())
+    }
+}
@@ -89,6 +89,21 @@ impl HdfsDelegator for HdfsNativeClient {
         Ok(file_info.length as u64)
     }
 
+    async fn read(&self, file_path: &str, offset: u64, length: u64) -> Result<Bytes, WorkerError> {
+        let file_path = &self.with_root(file_path)?;
+        let file_reader = self
+            .inner
+            .client
+            .read(file_path)
+            .instrument_await("opening...")
+            .await?;
+        let data = file_reader
+            .read_range(offset as usize, length as usize)
+            .instrument_await("reading...")
+            .await?;
+        Ok(data)
+    }
+
     async fn create_dir(&self, dir: &str) -> Result<()> {
         let dir = &self.with_root(dir)?;
         let _ = self.inner.client.mkdirs(dir, 777, true).await?;
@@ -7,7 +7,7 @@ use bytes::Bytes;
 use hdrs::{Client, ClientBuilder};
 use libc::stat;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
 use url::Url;
 
@@ -44,6 +44,14 @@ impl HdrsClient {
             }),
         })
     }
+
+    fn read_blocking(&self, path: &str, offset: u64, length: u64) -> Result<Bytes> {
+        let mut file = self.inner.client.open_file().read(true).open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0; length as usize];
+        file.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
 }
 
 #[async_trait]
@@ -76,6 +84,15 @@ impl HdfsDelegator for HdrsClient {
         Ok(metadata.len())
     }
 
+    async fn read(&self, file_path: &str, offset: u64, length: u64) -> Result<Bytes, WorkerError> {
+        let path = self.with_root(file_path)?;
+        let client = self.clone();
+        // the hdrs reading blocks on the jni calls, which should not occupy the async workers
+        let data = tokio::task::spawn_blocking(move || client.read_blocking(&path, offset, length))
+            .await??;
+        Ok(data)
+    }
+
     async fn create_dir(&self, dir: &str) -> Result<()> {
         let path = self.with_root(dir)?;
         let client = &self.inner.client;
@@ -0,0 +1,126 @@
+use crate::error::WorkerError;
+use crate::store::hadoop::{FileStatus, HdfsDelegator};
+use crate::store::BytesWrapper;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use dashmap::DashMap;
+use std::collections::HashSet;
+use url::Url;
+
+/// The in-process remote storage backend with the `memory://` scheme, which keeps the files
+/// in the memory without any external services. It's only used to verify the cold-tier
+/// data path in tests.
+pub struct MemoryRemoteClient {
+    root: String,
+    // key: the complete file path with root
+    files: DashMap<String, BytesMut>,
+}
+
+impl MemoryRemoteClient {
+    pub(crate) fn new(root: &str) -> Result<MemoryRemoteClient> {
+        let url = Url::parse(root)?;
+        let root_path = url.path().trim_end_matches('/');
+        Ok(Self {
+            root: root_path.to_string(),
+            files: DashMap::new(),
+        })
+    }
+
+    fn normalize(&self, path: &str) -> Result<String> {
+        let path = self.with_root(path)?;
+        Ok(path.trim_end_matches('/').to_string())
+    }
+}
+
+#[async_trait]
+impl HdfsDelegator for MemoryRemoteClient {
+    async fn touch(&self, file_path: &str) -> Result<()> {
+        let path = self.normalize(file_path)?;
+        self.files.entry(path).or_insert_with(BytesMut::new);
+        Ok(())
+    }
+
+    async fn append(&self, file_path: &str, data: BytesWrapper) -> Result<(), WorkerError> {
+        let path = self.normalize(file_path)?;
+        let mut file = self
+            .files
+            .get_mut(&path)
+            .ok_or(anyhow!("No such file: {}", &path))?;
+        file.extend_from_slice(&data.freeze());
+        Ok(())
+    }
+
+    async fn len(&self, file_path: &str) -> Result<u64> {
+        let path = self.normalize(file_path)?;
+        let file = self
+            .files
+            .get(&path)
+            .ok_or(anyhow!("No such file: {}", &path))?;
+        Ok(file.len() as u64)
+    }
+
+    async fn read(&self, file_path: &str, offset: u64, length: u64) -> Result<Bytes, WorkerError> {
+        let path = self.normalize(file_path)?;
+        let file = self
+            .files
+            .get(&path)
+            .ok_or(anyhow!("No such file: {}", &path))?;
+        let (start, end) = (offset as usize, (offset + length) as usize);
+        if end > file.len() {
+            return Err(WorkerError::Other(anyhow!(
+                "Out of range reading of {}. offset: {}, length: {}, file len: {}",
+                &path,
+                offset,
+                length,
+                file.len()
+            )));
+        }
+        Ok(Bytes::copy_from_slice(&file[start..end]))
+    }
+
+    async fn create_dir(&self, _dir: &str) -> Result<()> {
+        // the dirs are implicit with the file paths
+        Ok(())
+    }
+
+    async fn delete_dir(&self, dir: &str) -> Result<(), WorkerError> {
+        let prefix = format!("{}/", self.normalize(dir)?);
+        self.files.retain(|path, _| !path.starts_with(&prefix));
+        Ok(())
+    }
+
+    async fn delete_file(&self, file_path: &str) -> Result<(), WorkerError> {
+        let path = self.normalize(file_path)?;
+        self.files.remove(&path);
+        Ok(())
+    }
+
+    async fn list_status(&self, dir: &str) -> Result<Vec<FileStatus>, WorkerError> {
+        let prefix = format!("{}/", self.normalize(dir)?);
+        let mut visited = HashSet::new();
+        let mut result = vec![];
+        for entry in self.files.iter() {
+            let relative = match entry.key().strip_prefix(&prefix) {
+                Some(relative) => relative,
+                None => continue,
+            };
+            let (name, is_dir) = match relative.split_once('/') {
+                Some((name, _)) => (name, true),
+                None => (relative, false),
+            };
+            let path = format!("{}{}", &prefix, name);
+            if visited.insert(path.clone()) {
+                result.push(FileStatus {
+                    path: self.without_root(&path)?,
+                    is_dir,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    fn root(&self) -> String {
+        self.root.to_string()
+    }
+}
@@ -2,24 +2,35 @@
 mod hdfs_native;
 #[cfg(feature = "hdrs")]
 mod hdrs;
+// only for the tests of the cold-tier data path without the external services
+#[cfg(all(feature = "hdfs", test))]
+mod memory;
 
 #[cfg(feature = "hdfs")]
 use crate::store::hadoop::hdfs_native::HdfsNativeClient;
 #[cfg(feature = "hdrs")]
 use crate::store::hadoop::hdrs::HdrsClient;
+#[cfg(all(feature = "hdfs", test))]
+use crate::store::hadoop::memory::MemoryRemoteClient;
 
 use crate::error::WorkerError;
 use crate::store::BytesWrapper;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use bytes::Bytes;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use url::Url;
+
+#[cfg(test)]
+pub const MEMORY_SCHEME: &str = "memory";
 
 #[async_trait]
 pub(crate) trait HdfsDelegator: Send + Sync {
     async fn touch(&self, file_path: &str) -> Result<()>;
     async fn append(&self, file_path: &str, data: BytesWrapper) -> Result<(), WorkerError>;
     async fn len(&self, file_path: &str) -> Result<u64>;
+    async fn read(&self, file_path: &str, offset: u64, length: u64) -> Result<Bytes, WorkerError>;
 
     async fn create_dir(&self, dir: &str) -> Result<()>;
     async fn delete_dir(&self, dir: &str) -> Result<(), WorkerError>;
@@ -51,10 +62,30 @@ pub(crate) trait HdfsDelegator: Send + Sync {
     }
 }
 
+/// Picks the remote storage backend by the scheme of root, like `hdfs://ns/a`, while the
+/// `memory://a` is only available in tests.
 #[cfg(feature = "hdfs")]
 pub fn get_hdfs_delegator(
     root: &str,
     configs: HashMap<String, String>,
+) -> Result<Box<dyn HdfsDelegator>> {
+    let url = Url::parse(root)?;
+    match url.scheme() {
+        "hdfs" | "viewfs" => get_hadoop_delegator(root, configs),
+        #[cfg(test)]
+        MEMORY_SCHEME => Ok(Box::new(MemoryRemoteClient::new(root)?)),
+        scheme => Err(anyhow!(
+            "Unsupported remote storage scheme: {} of root: {}",
+            scheme,
+            root
+        )),
+    }
+}
+
+#[cfg(feature = "hdfs")]
+fn get_hadoop_delegator(
+    root: &str,
+    configs: HashMap<String, String>,
 ) -> Result<Box<dyn HdfsDelegator>> {
     #[cfg(not(feature = "hdrs"))]
     return Ok(Box::new(HdfsNativeClient::new(root.to_owned(), configs)?));
@@ -16,10 +16,11 @@
 // under the License.
 
 use crate::app::{
-    PartitionedUId, PurgeDataContext, PurgeReason, ReadingIndexViewContext, ReadingViewContext,
-    RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
-    SHUFFLE_SERVER_ID,
+    PartitionedUId, PurgeBatch, PurgeDataContext, PurgeReason, ReadingIndexViewContext,
+    ReadingViewContext, RegisterAppContext, ReleaseTicketContext, RemoteStorageConfig,
+    RequireBufferContext, WritingViewContext, SHUFFLE_SERVER_ID,
 };
+use crate::chaos::{FaultOperation, FAULT_INJECTOR};
 use crate::config::{HdfsStoreConfig, StorageType};
 use crate::error::WorkerError;
 
@@ -37,6 +38,7 @@ use dashmap::DashMap;
 
 use log::{error, info, warn};
 
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use crate::error::WorkerError::Other;
@@ -85,6 +87,9 @@ pub struct HdfsStore {
     // key: app_id, value: hdfs_native_client
     pub(crate) app_remote_clients: DashMap<String, Arc<LazyInit<Box<dyn HdfsDelegator>>>>,
 
+    // key: app_id, value: the remote storage root of the current client
+    app_remote_roots: DashMap<String, String>,
+
     // key: data_file_path
     partition_file_locks: DashMap<String, Arc<SemaphoreWithIndex>>,
 
@@ -103,6 +108,49 @@ unsafe impl Sync for HdfsStore {}
 impl Persistent for HdfsStore {}
 
 impl HdfsStore {
+    /// The written bytes of all the cached partitions.
+    pub fn resident_bytes(&self) -> u64 {
+        self.partition_cached_meta
+            .iter()
+            .map(|entry| entry.value().data_len as u64)
+            .sum()
+    }
+
+    /// Rebuilds the app's remote client with the rotated remote storage config.
+    /// The in-flight appends keep the old client they have cloned, and the
+    /// subsequent appends pick up the new one. When the root is changed, the
+    /// cached writing handlers are dropped to recreate the files under the new root.
+    pub fn update_remote_client(&self, app_id: &str, conf: RemoteStorageConfig) -> Result<()> {
+        if !self.app_remote_clients.contains_key(app_id) {
+            return Err(anyhow!(
+                "The app:[{}] has not been registered in the hdfs store",
+                app_id
+            ));
+        }
+
+        let root = conf.root.to_string();
+        let client = LazyInit::new(move || {
+            get_hdfs_delegator(conf.root.as_str(), conf.configs)
+                .expect("Errors on getting hdfs client")
+        });
+        self.app_remote_clients
+            .insert(app_id.to_owned(), Arc::new(client));
+
+        let previous_root = self
+            .app_remote_roots
+            .insert(app_id.to_owned(), root.clone());
+        if previous_root.as_ref() != Some(&root) {
+            let app_dir = self.get_app_dir(app_id);
+            self.partition_cached_meta
+                .retain(|key, _| !key.starts_with(app_dir.as_str()));
+        }
+        info!(
+            "The remote client of app:[{}] has been rebuilt. root: {:?} -> {}",
+            app_id, previous_root, &root
+        );
+        Ok(())
+    }
+
     pub fn from(conf: HdfsStoreConfig, runtime_manager: &RuntimeManager) -> Self {
         if let Some(kerberos_config) = &conf.kerberos_security_config {
             if let Err(e) = KerberosTask::init(&runtime_manager, kerberos_config) {
@@ -117,6 +165,7 @@ impl HdfsStore {
             concurrency_access_limiter: Semaphore::new(conf.max_concurrency),
             partition_cached_meta: Default::default(),
             app_remote_clients: Default::default(),
+            app_remote_roots: Default::default(),
             runtime_manager: runtime_manager.clone(),
 
             partition_write_concurrency: conf.partition_write_max_concurrency,
@@ -145,6 +194,12 @@ impl HdfsStore {
         )
     }
 
+    /// Whether the partition data has ever been flushed into hdfs by this worker.
+    pub fn contains_partition(&self, uid: &PartitionedUId) -> bool {
+        let (data_file_path, _) = self.get_file_path_prefix_by_uid(uid);
+        self.partition_file_locks.contains_key(&data_file_path)
+    }
+
     async fn data_insert(
         &self,
         uid: PartitionedUId,
@@ -153,6 +208,9 @@ impl HdfsStore {
         if !self.is_healthy().await? {
             return Err(WorkerError::HDFS_UNHEALTHY);
         }
+        FAULT_INJECTOR
+            .inject(StorageType::HDFS, None, FaultOperation::APPEND)
+            .await?;
 
         let _ = self
             .concurrency_access_limiter
@@ -336,6 +394,36 @@ impl HdfsStore {
         Ok(())
     }
 
+    async fn delete_purged_dir(
+        &self,
+        filesystem: &Box<dyn HdfsDelegator>,
+        dir: &str,
+        delete_all: bool,
+    ) -> Result<()> {
+        if delete_all {
+            let timer = Instant::now();
+            filesystem.delete_dir(dir).await?;
+            info!(
+                "The hdfs data of path[{}] has been deleted that cost [{}]ms",
+                dir,
+                timer.elapsed().as_millis()
+            );
+        } else {
+            let timer = Instant::now();
+            let prefix = SHUFFLE_SERVER_ID.get().unwrap().as_str();
+            match self.delete_recursively(filesystem, dir, prefix).await {
+                Ok(_) => {}
+                Err(WorkerError::DIR_OR_FILE_NOT_FOUND(_)) => {
+                    warn!("The internal hdfs file or dir is not found for path[{}]. Maybe this is also being deleted by other shuffle-servers. Ignore this!", dir);
+                }
+                Err(e) => return Err(anyhow::Error::from(e)),
+            }
+            info!("The hdfs data of path[{}] with prefix[{}] has been deleted recursively that costs [{}]ms",
+                dir, prefix, timer.elapsed().as_millis());
+        }
+        Ok(())
+    }
+
     async fn delete_recursively(
         &self,
         filesystem: &Box<dyn HdfsDelegator>,
@@ -392,7 +480,14 @@ impl Store for HdfsStore {
     async fn purge(&self, ctx: &PurgeDataContext) -> Result<i64> {
         let (app_id, shuffle_id_option) = ctx.extract();
 
+        // the whole app purge is not interruptible once the remote client is released.
+        if ctx.is_cancelled() {
+            info!("The purge of app:[{}] is cancelled", &app_id);
+            return Err(WorkerError::PURGE_CANCELLED(app_id).into());
+        }
+
         let fs_option = if shuffle_id_option.is_none() {
+            self.app_remote_roots.remove(&app_id);
             let fs = self.app_remote_clients.remove(&app_id);
             if fs.is_none() {
                 None
@@ -429,56 +524,96 @@ impl Store for HdfsStore {
             .filter(|entry| entry.key().starts_with(dir.as_str()))
             .map(|entry| entry.key().to_string())
             .collect();
+        if keys_to_delete.is_empty() {
+            return Ok(0);
+        }
 
+        // app level purge if the app heartbeat is timeout or explicitly purge.
+        // 1. But if the app heartbeat is timeout, we should only delete this server's own written files
+        // 2. If the app is explicitly unregistered, delete all basic directory.
+        // The detailed info could be referred from https://github.com/apache/incubator-uniffle/pull/1681
+        let is_app_level_explicit_unregister =
+            if let PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(_) = ctx.purge_reason {
+                true
+            } else {
+                false
+            };
+        let delete_all = shuffle_id_option.is_some() || is_app_level_explicit_unregister;
+
+        // the purge progress is reported in the shuffle level batches.
+        let mut shuffle_keys: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+        for key in keys_to_delete {
+            let shuffle_id = key
+                .strip_prefix(self.get_app_dir(app_id.as_str()).as_str())
+                .and_then(|path| path.split('/').next())
+                .and_then(|shuffle_id| shuffle_id.parse().ok())
+                .unwrap_or(-1);
+            shuffle_keys.entry(shuffle_id).or_default().push(key);
+        }
+
+        let mut batches = vec![];
         let mut removed_size = 0i64;
-        for deleted_key in &keys_to_delete {
-            self.partition_file_locks.remove(deleted_key);
-            for idx in 0..self.partition_write_concurrency {
-                let prefix = format!("{}_{}", &deleted_key, idx);
-                if let Some(meta) = self.partition_cached_meta.remove(&prefix) {
-                    removed_size += meta.1.data_len;
+        for (shuffle_id, keys) in shuffle_keys {
+            let mut removed_bytes = 0i64;
+            let mut removed_files = 0u64;
+            for deleted_key in &keys {
+                self.partition_file_locks.remove(deleted_key);
+                for idx in 0..self.partition_write_concurrency {
+                    let prefix = format!("{}_{}", &deleted_key, idx);
+                    if let Some(meta) = self.partition_cached_meta.remove(&prefix) {
+                        removed_bytes += meta.1.data_len;
+                        // the data and index files
+                        removed_files += 2;
+                    }
                 }
             }
+            removed_size += removed_bytes;
+            batches.push(PurgeBatch {
+                shuffle_id: Some(shuffle_id),
+                removed_bytes,
+                removed_files,
+            });
         }
 
-        if !keys_to_delete.is_empty() {
-            // app level purge if the app heartbeat is timeout or explicitly purge.
-            // 1. But if the app heartbeat is timeout, we should only delete this server's own written files
-            // 2. If the app is explicitly unregistered, delete all basic directory.
-            // The detailed info could be referred from https://github.com/apache/incubator-uniffle/pull/1681
-
-            let is_app_level_explicit_unregister =
-                if let PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(_) = ctx.purge_reason {
-                    true
-                } else {
-                    false
-                };
-            if shuffle_id_option.is_some() || is_app_level_explicit_unregister {
-                let timer = Instant::now();
-                filesystem.delete_dir(dir.as_str()).await?;
-                info!(
-                    "The hdfs data of path[{}] has been deleted that cost [{}]ms",
-                    &dir,
-                    timer.elapsed().as_millis()
-                );
-            } else {
-                let timer = Instant::now();
-                let prefix = SHUFFLE_SERVER_ID.get().unwrap().as_str();
-                match self
-                    .delete_recursively(&filesystem, dir.as_str(), prefix)
-                    .await
-                {
-                    Ok(_) => {}
-                    Err(WorkerError::DIR_OR_FILE_NOT_FOUND(e)) => {
-                        warn!("The internal hdfs file or dir is not found for path[{}]. Maybe this is also being deleted by other shuffle-servers. Ignore this!", &dir);
-                    }
-                    Err(e) => return Err(anyhow::Error::from(e)),
-                }
-                info!("The hdfs data of path[{}] with prefix[{}] has been deleted recursively that costs [{}]ms",
-                    &dir, prefix, timer.elapsed().as_millis());
+        // the whole purged dir is deleted at once, which has covered all the shuffle dirs
+        // of the app level purge.
+        self.delete_purged_dir(filesystem, dir.as_str(), delete_all)
+            .await?;
+        for batch in batches {
+            ctx.report_batch(batch);
+        }
+
+        Ok(removed_size)
+    }
+
+    async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64> {
+        let (data_file_path, _) = self.get_file_path_prefix_by_uid(&uid);
+        if self.partition_file_locks.remove(&data_file_path).is_none() {
+            return Ok(0);
+        }
+        let mut removed_size = 0i64;
+        for idx in 0..self.partition_write_concurrency {
+            let prefix = format!("{}_{}", &data_file_path, idx);
+            if let Some(meta) = self.partition_cached_meta.remove(&prefix) {
+                removed_size += meta.1.data_len;
             }
         }
 
+        let fs = match self.app_remote_clients.get(&uid.app_id) {
+            Some(fs) => fs.clone(),
+            _ => return Ok(removed_size),
+        };
+        if !fs.is_initialized() {
+            return Ok(removed_size);
+        }
+        let filesystem = fs.get_or_init();
+        // only the files written by this worker are deleted
+        let partition_dir = match Path::new(data_file_path.as_str()).parent() {
+            Some(dir) => format!("{}/", dir.to_str().unwrap()),
+            _ => return Ok(removed_size),
+        };
+        self.delete_purged_dir(filesystem, partition_dir.as_str(), false)
+            .await?;
         Ok(removed_size)
     }
 
@@ -506,6 +641,7 @@ impl Store for HdfsStore {
         }
 
         let remote_storage_conf = remote_storage_conf_option.unwrap();
+        let root = remote_storage_conf.root.to_string();
         let client = LazyInit::new(move || {
             get_hdfs_delegator(
                 remote_storage_conf.root.as_str(),
@@ -515,6 +651,7 @@ impl Store for HdfsStore {
         });
 
         let app_id = ctx.app_id.clone();
+        self.app_remote_roots.entry(app_id.clone()).or_insert(root);
         self.app_remote_clients
             .entry(app_id)
             .or_insert_with(|| Arc::new(client));
@@ -526,17 +663,8 @@ impl Store for HdfsStore {
     }
 
     async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
-        let uid = ctx.uid;
-        let mut data = vec![];
-        let batch_memory_block = ctx.data_blocks;
-        for blocks in batch_memory_block.iter() {
-            for block in blocks {
-                data.push(block);
-            }
-        }
-        // for AQE
-        data.sort_by_key(|block| block.task_attempt_id);
-        self.data_insert(uid, data)
+        let data = ctx.ordered_blocks();
+        self.data_insert(ctx.uid.clone(), data)
             .instrument_await("data insert")
             .await
     }
@@ -544,6 +672,7 @@ impl Store for HdfsStore {
 
 #[cfg(test)]
 mod tests {
+    use crate::app::{AppConfigOptions, DataDistribution, RegisterAppContext, RemoteStorageConfig};
     use crate::app::{PartitionedUId, PurgeReason, SHUFFLE_SERVER_ID};
     use crate::app::{PurgeDataContext, WritingViewContext};
     use crate::config::HdfsStoreConfig;
@@ -551,8 +680,9 @@ mod tests {
     use crate::lazy_initializer::LazyInit;
     use crate::runtime::manager::RuntimeManager;
     use crate::semaphore_with_index::SemaphoreWithIndex;
-    use crate::store::hadoop::{FileStatus, HdfsDelegator};
+    use crate::store::hadoop::{get_hdfs_delegator, FileStatus, HdfsDelegator};
     use crate::store::hdfs::HdfsStore;
+    use crate::store::index_codec::{IndexCodec, INDEX_BLOCK_SIZE};
     use crate::store::{Block, BytesWrapper, Store};
     use anyhow::anyhow;
     use async_trait::async_trait;
@@ -560,6 +690,7 @@ mod tests {
     use log::info;
     use std::fs;
     use std::fs::File;
+    use std::io::Write;
     use std::path::Path;
     use std::sync::atomic::AtomicBool;
     use std::sync::atomic::Ordering::SeqCst;
@@ -619,6 +750,15 @@ mod tests {
             Ok(1)
         }
 
+        async fn read(
+            &self,
+            file_path: &str,
+            offset: u64,
+            length: u64,
+        ) -> anyhow::Result<Bytes, WorkerError> {
+            Ok(Bytes::from(vec![0; length as usize]))
+        }
+
         async fn create_dir(&self, dir: &str) -> anyhow::Result<()> {
             Ok(())
         }
@@ -722,6 +862,19 @@ mod tests {
                 Ok(1)
             }
 
+            async fn read(
+                &self,
+                file_path: &str,
+                offset: u64,
+                length: u64,
+            ) -> anyhow::Result<Bytes, WorkerError> {
+                let path = self.with_root(file_path)?;
+                let data = fs::read(path)?;
+                Ok(Bytes::copy_from_slice(
+                    &data[offset as usize..(offset + length) as usize],
+                ))
+            }
+
             async fn create_dir(&self, dir: &str) -> anyhow::Result<()> {
                 let path = self.with_root(dir)?;
                 fs::create_dir_all(path)?;
@@ -851,6 +1004,7 @@ mod tests {
                     "partial_delete_test".to_string(),
                     1,
                 ),
+                progress_tracker: None,
             }))?;
         assert_eq!(2 + 1, file_number_recursively(temp_path.as_str()));
         println!("Done with shuffle_level purge");
@@ -862,6 +1016,7 @@ mod tests {
                 purge_reason: PurgeReason::APP_LEVEL_HEARTBEAT_TIMEOUT(
                     "partial_delete_test".to_string(),
                 ),
+                progress_tracker: None,
             }))?;
         assert_eq!(1, file_number_recursively(temp_path.as_str()));
         println!("Done with heartbeat timeout app level purge");
@@ -967,6 +1122,7 @@ mod tests {
             .default_runtime
             .block_on(hdfs_store.purge(&PurgeDataContext {
                 purge_reason: PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned()),
+                progress_tracker: None,
             }))?;
         assert_eq!(0, hdfs_store.app_remote_clients.len());
         assert_eq!(0, hdfs_store.partition_cached_meta.len());
@@ -974,4 +1130,252 @@ mod tests {
 
         Ok(())
     }
+
+    /// The hdfs mock backed by the local filesystem under the root.
+    struct LocalFsRemoteClient {
+        root: String,
+    }
+
+    #[async_trait]
+    impl HdfsDelegator for LocalFsRemoteClient {
+        async fn touch(&self, file_path: &str) -> anyhow::Result<()> {
+            let path = self.with_root(file_path)?;
+            File::create(path)?;
+            Ok(())
+        }
+
+        async fn append(
+            &self,
+            file_path: &str,
+            data: BytesWrapper,
+        ) -> anyhow::Result<(), WorkerError> {
+            let path = self.with_root(file_path)?;
+            let mut file = fs::OpenOptions::new().append(true).open(path)?;
+            file.write_all(&data.freeze())?;
+            Ok(())
+        }
+
+        async fn len(&self, file_path: &str) -> anyhow::Result<u64> {
+            let path = self.with_root(file_path)?;
+            Ok(fs::metadata(path)?.len())
+        }
+
+        async fn read(
+            &self,
+            file_path: &str,
+            offset: u64,
+            length: u64,
+        ) -> anyhow::Result<Bytes, WorkerError> {
+            let path = self.with_root(file_path)?;
+            let data = fs::read(&path)?;
+            let end = (offset + length) as usize;
+            if end > data.len() {
+                return Err(WorkerError::Other(anyhow!(
+                    "Out of range reading of {}",
+                    &path
+                )));
+            }
+            Ok(Bytes::copy_from_slice(&data[offset as usize..end]))
+        }
+
+        async fn create_dir(&self, dir: &str) -> anyhow::Result<()> {
+            let path = self.with_root(dir)?;
+            fs::create_dir_all(path)?;
+            Ok(())
+        }
+
+        async fn delete_dir(&self, dir: &str) -> anyhow::Result<(), WorkerError> {
+            let path = self.with_root(dir)?;
+            fs::remove_dir_all(path)?;
+            Ok(())
+        }
+
+        async fn delete_file(&self, file_path: &str) -> anyhow::Result<(), WorkerError> {
+            let path = self.with_root(file_path)?;
+            fs::remove_file(path)?;
+            Ok(())
+        }
+
+        async fn list_status(&self, dir: &str) -> anyhow::Result<Vec<FileStatus>, WorkerError> {
+            let path = self.with_root(dir)?;
+            let mut result = vec![];
+            for status in fs::read_dir(path)? {
+                let status = status?;
+                let path = status.path().as_path().to_str().unwrap().to_string();
+                result.push(FileStatus {
+                    path: self.without_root(path.as_str())?,
+                    is_dir: status.metadata()?.is_dir(),
+                });
+            }
+            Ok(result)
+        }
+
+        fn root(&self) -> String {
+            self.root.to_string()
+        }
+    }
+
+    /// Spills the blocks into the remote backend, then reads them back by the index and
+    /// purges them.
+    fn spill_read_cycle(
+        app_id: &str,
+        client: Arc<LazyInit<Box<dyn HdfsDelegator>>>,
+    ) -> anyhow::Result<()> {
+        let config = HdfsStoreConfig::default();
+        let runtime_manager = RuntimeManager::default();
+        let hdfs_store = HdfsStore::from(config, &runtime_manager);
+        hdfs_store
+            .app_remote_clients
+            .insert(app_id.to_owned(), client.clone());
+
+        let blocks = vec![
+            Block {
+                block_id: 0,
+                length: 10i32,
+                uncompress_length: 200,
+                crc: 0,
+                data: Bytes::copy_from_slice(&vec![1; 10]),
+                task_attempt_id: 0,
+            },
+            Block {
+                block_id: 1,
+                length: 20i32,
+                uncompress_length: 200,
+                crc: 0,
+                data: Bytes::copy_from_slice(&vec![2; 20]),
+                task_attempt_id: 0,
+            },
+        ];
+        let uid = PartitionedUId::from(app_id.to_owned(), 1, 1);
+        let writing_ctx = WritingViewContext::create_for_test(uid, blocks);
+        runtime_manager
+            .default_runtime
+            .block_on(hdfs_store.insert(writing_ctx))?;
+
+        let client = client.get_or_init();
+        let data_file = format!("{}/1/1-1/10.0.0.1_0_0.data", app_id);
+        let index_file = format!("{}/1/1-1/10.0.0.1_0_0.index", app_id);
+        let runtime = &runtime_manager.default_runtime;
+        assert_eq!(30, runtime.block_on(client.len(&data_file))?);
+        assert_eq!(
+            2 * INDEX_BLOCK_SIZE as u64,
+            runtime.block_on(client.len(&index_file))?
+        );
+
+        let index = runtime.block_on(client.read(
+            &index_file,
+            INDEX_BLOCK_SIZE as u64,
+            INDEX_BLOCK_SIZE as u64,
+        ))?;
+        let index_block = IndexCodec::decode(index)?;
+        assert_eq!(1, index_block.block_id);
+        let data = runtime.block_on(client.read(
+            &data_file,
+            index_block.offset as u64,
+            index_block.length as u64,
+        ))?;
+        assert_eq!(vec![2; 20], data.to_vec());
+        assert!(runtime.block_on(client.read(&data_file, 20, 20)).is_err());
+
+        let status = runtime.block_on(client.list_status(&format!("{}/1/1-1", app_id)))?;
+        assert_eq!(2, status.len());
+
+        runtime.block_on(hdfs_store.purge(&PurgeDataContext {
+            purge_reason: PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            progress_tracker: None,
+        }))?;
+        assert!(runtime.block_on(client.len(&data_file)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn remote_backend_spill_read_test() -> anyhow::Result<()> {
+        SHUFFLE_SERVER_ID.get_or_init(|| "10.0.0.1".to_owned());
+
+        assert!(get_hdfs_delegator("s3://bucket/a", Default::default()).is_err());
+
+        // case1: the hdfs mock backed by the local filesystem
+        let temp_dir = tempdir::TempDir::new("remote_backend_spill_read_test")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        spill_read_cycle(
+            "remote_backend_spill_read_test_local",
+            Arc::new(LazyInit::new(move || {
+                let client: Box<dyn HdfsDelegator> = Box::new(LocalFsRemoteClient { root });
+                client
+            })),
+        )?;
+
+        // case2: the in-memory backend selected by the scheme
+        spill_read_cycle(
+            "remote_backend_spill_read_test_memory",
+            Arc::new(LazyInit::new(|| {
+                get_hdfs_delegator("memory://bucket/base", Default::default()).unwrap()
+            })),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_remote_client_test() -> anyhow::Result<()> {
+        SHUFFLE_SERVER_ID.get_or_init(|| "10.0.0.1".to_owned());
+        let app_id = "update_remote_client_test";
+
+        let config = HdfsStoreConfig::default();
+        let runtime_manager = RuntimeManager::default();
+        let runtime = &runtime_manager.default_runtime;
+        let hdfs_store = HdfsStore::from(config, &runtime_manager);
+
+        let remote_storage_config = |root: &str| RemoteStorageConfig {
+            root: root.to_string(),
+            configs: Default::default(),
+        };
+        hdfs_store.register_app(RegisterAppContext {
+            app_id: app_id.to_string(),
+            app_config_options: AppConfigOptions::new(
+                DataDistribution::NORMAL,
+                20,
+                Some(remote_storage_config("memory://bucket/old")),
+            ),
+        })?;
+        assert!(hdfs_store
+            .update_remote_client("unknown_app", remote_storage_config("memory://bucket/new"))
+            .is_err());
+
+        let uid = PartitionedUId::from(app_id.to_owned(), 1, 1);
+        let writing_ctx = WritingViewContext::create_for_test(
+            uid,
+            vec![Block {
+                block_id: 0,
+                length: 10i32,
+                uncompress_length: 200,
+                crc: 0,
+                data: Bytes::copy_from_slice(&vec![1; 10]),
+                task_attempt_id: 0,
+            }],
+        );
+        runtime.block_on(hdfs_store.insert(writing_ctx.clone()))?;
+        let old_client = hdfs_store.app_remote_clients.get(app_id).unwrap().clone();
+        assert_eq!("/old", old_client.get_or_init().root());
+
+        // rotate the config and the subsequent appends go into the new root
+        hdfs_store.update_remote_client(app_id, remote_storage_config("memory://bucket/new"))?;
+        let new_client = hdfs_store.app_remote_clients.get(app_id).unwrap().clone();
+        assert!(!new_client.is_initialized());
+        assert_eq!("/new", new_client.get_or_init().root());
+
+        runtime.block_on(hdfs_store.insert(writing_ctx))?;
+        let data_file = format!("{}/1/1-1/10.0.0.1_0_0.data", app_id);
+        assert_eq!(
+            10,
+            runtime.block_on(new_client.get_or_init().len(&data_file))?
+        );
+        assert_eq!(
+            10,
+            runtime.block_on(old_client.get_or_init().len(&data_file))?
+        );
+
+        Ok(())
+    }
 }
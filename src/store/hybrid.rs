@@ -16,9 +16,9 @@
 // under the License.
 
 use crate::app::{
-    AppManagerRef, PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingOptions,
-    ReadingViewContext, RegisterAppContext, ReleaseTicketContext, RequireBufferContext,
-    WritingViewContext,
+    AppManagerRef, PartitionedUId, PurgeBatch, PurgeDataContext, ReadingIndexViewContext,
+    ReadingOptions, ReadingViewContext, RegisterAppContext, ReleaseTicketContext,
+    RemoteStorageConfig, RequireBufferContext, WritingViewContext,
 };
 
 use crate::config::{Config, HybridStoreConfig, StorageType};
@@ -26,32 +26,39 @@ use crate::error::WorkerError;
 use crate::metric::{
     GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES, GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_OF_HUGE_PARTITION,
     GAUGE_MEMORY_SPILL_TO_HDFS, GAUGE_MEMORY_SPILL_TO_LOCALFILE,
-    MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM, TOTAL_MEMORY_SPILL_BYTES, TOTAL_MEMORY_SPILL_TO_HDFS,
-    TOTAL_MEMORY_SPILL_TO_LOCALFILE,
+    MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM, TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL,
+    TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL_BYTES, TOTAL_MEMORY_SPILL_BYTES,
+    TOTAL_MEMORY_SPILL_TO_HDFS, TOTAL_MEMORY_SPILL_TO_LOCALFILE, TOTAL_SPILLED_READ_RETRY,
+    TOTAL_SPILL_EVENTS_CANCELLED_BY_PURGE, TOTAL_WATERMARK_SPILL_BYTES,
 };
 use crate::readable_size::ReadableSize;
 #[cfg(feature = "hdfs")]
 use crate::store::hdfs::HdfsStore;
-use crate::store::localfile::LocalFileStore;
+use crate::store::localfile::{IndexRepairOutcome, LocalFileStore, RebalanceProgress};
 use crate::store::memory::MemoryStore;
+use crate::util::run_until_deadline;
 
-use crate::store::{Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex, Store};
+use crate::store::{
+    PartitionFlushState, PartitionLocation, Persistent, RequireBufferResponse, ResponseData,
+    ResponseDataIndex, Store,
+};
 use anyhow::{anyhow, Result};
 
 use async_trait::async_trait;
+use dashmap::DashMap;
 use log::{error, info, warn};
 use prometheus::core::Atomic;
 use std::any::Any;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ops::Deref;
 
 use await_tree::InstrumentAwait;
 use fastrace::future::FutureExt;
 use once_cell::sync::OnceCell;
 use std::str::FromStr;
-use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
+use std::sync::atomic::{AtomicBool, AtomicU64};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
@@ -66,6 +73,12 @@ use crate::store::spill::storage_select_handler::StorageSelectHandler;
 use crate::store::spill::{SpillMessage, SpillWritingViewContext};
 use tokio::time::Instant;
 
+// the interval between the retried reads of the just spilled partition
+const SPILLED_READ_RETRY_INTERVAL_MS: u64 = 20;
+// the interval and the max waiting time of the reading at the not yet visible write sequence
+const WRITE_SEQUENCE_WAIT_INTERVAL_MS: u64 = 10;
+const WRITE_SEQUENCE_WAIT_MAX_MS: u64 = 1000;
+
 pub trait PersistentStore: Store + Persistent + Send + Sync + Any {
     fn as_any(&self) -> &dyn Any;
 }
@@ -91,7 +104,7 @@ pub struct HybridStore {
     pub(crate) warm_store: Option<Box<dyn PersistentStore>>,
     pub(crate) cold_store: Option<Box<dyn PersistentStore>>,
 
-    config: HybridStoreConfig,
+    pub(crate) config: HybridStoreConfig,
 
     async_watermark_spill_enable: bool,
 
@@ -113,6 +126,20 @@ pub struct HybridStore {
 
     huge_partition_memory_spill_to_hdfs_threshold_size: u64,
 
+    // once draining, the new data will be rejected and the buffered data will be flushed
+    draining: AtomicBool,
+
+    // key: app_id, val: the unfinished spill events keyed by (shuffle_id, partition_id, flight_id),
+    // which will be cancelled on purge.
+    pending_spill_events: DashMap<String, DashMap<(i32, i32, u64), SpillMessage>>,
+
+    // the number of the failed spill events waiting for the retry
+    spill_retry_queue_size: AtomicU64,
+
+    // key: app_id, val: the latest spill finished time keyed by (shuffle_id, partition_id),
+    // which is used to retry the empty reads in the memory to disk handoff.
+    spilled_partitions: DashMap<String, DashMap<(i32, i32), Instant>>,
+
     // Only for test
     sensitive_watermark_spill_tag: OnceCell<()>,
 }
@@ -186,6 +213,10 @@ impl HybridStore {
             huge_partition_memory_spill_to_hdfs_threshold_size,
             in_flight_bytes_of_huge_partition: Default::default(),
             sensitive_watermark_spill_tag: Default::default(),
+            draining: AtomicBool::new(false),
+            pending_spill_events: DashMap::new(),
+            spill_retry_queue_size: Default::default(),
+            spilled_partitions: DashMap::new(),
         };
         store
     }
@@ -199,7 +230,15 @@ impl HybridStore {
         GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES.add(bytes_size as i64);
     }
 
+    fn spill_event_key(msg: &SpillMessage) -> (i32, i32, u64) {
+        let uid = &msg.ctx.uid;
+        (uid.shuffle_id, uid.partition_id, msg.flight_id)
+    }
+
     pub fn finish_spill_event(&self, msg: &SpillMessage) {
+        if let Some(events) = self.pending_spill_events.get(&msg.ctx.uid.app_id) {
+            events.remove(&HybridStore::spill_event_key(msg));
+        }
         let bytes_size = msg.size as u64;
         self.memory_spill_event_num.fetch_sub(1, SeqCst);
         self.in_flight_bytes.fetch_sub(bytes_size, SeqCst);
@@ -214,6 +253,57 @@ impl HybridStore {
         }
     }
 
+    pub fn mark_spilled(&self, uid: &PartitionedUId) {
+        if self.config.spilled_read_retry_window_ms == 0 {
+            return;
+        }
+        self.spilled_partitions
+            .entry(uid.app_id.to_owned())
+            .or_default()
+            .insert((uid.shuffle_id, uid.partition_id), Instant::now());
+    }
+
+    fn is_just_spilled(&self, uid: &PartitionedUId) -> bool {
+        let window = Duration::from_millis(self.config.spilled_read_retry_window_ms);
+        match self.spilled_partitions.get(&uid.app_id) {
+            Some(partitions) => {
+                let key = (uid.shuffle_id, uid.partition_id);
+                let spilled_at = partitions.get(&key).map(|x| *x);
+                match spilled_at {
+                    Some(spilled_at) if spilled_at.elapsed() <= window => true,
+                    Some(_) => {
+                        partitions.remove(&key);
+                        false
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    async fn get_from_persistent(
+        &self,
+        ctx: ReadingViewContext,
+    ) -> Result<ResponseData, WorkerError> {
+        let warm = self.warm_store.as_ref().unwrap();
+        if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+            // the corrupted block could be read from memory if it's still buffered
+            let hot_store = self.hot_store.clone();
+            let uid = ctx.uid.clone();
+            return localfile
+                .get_with_repair(ctx, move |block_id| {
+                    hot_store
+                        .get_buffer(&uid)
+                        .ok()
+                        .and_then(|buffer| buffer.get_block(block_id))
+                        .map(|block| block.data)
+                })
+                .await;
+        }
+        warm.get(ctx).await
+    }
+
     fn is_memory_only(&self) -> bool {
         self.cold_store.is_none() && self.warm_store.is_none()
     }
@@ -235,6 +325,42 @@ impl HybridStore {
         let _ = self.app_manager.set(app_manager_ref.clone());
     }
 
+    /// Reserves the slot of the retry queue for the failed spill event.
+    /// Returns false if the queue is full.
+    pub fn try_enqueue_spill_retry(&self) -> bool {
+        let capacity = self.config.spill_retry_queue_capacity;
+        self.spill_retry_queue_size
+            .fetch_update(SeqCst, SeqCst, |size| {
+                if size < capacity {
+                    Some(size + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn dequeue_spill_retry(&self) {
+        self.spill_retry_queue_size.fetch_sub(1, SeqCst);
+    }
+
+    pub fn get_spill_retry_queue_size(&self) -> u64 {
+        self.spill_retry_queue_size.load(SeqCst)
+    }
+
+    /// Notifies the app that the blocks of the dropped spill event have been lost.
+    pub fn on_spill_event_dropped(&self, message: &SpillMessage) {
+        let uid = &message.ctx.uid;
+        if let Some(app) = self
+            .app_manager
+            .get()
+            .and_then(|app_manager| app_manager.get_app(&uid.app_id))
+        {
+            let blocks = message.ctx.data_blocks.iter().flat_map(|x| x.iter());
+            app.sub_dropped_crc_aggregate(uid, blocks);
+        }
+    }
+
     pub async fn flush_storage_for_buffer(
         &self,
         spill_message: &SpillMessage,
@@ -245,7 +371,7 @@ impl HybridStore {
         }
 
         let retry_cnt = spill_message.get_retry_counter();
-        if retry_cnt >= 3 {
+        if retry_cnt >= self.config.spill_retry_max_attempts {
             let app_id = &spill_message.ctx.uid.app_id;
             return Err(WorkerError::SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(
                 app_id.to_string(),
@@ -392,6 +518,82 @@ impl HybridStore {
         self.hot_store.memory_snapshot()
     }
 
+    /// The free bytes of the memory and localfile stores, which is None if the unbounded
+    /// cold store is used.
+    /// Rejects the buffer requirement when the used ratio of the memory store, including the
+    /// allocated tickets, exceeds the configured ratio.
+    pub fn check_memory_backpressure(&self) -> Result<(), WorkerError> {
+        let reject_ratio = match self.config.require_buffer_reject_ratio {
+            Some(ratio) => ratio,
+            _ => return Ok(()),
+        };
+        let snapshot = self.mem_snapshot()?;
+        let used = snapshot.used() + snapshot.allocated();
+        let capacity = snapshot.capacity();
+        if capacity > 0 && used as f64 / capacity as f64 > reject_ratio {
+            return Err(WorkerError::MEMORY_BACKPRESSURE(used, capacity));
+        }
+        Ok(())
+    }
+
+    pub fn free_capacity(&self) -> Result<Option<u64>> {
+        if self.cold_store.is_some() {
+            return Ok(None);
+        }
+        let memory_free =
+            (self.hot_store.get_capacity()? - self.hot_store.memory_snapshot()?.used()).max(0);
+        let mut free = memory_free as u64;
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                free += localfile.available_bytes()?;
+            }
+        }
+        Ok(Some(free))
+    }
+
+    /// The resident bytes of every storage tier. The memory tier includes the in-flight
+    /// spilling data, and the persistent tiers are the flushed data tracked by the stores.
+    pub async fn resident_bytes_by_tier(&self) -> HashMap<StorageType, u64> {
+        let mut tiers = HashMap::new();
+        tiers.insert(StorageType::MEMORY, self.hot_store.resident_bytes());
+        for store in [self.warm_store.as_ref(), self.cold_store.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            let store = store.as_any();
+            if let Some(localfile) = store.downcast_ref::<LocalFileStore>() {
+                tiers.insert(StorageType::LOCALFILE, localfile.resident_bytes().await);
+            }
+            #[cfg(feature = "hdfs")]
+            if let Some(hdfs) = store.downcast_ref::<HdfsStore>() {
+                tiers.insert(StorageType::HDFS, hdfs.resident_bytes());
+            }
+        }
+        tiers
+    }
+
+    /// Rebuilds the remote clients of the app with the rotated remote storage config.
+    pub fn update_remote_storage_config(
+        &self,
+        app_id: &str,
+        conf: RemoteStorageConfig,
+    ) -> Result<()> {
+        #[cfg(feature = "hdfs")]
+        for store in [self.warm_store.as_ref(), self.cold_store.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(hdfs) = store.as_any().downcast_ref::<HdfsStore>() {
+                return hdfs.update_remote_client(app_id, conf);
+            }
+        }
+        warn!(
+            "No remote store to rotate the remote storage config of app:[{}]. root: {}",
+            app_id, &conf.root
+        );
+        Ok(())
+    }
+
     pub fn localfile_stat(&self) -> Result<LocalfileStoreStat> {
         if let Some(warm) = self.warm_store.as_ref() {
             if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
@@ -401,6 +603,106 @@ impl HybridStore {
         Ok(Default::default())
     }
 
+    pub async fn rebalance_localfile_disks(
+        &self,
+        skew_threshold: f64,
+    ) -> Result<RebalanceProgress> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.rebalance(skew_threshold).await;
+            }
+        }
+        Err(anyhow!("No localfile store to be rebalanced"))
+    }
+
+    pub async fn evacuate_localfile_disks(&self) -> Result<i64> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.evacuate().await;
+            }
+        }
+        Ok(0)
+    }
+
+    pub async fn compact_localfile_partitions(&self) -> Result<i64> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.compact().await;
+            }
+        }
+        Ok(0)
+    }
+
+    fn localfile_compaction_check_interval(&self) -> Option<Duration> {
+        self.warm_store
+            .as_ref()
+            .and_then(|warm| warm.as_any().downcast_ref::<LocalFileStore>())
+            .and_then(|localfile| localfile.compaction_check_interval())
+    }
+
+    fn localfile_evacuation_check_interval(&self) -> Option<Duration> {
+        self.warm_store
+            .as_ref()
+            .and_then(|warm| warm.as_any().downcast_ref::<LocalFileStore>())
+            .and_then(|localfile| localfile.evacuation_check_interval())
+    }
+
+    pub async fn repair_localfile_index(&self, uid: &PartitionedUId) -> Result<IndexRepairOutcome> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.repair_index(uid).await;
+            }
+        }
+        Err(anyhow!("No localfile store to be repaired"))
+    }
+
+    pub fn reclaim_expired_localfiles<F>(&self, max_age: Duration, is_alive: F) -> Result<u64>
+    where
+        F: Fn(&str) -> bool,
+    {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.reclaim_expired_files(max_age, is_alive);
+            }
+        }
+        Ok(0)
+    }
+
+    pub fn localfile_rebalance_progress(&self) -> Result<RebalanceProgress> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return Ok(localfile.get_rebalance_progress());
+            }
+        }
+        Ok(Default::default())
+    }
+
+    pub async fn get_restorable_localfile_partition(
+        &self,
+        uid: &PartitionedUId,
+    ) -> Result<Option<(String, i64)>> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.get_restorable_partition(uid).await;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn restore_localfile_partition(
+        &self,
+        uid: &PartitionedUId,
+        disk_root: &str,
+        offset: i64,
+    ) -> Result<()> {
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                return localfile.restore_partition(uid, disk_root, offset);
+            }
+        }
+        Err(anyhow!("No localfile store to be restored"))
+    }
+
     pub async fn get_memory_buffer(&self, uid: &PartitionedUId) -> Result<Arc<MemoryBuffer>> {
         self.hot_store.get_buffer(uid)
     }
@@ -409,6 +711,50 @@ impl HybridStore {
         self.hot_store.get_buffer_size(uid)
     }
 
+    pub async fn get_partition_flush_state(
+        &self,
+        uid: &PartitionedUId,
+    ) -> Result<PartitionFlushState> {
+        let mut state = PartitionFlushState::default();
+        // the buffer may not exist when it has never been written or has been purged.
+        if let Ok(buffer) = self.hot_store.get_buffer(uid) {
+            state.pending_spill_event_num = buffer.flight_batch_num()?;
+            state.memory_buffered_bytes = buffer.total_size()? as u64;
+        }
+        if let Some(warm) = self.warm_store.as_ref() {
+            if let Some(localfile) = warm.as_any().downcast_ref::<LocalFileStore>() {
+                state.last_flushed_offset = localfile
+                    .get_flushed_offset(uid)
+                    .instrument_await("getting the flushed offset from localfile")
+                    .await?;
+            }
+        }
+        Ok(state)
+    }
+
+    pub async fn get_partition_location(&self, uid: &PartitionedUId) -> Result<PartitionLocation> {
+        let mut location = PartitionLocation::default();
+        if let Ok(buffer) = self.hot_store.get_buffer(uid) {
+            location.memory = buffer.total_size()? > 0;
+        }
+        for store in [self.warm_store.as_ref(), self.cold_store.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(localfile) = store.as_any().downcast_ref::<LocalFileStore>() {
+                location.localfile_disk_root = localfile
+                    .get_partition_disk_root(uid)
+                    .instrument_await("getting the partition disk root from localfile")
+                    .await?;
+            }
+            #[cfg(feature = "hdfs")]
+            if let Some(hdfs) = store.as_any().downcast_ref::<HdfsStore>() {
+                location.hdfs = hdfs.contains_partition(uid);
+            }
+        }
+        Ok(location)
+    }
+
     pub fn get_spill_event_num(&self) -> Result<u64> {
         Ok(self.memory_spill_event_num.load(Relaxed))
     }
@@ -419,11 +765,76 @@ impl HybridStore {
 
     pub async fn publish_spill_event(&self, message: SpillMessage) -> Result<()> {
         let size = message.size;
-        self.event_bus.publish(message.into()).await?;
+        self.pending_spill_events
+            .entry(message.ctx.uid.app_id.clone())
+            .or_default()
+            .insert(HybridStore::spill_event_key(&message), message.clone());
+        if let Err(err) = self.event_bus.publish(message.clone().into()).await {
+            if let Some(events) = self.pending_spill_events.get(&message.ctx.uid.app_id) {
+                events.remove(&HybridStore::spill_event_key(&message));
+            }
+            return Err(err);
+        }
         self.start_spill_event(size as u64);
         Ok(())
     }
 
+    /// Cancels the queued spill events of the purged app or shuffle, whose in-flight bytes are
+    /// released immediately instead of waiting for the flush to fail. The running events are
+    /// marked as purged to be skipped by the handlers.
+    fn cancel_spill_events(&self, app_id: &str, shuffle_id: Option<i32>) -> usize {
+        let messages: Vec<SpillMessage> = match shuffle_id {
+            Some(shuffle_id) => match self.pending_spill_events.get(app_id) {
+                Some(events) => events
+                    .iter()
+                    .filter(|entry| entry.key().0 == shuffle_id)
+                    .map(|entry| entry.value().clone())
+                    .collect(),
+                _ => vec![],
+            },
+            _ => match self.pending_spill_events.remove(app_id) {
+                Some((_, events)) => events.into_iter().map(|(_, message)| message).collect(),
+                _ => vec![],
+            },
+        };
+        self.cancel_spill_messages(messages)
+    }
+
+    /// Cancels the queued spill events of the deleted partition.
+    fn cancel_partition_spill_events(&self, uid: &PartitionedUId) -> usize {
+        let messages: Vec<SpillMessage> = match self.pending_spill_events.get(&uid.app_id) {
+            Some(events) => events
+                .iter()
+                .filter(|entry| {
+                    entry.key().0 == uid.shuffle_id && entry.key().1 == uid.partition_id
+                })
+                .map(|entry| entry.value().clone())
+                .collect(),
+            _ => vec![],
+        };
+        self.cancel_spill_messages(messages)
+    }
+
+    fn cancel_spill_messages(&self, messages: Vec<SpillMessage>) -> usize {
+        let mut cancelled = 0;
+        for message in messages {
+            // the running event may be paused into pending concurrently, retry until
+            // it is cancelled or marked as purged.
+            loop {
+                if message.cancel() {
+                    self.finish_spill_event(&message);
+                    cancelled += 1;
+                    break;
+                }
+                if message.mark_purged() {
+                    break;
+                }
+            }
+        }
+        TOTAL_SPILL_EVENTS_CANCELLED_BY_PURGE.inc_by(cancelled as u64);
+        cancelled
+    }
+
     pub async fn release_memory_buffer(
         &self,
         data_size: i64,
@@ -453,6 +864,14 @@ impl HybridStore {
         let spill_result = spill_result.unwrap();
         let flight_len = spill_result.flight_len();
 
+        // the blocks are kept in the arrival order only for the NORMAL data distribution
+        let local_order = self
+            .app_manager
+            .get()
+            .and_then(|manager| manager.get_app(&uid.app_id))
+            .map(|app| app.is_local_order())
+            .unwrap_or(true);
+
         let app_manager_ref = self.app_manager.clone();
         let app_is_exist_func = move |app_id: &str| -> bool {
             let app_ref = app_manager_ref.get();
@@ -463,7 +882,8 @@ impl HybridStore {
         };
 
         let writing_ctx =
-            SpillWritingViewContext::new(uid.clone(), spill_result.blocks(), app_is_exist_func);
+            SpillWritingViewContext::new(uid.clone(), spill_result.blocks(), app_is_exist_func)
+                .with_local_order(local_order);
         let message = SpillMessage {
             ctx: writing_ctx,
             size: flight_len as i64,
@@ -471,11 +891,193 @@ impl HybridStore {
             flight_id: spill_result.flight_id(),
             candidate_store_type: Arc::new(parking_lot::Mutex::new(None)),
             huge_partition_tag: OnceCell::new(),
+            state: Default::default(),
+            created_at: Instant::now(),
         };
         self.publish_spill_event(message).await?;
         Ok(flight_len)
     }
 
+    /// Inserts the data, and returns the monotonic write sequence of the partition. The reading
+    /// with this sequence as the `min_write_sequence` will see all the data written before,
+    /// no matter whether it is spilled.
+    pub async fn insert_with_sequence(&self, ctx: WritingViewContext) -> Result<u64, WorkerError> {
+        if self.is_draining() {
+            return Err(WorkerError::STORE_DRAINING);
+        }
+        let store = self.hot_store.clone();
+        let uid = ctx.uid.clone();
+        let huge_partition = ctx.huge_partition;
+        let sequence = store.insert_with_sequence(ctx);
+        if self.is_memory_only() {
+            return sequence;
+        }
+
+        // the huge partition buffer is spilled to the cold store directly once exceeding
+        // the threshold, rather than waiting for the watermark spill that will evict
+        // the other apps' small partitions.
+        if huge_partition {
+            let size = self.hot_store.get_buffer_staging_size(&uid)?;
+            if size > self.huge_partition_memory_spill_to_hdfs_threshold_size {
+                match self.single_buffer_spill(&uid).await {
+                    Ok(flushed) if flushed > 0 => {
+                        TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL.inc();
+                        TOTAL_HUGE_PARTITION_DIRECT_COLD_SPILL_BYTES.inc_by(flushed);
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        warn!(
+                            "Errors on huge partition buffer spill. uid: {:?}. err: {:?}",
+                            &uid, err
+                        );
+                    }
+                }
+            }
+        }
+
+        // for single buffer spill
+        //
+        // maybe the same partition will trigger spill at the same time, the thread
+        // safe will be ensured by the buffer self.
+        // if the first request has been handled, the following requests will not
+        // fast skip this logic.
+        if let Some(threshold) = self.memory_spill_partition_max_threshold {
+            let size = self.hot_store.get_buffer_staging_size(&uid)?;
+            if size > threshold {
+                if let Err(err) = self.single_buffer_spill(&uid).await {
+                    warn!(
+                        "Errors on single buffer spill. uid: {:?}. err: {:?}",
+                        &uid, err
+                    );
+                }
+            }
+        }
+
+        if !self.async_watermark_spill_enable {
+            if let Ok(_) = self.sync_memory_spill_lock.try_lock() {
+                if let Err(err) = self.watermark_spill().await {
+                    warn!("Errors on watermark spill. {:?}", err)
+                }
+            }
+        }
+
+        sequence
+    }
+
+    async fn wait_write_sequence_visible(
+        &self,
+        uid: &PartitionedUId,
+        sequence: u64,
+    ) -> Result<(), WorkerError> {
+        let deadline = Instant::now() + Duration::from_millis(WRITE_SEQUENCE_WAIT_MAX_MS);
+        loop {
+            let visible = self.hot_store.get_write_sequence(uid);
+            if visible >= sequence {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(WorkerError::WRITE_SEQUENCE_NOT_VISIBLE(sequence, visible));
+            }
+            tokio::time::sleep(Duration::from_millis(WRITE_SEQUENCE_WAIT_INTERVAL_MS)).await;
+        }
+    }
+
+    /// Flushes all the in-memory buffers of the app into the persistent stores without
+    /// deleting the app, and then waits for the published spill events to be finished.
+    /// The scheduled bytes are returned.
+    pub async fn flush_app(&self, app_id: &str) -> Result<u64> {
+        if self.is_memory_only() {
+            return Err(anyhow!("The memory only store can't be flushed"));
+        }
+        let buffers = self.hot_store.get_app_buffers(app_id);
+        let mut flushed_size = 0u64;
+        for (uid, buffer) in &buffers {
+            flushed_size += self.buffer_spill_impl(uid, buffer.clone()).await?;
+        }
+        info!(
+            "[Flush] Published spill events of app:[{}] for {} partitions with {}(bytes)",
+            app_id,
+            buffers.len(),
+            flushed_size
+        );
+
+        // the flushed data is cleared from the buffer once the spill event is finished,
+        // and the buffer of the purged partition is no longer waited.
+        loop {
+            let mut finished = true;
+            for (uid, buffer) in &buffers {
+                if buffer.flight_size()? > 0 && self.hot_store.get_buffer(uid).is_ok() {
+                    finished = false;
+                    break;
+                }
+            }
+            if finished {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100))
+                .instrument_await("waiting the spill events of app to be finished")
+                .await;
+        }
+        Ok(flushed_size)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(SeqCst)
+    }
+
+    /// Flip the store into the draining mode, which rejects the new data and flushes
+    /// all the in-memory buffers into the persistent stores, and then waits for the
+    /// in-flight spill events to be finished until the timeout.
+    pub async fn drain(&self, timeout: Duration) -> Result<()> {
+        if self.draining.swap(true, SeqCst) {
+            warn!("[Drain] The hybrid store has been in draining.");
+            return Ok(());
+        }
+        if self.is_memory_only() {
+            warn!("[Drain] Ignore draining for the memory only store.");
+            return Ok(());
+        }
+
+        let timer = Instant::now();
+        let buffers = self
+            .hot_store
+            .lookup_spill_buffers(i64::MAX, self.config.memory_spill_candidate_select_policy)?;
+        let partition_num = buffers.len();
+        let mut flushed_size = 0u64;
+        for (uid, buffer) in buffers {
+            match self.buffer_spill_impl(&uid, buffer).await {
+                Ok(flushed) => flushed_size += flushed,
+                Err(err) => error!(
+                    "[Drain] Errors on making buffer spill. uid: {:?}. err: {:?}",
+                    &uid, err
+                ),
+            }
+        }
+        info!(
+            "[Drain] Published spill events of {} partitions with {}(bytes). Waiting for them to finish...",
+            partition_num, flushed_size
+        );
+
+        let wait_result = tokio::time::timeout(timeout, async {
+            while self.memory_spill_event_num.load(SeqCst) > 0 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .instrument_await("waiting the spill events to be finished")
+        .await;
+        if wait_result.is_err() {
+            return Err(anyhow!(
+                "Timeout to drain the hybrid store. remaining spill events: {}",
+                self.memory_spill_event_num.load(SeqCst)
+            ));
+        }
+        info!(
+            "[Drain] Finished draining the hybrid store that costs {}(ms)",
+            timer.elapsed().as_millis()
+        );
+        Ok(())
+    }
+
     // Only for test
     pub fn enable_sensitive_watermark_spill(&self) {
         self.sensitive_watermark_spill_tag.set(());
@@ -529,9 +1131,10 @@ impl HybridStore {
             return Ok(());
         }
 
-        let buffers = self
-            .hot_store
-            .lookup_spill_buffers(mem_expected_spill_bytes)?;
+        let buffers = self.hot_store.lookup_spill_buffers(
+            mem_expected_spill_bytes,
+            self.config.memory_spill_candidate_select_policy,
+        )?;
         info!(
             "[Spill] Looked up all spill blocks that costs {}(ms). mem_expected_used: {}. mem_real_used: {}. mem_expected_spill_bytes: {}",
             timer.elapsed().as_millis(),
@@ -560,6 +1163,7 @@ impl HybridStore {
             }
             flushed_size += flushed;
         }
+        TOTAL_WATERMARK_SPILL_BYTES.inc_by(flushed_size);
         info!(
             "[Spill] Picked up {} partition blocks that should be async flushed with {}(bytes) that costs {}(ms). Spill events distribution: max={}(b), min={}(b)",
             partition_num,
@@ -604,52 +1208,78 @@ impl Store for HybridStore {
                 },
             );
         }
-    }
 
-    async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
-        let store = self.hot_store.clone();
-        let uid = ctx.uid.clone();
-        let insert_result = store.insert(ctx).await;
-
-        if self.is_memory_only() {
-            return insert_result;
-        }
-
-        // for single buffer spill
-        //
-        // maybe the same partition will trigger spill at the same time, the thread
-        // safe will be ensured by the buffer self.
-        // if the first request has been handled, the following requests will not
-        // fast skip this logic.
-        if let Some(threshold) = self.memory_spill_partition_max_threshold {
-            let size = self.hot_store.get_buffer_staging_size(&uid)?;
-            if size > threshold {
-                if let Err(err) = self.single_buffer_spill(&uid).await {
-                    warn!(
-                        "Errors on single buffer spill. uid: {:?}. err: {:?}",
-                        &uid, err
-                    );
-                }
-            }
+        if let Some(interval) = self.localfile_evacuation_check_interval() {
+            let store = self.clone();
+            self.runtime_manager.default_runtime.spawn_with_await_tree(
+                "localfile disks evacuation",
+                async move {
+                    loop {
+                        tokio::time::sleep(interval)
+                            .instrument_await("sleeping")
+                            .await;
+                        if let Err(err) = store.evacuate_localfile_disks().await {
+                            error!("Errors on evacuating localfile disks. err: {:?}", err);
+                        }
+                    }
+                },
+            );
         }
 
-        if !self.async_watermark_spill_enable {
-            if let Ok(_) = self.sync_memory_spill_lock.try_lock() {
-                if let Err(err) = self.watermark_spill().await {
-                    warn!("Errors on watermark spill. {:?}", err)
-                }
-            }
+        if let Some(interval) = self.localfile_compaction_check_interval() {
+            let store = self.clone();
+            self.runtime_manager.default_runtime.spawn_with_await_tree(
+                "localfile partitions compaction",
+                async move {
+                    loop {
+                        tokio::time::sleep(interval)
+                            .instrument_await("sleeping")
+                            .await;
+                        if let Err(err) = store.compact_localfile_partitions().await {
+                            error!("Errors on compacting localfile partitions. err: {:?}", err);
+                        }
+                    }
+                },
+            );
         }
+    }
 
-        insert_result
+    async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
+        self.insert_with_sequence(ctx).await.map(|_| ())
     }
 
     async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
+        if let Some(sequence) = ctx.min_write_sequence {
+            run_until_deadline(
+                ctx.deadline,
+                self.wait_write_sequence_visible(&ctx.uid, sequence),
+            )
+            .await?;
+        }
         match ctx.reading_options {
-            ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(_, _) => {
+            ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(_, _)
+            | ReadingOptions::MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE(_, _) => {
                 self.hot_store.get(ctx).await
             }
-            _ => self.warm_store.as_ref().unwrap().get(ctx).await,
+            _ => {
+                let is_miss = |result: &Result<ResponseData, WorkerError>| match result {
+                    Ok(ResponseData::Local(data)) => data.data.is_empty(),
+                    _ => false,
+                };
+                let mut result = self.get_from_persistent(ctx.clone()).await;
+                let mut attempts = 0;
+                // the spilled data may be not visible yet after the memory buffer is released
+                while is_miss(&result)
+                    && attempts < self.config.spilled_read_retry_max_attempts
+                    && self.is_just_spilled(&ctx.uid)
+                {
+                    attempts += 1;
+                    TOTAL_SPILLED_READ_RETRY.inc();
+                    tokio::time::sleep(Duration::from_millis(SPILLED_READ_RETRY_INTERVAL_MS)).await;
+                    result = self.get_from_persistent(ctx.clone()).await;
+                }
+                result
+            }
         }
     }
 
@@ -664,8 +1294,37 @@ impl Store for HybridStore {
         let app_id = &ctx.extract_app_id();
         let mut removed_size = 0i64;
 
-        removed_size += self.hot_store.purge(&ctx).await?;
+        let (_, shuffle_id) = ctx.extract();
+        let hot_removed_size = self.hot_store.purge(&ctx).await?;
+        removed_size += hot_removed_size;
+        // reported to keep the resident size corrected even if the rest purge is cancelled
+        ctx.report_batch(PurgeBatch {
+            shuffle_id,
+            removed_bytes: hot_removed_size,
+            removed_files: 0,
+        });
         info!("Removed data of app:[{}] in hot store", app_id);
+        match shuffle_id {
+            Some(shuffle_id) => {
+                if let Some(partitions) = self.spilled_partitions.get(app_id) {
+                    partitions.retain(|(v_shuffle_id, _), _| *v_shuffle_id != shuffle_id);
+                }
+            }
+            _ => {
+                self.spilled_partitions.remove(app_id);
+            }
+        }
+        let cancelled = self.cancel_spill_events(app_id, shuffle_id);
+        if cancelled > 0 {
+            info!(
+                "Cancelled {} pending spill events of app:[{}]",
+                cancelled, app_id
+            );
+        }
+        if ctx.is_cancelled() {
+            info!("The purge of app:[{}] is cancelled", app_id);
+            return Err(WorkerError::PURGE_CANCELLED(app_id.to_string()).into());
+        }
         if self.warm_store.is_some() {
             removed_size += self.warm_store.as_ref().unwrap().purge(&ctx).await?;
             info!("Removed data of app:[{}] in warm store", app_id);
@@ -677,6 +1336,31 @@ impl Store for HybridStore {
         Ok(removed_size)
     }
 
+    async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64> {
+        if let Some(partitions) = self.spilled_partitions.get(&uid.app_id) {
+            partitions.remove(&(uid.shuffle_id, uid.partition_id));
+        }
+        let cancelled = self.cancel_partition_spill_events(&uid);
+        if cancelled > 0 {
+            info!(
+                "Cancelled {} pending spill events of partition:[{:?}]",
+                cancelled, &uid
+            );
+        }
+        let mut removed_size = self.hot_store.delete_partition(uid.clone()).await?;
+        if let Some(warm) = self.warm_store.as_ref() {
+            removed_size += warm.delete_partition(uid.clone()).await?;
+        }
+        if let Some(cold) = self.cold_store.as_ref() {
+            removed_size += cold.delete_partition(uid.clone()).await?;
+        }
+        info!(
+            "Deleted partition:[{:?}] with {} bytes removed",
+            &uid, removed_size
+        );
+        Ok(removed_size)
+    }
+
     async fn is_healthy(&self) -> Result<bool> {
         async fn check_healthy(store: Option<&Box<dyn PersistentStore>>) -> Result<bool> {
             match store {
@@ -690,7 +1374,8 @@ impl Store for HybridStore {
         let cold = check_healthy(self.cold_store.as_ref())
             .await
             .unwrap_or(false);
-        Ok(self.hot_store.is_healthy().await? && warm && cold)
+        // mark unhealthy to steer the new apps away when draining.
+        Ok(!self.is_draining() && self.hot_store.is_healthy().await? && warm && cold)
     }
 
     async fn require_buffer(
@@ -738,16 +1423,20 @@ impl Store for HybridStore {
 pub(crate) mod tests {
     use crate::app::ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE;
     use crate::app::{
-        PartitionedUId, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
-        WritingViewContext,
+        PartitionedUId, PurgeDataContext, PurgeReason, ReadingIndexViewContext, ReadingOptions,
+        ReadingViewContext, WritingViewContext,
     };
+    use crate::chaos::{FaultMode, FaultOperation, FaultSpec, FaultTarget, FAULT_INJECTOR};
     use crate::config::{
-        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, StorageType,
+        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig,
+        SpillCandidateSelectPolicy, StorageType,
     };
+    use crate::error::WorkerError;
 
     use crate::store::hybrid::HybridStore;
     use crate::store::ResponseData::Mem;
     use crate::store::{Block, ResponseData, ResponseDataIndex, Store};
+    use crate::util::get_crc;
     use bytes::{Buf, Bytes};
 
     use std::any::Any;
@@ -895,6 +1584,9 @@ pub(crate) mod tests {
             uid: uid.clone(),
             reading_options: MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1024 * 1024 * 1024),
             serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         }))?;
 
         let mut accepted_block_ids: HashSet<i64> = HashSet::new();
@@ -904,6 +1596,8 @@ pub(crate) mod tests {
 
         let local_index_data = runtime.wait(store.get_index(ReadingIndexViewContext {
             partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
         }))?;
 
         match local_index_data {
@@ -935,6 +1629,647 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn memory_only_read_test() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId::from("memory_only_read_test".to_string(), 0, 0);
+        let memory_only_ctx = || ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE(
+                -1,
+                1024 * 1024,
+            ),
+            serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
+        };
+
+        // case1: the absent partition is the typed miss
+        match runtime.wait(store.get(memory_only_ctx())) {
+            Err(WorkerError::NOT_IN_MEMORY) => {}
+            _ => panic!(),
+        }
+
+        // case2: the data is read from memory
+        runtime.wait(write_some_data(
+            store.clone(),
+            uid.clone(),
+            data_len as i32,
+            data,
+            2,
+        ));
+        let response_data = runtime.wait(store.get(memory_only_ctx()))?;
+        assert_eq!(
+            2,
+            response_data
+                .from_memory()
+                .shuffle_data_block_segments
+                .len()
+        );
+
+        // case3: the spilled data is the typed miss rather than reading the disk
+        runtime.wait(store.single_buffer_spill(&uid))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            store.in_flight_bytes.load(SeqCst) == 0
+                && runtime.wait(store.get_memory_buffer_size(&uid)).unwrap() == 0
+        });
+        match runtime.wait(store.get(memory_only_ctx())) {
+            Err(WorkerError::NOT_IN_MEMORY) => {}
+            _ => panic!(),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resident_bytes_by_tier() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "test_resident_bytes_by_tier".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        runtime.wait(write_some_data(
+            store.clone(),
+            uid.clone(),
+            data_len as i32,
+            data,
+            10,
+        ));
+        let tiers = runtime.wait(store.resident_bytes_by_tier());
+        assert_eq!(
+            Some(&(data_len as u64 * 10)),
+            tiers.get(&StorageType::MEMORY)
+        );
+        assert_eq!(Some(&0), tiers.get(&StorageType::LOCALFILE));
+        assert_eq!(None, tiers.get(&StorageType::HDFS));
+
+        // the memory tier shrinks while the localfile tier grows after the spill
+        runtime.wait(store.single_buffer_spill(&uid))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            store.in_flight_bytes.load(SeqCst) == 0
+                && runtime.wait(store.get_memory_buffer_size(&uid)).unwrap() == 0
+        });
+        let tiers = runtime.wait(store.resident_bytes_by_tier());
+        assert_eq!(Some(&0), tiers.get(&StorageType::MEMORY));
+        assert_eq!(
+            Some(&(data_len as u64 * 10)),
+            tiers.get(&StorageType::LOCALFILE)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_at_write_sequence() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId::from("test_read_at_write_sequence".to_string(), 0, 0);
+        let insert = |block_id: i64| {
+            let writing_ctx = WritingViewContext::new_with_size(
+                uid.clone(),
+                vec![Block {
+                    block_id,
+                    length: data_len as i32,
+                    uncompress_length: 100,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                }],
+                data_len as u64,
+            );
+            let _ = store.inc_used(data_len as i64);
+            runtime.wait(store.insert_with_sequence(writing_ctx))
+        };
+
+        let mut sequence = 0;
+        for block_id in 0..3 {
+            let next = insert(block_id)?;
+            assert!(next > sequence);
+            sequence = next;
+        }
+
+        // spill all the written data up to the sequence
+        runtime.wait(store.single_buffer_spill(&uid))?;
+        awaitility::at_most(Duration::from_secs(5)).until(|| {
+            store.in_flight_bytes.load(SeqCst) == 0
+                && runtime.wait(store.get_memory_buffer_size(&uid)).unwrap() == 0
+        });
+
+        let read_at = |options: ReadingOptions, sequence: u64| {
+            runtime.wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: options,
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: Some(sequence),
+                deadline: None,
+            }))
+        };
+        let local_data = read_at(
+            ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len as i64 * 3),
+            sequence,
+        )?
+        .from_local();
+        assert_eq!(data_len * 3, local_data.len());
+
+        // the later write is visible in memory
+        let later = insert(3)?;
+        assert!(later > sequence);
+        let memory_data = read_at(
+            ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+            later,
+        )?
+        .from_memory();
+        assert_eq!(1, memory_data.shuffle_data_block_segments.len());
+
+        // the never written sequence is rejected after waiting
+        match read_at(
+            ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+            later + 1,
+        ) {
+            Err(WorkerError::WRITE_SEQUENCE_NOT_VISIBLE(expected, visible)) => {
+                assert_eq!(later + 1, expected);
+                assert_eq!(later, visible);
+            }
+            _ => panic!("the unwritten sequence should not be visible"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_app() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let app_id = "test_flush_app";
+        let uid = PartitionedUId::from(app_id.to_string(), 0, 0);
+        runtime.wait(write_some_data(
+            store.clone(),
+            uid.clone(),
+            data_len as i32,
+            data,
+            10,
+        ));
+
+        let flushed = runtime.wait(store.flush_app(app_id))?;
+        assert_eq!(data_len as u64 * 10, flushed);
+
+        // the memory read is empty, and the data is read from the localfile
+        let memory_data = runtime
+            .wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_memory();
+        assert_eq!(0, memory_data.shuffle_data_block_segments.len());
+
+        let local_data = runtime
+            .wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len as i64 * 10),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_local();
+        assert_eq!(data_len * 10, local_data.len());
+
+        // nothing left to be flushed
+        assert_eq!(0, runtime.wait(store.flush_app(app_id))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_watermark_spill_with_largest_first_policy() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        // high watermark: 960 bytes, low watermark: 240 bytes
+        let store = start_store(None, ((data_len * 100) as i64).to_string());
+        assert_eq!(
+            SpillCandidateSelectPolicy::LARGEST_FIRST,
+            store.config.memory_spill_candidate_select_policy
+        );
+        let runtime = store.runtime_manager.clone();
+
+        // the last write reaches the high watermark, and the largest partition
+        // is enough for the expected spill bytes.
+        let app_id = "test_watermark_spill_with_largest_first_policy";
+        let partitions = [(0, 5), (1, 70), (2, 6)];
+        for (partition_id, batch_size) in partitions {
+            runtime.wait(write_some_data(
+                store.clone(),
+                PartitionedUId::from(app_id.to_string(), 0, partition_id),
+                data_len as i32,
+                data,
+                batch_size,
+            ));
+        }
+
+        let staging_size = |partition_id: i32| {
+            store
+                .hot_store
+                .get_buffer_staging_size(&PartitionedUId::from(app_id.to_string(), 0, partition_id))
+                .unwrap()
+        };
+        assert_eq!(0, staging_size(1));
+        assert_eq!((data_len * 5) as u64, staging_size(0));
+        assert_eq!((data_len * 6) as u64, staging_size(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partition_flush_state() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(Some("1B".to_string()), ((data_len * 1) as i64).to_string());
+        store.clone().start();
+
+        let uid = PartitionedUId {
+            app_id: "1000".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        // case1: nothing has been written
+        let state = store.get_partition_flush_state(&uid).await?;
+        assert_eq!(0, state.pending_spill_event_num);
+        assert_eq!(0, state.memory_buffered_bytes);
+        assert_eq!(0, state.last_flushed_offset);
+
+        // case2: all data has been flushed into localfile
+        write_some_data(store.clone(), uid.clone(), data_len as i32, data, 10).await;
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| store.in_flight_bytes.load(SeqCst) == 0);
+
+        let state = store.get_partition_flush_state(&uid).await?;
+        assert_eq!(0, state.pending_spill_event_num);
+        assert_eq!(0, state.memory_buffered_bytes);
+        assert_eq!((data_len * 10) as i64, state.last_flushed_offset);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partition_location() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+
+        let uid = PartitionedUId {
+            app_id: "1000".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        // case1: nothing has been written
+        let location = store.get_partition_location(&uid).await?;
+        assert!(location.is_empty());
+
+        // case2: all data is resident in memory
+        write_some_data(store.clone(), uid.clone(), data_len as i32, data, 10).await;
+        let location = store.get_partition_location(&uid).await?;
+        assert!(location.memory);
+        assert_eq!(None, location.localfile_disk_root);
+        assert!(!location.is_spread());
+
+        // case3: all data has been spilled into localfile
+        store.single_buffer_spill(&uid).await?;
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| store.in_flight_bytes.load(SeqCst) == 0);
+        let location = store.get_partition_location(&uid).await?;
+        assert!(!location.memory);
+        assert!(location.localfile_disk_root.is_some());
+        assert!(!location.hdfs);
+
+        // case4: spread across memory and localfile
+        write_some_data(store.clone(), uid.clone(), data_len as i32, data, 1).await;
+        let location = store.get_partition_location(&uid).await?;
+        assert!(location.memory);
+        assert!(location.is_spread());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_retry_of_just_spilled_partition() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        let uid = PartitionedUId {
+            app_id: "test_read_retry_of_just_spilled_partition".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let read = |uid: &PartitionedUId| {
+            store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len as i64),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            })
+        };
+
+        // the spill is finished, but the localfile data is visible a little later
+        store.mark_spilled(&uid);
+        let delayed_store = store.clone();
+        let delayed_uid = uid.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            let ctx = WritingViewContext::new_with_size(
+                delayed_uid,
+                vec![Block {
+                    block_id: 0,
+                    length: data_len as i32,
+                    uncompress_length: 100,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                }],
+                data_len as u64,
+            );
+            delayed_store.warm_store.as_ref().unwrap().insert(ctx).await
+        });
+
+        // the data is found by the retried read
+        let data = read(&uid).await?.from_local();
+        assert_eq!(data_len, data.len());
+
+        // the miss of the partition not spilled is returned immediately
+        let other_uid = PartitionedUId {
+            app_id: uid.app_id.to_owned(),
+            shuffle_id: 0,
+            partition_id: 1,
+        };
+        assert!(read(&other_uid).await?.from_local().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_partition() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+
+        let deleted_uid = PartitionedUId::from("1000".to_string(), 0, 0);
+        let retained_uid = PartitionedUId::from("1000".to_string(), 0, 1);
+
+        // spread the partitions across memory and localfile
+        for uid in [&deleted_uid, &retained_uid] {
+            write_some_data(store.clone(), uid.clone(), data_len as i32, data, 10).await;
+            store.single_buffer_spill(uid).await?;
+        }
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| store.in_flight_bytes.load(SeqCst) == 0);
+        for uid in [&deleted_uid, &retained_uid] {
+            write_some_data(store.clone(), uid.clone(), data_len as i32, data, 1).await;
+        }
+
+        let removed = store.delete_partition(deleted_uid.clone()).await?;
+        assert_eq!((data_len * 11) as i64, removed);
+
+        let location = store.get_partition_location(&deleted_uid).await?;
+        assert!(location.is_empty());
+        let location = store.get_partition_location(&retained_uid).await?;
+        assert!(location.memory);
+        assert!(location.localfile_disk_root.is_some());
+
+        // deleting the absent partition is a no-op
+        assert_eq!(0, store.delete_partition(deleted_uid).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_crc_mismatch_fallback_to_memory() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_read_crc_mismatch_fallback").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut localfile_config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        localfile_config.read_crc_verify_enable = true;
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new("1G".to_string()));
+        config.localfile_store = Some(localfile_config);
+        config.hybrid_store = HybridStoreConfig::new(0.8, 0.2, None);
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        let store = HybridStore::from(config, Default::default());
+
+        let uid = PartitionedUId {
+            app_id: "test_read_crc_mismatch_fallback".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let blocks: Vec<Block> = (0..3)
+            .map(|idx| {
+                let data = Bytes::from(vec![idx as u8; 10]);
+                Block {
+                    block_id: idx,
+                    length: 10,
+                    uncompress_length: 10,
+                    crc: get_crc(&data),
+                    data,
+                    task_attempt_id: 0,
+                }
+            })
+            .collect();
+        // the blocks are still buffered in memory when they have been flushed
+        store
+            .insert(WritingViewContext::new_with_size(
+                uid.clone(),
+                blocks.clone(),
+                30,
+            ))
+            .await?;
+        store
+            .warm_store
+            .as_ref()
+            .unwrap()
+            .insert(WritingViewContext::create_for_test(uid.clone(), blocks))
+            .await?;
+
+        // corrupt one byte of the second block
+        let abs_data_file_path = format!("{}/{}/0/partition-0.data", &temp_path, &uid.app_id);
+        let mut raw = std::fs::read(&abs_data_file_path)?;
+        raw[15] = 0xff;
+        std::fs::write(&abs_data_file_path, raw)?;
+
+        let ctx = || ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, 30),
+            serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
+        };
+        let data = store.get(ctx()).await?.from_local();
+        let expected: Vec<u8> = (0..3u8).flat_map(|idx| vec![idx; 10]).collect();
+        assert_eq!(expected, data.as_ref());
+
+        // fail when the block is not buffered any more
+        let buffer = store.hot_store.get_buffer(&uid)?;
+        let spill_result = buffer.spill()?.unwrap();
+        buffer.clear(spill_result.flight_id(), spill_result.flight_len())?;
+        match store.get(ctx()).await {
+            Err(WorkerError::DATA_CRC_MISMATCH { block_id, .. }) => assert_eq!(1, block_id),
+            _ => panic!("the corrupted block should be detected"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_drain_on_shutdown() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let store = start_store(None, ((data_len * 10000) as i64).to_string());
+        store.clone().start();
+
+        let uid = PartitionedUId {
+            app_id: "1000".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        write_some_data(store.clone(), uid.clone(), data_len as i32, data, 10).await;
+        assert!(store.is_healthy().await?);
+        assert_eq!(0, store.get_spill_event_num()?);
+
+        store.drain(Duration::from_secs(10)).await?;
+        assert!(!store.is_healthy().await?);
+        assert_eq!(0, store.get_spill_event_num()?);
+
+        // all the buffered data should be readable from localfile
+        match store
+            .get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+                entry_range: None,
+                deadline: None,
+            })
+            .await?
+        {
+            ResponseDataIndex::Local(index) => {
+                assert_eq!((data_len * 10) as i64, index.data_file_len);
+            }
+        }
+
+        // the new data will be rejected
+        let writing_ctx = WritingViewContext::new_with_size(
+            uid.clone(),
+            vec![Block {
+                block_id: 100,
+                length: data_len as i32,
+                uncompress_length: 100,
+                crc: 0,
+                data: Bytes::copy_from_slice(data),
+                task_attempt_id: 0,
+            }],
+            data_len as u64,
+        );
+        match store.insert(writing_ctx).await {
+            Err(WorkerError::STORE_DRAINING) => {}
+            _ => panic!("the insert should be rejected when draining"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancel_pending_spill_events_on_purge() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len();
+
+        let temp_dir = tempdir::TempDir::new("test_cancel_pending_spill_events").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new("1M".to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path.clone()]));
+        config.hybrid_store = HybridStoreConfig::new(0.8, 0.2, None);
+        config.hybrid_store.memory_spill_to_localfile_concurrency = Some(1);
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        let store = Arc::new(HybridStore::from(config, Default::default()));
+        store.clone().start();
+
+        // the running spill event is blocked, and the others are queued
+        FAULT_INJECTOR.enable(true);
+        let fault_id = FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(temp_path.clone()),
+                store_type: None,
+                operation: Some(FaultOperation::APPEND),
+            },
+            mode: FaultMode::LATENCY_MS(2000),
+            probability: 1.0,
+            ttl_sec: 60,
+        })?;
+
+        let app_id = "test_cancel_pending_spill_events";
+        for partition_id in 0..4 {
+            let uid = PartitionedUId::from(app_id.to_string(), 0, partition_id);
+            write_some_data(store.clone(), uid.clone(), data_len as i32, data, 10).await;
+            store.single_buffer_spill(&uid).await?;
+        }
+        assert_eq!(4, store.get_spill_event_num()?);
+
+        store
+            .purge(&PurgeDataContext::new(
+                &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            ))
+            .await?;
+
+        // at most the running one is left
+        assert!(store.get_spill_event_num()? <= 1);
+        assert!(store.get_in_flight_size()? <= (data_len * 10) as u64);
+        assert_eq!(0, store.mem_snapshot()?.used());
+
+        FAULT_INJECTOR.remove(fault_id);
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            store.get_spill_event_num().unwrap() == 0 && store.get_in_flight_size().unwrap() == 0
+        });
+        assert_eq!(0, store.mem_snapshot()?.used());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn get_data_from_localfile() {
         let data = b"hello world!";
@@ -961,6 +2296,9 @@ pub(crate) mod tests {
                 data_len as i64,
             ),
             serialized_expected_task_ids_bitmap: None,
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
 
         let read_data = store.get(reading_view_ctx).await;
@@ -981,6 +2319,8 @@ pub(crate) mod tests {
         // 2. read data
         let index_view_ctx = ReadingIndexViewContext {
             partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
         };
         match store.get_index(index_view_ctx).await.unwrap() {
             ResponseDataIndex::Local(index) => {
@@ -997,6 +2337,9 @@ pub(crate) mod tests {
                         uid: uid.clone(),
                         reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, length as i64),
                         serialized_expected_task_ids_bitmap: None,
+                        latest_attempt_only: false,
+                        min_write_sequence: None,
+                        deadline: None,
                     };
                     println!("reading. offset: {:?}. len: {:?}", offset, length);
                     let read_data = store.get(reading_view_ctx).await.unwrap();
@@ -1048,6 +2391,9 @@ pub(crate) mod tests {
                     data_len as i64,
                 ),
                 serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
             };
 
             let read_data = runtime.wait(store.get(reading_view_ctx));
@@ -0,0 +1,108 @@
+use crate::store::index_codec::IndexBlock;
+use hashlink::LinkedHashMap;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// The crc of the block at the logical offset of the data file, which is in the same order
+/// with the index entries.
+#[derive(Clone, Copy)]
+pub struct BlockCrc {
+    pub offset: i64,
+    pub length: i32,
+    pub crc: i64,
+    pub block_id: i64,
+}
+
+impl From<&IndexBlock> for BlockCrc {
+    fn from(block: &IndexBlock) -> Self {
+        Self {
+            offset: block.offset,
+            length: block.length,
+            crc: block.crc,
+            block_id: block.block_id,
+        }
+    }
+}
+
+/// The LRU cache of the block crcs decoded from the index files, which is keyed by the
+/// relative data file path and bounded by the total entries of all the partitions. The
+/// callers populate and invalidate it under the partition lock.
+pub struct BlockCrcCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    partitions: LinkedHashMap<String, Arc<Vec<BlockCrc>>>,
+    entries: usize,
+}
+
+impl BlockCrcCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner {
+                partitions: LinkedHashMap::new(),
+                entries: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, path: &str) -> Option<Arc<Vec<BlockCrc>>> {
+        self.inner.lock().partitions.to_back(path).cloned()
+    }
+
+    /// Caches the crcs of the whole index, which is skipped if it exceeds the capacity.
+    pub fn put(&self, path: &str, crcs: Arc<Vec<BlockCrc>>) {
+        if crcs.len() > self.capacity {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        inner.entries += crcs.len();
+        if let Some(replaced) = inner.partitions.insert(path.to_string(), crcs) {
+            inner.entries -= replaced.len();
+        }
+        self.evict(&mut inner);
+    }
+
+    /// Appends the crcs of the newly flushed blocks if the partition is cached, otherwise
+    /// they are read from the index on the next verification.
+    pub fn append(&self, path: &str, crcs: &[BlockCrc]) {
+        let mut inner = self.inner.lock();
+        let cached = match inner.partitions.get_mut(path) {
+            Some(cached) => cached,
+            _ => return,
+        };
+        Arc::make_mut(cached).extend_from_slice(crcs);
+        inner.entries += crcs.len();
+        self.evict(&mut inner);
+    }
+
+    fn evict(&self, inner: &mut Inner) {
+        while inner.entries > self.capacity {
+            match inner.partitions.pop_front() {
+                Some((_, evicted)) => inner.entries -= evicted.len(),
+                _ => break,
+            }
+        }
+    }
+
+    /// Drops the cached crcs of the files under the path prefix.
+    pub fn invalidate(&self, path_prefix: &str) {
+        let mut inner = self.inner.lock();
+        let mut removed = 0;
+        inner.partitions.retain(|path, crcs| {
+            if path.starts_with(path_prefix) {
+                removed += crcs.len();
+                false
+            } else {
+                true
+            }
+        });
+        inner.entries -= removed;
+    }
+
+    pub fn entries(&self) -> usize {
+        self.inner.lock().entries
+    }
+}
@@ -1,6 +1,7 @@
 use crate::app::SHUFFLE_SERVER_ID;
 use crate::await_tree::AWAIT_TREE_REGISTRY;
-use crate::config::LocalfileStoreConfig;
+use crate::chaos::{FaultOperation, FAULT_INJECTOR};
+use crate::config::{LocalfileStoreConfig, StorageType};
 use crate::error::WorkerError;
 use crate::metric::{
     GAUGE_LOCAL_DISK_CAPACITY, GAUGE_LOCAL_DISK_IS_HEALTHY, GAUGE_LOCAL_DISK_USED,
@@ -14,8 +15,11 @@ use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
 use crate::store::local::limiter::TokenBucketLimiter;
 use crate::store::local::sync_io::SyncLocalIO;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::store::local::uring_io::UringLocalIO;
 use crate::store::local::{DiskStat, FileStat, LocalDiskStorage, LocalIO};
 use crate::store::BytesWrapper;
+use crate::timeline::{TimelineCategory, TIMELINE};
 use crate::util;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -40,10 +44,12 @@ pub struct LocalDiskDelegator {
 struct Inner {
     root: String,
 
-    io_handler: SyncLocalIO,
+    io_handler: IoHandler,
 
     is_healthy: Arc<AtomicBool>,
     is_corrupted: Arc<AtomicBool>,
+    // the monotonic seconds when marked as unhealthy, 0 means healthy
+    unhealthy_since_sec: AtomicU64,
 
     high_watermark: f32,
     low_watermark: f32,
@@ -57,6 +63,172 @@ struct Inner {
     io_limiter: Option<TokenBucketLimiter>,
 
     io_duration_threshold_sec: u64,
+
+    // the bytes reserved exclusively for the index files, 0 means disabled
+    index_reserved_capacity: u64,
+
+    // the number of the in-flight append/read operations, including the waiting ones
+    in_flight_io: AtomicU64,
+}
+
+/// The io backend of the disk, which is chosen on the startup.
+#[derive(Clone)]
+enum IoHandler {
+    Sync(SyncLocalIO),
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    Uring(UringLocalIO),
+}
+
+impl IoHandler {
+    fn create(root: &str, config: &LocalfileStoreConfig, sync_io_handler: SyncLocalIO) -> Self {
+        if let Some(uring_config) = config.io_uring.as_ref() {
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            {
+                match UringLocalIO::new(
+                    root,
+                    uring_config,
+                    sync_io_handler.clone(),
+                    config.parent_dir_sync_enable,
+                ) {
+                    Ok(uring_io_handler) => {
+                        info!("The io_uring backend is used for the disk: {}", root);
+                        return IoHandler::Uring(uring_io_handler);
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Falling back to the sync io backend for the disk: {} that io_uring is unsupported. err: {:?}",
+                            root, err
+                        );
+                    }
+                }
+            }
+            #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+            {
+                warn!(
+                    "The io_uring backend is configured but the io-uring feature is disabled. config: {:?}",
+                    uring_config
+                );
+            }
+        }
+        info!("The sync io backend is used for the disk: {}", root);
+        IoHandler::Sync(sync_io_handler)
+    }
+}
+
+#[async_trait]
+impl LocalIO for IoHandler {
+    async fn create_dir(&self, dir: &str) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.create_dir(dir).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.create_dir(dir).await,
+        }
+    }
+
+    async fn append(&self, path: &str, data: BytesWrapper) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.append(path, data).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.append(path, data).await,
+        }
+    }
+
+    async fn read(
+        &self,
+        path: &str,
+        offset: i64,
+        length: Option<i64>,
+    ) -> Result<Bytes, WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.read(path, offset, length).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.read(path, offset, length).await,
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.delete(path).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.delete(path).await,
+        }
+    }
+
+    async fn write(&self, path: &str, data: Bytes) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.write(path, data).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.write(path, data).await,
+        }
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.rename(from, to).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.rename(from, to).await,
+        }
+    }
+
+    async fn file_stat(&self, path: &str) -> Result<FileStat, WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.file_stat(path).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.file_stat(path).await,
+        }
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.exists(path).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.exists(path).await,
+        }
+    }
+
+    async fn direct_append(
+        &self,
+        path: &str,
+        written_bytes: usize,
+        data: BytesWrapper,
+    ) -> Result<(), WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.direct_append(path, written_bytes, data).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.direct_append(path, written_bytes, data).await,
+        }
+    }
+
+    async fn direct_read(
+        &self,
+        path: &str,
+        offset: i64,
+        length: i64,
+    ) -> Result<Bytes, WorkerError> {
+        match self {
+            IoHandler::Sync(io) => io.direct_read(path, offset, length).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            IoHandler::Uring(io) => io.direct_read(path, offset, length).await,
+        }
+    }
+}
+
+/// Tracks the in-flight io operation until dropped.
+struct InFlightIoGuard<'a> {
+    in_flight_io: &'a AtomicU64,
+}
+
+impl<'a> InFlightIoGuard<'a> {
+    fn new(in_flight_io: &'a AtomicU64) -> Self {
+        in_flight_io.fetch_add(1, SeqCst);
+        Self { in_flight_io }
+    }
+}
+
+impl Drop for InFlightIoGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight_io.fetch_sub(1, SeqCst);
+    }
 }
 
 impl LocalDiskDelegator {
@@ -70,13 +242,16 @@ impl LocalDiskDelegator {
         let write_capacity = ReadableSize::from_str(&config.disk_write_buf_capacity).unwrap();
         let read_capacity = ReadableSize::from_str(&config.disk_read_buf_capacity).unwrap();
 
-        let io_handler = SyncLocalIO::new(
+        let sync_io_handler = SyncLocalIO::new(
             &runtime_manager.read_runtime,
             &runtime_manager.localfile_write_runtime,
             root,
             Some(write_capacity.as_bytes() as usize),
             Some(read_capacity.as_bytes() as usize),
+            config.composed_bytes_vectored_write_enable,
+            config.parent_dir_sync_enable,
         );
+        let io_handler = IoHandler::create(root, config, sync_io_handler);
 
         let io_limiter = match config.io_limiter.as_ref() {
             Some(conf) => {
@@ -97,12 +272,18 @@ impl LocalDiskDelegator {
             _ => None,
         };
 
+        let index_reserved_capacity = config
+            .disk_index_reserved_capacity
+            .as_ref()
+            .map_or(0, |capacity| util::parse_raw_to_bytesize(capacity));
+
         let delegator = Self {
             inner: Arc::new(Inner {
                 root: root.to_owned(),
                 io_handler,
                 is_healthy: Arc::new(AtomicBool::new(true)),
                 is_corrupted: Arc::new(AtomicBool::new(false)),
+                unhealthy_since_sec: AtomicU64::new(0),
                 high_watermark,
                 low_watermark,
                 healthy_check_interval_sec: config.disk_healthy_check_interval_sec,
@@ -110,6 +291,8 @@ impl LocalDiskDelegator {
                 available_ref: Default::default(),
                 io_limiter,
                 io_duration_threshold_sec: config.io_duration_threshold_sec as u64,
+                index_reserved_capacity,
+                in_flight_io: AtomicU64::new(0),
             }),
         };
 
@@ -139,6 +322,28 @@ impl LocalDiskDelegator {
         Ok(())
     }
 
+    async fn inject_fault(&self, operation: FaultOperation) -> Result<(), WorkerError> {
+        FAULT_INJECTOR
+            .inject(StorageType::LOCALFILE, Some(&self.inner.root), operation)
+            .await
+    }
+
+    /// The appends except for the index files are refused once the available capacity
+    /// falls into the reserved one, so that the index appends of the nearly full disk
+    /// could always be completed to keep the data and index consistent.
+    fn check_index_reserved_capacity(&self, path: &str, len: usize) -> Result<(), WorkerError> {
+        let reserved = self.inner.index_reserved_capacity;
+        if reserved == 0 || path.ends_with(".index") {
+            return Ok(());
+        }
+        if self.get_disk_available()? < reserved + len as u64 {
+            return Err(WorkerError::LOCAL_DISK_RESERVED_FOR_INDEX(
+                self.inner.root.to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
     pub fn with_capacity(&self, capacity_ref: Arc<AtomicU64>) {
         let _ = self.inner.capacity_ref.set(capacity_ref);
     }
@@ -147,10 +352,24 @@ impl LocalDiskDelegator {
         let _ = self.inner.available_ref.set(available_ref);
     }
 
+    /// The io pressure of the disk, which is the in-flight operations number and the
+    /// used ratio of the disk capacity.
+    pub fn io_pressure(&self) -> Result<(u64, f64)> {
+        Ok((self.inner.in_flight_io.load(SeqCst), self.used_ratio()?))
+    }
+
     pub fn root(&self) -> String {
         self.inner.root.to_owned()
     }
 
+    /// The duration since the disk is marked as unhealthy, `None` if it's healthy.
+    pub fn unhealthy_duration_sec(&self) -> Option<u64> {
+        match self.inner.unhealthy_since_sec.load(SeqCst) {
+            0 => None,
+            since => Some(util::now_monotonic_as_sec().saturating_sub(since)),
+        }
+    }
+
     async fn schedule_check(&self) -> Result<()> {
         loop {
             tokio::time::sleep(Duration::from_secs(self.inner.healthy_check_interval_sec))
@@ -201,11 +420,23 @@ impl LocalDiskDelegator {
         Ok(used_ratio)
     }
 
+    pub fn capacity(&self) -> Result<u64> {
+        self.get_disk_capacity()
+    }
+
+    pub fn available(&self) -> Result<u64> {
+        self.get_disk_available()
+    }
+
     pub fn stat(&self) -> Result<DiskStat> {
-        let used_ratio = self.used_ratio()?;
+        let capacity = self.get_disk_capacity()?;
+        let used = capacity.saturating_sub(self.get_disk_available()?);
         Ok(DiskStat {
             root: self.root(),
-            used_ratio,
+            used_ratio: used as f64 / capacity as f64,
+            capacity,
+            used,
+            healthy: self.is_healthy()?,
         })
     }
 
@@ -256,7 +487,9 @@ impl LocalDiskDelegator {
         let shuffle_server_id = SHUFFLE_SERVER_ID.get().unwrap_or(&default_id);
         let detection_file = format!("corruption_check.file.{}", shuffle_server_id);
 
-        self.delete(&detection_file).await?;
+        if self.exists(&detection_file).await? {
+            self.delete(&detection_file).await?;
+        }
 
         let written_data = Bytes::copy_from_slice(b"hello world");
         self.write(&detection_file, written_data.clone()).await?;
@@ -302,10 +535,13 @@ impl LocalIO for LocalDiskDelegator {
     }
 
     async fn append(&self, path: &str, data: BytesWrapper) -> Result<(), WorkerError> {
+        let _in_flight = InFlightIoGuard::new(&self.inner.in_flight_io);
+        self.inject_fault(FaultOperation::APPEND).await?;
+        let len = data.len();
+        self.check_index_reserved_capacity(path, len)?;
         let timer = LOCALFILE_DISK_APPEND_OPERATION_DURATION
             .with_label_values(&[&self.inner.root])
             .start_timer();
-        let len = data.len();
 
         let future = self.inner.io_handler.append(path, data);
         timeout(
@@ -331,6 +567,8 @@ impl LocalIO for LocalDiskDelegator {
         offset: i64,
         length: Option<i64>,
     ) -> Result<Bytes, WorkerError> {
+        let _in_flight = InFlightIoGuard::new(&self.inner.in_flight_io);
+        self.inject_fault(FaultOperation::READ).await?;
         let timer = LOCALFILE_DISK_READ_OPERATION_DURATION
             .with_label_values(&[&self.inner.root])
             .start_timer();
@@ -354,6 +592,8 @@ impl LocalIO for LocalDiskDelegator {
     }
 
     async fn delete(&self, path: &str) -> Result<(), WorkerError> {
+        let _in_flight = InFlightIoGuard::new(&self.inner.in_flight_io);
+        self.inject_fault(FaultOperation::DELETE).await?;
         let timer = LOCALFILE_DISK_DELETE_OPERATION_DURATION
             .with_label_values(&[&self.inner.root])
             .start_timer();
@@ -372,6 +612,7 @@ impl LocalIO for LocalDiskDelegator {
     }
 
     async fn write(&self, path: &str, data: Bytes) -> Result<(), WorkerError> {
+        self.inject_fault(FaultOperation::WRITE).await?;
         let future = self.inner.io_handler.write(path, data);
         timeout(
             Duration::from_secs(self.inner.io_duration_threshold_sec),
@@ -382,6 +623,17 @@ impl LocalIO for LocalDiskDelegator {
         Ok(())
     }
 
+    async fn rename(&self, from: &str, to: &str) -> Result<(), WorkerError> {
+        let future = self.inner.io_handler.rename(from, to);
+        timeout(
+            Duration::from_secs(self.inner.io_duration_threshold_sec),
+            future,
+        )
+        .instrument_await(format!("rename in disk: {}", &self.inner.root))
+        .await??;
+        Ok(())
+    }
+
     async fn file_stat(&self, path: &str) -> Result<FileStat, WorkerError> {
         let future = self.inner.io_handler.file_stat(path);
         let file_stat = timeout(
@@ -393,13 +645,27 @@ impl LocalIO for LocalDiskDelegator {
         Ok(file_stat)
     }
 
+    async fn exists(&self, path: &str) -> Result<bool, WorkerError> {
+        let future = self.inner.io_handler.exists(path);
+        let exists = timeout(
+            Duration::from_secs(self.inner.io_duration_threshold_sec),
+            future,
+        )
+        .instrument_await(format!("checking existence in disk: {}", &self.inner.root))
+        .await??;
+        Ok(exists)
+    }
+
     async fn direct_append(
         &self,
         path: &str,
         written_bytes: usize,
         data: BytesWrapper,
     ) -> Result<(), WorkerError> {
+        let _in_flight = InFlightIoGuard::new(&self.inner.in_flight_io);
+        self.inject_fault(FaultOperation::APPEND).await?;
         let len = data.len();
+        self.check_index_reserved_capacity(path, len)?;
         self.get_permit(len).await?;
 
         let timer = LOCALFILE_DISK_DIRECT_APPEND_OPERATION_DURATION
@@ -432,6 +698,8 @@ impl LocalIO for LocalDiskDelegator {
         offset: i64,
         length: i64,
     ) -> Result<Bytes, WorkerError> {
+        let _in_flight = InFlightIoGuard::new(&self.inner.in_flight_io);
+        self.inject_fault(FaultOperation::READ).await?;
         self.get_permit(14 * 1024 * 1024).await?;
 
         let timer = LOCALFILE_DISK_DIRECT_READ_OPERATION_DURATION
@@ -466,17 +734,37 @@ impl LocalDiskStorage for LocalDiskDelegator {
     }
 
     fn mark_healthy(&self) -> Result<()> {
-        self.inner.is_healthy.store(true, SeqCst);
+        if !self.inner.is_healthy.swap(true, SeqCst) {
+            TIMELINE.record(
+                TimelineCategory::DISK_HEALTH,
+                format!("disk: {} becomes healthy", &self.inner.root),
+            );
+        }
+        self.inner.unhealthy_since_sec.store(0, SeqCst);
         Ok(())
     }
 
     fn mark_unhealthy(&self) -> Result<()> {
-        self.inner.is_healthy.store(false, SeqCst);
+        if !self.inner.is_healthy.swap(false, SeqCst) {
+            return Ok(());
+        }
+        // the zero is reserved for the healthy state
+        let now = util::now_monotonic_as_sec().max(1);
+        self.inner.unhealthy_since_sec.store(now, SeqCst);
+        TIMELINE.record(
+            TimelineCategory::DISK_HEALTH,
+            format!("disk: {} becomes unhealthy", &self.inner.root),
+        );
         Ok(())
     }
 
     fn mark_corrupted(&self) -> Result<()> {
-        self.inner.is_corrupted.store(true, SeqCst);
+        if !self.inner.is_corrupted.swap(true, SeqCst) {
+            TIMELINE.record(
+                TimelineCategory::DISK_HEALTH,
+                format!("disk: {} is corrupted", &self.inner.root),
+            );
+        }
         Ok(())
     }
 }
@@ -484,9 +772,11 @@ impl LocalDiskStorage for LocalDiskDelegator {
 #[cfg(test)]
 mod test {
     use crate::config::LocalfileStoreConfig;
+    use crate::error::WorkerError;
     use crate::runtime::manager::RuntimeManager;
     use crate::store::local::delegator::LocalDiskDelegator;
-    use crate::store::local::LocalDiskStorage;
+    use crate::store::local::{LocalDiskStorage, LocalIO};
+    use bytes::Bytes;
     use std::sync::atomic::AtomicU64;
     use std::sync::atomic::Ordering::SeqCst;
     use std::sync::Arc;
@@ -525,4 +815,40 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_index_reserved_capacity() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_index_reserved_capacity").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.disk_index_reserved_capacity = Some("64B".to_string());
+
+        let runtime_manager = RuntimeManager::default();
+        let delegator = LocalDiskDelegator::new(&runtime_manager, &temp_path, &config);
+        delegator.with_capacity(Arc::new(AtomicU64::new(10000)));
+        let available = Arc::new(AtomicU64::new(100));
+        delegator.with_available(available.clone());
+
+        let append = |path: &str, len: usize| {
+            runtime_manager.wait(delegator.append(path, Bytes::from(vec![0; len]).into()))
+        };
+
+        // the data append is accepted if it won't fall into the reserved capacity
+        append("1.data", 20)?;
+
+        // the disk is nearly full, the large data append is refused
+        available.store(80, SeqCst);
+        match append("1.data", 40) {
+            Err(WorkerError::LOCAL_DISK_RESERVED_FOR_INDEX(root)) => assert_eq!(temp_path, root),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // but the index append still succeeds even if the disk is almost full
+        available.store(30, SeqCst);
+        append("1.index", 24)?;
+        assert!(append("1.data", 1).is_err());
+
+        Ok(())
+    }
 }
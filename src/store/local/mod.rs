@@ -21,9 +21,13 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 
+pub mod crc_cache;
 pub mod delegator;
 mod limiter;
+pub mod read_ahead;
 pub mod sync_io;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring_io;
 
 pub struct FileStat {
     pub content_length: u64,
@@ -41,7 +45,12 @@ pub trait LocalIO: Clone {
     ) -> Result<Bytes, WorkerError>;
     async fn delete(&self, path: &str) -> Result<(), WorkerError>;
     async fn write(&self, path: &str, data: Bytes) -> Result<(), WorkerError>;
+    /// Renames the file atomically, and the existing target file will be replaced.
+    async fn rename(&self, from: &str, to: &str) -> Result<(), WorkerError>;
     async fn file_stat(&self, path: &str) -> Result<FileStat, WorkerError>;
+    /// Whether the file or directory exists. Unlike the file_stat error, the IO error like
+    /// the permission denied will be returned rather than being regarded as absent.
+    async fn exists(&self, path: &str) -> Result<bool, WorkerError>;
 
     async fn direct_append(
         &self,
@@ -65,6 +74,9 @@ pub trait LocalDiskStorage: LocalIO {
 pub struct DiskStat {
     pub(crate) root: String,
     pub(crate) used_ratio: f64,
+    pub(crate) capacity: u64,
+    pub(crate) used: u64,
+    pub(crate) healthy: bool,
 }
 
 pub struct LocalfileStoreStat {
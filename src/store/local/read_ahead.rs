@@ -0,0 +1,163 @@
+use crate::config::ReadAheadConfig;
+use crate::metric::{
+    GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES, TOTAL_LOCALFILE_READ_AHEAD_HIT,
+    TOTAL_LOCALFILE_READ_AHEAD_MISS,
+};
+use crate::util;
+use anyhow::Result;
+use bytes::Bytes;
+use hashlink::LinkedHashMap;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+type PageKey = (String, i64);
+
+/// The LRU cache of the prefetched pages of the localfile data files. The page is keyed by the
+/// relative data file path and the offset aligned with the read-ahead window.
+pub struct ReadAheadCache {
+    capacity: u64,
+    window_size: i64,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    pages: LinkedHashMap<PageKey, Bytes>,
+    size: u64,
+    // the prefetching pages with the generation of their prefetch
+    prefetching: HashMap<PageKey, u64>,
+    generation: u64,
+}
+
+/// The prefetching page, whose result is only accepted if the page has not been invalidated
+/// since the prefetch started.
+pub struct Prefetch {
+    pub page_offset: i64,
+    generation: u64,
+}
+
+impl ReadAheadCache {
+    pub fn new(conf: &ReadAheadConfig) -> Result<Self> {
+        let capacity = util::try_parse_raw_to_bytesize(&conf.capacity)?;
+        let window_size = util::try_parse_raw_to_bytesize(&conf.window_size)?.max(1) as i64;
+        Ok(Self {
+            capacity,
+            window_size,
+            inner: Mutex::new(Inner {
+                pages: LinkedHashMap::new(),
+                size: 0,
+                prefetching: HashMap::new(),
+                generation: 0,
+            }),
+        })
+    }
+
+    pub fn window_size(&self) -> i64 {
+        self.window_size
+    }
+
+    fn page_offset(&self, offset: i64) -> i64 {
+        offset / self.window_size * self.window_size
+    }
+
+    /// Returns the data only if the range is fully covered by one cached page.
+    pub fn get(&self, path: &str, offset: i64, len: i64) -> Option<Bytes> {
+        let page_offset = self.page_offset(offset);
+        let mut inner = self.inner.lock();
+        let data = inner
+            .pages
+            .to_back(&(path.to_string(), page_offset))
+            .and_then(|page| {
+                let start = (offset - page_offset) as usize;
+                let end = start + len as usize;
+                if end <= page.len() {
+                    Some(page.slice(start..end))
+                } else {
+                    None
+                }
+            });
+        match &data {
+            Some(_) => TOTAL_LOCALFILE_READ_AHEAD_HIT.inc(),
+            _ => TOTAL_LOCALFILE_READ_AHEAD_MISS.inc(),
+        }
+        data
+    }
+
+    /// Marks the page containing the offset as prefetching with a new generation.
+    /// None will be returned if the page has been cached or in prefetching.
+    pub fn start_prefetch(&self, path: &str, offset: i64) -> Option<Prefetch> {
+        let key = (path.to_string(), self.page_offset(offset));
+        let mut inner = self.inner.lock();
+        if inner.pages.contains_key(&key) || inner.prefetching.contains_key(&key) {
+            return None;
+        }
+        inner.generation += 1;
+        let generation = inner.generation;
+        let page_offset = key.1;
+        inner.prefetching.insert(key, generation);
+        Some(Prefetch {
+            page_offset,
+            generation,
+        })
+    }
+
+    /// Finishes the prefetching. The page is dropped if it has been invalidated in flight,
+    /// even though the same page has been prefetched again after the invalidation.
+    pub fn finish_prefetch(&self, path: &str, prefetch: &Prefetch, data: Option<Bytes>) {
+        let key = (path.to_string(), prefetch.page_offset);
+        let mut inner = self.inner.lock();
+        match inner.prefetching.get(&key) {
+            Some(generation) if *generation == prefetch.generation => {
+                inner.prefetching.remove(&key);
+            }
+            _ => return,
+        }
+        let data = match data {
+            Some(data) if !data.is_empty() => data,
+            _ => return,
+        };
+        inner.size += data.len() as u64;
+        GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.add(data.len() as i64);
+        if let Some(replaced) = inner.pages.insert(key, data) {
+            inner.size -= replaced.len() as u64;
+            GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.sub(replaced.len() as i64);
+        }
+        while inner.size > self.capacity {
+            match inner.pages.pop_front() {
+                Some((_, evicted)) => {
+                    inner.size -= evicted.len() as u64;
+                    GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.sub(evicted.len() as i64);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Drops the cached and prefetching pages of the files under the path prefix.
+    pub fn invalidate(&self, path_prefix: &str) {
+        let mut inner = self.inner.lock();
+        let mut removed = 0u64;
+        inner.pages.retain(|(path, _), page| {
+            if path.starts_with(path_prefix) {
+                removed += page.len() as u64;
+                false
+            } else {
+                true
+            }
+        });
+        inner.size -= removed;
+        GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.sub(removed as i64);
+        inner
+            .prefetching
+            .retain(|(path, _), _| !path.starts_with(path_prefix));
+    }
+
+    pub fn size(&self) -> u64 {
+        self.inner.lock().size
+    }
+}
+
+impl Drop for ReadAheadCache {
+    fn drop(&mut self) {
+        GAUGE_LOCALFILE_READ_AHEAD_CACHE_BYTES.sub(self.inner.get_mut().size as i64);
+    }
+}
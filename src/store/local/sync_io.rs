@@ -15,13 +15,17 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use await_tree::InstrumentAwait;
 use bytes::{Bytes, BytesMut};
+use dashmap::DashMap;
 use log::debug;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Error, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, BufWriter, Error, IoSlice, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::os::unix::fs::FileExt;
 use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
 use std::time::Instant;
 use std::{fs, io};
@@ -29,6 +33,10 @@ use std::{fs, io};
 static IO_BUFFER_POOL: Lazy<IoBufferPool> =
     Lazy::new(|| IoBufferPool::new(ALIGN * 1024 * 4, 64 * 4));
 
+// the direct read larger than this is split into the concurrent aligned reads,
+// each of which fits into the pooled io buffer.
+const DIRECT_READ_SPLIT_THRESHOLD: usize = ALIGN * 1024;
+
 #[derive(Clone)]
 pub struct SyncLocalIO {
     inner: Arc<Inner>,
@@ -40,6 +48,15 @@ struct Inner {
     buf_writer_capacity: Option<usize>,
     buf_reader_capacity: Option<usize>,
 
+    composed_bytes_vectored_write_enable: bool,
+
+    parent_dir_sync_enable: bool,
+    parent_dir_synced_times: AtomicU64,
+
+    // key: the direct appended file path with root
+    direct_append_lengths: DashMap<String, DirectAppendLength>,
+    direct_append_stat_times: AtomicU64,
+
     read_runtime_ref: RuntimeRef,
     write_runtime_ref: RuntimeRef,
 }
@@ -51,12 +68,19 @@ impl SyncLocalIO {
         root: &str,
         buf_writer_capacity: Option<usize>,
         buf_reader_capacity: Option<usize>,
+        composed_bytes_vectored_write_enable: bool,
+        parent_dir_sync_enable: bool,
     ) -> Self {
         Self {
             inner: Arc::new(Inner {
                 root: root.to_owned(),
                 buf_writer_capacity,
                 buf_reader_capacity,
+                composed_bytes_vectored_write_enable,
+                parent_dir_sync_enable,
+                parent_dir_synced_times: Default::default(),
+                direct_append_lengths: Default::default(),
+                direct_append_stat_times: Default::default(),
                 read_runtime_ref: read_runtime_ref.clone(),
                 write_runtime_ref: write_runtime_ref.clone(),
             }),
@@ -68,6 +92,46 @@ impl SyncLocalIO {
     }
 }
 
+/// The tracked length of the direct appended file to avoid the stat on every append.
+#[derive(Clone, Copy, Debug)]
+struct DirectAppendLength {
+    // the logical length written by the caller, which is also the version of this entry
+    written_bytes: u64,
+    // the physical length including the aligned padding
+    file_len: u64,
+}
+
+impl Inner {
+    /// Takes the cached file length if it matches the written bytes from the caller.
+    /// Once mismatched, the file may be touched by other writers and must be stat-verified.
+    fn take_direct_append_length(&self, path: &str, written_bytes: u64) -> Option<u64> {
+        self.direct_append_lengths
+            .remove(path)
+            .map(|(_, length)| length)
+            .filter(|length| length.written_bytes == written_bytes)
+            .map(|length| length.file_len)
+    }
+
+    fn invalidate_direct_append_lengths(&self, path: &str) {
+        self.direct_append_lengths
+            .retain(|key, _| !key.starts_with(path));
+    }
+
+    /// Whether the parent dir should be synced after writing, that is only for the new file.
+    fn is_parent_dir_sync_required(&self, path: &Path) -> bool {
+        self.parent_dir_sync_enable && !path.exists()
+    }
+
+    /// Syncs the parent dir to make the newly created file entry durable.
+    fn sync_parent_dir(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+            self.parent_dir_synced_times.fetch_add(1, SeqCst);
+        }
+        Ok(())
+    }
+}
+
 fn fill_buffer_and_write(
     io_buffer: &mut IoBuffer,
     buffer_size: usize,
@@ -124,7 +188,39 @@ fn fill_buffer_and_write(
     Ok(written_len)
 }
 
+// Write all the chunked bytes into the file by the vectored io to avoid the extra copy
+// of the buf writer. The file must be opened with the append mode.
+fn write_all_vectored(file: &mut File, chained_bytes: &[Bytes]) -> Result<(), Error> {
+    let mut slices: Vec<IoSlice> = chained_bytes.iter().map(|x| IoSlice::new(x)).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        match file.write_vectored(slices) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+            Ok(n) => IoSlice::advance_slices(&mut slices, n),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
 fn inner_direct_read(path: &str, offset: i64, len: i64) -> Result<Bytes, Error> {
+    let (buf, range) = inner_direct_read_buffer(path, offset, len)?;
+    Ok(Bytes::copy_from_slice(&buf[range]))
+}
+
+/// Reads the aligned range covering the requested data into the io buffer,
+/// returning the buffer and the range of the requested data in it.
+fn inner_direct_read_buffer(
+    path: &str,
+    offset: i64,
+    len: i64,
+) -> Result<(RecycledIoBuffer<'static>, Range<usize>), Error> {
     let left_boundary = align_down(ALIGN, offset as usize);
     let right_boundary = align_up(ALIGN, (offset + len) as usize);
     let range = right_boundary - left_boundary;
@@ -132,10 +228,11 @@ fn inner_direct_read(path: &str, offset: i64, len: i64) -> Result<Bytes, Error>
     let (mut buf, expected) = if range < IO_BUFFER_POOL.buffer_size() {
         (IO_BUFFER_POOL.acquire(), IO_BUFFER_POOL.buffer_size())
     } else {
-        /// todo: if the required data len > pool buffer size, it can split to multi
-        /// access to reuse the aligned buffer to reduce system load.
         ALIGNMENT_BUFFER_POOL_READ_ACQUIRE_MISS.inc();
-        (RecycledIoBuffer::new(None, IoBuffer::new(range)), range)
+        let start = Instant::now();
+        let buf = IoBuffer::new(range);
+        LOCALFILE_READ_MEMORY_ALLOCATION_LATENCY.record(start.elapsed().as_nanos() as u64);
+        (RecycledIoBuffer::new(None, buf), range)
     };
     // only gotten the min range buf to reduce io range access
     let mut range_buf = &mut buf[..range];
@@ -170,6 +267,15 @@ fn inner_direct_read(path: &str, offset: i64, len: i64) -> Result<Bytes, Error>
 
     let start = offset as usize - left_boundary;
     let end = start + len as usize;
+    if read < end {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "Not expected bytes of direct read. expected: {}, actual: {}",
+                end, read
+            ),
+        ));
+    }
     debug!(
         "read {} bytes. start:{}, end:{}. data: {:?}",
         &range_buf.len(),
@@ -177,8 +283,7 @@ fn inner_direct_read(path: &str, offset: i64, len: i64) -> Result<Bytes, Error>
         end,
         &range_buf.to_vec()
     );
-    let data = Bytes::copy_from_slice(&range_buf[start..end]);
-    Ok(data)
+    Ok((buf, start..end))
 }
 
 #[async_trait]
@@ -197,13 +302,30 @@ impl LocalIO for SyncLocalIO {
     async fn append(&self, path: &str, data: BytesWrapper) -> anyhow::Result<(), WorkerError> {
         let path = self.with_root(path);
         let buffer_capacity = self.inner.buf_writer_capacity.clone();
+        let vectored_write_enable = self.inner.composed_bytes_vectored_write_enable;
+        let inner = self.inner.clone();
 
         let r = self
             .inner
             .write_runtime_ref
             .spawn_blocking(move || {
                 let path = Path::new(&path);
+                let parent_dir_sync_required = inner.is_parent_dir_sync_required(path);
                 let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+
+                // the composed bytes have been chunked, there is no need to copy into buf writer
+                let data = match data {
+                    BytesWrapper::Composed(composed) if vectored_write_enable => {
+                        write_all_vectored(&mut file, &composed.to_vec())?;
+                        file.sync_all()?;
+                        if parent_dir_sync_required {
+                            inner.sync_parent_dir(path)?;
+                        }
+                        return Ok::<(), io::Error>(());
+                    }
+                    data => data,
+                };
+
                 let mut buf_writer = match buffer_capacity {
                     Some(capacity) => BufWriter::with_capacity(capacity, file),
                     _ => BufWriter::new(file),
@@ -219,6 +341,9 @@ impl LocalIO for SyncLocalIO {
 
                 let file = buf_writer.into_inner()?;
                 file.sync_all()?;
+                if parent_dir_sync_required {
+                    inner.sync_parent_dir(path)?;
+                }
 
                 Ok::<(), io::Error>(())
             })
@@ -284,6 +409,7 @@ impl LocalIO for SyncLocalIO {
 
     async fn delete(&self, path: &str) -> anyhow::Result<(), WorkerError> {
         let path = self.with_root(path);
+        self.inner.invalidate_direct_append_lengths(&path);
 
         let r = self
             .inner
@@ -305,6 +431,7 @@ impl LocalIO for SyncLocalIO {
 
     async fn write(&self, path: &str, data: Bytes) -> anyhow::Result<(), WorkerError> {
         let path = self.with_root(path);
+        self.inner.invalidate_direct_append_lengths(&path);
         let r = self
             .inner
             .write_runtime_ref
@@ -313,6 +440,18 @@ impl LocalIO for SyncLocalIO {
         Ok(())
     }
 
+    async fn rename(&self, from: &str, to: &str) -> anyhow::Result<(), WorkerError> {
+        let from = self.with_root(from);
+        let to = self.with_root(to);
+        self.inner.invalidate_direct_append_lengths(&from);
+        self.inner.invalidate_direct_append_lengths(&to);
+        self.inner
+            .write_runtime_ref
+            .spawn_blocking(move || fs::rename(from, to))
+            .await??;
+        Ok(())
+    }
+
     async fn file_stat(&self, path: &str) -> anyhow::Result<FileStat, WorkerError> {
         let path = self.with_root(path);
         let r = self
@@ -325,6 +464,16 @@ impl LocalIO for SyncLocalIO {
         })
     }
 
+    async fn exists(&self, path: &str) -> anyhow::Result<bool, WorkerError> {
+        let path = self.with_root(path);
+        let r = self
+            .inner
+            .read_runtime_ref
+            .spawn_blocking(move || Path::new(&path).try_exists())
+            .await??;
+        Ok(r)
+    }
+
     async fn direct_append(
         &self,
         path: &str,
@@ -332,17 +481,27 @@ impl LocalIO for SyncLocalIO {
         raw_data: BytesWrapper,
     ) -> anyhow::Result<(), WorkerError> {
         let raw_path = self.with_root(path);
+        let inner = self.inner.clone();
         let r = self
             .inner
             .write_runtime_ref
             .spawn_blocking(move || {
                 let path = Path::new(&raw_path);
-                let file_len = match fs::metadata(&path) {
-                    Ok(metadata) => {
-                        let len = metadata.len();
-                        len
+                // the entry is taken out and only put back on success, so that
+                // the file will be stat-verified again after any error.
+                let cached_file_len =
+                    inner.take_direct_append_length(&raw_path, written_bytes as u64);
+                let parent_dir_sync_required =
+                    cached_file_len.is_none() && inner.is_parent_dir_sync_required(path);
+                let file_len = match cached_file_len {
+                    Some(len) => len,
+                    None => {
+                        inner.direct_append_stat_times.fetch_add(1, SeqCst);
+                        match fs::metadata(&path) {
+                            Ok(metadata) => metadata.len(),
+                            Err(_) => 0,
+                        }
                     }
-                    Err(_) => 0,
                 };
                 let (mut next_offset, remain_bytes) = if file_len != written_bytes as u64 {
                     let left = align_down(ALIGN, written_bytes);
@@ -389,6 +548,16 @@ impl LocalIO for SyncLocalIO {
                     ));
                 }
                 file.sync_all()?;
+                if parent_dir_sync_required {
+                    inner.sync_parent_dir(path)?;
+                }
+                inner.direct_append_lengths.insert(
+                    raw_path.to_string(),
+                    DirectAppendLength {
+                        written_bytes: next_offset + total_len as u64,
+                        file_len: next_offset + align_up(ALIGN, total_len) as u64,
+                    },
+                );
                 Ok::<(), io::Error>(())
             })
             .instrument_await("wait the spawned block future")
@@ -405,14 +574,51 @@ impl LocalIO for SyncLocalIO {
         len: i64,
     ) -> anyhow::Result<Bytes, WorkerError> {
         let path = self.with_root(path);
-        let r = self
-            .inner
-            .read_runtime_ref
-            .spawn_blocking(move || inner_direct_read(&path, offset, len))
-            .instrument_await("wait the spawned block future")
-            .await??;
+        if len as usize <= DIRECT_READ_SPLIT_THRESHOLD {
+            let r = self
+                .inner
+                .read_runtime_ref
+                .spawn_blocking(move || inner_direct_read(&path, offset, len))
+                .instrument_await("wait the spawned block future")
+                .await??;
+            return Ok(r);
+        }
 
-        Ok(r)
+        // the large read is split by the aligned boundaries to be issued concurrently,
+        // and then reassembled without the zero-initialized allocation.
+        let (offset, len) = (offset as usize, len as usize);
+        let mut handles = vec![];
+        let mut chunk_start = align_down(ALIGN, offset);
+        while chunk_start < offset + len {
+            let chunk_end = (chunk_start + DIRECT_READ_SPLIT_THRESHOLD).min(offset + len);
+            let chunk_offset = chunk_start.max(offset);
+            let path = path.clone();
+            handles.push(self.inner.read_runtime_ref.spawn_blocking(move || {
+                inner_direct_read_buffer(
+                    &path,
+                    chunk_offset as i64,
+                    (chunk_end - chunk_offset) as i64,
+                )
+            }));
+            chunk_start = chunk_end;
+        }
+        let chunks = futures::future::try_join_all(handles)
+            .instrument_await("wait the spawned block futures")
+            .await?;
+
+        let mut data = BytesMut::with_capacity(len);
+        for chunk in chunks {
+            let (buf, range) = chunk?;
+            data.extend_from_slice(&buf[range]);
+        }
+        if data.len() != len {
+            return Err(WorkerError::Other(anyhow!(
+                "Not expected bytes of the split direct read. expected: {}, actual: {}",
+                len,
+                data.len()
+            )));
+        }
+        Ok(data.freeze())
     }
 }
 
@@ -423,13 +629,16 @@ mod test {
     use crate::runtime::manager::create_runtime;
     use crate::store::alignment::io_buffer_pool::IoBufferPool;
     use crate::store::alignment::io_bytes::IoBuffer;
-    use crate::store::local::sync_io::{fill_buffer_and_write, SyncLocalIO, ALIGN};
+    use crate::store::local::sync_io::{
+        fill_buffer_and_write, SyncLocalIO, ALIGN, DIRECT_READ_SPLIT_THRESHOLD,
+    };
     use crate::store::local::LocalIO;
     use bytes::{Bytes, BytesMut};
     use std::fs;
     use std::fs::{File, OpenOptions};
     use std::io::{Read, Seek, SeekFrom, Write};
     use std::path::Path;
+    use std::sync::atomic::Ordering::SeqCst;
     use std::thread::sleep;
     use std::time::Duration;
 
@@ -451,6 +660,8 @@ mod test {
             &temp_path,
             None,
             None,
+            true,
+            false,
         );
 
         // append
@@ -473,12 +684,130 @@ mod test {
         let data = base_runtime_ref.block_on(io_handler.read(data_file_name, 10, Some(20)))?;
         assert_eq!(vec![0; 20], *data);
 
+        // exists
+        assert!(base_runtime_ref.block_on(io_handler.exists(data_file_name))?);
+        assert!(base_runtime_ref.block_on(io_handler.exists(""))?);
+        assert!(!base_runtime_ref.block_on(io_handler.exists("2.data"))?);
+
         // delete
         base_runtime_ref.block_on(io_handler.delete(data_file_name))?;
         match base_runtime_ref.block_on(io_handler.file_stat(data_file_name)) {
             Err(_) => {}
             Ok(_) => panic!(),
         };
+        assert!(!base_runtime_ref.block_on(io_handler.exists(data_file_name))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parent_dir_sync() -> anyhow::Result<()> {
+        let base_runtime_ref = create_runtime(1, "base");
+        let read_rumtime_ref = create_runtime(1, "read");
+        let write_rumtime_ref = create_runtime(1, "write");
+
+        let temp_dir = tempdir::TempDir::new("test_parent_dir_sync").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        println!("created the temp file path: {}", &temp_path);
+
+        let io_handler = SyncLocalIO::new(
+            &read_rumtime_ref,
+            &write_rumtime_ref,
+            &temp_path,
+            None,
+            None,
+            true,
+            true,
+        );
+        let synced_times = || io_handler.inner.parent_dir_synced_times.load(SeqCst);
+
+        // case1: the dir is synced once for the new file
+        base_runtime_ref.block_on(io_handler.append("1.data", Bytes::from(vec![0; 10]).into()))?;
+        assert_eq!(1, synced_times());
+        base_runtime_ref.block_on(io_handler.append("1.data", Bytes::from(vec![0; 10]).into()))?;
+        assert_eq!(1, synced_times());
+
+        // case2: the direct append also syncs the dir for the new file
+        base_runtime_ref.block_on(io_handler.direct_append(
+            "2.data",
+            0,
+            Bytes::from(vec![0; ALIGN]).into(),
+        ))?;
+        assert_eq!(2, synced_times());
+        base_runtime_ref.block_on(io_handler.direct_append(
+            "2.data",
+            ALIGN,
+            Bytes::from(vec![0; ALIGN]).into(),
+        ))?;
+        assert_eq!(2, synced_times());
+
+        // case3: disabled
+        let io_handler = SyncLocalIO::new(
+            &read_rumtime_ref,
+            &write_rumtime_ref,
+            &temp_path,
+            None,
+            None,
+            true,
+            false,
+        );
+        base_runtime_ref.block_on(io_handler.append("3.data", Bytes::from(vec![0; 10]).into()))?;
+        assert_eq!(0, io_handler.inner.parent_dir_synced_times.load(SeqCst));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_composed_bytes_append() -> anyhow::Result<()> {
+        let base_runtime_ref = create_runtime(2, "base");
+        let read_rumtime_ref = create_runtime(1, "read");
+        let write_rumtime_ref = create_runtime(1, "write");
+
+        let temp_dir = tempdir::TempDir::new("test_composed_bytes_append")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        println!("created the temp file path: {}", &temp_path);
+
+        let composed = || {
+            let mut composed = ComposedBytes::new();
+            composed.put(Bytes::from(vec![b'a'; 100]));
+            composed.put(Bytes::new());
+            composed.put(Bytes::from(vec![b'b'; 4097]));
+            composed.put(Bytes::from(vec![b'c'; 3]));
+            composed
+        };
+
+        let vectored_io_handler = SyncLocalIO::new(
+            &read_rumtime_ref,
+            &write_rumtime_ref,
+            &temp_path,
+            Some(1024),
+            None,
+            true,
+            false,
+        );
+        let buffered_io_handler = SyncLocalIO::new(
+            &read_rumtime_ref,
+            &write_rumtime_ref,
+            &temp_path,
+            Some(1024),
+            None,
+            false,
+            false,
+        );
+
+        for _ in 0..2 {
+            base_runtime_ref
+                .block_on(vectored_io_handler.append("vectored.data", composed().into()))?;
+            base_runtime_ref
+                .block_on(buffered_io_handler.append("buffered.data", composed().into()))?;
+        }
+
+        let vectored =
+            base_runtime_ref.block_on(vectored_io_handler.read("vectored.data", 0, None))?;
+        let buffered =
+            base_runtime_ref.block_on(buffered_io_handler.read("buffered.data", 0, None))?;
+        assert_eq!((100 + 4097 + 3) * 2, vectored.len());
+        assert_eq!(buffered, vectored);
 
         Ok(())
     }
@@ -566,6 +895,84 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_direct_append_length_cache() -> anyhow::Result<()> {
+        let base_runtime_ref = create_runtime(1, "base");
+        let read_rumtime_ref = create_runtime(1, "read");
+        let write_rumtime_ref = create_runtime(1, "write");
+
+        let temp_dir = tempdir::TempDir::new("test_direct_append_length_cache")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let create_io_handler = || {
+            SyncLocalIO::new(
+                &read_rumtime_ref,
+                &write_rumtime_ref,
+                &temp_path,
+                None,
+                None,
+                true,
+                false,
+            )
+        };
+        let io_handler = create_io_handler();
+        let stat_times = || io_handler.inner.direct_append_stat_times.load(SeqCst);
+        let file_len = || {
+            fs::metadata(format!("{}/1.data", &temp_path))
+                .unwrap()
+                .len()
+        };
+
+        // case1: only the first append is stat-verified, and the offsets advance by the aligned sizes
+        let mut written_bytes = 0;
+        for len in [10, ALIGN, ALIGN + 3, 7] {
+            base_runtime_ref.block_on(io_handler.direct_append(
+                "1.data",
+                written_bytes,
+                Bytes::from(vec![b'a'; len]).into(),
+            ))?;
+            written_bytes += len;
+            assert_eq!(align_up(ALIGN, written_bytes) as u64, file_len());
+        }
+        assert_eq!(1, stat_times());
+        let data = base_runtime_ref.block_on(io_handler.direct_read("1.data", 0, 64))?;
+        assert_eq!(vec![b'a'; 64], data);
+
+        // case2: the other writer appends the same path, and the version mismatch falls back to stat
+        let other_io_handler = create_io_handler();
+        base_runtime_ref.block_on(other_io_handler.direct_append(
+            "1.data",
+            written_bytes,
+            Bytes::from(vec![b'b'; 5]).into(),
+        ))?;
+        written_bytes += 5;
+        base_runtime_ref.block_on(io_handler.direct_append(
+            "1.data",
+            written_bytes,
+            Bytes::from(vec![b'c'; 5]).into(),
+        ))?;
+        written_bytes += 5;
+        assert_eq!(2, stat_times());
+        let data = base_runtime_ref.block_on(io_handler.direct_read(
+            "1.data",
+            written_bytes as i64 - 10,
+            10,
+        ))?;
+        assert_eq!(b"bbbbbccccc".to_vec(), data);
+
+        // case3: the deletion invalidates the cached length
+        base_runtime_ref.block_on(io_handler.delete("1.data"))?;
+        assert!(io_handler.inner.direct_append_lengths.is_empty());
+        base_runtime_ref.block_on(io_handler.direct_append(
+            "1.data",
+            0,
+            Bytes::from(vec![b'd'; 5]).into(),
+        ))?;
+        assert_eq!(3, stat_times());
+        assert_eq!(ALIGN as u64, file_len());
+
+        Ok(())
+    }
+
     #[test]
     fn test_direct_io() -> anyhow::Result<()> {
         let base_runtime_ref = create_runtime(2, "base");
@@ -585,6 +992,8 @@ mod test {
             &temp_path,
             None,
             None,
+            true,
+            false,
         );
 
         let mut written_data = BytesMut::new();
@@ -630,6 +1039,56 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_split_direct_read() -> anyhow::Result<()> {
+        let base_runtime_ref = create_runtime(2, "base");
+        let read_rumtime_ref = create_runtime(4, "read");
+        let write_rumtime_ref = create_runtime(1, "write");
+
+        let temp_dir = tempdir::TempDir::new("test_split_direct_read")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let io_handler = SyncLocalIO::new(
+            &read_rumtime_ref,
+            &write_rumtime_ref,
+            &temp_path,
+            None,
+            None,
+            true,
+            false,
+        );
+
+        let written_data: Vec<u8> = (0..5 * 1024 * 1024 + 123)
+            .map(|x| (x % 251) as u8)
+            .collect();
+        base_runtime_ref.block_on(io_handler.direct_append(
+            "1.data",
+            0,
+            Bytes::from(written_data.clone()).into(),
+        ))?;
+
+        // the unaligned range larger than the threshold is split into the multiple reads
+        let (offset, len) = (7, DIRECT_READ_SPLIT_THRESHOLD + 1000);
+        let data =
+            base_runtime_ref.block_on(io_handler.direct_read("1.data", offset, len as i64))?;
+        let expected =
+            base_runtime_ref.block_on(io_handler.read("1.data", offset, Some(len as i64)))?;
+        assert_eq!(len, data.len());
+        assert_eq!(expected, data);
+        assert_eq!(
+            &written_data[offset as usize..offset as usize + len],
+            &data[..]
+        );
+
+        // the range reaching the end of the written data
+        let offset = ALIGN as i64 * 3 + 1;
+        let len = written_data.len() - offset as usize;
+        let data =
+            base_runtime_ref.block_on(io_handler.direct_read("1.data", offset, len as i64))?;
+        assert_eq!(&written_data[offset as usize..], &data[..]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_recycle_io_buffer() -> anyhow::Result<()> {
         for _ in 0..10 {
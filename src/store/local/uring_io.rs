@@ -0,0 +1,977 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::bits::{align_down, align_up, is_aligned};
+use crate::config::IoUringConfig;
+use crate::error::WorkerError;
+use crate::store::alignment::io_bytes::IoBuffer;
+use crate::store::alignment::ALIGN;
+use crate::store::local::sync_io::SyncLocalIO;
+use crate::store::local::{FileStat, LocalIO};
+use crate::store::BytesWrapper;
+use crate::util;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use await_tree::InstrumentAwait;
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+use io_uring::{cqueue, opcode, squeue, types, IoUring};
+use log::{error, warn};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+// the backoff of the ring thread when the submitting keeps failing, like the kernel resources
+// are exhausted, to not burn the cpu by retrying in the tight loop.
+const SUBMIT_FAILURE_MIN_BACKOFF: Duration = Duration::from_millis(1);
+const SUBMIT_FAILURE_MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The aligned buffers of one ring, which are registered into the kernel to avoid the page
+/// mapping of every direct io. If the registering fails, like exceeding the memlock limit,
+/// they are still pooled but submitted as the plain buffers.
+struct RegisteredBuffers {
+    // owns the memory of the addresses
+    _buffers: Vec<IoBuffer>,
+    addresses: Vec<usize>,
+    buffer_size: usize,
+    free: Mutex<Vec<usize>>,
+    registered: bool,
+}
+
+/// The exclusive lease of one registered buffer, which is returned to the pool once dropped.
+struct RegisteredBuffer {
+    buffers: Arc<RegisteredBuffers>,
+    index: usize,
+}
+
+impl RegisteredBuffer {
+    fn as_mut_ptr(&self) -> *mut u8 {
+        self.buffers.addresses[self.index] as *mut u8
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // safety: the buffer is leased exclusively until dropped
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.buffers.buffer_size) }
+    }
+}
+
+impl Drop for RegisteredBuffer {
+    fn drop(&mut self) {
+        self.buffers.free.lock().push(self.index);
+    }
+}
+
+enum AlignedBuffer {
+    Registered(RegisteredBuffer),
+    // allocated when the registered buffers are exhausted or too small
+    Allocated(IoBuffer),
+}
+
+impl AlignedBuffer {
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            AlignedBuffer::Registered(buffer) => buffer.as_mut_slice(),
+            AlignedBuffer::Allocated(buffer) => &mut buffer[..],
+        }
+    }
+}
+
+/// The single io operation submitted to the ring. It owns the file and the buffer until
+/// completed, so that the dropped caller future will not release them under the kernel.
+enum UringOp {
+    Write {
+        file: Arc<File>,
+        data: Bytes,
+        offset: u64,
+    },
+    Read {
+        file: Arc<File>,
+        buf: Vec<u8>,
+        // the read data is filled from this position of the buf
+        pos: usize,
+        offset: u64,
+    },
+    AlignedWrite {
+        file: Arc<File>,
+        buf: AlignedBuffer,
+        len: usize,
+        offset: u64,
+    },
+    AlignedRead {
+        file: Arc<File>,
+        buf: AlignedBuffer,
+        len: usize,
+        offset: u64,
+    },
+    Fsync {
+        file: Arc<File>,
+    },
+}
+
+impl UringOp {
+    fn to_entry(&mut self) -> squeue::Entry {
+        match self {
+            UringOp::Write { file, data, offset } => opcode::Write::new(
+                types::Fd(file.as_raw_fd()),
+                data.as_ptr(),
+                data.len() as u32,
+            )
+            .offset(*offset)
+            .build(),
+            UringOp::Read {
+                file,
+                buf,
+                pos,
+                offset,
+            } => {
+                let remaining = (buf.len() - *pos) as u32;
+                opcode::Read::new(
+                    types::Fd(file.as_raw_fd()),
+                    buf[*pos..].as_mut_ptr(),
+                    remaining,
+                )
+                .offset(*offset)
+                .build()
+            }
+            UringOp::AlignedWrite {
+                file,
+                buf,
+                len,
+                offset,
+            } => match buf {
+                AlignedBuffer::Registered(buffer) if buffer.buffers.registered => {
+                    opcode::WriteFixed::new(
+                        types::Fd(file.as_raw_fd()),
+                        buffer.as_mut_ptr(),
+                        *len as u32,
+                        buffer.index as u16,
+                    )
+                    .offset(*offset)
+                    .build()
+                }
+                _ => opcode::Write::new(
+                    types::Fd(file.as_raw_fd()),
+                    buf.as_mut_slice().as_ptr(),
+                    *len as u32,
+                )
+                .offset(*offset)
+                .build(),
+            },
+            UringOp::AlignedRead {
+                file,
+                buf,
+                len,
+                offset,
+            } => match buf {
+                AlignedBuffer::Registered(buffer) if buffer.buffers.registered => {
+                    opcode::ReadFixed::new(
+                        types::Fd(file.as_raw_fd()),
+                        buffer.as_mut_ptr(),
+                        *len as u32,
+                        buffer.index as u16,
+                    )
+                    .offset(*offset)
+                    .build()
+                }
+                _ => opcode::Read::new(
+                    types::Fd(file.as_raw_fd()),
+                    buf.as_mut_slice().as_mut_ptr(),
+                    *len as u32,
+                )
+                .offset(*offset)
+                .build(),
+            },
+            UringOp::Fsync { file } => opcode::Fsync::new(types::Fd(file.as_raw_fd())).build(),
+        }
+    }
+}
+
+struct UringRequest {
+    op: UringOp,
+    reply: oneshot::Sender<(io::Result<usize>, UringOp)>,
+}
+
+/// Owns the ring of one disk in the dedicated thread, which submits the requests in batch
+/// and replies the completions.
+struct UringDriver {
+    sender: Sender<UringRequest>,
+    buffers: Arc<RegisteredBuffers>,
+}
+
+impl UringDriver {
+    fn new(root: &str, config: &IoUringConfig) -> io::Result<Self> {
+        // fails on the kernel without the io_uring support
+        let ring = IoUring::new(config.entries)?;
+
+        let buffer_size = align_up(
+            ALIGN,
+            util::parse_raw_to_bytesize(&config.registered_buffer_size) as usize,
+        );
+        let mut buffers = Vec::with_capacity(config.registered_buffer_number);
+        let mut addresses = Vec::with_capacity(config.registered_buffer_number);
+        for _ in 0..config.registered_buffer_number {
+            let mut buffer = IoBuffer::new(buffer_size);
+            addresses.push(buffer.as_mut_ptr() as usize);
+            buffers.push(buffer);
+        }
+        let iovecs: Vec<libc::iovec> = addresses
+            .iter()
+            .map(|address| libc::iovec {
+                iov_base: *address as *mut libc::c_void,
+                iov_len: buffer_size,
+            })
+            .collect();
+        // safety: the registered buffers live longer than the in-flight operations
+        let registered = !iovecs.is_empty()
+            && match unsafe { ring.submitter().register_buffers(&iovecs) } {
+                Ok(_) => true,
+                Err(err) => {
+                    warn!(
+                        "Errors on registering the io_uring buffers for root: {}, they will be used as the plain buffers. err: {:?}",
+                        root, err
+                    );
+                    false
+                }
+            };
+        let free = (0..addresses.len()).rev().collect();
+        let buffers = Arc::new(RegisteredBuffers {
+            _buffers: buffers,
+            addresses,
+            buffer_size,
+            free: Mutex::new(free),
+            registered,
+        });
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let capacity = config.entries as usize;
+        std::thread::Builder::new()
+            .name("uring-io".to_string())
+            .spawn(move || drive(ring, receiver, capacity))?;
+
+        Ok(Self { sender, buffers })
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.buffers.buffer_size
+    }
+
+    fn acquire_buffer(&self, size: usize) -> AlignedBuffer {
+        if size <= self.buffers.buffer_size {
+            if let Some(index) = self.buffers.free.lock().pop() {
+                return AlignedBuffer::Registered(RegisteredBuffer {
+                    buffers: self.buffers.clone(),
+                    index,
+                });
+            }
+        }
+        AlignedBuffer::Allocated(IoBuffer::new(size))
+    }
+
+    async fn execute(&self, op: UringOp) -> io::Result<(usize, UringOp)> {
+        let (reply, receiver) = oneshot::channel();
+        if self.sender.send(UringRequest { op, reply }).is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The io_uring driver has exited",
+            ));
+        }
+        match receiver
+            .instrument_await("waiting the io_uring completion")
+            .await
+        {
+            Ok((result, op)) => Ok((result?, op)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The io_uring driver has exited",
+            )),
+        }
+    }
+}
+
+fn drive(mut ring: IoUring, receiver: Receiver<UringRequest>, capacity: usize) {
+    let mut in_flight: HashMap<u64, UringRequest> = HashMap::new();
+    let mut next_id = 0u64;
+    let mut backoff = SUBMIT_FAILURE_MIN_BACKOFF;
+    loop {
+        if in_flight.is_empty() {
+            match receiver.recv() {
+                Ok(request) => push(&mut ring, &mut in_flight, &mut next_id, request),
+                // all the senders are dropped, and nothing is in flight
+                Err(_) => return,
+            }
+        }
+        while in_flight.len() < capacity {
+            match receiver.try_recv() {
+                Ok(request) => push(&mut ring, &mut in_flight, &mut next_id, request),
+                Err(_) => break,
+            }
+        }
+        if in_flight.is_empty() {
+            continue;
+        }
+        // the in-flight operations must be waited even on errors, which are still
+        // referring the owned buffers.
+        match ring.submit_and_wait(1) {
+            Ok(_) => backoff = SUBMIT_FAILURE_MIN_BACKOFF,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                error!(
+                    "Errors on submitting the io_uring operations, retry after {:?}. err: {:?}",
+                    backoff, err
+                );
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(SUBMIT_FAILURE_MAX_BACKOFF);
+            }
+        }
+        let completed: Vec<cqueue::Entry> = ring.completion().collect();
+        for entry in completed {
+            if let Some(request) = in_flight.remove(&entry.user_data()) {
+                let result = if entry.result() < 0 {
+                    Err(io::Error::from_raw_os_error(-entry.result()))
+                } else {
+                    Ok(entry.result() as usize)
+                };
+                let _ = request.reply.send((result, request.op));
+            }
+        }
+    }
+}
+
+fn push(
+    ring: &mut IoUring,
+    in_flight: &mut HashMap<u64, UringRequest>,
+    next_id: &mut u64,
+    mut request: UringRequest,
+) {
+    let id = *next_id;
+    *next_id = next_id.wrapping_add(1);
+    let entry = request.op.to_entry().user_data(id);
+    // safety: the file and the buffer of the entry are owned by the in-flight request
+    let mut pushed = unsafe { ring.submission().push(&entry) }.is_ok();
+    if !pushed {
+        let _ = ring.submit();
+        pushed = unsafe { ring.submission().push(&entry) }.is_ok();
+    }
+    if !pushed {
+        let _ = request.reply.send((
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The io_uring submission queue is full",
+            )),
+            request.op,
+        ));
+        return;
+    }
+    in_flight.insert(id, request);
+}
+
+/// Runs the blocking file operations, like the opening and the metadata querying, off the
+/// async worker threads.
+async fn run_blocking<F, R>(func: F) -> io::Result<R>
+where
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    tokio::task::spawn_blocking(func)
+        .instrument_await("waiting the blocking file operation")
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+}
+
+/// The io_uring based local io. The data reading and writing are submitted to the ring of the
+/// disk rather than blocking the runtime threads, and the direct io uses the registered
+/// buffers. The other metadata operations are delegated to the sync io.
+#[derive(Clone)]
+pub struct UringLocalIO {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    root: String,
+    driver: UringDriver,
+    sync_io: SyncLocalIO,
+    parent_dir_sync_enable: bool,
+}
+
+impl UringLocalIO {
+    pub fn new(
+        root: &str,
+        config: &IoUringConfig,
+        sync_io: SyncLocalIO,
+        parent_dir_sync_enable: bool,
+    ) -> io::Result<Self> {
+        let driver = UringDriver::new(root, config)?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                root: root.to_owned(),
+                driver,
+                sync_io,
+                parent_dir_sync_enable,
+            }),
+        })
+    }
+
+    fn with_root(&self, path: &str) -> String {
+        format!("{}/{}", &self.inner.root, path)
+    }
+
+    async fn write_all(&self, file: &Arc<File>, data: Bytes, offset: u64) -> io::Result<u64> {
+        let mut data = data;
+        let mut offset = offset;
+        while !data.is_empty() {
+            let (written, _) = self
+                .inner
+                .driver
+                .execute(UringOp::Write {
+                    file: file.clone(),
+                    data: data.clone(),
+                    offset,
+                })
+                .await?;
+            if written == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+            data = data.slice(written..);
+            offset += written as u64;
+        }
+        Ok(offset)
+    }
+
+    async fn write_aligned(
+        &self,
+        file: &Arc<File>,
+        buf: AlignedBuffer,
+        len: usize,
+        offset: u64,
+    ) -> io::Result<AlignedBuffer> {
+        let (written, op) = self
+            .inner
+            .driver
+            .execute(UringOp::AlignedWrite {
+                file: file.clone(),
+                buf,
+                len,
+                offset,
+            })
+            .await?;
+        if written != len {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Errors on direct appending. expected: {}, actual: {}",
+                    len, written
+                ),
+            ));
+        }
+        match op {
+            UringOp::AlignedWrite { buf, .. } => Ok(buf),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Syncs the file, and also the parent dir to make the newly created file entry durable.
+    async fn sync(
+        &self,
+        file: &Arc<File>,
+        path: &Path,
+        parent_dir_sync_required: bool,
+    ) -> io::Result<()> {
+        self.inner
+            .driver
+            .execute(UringOp::Fsync { file: file.clone() })
+            .await?;
+        if parent_dir_sync_required {
+            if let Some(dir) = path.parent() {
+                let dir = dir.to_path_buf();
+                let dir = Arc::new(run_blocking(move || File::open(dir)).await?);
+                self.inner
+                    .driver
+                    .execute(UringOp::Fsync { file: dir })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the file to be appended, and returns the file length and whether the parent dir
+    /// should be synced for the newly created file.
+    async fn open_to_append(
+        &self,
+        path: &Path,
+        direct_io: bool,
+    ) -> io::Result<(Arc<File>, u64, bool)> {
+        let parent_dir_sync_enable = self.inner.parent_dir_sync_enable;
+        let path = path.to_path_buf();
+        run_blocking(move || {
+            let parent_dir_sync_required = parent_dir_sync_enable && !path.exists();
+            let mut options = OpenOptions::new();
+            options.write(true).create(true);
+            if direct_io {
+                options.custom_flags(libc::O_DIRECT | libc::O_NOATIME);
+            }
+            let file = options.open(&path)?;
+            let len = file.metadata()?.len();
+            Ok((Arc::new(file), len, parent_dir_sync_required))
+        })
+        .await
+    }
+
+    async fn inner_direct_read(&self, path: &str, offset: i64, len: i64) -> io::Result<Bytes> {
+        let left_boundary = align_down(ALIGN, offset as usize);
+        let right_boundary = align_up(ALIGN, (offset + len) as usize);
+        let range = right_boundary - left_boundary;
+
+        let path = path.to_string();
+        let file = run_blocking(move || {
+            OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_DIRECT | libc::O_NOATIME)
+                .open(path)
+        })
+        .await?;
+        let buf = self.inner.driver.acquire_buffer(range);
+        let (read, op) = self
+            .inner
+            .driver
+            .execute(UringOp::AlignedRead {
+                file: Arc::new(file),
+                buf,
+                len: range,
+                offset: left_boundary as u64,
+            })
+            .await?;
+        if !is_aligned(ALIGN, read) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Errors on direct read. expected: {}, actual: {}",
+                    range, read
+                ),
+            ));
+        }
+        let mut buf = match op {
+            UringOp::AlignedRead { buf, .. } => buf,
+            _ => unreachable!(),
+        };
+        let start = offset as usize - left_boundary;
+        let end = start + len as usize;
+        if read < end {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Not expected bytes of direct read. expected: {}, actual: {}",
+                    end, read
+                ),
+            ));
+        }
+        Ok(Bytes::copy_from_slice(&buf.as_mut_slice()[start..end]))
+    }
+}
+
+#[async_trait]
+impl LocalIO for UringLocalIO {
+    async fn create_dir(&self, dir: &str) -> anyhow::Result<(), WorkerError> {
+        self.inner.sync_io.create_dir(dir).await
+    }
+
+    async fn append(&self, path: &str, data: BytesWrapper) -> anyhow::Result<(), WorkerError> {
+        let path = self.with_root(path);
+        let path = Path::new(&path);
+        let (file, mut offset, parent_dir_sync_required) = self.open_to_append(path, false).await?;
+
+        let chunks = match data {
+            BytesWrapper::Direct(bytes) => vec![bytes],
+            BytesWrapper::Composed(composed) => composed.to_vec(),
+        };
+        for chunk in chunks {
+            offset = self.write_all(&file, chunk, offset).await?;
+        }
+        self.sync(&file, path, parent_dir_sync_required).await?;
+        Ok(())
+    }
+
+    async fn read(
+        &self,
+        path: &str,
+        offset: i64,
+        length: Option<i64>,
+    ) -> anyhow::Result<Bytes, WorkerError> {
+        let path = self.with_root(path);
+        let (file, file_len) = run_blocking(move || {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            Ok((Arc::new(file), len))
+        })
+        .await?;
+        // the whole file is read if the length is absent
+        let (offset, len) = match length {
+            Some(len) => (offset as u64, len as usize),
+            _ => (0, file_len as usize),
+        };
+
+        let mut buf = vec![0; len];
+        let mut pos = 0;
+        while pos < len {
+            let (read, op) = self
+                .inner
+                .driver
+                .execute(UringOp::Read {
+                    file: file.clone(),
+                    buf,
+                    pos,
+                    offset: offset + pos as u64,
+                })
+                .await?;
+            buf = match op {
+                UringOp::Read { buf, .. } => buf,
+                _ => unreachable!(),
+            };
+            if read == 0 {
+                break;
+            }
+            pos += read;
+        }
+
+        if length.is_some() && pos != len {
+            return Err(WorkerError::Other(anyhow!(
+                "Not expected bytes reading. expected: {}, actual: {}",
+                len,
+                pos
+            )));
+        }
+        buf.truncate(pos);
+        Ok(Bytes::from(buf))
+    }
+
+    async fn delete(&self, path: &str) -> anyhow::Result<(), WorkerError> {
+        self.inner.sync_io.delete(path).await
+    }
+
+    async fn write(&self, path: &str, data: Bytes) -> anyhow::Result<(), WorkerError> {
+        self.inner.sync_io.write(path, data).await
+    }
+
+    async fn file_stat(&self, path: &str) -> anyhow::Result<FileStat, WorkerError> {
+        self.inner.sync_io.file_stat(path).await
+    }
+
+    async fn exists(&self, path: &str) -> anyhow::Result<bool, WorkerError> {
+        self.inner.sync_io.exists(path).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> anyhow::Result<(), WorkerError> {
+        self.inner.sync_io.rename(from, to).await
+    }
+
+    async fn direct_append(
+        &self,
+        path: &str,
+        written_bytes: usize,
+        raw_data: BytesWrapper,
+    ) -> anyhow::Result<(), WorkerError> {
+        let raw_path = self.with_root(path);
+        let path = Path::new(&raw_path);
+        let (file, file_len, parent_dir_sync_required) = self.open_to_append(path, true).await?;
+        // the unaligned tail is read back and re-written with the new data
+        let (next_offset, remain_bytes) = if file_len != written_bytes as u64 {
+            let left = align_down(ALIGN, written_bytes);
+            let remaining_bytes = self
+                .inner_direct_read(&raw_path, left as i64, (written_bytes - left) as i64)
+                .await?;
+            (left as u64, Some(remaining_bytes))
+        } else {
+            (file_len, None)
+        };
+        let mut batch_bytes = match raw_data {
+            BytesWrapper::Direct(bytes) => vec![bytes],
+            BytesWrapper::Composed(composed) => composed.to_vec(),
+        };
+        if let Some(remain_bytes) = remain_bytes {
+            batch_bytes.insert(0, remain_bytes);
+        }
+        let total_len = batch_bytes.iter().map(|b| b.len()).sum::<usize>();
+
+        let buffer_size = self.inner.driver.buffer_size();
+        let mut buf = self.inner.driver.acquire_buffer(buffer_size);
+        let mut offset = next_offset;
+        let mut buffer_len = 0;
+        let mut written = 0;
+        for bytes in batch_bytes {
+            let mut bytes_offset = 0;
+            while bytes_offset < bytes.len() {
+                let copy_length = (bytes.len() - bytes_offset).min(buffer_size - buffer_len);
+                buf.as_mut_slice()[buffer_len..(buffer_len + copy_length)]
+                    .copy_from_slice(&bytes[bytes_offset..bytes_offset + copy_length]);
+                buffer_len += copy_length;
+                bytes_offset += copy_length;
+
+                if buffer_len == buffer_size {
+                    buf = self.write_aligned(&file, buf, buffer_len, offset).await?;
+                    offset += buffer_len as u64;
+                    written += buffer_len;
+                    buffer_len = 0;
+                }
+            }
+        }
+        if buffer_len > 0 {
+            buf = self
+                .write_aligned(&file, buf, align_up(ALIGN, buffer_len), offset)
+                .await?;
+            written += buffer_len;
+        }
+        drop(buf);
+
+        if written != total_len {
+            return Err(WorkerError::Other(anyhow!(
+                "Errors on direct appending. expected: {}, actual: {}",
+                total_len,
+                written
+            )));
+        }
+        self.sync(&file, path, parent_dir_sync_required).await?;
+        Ok(())
+    }
+
+    async fn direct_read(
+        &self,
+        path: &str,
+        offset: i64,
+        len: i64,
+    ) -> anyhow::Result<Bytes, WorkerError> {
+        let path = self.with_root(path);
+        Ok(self.inner_direct_read(&path, offset, len).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bits::align_up;
+    use crate::config::IoUringConfig;
+    use crate::runtime::manager::create_runtime;
+    use crate::runtime::RuntimeRef;
+    use crate::store::alignment::ALIGN;
+    use crate::store::local::sync_io::SyncLocalIO;
+    use crate::store::local::uring_io::UringLocalIO;
+    use crate::store::local::LocalIO;
+    use bytes::{Bytes, BytesMut};
+    use log::warn;
+    use std::fs;
+
+    fn create_sync_io(runtime_ref: &RuntimeRef, root: &str) -> SyncLocalIO {
+        SyncLocalIO::new(runtime_ref, runtime_ref, root, None, None, true, false)
+    }
+
+    // the io_uring backend is skipped if the kernel doesn't support
+    fn create_uring_io(
+        runtime_ref: &RuntimeRef,
+        root: &str,
+        registered_buffer_size: &str,
+    ) -> Option<UringLocalIO> {
+        let config = IoUringConfig {
+            entries: 32,
+            registered_buffer_number: 2,
+            registered_buffer_size: registered_buffer_size.to_string(),
+        };
+        match UringLocalIO::new(root, &config, create_sync_io(runtime_ref, root), false) {
+            Ok(io) => Some(io),
+            Err(err) => {
+                warn!("Skip the io_uring backend as unsupported. err: {:?}", err);
+                None
+            }
+        }
+    }
+
+    fn check_io<T: LocalIO>(runtime_ref: &RuntimeRef, io_handler: T) -> anyhow::Result<()> {
+        let data_file_name = "1.data";
+
+        // append
+        for _ in 0..3 {
+            runtime_ref
+                .block_on(io_handler.append(data_file_name, Bytes::from(vec![1; 1000]).into()))?;
+        }
+
+        // stat
+        let stat = runtime_ref.block_on(io_handler.file_stat(data_file_name))?;
+        assert_eq!(1000 * 3, stat.content_length);
+
+        // read all
+        let data = runtime_ref.block_on(io_handler.read(data_file_name, 0, None))?;
+        assert_eq!(vec![1; 3000], *data);
+
+        // seek read
+        let data = runtime_ref.block_on(io_handler.read(data_file_name, 10, Some(20)))?;
+        assert_eq!(vec![1; 20], *data);
+        // the reading beyond the file end is rejected
+        assert!(runtime_ref
+            .block_on(io_handler.read(data_file_name, 2990, Some(20)))
+            .is_err());
+
+        // delete
+        runtime_ref.block_on(io_handler.delete(data_file_name))?;
+        assert!(runtime_ref
+            .block_on(io_handler.file_stat(data_file_name))
+            .is_err());
+        assert!(!runtime_ref.block_on(io_handler.exists(data_file_name))?);
+
+        Ok(())
+    }
+
+    fn check_direct_io<T: LocalIO>(
+        runtime_ref: &RuntimeRef,
+        root: &str,
+        io_handler: T,
+    ) -> anyhow::Result<()> {
+        let data_file_name = "1.data";
+
+        let mut written_data = BytesMut::new();
+        written_data.extend_from_slice(&vec![b'x'; 3]);
+        written_data.extend_from_slice(&vec![b'y'; 2]);
+        written_data.extend_from_slice(&vec![b'z'; 5]);
+        let written_data = written_data.freeze();
+
+        // the unaligned appends
+        runtime_ref.block_on(io_handler.direct_append(
+            data_file_name,
+            0,
+            written_data.clone().into(),
+        ))?;
+        runtime_ref.block_on(io_handler.direct_append(
+            data_file_name,
+            10,
+            written_data.clone().into(),
+        ))?;
+        // the append across the aligned boundary
+        runtime_ref.block_on(io_handler.direct_append(
+            data_file_name,
+            20,
+            Bytes::from(vec![b'a'; 4096 + 10]).into(),
+        ))?;
+
+        let read = |offset: i64, len: i64| {
+            runtime_ref
+                .block_on(io_handler.direct_read(data_file_name, offset, len))
+                .unwrap()
+        };
+        assert_eq!(vec![b'y', b'y', b'z'], read(3, 3));
+        assert_eq!(vec![b'x', b'x', b'y', b'y'], read(11, 4));
+        assert_eq!(vec![b'z', b'a'], read(19, 2));
+        assert_eq!(vec![b'a'; 4096 + 10], read(20, 4096 + 10));
+
+        assert_eq!(
+            align_up(ALIGN, 10 + 10 + 4096 + 10) as u64,
+            fs::metadata(format!("{}/{}", root, data_file_name))?.len()
+        );
+        // the short read beyond the file end is rejected
+        let file_len = align_up(ALIGN, 10 + 10 + 4096 + 10) as i64;
+        assert!(runtime_ref
+            .block_on(io_handler.direct_read(data_file_name, file_len - 2, 10))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_io() -> anyhow::Result<()> {
+        let runtime_ref = create_runtime(2, "base");
+
+        let temp_dir = tempdir::TempDir::new("test_io_sync")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        check_io(&runtime_ref, create_sync_io(&runtime_ref, &root))?;
+
+        let temp_dir = tempdir::TempDir::new("test_io_uring")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        if let Some(io_handler) = create_uring_io(&runtime_ref, &root, "1M") {
+            check_io(&runtime_ref, io_handler)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_direct_io() -> anyhow::Result<()> {
+        let runtime_ref = create_runtime(2, "base");
+
+        let temp_dir = tempdir::TempDir::new("test_direct_io_sync")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        check_direct_io(&runtime_ref, &root, create_sync_io(&runtime_ref, &root))?;
+
+        // the registered buffer is large enough for the whole appending
+        let temp_dir = tempdir::TempDir::new("test_direct_io_uring")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        if let Some(io_handler) = create_uring_io(&runtime_ref, &root, "1M") {
+            check_direct_io(&runtime_ref, &root, io_handler)?;
+        }
+
+        // the appending is split into multiple aligned writes by the small registered buffer,
+        // and the reading beyond the buffer size uses the allocated buffer.
+        let temp_dir = tempdir::TempDir::new("test_direct_io_uring_small_buffer")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        if let Some(io_handler) = create_uring_io(&runtime_ref, &root, "4K") {
+            check_direct_io(&runtime_ref, &root, io_handler)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_direct_read() -> anyhow::Result<()> {
+        let runtime_ref = create_runtime(4, "base");
+        let temp_dir = tempdir::TempDir::new("test_concurrent_direct_read")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        let io_handler = match create_uring_io(&runtime_ref, &root, "8K") {
+            Some(io_handler) => io_handler,
+            _ => return Ok(()),
+        };
+
+        let data: Vec<u8> = (0..ALIGN * 4).map(|idx| (idx % 251) as u8).collect();
+        runtime_ref.block_on(io_handler.direct_append(
+            "1.data",
+            0,
+            Bytes::from(data.clone()).into(),
+        ))?;
+
+        // more concurrent readers than the registered buffers
+        let mut handles = vec![];
+        for idx in 0..16 {
+            let io_handler = io_handler.clone();
+            handles.push(runtime_ref.spawn(async move {
+                let offset = (idx * 997) % (ALIGN * 3);
+                let data = io_handler
+                    .direct_read("1.data", offset as i64, ALIGN as i64)
+                    .await
+                    .unwrap();
+                (offset, data)
+            }));
+        }
+        for handle in handles {
+            let (offset, read) = runtime_ref.block_on(handle)?;
+            assert_eq!(&data[offset..offset + ALIGN], read.as_ref());
+        }
+
+        Ok(())
+    }
+}
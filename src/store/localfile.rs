@@ -17,13 +17,19 @@
 
 use crate::app::ReadingOptions::FILE_OFFSET_AND_LEN;
 use crate::app::{
-    PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingViewContext,
-    RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
+    IndexEntryRange, PartitionedUId, PurgeBatch, PurgeDataContext, ReadingIndexViewContext,
+    ReadingViewContext, RegisterAppContext, ReleaseTicketContext, RequireBufferContext,
+    WritingViewContext,
 };
-use crate::config::{LocalfileStoreConfig, StorageType};
+use crate::config::{CompactionConfig, DiskPlacementMode, LocalfileStoreConfig, StorageType};
 use crate::error::WorkerError;
 use crate::metric::{
-    GAUGE_LOCAL_DISK_SERVICE_USED, TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY, TOTAL_LOCALFILE_USED,
+    GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES, GAUGE_LOCAL_DISK_SERVICE_USED,
+    TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY, TOTAL_LOCALFILE_COMPACTIONS,
+    TOTAL_LOCALFILE_COMPACTION_ABORTED, TOTAL_LOCALFILE_COMPACTION_RECLAIMED_BYTES,
+    TOTAL_LOCALFILE_COMPRESSION_SAVED_BYTES, TOTAL_LOCALFILE_DATA_CRC_MISMATCH,
+    TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS, TOTAL_LOCALFILE_DEDUP_BYTES,
+    TOTAL_LOCALFILE_INDEX_TRUNCATED, TOTAL_LOCALFILE_USED,
 };
 use crate::store::ResponseDataIndex::Local;
 use crate::store::{
@@ -31,40 +37,73 @@ use crate::store::{
     ResponseDataIndex, Store,
 };
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fs;
 use std::ops::Deref;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use await_tree::InstrumentAwait;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use dashmap::DashMap;
+use futures::StreamExt;
 
 use log::{debug, error, info, warn};
+use serde::Serialize;
 
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::composed_bytes::ComposedBytes;
 use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
 use crate::store::local::delegator::LocalDiskDelegator;
-use crate::util::get_crc;
+use crate::util::{check_deadline, get_crc, run_until_deadline};
 use dashmap::mapref::entry::Entry;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::Instrument;
 
-use crate::store::index_codec::{IndexCodec, INDEX_BLOCK_SIZE};
+use crate::store::compression::{
+    compress_chunks, get_codec, overlapped_chunks, Codec, CompressedChunk,
+};
+use crate::store::index_codec::{IndexBlock, IndexCodec, INDEX_BLOCK_SIZE};
+use crate::store::local::crc_cache::{BlockCrc, BlockCrcCache};
+use crate::store::local::read_ahead::ReadAheadCache;
 use crate::store::local::{LocalDiskStorage, LocalIO, LocalfileStoreStat};
 use crate::store::spill::SpillWritingViewContext;
+use crate::store::BytesWrapper::{Composed, Direct};
 use crate::util;
 
+struct DedupBlocks {
+    data_chain: Vec<Bytes>,
+    len: usize,
+    extents: Vec<Extent>,
+    new_digests: HashMap<BlockDigest, BlockLocation>,
+}
+
 struct LockedObj {
     disk: LocalDiskDelegator,
     pointer: AtomicI64,
+    last_written_timestamp: AtomicU64,
+
+    // only for the spill dedup. the pointer above indicates the logical data length
+    // that the index refers to, and the physical pointer is the real file length.
+    physical_pointer: AtomicI64,
+    extents: Option<Vec<Extent>>,
+    // only for the spill compression, which shares the physical pointer with the dedup.
+    compressed_chunks: Option<Vec<CompressedChunk>>,
+    // the truncation of the inconsistent index view is only logged once per partition
+    index_truncation_logged: AtomicBool,
+
+    // the index entries of the partition, and the ones kept by the last compaction
+    index_entries: AtomicU64,
+    compacted_index_entries: AtomicU64,
+    // the index and data reads, and the compaction won't change the offsets under the readers
+    last_read_timestamp: AtomicU64,
 }
 
 impl From<LocalDiskDelegator> for LockedObj {
@@ -72,8 +111,113 @@ impl From<LocalDiskDelegator> for LockedObj {
         Self {
             disk: value,
             pointer: Default::default(),
+            last_written_timestamp: Default::default(),
+            physical_pointer: Default::default(),
+            extents: None,
+            compressed_chunks: None,
+            index_truncation_logged: Default::default(),
+            index_entries: Default::default(),
+            compacted_index_entries: Default::default(),
+            last_read_timestamp: Default::default(),
+        }
+    }
+}
+
+impl LockedObj {
+    fn physical_len(&self) -> i64 {
+        if self.is_remapped() {
+            self.physical_pointer.load(SeqCst)
+        } else {
+            self.pointer.load(SeqCst)
+        }
+    }
+
+    /// The disk bytes of the data and index files, which are accounted in the disk gauge.
+    fn disk_used_bytes(&self) -> i64 {
+        self.physical_len() + (self.index_entries.load(SeqCst) as usize * INDEX_BLOCK_SIZE) as i64
+    }
+
+    /// Whether the logical data file is remapped to the different physical bytes, which
+    /// can't be moved to another disk by copying the data file.
+    fn is_remapped(&self) -> bool {
+        self.extents.is_some() || self.compressed_chunks.is_some()
+    }
+}
+
+/// The content digest of one block, which is used to find the identical blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BlockDigest {
+    crc: i64,
+    hash: u64,
+    length: i32,
+}
+
+impl From<&Block> for BlockDigest {
+    fn from(block: &Block) -> Self {
+        Self {
+            crc: get_crc(&block.data),
+            hash: fxhash::hash64(block.data.as_ref()),
+            length: block.length,
+        }
+    }
+}
+
+/// The physical location of the block bytes, which may be in another partition's data file.
+#[derive(Clone)]
+struct BlockLocation {
+    disk: LocalDiskDelegator,
+    data_file_path: String,
+    offset: i64,
+}
+
+/// Maps a range of the partition's logical data file to the physical bytes.
+#[derive(Clone)]
+struct Extent {
+    logical_offset: i64,
+    length: i64,
+    location: BlockLocation,
+}
+
+fn push_extent(extents: &mut Vec<Extent>, extent: Extent) {
+    if let Some(last) = extents.last_mut() {
+        if last.logical_offset + last.length == extent.logical_offset
+            && last.location.data_file_path == extent.location.data_file_path
+            && last.location.disk.root() == extent.location.disk.root()
+            && last.location.offset + last.length == extent.location.offset
+        {
+            last.length += extent.length;
+            return;
         }
     }
+    extents.push(extent);
+}
+
+/// The outcome of rewriting one partition's index file to its consistent entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexRepairOutcome {
+    pub index_file_path: String,
+    pub data_file_len: i64,
+    pub index_len_before: usize,
+    pub index_len_after: usize,
+}
+
+const REBALANCE_COPY_CHUNK_SIZE: i64 = 16 * 1024 * 1024;
+
+/// The outcome of moving one partition's data and index files between disks.
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionMoveOutcome {
+    pub data_file_path: String,
+    pub source_root: String,
+    pub target_root: String,
+    pub moved_bytes: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RebalanceProgress {
+    pub running: bool,
+    pub candidate_partition_num: usize,
+    pub outcomes: Vec<PartitionMoveOutcome>,
 }
 
 pub struct LocalFileStore {
@@ -81,12 +225,26 @@ pub struct LocalFileStore {
     min_number_of_available_disks: i32,
     runtime_manager: RuntimeManager,
     partition_locks: DashMap<String, Arc<RwLock<LockedObj>>>,
+    // key: the shuffle relative path. the dedup is only in the scope of one shuffle,
+    // so that the shuffle level purge won't break the references of other shuffles.
+    dedup_indexes: DashMap<String, Arc<DashMap<BlockDigest, BlockLocation>>>,
 
     direct_io_enable: bool,
     direct_io_read_enable: bool,
     direct_io_append_enable: bool,
 
     conf: LocalfileStoreConfig,
+
+    rebalance_progress: parking_lot::Mutex<RebalanceProgress>,
+
+    // key: app_id. the placement mode is decided on the app registry and sticky thereafter
+    app_placement_modes: DashMap<String, DiskPlacementMode>,
+
+    read_ahead: Option<Arc<ReadAheadCache>>,
+    // only for the read crc verification
+    crc_cache: Option<BlockCrcCache>,
+
+    compression_codec: Option<Box<dyn Codec>>,
 }
 
 impl Persistent for LocalFileStore {}
@@ -108,11 +266,42 @@ impl LocalFileStore {
             min_number_of_available_disks: 1,
             runtime_manager,
             partition_locks: Default::default(),
+            dedup_indexes: Default::default(),
             direct_io_enable: config.direct_io_enable,
             direct_io_read_enable: config.direct_io_read_enable,
             direct_io_append_enable: config.direct_io_append_enable,
             conf: Default::default(),
+            rebalance_progress: Default::default(),
+            app_placement_modes: Default::default(),
+            read_ahead: None,
+            crc_cache: None,
+            compression_codec: None,
+        }
+    }
+
+    /// The available bytes of all the healthy disks.
+    pub fn available_bytes(&self) -> Result<u64> {
+        let mut available = 0;
+        for local_disk in &self.local_disks {
+            if local_disk.is_healthy()? && !local_disk.is_corrupted()? {
+                available += local_disk.available()?;
+            }
         }
+        Ok(available)
+    }
+
+    /// The flushed bytes of all the tracked partitions.
+    pub async fn resident_bytes(&self) -> u64 {
+        let locked_objs: Vec<_> = self
+            .partition_locks
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        let mut bytes = 0u64;
+        for locked_obj in locked_objs {
+            bytes += locked_obj.read().await.physical_len() as u64;
+        }
+        bytes
     }
 
     pub fn stat(&self) -> Result<LocalfileStoreStat> {
@@ -157,15 +346,40 @@ impl LocalFileStore {
         info!("Initializing localfile store with the disk paths: [{:?}] and min_number_of_available_disks: [{}]",
             &localfile_config.data_paths, min_number_of_available_disks);
 
+        let read_ahead = localfile_config.read_ahead.as_ref().map(|conf| {
+            Arc::new(ReadAheadCache::new(conf).expect("Errors on creating the read-ahead cache"))
+        });
+
+        let crc_cache = if localfile_config.read_crc_verify_enable {
+            Some(BlockCrcCache::new(localfile_config.read_crc_cache_entries))
+        } else {
+            None
+        };
+
+        let compression_codec = match localfile_config.spill_compression {
+            Some(_) if localfile_config.spill_dedup_enable => {
+                warn!("The spill compression is ignored when the spill dedup is enabled");
+                None
+            }
+            Some(compression_type) => Some(get_codec(compression_type)),
+            _ => None,
+        };
+
         LocalFileStore {
             local_disks: local_disk_instances,
             min_number_of_available_disks,
             runtime_manager,
             partition_locks: Default::default(),
+            dedup_indexes: Default::default(),
             direct_io_enable: localfile_config.direct_io_enable,
             direct_io_read_enable: localfile_config.direct_io_read_enable,
             direct_io_append_enable: localfile_config.direct_io_append_enable,
             conf: localfile_config.clone(),
+            rebalance_progress: Default::default(),
+            app_placement_modes: Default::default(),
+            read_ahead,
+            crc_cache,
+            compression_codec,
         }
     }
 
@@ -206,822 +420,4081 @@ impl LocalFileStore {
         )
     }
 
-    fn healthy_check(&self) -> Result<bool> {
-        let mut available = 0;
-        for local_disk in &self.local_disks {
-            if local_disk.is_healthy()? && !local_disk.is_corrupted()? {
-                available += 1;
-            }
+    /// Get the latest flushed data offset of the partition file. If the partition has not
+    /// been flushed into this store, it will return 0.
+    pub async fn get_flushed_offset(&self, uid: &PartitionedUId) -> Result<i64> {
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let locked_obj = match self.partition_locks.get(&data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => return Ok(0),
+        };
+        let locked_obj = locked_obj
+            .read()
+            .instrument_await("waiting the partition file [read] lock")
+            .await;
+        Ok(locked_obj.pointer.load(SeqCst))
+    }
+
+    /// Returns the disk root where the partition data is located, `None` if it has
+    /// never been flushed into localfile.
+    pub async fn get_partition_disk_root(&self, uid: &PartitionedUId) -> Result<Option<String>> {
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let locked_obj = match self.partition_locks.get(&data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => return Ok(None),
+        };
+        let locked_obj = locked_obj
+            .read()
+            .instrument_await("waiting the partition file [read] lock")
+            .await;
+        Ok(Some(locked_obj.disk.root()))
+    }
+
+    /// The disk root and the committed data offset of the partition to be snapshotted. The
+    /// remapped partitions are excluded, since their extents and chunks only live in memory.
+    pub async fn get_restorable_partition(
+        &self,
+        uid: &PartitionedUId,
+    ) -> Result<Option<(String, i64)>> {
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let locked_obj = match self.partition_locks.get(&data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => return Ok(None),
+        };
+        let locked_obj = locked_obj
+            .read()
+            .instrument_await("waiting the partition file [read] lock")
+            .await;
+        if locked_obj.is_remapped() {
+            return Ok(None);
         }
+        Ok(Some((
+            locked_obj.disk.root(),
+            locked_obj.pointer.load(SeqCst),
+        )))
+    }
 
-        debug!(
-            "disk: available={}, healthy_check_min={}",
-            available, self.min_number_of_available_disks
-        );
-        Ok(available >= self.min_number_of_available_disks)
+    /// Restores the partition flushed before the worker restarts, so that its data could
+    /// be read and appended again. The bytes flushed after the snapshot are dropped, otherwise
+    /// the following appends would be indexed at the wrong offset.
+    pub fn restore_partition(
+        &self,
+        uid: &PartitionedUId,
+        disk_root: &str,
+        offset: i64,
+    ) -> Result<()> {
+        // the following appends would be remapped, which can't refer to the restored plain file.
+        if self.conf.spill_dedup_enable || self.compression_codec.is_some() {
+            return Err(anyhow!(
+                "The partition: {:?} can't be restored with the spill dedup or compression",
+                uid
+            ));
+        }
+        let disk = self
+            .local_disks
+            .iter()
+            .find(|disk| disk.root() == disk_root)
+            .ok_or_else(|| anyhow!("No such disk: {} to be restored", disk_root))?;
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(uid);
+        let index_entries = LocalFileStore::truncate_restored_files(
+            disk_root,
+            &data_file_path,
+            &index_file_path,
+            offset,
+        )?;
+        let locked_obj = LockedObj::from(disk.clone());
+        locked_obj.pointer.store(offset, SeqCst);
+        locked_obj.physical_pointer.store(offset, SeqCst);
+        locked_obj.index_entries.store(index_entries, SeqCst);
+        let used_bytes = locked_obj.disk_used_bytes();
+        self.partition_locks
+            .insert(data_file_path, Arc::new(RwLock::new(locked_obj)));
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[disk_root])
+            .add(used_bytes);
+        Ok(())
     }
 
-    fn select_disk(&self, uid: &PartitionedUId) -> Result<LocalDiskDelegator, WorkerError> {
-        let hash_value = PartitionedUId::get_hash(uid);
+    /// Truncates the data file to the snapshotted offset and the index file to the entries
+    /// within it, and returns the number of the kept index entries.
+    fn truncate_restored_files(
+        root: &str,
+        data_file_path: &str,
+        index_file_path: &str,
+        offset: i64,
+    ) -> Result<u64> {
+        let data_file = fs::OpenOptions::new()
+            .write(true)
+            .open(format!("{}/{}", root, data_file_path))?;
+        let data_len = data_file.metadata()?.len();
+        if data_len < offset as u64 {
+            return Err(anyhow!(
+                "The data file: {} of {} bytes is shorter than the snapshotted offset: {}",
+                data_file_path,
+                data_len,
+                offset
+            ));
+        }
+        data_file.set_len(offset as u64)?;
+
+        let index_path = format!("{}/{}", root, index_file_path);
+        let index_data = Bytes::from(fs::read(&index_path)?);
+        let index_data = LocalFileStore::truncate_inconsistent_index(index_data, offset)?;
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&index_path)?
+            .set_len(index_data.len() as u64)?;
+        Ok((index_data.len() / INDEX_BLOCK_SIZE) as u64)
+    }
+
+    pub fn get_rebalance_progress(&self) -> RebalanceProgress {
+        self.rebalance_progress.lock().clone()
+    }
+
+    /// Move the partitions from the most utilized disk to the least utilized disk when
+    /// the used ratio skew between them exceeds the threshold. The partitions without
+    /// recent writes will be moved first.
+    pub async fn rebalance(&self, skew_threshold: f64) -> Result<RebalanceProgress> {
+        {
+            let mut progress = self.rebalance_progress.lock();
+            if progress.running {
+                return Err(anyhow!("The disk rebalance job is running"));
+            }
+            *progress = RebalanceProgress {
+                running: true,
+                candidate_partition_num: 0,
+                outcomes: vec![],
+            };
+        }
+        let result = self
+            .rebalance_internal(skew_threshold)
+            .instrument_await("rebalancing the partitions between disks")
+            .await;
+        let mut progress = self.rebalance_progress.lock();
+        progress.running = false;
+        result?;
+        Ok(progress.clone())
+    }
+
+    async fn rebalance_internal(&self, skew_threshold: f64) -> Result<()> {
+        let mut source: Option<(LocalDiskDelegator, f64)> = None;
+        let mut target: Option<(LocalDiskDelegator, f64)> = None;
+        for disk in &self.local_disks {
+            if disk.is_corrupted()? {
+                continue;
+            }
+            let ratio = disk.stat()?.used_ratio;
+            if source.as_ref().map_or(true, |(_, r)| ratio > *r) {
+                source = Some((disk.clone(), ratio));
+            }
+            if disk.is_healthy()? && target.as_ref().map_or(true, |(_, r)| ratio < *r) {
+                target = Some((disk.clone(), ratio));
+            }
+        }
+        let ((source, source_ratio), (target, target_ratio)) = match (source, target) {
+            (Some(source), Some(target)) => (source, target),
+            _ => return Ok(()),
+        };
+        if source.root() == target.root() || source_ratio - target_ratio <= skew_threshold {
+            info!(
+                "[Rebalance] Skip rebalancing due to the skew is under the threshold: {}",
+                skew_threshold
+            );
+            return Ok(());
+        }
+        let expected_moved_bytes =
+            ((source_ratio - target_ratio) / 2.0 * source.capacity()? as f64) as i64;
 
+        // the partitions being written will be ignored, and the deduped partitions
+        // are pinned because their bytes may be referenced by other partitions. so are
+        // the compressed partitions whose chunks are only mapped in memory.
         let mut candidates = vec![];
-        for local_disk in &self.local_disks {
-            if !local_disk.is_corrupted()? && local_disk.is_healthy()? {
-                candidates.push(local_disk);
+        for entry in self.partition_locks.iter() {
+            if let Ok(obj) = entry.value().try_read() {
+                if obj.disk.root() == source.root() && !obj.is_remapped() {
+                    let timestamp = obj.last_written_timestamp.load(SeqCst);
+                    candidates.push((entry.key().clone(), timestamp));
+                }
             }
         }
+        candidates.sort_by_key(|(_, timestamp)| *timestamp);
+        self.rebalance_progress.lock().candidate_partition_num = candidates.len();
+        info!(
+            "[Rebalance] Moving {} bytes from disk: {}(ratio: {}) to disk: {}(ratio: {}) with {} candidate partitions",
+            expected_moved_bytes,
+            source.root(),
+            source_ratio,
+            target.root(),
+            target_ratio,
+            candidates.len()
+        );
 
-        let len = candidates.len();
-        if len == 0 {
-            error!("There is no available local disk!");
-            return Err(WorkerError::NO_AVAILABLE_LOCAL_DISK);
+        let mut moved_bytes = 0;
+        for (data_file_path, _) in candidates {
+            if moved_bytes >= expected_moved_bytes {
+                break;
+            }
+            let outcome = match self.move_partition(&data_file_path, &source, &target).await {
+                Ok(bytes) => {
+                    moved_bytes += bytes;
+                    PartitionMoveOutcome {
+                        data_file_path,
+                        source_root: source.root(),
+                        target_root: target.root(),
+                        moved_bytes: bytes,
+                        error: None,
+                    }
+                }
+                Err(err) => {
+                    warn!(
+                        "[Rebalance] Errors on moving partition: {}. err: {:?}",
+                        &data_file_path, err
+                    );
+                    PartitionMoveOutcome {
+                        data_file_path,
+                        source_root: source.root(),
+                        target_root: target.root(),
+                        moved_bytes: 0,
+                        error: Some(format!("{:?}", err)),
+                    }
+                }
+            };
+            self.rebalance_progress.lock().outcomes.push(outcome);
         }
+        Ok(())
+    }
 
-        let index = (hash_value % len as u64) as usize;
-        if let Some(&disk) = candidates.get(index) {
-            Ok(disk.clone())
-        } else {
-            Err(WorkerError::INTERNAL_ERROR)
+    /// The interval of checking the failing disks to be evacuated, `None` if disabled.
+    pub fn evacuation_check_interval(&self) -> Option<Duration> {
+        match self.conf.disk_evacuation_enable {
+            true => Some(Duration::from_secs(
+                self.conf.disk_healthy_check_interval_sec,
+            )),
+            _ => None,
         }
     }
 
-    async fn data_insert(
-        &self,
-        uid: PartitionedUId,
-        blocks: Vec<&Block>,
-    ) -> Result<(), WorkerError> {
-        let (data_file_path, index_file_path) =
-            LocalFileStore::gen_relative_path_for_partition(&uid);
+    fn is_evacuation_required(&self, disk: &LocalDiskDelegator) -> Result<bool> {
+        if disk.is_corrupted()? {
+            return Ok(true);
+        }
+        match (
+            disk.unhealthy_duration_sec(),
+            self.conf.disk_evacuation_unhealthy_duration_sec,
+        ) {
+            (Some(duration), Some(threshold)) => Ok(duration >= threshold),
+            _ => Ok(false),
+        }
+    }
 
-        let mut parent_dir_is_created = true;
-        let locked_obj = match self.partition_locks.entry(data_file_path.clone()) {
-            Entry::Vacant(e) => {
-                parent_dir_is_created = false;
-                let disk = self.select_disk(&uid)?;
-                let locked_obj = Arc::new(RwLock::new(LockedObj::from(disk)));
-                let obj = e.insert_entry(locked_obj.clone());
-                obj.get().clone()
+    /// Evacuate the partitions from the corrupted or long-term unhealthy disks onto the
+    /// remaining healthy disks, the reads will be routed to the new disk once moved.
+    /// The copying is throttled by the io limiter of the source disk.
+    pub async fn evacuate(&self) -> Result<i64> {
+        let mut evacuated_bytes = 0;
+        for disk in &self.local_disks {
+            if self.is_evacuation_required(disk)? {
+                evacuated_bytes += self
+                    .evacuate_disk(disk)
+                    .instrument_await(format!("evacuating the disk: {}", disk.root()))
+                    .await?;
             }
-            Entry::Occupied(v) => v.get().clone(),
-        };
-
-        let locked_obj = locked_obj
-            .write()
-            .instrument_await("waiting the localfile partition lock...")
-            .await;
-        let local_disk = &locked_obj.disk;
-        let next_offset = locked_obj.pointer.load(SeqCst);
+        }
+        Ok(evacuated_bytes)
+    }
 
-        if local_disk.is_corrupted()? {
-            return Err(WorkerError::PARTIAL_DATA_LOST(local_disk.root()));
+    async fn evacuate_disk(&self, source: &LocalDiskDelegator) -> Result<i64> {
+        // the partitions being written will be picked up in the next round, and the
+        // deduped partitions are pinned because their bytes may be referenced by others.
+        // so are the compressed partitions whose chunks are only mapped in memory.
+        let mut candidates = vec![];
+        let mut remaining_bytes = 0;
+        for entry in self.partition_locks.iter() {
+            if let Ok(obj) = entry.value().try_read() {
+                if obj.disk.root() == source.root() && !obj.is_remapped() {
+                    let len = obj.pointer.load(SeqCst);
+                    remaining_bytes += len;
+                    candidates.push((entry.key().clone(), len));
+                }
+            }
         }
+        let gauge =
+            GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES.with_label_values(&[&source.root()]);
+        gauge.set(remaining_bytes);
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+        info!(
+            "[Evacuation] Evacuating {} partitions with {} bytes from the failing disk: {}",
+            candidates.len(),
+            remaining_bytes,
+            source.root()
+        );
 
-        if !local_disk.is_healthy()? {
-            return Err(WorkerError::LOCAL_DISK_UNHEALTHY(local_disk.root()));
+        let mut evacuated_bytes = 0;
+        for (data_file_path, len) in candidates {
+            let target = match self.select_evacuation_target(source)? {
+                Some(target) => target,
+                _ => {
+                    warn!(
+                        "[Evacuation] No healthy disk is available for the evacuation of disk: {}",
+                        source.root()
+                    );
+                    break;
+                }
+            };
+            match self.move_partition(&data_file_path, source, &target).await {
+                Ok(bytes) => {
+                    evacuated_bytes += bytes;
+                    remaining_bytes -= len;
+                    gauge.set(remaining_bytes);
+                }
+                Err(err) => warn!(
+                    "[Evacuation] Errors on moving partition: {}. err: {:?}",
+                    &data_file_path, err
+                ),
+            }
         }
+        info!(
+            "[Evacuation] Evacuated {} bytes from the disk: {}. remaining: {}",
+            evacuated_bytes,
+            source.root(),
+            remaining_bytes
+        );
+        Ok(evacuated_bytes)
+    }
 
-        if !parent_dir_is_created {
-            if let Some(path) = Path::new(&data_file_path).parent() {
-                let path = format!("{}/", path.to_str().unwrap()).as_str().to_owned();
-                local_disk
-                    .create_dir(path.as_str())
-                    .instrument_await(format!("creating the directory: {}", path.as_str()))
-                    .await?;
+    /// Picks the least utilized healthy disk except the source.
+    fn select_evacuation_target(
+        &self,
+        source: &LocalDiskDelegator,
+    ) -> Result<Option<LocalDiskDelegator>> {
+        let mut target: Option<(LocalDiskDelegator, f64)> = None;
+        for disk in &self.local_disks {
+            if disk.root() == source.root() || disk.is_corrupted()? || !disk.is_healthy()? {
+                continue;
+            }
+            let ratio = disk.stat()?.used_ratio;
+            if target.as_ref().map_or(true, |(_, r)| ratio < *r) {
+                target = Some((disk.clone(), ratio));
             }
         }
+        Ok(target.map(|(disk, _)| disk))
+    }
 
-        let shuffle_file_format = self.create_shuffle_format(blocks, next_offset)?;
-        let append_future = if self.direct_io_enable && self.direct_io_append_enable {
-            local_disk.direct_append(
-                &data_file_path,
-                next_offset as usize,
-                shuffle_file_format.data,
+    /// Copy the partition files to the target disk under the read lock, so the reads are
+    /// still served from the source disk. And then switch to the target disk under the write
+    /// lock only when no flush happens during copying. Source files will be deleted at last.
+    async fn move_partition(
+        &self,
+        data_file_path: &str,
+        source: &LocalDiskDelegator,
+        target: &LocalDiskDelegator,
+    ) -> Result<i64> {
+        let index_file_path = format!("{}.index", data_file_path.trim_end_matches(".data"));
+        let locked_obj = match self.partition_locks.get(data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => return Err(anyhow!("The partition has been purged")),
+        };
+
+        let (data_len, index_entries, used_bytes) = {
+            let obj = locked_obj
+                .read()
+                .instrument_await("waiting the partition file [read] lock")
+                .await;
+            if obj.disk.root() != source.root() {
+                return Err(anyhow!("The partition has been moved"));
+            }
+            let data_len = obj.pointer.load(SeqCst);
+            let index_entries = obj.index_entries.load(SeqCst);
+            if let Err(err) = LocalFileStore::copy_partition_files(
+                data_file_path,
+                &index_file_path,
+                data_len,
+                source,
+                target,
             )
-        } else {
-            local_disk.append(&data_file_path, shuffle_file_format.data)
+            .await
+            {
+                target.delete(data_file_path).await?;
+                target.delete(&index_file_path).await?;
+                return Err(err);
+            }
+            (data_len, index_entries, obj.disk_used_bytes())
         };
-        append_future
-            .instrument_await(format!(
-                "data flushing with {} bytes. path: {}",
-                shuffle_file_format.len, &data_file_path
-            ))
-            .await?;
-        let index_bytes_len = shuffle_file_format.index.len();
-        local_disk
-            .append(&index_file_path, shuffle_file_format.index)
-            .instrument_await(format!(
-                "index flushing with {} bytes. path: {}",
-                index_bytes_len, &index_file_path
-            ))
-            .await?;
 
-        TOTAL_LOCALFILE_USED.inc_by(shuffle_file_format.len as u64);
+        let mut obj = locked_obj
+            .write()
+            .instrument_await("waiting the partition file [write] lock")
+            .await;
+        // the index may be appended without moving the pointer, like the empty blocks
+        if obj.pointer.load(SeqCst) != data_len || obj.index_entries.load(SeqCst) != index_entries {
+            drop(obj);
+            target.delete(data_file_path).await?;
+            target.delete(&index_file_path).await?;
+            return Err(anyhow!("The partition has been written during moving"));
+        }
+        obj.disk = target.clone();
+        drop(obj);
+
         GAUGE_LOCAL_DISK_SERVICE_USED
-            .with_label_values(&[&local_disk.root()])
-            .add(shuffle_file_format.len as i64);
+            .with_label_values(&[&source.root()])
+            .sub(used_bytes);
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[&target.root()])
+            .add(used_bytes);
 
-        locked_obj
-            .deref()
-            .pointer
-            .store(shuffle_file_format.offset, SeqCst);
+        source.delete(data_file_path).await?;
+        source.delete(&index_file_path).await?;
+        Ok(data_len)
+    }
 
-        Ok(())
+    pub fn compaction_check_interval(&self) -> Option<Duration> {
+        self.conf
+            .compaction
+            .as_ref()
+            .map(|conf| Duration::from_secs(conf.check_interval_sec))
     }
 
-    fn delete_all_files(dir: &Path) -> Result<()> {
-        let entries = fs::read_dir(dir)?;
-        for entry in entries {
-            let path = entry?.path();
-            if path.is_file() {
-                fs::remove_file(&path)?;
-            }
+    fn is_compaction_required(conf: &CompactionConfig, obj: &LockedObj) -> bool {
+        // the remapped partitions are not contiguous files that could be rewritten.
+        if obj.is_remapped() {
+            return false;
         }
-        Ok(())
+        let new_entries = obj
+            .index_entries
+            .load(SeqCst)
+            .saturating_sub(obj.compacted_index_entries.load(SeqCst));
+        if new_entries < conf.index_entry_threshold {
+            return false;
+        }
+        // the readers holding the fetched index would read the wrong bytes once the offsets
+        // are changed, so the partition is only compacted when neither written nor read.
+        let last_accessed = obj
+            .last_written_timestamp
+            .load(SeqCst)
+            .max(obj.last_read_timestamp.load(SeqCst));
+        let idle_millis = (util::now_timestamp_as_millis() as u64).saturating_sub(last_accessed);
+        idle_millis >= conf.idle_sec * 1000
     }
 
-    // To detect the index consistency with data file len for debug.
-    pub(crate) fn detect_index_inconsistency(
-        data: &Bytes,
-        data_file_len: i64,
-        root: &String,
-        index_file_path: &String,
-        data_file_path: &String,
-    ) -> Result<bool> {
-        let last_block_raw_bytes = data.slice(data.len() - INDEX_BLOCK_SIZE..);
-        match IndexCodec::decode(last_block_raw_bytes) {
-            Ok(index_block) => {
-                let index_indicated_data_len = index_block.offset + index_block.length as i64;
-                if data_file_len != index_indicated_data_len {
-                    TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY.inc();
-                    let timestamp = util::now_timestamp_as_millis();
-                    warn!("Attention: index indicated data len:{} != recorded data len:{}. root: {}. index path: {}. data path: {}. timestamp: {}",
-                            index_indicated_data_len, data_file_len, root, &index_file_path, &data_file_path, timestamp);
-                    let main_dir = Path::new("/tmp/riffle-detection");
-                    if !main_dir.exists() {
-                        fs::create_dir(main_dir)?;
+    /// Compacts the partitions with too many index entries from the small appends, and
+    /// returns the reclaimed bytes.
+    pub async fn compact(&self) -> Result<i64> {
+        let conf = match &self.conf.compaction {
+            Some(conf) => conf,
+            _ => return Ok(0),
+        };
+        // the partitions being written will be picked up in the next round.
+        let mut candidates = vec![];
+        for entry in self.partition_locks.iter() {
+            if let Ok(obj) = entry.value().try_read() {
+                if LocalFileStore::is_compaction_required(conf, &obj) {
+                    candidates.push(entry.key().clone());
+                }
+            }
+        }
+
+        let mut reclaimed_bytes = 0;
+        for data_file_path in candidates {
+            match self.compact_partition(&data_file_path).await {
+                Ok(Some(bytes)) => reclaimed_bytes += bytes,
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "[Compaction] Errors on compacting partition: {}. err: {:?}",
+                    &data_file_path, err
+                ),
+            }
+        }
+        Ok(reclaimed_bytes)
+    }
+
+    /// Compacts the partition under its write lock, so only the reads and writes of this
+    /// partition are blocked. None will be returned if the partition is purged before or
+    /// during the compaction.
+    async fn compact_partition(&self, data_file_path: &str) -> Result<Option<i64>> {
+        let index_file_path = format!("{}.index", data_file_path.trim_end_matches(".data"));
+        let abort = || {
+            TOTAL_LOCALFILE_COMPACTION_ABORTED.inc();
+            info!(
+                "[Compaction] Aborted the compaction of the purged partition: {}",
+                data_file_path
+            );
+        };
+        let locked_obj = match self.partition_locks.get(data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => {
+                abort();
+                return Ok(None);
+            }
+        };
+        let obj = locked_obj
+            .write()
+            .instrument_await("waiting the partition file [write] lock to compact")
+            .await;
+        // the purge deletes the files before removing the partition and waiting for its lock
+        let is_purged = || {
+            self.partition_locks
+                .get(data_file_path)
+                .map_or(true, |x| !Arc::ptr_eq(x.value(), &locked_obj))
+        };
+        if is_purged() {
+            abort();
+            return Ok(None);
+        }
+
+        match self
+            .rewrite_partition(&obj, data_file_path, &index_file_path)
+            .await
+        {
+            Ok(reclaimed_bytes) => Ok(Some(reclaimed_bytes)),
+            Err(err) => {
+                let disk = &obj.disk;
+                let _ = disk.delete(&format!("{}.compacting", data_file_path)).await;
+                let _ = disk
+                    .delete(&format!("{}.compacting", &index_file_path))
+                    .await;
+                if !is_purged() {
+                    return Err(err);
+                }
+                abort();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Rewrites the data file contiguously with the index entries in the committed data
+    /// file len. The adjacent entries of the same block stream are merged into one entry,
+    /// and the retried copies of the same block are dropped.
+    /// The new files are swapped in by renaming, the index file firstly and then the data.
+    async fn rewrite_partition(
+        &self,
+        obj: &LockedObj,
+        data_file_path: &str,
+        index_file_path: &str,
+    ) -> Result<i64> {
+        let used_bytes = obj.disk_used_bytes();
+        let disk = &obj.disk;
+        let data_len = obj.pointer.load(SeqCst);
+        let index_data = disk.read(index_file_path, 0, None).await?;
+        let data = match data_len {
+            0 => Bytes::new(),
+            _ => disk.read(data_file_path, 0, Some(data_len)).await?,
+        };
+
+        // key: block_id. the kept copies of the block to find out the retried ones
+        let mut copies: HashMap<i64, Vec<Bytes>> = HashMap::new();
+        // the kept entries with their bytes, and whether it's merged from multiple entries
+        let mut kept: Vec<(IndexBlock, BytesMut, bool)> = vec![];
+        for index_block in index_data.chunks_exact(INDEX_BLOCK_SIZE) {
+            let block = IndexCodec::decode(index_data.slice_ref(index_block))?;
+            let end = block.offset + block.length as i64;
+            if block.offset < 0 || block.length < 0 || end > data_len {
+                continue;
+            }
+            let bytes = data.slice(block.offset as usize..end as usize);
+            let block_copies = copies.entry(block.block_id).or_default();
+            if block_copies.contains(&bytes) {
+                continue;
+            }
+            block_copies.push(bytes.clone());
+
+            if let Some((last, last_bytes, merged)) = kept.last_mut() {
+                if last.block_id == block.block_id
+                    && last.task_attempt_id == block.task_attempt_id
+                    && last.length as i64 + block.length as i64 <= i32::MAX as i64
+                {
+                    last.length += block.length;
+                    last.uncompress_length = last
+                        .uncompress_length
+                        .saturating_add(block.uncompress_length);
+                    last_bytes.extend_from_slice(&bytes);
+                    *merged = true;
+                    continue;
+                }
+            }
+            kept.push((block, BytesMut::from(bytes.as_ref()), false));
+        }
+
+        let entries = kept.len() as u64;
+        let mut compacted_index = BytesMut::new();
+        let mut compacted_data = BytesMut::new();
+        for (mut block, bytes, merged) in kept {
+            if merged {
+                block.crc = get_crc(&bytes);
+            }
+            block.offset = compacted_data.len() as i64;
+            compacted_data.extend_from_slice(&bytes);
+            IndexCodec::encode(&block, &mut compacted_index)?;
+        }
+
+        let compacted_data_len = compacted_data.len() as i64;
+        let reclaimed_bytes =
+            (data_len - compacted_data_len) + (index_data.len() - compacted_index.len()) as i64;
+        // the overlapped entries of the inconsistent index can't be compacted.
+        if compacted_data_len <= data_len && reclaimed_bytes > 0 {
+            let data_tmp_path = format!("{}.compacting", data_file_path);
+            let index_tmp_path = format!("{}.compacting", index_file_path);
+            // clear up the stale files left by the previous failed compaction.
+            disk.delete(&data_tmp_path).await?;
+            disk.delete(&index_tmp_path).await?;
+            // the data file written by the direct io must be padded to be appended again.
+            if self.direct_io_enable && self.direct_io_append_enable {
+                disk.direct_append(&data_tmp_path, 0, compacted_data.freeze().into())
+                    .await?;
+            } else {
+                disk.write(&data_tmp_path, compacted_data.freeze()).await?;
+            }
+            disk.write(&index_tmp_path, compacted_index.freeze())
+                .await?;
+            disk.rename(&index_tmp_path, index_file_path).await?;
+            disk.rename(&data_tmp_path, data_file_path).await?;
+
+            if let Some(cache) = &self.read_ahead {
+                cache.invalidate(data_file_path);
+            }
+            if let Some(cache) = &self.crc_cache {
+                cache.invalidate(data_file_path);
+            }
+            obj.pointer.store(compacted_data_len, SeqCst);
+            TOTAL_LOCALFILE_COMPACTIONS.inc();
+            TOTAL_LOCALFILE_COMPACTION_RECLAIMED_BYTES.inc_by(reclaimed_bytes as u64);
+            info!(
+                "[Compaction] Compacted the partition: {} of root: {}. data: {} -> {}, index entries: {} -> {}",
+                data_file_path,
+                disk.root(),
+                data_len,
+                compacted_data_len,
+                index_data.len() / INDEX_BLOCK_SIZE,
+                entries
+            );
+        }
+        obj.index_entries.store(entries, SeqCst);
+        obj.compacted_index_entries.store(entries, SeqCst);
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[&obj.disk.root()])
+            .sub(used_bytes - obj.disk_used_bytes());
+        Ok(reclaimed_bytes)
+    }
+
+    async fn copy_partition_files(
+        data_file_path: &str,
+        index_file_path: &str,
+        data_len: i64,
+        source: &LocalDiskDelegator,
+        target: &LocalDiskDelegator,
+    ) -> Result<()> {
+        if let Some(path) = Path::new(data_file_path).parent() {
+            let path = format!("{}/", path.to_str().unwrap());
+            target.create_dir(path.as_str()).await?;
+        }
+        // clear up the stale files left by the previous failed moving.
+        target.delete(data_file_path).await?;
+        target.delete(index_file_path).await?;
+
+        let mut offset = 0;
+        while offset < data_len {
+            let len = min(REBALANCE_COPY_CHUNK_SIZE, data_len - offset);
+            source.get_permit(len as usize).await?;
+            let chunk = source.read(data_file_path, offset, Some(len)).await?;
+            target.append(data_file_path, chunk.into()).await?;
+            offset += len;
+        }
+        let index = source.read(index_file_path, 0, None).await?;
+        let index_len = index.len() as u64;
+        target.append(index_file_path, index.into()).await?;
+
+        // verify the copied files by the length and the crc of the sampled chunk
+        let data_stat = target.file_stat(data_file_path).await?;
+        let index_stat = target.file_stat(index_file_path).await?;
+        if data_stat.content_length != data_len as u64 || index_stat.content_length != index_len {
+            return Err(anyhow!(
+                "Mismatched length of the copied files. data: {}/{}, index: {}/{}",
+                data_stat.content_length,
+                data_len,
+                index_stat.content_length,
+                index_len
+            ));
+        }
+        if data_len > 0 {
+            let sampled_offset = data_len / 2;
+            let sampled_len = min(REBALANCE_COPY_CHUNK_SIZE, data_len - sampled_offset);
+            let expected = source
+                .read(data_file_path, sampled_offset, Some(sampled_len))
+                .await?;
+            let actual = target
+                .read(data_file_path, sampled_offset, Some(sampled_len))
+                .await?;
+            if get_crc(&expected) != get_crc(&actual) {
+                return Err(anyhow!("Mismatched crc of the copied data file"));
+            }
+        }
+        Ok(())
+    }
+
+    fn healthy_check(&self) -> Result<bool> {
+        let mut available = 0;
+        for local_disk in &self.local_disks {
+            if local_disk.is_healthy()? && !local_disk.is_corrupted()? {
+                available += 1;
+            }
+        }
+
+        debug!(
+            "disk: available={}, healthy_check_min={}",
+            available, self.min_number_of_available_disks
+        );
+        Ok(available >= self.min_number_of_available_disks)
+    }
+
+    fn get_placement_mode(&self, app_id: &str) -> DiskPlacementMode {
+        self.app_placement_modes
+            .get(app_id)
+            .map(|mode| *mode)
+            .unwrap_or(self.conf.disk_placement_mode)
+    }
+
+    fn select_disk(&self, uid: &PartitionedUId) -> Result<LocalDiskDelegator, WorkerError> {
+        let hash_value = PartitionedUId::get_hash(uid);
+
+        let mut candidates = vec![];
+        for local_disk in &self.local_disks {
+            if !local_disk.is_corrupted()? && local_disk.is_healthy()? {
+                candidates.push(local_disk);
+            }
+        }
+
+        let len = candidates.len();
+        if len == 0 {
+            error!("There is no available local disk!");
+            return Err(WorkerError::NO_AVAILABLE_LOCAL_DISK);
+        }
+
+        let index = match self.get_placement_mode(&uid.app_id) {
+            DiskPlacementMode::HASH => (hash_value % len as u64) as usize,
+            DiskPlacementMode::PARTITION_RANGE => {
+                // the ranges of the shuffle are placed on the disks in turn, starting from
+                // the app specific disk to spread the small shuffles of different apps.
+                let range_size = self.conf.disk_placement_partition_range_size.max(1) as u64;
+                let range_idx = uid.partition_id as u64 / range_size;
+                let start = fxhash::hash64(uid.app_id.as_bytes());
+                (start
+                    .wrapping_add(uid.shuffle_id as u64)
+                    .wrapping_add(range_idx)
+                    % len as u64) as usize
+            }
+            DiskPlacementMode::LEAST_IO_PRESSURE => {
+                // the disks are scanned from the hashed one, so that the equally loaded
+                // disks still share the partitions.
+                let mut least: Option<(usize, (u64, f64))> = None;
+                for offset in 0..len {
+                    let idx = (hash_value.wrapping_add(offset as u64) % len as u64) as usize;
+                    let pressure = candidates[idx].io_pressure()?;
+                    let is_less = match least {
+                        Some((_, least_pressure)) => {
+                            pressure.0 < least_pressure.0
+                                || (pressure.0 == least_pressure.0 && pressure.1 < least_pressure.1)
+                        }
+                        _ => true,
+                    };
+                    if is_less {
+                        least = Some((idx, pressure));
                     }
-                    // clear the previous file.
-                    LocalFileStore::delete_all_files(main_dir)?;
+                }
+                least.map_or(0, |(idx, _)| idx)
+            }
+        };
+        if let Some(&disk) = candidates.get(index) {
+            Ok(disk.clone())
+        } else {
+            Err(WorkerError::INTERNAL_ERROR)
+        }
+    }
+
+    async fn data_insert(
+        &self,
+        uid: PartitionedUId,
+        blocks: Vec<&Block>,
+    ) -> Result<(), WorkerError> {
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+
+        let mut parent_dir_is_created = true;
+        let locked_obj = match self.partition_locks.entry(data_file_path.clone()) {
+            Entry::Vacant(e) => {
+                parent_dir_is_created = false;
+                let disk = self.select_disk(&uid)?;
+                let locked_obj = Arc::new(RwLock::new(LockedObj::from(disk)));
+                let obj = e.insert_entry(locked_obj.clone());
+                obj.get().clone()
+            }
+            Entry::Occupied(v) => v.get().clone(),
+        };
+
+        let mut locked_obj = locked_obj
+            .write()
+            .instrument_await("waiting the localfile partition lock...")
+            .await;
+        let local_disk = &locked_obj.disk;
+        let next_offset = locked_obj.pointer.load(SeqCst);
+
+        if local_disk.is_corrupted()? {
+            return Err(WorkerError::PARTIAL_DATA_LOST(local_disk.root()));
+        }
+
+        if !local_disk.is_healthy()? {
+            return Err(WorkerError::LOCAL_DISK_UNHEALTHY(local_disk.root()));
+        }
+
+        if !parent_dir_is_created {
+            if let Some(path) = Path::new(&data_file_path).parent() {
+                let path = format!("{}/", path.to_str().unwrap()).as_str().to_owned();
+                local_disk
+                    .create_dir(path.as_str())
+                    .instrument_await(format!("creating the directory: {}", path.as_str()))
+                    .await?;
+            }
+        }
+
+        let dedup = if self.conf.spill_dedup_enable {
+            let physical_offset = locked_obj.physical_pointer.load(SeqCst);
+            Some(self.dedup_blocks(
+                &uid,
+                &blocks,
+                local_disk,
+                &data_file_path,
+                next_offset,
+                physical_offset,
+            ))
+        } else {
+            None
+        };
+
+        let block_crcs = if self.crc_cache.is_some() {
+            let mut offset = next_offset;
+            let crcs = blocks
+                .iter()
+                .map(|block| {
+                    let crc = BlockCrc {
+                        offset,
+                        length: block.length,
+                        crc: block.crc,
+                        block_id: block.block_id,
+                    };
+                    offset += block.length as i64;
+                    crc
+                })
+                .collect::<Vec<_>>();
+            Some(crcs)
+        } else {
+            None
+        };
+        let shuffle_file_format = self.create_shuffle_format(blocks, next_offset)?;
+        let mut compressed_chunks = None;
+        let (data, data_len, physical_offset) = match (&dedup, &self.compression_codec) {
+            (Some(dedup), _) => (
+                Composed(ComposedBytes::from(dedup.data_chain.clone())),
+                dedup.len,
+                locked_obj.physical_pointer.load(SeqCst),
+            ),
+            (_, Some(codec)) => {
+                let physical_offset = locked_obj.physical_pointer.load(SeqCst);
+                let data = match shuffle_file_format.data {
+                    Composed(composed) => composed,
+                    Direct(bytes) => ComposedBytes::from(vec![bytes]),
+                };
+                let (compressed, chunks) =
+                    compress_chunks(codec.as_ref(), &data, next_offset, physical_offset)?;
+                compressed_chunks = Some(chunks);
+                let len = compressed.len();
+                (Composed(compressed), len, physical_offset)
+            }
+            _ => (
+                shuffle_file_format.data,
+                shuffle_file_format.len,
+                next_offset,
+            ),
+        };
+        let append_future = if self.direct_io_enable && self.direct_io_append_enable {
+            local_disk.direct_append(&data_file_path, physical_offset as usize, data)
+        } else {
+            local_disk.append(&data_file_path, data)
+        };
+        append_future
+            .instrument_await(format!(
+                "data flushing with {} bytes. path: {}",
+                data_len, &data_file_path
+            ))
+            .await?;
+        let index_bytes_len = shuffle_file_format.index.len();
+        local_disk
+            .append(&index_file_path, shuffle_file_format.index)
+            .instrument_await(format!(
+                "index flushing with {} bytes. path: {}",
+                index_bytes_len, &index_file_path
+            ))
+            .await?;
+        locked_obj
+            .index_entries
+            .fetch_add((index_bytes_len / INDEX_BLOCK_SIZE) as u64, SeqCst);
+        if let (Some(cache), Some(crcs)) = (&self.crc_cache, block_crcs) {
+            cache.append(&data_file_path, &crcs);
+        }
+
+        TOTAL_LOCALFILE_USED.inc_by(data_len as u64);
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[&local_disk.root()])
+            .add((data_len + index_bytes_len) as i64);
+
+        if let Some(dedup) = dedup {
+            TOTAL_LOCALFILE_DEDUP_BYTES.inc_by((shuffle_file_format.len - data_len) as u64);
+            // the new digests are visible only after the bytes have been flushed.
+            let dedup_index = self.get_dedup_index(&uid);
+            for (digest, location) in dedup.new_digests {
+                dedup_index.entry(digest).or_insert(location);
+            }
+            let extents = locked_obj.extents.get_or_insert_with(|| vec![]);
+            for extent in dedup.extents {
+                push_extent(extents, extent);
+            }
+            locked_obj
+                .physical_pointer
+                .store(physical_offset + data_len as i64, SeqCst);
+        }
+
+        if let Some(chunks) = compressed_chunks {
+            TOTAL_LOCALFILE_COMPRESSION_SAVED_BYTES
+                .inc_by(shuffle_file_format.len.saturating_sub(data_len) as u64);
+            locked_obj
+                .compressed_chunks
+                .get_or_insert_with(|| vec![])
+                .extend(chunks);
+            locked_obj
+                .physical_pointer
+                .store(physical_offset + data_len as i64, SeqCst);
+        }
+
+        locked_obj
+            .deref()
+            .pointer
+            .store(shuffle_file_format.offset, SeqCst);
+        locked_obj
+            .deref()
+            .last_written_timestamp
+            .store(util::now_timestamp_as_millis() as u64, SeqCst);
+
+        Ok(())
+    }
+
+    fn get_dedup_index(&self, uid: &PartitionedUId) -> Arc<DashMap<BlockDigest, BlockLocation>> {
+        let shuffle_path =
+            LocalFileStore::gen_relative_path_for_shuffle(&uid.app_id, uid.shuffle_id);
+        self.dedup_indexes
+            .entry(shuffle_path)
+            .or_insert_with(|| Default::default())
+            .clone()
+    }
+
+    /// Picks out the blocks whose content has not been stored in the same shuffle, and
+    /// builds the extents mapping the logical offsets to the physical bytes.
+    fn dedup_blocks(
+        &self,
+        uid: &PartitionedUId,
+        blocks: &Vec<&Block>,
+        local_disk: &LocalDiskDelegator,
+        data_file_path: &String,
+        logical_offset: i64,
+        physical_offset: i64,
+    ) -> DedupBlocks {
+        let dedup_index = self.get_dedup_index(uid);
+
+        let mut logical_offset = logical_offset;
+        let mut physical_offset = physical_offset;
+        let mut new_digests: HashMap<BlockDigest, BlockLocation> = HashMap::new();
+        let mut data_chain = vec![];
+        let mut extents = vec![];
+        let mut len = 0;
+        for block in blocks {
+            let length = block.length as i64;
+            let digest = BlockDigest::from(*block);
+            let existing = match dedup_index.get(&digest) {
+                Some(location) => Some(location.clone()),
+                _ => new_digests.get(&digest).cloned(),
+            };
+            let location = match existing {
+                Some(location) => location,
+                _ => {
+                    let location = BlockLocation {
+                        disk: local_disk.clone(),
+                        data_file_path: data_file_path.clone(),
+                        offset: physical_offset,
+                    };
+                    new_digests.insert(digest, location.clone());
+                    data_chain.push(block.data.clone());
+                    len += block.length as usize;
+                    physical_offset += length;
+                    location
+                }
+            };
+            push_extent(
+                &mut extents,
+                Extent {
+                    logical_offset,
+                    length,
+                    location,
+                },
+            );
+            logical_offset += length;
+        }
+
+        DedupBlocks {
+            data_chain,
+            len,
+            extents,
+            new_digests,
+        }
+    }
+
+    /// Reads the logical range of the deduped partition from the referred physical bytes.
+    async fn read_extents(
+        &self,
+        extents: &Vec<Extent>,
+        offset: i64,
+        len: i64,
+    ) -> Result<Bytes, WorkerError> {
+        let end = offset + len;
+        let start_idx = extents.partition_point(|x| x.logical_offset + x.length <= offset);
+
+        let mut pieces = vec![];
+        for extent in &extents[start_idx..] {
+            if extent.logical_offset >= end {
+                break;
+            }
+            let location = &extent.location;
+            if location.disk.is_corrupted()? {
+                return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
+                    location.disk.root(),
+                ));
+            }
+            let piece_start = offset.max(extent.logical_offset);
+            let piece_end = end.min(extent.logical_offset + extent.length);
+            let physical_offset = location.offset + piece_start - extent.logical_offset;
+            let piece = location
+                .disk
+                .read(
+                    &location.data_file_path,
+                    physical_offset,
+                    Some(piece_end - piece_start),
+                )
+                .instrument_await(format!(
+                    "getting deduped data from offset:{} from localfile: {}",
+                    physical_offset, &location.data_file_path
+                ))
+                .await?;
+            pieces.push(piece);
+        }
+
+        if pieces.len() == 1 {
+            return Ok(pieces.pop().unwrap());
+        }
+        let mut data = BytesMut::with_capacity(pieces.iter().map(|x| x.len()).sum());
+        for piece in pieces {
+            data.put(piece);
+        }
+        Ok(data.freeze())
+    }
+
+    /// Reads the logical range of the compressed partition, and only the chunks covering
+    /// the range will be read and decompressed.
+    async fn read_compressed_chunks(
+        &self,
+        local_disk: &LocalDiskDelegator,
+        data_file_path: &str,
+        chunks: &Vec<CompressedChunk>,
+        offset: i64,
+        len: i64,
+    ) -> Result<Bytes, WorkerError> {
+        let codec = self
+            .compression_codec
+            .as_ref()
+            .ok_or(WorkerError::INTERNAL_ERROR)?;
+        let end = offset + len;
+
+        let mut pieces = vec![];
+        for chunk in overlapped_chunks(chunks, offset, len) {
+            let future_read = if self.direct_io_enable && self.direct_io_read_enable {
+                local_disk.direct_read(data_file_path, chunk.physical_offset, chunk.physical_length)
+            } else {
+                local_disk.read(
+                    data_file_path,
+                    chunk.physical_offset,
+                    Some(chunk.physical_length),
+                )
+            };
+            let compressed = future_read
+                .instrument_await(format!(
+                    "getting compressed chunk from offset:{} from localfile: {}",
+                    chunk.physical_offset, data_file_path
+                ))
+                .await?;
+            let decompressed = codec.decompress(&compressed, chunk.length as usize)?;
+            TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.inc();
+
+            let piece_start = offset.max(chunk.logical_offset) - chunk.logical_offset;
+            let piece_end = end.min(chunk.logical_offset + chunk.length) - chunk.logical_offset;
+            pieces.push(decompressed.slice(piece_start as usize..piece_end as usize));
+        }
+
+        if pieces.len() == 1 {
+            return Ok(pieces.pop().unwrap());
+        }
+        let mut data = BytesMut::with_capacity(pieces.iter().map(|x| x.len()).sum());
+        for piece in pieces {
+            data.put(piece);
+        }
+        Ok(data.freeze())
+    }
+
+    /// Reads the data, and the blocks failing the crc verification will be repaired by the
+    /// given fallback if possible. The reading is given up once exceeding the deadline.
+    pub async fn get_with_repair<F>(
+        &self,
+        ctx: ReadingViewContext,
+        repair: F,
+    ) -> Result<ResponseData, WorkerError>
+    where
+        F: Fn(i64) -> Option<Bytes> + Send + Sync,
+    {
+        let deadline = ctx.deadline;
+        run_until_deadline(deadline, self.read_with_repair(ctx, repair)).await
+    }
+
+    async fn read_with_repair<F>(
+        &self,
+        ctx: ReadingViewContext,
+        repair: F,
+    ) -> Result<ResponseData, WorkerError>
+    where
+        F: Fn(i64) -> Option<Bytes> + Send + Sync,
+    {
+        let deadline = ctx.deadline;
+        let uid = ctx.uid;
+        let (offset, len) = match ctx.reading_options {
+            FILE_OFFSET_AND_LEN(offset, len) => (offset, len),
+            _ => (0, 0),
+        };
+
+        if len == 0 {
+            warn!("There is no data in localfile for [{:?}]", &uid);
+            return Ok(ResponseData::Local(PartitionedLocalData {
+                data: Default::default(),
+            }));
+        }
+
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+
+        if !self.partition_locks.contains_key(&data_file_path) {
+            warn!(
+                "There is no cached data in localfile store for [{:?}]",
+                &uid
+            );
+            return Ok(ResponseData::Local(PartitionedLocalData {
+                data: Default::default(),
+            }));
+        }
+
+        let locked_object = self
+            .partition_locks
+            .entry(data_file_path.clone())
+            .or_insert_with(|| {
+                Arc::new(RwLock::new(LockedObj::from(
+                    self.select_disk(&uid).unwrap(),
+                )))
+            })
+            .clone();
+
+        let locked_object = locked_object
+            .read()
+            .instrument_await("waiting the partition file [write] lock")
+            .await;
+        let local_disk = &locked_object.disk;
+        locked_object
+            .last_read_timestamp
+            .store(util::now_timestamp_as_millis() as u64, SeqCst);
+        // the lock may be waited for long, and the disk reading is skipped if it's useless
+        check_deadline(deadline)?;
+
+        if local_disk.is_corrupted()? {
+            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
+                local_disk.root(),
+            ));
+        }
+
+        let data = if let Some(extents) = &locked_object.extents {
+            self.read_extents(extents, offset, len).await?
+        } else if let Some(chunks) = &locked_object.compressed_chunks {
+            self.read_compressed_chunks(local_disk, &data_file_path, chunks, offset, len)
+                .await?
+        } else {
+            let cached = self
+                .read_ahead
+                .as_ref()
+                .and_then(|cache| cache.get(&data_file_path, offset, len));
+            let data = match cached {
+                Some(data) => data,
+                _ => {
+                    let future_read = if self.direct_io_enable && self.direct_io_read_enable {
+                        local_disk.direct_read(&data_file_path, offset, len)
+                    } else {
+                        local_disk.read(&data_file_path, offset, Some(len))
+                    };
+                    future_read
+                        .instrument_await(format!(
+                            "getting data from offset:{} with expected {} bytes from localfile: {}",
+                            offset, len, &data_file_path
+                        ))
+                        .await?
+                }
+            };
+            self.read_ahead_async(
+                &data_file_path,
+                local_disk,
+                offset + len,
+                locked_object.pointer.load(SeqCst),
+            );
+            data
+        };
+
+        let data = if self.conf.read_crc_verify_enable {
+            self.verify_crc(
+                local_disk,
+                &data_file_path,
+                &index_file_path,
+                offset,
+                data,
+                &repair,
+            )
+            .instrument_await(format!(
+                "verifying the crc of localfile: {}",
+                &data_file_path
+            ))
+            .await?
+        } else {
+            data
+        };
+
+        Ok(ResponseData::Local(PartitionedLocalData { data }))
+    }
+
+    /// Reads the index of the partition, or only the index entries in the given range.
+    async fn read_index(
+        &self,
+        ctx: ReadingIndexViewContext,
+    ) -> Result<ResponseDataIndex, WorkerError> {
+        let uid = &ctx.partition_id;
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+
+        if !self.partition_locks.contains_key(&data_file_path) {
+            warn!(
+                "There is no cached data in localfile store for [{:?}]",
+                &uid
+            );
+            return Ok(Local(LocalDataIndex {
+                index_data: Default::default(),
+                data_file_len: 0,
+                index_len: 0,
+            }));
+        }
+
+        let locked_object = self
+            .partition_locks
+            .entry(data_file_path.clone())
+            .or_insert_with(|| {
+                Arc::new(RwLock::new(LockedObj::from(
+                    self.select_disk(&uid).unwrap(),
+                )))
+            })
+            .clone();
+
+        let locked_object = locked_object
+            .read()
+            .instrument_await("waiting the partition file [read] lock")
+            .await;
+        locked_object
+            .last_read_timestamp
+            .store(util::now_timestamp_as_millis() as u64, SeqCst);
+        let local_disk = &locked_object.disk;
+        if local_disk.is_corrupted()? {
+            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
+                local_disk.root(),
+            ));
+        }
+        let len = locked_object.pointer.load(SeqCst);
+        if let Some(range) = ctx.entry_range {
+            return self
+                .get_index_range(&locked_object, &index_file_path, len, range)
+                .await;
+        }
+        let data = local_disk
+            .read(&index_file_path, 0, None)
+            .instrument_await(format!(
+                "reading index data from file: {:?}",
+                &index_file_path
+            ))
+            .await?;
+
+        // Detect inconsistent data
+        if self.conf.index_consistency_detection_enable && data.len() > INDEX_BLOCK_SIZE {
+            if let Err(e) = LocalFileStore::detect_index_inconsistency(
+                &data,
+                len,
+                &local_disk.root(),
+                &index_file_path,
+                &data_file_path,
+            ) {
+                error!("Errors on detecting index inconsistency. err: {}", e);
+            }
+        }
+
+        let data =
+            LocalFileStore::truncate_index_view(&locked_object, &index_file_path, data, len)?;
+
+        let index_len = data.len() as i64;
+        Ok(Local(LocalDataIndex {
+            index_data: data,
+            data_file_len: len,
+            index_len,
+        }))
+    }
+
+    /// Prefetches the page following the sequential read into the read-ahead cache.
+    fn read_ahead_async(
+        &self,
+        data_file_path: &str,
+        local_disk: &LocalDiskDelegator,
+        next_offset: i64,
+        file_len: i64,
+    ) {
+        let cache = match &self.read_ahead {
+            Some(cache) => cache.clone(),
+            _ => return,
+        };
+        if next_offset >= file_len {
+            return;
+        }
+        let prefetch = match cache.start_prefetch(data_file_path, next_offset) {
+            Some(prefetch) => prefetch,
+            _ => return,
+        };
+        let page_offset = prefetch.page_offset;
+        let len = min(cache.window_size(), file_len - page_offset);
+        let local_disk = local_disk.clone();
+        let path = data_file_path.to_string();
+        self.runtime_manager.read_runtime.spawn(async move {
+            let data = match local_disk.read(&path, page_offset, Some(len)).await {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    debug!(
+                        "Errors on reading ahead the file: {} from offset: {}. err: {:?}",
+                        &path, page_offset, err
+                    );
+                    None
+                }
+            };
+            cache.finish_prefetch(&path, &prefetch, data);
+        });
+    }
+
+    /// Recomputes the crc of the blocks fully covered by the read range.
+    /// The cached block crcs are used if present, otherwise the index file is read and cached.
+    async fn verify_crc<F>(
+        &self,
+        local_disk: &LocalDiskDelegator,
+        data_file_path: &str,
+        index_file_path: &str,
+        offset: i64,
+        data: Bytes,
+        repair: &F,
+    ) -> Result<Bytes, WorkerError>
+    where
+        F: Fn(i64) -> Option<Bytes> + Send + Sync,
+    {
+        let end = offset + data.len() as i64;
+        let cached = self.crc_cache.as_ref().and_then(|x| x.get(data_file_path));
+        let crcs = match cached {
+            Some(crcs) => crcs,
+            _ => {
+                let index_data = local_disk.read(index_file_path, 0, None).await?;
+                let mut crcs = vec![];
+                for index_block in index_data.chunks_exact(INDEX_BLOCK_SIZE) {
+                    let block = IndexCodec::decode(index_data.slice_ref(index_block))?;
+                    crcs.push(BlockCrc::from(&block));
+                }
+                let crcs = Arc::new(crcs);
+                if let Some(cache) = &self.crc_cache {
+                    cache.put(data_file_path, crcs.clone());
+                }
+                crcs
+            }
+        };
+        // the entries are sorted by the offset
+        let start_idx = crcs.partition_point(|x| x.offset < offset);
+        let end_idx = crcs.partition_point(|x| x.offset < end);
+        let blocks = &crcs[start_idx..end_idx];
+
+        let mut repaired: Option<BytesMut> = None;
+        for block in blocks {
+            let block_end = block.offset + block.length as i64;
+            if block.offset < offset || block_end > end {
+                continue;
+            }
+            let start = (block.offset - offset) as usize;
+            let range = start..start + block.length as usize;
+            let actual = get_crc(&data.slice(range.clone()));
+            if actual == block.crc {
+                continue;
+            }
+
+            TOTAL_LOCALFILE_DATA_CRC_MISMATCH.inc();
+            match repair(block.block_id) {
+                Some(bytes) if bytes.len() == range.len() && get_crc(&bytes) == block.crc => {
+                    warn!(
+                        "Repaired the crc mismatched block: {} at offset: {} of index: {}",
+                        block.block_id, block.offset, index_file_path
+                    );
+                    let buf = repaired.get_or_insert_with(|| BytesMut::from(data.as_ref()));
+                    buf[range].copy_from_slice(&bytes);
+                }
+                _ => {
+                    error!(
+                        "Crc mismatched block: {} at offset: {} of index: {}. expected: {}, actual: {}",
+                        block.block_id, block.offset, index_file_path, block.crc, actual
+                    );
+                    return Err(WorkerError::DATA_CRC_MISMATCH {
+                        block_id: block.block_id,
+                        offset: block.offset,
+                        expected: block.crc,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        Ok(match repaired {
+            Some(data) => data.freeze(),
+            _ => data,
+        })
+    }
+
+    /// Reads the slice of the index file in the entry range, which is truncated within the
+    /// committed data file len. The range beyond the end of index file will get the empty
+    /// index data.
+    async fn get_index_range(
+        &self,
+        locked_obj: &LockedObj,
+        index_file_path: &str,
+        data_file_len: i64,
+        range: IndexEntryRange,
+    ) -> Result<ResponseDataIndex, WorkerError> {
+        let local_disk = &locked_obj.disk;
+        let file_len = local_disk
+            .file_stat(index_file_path)
+            .instrument_await(format!("stating the index file: {:?}", index_file_path))
+            .await?
+            .content_length;
+        let index_len = file_len / INDEX_BLOCK_SIZE as u64 * INDEX_BLOCK_SIZE as u64;
+
+        let offset = range.start_entry.saturating_mul(INDEX_BLOCK_SIZE as u64);
+        let length = range
+            .max_entries
+            .saturating_mul(INDEX_BLOCK_SIZE as u64)
+            .min(index_len.saturating_sub(offset));
+        let data = if length == 0 {
+            Default::default()
+        } else {
+            let data = local_disk
+                .read(index_file_path, offset as i64, Some(length as i64))
+                .instrument_await(format!(
+                    "reading index range data from file: {:?}",
+                    index_file_path
+                ))
+                .await?;
+            LocalFileStore::truncate_index_view(locked_obj, index_file_path, data, data_file_len)?
+        };
+
+        Ok(Local(LocalDataIndex {
+            index_data: data,
+            data_file_len,
+            index_len: index_len as i64,
+        }))
+    }
+
+    /// Truncates the index view to the entries within the committed data file len, so the
+    /// readers never see the index beyond the data. The index file is rewritten by the
+    /// explicit repair rather than here.
+    fn truncate_index_view(
+        locked_obj: &LockedObj,
+        index_file_path: &str,
+        index_data: Bytes,
+        data_file_len: i64,
+    ) -> Result<Bytes> {
+        let index_len = index_data.len();
+        let data = LocalFileStore::truncate_inconsistent_index(index_data, data_file_len)?;
+        if data.len() != index_len {
+            TOTAL_LOCALFILE_INDEX_TRUNCATED.inc();
+            if !locked_obj.index_truncation_logged.swap(true, SeqCst) {
+                warn!(
+                    "Truncated the index view of [{}] from {} to {} bytes, which exceeds the data file len: {}",
+                    index_file_path,
+                    index_len,
+                    data.len(),
+                    data_file_len
+                );
+            }
+        }
+        Ok(data)
+    }
+
+    /// Truncates the index data to the last entry fully covered by the data file len, and
+    /// the trailing partial entry is also dropped.
+    pub(crate) fn truncate_inconsistent_index(
+        index_data: Bytes,
+        data_file_len: i64,
+    ) -> Result<Bytes> {
+        let mut consistent_len = index_data.len() / INDEX_BLOCK_SIZE * INDEX_BLOCK_SIZE;
+        while consistent_len > 0 {
+            let block = IndexCodec::decode(
+                index_data.slice(consistent_len - INDEX_BLOCK_SIZE..consistent_len),
+            )?;
+            if block.offset + block.length as i64 <= data_file_len {
+                break;
+            }
+            consistent_len -= INDEX_BLOCK_SIZE;
+        }
+        if consistent_len == index_data.len() {
+            return Ok(index_data);
+        }
+        Ok(index_data.slice(..consistent_len))
+    }
+
+    /// Rewrites the index file of the partition to its consistent entries. The partition
+    /// write lock is held during the repair, so no writer is appending concurrently.
+    pub async fn repair_index(&self, uid: &PartitionedUId) -> Result<IndexRepairOutcome> {
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(uid);
+        let locked_object = match self.partition_locks.get(&data_file_path) {
+            Some(locked_object) => locked_object.clone(),
+            _ => return Err(anyhow!("No such partition: {:?} in localfile store", uid)),
+        };
+        let locked_object = locked_object
+            .write()
+            .instrument_await("waiting the partition file [write] lock to repair index")
+            .await;
+        let local_disk = &locked_object.disk;
+        let data_file_len = locked_object.pointer.load(SeqCst);
+        let index_data = local_disk.read(&index_file_path, 0, None).await?;
+        let index_len_before = index_data.len();
+        let index_data = LocalFileStore::truncate_inconsistent_index(index_data, data_file_len)?;
+        let index_len_after = index_data.len();
+        if index_len_after != index_len_before {
+            local_disk.write(&index_file_path, index_data).await?;
+            warn!(
+                "Repaired the index file: {} of root: {} from {} to {} bytes",
+                &index_file_path,
+                local_disk.root(),
+                index_len_before,
+                index_len_after
+            );
+        }
+        Ok(IndexRepairOutcome {
+            index_file_path,
+            data_file_len,
+            index_len_before,
+            index_len_after,
+        })
+    }
+
+    /// Deletes the spilled data/index files whose mtime is older than the max age, which
+    /// is the safety net for the leaked files of zombie apps. The files of the app that
+    /// `is_alive` returns true will be always kept. Returns the reclaimed bytes.
+    pub fn reclaim_expired_files<F>(&self, max_age: Duration, is_alive: F) -> Result<u64>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut reclaimed = 0;
+        for local_disk in &self.local_disks {
+            let root = local_disk.root();
+            match self.reclaim_expired_files_of_root(&root, max_age, &is_alive) {
+                Ok(bytes) => reclaimed += bytes,
+                Err(err) => warn!(
+                    "Errors on reclaiming expired files of disk: {}. err: {:?}",
+                    &root, err
+                ),
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    fn reclaim_expired_files_of_root<F>(
+        &self,
+        root: &str,
+        max_age: Duration,
+        is_alive: &F,
+    ) -> Result<u64>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let now = SystemTime::now();
+        let mut reclaimed = 0;
+        for app_entry in fs::read_dir(root)? {
+            let app_entry = app_entry?;
+            if !app_entry.file_type()?.is_dir() {
+                continue;
+            }
+            let app_id = app_entry.file_name().to_string_lossy().to_string();
+            if is_alive(&app_id) {
+                continue;
+            }
+            for shuffle_entry in fs::read_dir(app_entry.path())? {
+                let shuffle_entry = shuffle_entry?;
+                if !shuffle_entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let shuffle_id = shuffle_entry.file_name().to_string_lossy().to_string();
+                for file_entry in fs::read_dir(shuffle_entry.path())? {
+                    let file_entry = file_entry?;
+                    let file_name = file_entry.file_name().to_string_lossy().to_string();
+                    if !file_name.ends_with(".data") && !file_name.ends_with(".index") {
+                        continue;
+                    }
+                    let metadata = file_entry.metadata()?;
+                    let age = now
+                        .duration_since(metadata.modified()?)
+                        .unwrap_or(Duration::ZERO);
+                    if age <= max_age {
+                        continue;
+                    }
+                    fs::remove_file(file_entry.path())?;
+                    reclaimed += metadata.len();
+                    info!(
+                        "Reclaimed the expired file: {}/{}/{}. age: {}(s)",
+                        &app_id,
+                        &shuffle_id,
+                        &file_name,
+                        age.as_secs()
+                    );
+                    if file_name.ends_with(".data") {
+                        let relative_path = format!("{}/{}/{}", &app_id, &shuffle_id, &file_name);
+                        if let Some(cache) = &self.read_ahead {
+                            cache.invalidate(&relative_path);
+                        }
+                        if let Some(cache) = &self.crc_cache {
+                            cache.invalidate(&relative_path);
+                        }
+                        self.partition_locks.remove(&relative_path);
+                    }
+                }
+                if fs::read_dir(shuffle_entry.path())?.next().is_none() {
+                    fs::remove_dir(shuffle_entry.path())?;
+                    self.dedup_indexes
+                        .remove(&format!("{}/{}/", &app_id, &shuffle_id));
+                }
+            }
+            if fs::read_dir(app_entry.path())?.next().is_none() {
+                fs::remove_dir(app_entry.path())?;
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    /// Deletes the deduped partition, whose data file may be referred by the other partitions
+    /// of the same shuffle, so that it's kept and accounted until the shuffle is purged. The
+    /// partition is reset to the empty logical view in place, and the later appends continue
+    /// from the physical file end rather than overwriting the referred bytes.
+    /// Returns the logical bytes dropped from the partition.
+    async fn delete_deduped_partition(
+        &self,
+        data_file_path: &str,
+        index_file_path: &str,
+    ) -> Result<i64> {
+        let locked_obj = match self.partition_locks.get(data_file_path) {
+            Some(obj) => obj.clone(),
+            _ => return Ok(0),
+        };
+        let mut locked_obj = locked_obj
+            .write()
+            .instrument_await("waiting the partition file [write] lock")
+            .await;
+        let logical_len = locked_obj.pointer.load(SeqCst);
+        let used_bytes = locked_obj.disk_used_bytes();
+        locked_obj.disk.delete(index_file_path).await?;
+
+        locked_obj.pointer.store(0, SeqCst);
+        locked_obj.extents = Some(vec![]);
+        if let Some(cache) = &self.crc_cache {
+            cache.invalidate(data_file_path);
+        }
+        locked_obj.index_entries.store(0, SeqCst);
+        locked_obj.compacted_index_entries.store(0, SeqCst);
+        // the data file is kept and still accounted, only the index file is deleted
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[&locked_obj.disk.root()])
+            .sub(used_bytes - locked_obj.disk_used_bytes());
+        Ok(logical_len)
+    }
+
+    fn get_purging_shuffle_ids(&self, app_id: &str) -> Vec<i32> {
+        let app_prefix = format!("{}/", app_id);
+        let mut shuffle_ids: Vec<i32> = self
+            .partition_locks
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .key()
+                    .strip_prefix(&app_prefix)
+                    .and_then(|path| path.split('/').next())
+                    .and_then(|shuffle_id| shuffle_id.parse().ok())
+            })
+            .collect();
+        shuffle_ids.sort();
+        shuffle_ids.dedup();
+        shuffle_ids
+    }
+
+    /// Deletes the dir in all the disks and returns the removed bytes and files.
+    async fn purge_dir(&self, data_relative_dir_path: &str) -> Result<(i64, u64)> {
+        // the dir is spread over all the disks, which are deleted concurrently.
+        futures::future::try_join_all(
+            self.local_disks
+                .iter()
+                .map(|disk| disk.delete(data_relative_dir_path)),
+        )
+        .await?;
+
+        self.dedup_indexes
+            .retain(|key, _| !key.starts_with(data_relative_dir_path));
+        if let Some(cache) = &self.read_ahead {
+            cache.invalidate(data_relative_dir_path);
+        }
+        if let Some(cache) = &self.crc_cache {
+            cache.invalidate(data_relative_dir_path);
+        }
+
+        let keys_to_delete: Vec<_> = self
+            .partition_locks
+            .iter()
+            .filter(|entry| entry.key().starts_with(data_relative_dir_path))
+            .map(|entry| entry.key().to_string())
+            .collect();
+
+        let mut removed_data_size = 0i64;
+        let mut removed_files = 0u64;
+        for key in keys_to_delete {
+            let meta = self.partition_locks.remove(&key);
+            if let Some(x) = meta {
+                let lock_obj = x.1.write().await;
+                let size = lock_obj.physical_len();
+                removed_data_size += size;
+                // the data and index files
+                removed_files += 2;
+                GAUGE_LOCAL_DISK_SERVICE_USED
+                    .with_label_values(&[&lock_obj.disk.root()])
+                    .sub(lock_obj.disk_used_bytes());
+            }
+        }
+
+        Ok((removed_data_size, removed_files))
+    }
+
+    fn delete_all_files(dir: &Path) -> Result<()> {
+        let entries = fs::read_dir(dir)?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // To detect the index consistency with data file len for debug.
+    pub(crate) fn detect_index_inconsistency(
+        data: &Bytes,
+        data_file_len: i64,
+        root: &String,
+        index_file_path: &String,
+        data_file_path: &String,
+    ) -> Result<bool> {
+        let last_block_raw_bytes = data.slice(data.len() - INDEX_BLOCK_SIZE..);
+        match IndexCodec::decode(last_block_raw_bytes) {
+            Ok(index_block) => {
+                let index_indicated_data_len = index_block.offset + index_block.length as i64;
+                if data_file_len != index_indicated_data_len {
+                    TOTAL_DETECTED_LOCALFILE_IN_CONSISTENCY.inc();
+                    let timestamp = util::now_timestamp_as_millis();
+                    warn!("Attention: index indicated data len:{} != recorded data len:{}. root: {}. index path: {}. data path: {}. timestamp: {}",
+                            index_indicated_data_len, data_file_len, root, &index_file_path, &data_file_path, timestamp);
+                    let main_dir = Path::new("/tmp/riffle-detection");
+                    if !main_dir.exists() {
+                        fs::create_dir(main_dir)?;
+                    }
+                    // clear the previous file.
+                    LocalFileStore::delete_all_files(main_dir)?;
+
+                    let index_target_file_name = format!(
+                        "{}/{}-{}",
+                        &main_dir.to_string_lossy(),
+                        &index_file_path.replace("/", "-"),
+                        timestamp
+                    );
+                    let data_target_file_name = format!(
+                        "{}/{}-{}",
+                        &main_dir.to_string_lossy(),
+                        &data_file_path.replace("/", "-"),
+                        timestamp
+                    );
+
+                    fs::copy(
+                        &Path::new(&format!("{}/{}", root, index_file_path)),
+                        &Path::new(index_target_file_name.as_str()),
+                    )?;
+                    if data_file_len < 1024 * 1024 * 1024 {
+                        fs::copy(
+                            &Path::new(&format!("{}/{}", root, data_file_path)),
+                            &Path::new(data_target_file_name.as_str()),
+                        )?;
+                    } else {
+                        error!("Ignore copying data file due to the too large file. data_file_path: {}", &data_file_path);
+                    }
+
+                    return Ok(false);
+                }
+            }
+            Err(err) => {
+                error!("Errors on decoding the raw block. {:?}", err);
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Store for LocalFileStore {
+    fn start(self: Arc<Self>) {
+        todo!()
+    }
+
+    async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
+        if ctx.data_blocks.len() <= 0 {
+            return Ok(());
+        }
+
+        let uid = ctx.uid;
+        let blocks: Vec<&Block> = ctx.data_blocks.iter().collect();
+        self.data_insert(uid, blocks).await
+    }
+
+    async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
+        self.get_with_repair(ctx, |_| None).await
+    }
+
+    async fn get_index(
+        &self,
+        ctx: ReadingIndexViewContext,
+    ) -> Result<ResponseDataIndex, WorkerError> {
+        let deadline = ctx.deadline;
+        run_until_deadline(deadline, self.read_index(ctx)).await
+    }
+
+    async fn purge(&self, ctx: &PurgeDataContext) -> Result<i64> {
+        let (app_id, shuffle_id_option) = ctx.extract();
+
+        // the purge is split into the shuffle level batches to report the progress.
+        let shuffle_ids = match shuffle_id_option {
+            Some(shuffle_id) => vec![shuffle_id],
+            _ => self.get_purging_shuffle_ids(&app_id),
+        };
+
+        // the shuffle dirs are deleted concurrently with the bounded parallelism, and the
+        // batches are still reported in the shuffle order. Only the successfully deleted
+        // shuffles are accounted, the failed one stops the following deletions.
+        let parallelism = self.conf.purge_parallelism.max(1);
+        let app_id_ref = &app_id;
+        let mut purging = futures::stream::iter(shuffle_ids)
+            .map(|shuffle_id| async move {
+                if ctx.is_cancelled() {
+                    return (shuffle_id, None);
+                }
+                let relative_dir_path =
+                    LocalFileStore::gen_relative_path_for_shuffle(app_id_ref, shuffle_id);
+                (shuffle_id, Some(self.purge_dir(&relative_dir_path).await))
+            })
+            .buffered(parallelism);
+
+        let mut removed_data_size = 0i64;
+        while let Some((shuffle_id, purged)) = purging.next().await {
+            let (removed_bytes, removed_files) = match purged {
+                Some(purged) => purged?,
+                None => {
+                    info!(
+                        "The purge of app:[{}] is cancelled before shuffle:[{}] with [{}] bytes removed",
+                        &app_id, shuffle_id, removed_data_size
+                    );
+                    return Err(WorkerError::PURGE_CANCELLED(app_id.clone()).into());
+                }
+            };
+            removed_data_size += removed_bytes;
+            ctx.report_batch(PurgeBatch {
+                shuffle_id: Some(shuffle_id),
+                removed_bytes,
+                removed_files,
+            });
+        }
+
+        if shuffle_id_option.is_none() {
+            // clean up the rest app dir, which may hold the files that are not tracked.
+            let relative_dir_path = LocalFileStore::gen_relative_path_for_app(&app_id);
+            let (removed_bytes, _) = self.purge_dir(&relative_dir_path).await?;
+            removed_data_size += removed_bytes;
+            self.app_placement_modes.remove(&app_id);
+        }
+
+        Ok(removed_data_size)
+    }
+
+    async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64> {
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+        if self.conf.spill_dedup_enable {
+            return self
+                .delete_deduped_partition(&data_file_path, &index_file_path)
+                .await;
+        }
+        let locked_obj = match self.partition_locks.remove(&data_file_path) {
+            Some((_, locked_obj)) => locked_obj,
+            _ => return Ok(0),
+        };
+        let locked_obj = locked_obj
+            .write()
+            .instrument_await("waiting the partition file [write] lock")
+            .await;
+        let local_disk = &locked_obj.disk;
+        if let Some(cache) = &self.read_ahead {
+            cache.invalidate(&data_file_path);
+        }
+        if let Some(cache) = &self.crc_cache {
+            cache.invalidate(&data_file_path);
+        }
+        local_disk.delete(&index_file_path).await?;
+        local_disk.delete(&data_file_path).await?;
+
+        let size = locked_obj.physical_len();
+        GAUGE_LOCAL_DISK_SERVICE_USED
+            .with_label_values(&[&local_disk.root()])
+            .sub(locked_obj.disk_used_bytes());
+        Ok(size)
+    }
+
+    async fn is_healthy(&self) -> Result<bool> {
+        self.healthy_check()
+    }
+
+    async fn require_buffer(
+        &self,
+        _ctx: RequireBufferContext,
+    ) -> Result<RequireBufferResponse, WorkerError> {
+        todo!()
+    }
+
+    async fn release_ticket(&self, _ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
+        todo!()
+    }
+
+    fn register_app(&self, ctx: RegisterAppContext) -> Result<()> {
+        let mode = ctx
+            .app_config_options
+            .disk_placement_mode
+            .unwrap_or(self.conf.disk_placement_mode);
+        self.app_placement_modes.entry(ctx.app_id).or_insert(mode);
+        Ok(())
+    }
+
+    async fn name(&self) -> StorageType {
+        StorageType::LOCALFILE
+    }
+
+    async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
+        let data = ctx.ordered_blocks();
+        self.data_insert(ctx.uid.clone(), data)
+            .instrument_await("data insert")
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::app::{
+        AppConfigOptions, IndexEntryRange, PartitionedUId, PurgeBatch, PurgeDataContext,
+        PurgeProgressTracker, PurgeReason, ReadingIndexViewContext, ReadingOptions,
+        ReadingViewContext, RegisterAppContext, WritingViewContext,
+    };
+    use crate::chaos::{FaultMode, FaultOperation, FaultSpec, FaultTarget, FAULT_INJECTOR};
+    use crate::config::{
+        CompactionConfig, CompressionType, DiskPlacementMode, LocalfileStoreConfig,
+        ReadAheadConfig, StorageType,
+    };
+    use crate::store::localfile::LocalFileStore;
+
+    use crate::error::WorkerError;
+    use crate::metric::{
+        GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES, GAUGE_LOCAL_DISK_SERVICE_USED,
+        TOTAL_LOCALFILE_COMPACTIONS, TOTAL_LOCALFILE_COMPACTION_ABORTED,
+        TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS, TOTAL_LOCALFILE_READ_AHEAD_HIT,
+    };
+    use crate::store::index_codec::{IndexBlock, IndexCodec, INDEX_BLOCK_SIZE};
+    use crate::store::local::crc_cache::{BlockCrc, BlockCrcCache};
+    use crate::store::local::{LocalDiskStorage, LocalIO};
+    use crate::store::mem::buffer::BatchMemoryBlock;
+    use crate::store::spill::SpillWritingViewContext;
+    use crate::store::{Block, LocalDataIndex, ResponseData, ResponseDataIndex, Store};
+    use crate::util::get_crc;
+    use bytes::{Buf, Bytes, BytesMut};
+    use log::{error, info};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn create_writing_ctx() -> WritingViewContext {
+        let uid = PartitionedUId {
+            app_id: "100".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        let data = b"hello world!hello china!";
+        let size = data.len();
+        let writing_ctx = WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![
+                Block {
+                    block_id: 0,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+                Block {
+                    block_id: 1,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+            ],
+        );
+
+        writing_ctx
+    }
+
+    #[test]
+    fn local_disk_under_exception_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("local_disk_under_exception_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        println!("init local file path: {}", &temp_path);
+        let local_store = LocalFileStore::new(vec![temp_path.to_string()]);
+
+        let runtime = local_store.runtime_manager.clone();
+
+        let writing_view_ctx = create_writing_ctx();
+        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
+
+        if insert_result.is_err() {
+            println!("{:?}", insert_result.err());
+            panic!()
+        }
+
+        // case1: mark the local disk unhealthy, that will the following flush throw exception directly.
+        let local_disk = local_store.local_disks[0].clone();
+        local_disk.mark_unhealthy();
+
+        let writing_view_ctx = create_writing_ctx();
+        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
+        match insert_result {
+            Err(WorkerError::LOCAL_DISK_UNHEALTHY(_)) => {}
+            _ => panic!(),
+        }
+
+        // case2: mark the local disk healthy, all things work!
+        local_disk.mark_healthy();
+        let writing_view_ctx = create_writing_ctx();
+        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
+        match insert_result {
+            Err(WorkerError::LOCAL_DISK_UNHEALTHY(_)) => panic!(),
+            _ => {}
+        }
+
+        // case3: mark the local disk corrupted, fail directly.
+        local_disk.mark_corrupted();
+        let writing_view_ctx = create_writing_ctx();
+        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
+        match insert_result {
+            Err(WorkerError::PARTIAL_DATA_LOST(_)) => {}
+            _ => panic!(),
+        }
+
+        Ok(())
+    }
+
+    fn create_writing_ctx_by_uid(uid: &PartitionedUId) -> WritingViewContext {
+        let data = b"hello world!hello china!";
+        let size = data.len();
+        let writing_ctx = WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![
+                Block {
+                    block_id: 0,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+                Block {
+                    block_id: 1,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+            ],
+        );
+        writing_ctx
+    }
+
+    #[test]
+    fn purge_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_local_store").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        println!("init local file path: {}", &temp_path);
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+
+        let runtime = local_store.runtime_manager.clone();
+
+        let app_id = "purge_test-app-id".to_string();
+
+        let shuffle_id_1 = 1;
+        let shuffle_id_2 = 13;
+
+        let uid_1 = PartitionedUId {
+            app_id: app_id.clone(),
+            shuffle_id: shuffle_id_1,
+            partition_id: 0,
+        };
+        let uid_2 = PartitionedUId {
+            app_id: app_id.to_owned(),
+            shuffle_id: shuffle_id_2,
+            partition_id: 0,
+        };
+
+        // for shuffle_id = 1
+        let writing_ctx_1 = create_writing_ctx_by_uid(&uid_1);
+        let insert_result = runtime.wait(local_store.insert(writing_ctx_1));
+        if insert_result.is_err() {
+            println!("{:?}", insert_result.err());
+            panic!()
+        }
+        assert_eq!(
+            true,
+            runtime.wait(tokio::fs::try_exists(format!(
+                "{}/{}/{}/partition-{}.data",
+                &temp_path, &app_id, shuffle_id_1, "0"
+            )))?
+        );
+
+        // for shuffle_id = 13
+        let writing_ctx_2 = create_writing_ctx_by_uid(&uid_2);
+        let insert_result = runtime.wait(local_store.insert(writing_ctx_2));
+        if insert_result.is_err() {
+            println!("{:?}", insert_result.err());
+            panic!()
+        }
+        assert_eq!(
+            true,
+            runtime.wait(tokio::fs::try_exists(format!(
+                "{}/{}/{}/partition-{}.data",
+                &temp_path, &app_id, shuffle_id_2, "0"
+            )))?
+        );
+
+        // shuffle level purge
+        runtime
+            .wait(local_store.purge(&PurgeDataContext::new(
+                &PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned(), shuffle_id_1),
+            )))
+            .expect("");
+        assert_eq!(
+            false,
+            runtime.wait(tokio::fs::try_exists(format!(
+                "{}/{}/{}",
+                &temp_path, &app_id, shuffle_id_1
+            )))?
+        );
+        // the shuffle_id = 1 deletion will not effect shuffle_id = 13
+        let reading_ctx = ReadingIndexViewContext {
+            partition_id: uid_2.clone(),
+            entry_range: None,
+            deadline: None,
+        };
+        let reading_result = runtime.wait(local_store.get_index(reading_ctx)).expect("");
+        if let ResponseDataIndex::Local(index) = reading_result {
+            assert!(index.data_file_len > 0);
+        }
+
+        // app level purge
+        runtime.wait(local_store.purge(&PurgeDataContext {
+            purge_reason: PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned()),
+            progress_tracker: None,
+        }))?;
+        assert_eq!(
+            false,
+            runtime.wait(tokio::fs::try_exists(format!("{}/{}", &temp_path, &app_id)))?
+        );
+
+        Ok(())
+    }
 
-                    let index_target_file_name = format!(
-                        "{}/{}-{}",
-                        &main_dir.to_string_lossy(),
-                        &index_file_path.replace("/", "-"),
-                        timestamp
-                    );
-                    let data_target_file_name = format!(
-                        "{}/{}-{}",
-                        &main_dir.to_string_lossy(),
-                        &data_file_path.replace("/", "-"),
-                        timestamp
+    #[test]
+    #[ignore]
+    fn local_store_test() {
+        let temp_dir = tempdir::TempDir::new("test_local_store").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        info!("init local file path: {}", temp_path);
+        let mut local_store = LocalFileStore::new(vec![temp_path]);
+
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "100".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        let data = b"hello world!hello china!";
+        let size = data.len();
+        let writing_ctx = WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![
+                Block {
+                    block_id: 0,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+                Block {
+                    block_id: 1,
+                    length: size as i32,
+                    uncompress_length: 200,
+                    crc: 0,
+                    data: Bytes::copy_from_slice(data),
+                    task_attempt_id: 0,
+                },
+            ],
+        );
+
+        let insert_result = runtime.wait(local_store.insert(writing_ctx));
+        if insert_result.is_err() {
+            println!("{:?}", insert_result.err());
+            panic!()
+        }
+
+        async fn get_and_check_partitial_data(
+            local_store: &mut LocalFileStore,
+            uid: PartitionedUId,
+            size: i64,
+            expected: &[u8],
+        ) {
+            let reading_ctx = ReadingViewContext {
+                uid,
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, size as i64),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            };
+
+            let read_result = local_store.get(reading_ctx).await;
+            if read_result.is_err() {
+                error!("failed to get the localfile data: {:?}", read_result.err());
+                panic!()
+            }
+
+            match read_result.unwrap() {
+                ResponseData::Local(partitioned_data) => {
+                    assert_eq!(expected, partitioned_data.data.as_ref());
+                }
+                _ => panic!(),
+            }
+        }
+
+        // case1: read the one partition block data
+        runtime.wait(get_and_check_partitial_data(
+            &mut local_store,
+            uid.clone(),
+            size as i64,
+            data,
+        ));
+
+        // case2: read the complete block data
+        let mut expected = BytesMut::with_capacity(size * 2);
+        expected.extend_from_slice(data);
+        expected.extend_from_slice(data);
+        runtime.wait(get_and_check_partitial_data(
+            &mut local_store,
+            uid.clone(),
+            size as i64 * 2,
+            expected.freeze().as_ref(),
+        ));
+
+        // case3: get the index data
+        let reading_index_view_ctx = ReadingIndexViewContext {
+            partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
+        };
+        let result = runtime.wait(local_store.get_index(reading_index_view_ctx));
+        if result.is_err() {
+            panic!()
+        }
+
+        match result.unwrap() {
+            ResponseDataIndex::Local(data) => {
+                let mut index = data.index_data;
+                let offset_1 = index.get_i64();
+                assert_eq!(0, offset_1);
+                let length_1 = index.get_i32();
+                assert_eq!(size as i32, length_1);
+                index.get_i32();
+                index.get_i64();
+                let block_id_1 = index.get_i64();
+                assert_eq!(0, block_id_1);
+                let task_id = index.get_i64();
+                assert_eq!(0, task_id);
+
+                let offset_2 = index.get_i64();
+                assert_eq!(size as i64, offset_2);
+                assert_eq!(size as i32, index.get_i32());
+            }
+        }
+
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_index_consistency() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_index_consistency").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        info!("init local file path: {}", temp_path);
+
+        let mut raw_bytes = BytesMut::new();
+        IndexCodec::encode(
+            &IndexBlock {
+                offset: 0,
+                length: 10,
+                uncompress_length: 0,
+                crc: 0,
+                block_id: 0,
+                task_attempt_id: 0,
+            },
+            &mut raw_bytes,
+        )?;
+        let data_file_len = 10;
+
+        let raw_bytes = raw_bytes.freeze();
+
+        // case1: legal pass
+        assert_eq!(
+            true,
+            LocalFileStore::detect_index_inconsistency(
+                &raw_bytes,
+                data_file_len,
+                &"/".to_owned(),
+                &"i.1".to_owned(),
+                &"d.1".to_owned()
+            )?
+        );
+
+        // case2: Illegal
+        let data_file_len = 9;
+        // create the index file in the dir of temp_path
+        let index_file_path = "app-1/patition-1.index";
+        let data_file_path = "app-1/partition-1.data";
+
+        let abs_index_file_path = format!("{}/{}", &temp_path, index_file_path);
+        let abs_data_file_path = format!("{}/{}", &temp_path, data_file_path);
+        // create the empty file for the abs_index_file_path. empty file
+        std::fs::create_dir_all(Path::new(&abs_index_file_path).parent().unwrap())?;
+        std::fs::create_dir_all(Path::new(&abs_data_file_path).parent().unwrap())?;
+        std::fs::write(&abs_index_file_path, &raw_bytes)?;
+        std::fs::write(&abs_data_file_path, &raw_bytes)?;
+
+        assert_eq!(
+            false,
+            LocalFileStore::detect_index_inconsistency(
+                &raw_bytes,
+                data_file_len,
+                &temp_path,
+                &index_file_path.to_owned(),
+                &data_file_path.to_owned()
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebalance_between_disks() -> anyhow::Result<()> {
+        let temp_dir_1 = tempdir::TempDir::new("test_rebalance_between_disks_1").unwrap();
+        let temp_dir_2 = tempdir::TempDir::new("test_rebalance_between_disks_2").unwrap();
+        let temp_path_1 = temp_dir_1.path().to_str().unwrap().to_string();
+        let temp_path_2 = temp_dir_2.path().to_str().unwrap().to_string();
+        let local_store = Arc::new(LocalFileStore::new(vec![
+            temp_path_1.clone(),
+            temp_path_2.clone(),
+        ]));
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "100".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+        let data_len = 48;
+
+        // make the disk owned by the partition over-utilized
+        let source_root = runtime
+            .wait(local_store.get_partition_disk_root(&uid))?
+            .unwrap();
+        for disk in &local_store.local_disks {
+            disk.with_capacity(Arc::new(AtomicU64::new(100)));
+            let available = if disk.root() == source_root { 10 } else { 90 };
+            disk.with_available(Arc::new(AtomicU64::new(available)));
+        }
+        let target_root = if source_root == temp_path_1 {
+            temp_path_2.clone()
+        } else {
+            temp_path_1.clone()
+        };
+
+        // case1: skip rebalancing when the skew is under the threshold
+        let progress = runtime.wait(local_store.rebalance(0.9))?;
+        assert_eq!(0, progress.outcomes.len());
+
+        // case2: the reads never fail during moving
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut readers = vec![];
+        for _ in 0..4 {
+            let store = local_store.clone();
+            let uid = uid.clone();
+            let stopped = stopped.clone();
+            readers.push(runtime.default_runtime.spawn(async move {
+                let mut read_times = 0;
+                while !stopped.load(SeqCst) || read_times == 0 {
+                    let data = store
+                        .get(ReadingViewContext {
+                            uid: uid.clone(),
+                            reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len),
+                            serialized_expected_task_ids_bitmap: Default::default(),
+                            latest_attempt_only: false,
+                            min_write_sequence: None,
+                            deadline: None,
+                        })
+                        .await
+                        .unwrap()
+                        .from_local();
+                    assert_eq!(
+                        Bytes::copy_from_slice(b"hello world!hello china!hello world!hello china!"),
+                        data
                     );
+                    read_times += 1;
+                }
+            }));
+        }
 
-                    fs::copy(
-                        &Path::new(&format!("{}/{}", root, index_file_path)),
-                        &Path::new(index_target_file_name.as_str()),
-                    )?;
-                    if data_file_len < 1024 * 1024 * 1024 {
-                        fs::copy(
-                            &Path::new(&format!("{}/{}", root, data_file_path)),
-                            &Path::new(data_target_file_name.as_str()),
-                        )?;
-                    } else {
-                        error!("Ignore copying data file due to the too large file. data_file_path: {}", &data_file_path);
-                    }
+        let progress = runtime.wait(local_store.rebalance(0.1))?;
+        stopped.store(true, SeqCst);
+        for reader in readers {
+            runtime.wait(reader)?;
+        }
 
-                    return Ok(false);
+        assert!(!progress.running);
+        assert_eq!(1, progress.candidate_partition_num);
+        assert_eq!(1, progress.outcomes.len());
+        assert_eq!(None, progress.outcomes[0].error);
+        assert_eq!(data_len, progress.outcomes[0].moved_bytes);
+        assert_eq!(
+            Some(target_root.clone()),
+            runtime.wait(local_store.get_partition_disk_root(&uid))?
+        );
+
+        // the source files should be removed after the switch
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+        assert!(!Path::new(&format!("{}/{}", &source_root, &data_file_path)).exists());
+        assert!(!Path::new(&format!("{}/{}", &source_root, &index_file_path)).exists());
+        assert!(Path::new(&format!("{}/{}", &target_root, &data_file_path)).exists());
+        assert!(Path::new(&format!("{}/{}", &target_root, &index_file_path)).exists());
+
+        // both the data and index bytes are accounted to the target disk
+        let index_len = std::fs::metadata(format!("{}/{}", &target_root, &index_file_path))?.len();
+        assert_eq!(
+            0,
+            GAUGE_LOCAL_DISK_SERVICE_USED
+                .with_label_values(&[&source_root])
+                .get()
+        );
+        assert_eq!(
+            data_len + index_len as i64,
+            GAUGE_LOCAL_DISK_SERVICE_USED
+                .with_label_values(&[&target_root])
+                .get()
+        );
+
+        // the following flushes should follow to the target disk
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+        let data = runtime
+            .wait(local_store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len * 2),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_local();
+        assert_eq!(data_len * 2, data.len() as i64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partition_range_placement() -> anyhow::Result<()> {
+        let temp_dirs: Vec<_> = (0..4)
+            .map(|idx| tempdir::TempDir::new(&format!("test_partition_range_placement_{}", idx)))
+            .collect::<Result<_, _>>()?;
+        let roots: Vec<String> = temp_dirs
+            .iter()
+            .map(|x| x.path().to_str().unwrap().to_string())
+            .collect();
+        let local_store = LocalFileStore::new(roots.clone());
+        let runtime = local_store.runtime_manager.clone();
+
+        let hash_app = "test_partition_range_placement_hash";
+        let range_app = "test_partition_range_placement_range";
+        let register = |app_id: &str, mode: Option<DiskPlacementMode>| {
+            let mut options = AppConfigOptions::default();
+            options.disk_placement_mode = mode;
+            local_store.register_app(RegisterAppContext {
+                app_id: app_id.to_string(),
+                app_config_options: options,
+            })
+        };
+        register(hash_app, None)?;
+        register(range_app, Some(DiskPlacementMode::PARTITION_RANGE))?;
+        // the mode is sticky after registry
+        register(range_app, Some(DiskPlacementMode::HASH))?;
+
+        let shuffle_num = 8;
+        let partition_num = 16;
+        for app_id in [hash_app, range_app] {
+            for shuffle_id in 0..shuffle_num {
+                for partition_id in 0..partition_num {
+                    let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+                    runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+                }
+            }
+        }
+
+        // the shuffle dirs to be touched by the shuffle level purge
+        let shuffle_dir_num = |app_id: &str| {
+            let mut num = 0;
+            for root in &roots {
+                for shuffle_id in 0..shuffle_num {
+                    if Path::new(root)
+                        .join(format!("{}/{}", app_id, shuffle_id))
+                        .exists()
+                    {
+                        num += 1;
+                    }
                 }
             }
-            Err(err) => {
-                error!("Errors on decoding the raw block. {:?}", err);
-                return Ok(false);
+            num
+        };
+        assert_eq!(shuffle_num as usize, shuffle_dir_num(range_app));
+        assert!(shuffle_dir_num(hash_app) > 2 * shuffle_num as usize);
+
+        // the bytes are still balanced across disks, and the reads work
+        let mut partitions_per_disk = HashMap::new();
+        for shuffle_id in 0..shuffle_num {
+            for partition_id in 0..partition_num {
+                let uid = PartitionedUId::from(range_app.to_string(), shuffle_id, partition_id);
+                let root = runtime
+                    .wait(local_store.get_partition_disk_root(&uid))?
+                    .unwrap();
+                *partitions_per_disk.entry(root).or_insert(0) += 1;
+
+                let data = runtime.wait(local_store.get(ReadingViewContext {
+                    uid,
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, 48),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                    latest_attempt_only: false,
+                    min_write_sequence: None,
+                    deadline: None,
+                }))?;
+                assert_eq!(48, data.from_local().len());
             }
         }
+        assert_eq!(roots.len(), partitions_per_disk.len());
+        for (_, num) in partitions_per_disk {
+            assert_eq!(shuffle_num * partition_num / roots.len() as i32, num);
+        }
 
-        Ok(true)
+        Ok(())
     }
-}
 
-#[async_trait]
-impl Store for LocalFileStore {
-    fn start(self: Arc<Self>) {
-        todo!()
-    }
+    #[test]
+    fn test_least_io_pressure_placement() -> anyhow::Result<()> {
+        let temp_dirs: Vec<_> = (0..3)
+            .map(|idx| tempdir::TempDir::new(&format!("test_least_io_pressure_placement_{}", idx)))
+            .collect::<Result<_, _>>()?;
+        let roots: Vec<String> = temp_dirs
+            .iter()
+            .map(|x| x.path().to_str().unwrap().to_string())
+            .collect();
+        let local_store = LocalFileStore::new(roots.clone());
+        let runtime = local_store.runtime_manager.clone();
 
-    async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
-        if ctx.data_blocks.len() <= 0 {
-            return Ok(());
+        let app_id = "test_least_io_pressure_placement";
+        let mut options = AppConfigOptions::default();
+        options.disk_placement_mode = Some(DiskPlacementMode::LEAST_IO_PRESSURE);
+        local_store.register_app(RegisterAppContext {
+            app_id: app_id.to_string(),
+            app_config_options: options,
+        })?;
+
+        // the appends of the busy disk are hung to simulate the saturated io
+        let busy_disk = local_store.local_disks[0].clone();
+        FAULT_INJECTOR.enable(true);
+        FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(busy_disk.root()),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::APPEND),
+            },
+            mode: FaultMode::LATENCY_MS(3000),
+            probability: 1.0,
+            ttl_sec: 10,
+        })?;
+        let hung_disk = busy_disk.clone();
+        runtime.default_runtime.spawn(async move {
+            hung_disk
+                .append("hung.data", Bytes::from(vec![0; 10]).into())
+                .await
+        });
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| busy_disk.io_pressure().unwrap().0 > 0);
+
+        // the new partitions land on the other disks
+        let uids: Vec<_> = (0..10)
+            .map(|partition_id| PartitionedUId::from(app_id.to_string(), 0, partition_id))
+            .collect();
+        let mut placed_roots = HashMap::new();
+        for uid in &uids {
+            runtime.wait(local_store.insert(create_writing_ctx_by_uid(uid)))?;
+            let root = runtime
+                .wait(local_store.get_partition_disk_root(uid))?
+                .unwrap();
+            assert_ne!(busy_disk.root(), root);
+            placed_roots.insert(uid.clone(), root);
         }
 
-        let uid = ctx.uid;
-        let blocks: Vec<&Block> = ctx.data_blocks.iter().collect();
-        self.data_insert(uid, blocks).await
+        // the placement sticks for the lifetime of the partition even if the disk is idle
+        awaitility::at_most(Duration::from_secs(10))
+            .until(|| busy_disk.io_pressure().unwrap().0 == 0);
+        for uid in &uids {
+            runtime.wait(local_store.insert(create_writing_ctx_by_uid(uid)))?;
+            assert_eq!(
+                placed_roots.get(uid),
+                runtime
+                    .wait(local_store.get_partition_disk_root(uid))?
+                    .as_ref()
+            );
+        }
+
+        Ok(())
     }
 
-    async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
-        let uid = ctx.uid;
-        let (offset, len) = match ctx.reading_options {
-            FILE_OFFSET_AND_LEN(offset, len) => (offset, len),
-            _ => (0, 0),
+    #[test]
+    fn test_read_ahead() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_read_ahead")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path]);
+        config.read_ahead = Some(ReadAheadConfig {
+            capacity: "1M".to_string(),
+            window_size: "32B".to_string(),
+        });
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
+        let cache = local_store.read_ahead.clone().unwrap();
+
+        let app_id = "test_read_ahead";
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+        let read = |offset: i64, len: i64| {
+            runtime.wait(local_store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, len),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))
         };
+        let expected = b"hello world!hello china!hello world!hello china!";
 
-        if len == 0 {
-            warn!("There is no data in localfile for [{:?}]", &uid);
-            return Ok(ResponseData::Local(PartitionedLocalData {
-                data: Default::default(),
-            }));
+        // case1: the first read misses, and the following page is prefetched
+        let hits = TOTAL_LOCALFILE_READ_AHEAD_HIT.get();
+        assert_eq!(&expected[0..16], read(0, 16)?.from_local().as_ref());
+        awaitility::at_most(Duration::from_secs(10)).until(|| cache.size() == 32);
+
+        // case2: the second sequential read is served from the cache
+        assert_eq!(&expected[16..32], read(16, 16)?.from_local().as_ref());
+        assert!(TOTAL_LOCALFILE_READ_AHEAD_HIT.get() > hits);
+
+        // case3: the purge drops the cached pages
+        runtime.wait(local_store.purge(&PurgeDataContext {
+            purge_reason: PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string(), 1),
+            progress_tracker: None,
+        }))?;
+        assert_eq!(0, cache.size());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(0, cache.size());
+
+        // case4: the stale prefetch started before the invalidation is dropped, even though
+        // the same page is prefetching again
+        let path = "test_read_ahead/1/0-0.data";
+        let stale = cache.start_prefetch(path, 0).unwrap();
+        cache.invalidate("test_read_ahead/1");
+        let fresh = cache.start_prefetch(path, 0).unwrap();
+        assert!(cache.start_prefetch(path, 0).is_none());
+        cache.finish_prefetch(path, &stale, Some(Bytes::from_static(b"stale")));
+        assert_eq!(0, cache.size());
+        cache.finish_prefetch(path, &fresh, Some(Bytes::from_static(b"fresh")));
+        assert_eq!(5, cache.size());
+        assert_eq!(b"fresh", cache.get(path, 0, 5).unwrap().as_ref());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evacuate_failing_disk() -> anyhow::Result<()> {
+        let temp_dir_1 = tempdir::TempDir::new("test_evacuate_failing_disk_1").unwrap();
+        let temp_dir_2 = tempdir::TempDir::new("test_evacuate_failing_disk_2").unwrap();
+        let temp_dir_3 = tempdir::TempDir::new("test_evacuate_failing_disk_3").unwrap();
+        let mut local_store = LocalFileStore::new(vec![
+            temp_dir_1.path().to_str().unwrap().to_string(),
+            temp_dir_2.path().to_str().unwrap().to_string(),
+            temp_dir_3.path().to_str().unwrap().to_string(),
+        ]);
+        local_store.conf.disk_evacuation_unhealthy_duration_sec = Some(0);
+        let local_store = Arc::new(local_store);
+        let runtime = local_store.runtime_manager.clone();
+        for disk in &local_store.local_disks {
+            disk.with_capacity(Arc::new(AtomicU64::new(100)));
+            disk.with_available(Arc::new(AtomicU64::new(90)));
         }
 
-        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let uid = PartitionedUId::from("100".to_string(), 0, 0);
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+        let data_len = 48;
+        let read = |uid: &PartitionedUId| {
+            runtime.wait(local_store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))
+        };
+        let disk_of = |root: &str| {
+            local_store
+                .local_disks
+                .iter()
+                .find(|disk| disk.root() == root)
+                .unwrap()
+                .clone()
+        };
 
-        if !self.partition_locks.contains_key(&data_file_path) {
-            warn!(
-                "There is no cached data in localfile store for [{:?}]",
-                &uid
-            );
-            return Ok(ResponseData::Local(PartitionedLocalData {
-                data: Default::default(),
-            }));
+        // case1: nothing to be evacuated for the healthy disks
+        assert_eq!(0, runtime.wait(local_store.evacuate())?);
+
+        // case2: the corrupted disk is evacuated and the reads are routed to the new disk
+        let source_root = runtime
+            .wait(local_store.get_partition_disk_root(&uid))?
+            .unwrap();
+        disk_of(&source_root).mark_corrupted()?;
+        match read(&uid) {
+            Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(_)) => {}
+            _ => panic!(),
         }
+        assert_eq!(data_len, runtime.wait(local_store.evacuate())?);
+        let target_root = runtime
+            .wait(local_store.get_partition_disk_root(&uid))?
+            .unwrap();
+        assert_ne!(source_root, target_root);
+        assert_eq!(data_len, read(&uid)?.from_local().len() as i64);
+        assert_eq!(
+            0,
+            GAUGE_LOCAL_DISK_EVACUATION_REMAINING_BYTES
+                .with_label_values(&[&source_root])
+                .get()
+        );
 
-        let locked_object = self
-            .partition_locks
-            .entry(data_file_path.clone())
-            .or_insert_with(|| {
-                Arc::new(RwLock::new(LockedObj::from(
-                    self.select_disk(&uid).unwrap(),
-                )))
-            })
-            .clone();
+        // case3: the unhealthy disk beyond the duration is also evacuated
+        disk_of(&target_root).mark_unhealthy()?;
+        assert_eq!(data_len, runtime.wait(local_store.evacuate())?);
+        let last_root = runtime
+            .wait(local_store.get_partition_disk_root(&uid))?
+            .unwrap();
+        assert_ne!(source_root, last_root);
+        assert_ne!(target_root, last_root);
+        assert_eq!(data_len, read(&uid)?.from_local().len() as i64);
 
-        let locked_object = locked_object
-            .read()
-            .instrument_await("waiting the partition file [write] lock")
-            .await;
-        let local_disk = &locked_object.disk;
+        // case4: no healthy disk is left
+        disk_of(&last_root).mark_corrupted()?;
+        assert_eq!(0, runtime.wait(local_store.evacuate())?);
 
-        if local_disk.is_corrupted()? {
-            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
-                local_disk.root(),
-            ));
+        Ok(())
+    }
+
+    fn create_block(block_id: i64, data: &[u8]) -> Block {
+        Block {
+            block_id,
+            length: data.len() as i32,
+            uncompress_length: data.len() as i32,
+            crc: 0,
+            data: Bytes::copy_from_slice(data),
+            task_attempt_id: 0,
         }
+    }
 
-        let future_read = if self.direct_io_enable && self.direct_io_read_enable {
-            local_disk.direct_read(&data_file_path, offset, len)
-        } else {
-            local_disk.read(&data_file_path, offset, Some(len))
+    #[test]
+    fn test_spill_dedup() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_dedup").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.spill_dedup_enable = true;
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
+
+        let app_id = "test_spill_dedup-app-id";
+        let uid = |shuffle_id: i32, partition_id: i32| PartitionedUId {
+            app_id: app_id.to_owned(),
+            shuffle_id,
+            partition_id,
         };
-        let data = future_read
-            .instrument_await(format!(
-                "getting data from offset:{} with expected {} bytes from localfile: {}",
-                offset, len, &data_file_path
-            ))
-            .await?;
+        let a = b"aaaaaaaaaa";
+        let b = b"bbbbbbbbbbbbbbbbbbbb";
+        let c = b"cccccccccccccccccccccccccccccc";
 
-        Ok(ResponseData::Local(PartitionedLocalData { data }))
-    }
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(0, 0),
+            vec![create_block(0, a), create_block(1, b)],
+        )))?;
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(0, 1),
+            vec![
+                create_block(2, b),
+                create_block(3, a),
+                create_block(4, c),
+                create_block(5, c),
+            ],
+        )))?;
+        // the dedup is not across the shuffles
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(1, 0),
+            vec![create_block(6, a)],
+        )))?;
 
-    async fn get_index(
-        &self,
-        ctx: ReadingIndexViewContext,
-    ) -> Result<ResponseDataIndex, WorkerError> {
-        let uid = &ctx.partition_id;
-        let (data_file_path, index_file_path) =
-            LocalFileStore::gen_relative_path_for_partition(&uid);
+        // only the unique bytes are stored on disk
+        let file_len = |uid: &PartitionedUId| {
+            let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(uid);
+            std::fs::metadata(format!("{}/{}", &temp_path, data_file_path))
+                .unwrap()
+                .len() as usize
+        };
+        assert_eq!(a.len() + b.len(), file_len(&uid(0, 0)));
+        assert_eq!(c.len(), file_len(&uid(0, 1)));
+        assert_eq!(a.len(), file_len(&uid(1, 0)));
 
-        if !self.partition_locks.contains_key(&data_file_path) {
-            warn!(
-                "There is no cached data in localfile store for [{:?}]",
-                &uid
-            );
-            return Ok(Local(LocalDataIndex {
-                index_data: Default::default(),
-                data_file_len: 0,
-            }));
+        // the index still refers to the logical data file
+        let logical_len = (b.len() + a.len() + c.len() * 2) as i64;
+        match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+            partition_id: uid(0, 1),
+            entry_range: None,
+            deadline: None,
+        }))? {
+            ResponseDataIndex::Local(index) => {
+                assert_eq!(logical_len, index.data_file_len);
+                let mut index_data = index.index_data;
+                let mut offset = 0;
+                while index_data.has_remaining() {
+                    let block = IndexCodec::decode(index_data.split_to(INDEX_BLOCK_SIZE))?;
+                    assert_eq!(offset, block.offset);
+                    offset += block.length as i64;
+                }
+                assert_eq!(logical_len, offset);
+            }
         }
 
-        let locked_object = self
-            .partition_locks
-            .entry(data_file_path.clone())
-            .or_insert_with(|| {
-                Arc::new(RwLock::new(LockedObj::from(
-                    self.select_disk(&uid).unwrap(),
-                )))
-            })
-            .clone();
+        let read = |uid: PartitionedUId, offset: i64, len: i64| {
+            runtime
+                .wait(local_store.get(ReadingViewContext {
+                    uid,
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, len),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                    latest_attempt_only: false,
+                    min_write_sequence: None,
+                    deadline: None,
+                }))
+                .unwrap()
+                .from_local()
+        };
+        let expected = [&b[..], &a[..], &c[..], &c[..]].concat();
+        assert_eq!(expected, read(uid(0, 1), 0, logical_len).as_ref());
+        // the partial range across the referred blocks
+        assert_eq!(&expected[15..45], read(uid(0, 1), 15, 30).as_ref());
+        assert_eq!([&a[..], &b[..]].concat(), read(uid(0, 0), 0, 30).as_ref());
+        assert_eq!(&a[..], read(uid(1, 0), 0, 10).as_ref());
 
-        let locked_object = locked_object
-            .read()
-            .instrument_await("waiting the partition file [read] lock")
-            .await;
-        let local_disk = &locked_object.disk;
-        if local_disk.is_corrupted()? {
-            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
-                local_disk.root(),
-            ));
-        }
-        let len = locked_object.pointer.load(SeqCst);
-        let data = local_disk
-            .read(&index_file_path, 0, None)
-            .instrument_await(format!(
-                "reading index data from file: {:?}",
-                &index_file_path
-            ))
-            .await?;
+        // the purged shuffle's dedup index should be removed
+        runtime.wait(local_store.purge(&PurgeDataContext::new(
+            &PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned(), 0),
+        )))?;
+        assert_eq!(1, local_store.dedup_indexes.len());
+        assert_eq!(&a[..], read(uid(1, 0), 0, 10).as_ref());
 
-        // Detect inconsistent data
-        if self.conf.index_consistency_detection_enable && data.len() > INDEX_BLOCK_SIZE {
-            if let Err(e) = LocalFileStore::detect_index_inconsistency(
-                &data,
-                len,
-                &local_disk.root(),
-                &index_file_path,
-                &data_file_path,
-            ) {
-                error!("Errors on detecting index inconsistency. err: {}", e);
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_deduped_partition() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_delete_deduped_partition").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.spill_dedup_enable = true;
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
+
+        let app_id = "test_delete_deduped_partition-app-id";
+        let uid = |partition_id: i32| PartitionedUId {
+            app_id: app_id.to_owned(),
+            shuffle_id: 0,
+            partition_id,
+        };
+        let a = b"aaaaaaaaaa";
+        let b = b"bbbbbbbbbbbbbbbbbbbb";
+        let c = b"cccccccccccccccccccccccccccccc";
+        let d = b"dddddddddddddddddddddddddddddddddddddddd";
+
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(0),
+            vec![create_block(0, a), create_block(1, b)],
+        )))?;
+        // the block b refers to the bytes of partition 0
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(1),
+            vec![create_block(2, b), create_block(3, c)],
+        )))?;
+
+        let removed = runtime.wait(local_store.delete_partition(uid(0)))?;
+        assert_eq!((a.len() + b.len()) as i64, removed);
+
+        // the rewritten partition appends after the kept bytes
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid(0),
+            vec![create_block(4, d), create_block(5, a)],
+        )))?;
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid(0));
+        let file_len = std::fs::metadata(format!("{}/{}", &temp_path, data_file_path))?.len();
+        assert_eq!((a.len() + b.len() + d.len()) as u64, file_len);
+
+        let read = |uid: PartitionedUId, len: usize| {
+            runtime
+                .wait(local_store.get(ReadingViewContext {
+                    uid,
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, len as i64),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                    latest_attempt_only: false,
+                    min_write_sequence: None,
+                    deadline: None,
+                }))
+                .unwrap()
+                .from_local()
+        };
+        assert_eq!(
+            [&d[..], &a[..]].concat(),
+            read(uid(0), d.len() + a.len()).as_ref()
+        );
+        assert_eq!(
+            [&b[..], &c[..]].concat(),
+            read(uid(1), b.len() + c.len()).as_ref()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_partition() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_restore_partition").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        let local_store = LocalFileStore::from(config.clone(), Default::default());
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId::from("test_restore_partition-app-id".to_string(), 0, 0);
+        let a = b"aaaaaaaaaa";
+        let b = b"bbbbbbbbbbbbbbbbbbbb";
+        let c = b"cccccccccccccccccccccccccccccc";
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(0, a)],
+        )))?;
+        let (disk_root, offset) = runtime
+            .wait(local_store.get_restorable_partition(&uid))?
+            .unwrap();
+        assert_eq!(a.len() as i64, offset);
+        // flushed after the snapshot, which is dropped on restoring
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(1, b)],
+        )))?;
+
+        let local_store = LocalFileStore::from(config, Default::default());
+        local_store.restore_partition(&uid, &disk_root, offset)?;
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(2, c)],
+        )))?;
+
+        match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+            partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
+        }))? {
+            ResponseDataIndex::Local(index) => {
+                assert_eq!((a.len() + c.len()) as i64, index.data_file_len);
+                let mut index_data = index.index_data;
+                let mut block_ids = vec![];
+                while index_data.has_remaining() {
+                    let block = IndexCodec::decode(index_data.split_to(INDEX_BLOCK_SIZE))?;
+                    block_ids.push((block.block_id, block.offset));
+                }
+                assert_eq!(vec![(0, 0), (2, a.len() as i64)], block_ids);
             }
         }
+        let data = runtime
+            .wait(local_store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, (a.len() + c.len()) as i64),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_local();
+        assert_eq!([&a[..], &c[..]].concat(), data.as_ref());
 
-        Ok(Local(LocalDataIndex {
-            index_data: data,
-            data_file_len: len,
-        }))
+        Ok(())
     }
 
-    async fn purge(&self, ctx: &PurgeDataContext) -> Result<i64> {
-        let (app_id, shuffle_id_option) = ctx.extract();
+    #[test]
+    fn test_spill_compression() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_compression").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.spill_compression = Some(CompressionType::LZ4);
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
 
-        let data_relative_dir_path = match shuffle_id_option {
-            Some(shuffle_id) => LocalFileStore::gen_relative_path_for_shuffle(&app_id, shuffle_id),
-            _ => LocalFileStore::gen_relative_path_for_app(&app_id),
+        let uid = PartitionedUId {
+            app_id: "test_spill_compression-app-id".to_owned(),
+            shuffle_id: 0,
+            partition_id: 0,
         };
+        let a = vec![b'a'; 1000];
+        let b = vec![b'b'; 2000];
+        let c = vec![b'c'; 3000];
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(0, &a), create_block(1, &b)],
+        )))?;
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(2, &c)],
+        )))?;
 
-        for local_disk_ref in &self.local_disks {
-            let disk = local_disk_ref.clone();
-            disk.delete(&data_relative_dir_path).await?;
+        // the chunks are compressed on disk, while the index refers to the logical data
+        let logical_len = (a.len() + b.len() + c.len()) as i64;
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let file_len = std::fs::metadata(format!("{}/{}", &temp_path, data_file_path))?.len();
+        assert!(file_len < logical_len as u64);
+        match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+            partition_id: uid.clone(),
+            entry_range: None,
+            deadline: None,
+        }))? {
+            ResponseDataIndex::Local(index) => assert_eq!(logical_len, index.data_file_len),
         }
 
-        let keys_to_delete: Vec<_> = self
-            .partition_locks
-            .iter()
-            .filter(|entry| entry.key().starts_with(&data_relative_dir_path))
-            .map(|entry| entry.key().to_string())
-            .collect();
+        let read = |offset: i64, len: i64| {
+            runtime
+                .wait(local_store.get(ReadingViewContext {
+                    uid: uid.clone(),
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, len),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                    latest_attempt_only: false,
+                    min_write_sequence: None,
+                    deadline: None,
+                }))
+                .unwrap()
+                .from_local()
+        };
 
-        let mut removed_data_size = 0i64;
-        for key in keys_to_delete {
-            let meta = self.partition_locks.remove(&key);
-            if let Some(x) = meta {
-                let lock_obj = x.1.write().await;
-                let size = lock_obj.pointer.load(SeqCst);
-                removed_data_size += size;
-                GAUGE_LOCAL_DISK_SERVICE_USED
-                    .with_label_values(&[&lock_obj.disk.root()])
-                    .sub(size);
-            }
-        }
+        // case1: reading a single segment only decompresses its chunk
+        let decompressed = TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.get();
+        assert_eq!(b, read(1000, 2000).as_ref());
+        assert_eq!(1, TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.get() - decompressed);
 
-        Ok(removed_data_size)
+        // case2: the range across the chunks of different spills
+        let decompressed = TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.get();
+        let expected = [&b[1500..], &c[..500]].concat();
+        assert_eq!(expected, read(2500, 1000).as_ref());
+        assert_eq!(2, TOTAL_LOCALFILE_DECOMPRESSED_CHUNKS.get() - decompressed);
+
+        // case3: the whole partition
+        assert_eq!(
+            [&a[..], &b[..], &c[..]].concat(),
+            read(0, logical_len).as_ref()
+        );
+
+        Ok(())
     }
 
-    async fn is_healthy(&self) -> Result<bool> {
-        self.healthy_check()
-    }
+    #[test]
+    fn test_compaction() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_compaction").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.compaction = Some(CompactionConfig {
+            index_entry_threshold: 4,
+            idle_sec: 3600,
+            check_interval_sec: 60,
+        });
+        let local_store = Arc::new(LocalFileStore::from(config, Default::default()));
+        let runtime = local_store.runtime_manager.clone();
+
+        let app_id = "test_compaction-app-id";
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        let a = b"aaaaaaaaaa";
+        let b = b"bbbbbbbbbbbbbbbbbbbb";
+        let c = b"cccccccccccccccccccccccccccccc";
+        let d = b"dddddddddd";
+        let e = b"eeeeeeeeee";
+        // the retried spills bring the duplicate blocks, and the last block comes in pieces
+        for blocks in [
+            vec![create_block(0, a), create_block(1, b)],
+            vec![create_block(1, b), create_block(2, c)],
+            vec![create_block(2, c)],
+            vec![create_block(3, d), create_block(3, e)],
+        ] {
+            runtime.wait(
+                local_store.insert(WritingViewContext::create_for_test(uid.clone(), blocks)),
+            )?;
+        }
+
+        let get_index = || match runtime
+            .wait(local_store.get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+                entry_range: None,
+                deadline: None,
+            }))
+            .unwrap()
+        {
+            ResponseDataIndex::Local(index) => {
+                let mut index_data = index.index_data;
+                let mut blocks = vec![];
+                while index_data.has_remaining() {
+                    blocks.push(IndexCodec::decode(index_data.split_to(INDEX_BLOCK_SIZE)).unwrap());
+                }
+                (blocks, index.data_file_len)
+            }
+        };
+        let index = || {
+            let (blocks, data_file_len) = get_index();
+            (blocks.len(), data_file_len)
+        };
+        let read = |offset: i64, len: i64| {
+            runtime
+                .wait(local_store.get(ReadingViewContext {
+                    uid: uid.clone(),
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, len),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                    latest_attempt_only: false,
+                    min_write_sequence: None,
+                    deadline: None,
+                }))
+                .unwrap()
+                .from_local()
+        };
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        // as if the partition has been neither written nor read for the idle duration
+        let make_idle = || {
+            let locked_obj = local_store
+                .partition_locks
+                .get(&data_file_path)
+                .unwrap()
+                .clone();
+            let obj = runtime.wait(locked_obj.read());
+            obj.last_written_timestamp.store(0, SeqCst);
+            obj.last_read_timestamp.store(0, SeqCst);
+        };
+
+        // case1: the partition is just written
+        assert_eq!(0, runtime.wait(local_store.compact())?);
+        assert_eq!((7, 130), index());
+
+        // case2: the reader holding the fetched index keeps the partition from compaction
+        make_idle();
+        let (blocks, _) = get_index();
+        assert_eq!(0, runtime.wait(local_store.compact())?);
+        let expected = [&a[..], &b[..], &b[..], &c[..], &c[..], &d[..], &e[..]];
+        for (block, expected) in blocks.iter().zip(expected) {
+            assert_eq!(expected, read(block.offset, block.length as i64).as_ref());
+        }
 
-    async fn require_buffer(
-        &self,
-        _ctx: RequireBufferContext,
-    ) -> Result<RequireBufferResponse, WorkerError> {
-        todo!()
-    }
+        // case3: compacted after being idle
+        make_idle();
+        let compactions = TOTAL_LOCALFILE_COMPACTIONS.get();
+        assert_eq!(
+            50 + 3 * INDEX_BLOCK_SIZE as i64,
+            runtime.wait(local_store.compact())?
+        );
+        assert_eq!(1, TOTAL_LOCALFILE_COMPACTIONS.get() - compactions);
+        let (blocks, data_file_len) = get_index();
+        assert_eq!(80, data_file_len);
+        let merged = blocks.last().unwrap();
+        assert_eq!((3, 60, 20), (merged.block_id, merged.offset, merged.length));
+        assert_eq!(get_crc(&[&d[..], &e[..]].concat()), merged.crc);
+        assert_eq!(
+            [&a[..], &b[..], &c[..], &d[..], &e[..]].concat(),
+            read(0, 80).as_ref()
+        );
+        let file_len = std::fs::metadata(format!("{}/{}", &temp_path, &data_file_path))?.len();
+        assert_eq!(80, file_len);
 
-    async fn release_ticket(&self, _ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
-        todo!()
-    }
+        // case4: the following appends are not compacted until reaching the threshold
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(4, a)],
+        )))?;
+        make_idle();
+        assert_eq!(0, runtime.wait(local_store.compact())?);
+        assert_eq!((5, 90), index());
+        assert_eq!(&a[..], read(80, 10).as_ref());
+
+        // case5: the compaction aborts once the partition is purged concurrently
+        let aborted = TOTAL_LOCALFILE_COMPACTION_ABORTED.get();
+        let locked_obj = local_store
+            .partition_locks
+            .get(&data_file_path)
+            .unwrap()
+            .clone();
+        let guard = runtime.wait(locked_obj.write());
+        let store = local_store.clone();
+        let path = data_file_path.clone();
+        let compaction = runtime
+            .default_runtime
+            .spawn(async move { store.compact_partition(&path).await });
+        let store = local_store.clone();
+        let purge = runtime.default_runtime.spawn(async move {
+            store
+                .purge(&PurgeDataContext::new(
+                    &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+                ))
+                .await
+        });
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| !local_store.partition_locks.contains_key(&data_file_path));
+        drop(guard);
+        assert!(runtime.wait(compaction)??.is_none());
+        runtime.wait(purge)??;
+        assert_eq!(1, TOTAL_LOCALFILE_COMPACTION_ABORTED.get() - aborted);
+        assert!(!Path::new(&format!("{}/{}", &temp_path, &data_file_path)).exists());
 
-    fn register_app(&self, _ctx: RegisterAppContext) -> Result<()> {
         Ok(())
     }
 
-    async fn name(&self) -> StorageType {
-        StorageType::LOCALFILE
-    }
+    #[test]
+    fn test_spill_with_local_order() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_with_local_order").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
 
-    async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
-        let uid = ctx.uid;
-        let mut data = vec![];
-        let batch_memory_block = ctx.data_blocks;
-        for blocks in batch_memory_block.iter() {
-            for block in blocks {
-                data.push(block);
+        let app_id = "test_spill_with_local_order-app-id";
+        let uid = |partition_id: i32| PartitionedUId::from(app_id.to_owned(), 0, partition_id);
+        // the blocks of the task attempts are interleaved in the arrival order
+        let batch = || {
+            let block = |block_id: i64, task_attempt_id: i64| Block {
+                block_id,
+                length: 5,
+                uncompress_length: 5,
+                crc: 0,
+                data: Bytes::from_static(b"hello"),
+                task_attempt_id,
+            };
+            let mut batch = BatchMemoryBlock::default();
+            batch.push(vec![block(0, 2), block(1, 1)]);
+            batch.push(vec![block(2, 2), block(3, 1), block(4, 3)]);
+            Arc::new(batch)
+        };
+        let flushed_order = |uid: PartitionedUId| -> anyhow::Result<Vec<(i64, i64)>> {
+            let mut order = vec![];
+            match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+                partition_id: uid,
+                entry_range: None,
+                deadline: None,
+            }))? {
+                ResponseDataIndex::Local(index) => {
+                    let mut index_data = index.index_data;
+                    while index_data.has_remaining() {
+                        let block = IndexCodec::decode(index_data.split_to(INDEX_BLOCK_SIZE))?;
+                        order.push((block.task_attempt_id, block.block_id));
+                    }
+                }
             }
-        }
-        // for AQE
-        data.sort_by_key(|block| block.task_attempt_id);
-        self.data_insert(uid, data)
-            .instrument_await("data insert")
-            .await
-    }
-}
+            Ok(order)
+        };
 
-#[cfg(test)]
-mod test {
-    use std::path::Path;
+        runtime.wait(local_store.spill_insert(SpillWritingViewContext::new(
+            uid(0),
+            batch(),
+            |_| true,
+        )))?;
+        assert_eq!(
+            vec![(1, 1), (1, 3), (2, 0), (2, 2), (3, 4)],
+            flushed_order(uid(0))?
+        );
 
-    use crate::app::{
-        PartitionedUId, PurgeDataContext, PurgeReason, ReadingIndexViewContext, ReadingOptions,
-        ReadingViewContext, WritingViewContext,
-    };
-    use crate::store::localfile::LocalFileStore;
+        // the arrival order is kept for the NORMAL distribution
+        runtime.wait(local_store.spill_insert(
+            SpillWritingViewContext::new(uid(1), batch(), |_| true).with_local_order(false),
+        ))?;
+        assert_eq!(
+            vec![(2, 0), (1, 1), (2, 2), (1, 3), (3, 4)],
+            flushed_order(uid(1))?
+        );
 
-    use crate::error::WorkerError;
-    use crate::store::index_codec::{IndexBlock, IndexCodec};
-    use crate::store::local::LocalDiskStorage;
-    use crate::store::{Block, ResponseData, ResponseDataIndex, Store};
-    use bytes::{Buf, Bytes, BytesMut};
-    use log::{error, info};
+        Ok(())
+    }
 
-    fn create_writing_ctx() -> WritingViewContext {
-        let uid = PartitionedUId {
-            app_id: "100".to_string(),
+    #[test]
+    fn test_injected_append_fault() -> anyhow::Result<()> {
+        let temp_dir_1 = tempdir::TempDir::new("test_injected_append_fault_1").unwrap();
+        let temp_dir_2 = tempdir::TempDir::new("test_injected_append_fault_2").unwrap();
+        let temp_path_1 = temp_dir_1.path().to_str().unwrap().to_string();
+        let temp_path_2 = temp_dir_2.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path_1.clone(), temp_path_2.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = |partition_id: i32| PartitionedUId {
+            app_id: "test_injected_append_fault".to_string(),
             shuffle_id: 0,
-            partition_id: 0,
+            partition_id,
         };
+        let faulty_root = local_store.select_disk(&uid(0))?.root();
+        let healthy_uid = (1..100)
+            .map(|id| uid(id))
+            .find(|uid| local_store.select_disk(uid).unwrap().root() != faulty_root)
+            .unwrap();
 
-        let data = b"hello world!hello china!";
-        let size = data.len();
-        let writing_ctx = WritingViewContext::create_for_test(
-            uid.clone(),
-            vec![
-                Block {
-                    block_id: 0,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-                Block {
-                    block_id: 1,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-            ],
-        );
+        FAULT_INJECTOR.enable(true);
+        let fault_id = FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(faulty_root.clone()),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::APPEND),
+            },
+            mode: FaultMode::ERROR("EIO".to_string()),
+            probability: 1.0,
+            ttl_sec: 1,
+        })?;
+        assert!(FAULT_INJECTOR
+            .list()
+            .iter()
+            .any(|fault| fault.id == fault_id));
 
-        writing_ctx
+        // the appends on the faulty disk fail, and the others are not affected
+        match runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid(0)))) {
+            Err(WorkerError::INJECTED_FAULT(_)) => {}
+            _ => panic!("the append should fail with the injected fault"),
+        }
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&healthy_uid)))?;
+
+        // recover after the fault expires
+        awaitility::at_most(Duration::from_secs(5)).until(|| {
+            !FAULT_INJECTOR
+                .list()
+                .iter()
+                .any(|fault| fault.id == fault_id)
+        });
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid(0))))?;
+        let data = runtime
+            .wait(local_store.get(ReadingViewContext {
+                uid: uid(0),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, 48),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))?
+            .from_local();
+        assert_eq!(48, data.len());
+
+        Ok(())
     }
 
     #[test]
-    fn local_disk_under_exception_test() -> anyhow::Result<()> {
-        let temp_dir = tempdir::TempDir::new("local_disk_under_exception_test").unwrap();
+    fn test_read_crc_verification() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_read_crc_verification").unwrap();
         let temp_path = temp_dir.path().to_str().unwrap().to_string();
-        println!("init local file path: {}", &temp_path);
-        let local_store = LocalFileStore::new(vec![temp_path.to_string()]);
-
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.read_crc_verify_enable = true;
+        let local_store = LocalFileStore::from(config, Default::default());
         let runtime = local_store.runtime_manager.clone();
 
-        let writing_view_ctx = create_writing_ctx();
-        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
+        let uid = PartitionedUId {
+            app_id: "test_read_crc_verification".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let blocks = (0..3)
+            .map(|idx| {
+                let data = Bytes::from(vec![idx as u8; 10]);
+                Block {
+                    block_id: idx,
+                    length: 10,
+                    uncompress_length: 10,
+                    crc: get_crc(&data),
+                    data,
+                    task_attempt_id: 0,
+                }
+            })
+            .collect();
+        runtime
+            .wait(local_store.insert(WritingViewContext::create_for_test(uid.clone(), blocks)))?;
 
-        if insert_result.is_err() {
-            println!("{:?}", insert_result.err());
-            panic!()
-        }
+        let read = |offset: i64, len: i64| {
+            runtime.wait(local_store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, len),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))
+        };
+        assert_eq!(30, read(0, 30)?.from_local().len());
 
-        // case1: mark the local disk unhealthy, that will the following flush throw exception directly.
-        let local_disk = local_store.local_disks[0].clone();
-        local_disk.mark_unhealthy();
+        // corrupt one byte of the second block
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let abs_data_file_path = format!("{}/{}", &temp_path, data_file_path);
+        let mut raw = std::fs::read(&abs_data_file_path)?;
+        raw[15] = 0xff;
+        std::fs::write(&abs_data_file_path, raw)?;
 
-        let writing_view_ctx = create_writing_ctx();
-        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
-        match insert_result {
-            Err(WorkerError::LOCAL_DISK_UNHEALTHY(_)) => {}
-            _ => panic!(),
+        match read(0, 30) {
+            Err(WorkerError::DATA_CRC_MISMATCH {
+                block_id, offset, ..
+            }) => {
+                assert_eq!(1, block_id);
+                assert_eq!(10, offset);
+            }
+            _ => panic!("the corrupted block should be detected"),
         }
+        // the range without the corrupted block is not affected
+        assert_eq!(10, read(20, 10)?.from_local().len());
 
-        // case2: mark the local disk healthy, all things work!
-        local_disk.mark_healthy();
-        let writing_view_ctx = create_writing_ctx();
-        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
-        match insert_result {
-            Err(WorkerError::LOCAL_DISK_UNHEALTHY(_)) => panic!(),
-            _ => {}
-        }
+        // the cached entries are verified without reading the index file
+        let crc_cache = local_store.crc_cache.as_ref().unwrap();
+        assert_eq!(3, crc_cache.entries());
+        let (_, index_file_path) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let abs_index_file_path = format!("{}/{}", &temp_path, index_file_path);
+        let moved_index_file_path = format!("{}.moved", &abs_index_file_path);
+        std::fs::rename(&abs_index_file_path, &moved_index_file_path)?;
+        assert!(matches!(
+            read(0, 30),
+            Err(WorkerError::DATA_CRC_MISMATCH { block_id: 1, .. })
+        ));
+        std::fs::rename(&moved_index_file_path, &abs_index_file_path)?;
 
-        // case3: mark the local disk corrupted, fail directly.
-        local_disk.mark_corrupted();
-        let writing_view_ctx = create_writing_ctx();
-        let insert_result = runtime.wait(local_store.insert(writing_view_ctx));
-        match insert_result {
-            Err(WorkerError::PARTIAL_DATA_LOST(_)) => {}
-            _ => panic!(),
-        }
+        // the cached entries are appended by the later flushes
+        let data = Bytes::from(vec![3u8; 10]);
+        let block = Block {
+            block_id: 3,
+            length: 10,
+            uncompress_length: 10,
+            crc: get_crc(&data),
+            data,
+            task_attempt_id: 0,
+        };
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![block],
+        )))?;
+        assert_eq!(4, crc_cache.entries());
+        assert_eq!(20, read(20, 20)?.from_local().len());
+
+        // the partition without the cached entries, like the restored one, reads the index file
+        crc_cache.invalidate(&data_file_path);
+        assert_eq!(0, crc_cache.entries());
+        assert!(matches!(
+            read(0, 30),
+            Err(WorkerError::DATA_CRC_MISMATCH { block_id: 1, .. })
+        ));
+        assert_eq!(4, crc_cache.entries());
+        assert_eq!(10, read(20, 10)?.from_local().len());
 
         Ok(())
     }
 
-    fn create_writing_ctx_by_uid(uid: &PartitionedUId) -> WritingViewContext {
-        let data = b"hello world!hello china!";
-        let size = data.len();
-        let writing_ctx = WritingViewContext::create_for_test(
-            uid.clone(),
-            vec![
-                Block {
-                    block_id: 0,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-                Block {
-                    block_id: 1,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-            ],
-        );
-        writing_ctx
+    #[test]
+    fn test_block_crc_cache_eviction() {
+        let crcs = |number: i64| {
+            Arc::new(
+                (0..number)
+                    .map(|idx| BlockCrc {
+                        offset: idx * 10,
+                        length: 10,
+                        crc: idx,
+                        block_id: idx,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let cache = BlockCrcCache::new(4);
+        cache.put("a/1/0-0.data", crcs(3));
+        cache.put("a/1/1-1.data", crcs(1));
+        assert_eq!(4, cache.entries());
+
+        // the least recently used partition is evicted
+        assert!(cache.get("a/1/0-0.data").is_some());
+        cache.put("a/1/2-2.data", crcs(1));
+        assert!(cache.get("a/1/1-1.data").is_none());
+        assert_eq!(4, cache.entries());
+
+        // the appended entries are bounded too
+        cache.append("a/1/2-2.data", &crcs(1));
+        assert!(cache.get("a/1/0-0.data").is_none());
+        assert_eq!(2, cache.entries());
+
+        // the partition exceeding the capacity is never cached
+        cache.put("a/1/3-3.data", crcs(5));
+        assert!(cache.get("a/1/3-3.data").is_none());
+
+        cache.invalidate("a/1/");
+        assert_eq!(0, cache.entries());
     }
 
     #[test]
-    fn purge_test() -> anyhow::Result<()> {
-        let temp_dir = tempdir::TempDir::new("test_local_store").unwrap();
+    fn test_inconsistent_index_truncation_and_repair() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_inconsistent_index").unwrap();
         let temp_path = temp_dir.path().to_str().unwrap().to_string();
-        println!("init local file path: {}", &temp_path);
         let local_store = LocalFileStore::new(vec![temp_path.clone()]);
-
         let runtime = local_store.runtime_manager.clone();
 
-        let app_id = "purge_test-app-id".to_string();
-
-        let shuffle_id_1 = 1;
-        let shuffle_id_2 = 13;
-
-        let uid_1 = PartitionedUId {
-            app_id: app_id.clone(),
-            shuffle_id: shuffle_id_1,
-            partition_id: 0,
-        };
-        let uid_2 = PartitionedUId {
-            app_id: app_id.to_owned(),
-            shuffle_id: shuffle_id_2,
+        let uid = PartitionedUId {
+            app_id: "test_inconsistent_index".to_string(),
+            shuffle_id: 0,
             partition_id: 0,
         };
-
-        // for shuffle_id = 1
-        let writing_ctx_1 = create_writing_ctx_by_uid(&uid_1);
-        let insert_result = runtime.wait(local_store.insert(writing_ctx_1));
-        if insert_result.is_err() {
-            println!("{:?}", insert_result.err());
-            panic!()
-        }
-        assert_eq!(
-            true,
-            runtime.wait(tokio::fs::try_exists(format!(
-                "{}/{}/{}/partition-{}.data",
-                &temp_path, &app_id, shuffle_id_1, "0"
-            )))?
-        );
-
-        // for shuffle_id = 13
-        let writing_ctx_2 = create_writing_ctx_by_uid(&uid_2);
-        let insert_result = runtime.wait(local_store.insert(writing_ctx_2));
-        if insert_result.is_err() {
-            println!("{:?}", insert_result.err());
-            panic!()
-        }
-        assert_eq!(
-            true,
-            runtime.wait(tokio::fs::try_exists(format!(
-                "{}/{}/{}/partition-{}.data",
-                &temp_path, &app_id, shuffle_id_2, "0"
-            )))?
-        );
-
-        // shuffle level purge
+        let blocks = (0..2)
+            .map(|idx| Block {
+                block_id: idx,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                data: Bytes::from(vec![idx as u8; 10]),
+                task_attempt_id: 0,
+            })
+            .collect();
         runtime
-            .wait(local_store.purge(&PurgeDataContext::new(
-                &PurgeReason::SHUFFLE_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned(), shuffle_id_1),
-            )))
-            .expect("");
-        assert_eq!(
-            false,
-            runtime.wait(tokio::fs::try_exists(format!(
-                "{}/{}/{}",
-                &temp_path, &app_id, shuffle_id_1
-            )))?
-        );
-        // the shuffle_id = 1 deletion will not effect shuffle_id = 13
-        let reading_ctx = ReadingIndexViewContext {
-            partition_id: uid_2.clone(),
+            .wait(local_store.insert(WritingViewContext::create_for_test(uid.clone(), blocks)))?;
+
+        // append the dangling entry beyond the data file and a partial entry
+        let (_, index_file_path) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let abs_index_file_path = format!("{}/{}", &temp_path, index_file_path);
+        let mut raw = std::fs::read(&abs_index_file_path)?;
+        let mut dangling = BytesMut::new();
+        IndexCodec::encode(
+            &IndexBlock {
+                offset: 20,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                block_id: 2,
+                task_attempt_id: 0,
+            },
+            &mut dangling,
+        )?;
+        raw.extend_from_slice(&dangling);
+        raw.extend_from_slice(&[0; 8]);
+        std::fs::write(&abs_index_file_path, &raw)?;
+
+        // case1: only the response is truncated
+        let get_index = || {
+            runtime.wait(local_store.get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+                entry_range: None,
+                deadline: None,
+            }))
         };
-        let reading_result = runtime.wait(local_store.get_index(reading_ctx)).expect("");
-        if let ResponseDataIndex::Local(index) = reading_result {
-            assert!(index.data_file_len > 0);
+        match get_index()? {
+            ResponseDataIndex::Local(index) => {
+                assert_eq!(20, index.data_file_len);
+                assert_eq!(2 * INDEX_BLOCK_SIZE, index.index_data.len());
+            }
+            _ => panic!(),
         }
+        assert_eq!(
+            raw.len(),
+            std::fs::metadata(&abs_index_file_path)?.len() as usize
+        );
 
-        // app level purge
-        runtime.wait(local_store.purge(&PurgeDataContext {
-            purge_reason: PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_owned()),
-        }))?;
+        // case2: the index file is rewritten by the repair
+        let outcome = runtime.wait(local_store.repair_index(&uid))?;
+        assert_eq!(3 * INDEX_BLOCK_SIZE + 8, outcome.index_len_before);
+        assert_eq!(2 * INDEX_BLOCK_SIZE, outcome.index_len_after);
         assert_eq!(
-            false,
-            runtime.wait(tokio::fs::try_exists(format!("{}/{}", &temp_path, &app_id)))?
+            2 * INDEX_BLOCK_SIZE,
+            std::fs::metadata(&abs_index_file_path)?.len() as usize
         );
 
         Ok(())
     }
 
     #[test]
-    #[ignore]
-    fn local_store_test() {
-        let temp_dir = tempdir::TempDir::new("test_local_store").unwrap();
+    fn test_reclaim_expired_files() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_reclaim_expired_files").unwrap();
         let temp_path = temp_dir.path().to_str().unwrap().to_string();
-        info!("init local file path: {}", temp_path);
-        let mut local_store = LocalFileStore::new(vec![temp_path]);
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        let zombie_uid = PartitionedUId::from("zombie-app".to_string(), 1, 0);
+        let alive_uid = PartitionedUId::from("alive-app".to_string(), 1, 0);
+        for uid in [&zombie_uid, &alive_uid] {
+            runtime.wait(local_store.insert(create_writing_ctx_by_uid(uid)))?;
+        }
+
+        // age all the files past the ttl
+        let max_age = Duration::from_secs(60 * 60);
+        let aged = std::time::SystemTime::now() - 2 * max_age;
+        for uid in [&zombie_uid, &alive_uid] {
+            let (data, index) = LocalFileStore::gen_relative_path_for_partition(uid);
+            for file in [data, index] {
+                std::fs::File::options()
+                    .write(true)
+                    .open(format!("{}/{}", &temp_path, file))?
+                    .set_modified(aged)?;
+            }
+        }
 
+        let reclaimed =
+            local_store.reclaim_expired_files(max_age, |app_id| app_id == "alive-app")?;
+        assert!(reclaimed > 0);
+        assert!(!Path::new(&format!("{}/zombie-app", &temp_path)).exists());
+        let (data, index) = LocalFileStore::gen_relative_path_for_partition(&alive_uid);
+        assert!(Path::new(&format!("{}/{}", &temp_path, data)).exists());
+        assert!(Path::new(&format!("{}/{}", &temp_path, index)).exists());
+
+        // the fresh files of zombie app are kept
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&zombie_uid)))?;
+        assert_eq!(0, local_store.reclaim_expired_files(max_age, |_| false)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_progress() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_purge_progress").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
         let runtime = local_store.runtime_manager.clone();
 
-        let uid = PartitionedUId {
-            app_id: "100".to_string(),
-            shuffle_id: 0,
-            partition_id: 0,
+        let app_id = "test_purge_progress";
+        let shuffle_number = 10;
+        for shuffle_id in 0..shuffle_number {
+            for partition_id in 0..3 {
+                let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+                runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+            }
+        }
+        let total_size: i64 = local_store
+            .partition_locks
+            .iter()
+            .map(|entry| runtime.wait(entry.value().read()).physical_len())
+            .sum();
+
+        // the resident size is decreased by every reported batch
+        let resident = Arc::new(AtomicI64::new(total_size));
+        let resident_snapshots = Arc::new(parking_lot::Mutex::new(vec![]));
+        let tracker = {
+            let resident = resident.clone();
+            let resident_snapshots = resident_snapshots.clone();
+            PurgeProgressTracker::new(move |batch: &PurgeBatch| {
+                assert!(batch.removed_bytes > 0);
+                let left = resident.fetch_sub(batch.removed_bytes, SeqCst) - batch.removed_bytes;
+                resident_snapshots.lock().push(left);
+            })
         };
+        let removed = runtime.wait(local_store.purge(&PurgeDataContext::with_progress_tracker(
+            &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            tracker.clone(),
+        )))?;
 
-        let data = b"hello world!hello china!";
-        let size = data.len();
-        let writing_ctx = WritingViewContext::create_for_test(
-            uid.clone(),
-            vec![
-                Block {
-                    block_id: 0,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-                Block {
-                    block_id: 1,
-                    length: size as i32,
-                    uncompress_length: 200,
-                    crc: 0,
-                    data: Bytes::copy_from_slice(data),
-                    task_attempt_id: 0,
-                },
-            ],
-        );
+        let resident_snapshots = resident_snapshots.lock().clone();
+        assert_eq!(shuffle_number as usize, resident_snapshots.len());
+        assert!(resident_snapshots.windows(2).all(|x| x[0] > x[1]));
+        assert_eq!(0, resident.load(SeqCst));
+        assert_eq!(total_size, removed);
 
-        let insert_result = runtime.wait(local_store.insert(writing_ctx));
-        if insert_result.is_err() {
-            println!("{:?}", insert_result.err());
-            panic!()
-        }
+        let progress = tracker.progress();
+        assert_eq!(total_size, progress.removed_bytes);
+        assert_eq!((shuffle_number * 3 * 2) as u64, progress.removed_files);
+        assert_eq!(Some(shuffle_number - 1), progress.current_shuffle_id);
+        assert!(!Path::new(&format!("{}/{}", &temp_path, app_id)).exists());
 
-        async fn get_and_check_partitial_data(
-            local_store: &mut LocalFileStore,
-            uid: PartitionedUId,
-            size: i64,
-            expected: &[u8],
-        ) {
-            let reading_ctx = ReadingViewContext {
-                uid,
-                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, size as i64),
-                serialized_expected_task_ids_bitmap: Default::default(),
-            };
+        Ok(())
+    }
 
-            let read_result = local_store.get(reading_ctx).await;
-            if read_result.is_err() {
-                error!("failed to get the localfile data: {:?}", read_result.err());
-                panic!()
-            }
+    #[test]
+    fn test_purge_with_parallelism() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_purge_with_parallelism").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path.clone()]);
+        config.purge_parallelism = 8;
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
 
-            match read_result.unwrap() {
-                ResponseData::Local(partitioned_data) => {
-                    assert_eq!(expected, partitioned_data.data.as_ref());
-                }
-                _ => panic!(),
+        let app_id = "test_purge_with_parallelism";
+        let shuffle_number = 16;
+        for shuffle_id in 0..shuffle_number {
+            for partition_id in 0..2 {
+                let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+                runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
             }
         }
+        let total_size: i64 = local_store
+            .partition_locks
+            .iter()
+            .map(|entry| runtime.wait(entry.value().read()).physical_len())
+            .sum();
 
-        // case1: read the one partition block data
-        runtime.wait(get_and_check_partitial_data(
-            &mut local_store,
-            uid.clone(),
-            size as i64,
-            data,
-        ));
+        // every deletion is slowed down, that the sequential purge takes 16 * 200ms at least.
+        let delete_latency_ms = 200;
+        FAULT_INJECTOR.enable(true);
+        FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(local_store.local_disks[0].root()),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::DELETE),
+            },
+            mode: FaultMode::LATENCY_MS(delete_latency_ms),
+            probability: 1.0,
+            ttl_sec: 30,
+        })?;
 
-        // case2: read the complete block data
-        let mut expected = BytesMut::with_capacity(size * 2);
-        expected.extend_from_slice(data);
-        expected.extend_from_slice(data);
-        runtime.wait(get_and_check_partitial_data(
-            &mut local_store,
-            uid.clone(),
-            size as i64 * 2,
-            expected.freeze().as_ref(),
-        ));
+        let tracker = PurgeProgressTracker::new(|_: &PurgeBatch| {});
+        let timer = std::time::Instant::now();
+        let removed = runtime.wait(local_store.purge(&PurgeDataContext::with_progress_tracker(
+            &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            tracker.clone(),
+        )))?;
+        let elapsed = timer.elapsed().as_millis() as u64;
+        assert!(
+            elapsed < shuffle_number as u64 * delete_latency_ms / 2,
+            "the purge costs {}ms",
+            elapsed
+        );
 
-        // case3: get the index data
-        let reading_index_view_ctx = ReadingIndexViewContext {
-            partition_id: uid.clone(),
-        };
-        let result = runtime.wait(local_store.get_index(reading_index_view_ctx));
-        if result.is_err() {
-            panic!()
-        }
+        assert_eq!(total_size, removed);
+        let progress = tracker.progress();
+        assert_eq!(total_size, progress.removed_bytes);
+        assert_eq!((shuffle_number * 2 * 2) as u64, progress.removed_files);
+        assert_eq!(Some(shuffle_number - 1), progress.current_shuffle_id);
+        assert_eq!(0, local_store.partition_locks.len());
+        assert!(!Path::new(&format!("{}/{}", &temp_path, app_id)).exists());
 
-        match result.unwrap() {
-            ResponseDataIndex::Local(data) => {
-                let mut index = data.index_data;
-                let offset_1 = index.get_i64();
-                assert_eq!(0, offset_1);
-                let length_1 = index.get_i32();
-                assert_eq!(size as i32, length_1);
-                index.get_i32();
-                index.get_i64();
-                let block_id_1 = index.get_i64();
-                assert_eq!(0, block_id_1);
-                let task_id = index.get_i64();
-                assert_eq!(0, task_id);
+        Ok(())
+    }
 
-                let offset_2 = index.get_i64();
-                assert_eq!(size as i64, offset_2);
-                assert_eq!(size as i32, index.get_i32());
-            }
+    #[test]
+    fn test_purge_cancelled_between_batches() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_purge_cancelled").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        let app_id = "test_purge_cancelled";
+        for shuffle_id in 0..3 {
+            let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, 0);
+            runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
         }
 
-        temp_dir.close().unwrap();
+        // cancel the purge after the first batch
+        let cancelled = Arc::new(parking_lot::Mutex::new(None::<PurgeProgressTracker>));
+        let tracker = {
+            let cancelled = cancelled.clone();
+            PurgeProgressTracker::new(move |_: &PurgeBatch| {
+                if let Some(tracker) = cancelled.lock().as_ref() {
+                    tracker.cancel();
+                }
+            })
+        };
+        let _ = cancelled.lock().replace(tracker.clone());
+        let purged = runtime.wait(local_store.purge(&PurgeDataContext::with_progress_tracker(
+            &PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER(app_id.to_string()),
+            tracker.clone(),
+        )));
+        match purged {
+            Err(e) => match e.downcast_ref::<WorkerError>() {
+                Some(WorkerError::PURGE_CANCELLED(_)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+            _ => panic!("the cancelled purge should fail"),
+        }
+
+        let progress = tracker.progress();
+        assert!(progress.cancelled);
+        assert_eq!(Some(0), progress.current_shuffle_id);
+        assert!(!Path::new(&format!("{}/{}/0", &temp_path, app_id)).exists());
+        assert!(Path::new(&format!("{}/{}/1", &temp_path, app_id)).exists());
+        assert!(Path::new(&format!("{}/{}/2", &temp_path, app_id)).exists());
+        // the rest partitions are still tracked to be purged again
+        assert_eq!(2, local_store.partition_locks.len());
+
+        Ok(())
     }
 
     #[test]
-    fn test_index_consistency() -> anyhow::Result<()> {
-        let temp_dir = tempdir::TempDir::new("test_index_consistency").unwrap();
+    fn test_get_index_with_index_ahead_of_data() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_get_index_ahead_of_data").unwrap();
         let temp_path = temp_dir.path().to_str().unwrap().to_string();
-        info!("init local file path: {}", temp_path);
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
 
-        let mut raw_bytes = BytesMut::new();
+        let uid = PartitionedUId::from("test_get_index_ahead_of_data".to_string(), 0, 0);
+        runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+
+        // the index entry is written ahead of the data
+        let (data_file_path, index_file_path) =
+            LocalFileStore::gen_relative_path_for_partition(&uid);
+        let mut ahead = BytesMut::new();
         IndexCodec::encode(
             &IndexBlock {
-                offset: 0,
-                length: 10,
-                uncompress_length: 0,
+                offset: 48,
+                length: 24,
+                uncompress_length: 24,
                 crc: 0,
-                block_id: 0,
+                block_id: 2,
                 task_attempt_id: 0,
             },
-            &mut raw_bytes,
+            &mut ahead,
         )?;
-        let data_file_len = 10;
+        let mut raw = std::fs::read(format!("{}/{}", &temp_path, &index_file_path))?;
+        raw.extend_from_slice(&ahead);
+        std::fs::write(format!("{}/{}", &temp_path, &index_file_path), &raw)?;
 
-        let raw_bytes = raw_bytes.freeze();
+        let get_index = |entry_range: Option<IndexEntryRange>| -> anyhow::Result<LocalDataIndex> {
+            match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+                entry_range,
+                deadline: None,
+            }))? {
+                ResponseDataIndex::Local(index) => Ok(index),
+            }
+        };
 
-        // case1: legal pass
-        assert_eq!(
-            true,
-            LocalFileStore::detect_index_inconsistency(
-                &raw_bytes,
-                data_file_len,
-                &"/".to_owned(),
-                &"i.1".to_owned(),
-                &"d.1".to_owned()
-            )?
-        );
+        // only the consistent prefix is returned for both the full and ranged reading
+        let index = get_index(None)?;
+        assert_eq!(48, index.data_file_len);
+        assert_eq!(2 * INDEX_BLOCK_SIZE, index.index_data.len());
+        let index = get_index(Some(IndexEntryRange {
+            start_entry: 1,
+            max_entries: 10,
+        }))?;
+        assert_eq!(INDEX_BLOCK_SIZE, index.index_data.len());
 
-        // case2: Illegal
-        let data_file_len = 9;
-        // create the index file in the dir of temp_path
-        let index_file_path = "app-1/patition-1.index";
-        let data_file_path = "app-1/partition-1.data";
+        // the truncation is logged once
+        let locked_obj = local_store.partition_locks.get(&data_file_path).unwrap();
+        assert!(runtime
+            .wait(locked_obj.read())
+            .index_truncation_logged
+            .load(SeqCst));
 
-        let abs_index_file_path = format!("{}/{}", &temp_path, index_file_path);
-        let abs_data_file_path = format!("{}/{}", &temp_path, data_file_path);
-        // create the empty file for the abs_index_file_path. empty file
-        std::fs::create_dir_all(Path::new(&abs_index_file_path).parent().unwrap())?;
-        std::fs::create_dir_all(Path::new(&abs_data_file_path).parent().unwrap())?;
-        std::fs::write(&abs_index_file_path, &raw_bytes)?;
-        std::fs::write(&abs_data_file_path, &raw_bytes)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_index_with_entry_range() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_get_index_with_entry_range").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        // 3 batches with 2 blocks, 6 index entries
+        let uid = PartitionedUId::from("test_get_index_with_entry_range".to_string(), 0, 0);
+        for _ in 0..3 {
+            runtime.wait(local_store.insert(create_writing_ctx_by_uid(&uid)))?;
+        }
+
+        let get_index = |entry_range: Option<IndexEntryRange>| -> anyhow::Result<LocalDataIndex> {
+            match runtime.wait(local_store.get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+                entry_range,
+                deadline: None,
+            }))? {
+                ResponseDataIndex::Local(index) => Ok(index),
+            }
+        };
+        let range = |start_entry: u64, max_entries: u64| {
+            Some(IndexEntryRange {
+                start_entry,
+                max_entries,
+            })
+        };
 
+        let full = get_index(None)?;
+        assert_eq!(6 * INDEX_BLOCK_SIZE, full.index_data.len());
+        assert_eq!(full.index_data.len() as i64, full.index_len);
+
+        // case1: the middle entries
+        let partial = get_index(range(1, 2))?;
         assert_eq!(
-            false,
-            LocalFileStore::detect_index_inconsistency(
-                &raw_bytes,
-                data_file_len,
-                &temp_path,
-                &index_file_path.to_owned(),
-                &data_file_path.to_owned()
-            )?
+            full.index_data
+                .slice(INDEX_BLOCK_SIZE..3 * INDEX_BLOCK_SIZE),
+            partial.index_data
+        );
+        assert_eq!(full.index_len, partial.index_len);
+        assert_eq!(full.data_file_len, partial.data_file_len);
+
+        // case2: the tail entries are cut by the end of index
+        let partial = get_index(range(4, 10))?;
+        assert_eq!(
+            full.index_data.slice(4 * INDEX_BLOCK_SIZE..),
+            partial.index_data
         );
 
+        // case3: the range beyond the end of index is empty rather than error
+        let partial = get_index(range(6, 10))?;
+        assert!(partial.index_data.is_empty());
+        assert_eq!(full.index_len, partial.index_len);
+        let partial = get_index(range(100, 10))?;
+        assert!(partial.index_data.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_deadline() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_read_deadline").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let local_store = LocalFileStore::new(vec![temp_path.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId::from("test_read_deadline".to_string(), 0, 0);
+        let len = 8 * 1024 * 1024;
+        runtime.wait(local_store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![create_block(0, &vec![b'a'; len])],
+        )))?;
+        let reading_ctx = |deadline: Option<std::time::Instant>| ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, len as i64),
+            serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline,
+        };
+
+        // the disk reading is hung, which is given up by the request deadline
+        let disk = local_store.local_disks[0].clone();
+        FAULT_INJECTOR.enable(true);
+        let fault_id = FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(disk.root()),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::READ),
+            },
+            mode: FaultMode::LATENCY_MS(5000),
+            probability: 1.0,
+            ttl_sec: 30,
+        })?;
+        let start = std::time::Instant::now();
+        let deadline = std::time::Instant::now() + Duration::from_millis(1);
+        match runtime.wait(local_store.get(reading_ctx(Some(deadline)))) {
+            Err(WorkerError::DEADLINE_EXCEEDED) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        // the in-flight io and the partition lock are released promptly
+        assert_eq!(0, disk.io_pressure()?.0);
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
+        let lock = local_store
+            .partition_locks
+            .get(&data_file_path)
+            .unwrap()
+            .clone();
+        assert!(lock.try_write().is_ok());
+
+        // the exceeded deadline fails fast without touching the disk
+        FAULT_INJECTOR.remove(fault_id);
+        match runtime.wait(local_store.get(reading_ctx(Some(deadline)))) {
+            Err(WorkerError::DEADLINE_EXCEEDED) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match runtime.wait(local_store.get(reading_ctx(None)))? {
+            ResponseData::Local(data) => assert_eq!(vec![b'a'; len], data.data),
+            _ => panic!("unexpected data"),
+        }
+
         Ok(())
     }
 }
@@ -1,22 +1,54 @@
 use crate::composed_bytes;
 use crate::composed_bytes::ComposedBytes;
 use crate::constant::INVALID_BLOCK_ID;
+use crate::metric::GAUGE_MEMORY_BUFFER_SLACK_BYTES;
 use crate::store::BytesWrapper;
 use crate::store::{Block, DataSegment, PartitionedMemoryData};
 use anyhow::Result;
 use croaring::Treemap;
 use fastrace::trace;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
 use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
+use std::time::Instant;
 
 pub struct MemoryBuffer {
     buffer: RwLock<BufferInternal>,
 }
 
+// the small capacity is not worth to be shrunk, to avoid the re-allocation churn.
+const MIN_SHRINK_CAPACITY: usize = 16;
+
+// the block ids of the recently cleared flights are kept to resume the paged reads
+// anchored on the flushed blocks, and the older ones fall back to read from the start.
+const MAX_TRACKED_CLEARED_FLIGHTS: usize = 64;
+
+/// The capacity sizing of the buffer. The staging batches start empty and grow
+/// geometrically, and are pre-sized with the batch number of the previous spill.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferCapacityOptions {
+    pub max_initial_batches: usize,
+    pub shrink_ratio: f64,
+}
+
+impl Default for BufferCapacityOptions {
+    fn default() -> Self {
+        Self {
+            max_initial_batches: 64,
+            shrink_ratio: 0.25,
+        }
+    }
+}
+
+impl BufferCapacityOptions {
+    fn should_shrink(&self, len: usize, capacity: usize) -> bool {
+        capacity > MIN_SHRINK_CAPACITY && (len as f64) < capacity as f64 * self.shrink_ratio
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct BatchMemoryBlock(Vec<Vec<Block>>);
 impl Deref for BatchMemoryBlock {
@@ -75,10 +107,24 @@ pub struct BufferInternal {
 
     flight: HashMap<u64, Arc<BatchMemoryBlock>>,
     flight_counter: u64,
+
+    // the time of the earliest staging data that is not spilled
+    staging_since: Option<Instant>,
+
+    capacity_options: BufferCapacityOptions,
+    staging_grow_times: u64,
+    // the slack bytes that have been reported into the gauge
+    slack_bytes: i64,
+
+    // the flight id and the block ids of the recently cleared flights
+    cleared_flights: VecDeque<(u64, Treemap)>,
+
+    // the monotonic sequence of the appended batches, which is kept across the spills
+    write_sequence: u64,
 }
 
 impl BufferInternal {
-    fn new() -> Self {
+    fn new(capacity_options: BufferCapacityOptions) -> Self {
         BufferInternal {
             total_size: 0,
             staging_size: 0,
@@ -86,17 +132,127 @@ impl BufferInternal {
             staging: Default::default(),
             flight: Default::default(),
             flight_counter: 0,
+            staging_since: None,
+            capacity_options,
+            staging_grow_times: 0,
+            slack_bytes: 0,
+            cleared_flights: VecDeque::new(),
+            write_sequence: 0,
+        }
+    }
+
+    /// The batches in the sequence order. Every block has the stable sequence number of
+    /// (batch id, position in batch), that the batch id is the flight id, and the staging
+    /// takes the next flight id which is kept after it's spilled. So the sequence is never
+    /// changed by the concurrent appends, spills and clears.
+    fn ordered_batches(&self) -> Vec<(u64, &BatchMemoryBlock)> {
+        let mut batches: Vec<_> = self
+            .flight
+            .iter()
+            .map(|(flight_id, batch)| (*flight_id, batch.deref()))
+            .collect();
+        batches.sort_unstable_by_key(|(flight_id, _)| *flight_id);
+        batches.push((self.flight_counter, &self.staging));
+        batches
+    }
+
+    /// Locates the sequence number of the last read block. If the block has been cleared,
+    /// all the blocks of its flight are treated as read. None means to read from the start.
+    fn locate(&self, batches: &[(u64, &BatchMemoryBlock)], block_id: i64) -> Option<(u64, usize)> {
+        if block_id == INVALID_BLOCK_ID {
+            return None;
+        }
+        for (batch_id, batch) in batches {
+            if let Some(pos) = batch
+                .iter()
+                .flat_map(|blocks| blocks.iter())
+                .position(|block| block.block_id == block_id)
+            {
+                return Some((*batch_id, pos));
+            }
         }
+        self.cleared_flights
+            .iter()
+            .rev()
+            .find(|(_, block_ids)| block_ids.contains(block_id as u64))
+            .map(|(flight_id, _)| (*flight_id, usize::MAX))
+    }
+
+    /// Collects the blocks after the last read block in the sequence order, which are
+    /// bounded by the batch_len and the max_segments. The flag of whether more blocks
+    /// remain in memory is also returned.
+    fn read_after(
+        &self,
+        last_block_id: i64,
+        batch_len: i64,
+        task_ids: &Option<Treemap>,
+        max_segments: usize,
+    ) -> (Vec<&Block>, i64, bool) {
+        let batches = self.ordered_batches();
+        let last_read = self.locate(&batches, last_block_id);
+
+        let mut read_result = vec![];
+        let mut read_len = 0i64;
+        for (batch_id, batch) in batches {
+            for (pos, block) in batch.iter().flat_map(|blocks| blocks.iter()).enumerate() {
+                if let Some(last_read) = last_read {
+                    if (batch_id, pos) <= last_read {
+                        continue;
+                    }
+                }
+                if let Some(expected_task_id) = task_ids {
+                    if !expected_task_id.contains(block.task_attempt_id as u64) {
+                        continue;
+                    }
+                }
+                if read_len >= batch_len || read_result.len() > max_segments {
+                    return (read_result, read_len, true);
+                }
+                read_len += block.length as i64;
+                read_result.push(block);
+            }
+        }
+        (read_result, read_len, false)
+    }
+
+    fn refresh_slack_bytes(&mut self) {
+        let staging_slack =
+            (self.staging.capacity() - self.staging.len()) * mem::size_of::<Vec<Block>>();
+        let flight_slack = (self.flight.capacity() - self.flight.len())
+            * mem::size_of::<(u64, Arc<BatchMemoryBlock>)>();
+        let slack_bytes = (staging_slack + flight_slack) as i64;
+        GAUGE_MEMORY_BUFFER_SLACK_BYTES.add(slack_bytes - self.slack_bytes);
+        self.slack_bytes = slack_bytes;
+    }
+}
+
+impl Drop for MemoryBuffer {
+    fn drop(&mut self) {
+        GAUGE_MEMORY_BUFFER_SLACK_BYTES.sub(self.buffer.get_mut().slack_bytes);
     }
 }
 
 impl MemoryBuffer {
     pub fn new() -> MemoryBuffer {
+        MemoryBuffer::with_capacity_options(Default::default())
+    }
+
+    pub fn with_capacity_options(capacity_options: BufferCapacityOptions) -> MemoryBuffer {
         MemoryBuffer {
-            buffer: RwLock::new(BufferInternal::new()),
+            buffer: RwLock::new(BufferInternal::new(capacity_options)),
         }
     }
 
+    /// the allocated but unused capacity bytes of this buffer.
+    pub fn slack_bytes(&self) -> i64 {
+        self.buffer.read().slack_bytes
+    }
+
+    /// the re-allocation times of the staging batches.
+    pub fn staging_grow_times(&self) -> u64 {
+        self.buffer.read().staging_grow_times
+    }
+
     #[trace]
     pub fn total_size(&self) -> Result<i64> {
         return Ok(self.buffer.read().total_size);
@@ -112,6 +268,22 @@ impl MemoryBuffer {
         return Ok(self.buffer.read().staging_size);
     }
 
+    /// the write sequence of the latest appended batch, 0 if nothing is appended.
+    pub fn write_sequence(&self) -> u64 {
+        self.buffer.read().write_sequence
+    }
+
+    /// the time of the earliest staging data, which is None if no staging data.
+    pub fn staging_since(&self) -> Option<Instant> {
+        self.buffer.read().staging_since
+    }
+
+    /// the number of the spilled batches that are still not flushed.
+    #[trace]
+    pub fn flight_batch_num(&self) -> Result<u64> {
+        return Ok(self.buffer.read().flight.len() as u64);
+    }
+
     #[trace]
     pub fn clear(&self, flight_id: u64, flight_size: u64) -> Result<()> {
         let mut buffer = self.buffer.write();
@@ -120,85 +292,80 @@ impl MemoryBuffer {
         if let Some(block_ref) = removed {
             buffer.total_size -= flight_size as i64;
             buffer.flight_size -= flight_size as i64;
+
+            let mut block_ids = Treemap::new();
+            for block in block_ref.iter().flat_map(|blocks| blocks.iter()) {
+                block_ids.add(block.block_id as u64);
+            }
+            if buffer.cleared_flights.len() >= MAX_TRACKED_CLEARED_FLIGHTS {
+                buffer.cleared_flights.pop_front();
+            }
+            buffer.cleared_flights.push_back((flight_id, block_ids));
+        }
+        if buffer
+            .capacity_options
+            .should_shrink(buffer.flight.len(), buffer.flight.capacity())
+        {
+            buffer.flight.shrink_to_fit();
         }
+        buffer.refresh_slack_bytes();
         Ok(())
     }
 
+    /// The order-independent sum of the crc of the in-flight and staging blocks.
+    pub fn crc_aggregate(&self) -> i64 {
+        let buffer = self.buffer.read();
+        buffer
+            .flight
+            .values()
+            .flat_map(|batch| batch.iter())
+            .chain(buffer.staging.iter())
+            .flat_map(|blocks| blocks.iter())
+            .fold(0i64, |acc, block| acc.wrapping_add(block.crc))
+    }
+
+    /// The block ids of the in-flight and staging blocks.
+    pub fn block_ids(&self) -> Vec<i64> {
+        let buffer = self.buffer.read();
+        buffer
+            .flight
+            .values()
+            .flat_map(|batch| batch.iter())
+            .chain(buffer.staging.iter())
+            .flat_map(|blocks| blocks.iter())
+            .map(|block| block.block_id)
+            .collect()
+    }
+
+    /// Finds the block from the in-flight and staging blocks.
+    pub fn get_block(&self, block_id: i64) -> Option<Block> {
+        let buffer = self.buffer.read();
+        buffer
+            .flight
+            .values()
+            .flat_map(|batch| batch.iter())
+            .chain(buffer.staging.iter())
+            .flat_map(|blocks| blocks.iter())
+            .find(|block| block.block_id == block_id)
+            .cloned()
+    }
+
+    /// The collected blocks are bounded by the max_segments, and at most max_segments + 1
+    /// blocks are returned to indicate the limit is exceeded.
     pub fn get_v2(
         &self,
         last_block_id: i64,
         batch_len: i64,
         task_ids: Option<Treemap>,
+        max_segments: usize,
+        latest_attempt_only: bool,
     ) -> Result<PartitionedMemoryData> {
-        /// read sequence
-        /// 1. from flight (expect: last_block_id not found or last_block_id == -1)
-        /// 2. from staging
         let buffer = self.buffer.read();
+        let (mut read_result, _, has_more) =
+            buffer.read_after(last_block_id, batch_len, &task_ids, max_segments);
 
-        let mut read_result = vec![];
-        let mut read_len = 0i64;
-        let mut flight_found = false;
-
-        let mut exit = false;
-        while !exit {
-            exit = true;
-            {
-                if last_block_id == INVALID_BLOCK_ID {
-                    flight_found = true;
-                }
-                for (_, batch_block) in buffer.flight.iter() {
-                    for blocks in batch_block.iter() {
-                        for block in blocks {
-                            if !flight_found && block.block_id == last_block_id {
-                                flight_found = true;
-                                continue;
-                            }
-                            if !flight_found {
-                                continue;
-                            }
-                            if read_len >= batch_len {
-                                break;
-                            }
-                            if let Some(ref expected_task_id) = task_ids {
-                                if !expected_task_id.contains(block.task_attempt_id as u64) {
-                                    continue;
-                                }
-                            }
-                            read_len += block.length as i64;
-                            read_result.push(block);
-                        }
-                    }
-                }
-            }
-
-            {
-                for blocks in buffer.staging.iter() {
-                    for block in blocks {
-                        if !flight_found && block.block_id == last_block_id {
-                            flight_found = true;
-                            continue;
-                        }
-                        if !flight_found {
-                            continue;
-                        }
-                        if read_len >= batch_len {
-                            break;
-                        }
-                        if let Some(ref expected_task_id) = task_ids {
-                            if !expected_task_id.contains(block.task_attempt_id as u64) {
-                                continue;
-                            }
-                        }
-                        read_len += block.length as i64;
-                        read_result.push(block);
-                    }
-                }
-            }
-
-            if !flight_found {
-                flight_found = true;
-                exit = false;
-            }
+        if latest_attempt_only {
+            read_result = Self::retain_latest_attempt(read_result);
         }
 
         let mut block_bytes = Vec::with_capacity(read_result.len());
@@ -218,93 +385,53 @@ impl MemoryBuffer {
             offset += block.length as i64;
         }
 
-        let composed_bytes = ComposedBytes::from(block_bytes, offset as usize);
+        let composed_bytes = ComposedBytes::from(block_bytes);
         Ok(PartitionedMemoryData {
             shuffle_data_block_segments: segments,
             data: BytesWrapper::Composed(composed_bytes),
+            has_more,
         })
     }
 
+    /// Keeps the block of the highest task attempt id for the duplicate block ids, and
+    /// the first one is kept if the same attempt has been written more than once.
+    fn retain_latest_attempt(blocks: Vec<&Block>) -> Vec<&Block> {
+        let mut latest_attempts: HashMap<i64, i64> = HashMap::with_capacity(blocks.len());
+        for block in blocks.iter() {
+            let attempt = latest_attempts
+                .entry(block.block_id)
+                .or_insert(block.task_attempt_id);
+            if *attempt < block.task_attempt_id {
+                *attempt = block.task_attempt_id;
+            }
+        }
+        blocks
+            .into_iter()
+            .filter(|block| {
+                if latest_attempts.get(&block.block_id) == Some(&block.task_attempt_id) {
+                    // the retained one is removed to skip the later duplicates
+                    latest_attempts.remove(&block.block_id);
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect()
+    }
+
     pub fn get(
         &self,
         last_block_id: i64,
         batch_len: i64,
         task_ids: Option<Treemap>,
     ) -> Result<BufferReadResult> {
-        /// read sequence
-        /// 1. from flight (expect: last_block_id not found or last_block_id == 0)
-        /// 2. from staging
         let buffer = self.buffer.read();
-
-        let mut read_result = vec![];
-        let mut read_len = 0i64;
-        let mut flight_found = false;
-
-        let mut exit = false;
-        while !exit {
-            exit = true;
-            {
-                if last_block_id == INVALID_BLOCK_ID {
-                    flight_found = true;
-                }
-                for (_, batch_block) in buffer.flight.iter() {
-                    for blocks in batch_block.iter() {
-                        for block in blocks {
-                            if !flight_found && block.block_id == last_block_id {
-                                flight_found = true;
-                                continue;
-                            }
-                            if !flight_found {
-                                continue;
-                            }
-                            if read_len >= batch_len {
-                                break;
-                            }
-                            if let Some(ref expected_task_id) = task_ids {
-                                if !expected_task_id.contains(block.task_attempt_id as u64) {
-                                    continue;
-                                }
-                            }
-                            read_len += block.length as i64;
-                            read_result.push(block.clone());
-                        }
-                    }
-                }
-            }
-
-            {
-                for blocks in buffer.staging.iter() {
-                    for block in blocks {
-                        if !flight_found && block.block_id == last_block_id {
-                            flight_found = true;
-                            continue;
-                        }
-                        if !flight_found {
-                            continue;
-                        }
-                        if read_len >= batch_len {
-                            break;
-                        }
-                        if let Some(ref expected_task_id) = task_ids {
-                            if !expected_task_id.contains(block.task_attempt_id as u64) {
-                                continue;
-                            }
-                        }
-                        read_len += block.length as i64;
-                        read_result.push(block.clone());
-                    }
-                }
-            }
-
-            if !flight_found {
-                flight_found = true;
-                exit = false;
-            }
-        }
+        let (read_result, read_len, _) =
+            buffer.read_after(last_block_id, batch_len, &task_ids, usize::MAX);
 
         Ok(BufferReadResult {
             read_len: read_len as u64,
-            blocks: read_result,
+            blocks: read_result.into_iter().cloned().collect(),
         })
     }
 
@@ -315,7 +442,24 @@ impl MemoryBuffer {
             return Ok(None);
         }
 
-        let staging: BatchMemoryBlock = { mem::replace(&mut buffer.staging, Default::default()) };
+        // pre-size with the batch number of this spill to avoid the repeated re-allocation
+        let initial_batches = buffer
+            .staging
+            .len()
+            .min(buffer.capacity_options.max_initial_batches);
+        let mut staging: BatchMemoryBlock = {
+            mem::replace(
+                &mut buffer.staging,
+                BatchMemoryBlock(Vec::with_capacity(initial_batches)),
+            )
+        };
+        // the spilled batches are immutable, so the excess capacity is useless.
+        if buffer
+            .capacity_options
+            .should_shrink(staging.len(), staging.capacity())
+        {
+            staging.shrink_to_fit();
+        }
         let staging_ref = Arc::new(staging);
         let flight_id = buffer.flight_counter;
 
@@ -326,6 +470,8 @@ impl MemoryBuffer {
         buffer.flight_counter += 1;
         buffer.flight_size += spill_size;
         buffer.staging_size = 0;
+        buffer.staging_since = None;
+        buffer.refresh_slack_bytes();
 
         Ok(Some(BufferSpillResult {
             flight_id,
@@ -335,21 +481,31 @@ impl MemoryBuffer {
     }
 
     #[trace]
-    pub fn append(&self, blocks: Vec<Block>, size: u64) -> Result<()> {
+    /// Appends the blocks into the staging, and returns the write sequence of this batch.
+    /// All the batches with the smaller or equal sequence are visible once returned.
+    pub fn append(&self, blocks: Vec<Block>, size: u64) -> Result<u64> {
         let mut buffer = self.buffer.write();
+        if buffer.staging.len() == buffer.staging.capacity() {
+            buffer.staging_grow_times += 1;
+        }
         let mut staging = &mut buffer.staging;
         staging.push(blocks);
 
         buffer.staging_size += size as i64;
         buffer.total_size += size as i64;
+        if buffer.staging_since.is_none() {
+            buffer.staging_since = Some(Instant::now());
+        }
+        buffer.refresh_slack_bytes();
+        buffer.write_sequence += 1;
 
-        Ok(())
+        Ok(buffer.write_sequence)
     }
 }
 
 /// for tests.
 impl MemoryBuffer {
-    fn direct_push(&self, blocks: Vec<Block>) -> Result<()> {
+    fn direct_push(&self, blocks: Vec<Block>) -> Result<u64> {
         let len: u64 = blocks.iter().map(|block| block.length).sum::<i32>() as u64;
         self.append(blocks, len)
     }
@@ -357,12 +513,14 @@ impl MemoryBuffer {
 
 #[cfg(test)]
 mod test {
-    use crate::store::mem::buffer::MemoryBuffer;
+    use crate::store::mem::buffer::{BufferCapacityOptions, MemoryBuffer};
     use crate::store::Block;
     use hashlink::LinkedHashMap;
-    use std::collections::LinkedList;
+    use std::collections::{HashSet, LinkedList, VecDeque};
+    use std::mem;
     use std::ops::Deref;
-    use std::sync::RwLock;
+    use std::sync::{Arc, Mutex, RwLock};
+    use std::thread;
 
     fn create_blocks(start_block_idx: i32, cnt: i32, block_len: i32) -> Vec<Block> {
         let mut blocks = vec![];
@@ -404,7 +562,7 @@ mod test {
             if cnt > 1 {
                 panic!();
             }
-            let mem_data = &buffer.get_v2(last_block_id, 19, None)?;
+            let mem_data = &buffer.get_v2(last_block_id, 19, None, usize::MAX, false)?;
             let segs = &mem_data.shuffle_data_block_segments;
             if segs.len() > 0 {
                 let last = segs.get(segs.len() - 1).unwrap();
@@ -450,6 +608,7 @@ mod test {
         assert_eq!(10 * 10 * 2, buffer.total_size()?);
         assert_eq!(10 * 10 * 2, buffer.flight_size()?);
         assert_eq!(0, buffer.staging_size()?);
+        assert_eq!(1, buffer.flight_batch_num()?);
 
         /// case4: write blocks into staging and then reading
         buffer.direct_push(create_blocks(20, 10, 10))?;
@@ -488,7 +647,158 @@ mod test {
         assert_eq!(10 * 10, buffer.total_size()?);
         assert_eq!(10 * 10, buffer.staging_size()?);
         assert_eq!(0, buffer.flight_size()?);
+        assert_eq!(0, buffer.flight_batch_num()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paged_read_across_spill_and_clear() -> anyhow::Result<()> {
+        let buffer = MemoryBuffer::new();
+        buffer.direct_push(create_blocks(0, 10, 10))?;
+        let spill_result = buffer.spill()?.unwrap();
+        buffer.direct_push(create_blocks(10, 10, 10))?;
 
+        // case1: the first page of the flight
+        let data = buffer.get_v2(-1, 50, None, usize::MAX, false)?;
+        let segments = &data.shuffle_data_block_segments;
+        assert_eq!(5, segments.len());
+        assert_eq!(4, segments.last().unwrap().block_id);
+        assert!(data.has_more);
+
+        // case2: the anchored flight is cleared, the following blocks are read from staging
+        buffer.clear(spill_result.flight_id(), spill_result.flight_len())?;
+        buffer.direct_push(create_blocks(20, 5, 10))?;
+        let data = buffer.get_v2(4, 100, None, usize::MAX, false)?;
+        let segments = &data.shuffle_data_block_segments;
+        assert_eq!(10, segments.first().unwrap().block_id);
+        assert_eq!(19, segments.last().unwrap().block_id);
+        assert!(data.has_more);
+
+        // case3: the staging is spilled between the pages, the sequence is kept
+        let _ = buffer.spill()?.unwrap();
+        buffer.direct_push(create_blocks(25, 5, 10))?;
+        let data = buffer.get_v2(19, 1000, None, usize::MAX, false)?;
+        let segments = &data.shuffle_data_block_segments;
+        assert_eq!(
+            (20..30).collect::<Vec<i64>>(),
+            segments.iter().map(|x| x.block_id).collect::<Vec<i64>>()
+        );
+        assert!(!data.has_more);
+
+        // case4: the last page has no more data
+        let data = buffer.get_v2(29, 1000, None, usize::MAX, false)?;
+        assert_eq!(0, data.shuffle_data_block_segments.len());
+        assert!(!data.has_more);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paged_read_with_concurrent_insert() -> anyhow::Result<()> {
+        let buffer = Arc::new(MemoryBuffer::new());
+        let block_number = 2000;
+        let cleared_block_ids = Arc::new(Mutex::new(HashSet::new()));
+
+        let writer = {
+            let buffer = buffer.clone();
+            let cleared_block_ids = cleared_block_ids.clone();
+            thread::spawn(move || {
+                let mut pending_spills = VecDeque::new();
+                for idx in 0..block_number {
+                    buffer.direct_push(create_blocks(idx, 1, 10)).unwrap();
+                    if idx % 50 == 49 {
+                        pending_spills.push_back(buffer.spill().unwrap().unwrap());
+                    }
+                    // the flushed flights are removed from memory with some lag
+                    if pending_spills.len() > 2 {
+                        let spill_result = pending_spills.pop_front().unwrap();
+                        let mut cleared = cleared_block_ids.lock().unwrap();
+                        for blocks in spill_result.blocks().iter() {
+                            cleared.extend(blocks.iter().map(|block| block.block_id));
+                        }
+                        buffer
+                            .clear(spill_result.flight_id(), spill_result.flight_len())
+                            .unwrap();
+                    }
+                }
+            })
+        };
+
+        let mut read_block_ids = vec![];
+        let mut last_block_id = -1;
+        loop {
+            let writer_finished = writer.is_finished();
+            let data = buffer.get_v2(last_block_id, 35, None, usize::MAX, false)?;
+            let segments = &data.shuffle_data_block_segments;
+            if let Some(last) = segments.last() {
+                last_block_id = last.block_id;
+            }
+            read_block_ids.extend(segments.iter().map(|x| x.block_id));
+            if writer_finished && !data.has_more {
+                break;
+            }
+        }
+        writer.join().unwrap();
+
+        // no duplicates and in the written order
+        assert!(read_block_ids.windows(2).all(|x| x[0] < x[1]));
+        // no skip, the unread blocks must be flushed before being read
+        let cleared_block_ids = cleared_block_ids.lock().unwrap();
+        let read_block_ids: HashSet<i64> = read_block_ids.into_iter().collect();
+        for block_id in 0..block_number as i64 {
+            assert!(
+                read_block_ids.contains(&block_id) || cleared_block_ids.contains(&block_id),
+                "the block:{} is skipped",
+                block_id
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_capacity() -> anyhow::Result<()> {
+        let options = BufferCapacityOptions {
+            max_initial_batches: 64,
+            shrink_ratio: 0.25,
+        };
+
+        // case1: the tiny partitions start empty without any pre-allocation
+        let buffers: Vec<_> = (0..100)
+            .map(|_| MemoryBuffer::with_capacity_options(options))
+            .collect();
+        for (idx, buffer) in buffers.iter().enumerate() {
+            assert_eq!(0, buffer.slack_bytes());
+            buffer.direct_push(create_blocks(idx as i32, 1, 10))?;
+            assert_eq!(1, buffer.staging_grow_times());
+        }
+        let slack_bytes: i64 = buffers.iter().map(|x| x.slack_bytes()).sum();
+        assert!(slack_bytes <= 100 * 3 * mem::size_of::<Vec<Block>>() as i64);
+
+        // case2: the huge partition is pre-sized with the previous spill
+        let buffer = MemoryBuffer::with_capacity_options(options);
+        for idx in 0..1000 {
+            buffer.direct_push(create_blocks(idx, 1, 10))?;
+        }
+        let first_grow_times = buffer.staging_grow_times();
+        let spill_result = buffer.spill()?.unwrap();
+        buffer.clear(spill_result.flight_id(), spill_result.flight_len())?;
+        for idx in 0..1000 {
+            buffer.direct_push(create_blocks(idx, 1, 10))?;
+        }
+        let second_grow_times = buffer.staging_grow_times() - first_grow_times;
+        assert!(second_grow_times < first_grow_times);
+        assert_eq!(4, second_grow_times);
+
+        // case3: the excess capacity is released after spill
+        let _ = buffer.spill()?.unwrap();
+        buffer.direct_push(create_blocks(0, 1, 10))?;
+        buffer.direct_push(create_blocks(1, 1, 10))?;
+        let slack_before_spill = buffer.slack_bytes();
+        let spill_result = buffer.spill()?.unwrap();
+        assert_eq!(2, spill_result.blocks().capacity());
+        assert!(slack_before_spill > 0);
         Ok(())
     }
 
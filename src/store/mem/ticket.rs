@@ -51,7 +51,7 @@ impl Ticket {
     }
 
     pub fn is_timeout(&self, timeout_sec: i64) -> bool {
-        (crate::util::now_timestamp_as_sec() - self.created_time) as i64 > timeout_sec
+        crate::util::now_timestamp_as_sec().saturating_sub(self.created_time) as i64 > timeout_sec
     }
 
     pub fn id(&self) -> i64 {
@@ -193,12 +193,14 @@ impl TicketManager {
 
 #[cfg(test)]
 mod test {
+    use crate::metric::TOTAL_EVICT_TIMEOUT_TICKETS_NUM;
     use crate::runtime::manager::RuntimeManager;
     use crate::store::mem::ticket::TicketManager;
     use dashmap::DashMap;
     use std::sync::{Arc, Mutex};
     use std::thread;
     use std::thread::JoinHandle;
+    use std::time::Duration;
 
     #[test]
     fn test_closure() {
@@ -249,10 +251,12 @@ mod test {
         ticket_manager.delete_by_app_id(app_id);
         assert!(!ticket_manager.exist(2));
 
-        // case4
-        // ticket_manager.insert(3, 10, crate::util::current_timestamp_sec() + 1, app_id);
-        // assert!(ticket_manager.exist(3));
-        // awaitility::at_most(Duration::from_secs(5)).until(|| !ticket_manager.exist(3));
-        // assert_eq!(10, *released_size.lock().unwrap());
+        // case4: the expired ticket should be released by the checker
+        let evicted = TOTAL_EVICT_TIMEOUT_TICKETS_NUM.get();
+        ticket_manager.insert(3, 10, crate::util::now_timestamp_as_sec(), app_id);
+        assert!(ticket_manager.exist(3));
+        awaitility::at_most(Duration::from_secs(5)).until(|| !ticket_manager.exist(3));
+        assert_eq!(10, *released_size.lock().unwrap());
+        assert!(TOTAL_EVICT_TIMEOUT_TICKETS_NUM.get() > evicted);
     }
 }
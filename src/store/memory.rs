@@ -15,27 +15,32 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::app::ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE;
+use crate::app::ReadingOptions::{
+    MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE, MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE,
+};
 use crate::app::{
     PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingViewContext,
     RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
 };
-use crate::config::{MemoryStoreConfig, StorageType};
+use crate::config::{MemoryStoreConfig, SpillCandidateSelectPolicy, StorageType};
 use crate::error::WorkerError;
-use crate::metric::TOTAL_MEMORY_USED;
+use crate::metric::{TOTAL_MEMORY_DATA_CRC_MISMATCH, TOTAL_MEMORY_USED};
 use crate::readable_size::ReadableSize;
-use crate::store::{Block, RequireBufferResponse, ResponseData, ResponseDataIndex, Store};
+use crate::store::{
+    Block, BytesWrapper, PartitionedMemoryData, RequireBufferResponse, ResponseData,
+    ResponseDataIndex, Store,
+};
+use crate::util::{check_deadline, get_crc};
 use crate::*;
 use async_trait::async_trait;
 use dashmap::DashMap;
 
-use std::collections::{BTreeMap, HashMap};
 use std::hash::BuildHasherDefault;
 
 use std::str::FromStr;
 
 use crate::store::mem::budget::MemoryBudget;
-use crate::store::mem::buffer::MemoryBuffer;
+use crate::store::mem::buffer::{BufferCapacityOptions, MemoryBuffer};
 use crate::store::mem::capacity::CapacitySnapshot;
 use crate::store::mem::ticket::TicketManager;
 use crate::store::spill::SpillWritingViewContext;
@@ -43,7 +48,7 @@ use anyhow::anyhow;
 use croaring::Treemap;
 use fastrace::trace;
 use fxhash::{FxBuildHasher, FxHasher};
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 use std::sync::Arc;
 
 pub struct MemoryStore {
@@ -52,6 +57,9 @@ pub struct MemoryStore {
     budget: MemoryBudget,
     runtime_manager: RuntimeManager,
     ticket_manager: TicketManager,
+    max_segments_per_response: usize,
+    buffer_capacity_options: BufferCapacityOptions,
+    read_crc_verify_enable: bool,
 }
 
 unsafe impl Send for MemoryStore {}
@@ -75,6 +83,9 @@ impl MemoryStore {
             memory_capacity: max_memory_size,
             ticket_manager,
             runtime_manager,
+            max_segments_per_response: usize::MAX,
+            buffer_capacity_options: Default::default(),
+            read_crc_verify_enable: false,
         }
     }
 
@@ -99,17 +110,59 @@ impl MemoryStore {
 
         MemoryStore {
             state: dashmap,
-            budget: MemoryBudget::new(capacity.as_bytes() as i64),
+            budget,
             memory_capacity: capacity.as_bytes() as i64,
             ticket_manager,
             runtime_manager,
+            max_segments_per_response: conf.max_segments_per_response,
+            buffer_capacity_options: BufferCapacityOptions {
+                max_initial_batches: conf.buffer_max_initial_batches,
+                shrink_ratio: conf.buffer_shrink_ratio,
+            },
+            read_crc_verify_enable: conf.read_crc_verify_enable,
         }
     }
 
+    /// Recomputes the crc of every block read from memory, so that the corrupted buffer
+    /// is failed rather than silently propagated to the client.
+    fn verify_crc(uid: &PartitionedUId, data: &PartitionedMemoryData) -> Result<(), WorkerError> {
+        for segment in &data.shuffle_data_block_segments {
+            let (offset, len) = (segment.offset as usize, segment.length as usize);
+            let bytes = match &data.data {
+                BytesWrapper::Direct(bytes) => bytes.slice(offset..offset + len),
+                BytesWrapper::Composed(composed) => composed.slice(offset, len)?,
+            };
+            let actual = get_crc(&bytes);
+            if actual == segment.crc {
+                continue;
+            }
+            TOTAL_MEMORY_DATA_CRC_MISMATCH.inc();
+            error!(
+                "Crc mismatched block: {} at offset: {} of memory buffer: {:?}. expected: {}, actual: {}",
+                segment.block_id, segment.offset, uid, segment.crc, actual
+            );
+            return Err(WorkerError::DATA_CRC_MISMATCH {
+                block_id: segment.block_id,
+                offset: segment.offset,
+                expected: segment.crc,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
     pub fn memory_snapshot(&self) -> Result<CapacitySnapshot> {
         Ok(self.budget.snapshot())
     }
 
+    /// The buffered bytes of all the partitions, including the in-flight spilling ones.
+    pub fn resident_bytes(&self) -> u64 {
+        self.state
+            .iter()
+            .map(|entry| entry.value().total_size().unwrap_or(0) as u64)
+            .sum()
+    }
+
     pub fn get_capacity(&self) -> Result<i64> {
         Ok(self.memory_capacity)
     }
@@ -131,51 +184,46 @@ impl MemoryStore {
         self.budget.move_allocated_to_used(size)
     }
 
+    /// Looks up the spill candidates in the order of the given policy until the
+    /// expected spill bytes are reached.
     pub fn lookup_spill_buffers(
         &self,
         expected_spill_total_bytes: i64,
-    ) -> Result<HashMap<PartitionedUId, Arc<MemoryBuffer>>, anyhow::Error> {
-        // 1. sort by the staging size.
-        // 2. get the spill buffers until reaching the single max batch size
-
-        let mut sorted_tree_map = BTreeMap::new();
-
+        policy: SpillCandidateSelectPolicy,
+    ) -> Result<Vec<(PartitionedUId, Arc<MemoryBuffer>)>, anyhow::Error> {
+        let mut candidates = vec![];
         let buffers = self.state.clone().into_read_only();
-        for buffer in buffers.iter() {
-            let key = buffer.0;
-            let memory_buf = buffer.1;
-            let staging_size = memory_buf.staging_size()?;
+        for (uid, buffer) in buffers.iter() {
+            let staging_size = buffer.staging_size()?;
             if staging_size == 0 {
                 continue;
             }
-            let valset = sorted_tree_map
-                .entry(staging_size)
-                .or_insert_with(|| vec![]);
-            valset.push(key);
+            candidates.push((uid, buffer, staging_size, buffer.staging_since()));
         }
 
-        let mut real_spill_total_bytes = 0;
-        let mut spill_candidates = HashMap::new();
+        match policy {
+            SpillCandidateSelectPolicy::FIFO => {}
+            SpillCandidateSelectPolicy::LARGEST_FIRST => {
+                candidates.sort_by(|a, b| b.2.cmp(&a.2));
+            }
+            SpillCandidateSelectPolicy::OLDEST_FIRST => {
+                candidates.sort_by(|a, b| a.3.cmp(&b.3));
+            }
+        }
 
-        let iter = sorted_tree_map.iter().rev();
-        'outer: for (size, vals) in iter {
-            for pid in vals {
-                if real_spill_total_bytes >= expected_spill_total_bytes {
-                    break 'outer;
-                }
-                let partition_uid = (*pid).clone();
-                let buffer = self.get_buffer(*pid);
-                if buffer.is_err() {
-                    continue;
-                }
-                real_spill_total_bytes += *size;
-                spill_candidates.insert(partition_uid, buffer?);
+        let mut real_spill_total_bytes = 0;
+        let mut spill_candidates = vec![];
+        for (uid, buffer, staging_size, _) in candidates {
+            if real_spill_total_bytes >= expected_spill_total_bytes {
+                break;
             }
+            real_spill_total_bytes += staging_size;
+            spill_candidates.push((uid.clone(), buffer.clone()));
         }
 
         info!(
-            "[Spill] Candidate spill bytes. excepted/real: {}/{}",
-            &expected_spill_total_bytes, &real_spill_total_bytes
+            "[Spill] Candidate spill bytes with policy: {:?}. excepted/real: {}/{}",
+            policy, &expected_spill_total_bytes, &real_spill_total_bytes
         );
         Ok(spill_candidates)
     }
@@ -204,13 +252,45 @@ impl MemoryStore {
 
     // only invoked when inserting
     pub fn get_or_create_buffer(&self, uid: PartitionedUId) -> Arc<MemoryBuffer> {
-        let buffer = self
-            .state
-            .entry(uid)
-            .or_insert_with(|| Arc::new(MemoryBuffer::new()));
+        let buffer = self.state.entry(uid).or_insert_with(|| {
+            Arc::new(MemoryBuffer::with_capacity_options(
+                self.buffer_capacity_options,
+            ))
+        });
         buffer.clone()
     }
 
+    /// Inserts the data, and returns the monotonic write sequence of the partition.
+    pub fn insert_with_sequence(&self, ctx: WritingViewContext) -> Result<u64, WorkerError> {
+        let uid = ctx.uid;
+        let blocks = ctx.data_blocks;
+        let size = ctx.data_size;
+
+        let buffer = self.get_or_create_buffer(uid);
+        let sequence = buffer.append(blocks, ctx.data_size)?;
+
+        TOTAL_MEMORY_USED.inc_by(size);
+
+        Ok(sequence)
+    }
+
+    /// The latest visible write sequence of the partition, 0 if the partition is absent.
+    pub fn get_write_sequence(&self, uid: &PartitionedUId) -> u64 {
+        self.state
+            .get(uid)
+            .map(|buffer| buffer.write_sequence())
+            .unwrap_or(0)
+    }
+
+    /// All the buffers of the app, regardless of whether they hold the staging data.
+    pub fn get_app_buffers(&self, app_id: &str) -> Vec<(PartitionedUId, Arc<MemoryBuffer>)> {
+        self.state
+            .iter()
+            .filter(|entry| entry.key().app_id == app_id)
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
     pub fn get_buffer(&self, uid: &PartitionedUId) -> Result<Arc<MemoryBuffer>> {
         let buffer = self.state.get(uid);
         if buffer.is_none() {
@@ -256,31 +336,48 @@ impl Store for MemoryStore {
 
     #[trace]
     async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
-        let uid = ctx.uid;
-        let blocks = ctx.data_blocks;
-        let size = ctx.data_size;
-
-        let buffer = self.get_or_create_buffer(uid);
-        buffer.append(blocks, ctx.data_size)?;
-
-        TOTAL_MEMORY_USED.inc_by(size);
-
-        Ok(())
+        self.insert_with_sequence(ctx).map(|_| ())
     }
 
     #[trace]
     async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
+        check_deadline(ctx.deadline)?;
         let uid = ctx.uid;
-        let buffer = self.get_buffer(&uid)?;
         let options = ctx.reading_options;
+        let memory_only = matches!(options, MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE(_, _));
+        let buffer = match self.get_buffer(&uid) {
+            Ok(buffer) => buffer,
+            Err(_) if memory_only => return Err(WorkerError::NOT_IN_MEMORY),
+            Err(err) => return Err(err.into()),
+        };
+        // the empty bitmap means no filtering
+        let task_ids_filter = ctx
+            .serialized_expected_task_ids_bitmap
+            .filter(|bitmap| !bitmap.is_empty());
         let read_data = match options {
-            MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(last_block_id, max_size) => buffer.get_v2(
+            MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(last_block_id, max_size)
+            | MEMORY_ONLY_LAST_BLOCK_ID_AND_MAX_SIZE(last_block_id, max_size) => buffer.get_v2(
                 last_block_id,
                 max_size,
-                ctx.serialized_expected_task_ids_bitmap,
+                task_ids_filter,
+                self.max_segments_per_response,
+                ctx.latest_attempt_only,
             )?,
             _ => panic!("Should not happen."),
         };
+        let segments = read_data.shuffle_data_block_segments.len();
+        if memory_only && segments == 0 {
+            return Err(WorkerError::NOT_IN_MEMORY);
+        }
+        if segments > self.max_segments_per_response {
+            return Err(WorkerError::TOO_MANY_SEGMENTS_PER_RESPONSE(
+                segments,
+                self.max_segments_per_response,
+            ));
+        }
+        if self.read_crc_verify_enable {
+            MemoryStore::verify_crc(&uid, &read_data)?;
+        }
 
         Ok(ResponseData::Mem(read_data))
     }
@@ -333,6 +430,16 @@ impl Store for MemoryStore {
         Ok(used)
     }
 
+    #[trace]
+    async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64> {
+        let used = match self.state.remove(&uid) {
+            Some((_, buffer)) => buffer.total_size()?,
+            _ => 0,
+        };
+        self.budget.dec_used(used)?;
+        Ok(used)
+    }
+
     #[trace]
     async fn is_healthy(&self) -> Result<bool> {
         Ok(true)
@@ -409,14 +516,18 @@ mod test {
         RequireBufferContext, WritingViewContext,
     };
 
+    use crate::config::{MemoryStoreConfig, SpillCandidateSelectPolicy};
+    use crate::error::WorkerError;
     use crate::store::memory::MemoryStore;
     use crate::store::ResponseData::Mem;
 
     use crate::store::{Block, PartitionedMemoryData, ResponseData, Store};
+    use crate::util::get_crc;
 
-    use bytes::BytesMut;
+    use bytes::{Bytes, BytesMut};
     use core::panic;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use anyhow::Result;
     use croaring::Treemap;
@@ -606,6 +717,9 @@ mod test {
                 default_single_read_size,
             ),
             serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
         if let Ok(data) = store.get(ctx).await {
             match data {
@@ -636,6 +750,203 @@ mod test {
         WritingViewContext::create_for_test(uid, data_blocks)
     }
 
+    #[test]
+    fn test_lookup_spill_buffers_with_policy() -> Result<()> {
+        let store = MemoryStore::new(1024 * 1024);
+        let runtime = store.runtime_manager.clone();
+
+        // the older partition is smaller
+        let sizes = [10, 30, 20];
+        for (idx, size) in sizes.iter().enumerate() {
+            let uid = PartitionedUId::from("test_spill_policy".to_string(), 0, idx as i32);
+            runtime.wait(store.insert(create_writing_ctx_with_blocks(10, *size, uid)))?;
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let partition_ids = |buffers: Vec<(PartitionedUId, _)>| -> Vec<i32> {
+            buffers.iter().map(|(uid, _)| uid.partition_id).collect()
+        };
+
+        // case1: largest first
+        let buffers =
+            store.lookup_spill_buffers(i64::MAX, SpillCandidateSelectPolicy::LARGEST_FIRST)?;
+        assert_eq!(vec![1, 2, 0], partition_ids(buffers));
+
+        // the largest one is enough for the expected spill bytes
+        let buffers = store.lookup_spill_buffers(100, SpillCandidateSelectPolicy::LARGEST_FIRST)?;
+        assert_eq!(vec![1], partition_ids(buffers));
+
+        // case2: oldest first
+        let buffers =
+            store.lookup_spill_buffers(i64::MAX, SpillCandidateSelectPolicy::OLDEST_FIRST)?;
+        assert_eq!(vec![0, 1, 2], partition_ids(buffers));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_segments_per_response() -> Result<()> {
+        let mut conf = MemoryStoreConfig::new("1M".to_string());
+        conf.max_segments_per_response = 5;
+        let store = MemoryStore::from(conf, Default::default());
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "test_max_segments_per_response".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        runtime.wait(store.insert(create_writing_ctx_with_blocks(10, 0, uid.clone())))?;
+
+        let read = |max_size: i64| {
+            runtime.wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, max_size),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))
+        };
+
+        // the zero-length blocks make the read size useless to bound the segments
+        match read(1024) {
+            Err(WorkerError::TOO_MANY_SEGMENTS_PER_RESPONSE(segments, limit)) => {
+                assert_eq!(6, segments);
+                assert_eq!(5, limit);
+            }
+            _ => panic!("the segments limit should be exceeded"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_crc_verify() -> Result<()> {
+        let mut conf = MemoryStoreConfig::new("1M".to_string());
+        conf.read_crc_verify_enable = true;
+        let store = MemoryStore::from(conf, Default::default());
+        let runtime = store.runtime_manager.clone();
+
+        let block = |block_id: i64, data: &[u8], crc: i64| Block {
+            block_id,
+            length: data.len() as i32,
+            uncompress_length: data.len() as i32,
+            crc,
+            data: Bytes::copy_from_slice(data),
+            task_attempt_id: 0,
+        };
+        let read = |uid: &PartitionedUId| {
+            runtime.wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1024),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            }))
+        };
+
+        // case1: the clean data passes
+        let uid = PartitionedUId::from("test_read_crc_verify".to_string(), 0, 0);
+        let (a, b) = (b"hello world!", b"hello china!");
+        runtime.wait(store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![
+                block(1, a, get_crc(&Bytes::from_static(a))),
+                block(2, b, get_crc(&Bytes::from_static(b))),
+            ],
+        )))?;
+        match read(&uid)? {
+            Mem(data) => {
+                assert_eq!(2, data.shuffle_data_block_segments.len());
+                assert_eq!(b"hello world!hello china!".to_vec(), data.data.freeze());
+            }
+            _ => panic!("the data should be read from memory"),
+        }
+
+        // case2: the bit flipped block is detected
+        let uid = PartitionedUId::from("test_read_crc_verify".to_string(), 0, 1);
+        let flipped = b"hello chinb!";
+        runtime.wait(store.insert(WritingViewContext::create_for_test(
+            uid.clone(),
+            vec![
+                block(1, a, get_crc(&Bytes::from_static(a))),
+                block(2, flipped, get_crc(&Bytes::from_static(b))),
+            ],
+        )))?;
+        match read(&uid) {
+            Err(WorkerError::DATA_CRC_MISMATCH {
+                block_id,
+                offset,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(2, block_id);
+                assert_eq!(a.len() as i64, offset);
+                assert_eq!(get_crc(&Bytes::from_static(b)), expected);
+                assert_eq!(get_crc(&Bytes::from_static(flipped)), actual);
+            }
+            _ => panic!("the crc mismatch should be detected"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_latest_attempt_only() -> Result<()> {
+        let store = MemoryStore::new(1024 * 1024);
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "test_read_latest_attempt_only".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let block = |block_id: i64, task_attempt_id: i64, data: &'static [u8]| Block {
+            block_id,
+            length: data.len() as i32,
+            uncompress_length: 0,
+            crc: 0,
+            data: Bytes::from_static(data),
+            task_attempt_id,
+        };
+        // the block 1 is written by the speculative attempt 2 again
+        let blocks = vec![
+            block(1, 1, b"stale"),
+            block(2, 1, b"other"),
+            block(1, 2, b"fresh"),
+        ];
+        runtime.wait(store.insert(WritingViewContext::create_for_test(uid.clone(), blocks)))?;
+
+        let read = |latest_attempt_only: bool| -> Result<PartitionedMemoryData> {
+            match runtime.wait(store.get(ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1024),
+                serialized_expected_task_ids_bitmap: Default::default(),
+                latest_attempt_only,
+                min_write_sequence: None,
+                deadline: None,
+            }))? {
+                Mem(data) => Ok(data),
+                _ => panic!(),
+            }
+        };
+
+        // case1: all the attempts are returned by default
+        assert_eq!(3, read(false)?.shuffle_data_block_segments.len());
+
+        // case2: only the highest attempt is returned for the duplicate block id
+        let data = read(true)?;
+        let segments = &data.shuffle_data_block_segments;
+        assert_eq!(2, segments.len());
+        assert_eq!((2, 1), (segments[0].block_id, segments[0].task_attempt_id));
+        assert_eq!((1, 2), (segments[1].block_id, segments[1].task_attempt_id));
+        assert_eq!(5, segments[1].offset);
+        assert_eq!(b"otherfresh".as_slice(), data.data.freeze().as_ref());
+
+        Ok(())
+    }
+
     #[test]
     fn test_allocated_and_purge_for_memory() {
         let store = MemoryStore::new(1024 * 1024 * 1024);
@@ -656,6 +967,7 @@ mod test {
                     .default_runtime
                     .block_on(store.purge(&PurgeDataContext {
                         purge_reason: PurgeReason::APP_LEVEL_EXPLICIT_UNREGISTER("100".to_string()),
+                        progress_tracker: None,
                     }));
             }
             _ => panic!(),
@@ -666,6 +978,25 @@ mod test {
         assert_eq!(1024 * 1024 * 1024, snapshot.capacity());
     }
 
+    #[test]
+    fn test_expired_ticket_release() {
+        let mut conf = MemoryStoreConfig::from("1M".to_string(), 1);
+        conf.buffer_ticket_check_interval_sec = 1;
+        let store = MemoryStore::from(conf, Default::default());
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId::from("expired_ticket_app".to_string(), 0, 0);
+        runtime
+            .wait(store.require_buffer(RequireBufferContext::create_for_test(uid, 1000)))
+            .unwrap();
+        assert_eq!(1000, store.budget.snapshot().allocated());
+
+        // the ticket is never released by the client, the checker should restore the budget.
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.budget.snapshot().allocated() == 0);
+        assert_eq!(0, store.budget.snapshot().used());
+    }
+
     #[test]
     fn test_purge() -> Result<()> {
         let store = MemoryStore::new(1024);
@@ -700,6 +1031,9 @@ mod test {
             uid: uid.clone(),
             reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
             serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
         let data = runtime.wait(store.get(reading_ctx.clone())).expect("");
         assert_eq!(1, data.from_memory().shuffle_data_block_segments.len());
@@ -778,6 +1112,9 @@ mod test {
             uid: Default::default(),
             reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
             serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
 
         match runtime.wait(store.get(reading_ctx)).unwrap() {
@@ -790,6 +1127,67 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_task_ids_filter_for_memory() {
+        let store = MemoryStore::new(1024 * 1024 * 1024);
+        let runtime = store.runtime_manager.clone();
+
+        // write blocks with the mixed task_attempt_ids
+        let mut blocks = vec![];
+        for idx in 0..10 {
+            blocks.push(Block {
+                block_id: idx,
+                length: 10 + idx as i32,
+                uncompress_length: 100,
+                crc: 99,
+                data: Bytes::from(vec![idx as u8; 10 + idx as usize]),
+                task_attempt_id: idx % 3,
+            });
+        }
+        runtime
+            .wait(store.insert(WritingViewContext::create_for_test(
+                Default::default(),
+                blocks,
+            )))
+            .unwrap();
+
+        let read = |bitmap: Option<Treemap>| {
+            let ctx = ReadingViewContext {
+                uid: Default::default(),
+                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+                serialized_expected_task_ids_bitmap: bitmap,
+                latest_attempt_only: false,
+                min_write_sequence: None,
+                deadline: None,
+            };
+            runtime.wait(store.get(ctx)).unwrap().from_memory()
+        };
+
+        // case1: only the expected task ids will be returned
+        let data = read(Some(Treemap::from_iter(vec![0u64, 2])));
+        let segments = &data.shuffle_data_block_segments;
+        assert_eq!(7, segments.len());
+        assert!(segments.iter().all(|x| x.task_attempt_id != 1));
+        let expected_len: i32 = segments.iter().map(|x| x.length).sum();
+        assert_eq!(expected_len as usize, data.data.len());
+        let bytes = data.data.freeze();
+        for segment in segments {
+            let start = segment.offset as usize;
+            let end = start + segment.length as usize;
+            assert!(bytes[start..end]
+                .iter()
+                .all(|x| *x == segment.block_id as u8));
+        }
+
+        // case2: empty bitmap means no filtering
+        let data = read(Some(Treemap::new()));
+        assert_eq!(10, data.shuffle_data_block_segments.len());
+
+        // case3: no bitmap
+        let data = read(None);
+        assert_eq!(10, data.shuffle_data_block_segments.len());
+    }
+
     #[test]
     fn test_block_id_filter_for_memory() {
         let store = MemoryStore::new(1024 * 1024 * 1024);
@@ -824,6 +1222,9 @@ mod test {
             uid: Default::default(),
             reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
             serialized_expected_task_ids_bitmap: Default::default(),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
 
         match runtime.wait(store.get(reading_ctx)).unwrap() {
@@ -840,6 +1241,9 @@ mod test {
             uid: Default::default(),
             reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(0, 1000000),
             serialized_expected_task_ids_bitmap: Option::from(bitmap.clone()),
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: None,
         };
 
         match runtime.wait(store.get(reading_ctx)).unwrap() {
@@ -16,6 +16,7 @@
 // under the License.
 
 pub mod alignment;
+pub mod compression;
 mod hadoop;
 #[cfg(feature = "hdfs")]
 pub mod hdfs;
@@ -33,19 +34,21 @@ use crate::app::{
 };
 use crate::config::{Config, StorageType};
 use crate::error::WorkerError;
-use crate::grpc::protobuf::uniffle::{ShuffleData, ShuffleDataBlockSegment};
+use crate::grpc::protobuf::uniffle::{ShuffleBlock, ShuffleData, ShuffleDataBlockSegment};
 use crate::store::hybrid::HybridStore;
 
 use crate::util::now_timestamp_as_sec;
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::{BufMut, Bytes, BytesMut};
+use serde::Serialize;
 
 use crate::composed_bytes::ComposedBytes;
 use crate::runtime::manager::RuntimeManager;
 use crate::store::index_codec::IndexCodec;
 use crate::store::spill::SpillWritingViewContext;
 use crate::store::BytesWrapper::{Composed, Direct};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -54,7 +57,7 @@ pub struct PartitionedData {
     pub blocks: Vec<Block>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     pub block_id: i64,
     pub length: i32,
@@ -64,27 +67,42 @@ pub struct Block {
     pub task_attempt_id: i64,
 }
 
+impl From<ShuffleBlock> for Block {
+    fn from(block: ShuffleBlock) -> Block {
+        Block {
+            block_id: block.block_id,
+            length: block.length,
+            uncompress_length: block.uncompress_length,
+            crc: block.crc,
+            // zero-copy slice of the decoded request buffer
+            data: block.data,
+            task_attempt_id: block.task_attempt_id,
+        }
+    }
+}
+
 impl From<ShuffleData> for PartitionedData {
     fn from(shuffle_data: ShuffleData) -> PartitionedData {
-        let mut blocks = vec![];
-        for data in shuffle_data.block {
-            let block = Block {
-                block_id: data.block_id,
-                length: data.length,
-                uncompress_length: data.uncompress_length,
-                crc: data.crc,
-                data: data.data,
-                task_attempt_id: data.task_attempt_id,
-            };
-            blocks.push(block);
-        }
         PartitionedData {
             partition_id: shuffle_data.partition_id,
-            blocks,
+            blocks: shuffle_data.block.into_iter().map(Block::from).collect(),
         }
     }
 }
 
+/// Group the blocks by partition directly from the decoded shuffle data to avoid
+/// the intermediate [`PartitionedData`] allocations.
+pub fn group_blocks_by_partition(shuffle_data: Vec<ShuffleData>) -> HashMap<i32, Vec<Block>> {
+    let mut blocks_map: HashMap<i32, Vec<Block>> = HashMap::with_capacity(shuffle_data.len());
+    for data in shuffle_data {
+        let blocks = blocks_map
+            .entry(data.partition_id)
+            .or_insert_with(|| Vec::with_capacity(data.block.len()));
+        blocks.extend(data.block.into_iter().map(Block::from));
+    }
+    blocks_map
+}
+
 pub enum ResponseDataIndex {
     Local(LocalDataIndex),
 }
@@ -93,6 +111,8 @@ pub enum ResponseDataIndex {
 pub struct LocalDataIndex {
     pub index_data: Bytes,
     pub data_file_len: i64,
+    // the total index length, which is larger than the index data for the ranged reading
+    pub index_len: i64,
 }
 
 #[derive(Debug)]
@@ -126,6 +146,8 @@ pub struct PartitionedLocalData {
 pub struct PartitionedMemoryData {
     pub shuffle_data_block_segments: Vec<DataSegment>,
     pub data: BytesWrapper,
+    // whether more in-memory data remains after this page
+    pub has_more: bool,
 }
 
 #[derive(Debug)]
@@ -172,7 +194,7 @@ impl BytesWrapper {
     pub fn always_composed(&self) -> ComposedBytes {
         match self {
             BytesWrapper::Composed(bytes) => bytes.clone(),
-            BytesWrapper::Direct(data) => ComposedBytes::from(vec![data.clone()], data.len()),
+            BytesWrapper::Direct(data) => ComposedBytes::from(vec![data.clone()]),
             _ => panic!(),
         }
     }
@@ -228,6 +250,42 @@ impl RequireBufferResponse {
     }
 }
 
+/// The flushing progress of one partition, which could be used by the client side
+/// to determine whether the inconsistent data is caused by the in-flight spill.
+#[derive(Clone, Debug, Default)]
+pub struct PartitionFlushState {
+    pub pending_spill_event_num: u64,
+    pub memory_buffered_bytes: u64,
+    pub last_flushed_offset: i64,
+}
+
+/// The storage tiers where the partition's data currently resides. The data may be
+/// spread across multiple tiers when part of it has been spilled.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PartitionLocation {
+    pub memory: bool,
+    /// the disk root of localfile when the data has been flushed into localfile
+    pub localfile_disk_root: Option<String>,
+    pub hdfs: bool,
+}
+
+impl PartitionLocation {
+    fn tier_num(&self) -> usize {
+        [self.memory, self.localfile_disk_root.is_some(), self.hdfs]
+            .iter()
+            .filter(|x| **x)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tier_num() == 0
+    }
+
+    pub fn is_spread(&self) -> bool {
+        self.tier_num() > 1
+    }
+}
+
 // =====================================================
 
 #[async_trait]
@@ -240,6 +298,8 @@ pub trait Store {
         ctx: ReadingIndexViewContext,
     ) -> Result<ResponseDataIndex, WorkerError>;
     async fn purge(&self, ctx: &PurgeDataContext) -> Result<i64>;
+    /// Deletes the data of the single partition, and returns the removed bytes.
+    async fn delete_partition(&self, uid: PartitionedUId) -> Result<i64>;
     async fn is_healthy(&self) -> Result<bool>;
 
     async fn require_buffer(
@@ -259,22 +319,22 @@ pub trait Store {
         let mut index_bytes_holder = BytesMut::new();
         let mut data_chain = Vec::with_capacity(blocks.len());
 
-        let mut total_size = 0;
         for block in blocks {
             let _ = IndexCodec::encode(&(block, offset).into(), &mut index_bytes_holder)?;
 
             let length = block.length;
-            total_size += length as usize;
             offset += length as i64;
 
             let data = &block.data;
             data_chain.push(data.clone());
         }
 
+        let data = ComposedBytes::from(data_chain);
+        let len = data.len();
         Ok(ShuffleFileFormat {
-            data: Composed(ComposedBytes::from(data_chain, total_size)),
+            data: Composed(data),
             index: Direct(index_bytes_holder.into()),
-            len: total_size,
+            len,
             offset,
         })
     }
@@ -296,3 +356,57 @@ impl StoreProvider {
         HybridStore::from(config, runtime_manager)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::grpc::protobuf::uniffle::{ShuffleBlock, ShuffleData};
+    use crate::store::{group_blocks_by_partition, Block, PartitionedData};
+    use bytes::Bytes;
+    use std::collections::HashMap;
+
+    fn mock_shuffle_data(partition_id: i32, block_num: i64) -> ShuffleData {
+        let block = (0..block_num)
+            .map(|idx| ShuffleBlock {
+                block_id: idx,
+                length: 10,
+                uncompress_length: 20,
+                crc: idx,
+                data: Bytes::from(vec![idx as u8; 10]),
+                task_attempt_id: idx,
+            })
+            .collect();
+        ShuffleData {
+            partition_id,
+            block,
+            crc_aggregate: None,
+        }
+    }
+
+    #[test]
+    fn test_group_blocks_by_partition() {
+        let shuffle_data = vec![
+            mock_shuffle_data(1, 10000),
+            mock_shuffle_data(2, 10),
+            mock_shuffle_data(1, 5),
+        ];
+        let raw_data_ptr = shuffle_data[0].block[0].data.as_ptr();
+
+        // the previous conversion path with the intermediate partitioned data
+        let mut expected: HashMap<i32, Vec<Block>> = HashMap::new();
+        for data in shuffle_data.clone() {
+            let data: PartitionedData = data.into();
+            expected
+                .entry(data.partition_id)
+                .or_insert_with(|| vec![])
+                .extend(data.blocks);
+        }
+
+        let actual = group_blocks_by_partition(shuffle_data);
+        assert_eq!(expected, actual);
+        assert_eq!(10005, actual.get(&1).unwrap().len());
+        assert_eq!(10, actual.get(&2).unwrap().len());
+
+        // the payload should not be copied
+        assert_eq!(raw_data_ptr, actual.get(&1).unwrap()[0].data.as_ptr());
+    }
+}
@@ -252,12 +252,15 @@ mod tests {
                 uid: Default::default(),
                 data_blocks: Arc::new(Default::default()),
                 app_is_exist_func: Arc::new(Box::new((|app| true))),
+                local_order: true,
             },
             size: 0,
             retry_cnt: Default::default(),
             flight_id: 0,
             candidate_store_type: Arc::new(parking_lot::Mutex::new(None)),
             huge_partition_tag: Default::default(),
+            state: Default::default(),
+            created_at: tokio::time::Instant::now(),
         };
         let f = event_bus.publish(spill_msg.clone().into());
         let _ = runtime_manager.wait(f);
@@ -1,19 +1,26 @@
 use crate::app::PartitionedUId;
-use crate::config::StorageType;
+use crate::config::{HybridStoreConfig, StorageType};
 use crate::error::WorkerError;
 use crate::metric::{
     TOTAL_MEMORY_SPILL_OPERATION_FAILED, TOTAL_MEMORY_SPILL_TO_HDFS_OPERATION_FAILED,
-    TOTAL_MEMORY_SPILL_TO_LOCALFILE_OPERATION_FAILED, TOTAL_SPILL_EVENTS_DROPPED,
+    TOTAL_MEMORY_SPILL_TO_LOCALFILE_OPERATION_FAILED, TOTAL_SPILL_BUFFER_RELEASE_FAILED,
+    TOTAL_SPILL_DATA_DROPPED_BYTES, TOTAL_SPILL_EVENTS_DEAD_LETTERED, TOTAL_SPILL_EVENTS_DROPPED,
     TOTAL_SPILL_EVENTS_DROPPED_WITH_APP_NOT_FOUND,
 };
 use crate::store::hybrid::{HybridStore, PersistentStore};
+use crate::store::index_codec::IndexCodec;
 use crate::store::mem::buffer::BatchMemoryBlock;
-use log::{debug, error, warn};
+use crate::store::Block;
+use crate::util::{now_timestamp_as_millis, write_atomically};
+use bytes::BytesMut;
+use log::{debug, error, info, warn};
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
-use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicU32, AtomicU8};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
 
 pub mod hierarchy_event_bus;
 mod metrics;
@@ -29,9 +36,60 @@ pub struct SpillMessage {
     pub flight_id: u64,
     pub candidate_store_type: Arc<Mutex<Option<StorageType>>>,
     pub huge_partition_tag: OnceCell<bool>,
+    pub state: Arc<AtomicU8>,
+    pub created_at: Instant,
 }
 
+const SPILL_EVENT_PENDING: u8 = 0;
+const SPILL_EVENT_RUNNING: u8 = 1;
+const SPILL_EVENT_CANCELLED: u8 = 2;
+// the app or partition of the running event has been purged, whose buffer has been
+// released by the purge.
+const SPILL_EVENT_PURGED: u8 = 3;
+
+const SPILL_DISK_FULL_BACKOFF_MILLIS: u64 = 500;
+const SPILL_RETRY_BACKOFF_MAX_MILLIS: u64 = 30_000;
+
 impl SpillMessage {
+    /// Marks the queued event as running, which could not be cancelled by the purge anymore.
+    /// Returns false if it has been cancelled.
+    pub fn try_start(&self) -> bool {
+        self.state
+            .compare_exchange(SPILL_EVENT_PENDING, SPILL_EVENT_RUNNING, SeqCst, SeqCst)
+            .is_ok()
+    }
+
+    /// Marks the running event as pending before it is queued again.
+    pub fn pause(&self) {
+        let _ =
+            self.state
+                .compare_exchange(SPILL_EVENT_RUNNING, SPILL_EVENT_PENDING, SeqCst, SeqCst);
+    }
+
+    /// Cancels the queued event. Returns false if it is running.
+    pub fn cancel(&self) -> bool {
+        self.state
+            .compare_exchange(SPILL_EVENT_PENDING, SPILL_EVENT_CANCELLED, SeqCst, SeqCst)
+            .is_ok()
+    }
+
+    /// Marks the running event which could not be cancelled as purged, then it will be
+    /// skipped by the handlers. Returns false if it is pending, which should be cancelled.
+    pub fn mark_purged(&self) -> bool {
+        match self.state.load(SeqCst) {
+            SPILL_EVENT_PENDING => false,
+            SPILL_EVENT_RUNNING => self
+                .state
+                .compare_exchange(SPILL_EVENT_RUNNING, SPILL_EVENT_PURGED, SeqCst, SeqCst)
+                .is_ok(),
+            _ => true,
+        }
+    }
+
+    pub fn is_purged(&self) -> bool {
+        self.state.load(SeqCst) == SPILL_EVENT_PURGED
+    }
+
     pub fn has_candidate_storage(&self) -> bool {
         let guard = self.candidate_store_type.lock();
         guard.is_some()
@@ -68,6 +126,8 @@ pub struct SpillWritingViewContext {
     pub uid: PartitionedUId,
     pub data_blocks: Arc<BatchMemoryBlock>,
     app_is_exist_func: Arc<Box<dyn Fn(&str) -> bool + 'static>>,
+    // sort the blocks by the task attempt id for the LOCAL_ORDER data distribution
+    pub local_order: bool,
 }
 unsafe impl Send for SpillWritingViewContext {}
 unsafe impl Sync for SpillWritingViewContext {}
@@ -81,7 +141,29 @@ impl SpillWritingViewContext {
             uid,
             data_blocks: blocks,
             app_is_exist_func: Arc::new(Box::new(func)),
+            local_order: true,
+        }
+    }
+
+    pub fn with_local_order(mut self, local_order: bool) -> Self {
+        self.local_order = local_order;
+        self
+    }
+
+    /// The blocks to be flushed. For the LOCAL_ORDER distribution, the blocks of this batch
+    /// are grouped by the task attempt id, and the arrival order is kept in the same attempt
+    /// by the stable sort. The index entries follow this order.
+    pub fn ordered_blocks(&self) -> Vec<&Block> {
+        let mut data = vec![];
+        for blocks in self.data_blocks.iter() {
+            for block in blocks {
+                data.push(block);
+            }
+        }
+        if self.local_order {
+            data.sort_by_key(|block| block.task_attempt_id);
         }
+        data
     }
 
     pub fn is_valid(&self) -> bool {
@@ -90,6 +172,155 @@ impl SpillWritingViewContext {
     }
 }
 
+/// The decision for the failed spill event.
+#[derive(Debug, PartialEq)]
+pub enum SpillFailureAction {
+    DROP,
+    // retry after the exponential backoff in the retry queue
+    RETRY,
+    // retry after the backoff to wait the disk space is released
+    BACKOFF(Duration),
+}
+
+/// The exponential backoff of the failed spill event waiting in the retry queue.
+pub fn spill_retry_backoff(config: &HybridStoreConfig, retry_cnt: u32) -> Duration {
+    let factor = 1u64 << retry_cnt.saturating_sub(1).min(16);
+    Duration::from_millis(
+        config
+            .spill_retry_backoff_base_millis
+            .saturating_mul(factor)
+            .min(SPILL_RETRY_BACKOFF_MAX_MILLIS),
+    )
+}
+
+/// Writes the data of the spill event into the dead letter dir in the same data and index
+/// format of the localfile, which could be recovered by the human. Returns the data file path.
+fn write_dead_letter(dir: &str, message: &SpillMessage) -> anyhow::Result<String> {
+    let uid = &message.ctx.uid;
+    let parent = format!("{}/{}/{}", dir, &uid.app_id, uid.shuffle_id);
+    std::fs::create_dir_all(&parent)?;
+
+    let mut data = BytesMut::new();
+    let mut index = BytesMut::new();
+    for block in message.ctx.data_blocks.iter().flat_map(|x| x.iter()) {
+        IndexCodec::encode(&(block, data.len() as i64).into(), &mut index)?;
+        data.extend_from_slice(&block.data);
+    }
+    // the flight id is reset after restarting, so the timestamp is attached to be unique
+    let prefix = format!(
+        "{}/{}-{}-{}",
+        parent,
+        uid.partition_id,
+        message.flight_id,
+        now_timestamp_as_millis()
+    );
+    let index_path = format!("{}.index", prefix);
+    write_atomically(&index_path, &index)?;
+    let data_path = format!("{}.data", prefix);
+    write_atomically(&data_path, &data)?;
+    Ok(data_path)
+}
+
+async fn dead_letter(message: &SpillMessage, store_ref: &HybridStore) -> bool {
+    let dir = match store_ref.config.spill_dead_letter_dir.as_ref() {
+        Some(dir) => dir.to_string(),
+        _ => return false,
+    };
+    let cloned = message.clone();
+    let result = tokio::task::spawn_blocking(move || write_dead_letter(&dir, &cloned)).await;
+    match result {
+        Ok(Ok(path)) => {
+            warn!(
+                "The spill event for uid: {:?} has been written into the dead letter: {}",
+                &message.ctx.uid, path
+            );
+            TOTAL_SPILL_EVENTS_DEAD_LETTERED.inc();
+            true
+        }
+        Ok(Err(err)) => {
+            error!(
+                "Errors on writing the dead letter for uid: {:?}. err: {:#?}",
+                &message.ctx.uid, err
+            );
+            false
+        }
+        Err(err) => {
+            error!(
+                "Errors on writing the dead letter for uid: {:?}. err: {:#?}",
+                &message.ctx.uid, err
+            );
+            false
+        }
+    }
+}
+
+fn is_disk_full(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(io_err) = cause.downcast_ref::<std::io::Error>() {
+            if let Some(code) = io_err.raw_os_error() {
+                if code == libc::ENOSPC || code == libc::EDQUOT {
+                    return true;
+                }
+            }
+        }
+        // the hdfs errors are only distinguished by the message
+        let msg = cause.to_string();
+        msg.contains("No space left on device")
+            || msg.contains("DSQuotaExceededException")
+            || msg.contains("NSQuotaExceededException")
+    })
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .map(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
+            .unwrap_or(false)
+    })
+}
+
+/// Maps the underlying io/hdfs errors of the spill into the structured errors,
+/// that the missing dir is treated as the purged app when the app is not valid,
+/// because the app dir may be deleted by the purge when the spill is in flight.
+pub fn map_spill_error(err: WorkerError, app_id: &str, is_valid_app: bool) -> WorkerError {
+    let dir_missing = |err: anyhow::Error| {
+        if is_valid_app {
+            WorkerError::SPILL_TARGET_DIR_MISSING(err)
+        } else {
+            WorkerError::SPILL_APP_PURGED(app_id.to_string())
+        }
+    };
+    match err {
+        WorkerError::APP_HAS_BEEN_PURGED | WorkerError::APP_IS_NOT_FOUND => {
+            WorkerError::SPILL_APP_PURGED(app_id.to_string())
+        }
+        WorkerError::DIR_OR_FILE_NOT_FOUND(err) => dir_missing(err),
+        err @ WorkerError::LOCAL_DISK_RESERVED_FOR_INDEX(_) => {
+            WorkerError::SPILL_DISK_FULL(anyhow::Error::new(err))
+        }
+        WorkerError::Other(err) => {
+            if is_disk_full(&err) {
+                WorkerError::SPILL_DISK_FULL(err)
+            } else if is_not_found(&err) {
+                dir_missing(err)
+            } else {
+                WorkerError::Other(err)
+            }
+        }
+        WorkerError::HDFS_IO_ERROR(msg, err) => {
+            if is_disk_full(&err) {
+                WorkerError::SPILL_DISK_FULL(err.context(msg))
+            } else if is_not_found(&err) {
+                dir_missing(err.context(msg))
+            } else {
+                WorkerError::HDFS_IO_ERROR(msg, err)
+            }
+        }
+        err => err,
+    }
+}
+
 async fn handle_spill_failure_whatever_error(
     message: &SpillMessage,
     store_ref: Arc<HybridStore>,
@@ -101,25 +332,42 @@ async fn handle_spill_failure_whatever_error(
     let is_app_not_found_or_purged = match flush_error {
         WorkerError::APP_HAS_BEEN_PURGED
         | WorkerError::APP_IS_NOT_FOUND
+        | WorkerError::SPILL_APP_PURGED(_)
         | WorkerError::DIR_OR_FILE_NOT_FOUND(_) => true,
         _ => false,
     };
     if !is_valid_app || is_app_not_found_or_purged {
-        debug!("Dropping the spill event for uid: {:?}. Ths app is not found, may be purged. Ignore this. error: {}", &message.ctx.uid, flush_error);
+        info!("Dropping the spill event for uid: {:?}. Ths app is not found, may be purged. Ignore this. error: {}", &message.ctx.uid, flush_error);
         TOTAL_SPILL_EVENTS_DROPPED_WITH_APP_NOT_FOUND.inc();
     } else {
-        warn!(
-            "Dropping the spill event for uid: {:?}. Attention: this will make data lost! error: {}",
-            &message.ctx.uid, flush_error
-        );
+        let dead_lettered = match flush_error {
+            WorkerError::SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(_) => {
+                dead_letter(message, &store_ref).await
+            }
+            _ => false,
+        };
+        if !dead_lettered {
+            warn!(
+                "Dropping the spill event for uid: {:?}. Attention: this will make data lost! error: {}",
+                &message.ctx.uid, flush_error
+            );
+            TOTAL_SPILL_DATA_DROPPED_BYTES.inc_by(message.size as u64);
+        }
         if let Err(err) = store_ref
             .release_memory_buffer(message.size, &message)
             .await
         {
-            error!("Errors on releasing memory data when dropping the spill event, that should not happen. err: {:#?}. flush_error: {}", err, flush_error);
+            if message.is_purged() || !ctx.is_valid() {
+                // the buffer has been removed by the purge in flight
+                debug!("Ignoring the buffer releasing failure of the purged uid: {:?}. err: {:#?}. flush_error: {}", &message.ctx.uid, err, flush_error);
+            } else {
+                TOTAL_SPILL_BUFFER_RELEASE_FAILED.inc();
+                error!("Errors on releasing memory data when dropping the spill event, that should not happen. err: {:#?}. flush_error: {}", err, flush_error);
+            }
         }
         TOTAL_SPILL_EVENTS_DROPPED.inc();
         TOTAL_MEMORY_SPILL_OPERATION_FAILED.inc();
+        store_ref.on_spill_event_dropped(message);
     }
     store_ref.finish_spill_event(message);
 }
@@ -129,16 +377,17 @@ async fn handle_spill_failure(
     err: WorkerError,
     message: &SpillMessage,
     store_ref: Arc<HybridStore>,
-) -> bool {
+) -> SpillFailureAction {
+    let err = map_spill_error(err, &message.ctx.uid.app_id, message.ctx.is_valid());
     match err {
         WorkerError::SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(_)
+        | WorkerError::SPILL_EVENT_EXPIRED(_)
+        | WorkerError::SPILL_RETRY_QUEUE_FULL(_)
         | WorkerError::PARTIAL_DATA_LOST(_)
-        | WorkerError::APP_HAS_BEEN_PURGED
-        | WorkerError::APP_IS_NOT_FOUND
-        | WorkerError::FUTURE_EXEC_TIMEOUT(_)
-        | WorkerError::DIR_OR_FILE_NOT_FOUND(_) => {
+        | WorkerError::SPILL_APP_PURGED(_)
+        | WorkerError::FUTURE_EXEC_TIMEOUT(_) => {
             handle_spill_failure_whatever_error(message, store_ref, err).await;
-            false
+            SpillFailureAction::DROP
         }
         error => {
             TOTAL_MEMORY_SPILL_OPERATION_FAILED.inc();
@@ -156,13 +405,45 @@ async fn handle_spill_failure(
                 "Errors on spill memory data to persistent storage for uid: {:?}. The error: {:#?}",
                 uid, error
             );
-            // could be retry?
-            true
+            // the memory should not be held by the failed event forever
+            let max_age = Duration::from_secs(store_ref.config.spill_retry_max_age_sec);
+            if message.created_at.elapsed() > max_age {
+                let err = WorkerError::SPILL_EVENT_EXPIRED(uid.app_id.to_string());
+                handle_spill_failure_whatever_error(message, store_ref, err).await;
+                return SpillFailureAction::DROP;
+            }
+            match error {
+                WorkerError::SPILL_DISK_FULL(_) => {
+                    SpillFailureAction::BACKOFF(Duration::from_millis(
+                        SPILL_DISK_FULL_BACKOFF_MILLIS * message.get_retry_counter() as u64,
+                    ))
+                }
+                _ => {
+                    if store_ref.try_enqueue_spill_retry() {
+                        return SpillFailureAction::RETRY;
+                    }
+                    // no more retry could be made when the retry queue is full, and it
+                    // is dropped to release the memory rather than dead lettered
+                    let err = WorkerError::SPILL_RETRY_QUEUE_FULL(uid.app_id.to_string());
+                    handle_spill_failure_whatever_error(message, store_ref, err).await;
+                    SpillFailureAction::DROP
+                }
+            }
         }
     }
 }
 
+// the buffer of the purged event has been released, so it's only finished.
+fn handle_purged_spill_event(message: &SpillMessage, store_ref: &HybridStore) {
+    debug!(
+        "Skipping the spill event of the purged uid: {:?}",
+        &message.ctx.uid
+    );
+    store_ref.finish_spill_event(message);
+}
+
 async fn handle_spill_success(message: &SpillMessage, store_ref: Arc<HybridStore>) {
+    store_ref.mark_spilled(&message.ctx.uid);
     if let Err(err) = store_ref
         .release_memory_buffer(message.size, &message)
         .await
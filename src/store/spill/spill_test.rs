@@ -1,22 +1,26 @@
 #[cfg(test)]
 mod tests {
     use crate::app::test::mock_writing_context;
-    use crate::app::{AppManager, PartitionedUId};
+    use crate::app::{AppManager, PartitionedUId, WritingViewContext};
     use crate::config::StorageType::{HDFS, LOCALFILE};
     use crate::config::{Config, StorageType};
     use crate::config_reconfigure::ReconfigurableConfManager;
     use crate::log_service::LogService;
     use crate::metric::{
         GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES, TOTAL_MEMORY_SPILL_BYTES,
-        TOTAL_MEMORY_SPILL_OPERATION_FAILED, TOTAL_SPILL_EVENTS_DROPPED,
+        TOTAL_MEMORY_SPILL_OPERATION_FAILED, TOTAL_SPILL_BUFFER_RELEASE_FAILED,
+        TOTAL_SPILL_DATA_DROPPED_BYTES, TOTAL_SPILL_EVENTS_DROPPED,
         TOTAL_SPILL_EVENTS_DROPPED_WITH_APP_NOT_FOUND,
     };
     use crate::runtime::manager::RuntimeManager;
     use crate::store::hybrid::{HybridStore, PersistentStore};
+    use crate::store::index_codec::{IndexCodec, INDEX_BLOCK_SIZE};
+    use crate::store::spill::spill_retry_backoff;
     use crate::store::spill::spill_test::mock::MockStore;
     use crate::store::spill::storage_flush_handler::StorageFlushHandler;
     use crate::store::spill::storage_select_handler::StorageSelectHandler;
-    use crate::store::Store;
+    use crate::store::{Block, Store};
+    use bytes::Bytes;
     use libc::{c_int, stpcpy};
     use log::info;
     use once_cell::sync::Lazy;
@@ -30,6 +34,69 @@ mod tests {
         ()
     });
 
+    #[test]
+    fn test_map_spill_error() {
+        use crate::error::WorkerError;
+        use crate::store::spill::map_spill_error;
+        use anyhow::anyhow;
+        use std::io::{Error, ErrorKind};
+
+        let disk_full = || Error::from_raw_os_error(libc::ENOSPC);
+        let not_found = || Error::new(ErrorKind::NotFound, "no such dir");
+
+        // io errors of localfile
+        let err = map_spill_error(WorkerError::from(disk_full()), "app", true);
+        assert!(matches!(err, WorkerError::SPILL_DISK_FULL(_)));
+        let err = map_spill_error(WorkerError::from(not_found()), "app", true);
+        assert!(matches!(err, WorkerError::SPILL_TARGET_DIR_MISSING(_)));
+        let err = map_spill_error(WorkerError::from(not_found()), "app", false);
+        assert!(matches!(err, WorkerError::SPILL_APP_PURGED(_)));
+        let err = map_spill_error(
+            WorkerError::Other(anyhow::Error::new(disk_full()).context("appending")),
+            "app",
+            true,
+        );
+        assert!(matches!(err, WorkerError::SPILL_DISK_FULL(_)));
+
+        // hdfs errors
+        let err = map_spill_error(
+            WorkerError::HDFS_IO_ERROR(
+                "Errors on appending".to_string(),
+                anyhow!("org.apache.hadoop.hdfs.protocol.DSQuotaExceededException"),
+            ),
+            "app",
+            true,
+        );
+        assert!(matches!(err, WorkerError::SPILL_DISK_FULL(_)));
+        let err = map_spill_error(
+            WorkerError::HDFS_IO_ERROR(
+                "Errors on appending".to_string(),
+                anyhow::Error::new(not_found()),
+            ),
+            "app",
+            false,
+        );
+        assert!(matches!(err, WorkerError::SPILL_APP_PURGED(_)));
+        let err = map_spill_error(
+            WorkerError::HDFS_IO_ERROR("Errors on appending".to_string(), anyhow!("timeout")),
+            "app",
+            true,
+        );
+        assert!(matches!(err, WorkerError::HDFS_IO_ERROR(_, _)));
+
+        // app purged
+        let err = map_spill_error(WorkerError::APP_HAS_BEEN_PURGED, "app", true);
+        assert!(matches!(err, WorkerError::SPILL_APP_PURGED(app) if app == "app"));
+        let err = map_spill_error(WorkerError::APP_IS_NOT_FOUND, "app", false);
+        assert!(matches!(err, WorkerError::SPILL_APP_PURGED(_)));
+
+        // the others are kept
+        let err = map_spill_error(WorkerError::INTERNAL_ERROR, "app", true);
+        assert!(matches!(err, WorkerError::INTERNAL_ERROR));
+        let err = map_spill_error(WorkerError::Other(anyhow!("mock")), "app", true);
+        assert!(matches!(err, WorkerError::Other(_)));
+    }
+
     #[test]
     fn test_enum_display() {
         let store_type = StorageType::HDFS;
@@ -146,6 +213,113 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_purge_app_in_flight_spill() -> anyhow::Result<()> {
+        let _ = LOG;
+        TOTAL_SPILL_BUFFER_RELEASE_FAILED.reset();
+
+        let warm_healthy = Arc::new(AtomicBool::new(true));
+        let mark_write_fail = Arc::new(AtomicBool::new(false));
+        let mark_write_hang = Arc::new(AtomicBool::new(true));
+        let warm = MockStore::new(
+            LOCALFILE,
+            &warm_healthy,
+            Some(mark_write_fail.clone()),
+            Some(mark_write_hang.clone()),
+        );
+
+        let temp_dir = tempdir::TempDir::new("test_purge_app_in_flight_spill").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = create_multi_level_config(
+            StorageType::MEMORY_LOCALFILE,
+            1,
+            "1M".to_string(),
+            temp_path,
+        );
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let store = create_hybrid_store(&config, &warm, None);
+        let runtime = store.runtime_manager.clone();
+        let app_manager_ref = AppManager::get_ref(runtime, config, &store, &reconf_manager);
+        store.with_app_manager(&app_manager_ref);
+
+        let app_id = "test_purge_app_in_flight_spill-app";
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        app.insert(mock_writing_context(app_id, 1, 0, 1, 20))
+            .await?;
+
+        // the spill event is running and hung
+        awaitility::at_most(Duration::from_secs(2))
+            .until(|| warm.inner.spill_insert_ops.load(SeqCst) == 1);
+        assert_eq!(1, store.get_spill_event_num()?);
+
+        app_manager_ref.unregister_app(app_id.to_string()).await?;
+        awaitility::at_most(Duration::from_secs(2)).until(|| !app_manager_ref.app_is_exist(app_id));
+
+        // the failed flush of the purged app is skipped without retrying
+        mark_write_fail.store(true, SeqCst);
+        mark_write_hang.store(false, SeqCst);
+        awaitility::at_most(Duration::from_secs(2))
+            .until(|| store.get_spill_event_num().unwrap() == 0);
+        assert_eq!(1, warm.inner.spill_insert_ops.load(SeqCst));
+        assert_eq!(0, store.get_in_flight_size()?);
+        assert_eq!(0, TOTAL_SPILL_BUFFER_RELEASE_FAILED.get());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spill_without_storage_event_bus() -> anyhow::Result<()> {
+        let _ = LOG;
+
+        let warm_healthy = Arc::new(AtomicBool::new(true));
+        let warm = MockStore::new(LOCALFILE, &warm_healthy, None, None);
+
+        let temp_dir = tempdir::TempDir::new("test_spill_without_storage_event_bus").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = create_multi_level_config(
+            StorageType::MEMORY_LOCALFILE,
+            1,
+            "1M".to_string(),
+            temp_path,
+        );
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None).unwrap();
+        let store = create_hybrid_store(&config, &warm, None);
+        let runtime = store.runtime_manager.clone();
+        let app_manager_ref = AppManager::get_ref(runtime, config, &store, &reconf_manager);
+        store.with_app_manager(&app_manager_ref);
+
+        // the selected localfile has no event bus to flush the spill event
+        store.event_bus.children.remove(&LOCALFILE);
+
+        let app_id = "test_spill_without_storage_event_bus-app";
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+        app.insert(mock_writing_context(app_id, 1, 0, 1, 20))
+            .await?;
+
+        // the event is finished with the memory released
+        awaitility::at_most(Duration::from_secs(2))
+            .until(|| store.get_spill_event_num().unwrap() == 0);
+        assert_eq!(0, warm.inner.spill_insert_ops.load(SeqCst));
+        assert_eq!(0, store.get_in_flight_size()?);
+        assert_eq!(
+            0,
+            store
+                .get_memory_buffer_size(&PartitionedUId::from(app_id.to_string(), 1, 0))
+                .await?
+        );
+        let snapshot = store.hot_store.memory_snapshot().unwrap();
+        assert_eq!(0, snapshot.used());
+        assert_eq!(0, snapshot.allocated());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_flush_failed() {
         let _ = LOG;
@@ -200,6 +374,124 @@ mod tests {
         TOTAL_SPILL_EVENTS_DROPPED.reset();
     }
 
+    #[tokio::test]
+    async fn test_spill_dead_letter() -> anyhow::Result<()> {
+        let _ = LOG;
+
+        // both the warm and cold stores always fail
+        let mark_fail_error = Arc::new(AtomicBool::new(true));
+        let healthy = Arc::new(AtomicBool::new(true));
+        let warm = MockStore::new(LOCALFILE, &healthy, Some(mark_fail_error.clone()), None);
+        let cold = MockStore::new(HDFS, &healthy, Some(mark_fail_error.clone()), None);
+
+        let temp_dir = tempdir::TempDir::new("test_spill_dead_letter")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let dead_letter_dir = format!("{}/dead_letter", &temp_path);
+        let mut config = create_multi_level_config(
+            StorageType::MEMORY_LOCALFILE,
+            1,
+            "1M".to_string(),
+            temp_path,
+        );
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config.hybrid_store.spill_retry_backoff_base_millis = 10;
+        config.hybrid_store.spill_dead_letter_dir = Some(dead_letter_dir.clone());
+
+        assert_eq!(
+            Duration::from_millis(40),
+            spill_retry_backoff(&config.hybrid_store, 3)
+        );
+        assert_eq!(
+            Duration::from_secs(30),
+            spill_retry_backoff(&config.hybrid_store, 100)
+        );
+
+        // case1: the data is written into the dead letter after the max attempts
+        let store = create_hybrid_store(&config, &warm, Some(&cold));
+        let app_id = "test_spill_dead_letter-app";
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        let blocks = vec![
+            Block {
+                block_id: 1,
+                length: 12,
+                uncompress_length: 12,
+                crc: 10,
+                data: Bytes::from_static(b"hello world!"),
+                task_attempt_id: 0,
+            },
+            Block {
+                block_id: 2,
+                length: 12,
+                uncompress_length: 12,
+                crc: 20,
+                data: Bytes::from_static(b"hello china!"),
+                task_attempt_id: 1,
+            },
+        ];
+        let _ = store
+            .insert(WritingViewContext::new_with_size(uid.clone(), blocks, 24))
+            .await;
+
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.get_spill_event_num().unwrap() == 0);
+        // the first attempt is on the warm, and then fallback to the cold
+        assert_eq!(1, warm.inner.spill_insert_fail_ops.load(SeqCst));
+        assert_eq!(2, cold.inner.spill_insert_fail_ops.load(SeqCst));
+        assert_eq!(0, store.get_spill_retry_queue_size());
+        assert_eq!(0, store.get_in_flight_size()?);
+        assert_eq!(0, store.hot_store.memory_snapshot()?.used());
+
+        let partition_dir = format!("{}/{}/1", &dead_letter_dir, app_id);
+        let mut files = std::fs::read_dir(&partition_dir)?
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(2, files.len());
+        let data = std::fs::read(&files[0])?;
+        assert!(files[0].to_str().unwrap().ends_with(".data"));
+        assert_eq!(b"hello world!hello china!", data.as_slice());
+        let index = std::fs::read(&files[1])?;
+        assert_eq!(2 * INDEX_BLOCK_SIZE, index.len());
+        let second = IndexCodec::decode(Bytes::copy_from_slice(&index[INDEX_BLOCK_SIZE..]))?;
+        assert_eq!(12, second.offset);
+        assert_eq!(2, second.block_id);
+        assert_eq!(20, second.crc);
+        assert_eq!(1, second.task_attempt_id);
+
+        // case2: the expired event is dropped to release the memory without retrying
+        let warm = MockStore::new(LOCALFILE, &healthy, Some(mark_fail_error.clone()), None);
+        config.hybrid_store.spill_retry_max_age_sec = 0;
+        config.hybrid_store.spill_dead_letter_dir = None;
+        let store = create_hybrid_store(&config, &warm, None);
+        let dropped_bytes = TOTAL_SPILL_DATA_DROPPED_BYTES.get();
+        let ctx = mock_writing_context(app_id, 2, 0, 1, 20);
+        let _ = store.insert(ctx).await;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.get_spill_event_num().unwrap() == 0);
+        assert_eq!(1, warm.inner.spill_insert_fail_ops.load(SeqCst));
+        assert!(TOTAL_SPILL_DATA_DROPPED_BYTES.get() >= dropped_bytes + 20);
+        assert_eq!(0, store.hot_store.memory_snapshot()?.used());
+
+        // case3: the event is dropped without the dead letter when the retry queue is full
+        let warm = MockStore::new(LOCALFILE, &healthy, Some(mark_fail_error.clone()), None);
+        config.hybrid_store.spill_retry_max_age_sec = 600;
+        config.hybrid_store.spill_retry_queue_capacity = 0;
+        config.hybrid_store.spill_dead_letter_dir = Some(dead_letter_dir.clone());
+        let store = create_hybrid_store(&config, &warm, None);
+        let dropped_bytes = TOTAL_SPILL_DATA_DROPPED_BYTES.get();
+        let ctx = mock_writing_context(app_id, 3, 0, 1, 20);
+        let _ = store.insert(ctx).await;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.get_spill_event_num().unwrap() == 0);
+        assert_eq!(1, warm.inner.spill_insert_fail_ops.load(SeqCst));
+        assert!(TOTAL_SPILL_DATA_DROPPED_BYTES.get() >= dropped_bytes + 20);
+        assert_eq!(0, store.hot_store.memory_snapshot()?.used());
+        let partition_dir = format!("{}/{}/3", &dead_letter_dir, app_id);
+        assert!(!std::path::Path::new(&partition_dir).exists());
+
+        Ok(())
+    }
+
     // This test case will test the watermark spill on excluding inflight bytes when huge partition is found.
     // for sensitive watermark-spill mechanism
     #[tokio::test]
@@ -498,6 +790,10 @@ mod mock {
         }
 
         async fn purge(&self, ctx: &PurgeDataContext) -> anyhow::Result<i64> {
+            Ok(0)
+        }
+
+        async fn delete_partition(&self, uid: PartitionedUId) -> anyhow::Result<i64> {
             todo!()
         }
 
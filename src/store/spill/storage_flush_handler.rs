@@ -1,8 +1,12 @@
 use crate::event_bus::{Event, Subscriber};
 use crate::store::hybrid::HybridStore;
 use crate::store::spill::metrics::FlushingMetricsMonitor;
-use crate::store::spill::{handle_spill_failure, handle_spill_success, SpillMessage};
+use crate::store::spill::{
+    handle_purged_spill_event, handle_spill_failure, handle_spill_success, spill_retry_backoff,
+    SpillFailureAction, SpillMessage,
+};
 use async_trait::async_trait;
+use await_tree::InstrumentAwait;
 use log::{error, warn};
 use std::sync::Arc;
 
@@ -26,26 +30,68 @@ impl Subscriber for StorageFlushHandler {
 
     async fn on_event(&self, event: Event<Self::Input>) -> bool {
         let message = event.get_data();
+        if !message.try_start() {
+            // the paused event has been purged, or the cancelled event has been
+            // finished by the purge
+            if message.is_purged() {
+                handle_purged_spill_event(message, &self.store);
+            }
+            return true;
+        }
         let app_id = &message.ctx.uid.app_id;
 
         let _ =
             FlushingMetricsMonitor::new(app_id, message.size, message.get_candidate_storage_type());
 
         let result = self.store.flush_storage_for_buffer(message).await;
+        if message.is_purged() {
+            handle_purged_spill_event(message, &self.store);
+            return true;
+        }
         match result {
             Ok(_) => {
                 handle_spill_success(message, self.store.clone()).await;
             }
             Err(err) => {
                 message.inc_retry_counter();
-                let could_be_retried = handle_spill_failure(err, message, self.store.clone()).await;
-                if could_be_retried {
-                    if let Err(e) = &self.store.event_bus.publish(event).await {
-                        error!(
-                            "Errors on resending the event into parent event bus. err: {:#?}",
-                            e
-                        );
+                let action = handle_spill_failure(err, message, self.store.clone()).await;
+                match action {
+                    SpillFailureAction::BACKOFF(duration) => {
+                        tokio::time::sleep(duration)
+                            .instrument_await("backing off the disk full spill event")
+                            .await;
+                        message.pause();
+                        if let Err(e) = &self.store.event_bus.publish(event).await {
+                            error!(
+                                "Errors on resending the event into parent event bus. err: {:#?}",
+                                e
+                            );
+                        }
+                    }
+                    SpillFailureAction::RETRY => {
+                        // the retried event waits in the retry queue without holding
+                        // the flushing concurrency
+                        message.pause();
+                        let backoff =
+                            spill_retry_backoff(&self.store.config, message.get_retry_counter());
+                        let store = self.store.clone();
+                        self.store
+                            .runtime_manager
+                            .dispatch_runtime
+                            .spawn_with_await_tree("Spill event retry", async move {
+                                tokio::time::sleep(backoff)
+                                    .instrument_await("backing off the failed spill event")
+                                    .await;
+                                store.dequeue_spill_retry();
+                                if let Err(e) = &store.event_bus.publish(event).await {
+                                    error!(
+                                        "Errors on resending the event into parent event bus. err: {:#?}",
+                                        e
+                                    );
+                                }
+                            });
                     }
+                    SpillFailureAction::DROP => {}
                 }
             }
         }
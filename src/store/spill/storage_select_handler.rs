@@ -1,8 +1,11 @@
+use crate::error::WorkerError;
 use crate::event_bus::{Event, Subscriber};
 use crate::store::hybrid::HybridStore;
 use crate::store::spill::{
-    handle_spill_failure, handle_spill_failure_whatever_error, SpillMessage,
+    handle_purged_spill_event, handle_spill_failure, handle_spill_failure_whatever_error,
+    SpillMessage,
 };
+use anyhow::anyhow;
 use async_trait::async_trait;
 use log::error;
 use std::sync::Arc;
@@ -28,16 +31,35 @@ impl Subscriber for StorageSelectHandler {
 
     async fn on_event(&self, event: Event<Self::Input>) -> bool {
         let msg = event.get_data();
+        if !msg.try_start() {
+            // the paused event has been purged, or the cancelled event has been
+            // finished by the purge
+            if msg.is_purged() {
+                handle_purged_spill_event(msg, &self.store);
+            }
+            return false;
+        }
         let select_result = self.store.select_storage_for_buffer(msg).await;
         let upstream_event_bus = &self.store.event_bus;
         match select_result {
-            Ok(storage) => {
-                if let Some(event_bus) = upstream_event_bus.children.get(&storage) {
+            Ok(storage) => match upstream_event_bus.children.get(&storage) {
+                Some(event_bus) => {
                     msg.set_candidate_storage_type(storage);
+                    msg.pause();
                     let _ = event_bus.publish(event).await;
+                    true
                 }
-                true
-            }
+                _ => {
+                    // the buffer is released rather than held by the event never flushed
+                    let e = WorkerError::Other(anyhow!(
+                        "No spill event bus for the selected storage: {:?}",
+                        storage
+                    ));
+                    error!("Errors on the selecting storage for app: {:?} and then drop this event. error: {:?}", &msg.ctx.uid, &e);
+                    handle_spill_failure_whatever_error(msg, self.store.clone(), e).await;
+                    false
+                }
+            },
             Err(e) => {
                 error!("Errors on the selecting storage for app: {:?} and then drop this event. error: {:?}", &msg.ctx.uid, &e);
                 handle_spill_failure_whatever_error(msg, self.store.clone(), e).await;
@@ -0,0 +1,342 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The lifecycle management of the auxiliary subsystems, like the urpc listener and the
+//! coordinator heartbeat, which could be stopped/restarted individually via the admin http
+//! api for the targeted maintenance. The data-path subsystems (stores, app manager) are
+//! never registered here, so their buffered data won't be lost.
+
+use crate::runtime::JoinHandle;
+use anyhow::{anyhow, Result};
+use dashmap::mapref::one::RefMut;
+use dashmap::DashMap;
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+pub const URPC_LISTENER: &str = "urpc_listener";
+pub const COORDINATOR_HEARTBEAT: &str = "coordinator_heartbeat";
+pub const METRICS_REPORTER: &str = "metrics_reporter";
+
+// the max waiting time for the subsystem tasks to exit, which should cover the urpc
+// connections draining.
+const SUBSYSTEM_STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub static SUBSYSTEM_MANAGER: Lazy<SubsystemManager> = Lazy::new(SubsystemManager::new);
+
+/// Spawns the subsystem tasks, which must exit once the shutdown signal is received.
+/// It should return the error rather than panic if the subsystem fails to start, and
+/// return the spawned tasks to track whether the subsystem is still running.
+pub type SubsystemStarter =
+    Arc<dyn Fn(broadcast::Receiver<()>) -> Result<Vec<SubsystemTask>> + Send + Sync>;
+
+/// The handle of the spawned subsystem task.
+pub enum SubsystemTask {
+    Async(JoinHandle<()>),
+    Thread(std::thread::JoinHandle<()>),
+}
+
+impl SubsystemTask {
+    fn is_finished(&self) -> bool {
+        match self {
+            SubsystemTask::Async(handle) => handle.is_finished(),
+            SubsystemTask::Thread(handle) => handle.is_finished(),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SubsystemAction {
+    START,
+    STOP,
+    RESTART,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SubsystemState {
+    pub name: String,
+    pub running: bool,
+    pub last_error: Option<String>,
+    pub restart_count: u64,
+}
+
+struct Subsystem {
+    starter: SubsystemStarter,
+    shutdown: Option<broadcast::Sender<()>>,
+    tasks: Vec<SubsystemTask>,
+    state: SubsystemState,
+}
+
+impl Subsystem {
+    fn start(&mut self) -> Result<()> {
+        if self.shutdown.is_some() {
+            return Ok(());
+        }
+        if self.tasks.iter().any(|task| !task.is_finished()) {
+            return Err(anyhow!(
+                "The subsystem: {} is still stopping",
+                &self.state.name
+            ));
+        }
+        let (tx, rx) = broadcast::channel(1);
+        match (self.starter)(rx) {
+            Ok(tasks) => {
+                self.shutdown = Some(tx);
+                self.tasks = tasks;
+                self.state.running = true;
+                Ok(())
+            }
+            Err(err) => {
+                self.state.last_error = Some(format!("{:?}", err));
+                Err(err)
+            }
+        }
+    }
+
+    /// Sends the shutdown signal, and returns the tasks to wait for their exit.
+    fn stop(&mut self) -> Vec<SubsystemTask> {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        std::mem::take(&mut self.tasks)
+    }
+
+    /// Marks the subsystem as stopped once all its tasks have exited, which is unexpected
+    /// if it's not stopped explicitly.
+    fn refresh(&mut self) {
+        if self.tasks.is_empty() || self.tasks.iter().any(|task| !task.is_finished()) {
+            return;
+        }
+        self.tasks.clear();
+        self.state.running = false;
+        if self.shutdown.take().is_some() {
+            warn!("The subsystem: {} exited unexpectedly", &self.state.name);
+            self.state.last_error = Some("The subsystem exited unexpectedly".to_string());
+        }
+    }
+}
+
+async fn wait_for_exit(tasks: &[SubsystemTask], timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    while tasks.iter().any(|task| !task.is_finished()) {
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "The subsystem tasks are not stopped in {:?}",
+                timeout
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Ok(())
+}
+
+pub struct SubsystemManager {
+    subsystems: DashMap<String, Subsystem>,
+    stop_timeout: Duration,
+}
+
+impl SubsystemManager {
+    fn new() -> Self {
+        Self {
+            subsystems: DashMap::new(),
+            stop_timeout: SUBSYSTEM_STOP_TIMEOUT,
+        }
+    }
+
+    /// Registers and starts the subsystem. The registered one with the same name will be
+    /// stopped and replaced.
+    pub fn register(&self, name: &str, starter: SubsystemStarter) -> Result<()> {
+        if let Some((_, mut previous)) = self.subsystems.remove(name) {
+            warn!("The subsystem: {} has been registered, replacing it", name);
+            previous.stop();
+        }
+        let mut subsystem = Subsystem {
+            starter,
+            shutdown: None,
+            tasks: vec![],
+            state: SubsystemState {
+                name: name.to_string(),
+                running: false,
+                last_error: None,
+                restart_count: 0,
+            },
+        };
+        let result = subsystem.start();
+        self.subsystems.insert(name.to_string(), subsystem);
+        if let Err(err) = &result {
+            error!("Errors on starting the subsystem: {}. err: {:?}", name, err);
+        }
+        result
+    }
+
+    fn get_mut(&self, name: &str) -> Result<RefMut<'_, String, Subsystem>> {
+        self.subsystems
+            .get_mut(name)
+            .ok_or(anyhow!("No such controllable subsystem: {}", name))
+    }
+
+    /// Applies the action to the subsystem, the start and stop are idempotent. The stopping
+    /// waits for all the subsystem tasks to exit, like the urpc listeners being closed.
+    pub async fn apply(&self, name: &str, action: SubsystemAction) -> Result<SubsystemState> {
+        info!(
+            "Applying the action: {:?} to the subsystem: {}",
+            action, name
+        );
+        self.get_mut(name)?.refresh();
+        if action != SubsystemAction::START {
+            let tasks = self.get_mut(name)?.stop();
+            // the lock is not held across the waiting
+            if let Err(err) = wait_for_exit(&tasks, self.stop_timeout).await {
+                let mut subsystem = self.get_mut(name)?;
+                subsystem.tasks = tasks;
+                subsystem.state.last_error = Some(format!("{:?}", err));
+                return Err(err);
+            }
+            self.get_mut(name)?.state.running = false;
+        }
+        let mut subsystem = self.get_mut(name)?;
+        if action != SubsystemAction::STOP {
+            if action == SubsystemAction::RESTART {
+                subsystem.state.restart_count += 1;
+            }
+            subsystem.start()?;
+        }
+        Ok(subsystem.state.clone())
+    }
+
+    pub fn states(&self) -> Vec<SubsystemState> {
+        let mut states: Vec<SubsystemState> = self
+            .subsystems
+            .iter_mut()
+            .map(|mut entry| {
+                entry.refresh();
+                entry.state.clone()
+            })
+            .collect();
+        states.sort_by(|a, b| a.name.cmp(&b.name));
+        states
+    }
+
+    pub fn state(&self, name: &str) -> Option<SubsystemState> {
+        self.subsystems.get_mut(name).map(|mut x| {
+            x.refresh();
+            x.state.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subsystem::{SubsystemAction, SubsystemManager, SubsystemTask};
+    use anyhow::anyhow;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lifecycle() -> anyhow::Result<()> {
+        let manager = SubsystemManager::new();
+        let started = Arc::new(AtomicU64::new(0));
+        let started_ref = started.clone();
+        manager.register(
+            "mock",
+            Arc::new(move |mut rx| {
+                started_ref.fetch_add(1, SeqCst);
+                let task = std::thread::spawn(move || {
+                    let _ = rx.blocking_recv();
+                });
+                Ok(vec![SubsystemTask::Thread(task)])
+            }),
+        )?;
+        assert_eq!(1, started.load(SeqCst));
+        assert!(manager.state("mock").unwrap().running);
+
+        // the start is idempotent
+        manager.apply("mock", SubsystemAction::START).await?;
+        assert_eq!(1, started.load(SeqCst));
+
+        let state = manager.apply("mock", SubsystemAction::STOP).await?;
+        assert!(!state.running);
+        manager.apply("mock", SubsystemAction::STOP).await?;
+
+        let state = manager.apply("mock", SubsystemAction::RESTART).await?;
+        assert!(state.running);
+        assert_eq!(1, state.restart_count);
+        assert_eq!(2, started.load(SeqCst));
+
+        // the failure is recorded
+        assert!(manager
+            .register("broken", Arc::new(|_| Err(anyhow!("mock failure"))))
+            .is_err());
+        let state = manager.state("broken").unwrap();
+        assert!(!state.running);
+        assert!(state.last_error.unwrap().contains("mock failure"));
+
+        // the exited subsystem is not running, and could be started again
+        manager.register(
+            "exited",
+            Arc::new(|_| Ok(vec![SubsystemTask::Thread(std::thread::spawn(|| {}))])),
+        )?;
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| !manager.state("exited").unwrap().running);
+        assert!(manager.state("exited").unwrap().last_error.is_some());
+        let state = manager.apply("exited", SubsystemAction::START).await?;
+        assert!(state.running);
+
+        assert!(manager.apply("store", SubsystemAction::STOP).await.is_err());
+        assert_eq!(3, manager.states().len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_timeout() -> anyhow::Result<()> {
+        let mut manager = SubsystemManager::new();
+        manager.stop_timeout = Duration::from_millis(100);
+        // the task ignoring the shutdown signal
+        manager.register(
+            "stuck",
+            Arc::new(|_| {
+                Ok(vec![SubsystemTask::Thread(std::thread::spawn(|| {
+                    std::thread::sleep(Duration::from_secs(1))
+                }))])
+            }),
+        )?;
+        assert!(manager
+            .apply("stuck", SubsystemAction::RESTART)
+            .await
+            .is_err());
+        let state = manager.state("stuck").unwrap();
+        assert!(state.running);
+        assert!(state.last_error.is_some());
+
+        // the restarting is refused until the previous tasks exit
+        assert!(manager
+            .apply("stuck", SubsystemAction::START)
+            .await
+            .is_err());
+        awaitility::at_most(Duration::from_secs(2))
+            .until(|| !manager.state("stuck").unwrap().running);
+
+        Ok(())
+    }
+}
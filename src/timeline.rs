@@ -0,0 +1,332 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The bounded timeline of the significant operational events, like the disk health
+//! transitions, the decommission and the dynamic config changes, which is used to
+//! reconstruct what happened in the postmortems and is persisted to survive the restarts.
+
+use crate::runtime::manager::RuntimeManager;
+use crate::util;
+use crate::util::now_timestamp_as_sec;
+use anyhow::Result;
+use await_tree::InstrumentAwait;
+use log::{error, info};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::time::Duration;
+
+pub const TIMELINE_CAPACITY: usize = 1024;
+const TIMELINE_FLUSH_INTERVAL_SEC: u64 = 60;
+const DEFAULT_PURGE_SIZE_THRESHOLD: &str = "1G";
+// the number of the latest events attached to the diagnostic dumps
+const TIMELINE_DIAGNOSTIC_EVENT_NUM: usize = 32;
+
+pub static TIMELINE: Lazy<Timeline> = Lazy::new(|| Timeline::new(TIMELINE_CAPACITY));
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[allow(non_camel_case_types)]
+pub enum TimelineCategory {
+    DISK_HEALTH,
+    SERVICE_HEALTH,
+    DECOMMISSION,
+    CONFIG_CHANGE,
+    PURGE,
+    SPILL_BACKLOG,
+    RATE_LIMIT,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimelineEvent {
+    pub seq: u64,
+    pub timestamp_sec: u64,
+    // the consecutive duplicate events are compacted into one with the count
+    pub last_timestamp_sec: u64,
+    pub count: u64,
+    pub category: TimelineCategory,
+    pub payload: String,
+}
+
+struct Inner {
+    events: VecDeque<TimelineEvent>,
+    next_seq: u64,
+}
+
+pub struct Timeline {
+    capacity: usize,
+    inner: Mutex<Inner>,
+    // whether there are the unflushed events
+    dirty: AtomicBool,
+    purge_size_threshold: AtomicU64,
+}
+
+impl Timeline {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner {
+                events: VecDeque::with_capacity(capacity),
+                next_seq: 0,
+            }),
+            dirty: AtomicBool::new(false),
+            purge_size_threshold: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    pub fn record(&self, category: TimelineCategory, payload: impl Into<String>) {
+        self.record_at(now_timestamp_as_sec(), category, payload.into());
+    }
+
+    fn record_at(&self, timestamp_sec: u64, category: TimelineCategory, payload: String) {
+        let mut inner = self.inner.lock();
+        if let Some(last) = inner.events.back_mut() {
+            if last.category == category && last.payload == payload {
+                last.count += 1;
+                last.last_timestamp_sec = timestamp_sec;
+                self.dirty.store(true, SeqCst);
+                return;
+            }
+        }
+        if inner.events.len() >= self.capacity {
+            inner.events.pop_front();
+        }
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        inner.events.push_back(TimelineEvent {
+            seq,
+            timestamp_sec,
+            last_timestamp_sec: timestamp_sec,
+            count: 1,
+            category,
+            payload,
+        });
+        self.dirty.store(true, SeqCst);
+    }
+
+    /// The app purge removing more bytes than this threshold is recorded.
+    pub fn purge_size_threshold(&self) -> u64 {
+        self.purge_size_threshold.load(SeqCst)
+    }
+
+    /// Returns the events in the ascending order of sequence, which overlap with the
+    /// time range and belong to the categories. The empty categories mean all.
+    pub fn query(
+        &self,
+        start_sec: Option<u64>,
+        end_sec: Option<u64>,
+        categories: &[TimelineCategory],
+    ) -> Vec<TimelineEvent> {
+        self.inner
+            .lock()
+            .events
+            .iter()
+            .filter(|event| start_sec.map_or(true, |start| event.last_timestamp_sec >= start))
+            .filter(|event| end_sec.map_or(true, |end| event.timestamp_sec <= end))
+            .filter(|event| categories.is_empty() || categories.contains(&event.category))
+            .cloned()
+            .collect()
+    }
+
+    /// Renders the latest events for the diagnostic dumps. The events are skipped if the
+    /// timeline is being locked, to avoid blocking the dumping in the abnormal state.
+    pub fn diagnostic_section(&self) -> String {
+        let mut section = String::from("Recent operational timeline:\n");
+        match self.inner.try_lock() {
+            Some(inner) => {
+                let skip = inner
+                    .events
+                    .len()
+                    .saturating_sub(TIMELINE_DIAGNOSTIC_EVENT_NUM);
+                for event in inner.events.iter().skip(skip) {
+                    let _ = writeln!(
+                        section,
+                        "  {} [{}] {} (x{})",
+                        event.timestamp_sec, event.category, event.payload, event.count
+                    );
+                }
+            }
+            _ => section.push_str("  <unavailable>\n"),
+        }
+        section
+    }
+
+    /// Restores the persisted events ahead of the current ones.
+    pub fn load(&self, path: &str) -> Result<()> {
+        if !Path::new(path).exists() {
+            return Ok(());
+        }
+        let persisted: Vec<TimelineEvent> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut inner = self.inner.lock();
+        let current: Vec<TimelineEvent> = inner.events.drain(..).collect();
+        let mut next_seq = 0;
+        for mut event in persisted.into_iter().chain(current) {
+            event.seq = next_seq;
+            next_seq += 1;
+            if inner.events.len() >= self.capacity {
+                inner.events.pop_front();
+            }
+            inner.events.push_back(event);
+        }
+        inner.next_seq = next_seq;
+        Ok(())
+    }
+
+    /// Flushes only when new events are recorded since the last flush.
+    pub fn flush(&self, path: &str) -> Result<()> {
+        if !self.dirty.swap(false, SeqCst) {
+            return Ok(());
+        }
+        let events = self.query(None, None, &[]);
+        let flushed = util::write_atomically(path, &serde_json::to_vec(&events)?);
+        if flushed.is_err() {
+            self.dirty.store(true, SeqCst);
+        }
+        Ok(flushed?)
+    }
+
+    /// Starts the background flusher to persist the timeline into the given path.
+    pub fn start(
+        &'static self,
+        path: Option<String>,
+        purge_size_threshold: Option<String>,
+        runtime_manager: &RuntimeManager,
+    ) {
+        let purge_size_threshold = util::parse_raw_to_bytesize(
+            purge_size_threshold
+                .as_deref()
+                .unwrap_or(DEFAULT_PURGE_SIZE_THRESHOLD),
+        );
+        self.purge_size_threshold
+            .store(purge_size_threshold, SeqCst);
+        let path = match path {
+            Some(path) => path,
+            _ => return,
+        };
+        if let Err(err) = self.load(&path) {
+            error!(
+                "Errors on loading the timeline from: {}. err: {}",
+                &path, err
+            );
+        }
+        runtime_manager
+            .default_runtime
+            .spawn_with_await_tree("Timeline flusher", async move {
+                info!("Starting the timeline flusher...");
+                loop {
+                    tokio::time::sleep(Duration::from_secs(TIMELINE_FLUSH_INTERVAL_SEC))
+                        .instrument_await("sleeping")
+                        .await;
+                    if let Err(err) = self.flush(&path) {
+                        error!(
+                            "Errors on flushing the timeline to: {}. err: {}",
+                            &path, err
+                        );
+                    }
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::timeline::{Timeline, TimelineCategory};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_timeline() -> anyhow::Result<()> {
+        let timeline = Timeline::new(4);
+        timeline.record_at(100, TimelineCategory::DISK_HEALTH, "disk1 unhealthy".into());
+        timeline.record_at(110, TimelineCategory::CONFIG_CHANGE, "a: 1 -> 2".into());
+        timeline.record_at(120, TimelineCategory::PURGE, "app1".into());
+        // the consecutive duplicate ones are compacted
+        timeline.record_at(125, TimelineCategory::PURGE, "app1".into());
+        timeline.record_at(130, TimelineCategory::DISK_HEALTH, "disk1 healthy".into());
+
+        // case1: ordering
+        let events = timeline.query(None, None, &[]);
+        assert_eq!(4, events.len());
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            events.iter().map(|e| e.seq).collect::<Vec<_>>()
+        );
+        assert_eq!(2, events[2].count);
+        assert_eq!(120, events[2].timestamp_sec);
+        assert_eq!(125, events[2].last_timestamp_sec);
+
+        // case2: filtering by the time range and categories
+        let events = timeline.query(Some(121), Some(130), &[]);
+        assert_eq!(2, events.len());
+        assert_eq!(TimelineCategory::PURGE, events[0].category);
+        let events = timeline.query(None, None, &[TimelineCategory::DISK_HEALTH]);
+        assert_eq!(2, events.len());
+        assert_eq!("disk1 healthy", events[1].payload);
+        assert_eq!(
+            TimelineCategory::SPILL_BACKLOG,
+            TimelineCategory::from_str("SPILL_BACKLOG")?
+        );
+
+        // case3: the oldest one is evicted when exceeding the capacity
+        timeline.record_at(
+            140,
+            TimelineCategory::DECOMMISSION,
+            "DECOMMISSIONING".into(),
+        );
+        timeline.record_at(150, TimelineCategory::SPILL_BACKLOG, "full".into());
+        let events = timeline.query(None, None, &[]);
+        assert_eq!(4, events.len());
+        assert_eq!(TimelineCategory::PURGE, events[0].category);
+        assert_eq!(5, events[3].seq);
+
+        // case4: the diagnostic dump
+        let section = timeline.diagnostic_section();
+        assert!(section.contains("150 [SPILL_BACKLOG] full (x1)"));
+        assert!(section.contains("120 [PURGE] app1 (x2)"));
+
+        // case5: persistence across the restart
+        let temp_dir = tempdir::TempDir::new("test_timeline")?;
+        let path = format!("{}/timeline.json", temp_dir.path().to_str().unwrap());
+        timeline.flush(&path)?;
+        let restarted = Timeline::new(4);
+        restarted.record_at(200, TimelineCategory::RATE_LIMIT, "app2".into());
+        restarted.load(&path)?;
+        let events = restarted.query(None, None, &[]);
+        assert_eq!(4, events.len());
+        assert_eq!(TimelineCategory::DECOMMISSION, events[1].category);
+        assert_eq!(TimelineCategory::RATE_LIMIT, events[3].category);
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            events.iter().map(|e| e.seq).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+}
@@ -3,18 +3,25 @@ use crate::app::{
     WritingViewContext,
 };
 use crate::constant::StatusCode;
-use crate::metric::URPC_SEND_DATA_TRANSPORT_TIME;
+use crate::metric::{
+    URPC_COMMAND_ERRORS, URPC_COMMAND_IN_FLIGHT, URPC_COMMAND_PROCESSING_LATENCY,
+    URPC_SEND_DATA_TRANSPORT_TIME,
+};
 use crate::store::ResponseDataIndex::Local;
 use crate::store::{Block, LocalDataIndex, ResponseData};
 use crate::urpc::connection::Connection;
 use crate::urpc::frame::Frame;
+use crate::urpc::registry;
+use crate::urpc::registry::{CommandDescriptor, UNKNOWN_COMMAND_NAME};
 use crate::urpc::shutdown::Shutdown;
 use crate::util;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use await_tree::InstrumentAwait;
 use bytes::Bytes;
+use croaring::{JvmLegacy, Treemap};
 use log::{debug, error};
 use std::collections::HashMap;
+use std::future::Future;
 
 #[derive(Debug)]
 pub enum Command {
@@ -22,6 +29,8 @@ pub enum Command {
     GetMem(GetMemoryDataRequestCommand),
     GetLocalIndex(GetLocalDataIndexRequestCommand),
     GetLocalData(GetLocalDataRequestCommand),
+    GetPartitionFlushState(GetPartitionFlushStateRequestCommand),
+    Unknown(UnknownCommandRequestCommand),
 }
 
 impl Command {
@@ -31,24 +40,88 @@ impl Command {
             Frame::GetMemoryData(req) => Ok(Command::GetMem(req)),
             Frame::GetLocalDataIndex(req) => Ok(Command::GetLocalIndex(req)),
             Frame::GetLocalData(req) => Ok(Command::GetLocalData(req)),
-            _ => todo!(),
+            Frame::GetPartitionFlushState(req) => Ok(Command::GetPartitionFlushState(req)),
+            Frame::UnknownCommand(req) => Ok(Command::Unknown(req)),
+            _ => Err(anyhow!(
+                "Unexpected frame: {} as the request command",
+                frame
+            )),
         }
     }
 
+    pub fn descriptor(&self) -> Option<&'static CommandDescriptor> {
+        match self {
+            Command::Send(_) => Some(&registry::SEND_SHUFFLE_DATA),
+            Command::GetMem(_) => Some(&registry::GET_MEMORY_DATA),
+            Command::GetLocalIndex(_) => Some(&registry::GET_LOCAL_DATA_INDEX),
+            Command::GetLocalData(_) => Some(&registry::GET_LOCAL_DATA),
+            Command::GetPartitionFlushState(_) => Some(&registry::GET_PARTITION_FLUSH_STATE),
+            Command::Unknown(_) => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.descriptor()
+            .map(|descriptor| descriptor.name)
+            .unwrap_or(UNKNOWN_COMMAND_NAME)
+    }
+
     pub async fn apply(
         self,
         app_manager_ref: AppManagerRef,
         conn: &mut Connection,
         shutdown: &mut Shutdown,
     ) -> Result<()> {
-        match self {
-            Command::Send(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetMem(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetLocalIndex(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetLocalData(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            _ => {}
+        let name = self.name();
+        instrument(name, async move {
+            match self {
+                Command::Send(req) => req.apply(app_manager_ref, conn, shutdown).await,
+                Command::GetMem(req) => req.apply(app_manager_ref, conn, shutdown).await,
+                Command::GetLocalIndex(req) => req.apply(app_manager_ref, conn, shutdown).await,
+                Command::GetLocalData(req) => req.apply(app_manager_ref, conn, shutdown).await,
+                Command::GetPartitionFlushState(req) => {
+                    req.apply(app_manager_ref, conn, shutdown).await
+                }
+                Command::Unknown(req) => req.apply(conn).await,
+            }
+        })
+        .await
+    }
+}
+
+/// Records the latency, in-flight number and errors of the command invocation.
+async fn instrument<F: Future<Output = Result<()>>>(name: &str, f: F) -> Result<()> {
+    let in_flight = URPC_COMMAND_IN_FLIGHT.with_label_values(&[name]);
+    in_flight.inc();
+    let timer = URPC_COMMAND_PROCESSING_LATENCY
+        .with_label_values(&[name])
+        .start_timer();
+    let result = f.await;
+    timer.observe_duration();
+    in_flight.dec();
+    if result.is_err() {
+        URPC_COMMAND_ERRORS.with_label_values(&[name]).inc();
+    }
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownCommandRequestCommand {
+    pub(crate) request_id: i64,
+    pub(crate) command_id: u8,
+}
+
+impl UnknownCommandRequestCommand {
+    pub(crate) fn response(&self) -> RpcResponseCommand {
+        RpcResponseCommand {
+            request_id: self.request_id,
+            status_code: StatusCode::INVALID_REQUEST.into(),
+            ret_msg: format!("Unknown command id: {}", self.command_id),
         }
-        Ok(())
+    }
+
+    async fn apply(self, conn: &mut Connection) -> Result<()> {
+        write_response(conn, self.response()).await
     }
 }
 
@@ -91,19 +164,26 @@ impl GetMemoryDataRequestCommand {
 
         let app = app.unwrap();
         let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+        let serialized_expected_task_ids_bitmap = self
+            .expected_tasks_bitmap_raw
+            .as_ref()
+            .map(|raw| Treemap::deserialize::<JvmLegacy>(raw));
         let ctx = ReadingViewContext {
             uid,
             reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(
                 last_block_id,
                 read_buffer_size as i64,
             ),
-            serialized_expected_task_ids_bitmap: None,
+            serialized_expected_task_ids_bitmap,
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: app_manager_ref.read_deadline(None),
         };
 
         let response = match app.select(ctx).await {
             Err(e) => GetMemoryDataResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: e.reading_status_code().into(),
                 ret_msg: format!("Errors on getting memory data. err: {:#?}", e),
                 data: ResponseData::Mem(Default::default()),
             },
@@ -172,10 +252,35 @@ impl GetLocalDataRequestCommand {
 
         let app = app.unwrap();
         let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+        if let Some(data) = conn.read_coalescer.get(&uid, offset, length as i64) {
+            let command = GetLocalDataResponseCommand {
+                request_id,
+                status_code: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+                data,
+            };
+            let frame = Frame::GetLocalDataResponse(command);
+            conn.write_frame(&frame).await?;
+            return Ok(());
+        }
+
+        // the tiny read is expanded to the merged one, bounded by the flushed file length
+        let read_length = if conn.read_coalescer.is_enabled() {
+            let file_len = app
+                .get_partition_flush_state(&uid)
+                .await
+                .map_or(0, |state| state.last_flushed_offset);
+            conn.read_coalescer.expand(offset, length as i64, file_len)
+        } else {
+            length as i64
+        };
         let ctx = ReadingViewContext {
-            uid,
-            reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, length as i64),
+            uid: uid.clone(),
+            reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, read_length),
             serialized_expected_task_ids_bitmap: None,
+            latest_attempt_only: false,
+            min_write_sequence: None,
+            deadline: app_manager_ref.read_deadline(None),
         };
         let command = match app
             .select(ctx)
@@ -184,17 +289,23 @@ impl GetLocalDataRequestCommand {
         {
             Err(e) => GetLocalDataResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: e.reading_status_code().into(),
                 ret_msg: format!("Errors on getting file data. err: {:#?}", e),
                 data: Default::default(),
             },
             Ok(result) => {
                 if let ResponseData::Local(data) = result {
+                    let data = if read_length > length as i64 {
+                        conn.read_coalescer
+                            .put(uid, offset, length as i64, data.data)
+                    } else {
+                        data.data
+                    };
                     GetLocalDataResponseCommand {
                         request_id,
                         status_code: StatusCode::SUCCESS.into(),
                         ret_msg: "".to_string(),
-                        data: data.data,
+                        data,
                     }
                 } else {
                     GetLocalDataResponseCommand {
@@ -258,7 +369,11 @@ impl GetLocalDataIndexRequestCommand {
 
         let app = app.unwrap();
         let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
-        let ctx = ReadingIndexViewContext { partition_id: uid };
+        let ctx = ReadingIndexViewContext {
+            partition_id: uid,
+            entry_range: None,
+            deadline: app_manager_ref.read_deadline(None),
+        };
 
         let command = match app
             .list_index(ctx)
@@ -267,7 +382,7 @@ impl GetLocalDataIndexRequestCommand {
         {
             Err(err) => GetLocalDataIndexResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: err.reading_status_code().into(),
                 ret_msg: format!("Errors on listing local index. err: {:#?}", err),
                 data_index: Default::default(),
             },
@@ -287,6 +402,79 @@ impl GetLocalDataIndexRequestCommand {
     }
 }
 
+#[derive(Debug)]
+pub struct GetPartitionFlushStateResponseCommand {
+    pub(crate) request_id: i64,
+    pub(crate) status_code: i32,
+    pub(crate) ret_msg: String,
+    pub(crate) pending_spill_event_num: i64,
+    pub(crate) memory_buffered_bytes: i64,
+    pub(crate) last_flushed_offset: i64,
+}
+
+#[derive(Debug)]
+pub struct GetPartitionFlushStateRequestCommand {
+    pub(crate) request_id: i64,
+    pub(crate) app_id: String,
+    pub(crate) shuffle_id: i32,
+    pub(crate) partition_id: i32,
+}
+
+impl GetPartitionFlushStateRequestCommand {
+    pub(crate) async fn apply(
+        &self,
+        app_manager_ref: AppManagerRef,
+        conn: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> Result<()> {
+        let request_id = self.request_id;
+        let app_id = self.app_id.as_str();
+
+        let app = app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            let command = GetPartitionFlushStateResponseCommand {
+                request_id,
+                status_code: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in server side".to_string(),
+                pending_spill_event_num: 0,
+                memory_buffered_bytes: 0,
+                last_flushed_offset: 0,
+            };
+            let frame = Frame::GetPartitionFlushStateResponse(command);
+            conn.write_frame(&frame).await?;
+            return Ok(());
+        }
+
+        let app = app.unwrap();
+        let uid = PartitionedUId::from(app_id.to_string(), self.shuffle_id, self.partition_id);
+        let command = match app
+            .get_partition_flush_state(&uid)
+            .instrument_await(format!("getting partition flush state for app:{}", &app_id))
+            .await
+        {
+            Err(err) => GetPartitionFlushStateResponseCommand {
+                request_id,
+                status_code: StatusCode::INTERNAL_ERROR.into(),
+                ret_msg: format!("Errors on getting partition flush state. err: {:#?}", err),
+                pending_spill_event_num: 0,
+                memory_buffered_bytes: 0,
+                last_flushed_offset: 0,
+            },
+            Ok(state) => GetPartitionFlushStateResponseCommand {
+                request_id,
+                status_code: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+                pending_spill_event_num: state.pending_spill_event_num as i64,
+                memory_buffered_bytes: state.memory_buffered_bytes as i64,
+                last_flushed_offset: state.last_flushed_offset,
+            },
+        };
+        let frame = Frame::GetPartitionFlushStateResponse(command);
+        conn.write_frame(&frame).await?;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct GetMemoryDataResponseCommand {
     pub(crate) request_id: i64,
@@ -368,6 +556,22 @@ impl SendDataRequestCommand {
         let mut insert_len = 0;
 
         let blocks = self.blocks;
+        let written_len: i64 = blocks
+            .iter()
+            .flat_map(|(_, partition_blocks)| partition_blocks.iter())
+            .map(|block| block.length as i64)
+            .sum();
+        if let Err(err) = app.check_write_reservation(ticket_len, written_len) {
+            let _ = app.dec_allocated_from_budget(ticket_len);
+            let response = RpcResponseCommand {
+                request_id,
+                status_code: StatusCode::INTERNAL_ERROR.into(),
+                ret_msg: err.to_string(),
+            };
+            write_response(conn, response).await?;
+            return Ok(());
+        }
+
         for block in blocks {
             let partition_id = block.0;
             let partition_blocks = block.1;
@@ -413,3 +617,40 @@ impl SendDataRequestCommand {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::metric::{
+        URPC_COMMAND_ERRORS, URPC_COMMAND_IN_FLIGHT, URPC_COMMAND_PROCESSING_LATENCY,
+    };
+    use crate::urpc::command::instrument;
+    use anyhow::anyhow;
+
+    #[tokio::test]
+    async fn test_instrument_per_command() -> anyhow::Result<()> {
+        instrument("test_ok_command", async { Ok(()) }).await?;
+        instrument("test_ok_command", async { Ok(()) }).await?;
+        assert!(
+            instrument("test_err_command", async { Err(anyhow!("failed")) })
+                .await
+                .is_err()
+        );
+
+        let latency = |name: &str| {
+            URPC_COMMAND_PROCESSING_LATENCY
+                .with_label_values(&[name])
+                .get_sample_count()
+        };
+        assert_eq!(2, latency("test_ok_command"));
+        assert_eq!(1, latency("test_err_command"));
+
+        let errors = |name: &str| URPC_COMMAND_ERRORS.with_label_values(&[name]).get();
+        assert_eq!(0, errors("test_ok_command"));
+        assert_eq!(1, errors("test_err_command"));
+
+        for name in ["test_ok_command", "test_err_command"] {
+            assert_eq!(0, URPC_COMMAND_IN_FLIGHT.with_label_values(&[name]).get());
+        }
+        Ok(())
+    }
+}
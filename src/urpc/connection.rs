@@ -6,6 +6,7 @@ use tokio::net::TcpStream;
 use crate::error::WorkerError;
 use crate::metric::URPC_REQUEST_PARSING_LATENCY;
 use crate::urpc::frame::Frame;
+use crate::urpc::read_coalescer::{ReadCoalesceOptions, ReadCoalescer};
 use anyhow::Result;
 
 const INITIAL_BUFFER_LENGTH: usize = 1024 * 1024;
@@ -14,6 +15,8 @@ const INITIAL_BUFFER_LENGTH: usize = 1024 * 1024;
 pub struct Connection {
     stream: BufWriter<TcpStream>,
     buffer: BytesMut,
+    // the connection scoped merging of the tiny local reads
+    pub(crate) read_coalescer: ReadCoalescer,
 }
 
 impl Connection {
@@ -21,9 +24,15 @@ impl Connection {
         Connection {
             stream: BufWriter::new(socket),
             buffer: BytesMut::with_capacity(INITIAL_BUFFER_LENGTH),
+            read_coalescer: Default::default(),
         }
     }
 
+    pub fn with_read_coalescer(mut self, options: Option<ReadCoalesceOptions>) -> Self {
+        self.read_coalescer = ReadCoalescer::new(options);
+        self
+    }
+
     fn parse_frame(&mut self) -> Result<Option<Frame>> {
         let mut buf = Cursor::new(&self.buffer[..]);
 
@@ -5,8 +5,10 @@ use crate::store::{Block, BytesWrapper};
 use crate::urpc::command::{
     GetLocalDataIndexRequestCommand, GetLocalDataIndexResponseCommand, GetLocalDataRequestCommand,
     GetLocalDataResponseCommand, GetMemoryDataRequestCommand, GetMemoryDataResponseCommand,
-    RpcResponseCommand, SendDataRequestCommand,
+    GetPartitionFlushStateRequestCommand, GetPartitionFlushStateResponseCommand,
+    RpcResponseCommand, SendDataRequestCommand, UnknownCommandRequestCommand,
 };
+use crate::urpc::registry;
 use anyhow::{Error, Result};
 use bytes::{Buf, Bytes};
 use log::warn;
@@ -16,8 +18,7 @@ use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::io::{Cursor, IoSlice};
 use strum_macros::EnumVariantNames;
-use tokio::io::{AsyncWriteExt, BufWriter};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tracing::{debug, info};
 
 ///
@@ -41,7 +42,7 @@ impl From<TryFromPrimitiveError<MessageType>> for WorkerError {
 #[allow(non_camel_case_types)]
 #[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
 #[repr(u8)]
-enum MessageType {
+pub(crate) enum MessageType {
     SendShuffleData = 3,
     GetMemoryData = 6,
     GetMemoryDataResponse = 16,
@@ -52,6 +53,9 @@ enum MessageType {
     GetLocalData = 5,
     GetLocalDataResponse = 15,
 
+    GetPartitionFlushState = 30,
+    GetPartitionFlushStateResponse = 40,
+
     RpcResponse = 0,
 }
 
@@ -77,12 +81,20 @@ pub enum Frame {
     #[strum(serialize = "GetLocalDataResponse")]
     GetLocalDataResponse(GetLocalDataResponseCommand),
 
+    #[strum(serialize = "GetPartitionFlushState")]
+    GetPartitionFlushState(GetPartitionFlushStateRequestCommand),
+    #[strum(serialize = "GetPartitionFlushStateResponse")]
+    GetPartitionFlushStateResponse(GetPartitionFlushStateResponseCommand),
+
     #[strum(serialize = "RpcResponse")]
     RpcResponse(RpcResponseCommand),
+
+    #[strum(serialize = "UnknownCommand")]
+    UnknownCommand(UnknownCommandRequestCommand),
 }
 
 impl Frame {
-    pub async fn write(stream: &mut BufWriter<TcpStream>, frame: &Frame) -> Result<()> {
+    pub async fn write<W: AsyncWrite + Unpin>(stream: &mut W, frame: &Frame) -> Result<()> {
         match frame {
             Frame::GetLocalDataResponse(resp) => {
                 debug!("gotten the localfile data response");
@@ -201,6 +213,59 @@ impl Frame {
                 }
                 return Ok(());
             }
+            Frame::GetPartitionFlushStateResponse(resp) => {
+                let request_id = resp.request_id;
+                let status_code = resp.status_code;
+
+                let msg = &resp.ret_msg;
+                let msg_bytes = msg.as_bytes();
+
+                // header
+                stream
+                    .write_i32(msg_bytes.len() as i32 + 8 + 4 + 4 + 3 * 8)
+                    .await?;
+                stream
+                    .write_u8(MessageType::GetPartitionFlushStateResponse as u8)
+                    .await?;
+                stream.write_i32(0).await?;
+
+                // partial content with general response info
+                stream.write_i64(request_id).await?;
+                stream.write_i32(status_code).await?;
+
+                stream.write_i32(msg_bytes.len() as i32).await?;
+                stream.write_all(msg_bytes).await?;
+
+                // write the flush state
+                stream.write_i64(resp.pending_spill_event_num).await?;
+                stream.write_i64(resp.memory_buffered_bytes).await?;
+                stream.write_i64(resp.last_flushed_offset).await?;
+                return Ok(());
+            }
+            Frame::GetLocalData(req) => {
+                // the client side request, which is used to read the localfile data
+                let app_id_bytes = req.app_id.as_bytes();
+
+                // header
+                stream
+                    .write_i32(8 + 4 + app_id_bytes.len() as i32 + 4 * 4 + 8 + 4 + 8)
+                    .await?;
+                stream.write_u8(MessageType::GetLocalData as u8).await?;
+                stream.write_i32(0).await?;
+
+                // content
+                stream.write_i64(req.request_id).await?;
+                stream.write_i32(app_id_bytes.len() as i32).await?;
+                stream.write_all(app_id_bytes).await?;
+                stream.write_i32(req.shuffle_id).await?;
+                stream.write_i32(req.partition_id).await?;
+                stream.write_i32(req.partition_num_per_range).await?;
+                stream.write_i32(req.partition_num).await?;
+                stream.write_i64(req.offset).await?;
+                stream.write_i32(req.length).await?;
+                stream.write_i64(req.timestamp).await?;
+                return Ok(());
+            }
             Frame::RpcResponse(resp) => {
                 let request_id = resp.request_id;
                 let status_code = resp.status_code;
@@ -242,7 +307,7 @@ impl Frame {
         Ok(())
     }
 
-    fn parse_to_get_localfile_data_command(
+    pub(crate) fn parse_to_get_localfile_data_command(
         src: &mut Cursor<&[u8]>,
     ) -> Result<GetLocalDataRequestCommand> {
         debug!("Gotten the localfile data request");
@@ -270,7 +335,7 @@ impl Frame {
         })
     }
 
-    fn parse_to_send_shuffle_data_command(
+    pub(crate) fn parse_to_send_shuffle_data_command(
         src: &mut Cursor<&[u8]>,
     ) -> Result<SendDataRequestCommand> {
         let request_id = get_i64(src)?;
@@ -332,7 +397,7 @@ impl Frame {
         return Ok(req);
     }
 
-    fn parse_to_get_localfile_index_command(
+    pub(crate) fn parse_to_get_localfile_index_command(
         src: &mut Cursor<&[u8]>,
     ) -> Result<GetLocalDataIndexRequestCommand> {
         debug!("Gotten the localfile index request");
@@ -353,7 +418,23 @@ impl Frame {
         })
     }
 
-    fn parse_to_get_memory_data_command(
+    pub(crate) fn parse_to_get_partition_flush_state_command(
+        src: &mut Cursor<&[u8]>,
+    ) -> Result<GetPartitionFlushStateRequestCommand> {
+        let request_id = get_i64(src)?;
+        let app_id = get_string(src)?;
+        let shuffle_id = get_i32(src)?;
+        let partition_id = get_i32(src)?;
+
+        Ok(GetPartitionFlushStateRequestCommand {
+            request_id,
+            app_id,
+            shuffle_id,
+            partition_id,
+        })
+    }
+
+    pub(crate) fn parse_to_get_memory_data_command(
         src: &mut Cursor<&[u8]>,
     ) -> Result<GetMemoryDataRequestCommand> {
         let request_id = get_i64(src)?;
@@ -387,44 +468,52 @@ impl Frame {
             return Err(WorkerError::STREAM_ABNORMAL);
         }
 
-        let msg_type = MessageType::try_from(msg_type);
-        match msg_type {
-            Err(e) => return Err(WorkerError::STREAM_MESSAGE_TYPE_NOT_FOUND),
-            _ => {}
+        if msg_type == MessageType::RpcResponse as u8 {
+            let request_id = get_i64(src)?;
+            let status_code = get_i32(src)?;
+            let ret_msg = get_string(src)?;
+            return Ok(Frame::RpcResponse(RpcResponseCommand {
+                request_id,
+                status_code,
+                ret_msg,
+            }));
         }
 
-        match msg_type? {
-            MessageType::GetLocalData => {
-                let command = Frame::parse_to_get_localfile_data_command(src)?;
-                return Ok(Frame::GetLocalData(command));
-            }
-            MessageType::GetLocalDataIndex => {
-                let command = Frame::parse_to_get_localfile_index_command(src)?;
-                return Ok(Frame::GetLocalDataIndex(command));
-            }
-            MessageType::GetMemoryData => {
-                let command = Frame::parse_to_get_memory_data_command(src)?;
-                return Ok(Frame::GetMemoryData(command));
-            }
-            MessageType::SendShuffleData => {
-                let command = Frame::parse_to_send_shuffle_data_command(src)?;
-                return Ok(Frame::SendShuffleData(command));
-            }
-            MessageType::RpcResponse => {
-                let request_id = get_i64(src)?;
-                let status_code = get_i32(src)?;
-                let ret_msg = get_string(src)?;
-                return Ok(Frame::RpcResponse(RpcResponseCommand {
+        if msg_type == MessageType::GetLocalDataResponse as u8 {
+            let request_id = get_i64(src)?;
+            let status_code = get_i32(src)?;
+            let ret_msg = get_string(src)?;
+            // the body is the raw file data without the length prefix
+            let data = Bytes::copy_from_slice(&Buf::chunk(src)[..body_len as usize]);
+            skip(src, body_len as usize)?;
+            return Ok(Frame::GetLocalDataResponse(GetLocalDataResponseCommand {
+                request_id,
+                status_code,
+                ret_msg,
+                data,
+            }));
+        }
+
+        match registry::lookup(msg_type) {
+            Some(descriptor) => Ok((descriptor.parse)(src)?),
+            None => {
+                // the request id is always the leading field of the request content,
+                // which is used to respond the unknown command to the client.
+                let request_id = if encode_msg_len >= 8 {
+                    get_i64(src)?
+                } else {
+                    -1
+                };
+                warn!(
+                    "Unknown urpc command id: {}. request_id: {}",
+                    msg_type, request_id
+                );
+                Ok(Frame::UnknownCommand(UnknownCommandRequestCommand {
                     request_id,
-                    status_code,
-                    ret_msg,
-                }));
-            }
-            _ => {
-                todo!()
+                    command_id: msg_type,
+                }))
             }
         }
-        todo!()
     }
 }
 
@@ -506,12 +595,82 @@ fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, WorkerError> {
 
 #[cfg(test)]
 mod test {
+    use crate::constant::StatusCode;
     use crate::error::WorkerError;
     use crate::urpc::frame::Frame;
     use anyhow::Result;
     use bytes::{BufMut, Bytes, BytesMut};
     use std::io::Cursor;
 
+    fn encode_request(message_type: u8, content: &[u8]) -> BytesMut {
+        let mut request = BytesMut::new();
+        request.put_i32(content.len() as i32);
+        request.put_u8(message_type);
+        request.put_i32(0);
+        request.put_slice(content);
+        request
+    }
+
+    fn put_string(buf: &mut BytesMut, value: &str) {
+        buf.put_i32(value.len() as i32);
+        buf.put_slice(value.as_bytes());
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Frame> {
+        let cursor = &mut Cursor::new(bytes);
+        Frame::check(cursor)?;
+        assert_eq!(bytes.len() as u64, cursor.position());
+        let cursor = &mut Cursor::new(bytes);
+        Ok(Frame::parse(cursor)?)
+    }
+
+    #[test]
+    fn frame_parse_registered_command() -> Result<()> {
+        let mut content = BytesMut::new();
+        content.put_i64(7);
+        put_string(&mut content, "app-1");
+        content.put_i32(1);
+        content.put_i32(2);
+
+        match parse(&encode_request(30, &content))? {
+            Frame::GetPartitionFlushState(req) => {
+                assert_eq!(7, req.request_id);
+                assert_eq!("app-1", req.app_id);
+                assert_eq!(1, req.shuffle_id);
+                assert_eq!(2, req.partition_id);
+            }
+            frame => panic!("unexpected frame: {}", frame),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_unknown_command_round_trip() -> Result<()> {
+        let mut content = BytesMut::new();
+        content.put_i64(11);
+        content.put_i32(0);
+
+        let unknown = match parse(&encode_request(99, &content))? {
+            Frame::UnknownCommand(req) => req,
+            frame => panic!("unexpected frame: {}", frame),
+        };
+        assert_eq!(11, unknown.request_id);
+        assert_eq!(99, unknown.command_id);
+
+        // the error frame carrying the offending id is decoded by the client side
+        let mut written: Vec<u8> = vec![];
+        Frame::write(&mut written, &Frame::RpcResponse(unknown.response())).await?;
+        match parse(&written)? {
+            Frame::RpcResponse(resp) => {
+                assert_eq!(11, resp.request_id);
+                assert_eq!(StatusCode::INVALID_REQUEST as i32, resp.status_code);
+                assert_eq!("Unknown command id: 99", resp.ret_msg);
+            }
+            frame => panic!("unexpected frame: {}", frame),
+        }
+        Ok(())
+    }
+
     ///
     /// The encode urpc:
     ///
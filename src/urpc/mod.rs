@@ -2,5 +2,7 @@
 pub mod command;
 pub mod connection;
 pub mod frame;
+pub mod read_coalescer;
+pub mod registry;
 pub mod server;
 pub mod shutdown;
@@ -0,0 +1,143 @@
+use crate::app::PartitionedUId;
+use crate::config::ReadCoalesceConfig;
+use crate::metric::URPC_COALESCED_READ_COUNTER;
+use crate::util;
+use anyhow::Result;
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+
+/// The parsed options of the read coalescing, shared by all the connections.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadCoalesceOptions {
+    tiny_read_size: i64,
+    merged_read_size: i64,
+    window: Duration,
+}
+
+impl ReadCoalesceOptions {
+    pub fn parse(conf: &ReadCoalesceConfig) -> Result<Self> {
+        Ok(Self {
+            tiny_read_size: util::try_parse_raw_to_bytesize(&conf.tiny_read_size)? as i64,
+            merged_read_size: util::try_parse_raw_to_bytesize(&conf.merged_read_size)? as i64,
+            window: Duration::from_millis(conf.window_ms),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MergedRead {
+    uid: PartitionedUId,
+    offset: i64,
+    data: Bytes,
+    read_at: Instant,
+}
+
+/// Coalesces the rapid tiny local reads of one partition from one connection. The tiny
+/// read is expanded to the merged size, and the following tiny reads covered by it are
+/// served from memory within the window.
+#[derive(Debug, Default)]
+pub struct ReadCoalescer {
+    options: Option<ReadCoalesceOptions>,
+    merged: Option<MergedRead>,
+}
+
+impl ReadCoalescer {
+    pub fn new(options: Option<ReadCoalesceOptions>) -> Self {
+        Self {
+            options,
+            merged: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.options.is_some()
+    }
+
+    /// Returns the data if the range is fully covered by the merged read in the window.
+    pub fn get(&mut self, uid: &PartitionedUId, offset: i64, len: i64) -> Option<Bytes> {
+        let options = self.options.as_ref()?;
+        let merged = self.merged.as_ref()?;
+        if merged.read_at.elapsed() > options.window {
+            self.merged = None;
+            return None;
+        }
+        if &merged.uid != uid
+            || offset < merged.offset
+            || offset + len > merged.offset + merged.data.len() as i64
+        {
+            return None;
+        }
+        URPC_COALESCED_READ_COUNTER.inc();
+        let start = (offset - merged.offset) as usize;
+        Some(merged.data.slice(start..start + len as usize))
+    }
+
+    /// Returns the expanded length of the tiny read, which is bounded by the file length.
+    pub fn expand(&self, offset: i64, len: i64, file_len: i64) -> i64 {
+        match self.options.as_ref() {
+            Some(options) if len <= options.tiny_read_size => {
+                len.max(options.merged_read_size.min(file_len - offset))
+            }
+            _ => len,
+        }
+    }
+
+    /// Keeps the merged read and returns the originally requested part.
+    pub fn put(&mut self, uid: PartitionedUId, offset: i64, len: i64, data: Bytes) -> Bytes {
+        let requested = data.slice(0..(len.max(0) as usize).min(data.len()));
+        if self.options.is_some() && data.len() > requested.len() {
+            self.merged = Some(MergedRead {
+                uid,
+                offset,
+                data,
+                read_at: Instant::now(),
+            });
+        }
+        requested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::PartitionedUId;
+    use crate::config::ReadCoalesceConfig;
+    use crate::urpc::read_coalescer::{ReadCoalesceOptions, ReadCoalescer};
+    use bytes::Bytes;
+    use std::time::Duration;
+
+    #[test]
+    fn test_coalesce() -> anyhow::Result<()> {
+        let options = ReadCoalesceOptions::parse(&ReadCoalesceConfig {
+            tiny_read_size: "4B".to_string(),
+            merged_read_size: "16B".to_string(),
+            window_ms: 200,
+        })?;
+        let mut coalescer = ReadCoalescer::new(Some(options));
+        let uid = PartitionedUId::from("app".to_string(), 1, 0);
+
+        // the tiny read is expanded and bounded by the file length
+        assert_eq!(16, coalescer.expand(0, 4, 100));
+        assert_eq!(10, coalescer.expand(0, 4, 10));
+        assert_eq!(8, coalescer.expand(0, 8, 100));
+
+        let data = Bytes::from_static(b"0123456789abcdef");
+        assert_eq!(b"0123", coalescer.put(uid.clone(), 0, 4, data).as_ref());
+        assert_eq!(b"4567", coalescer.get(&uid, 4, 4).unwrap().as_ref());
+        assert_eq!(None, coalescer.get(&uid, 14, 4));
+        assert_eq!(
+            None,
+            coalescer.get(&PartitionedUId::from("app".to_string(), 1, 1), 4, 4)
+        );
+
+        // expired after the window
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(None, coalescer.get(&uid, 4, 4));
+
+        // nothing is merged when disabled
+        let mut coalescer = ReadCoalescer::new(None);
+        assert_eq!(4, coalescer.expand(0, 4, 100));
+        assert_eq!(None, coalescer.get(&uid, 0, 4));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use crate::urpc::frame::{Frame, MessageType};
+use anyhow::Result;
+use std::io::Cursor;
+
+/// The declarative descriptor of one urpc request command. The new command
+/// only needs to be registered into the `COMMAND_REGISTRY` to be parsed.
+#[derive(Debug)]
+pub struct CommandDescriptor {
+    pub id: u8,
+    pub name: &'static str,
+    // whether the request carries the shuffle block data
+    pub expects_payload: bool,
+    // whether the request changes the state of the worker
+    pub mutates_state: bool,
+    pub(crate) parse: fn(&mut Cursor<&[u8]>) -> Result<Frame>,
+}
+
+pub const UNKNOWN_COMMAND_NAME: &str = "UnknownCommand";
+
+pub static SEND_SHUFFLE_DATA: CommandDescriptor = CommandDescriptor {
+    id: MessageType::SendShuffleData as u8,
+    name: "SendShuffleData",
+    expects_payload: true,
+    mutates_state: true,
+    parse: parse_send_shuffle_data,
+};
+
+pub static GET_MEMORY_DATA: CommandDescriptor = CommandDescriptor {
+    id: MessageType::GetMemoryData as u8,
+    name: "GetMemoryData",
+    expects_payload: false,
+    mutates_state: false,
+    parse: parse_get_memory_data,
+};
+
+pub static GET_LOCAL_DATA_INDEX: CommandDescriptor = CommandDescriptor {
+    id: MessageType::GetLocalDataIndex as u8,
+    name: "GetLocalDataIndex",
+    expects_payload: false,
+    mutates_state: false,
+    parse: parse_get_local_data_index,
+};
+
+pub static GET_LOCAL_DATA: CommandDescriptor = CommandDescriptor {
+    id: MessageType::GetLocalData as u8,
+    name: "GetLocalData",
+    expects_payload: false,
+    mutates_state: false,
+    parse: parse_get_local_data,
+};
+
+pub static GET_PARTITION_FLUSH_STATE: CommandDescriptor = CommandDescriptor {
+    id: MessageType::GetPartitionFlushState as u8,
+    name: "GetPartitionFlushState",
+    expects_payload: false,
+    mutates_state: false,
+    parse: parse_get_partition_flush_state,
+};
+
+pub static COMMAND_REGISTRY: [&CommandDescriptor; 5] = [
+    &SEND_SHUFFLE_DATA,
+    &GET_MEMORY_DATA,
+    &GET_LOCAL_DATA_INDEX,
+    &GET_LOCAL_DATA,
+    &GET_PARTITION_FLUSH_STATE,
+];
+
+pub fn lookup(id: u8) -> Option<&'static CommandDescriptor> {
+    COMMAND_REGISTRY
+        .iter()
+        .find(|descriptor| descriptor.id == id)
+        .copied()
+}
+
+fn parse_send_shuffle_data(src: &mut Cursor<&[u8]>) -> Result<Frame> {
+    Ok(Frame::SendShuffleData(
+        Frame::parse_to_send_shuffle_data_command(src)?,
+    ))
+}
+
+fn parse_get_memory_data(src: &mut Cursor<&[u8]>) -> Result<Frame> {
+    Ok(Frame::GetMemoryData(
+        Frame::parse_to_get_memory_data_command(src)?,
+    ))
+}
+
+fn parse_get_local_data_index(src: &mut Cursor<&[u8]>) -> Result<Frame> {
+    Ok(Frame::GetLocalDataIndex(
+        Frame::parse_to_get_localfile_index_command(src)?,
+    ))
+}
+
+fn parse_get_local_data(src: &mut Cursor<&[u8]>) -> Result<Frame> {
+    Ok(Frame::GetLocalData(
+        Frame::parse_to_get_localfile_data_command(src)?,
+    ))
+}
+
+fn parse_get_partition_flush_state(src: &mut Cursor<&[u8]>) -> Result<Frame> {
+    Ok(Frame::GetPartitionFlushState(
+        Frame::parse_to_get_partition_flush_state_command(src)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::urpc::registry::{lookup, COMMAND_REGISTRY};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry() {
+        let ids: HashSet<u8> = COMMAND_REGISTRY.iter().map(|d| d.id).collect();
+        assert_eq!(COMMAND_REGISTRY.len(), ids.len());
+
+        assert_eq!("SendShuffleData", lookup(3).unwrap().name);
+        assert_eq!("GetPartitionFlushState", lookup(30).unwrap().name);
+        // the response message types are not the commands
+        assert!(lookup(0).is_none());
+        assert!(lookup(16).is_none());
+        assert!(lookup(200).is_none());
+    }
+}
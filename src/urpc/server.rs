@@ -1,38 +1,210 @@
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::sync::{broadcast, mpsc, OwnedSemaphorePermit, Semaphore};
 
 use crate::urpc::connection::Connection;
+use crate::urpc::read_coalescer::ReadCoalesceOptions;
 use crate::urpc::shutdown::Shutdown;
 
 use crate::app::AppManagerRef;
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::error::WorkerError;
-use crate::metric::{URPC_CONNECTION_NUMBER, URPC_REQUEST_PROCESSING_LATENCY};
+use crate::metric::{
+    URPC_CONNECTIONS_IN_FLIGHT, URPC_CONNECTION_IDLE_TIMEOUT_COUNTER, URPC_CONNECTION_NUMBER,
+    URPC_LISTENER_FAILURE_COUNTER, URPC_LISTENER_RESTART_COUNTER,
+};
 use crate::urpc::command::Command;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use await_tree::InstrumentAwait;
 use tracing::Instrument;
 
-const MAX_CONNECTIONS: usize = 40000;
+// the saturation of the connections is warned at most once in this interval
+const SATURATION_WARN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The connection permit, which is tracked by the in-flight gauge until the handler completes.
+struct ConnectionPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ConnectionPermit {
+    fn new(permit: OwnedSemaphorePermit) -> Self {
+        URPC_CONNECTIONS_IN_FLIGHT.inc();
+        Self { _permit: permit }
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        URPC_CONNECTIONS_IN_FLIGHT.dec();
+    }
+}
+
+/// The options of the urpc server, shared by all the listeners.
+#[derive(Clone, Copy, Debug)]
+pub struct UrpcServerOptions {
+    pub max_connections: usize,
+    pub idle_timeout: Duration,
+    pub shutdown_grace_period: Duration,
+    // the coalescing of the tiny local reads is disabled if None
+    pub read_coalesce: Option<ReadCoalesceOptions>,
+    // the accept errors are retried with the doubled backoff until exceeding this
+    pub accept_max_backoff: Duration,
+    // the listener exited with errors is rebound on the same address at most this times
+    pub listener_max_restarts: usize,
+    pub listener_restart_backoff: Duration,
+    // the restarts are counted from zero again after the listener runs healthy for this period
+    pub listener_healthy_reset: Duration,
+}
+
+/// Binds the urpc socket with the reused port, so that the multiple listeners
+/// and the restarted one could share the same address.
+pub fn bind(addr: SocketAddr) -> Result<std::net::TcpListener> {
+    let sock = socket2::Socket::new(
+        match addr {
+            SocketAddr::V4(_) => socket2::Domain::IPV4,
+            SocketAddr::V6(_) => socket2::Domain::IPV6,
+        },
+        socket2::Type::STREAM,
+        None,
+    )?;
+
+    sock.set_reuse_address(true)?;
+    sock.set_reuse_port(true)?;
+    sock.set_nonblocking(true)?;
+    sock.bind(&addr.into())?;
+    sock.listen(8192)?;
+    Ok(sock.into())
+}
 
 struct Listener {
     listener: TcpListener,
+    // the bound address to restart the listener on, which is unknown if None
+    addr: Option<SocketAddr>,
+    options: UrpcServerOptions,
     limit_connections: Arc<Semaphore>,
+    last_saturation_warn: Option<Instant>,
     notify_shutdown: broadcast::Sender<()>,
+    // dropped to abort the handlers not finished in the shutdown grace period
+    notify_abort: broadcast::Sender<()>,
     shutdown_complete_tx: mpsc::Sender<()>,
 }
 
+/// Bounds the restarts of the failed listener. The restarts are counted from zero again once
+/// the listener has run healthy for the reset period, so that the sporadic failures over the
+/// long uptime won't exhaust the max restarts.
+struct RestartTracker {
+    restarts: usize,
+    max_restarts: usize,
+    healthy_reset: Duration,
+    last_started: Instant,
+}
+
+impl RestartTracker {
+    fn new(max_restarts: usize, healthy_reset: Duration) -> Self {
+        Self {
+            restarts: 0,
+            max_restarts,
+            healthy_reset,
+            last_started: Instant::now(),
+        }
+    }
+
+    fn on_started(&mut self) {
+        self.last_started = Instant::now();
+    }
+
+    fn on_failure(&mut self) {
+        if self.restarts > 0 && self.last_started.elapsed() >= self.healthy_reset {
+            info!(
+                "Resetting the urpc listener restarts: {} after running healthy for {:?}",
+                self.restarts,
+                self.last_started.elapsed()
+            );
+            self.restarts = 0;
+        }
+    }
+
+    /// Returns false if the max restarts are exhausted.
+    fn try_restart(&mut self) -> bool {
+        if self.restarts >= self.max_restarts {
+            return false;
+        }
+        self.restarts += 1;
+        true
+    }
+}
+
 impl Listener {
+    /// Supervises the accepting loop. Once it exits with errors, the listener is rebound
+    /// on the same address after the backoff, until the max restarts are exhausted.
+    /// The connections accepted before are kept untouched by the restarting.
+    async fn supervise(&mut self, app_manager_ref: AppManagerRef) -> Result<()> {
+        let mut tracker = RestartTracker::new(
+            self.options.listener_max_restarts,
+            self.options.listener_healthy_reset,
+        );
+        loop {
+            tracker.on_started();
+            let err = match self.run(app_manager_ref.clone()).await {
+                Ok(_) => return Ok(()),
+                Err(err) => err,
+            };
+            URPC_LISTENER_FAILURE_COUNTER.inc();
+            tracker.on_failure();
+            error!(
+                "The urpc listener on {:?} exits with errors. restarts: {}/{}. err: {:#?}",
+                self.addr, tracker.restarts, tracker.max_restarts, err
+            );
+
+            loop {
+                if !tracker.try_restart() {
+                    return Err(anyhow!(
+                        "The urpc listener on {:?} is given up after {} restarts",
+                        self.addr,
+                        tracker.restarts
+                    ));
+                }
+                tokio::time::sleep(self.options.listener_restart_backoff).await;
+                match self.rebind() {
+                    Ok(_) => {
+                        URPC_LISTENER_RESTART_COUNTER.inc();
+                        warn!(
+                            "The urpc listener on {:?} has been restarted. restarts: {}/{}",
+                            self.addr, tracker.restarts, tracker.max_restarts
+                        );
+                        break;
+                    }
+                    Err(err) => {
+                        error!(
+                            "Errors on restarting the urpc listener on {:?}. restarts: {}/{}. err: {:#?}",
+                            self.addr, tracker.restarts, tracker.max_restarts, err
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn rebind(&mut self) -> Result<()> {
+        let addr = self
+            .addr
+            .ok_or_else(|| anyhow!("Unknown bound address of the urpc listener"))?;
+        self.listener = TcpListener::from_std(bind(addr)?)?;
+        Ok(())
+    }
+
     async fn run(&mut self, app_manager_ref: AppManagerRef) -> Result<()> {
         debug!("Accepting inbound connections");
 
         loop {
             let app_manager = app_manager_ref.clone();
+            if self.limit_connections.available_permits() == 0 {
+                self.warn_saturation();
+            }
             let permit = self
                 .limit_connections
                 .clone()
@@ -41,14 +213,18 @@ impl Listener {
                 .unwrap();
 
             let socket = self.accept().await?;
+            // the in-flight connections are only counted after accepted
+            let permit = ConnectionPermit::new(permit);
             let addr = socket.peer_addr()?.to_string();
             debug!("Accepted connection from client: {}", &addr);
 
             let mut handler = Handler {
-                connection: Connection::new(socket),
+                connection: Connection::new(socket).with_read_coalescer(self.options.read_coalesce),
+                idle_timeout: self.options.idle_timeout,
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
             };
+            let mut abort = self.notify_abort.subscribe();
 
             let await_registry = AWAIT_TREE_REGISTRY.clone();
             let await_root = await_registry
@@ -56,8 +232,15 @@ impl Listener {
                 .await;
             tokio::spawn(await_root.instrument(async move {
                 URPC_CONNECTION_NUMBER.inc();
-                if let Err(error) = handler.run(app_manager).await {
-                    error!("Errors on handling the request. {:#?}", error);
+                tokio::select! {
+                    res = handler.run(app_manager) => {
+                        if let Err(error) = res {
+                            error!("Errors on handling the request. {:#?}", error);
+                        }
+                    }
+                    _ = abort.recv() => {
+                        warn!("Aborting the urpc connection with remote client: {} not drained in the shutdown grace period", &addr);
+                    }
                 }
                 drop(permit);
                 URPC_CONNECTION_NUMBER.dec();
@@ -65,22 +248,39 @@ impl Listener {
         }
     }
 
+    fn warn_saturation(&mut self) {
+        if self
+            .last_saturation_warn
+            .map_or(false, |last| last.elapsed() < SATURATION_WARN_INTERVAL)
+        {
+            return;
+        }
+        self.last_saturation_warn = Some(Instant::now());
+        warn!(
+            "The urpc connections have reached the max limit: {}, and the new ones will wait",
+            self.options.max_connections
+        );
+    }
+
     async fn accept(&mut self) -> Result<TcpStream> {
-        let mut backoff = 1;
+        let mut backoff = Duration::from_secs(1);
 
         loop {
             match self.listener.accept().await {
                 Ok((socket, _)) => return Ok(socket),
                 Err(err) => {
-                    if backoff > 64 {
+                    if backoff > self.options.accept_max_backoff {
                         return Err(err.into());
                     }
+                    warn!(
+                        "Errors on accepting the urpc connection, retrying after {:?}. err: {:?}",
+                        backoff, err
+                    );
                 }
             }
 
-            tokio::time::sleep(Duration::from_secs(backoff)).await;
+            tokio::time::sleep(backoff).await;
             backoff *= 2;
-            info!("Backoff: {}", backoff);
         }
     }
 }
@@ -88,20 +288,33 @@ impl Listener {
 #[derive(Debug)]
 struct Handler {
     connection: Connection,
+    // the connection is closed if no frame arrives within this time,
+    // to release the permit held by the half-open connection.
+    idle_timeout: Duration,
     shutdown: Shutdown,
     _shutdown_complete: mpsc::Sender<()>,
 }
 
 impl Handler {
     /// when the shutdown signal is received, the connection is processed
-    /// util it reaches a safe state, at which point it is terminated
+    /// util it reaches a safe state, at which point it is terminated.
+    /// The in-flight command is always applied to the end, and the shutdown
+    /// is only checked between the commands.
     async fn run(&mut self, app_manager_ref: AppManagerRef) -> Result<(), WorkerError> {
         while !self.shutdown.is_shutdown() {
             let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+                biased;
                 _ = self.shutdown.recv() => {
                     return Ok(());
                 },
+                res = tokio::time::timeout(self.idle_timeout, self.connection.read_frame()) => match res {
+                    Ok(res) => res?,
+                    Err(_) => {
+                        URPC_CONNECTION_IDLE_TIMEOUT_COUNTER.inc();
+                        warn!("Closing the urpc connection idle for more than {:?}", self.idle_timeout);
+                        return Ok(());
+                    }
+                },
             };
 
             let frame = match maybe_frame {
@@ -109,9 +322,6 @@ impl Handler {
                 None => return Ok(()),
             };
 
-            let _ = URPC_REQUEST_PROCESSING_LATENCY
-                .with_label_values(&[&format!("{}", &frame)])
-                .start_timer();
             Command::from_frame(frame)?
                 .apply(
                     app_manager_ref.clone(),
@@ -125,19 +335,30 @@ impl Handler {
     }
 }
 
-pub async fn run(listener: TcpListener, shutdown: impl Future, app_manager_ref: AppManagerRef) {
+pub async fn run(
+    listener: TcpListener,
+    options: UrpcServerOptions,
+    shutdown: impl Future,
+    app_manager_ref: AppManagerRef,
+) {
     let (notify_shutdown, _) = broadcast::channel(1);
+    let (notify_abort, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
 
+    let addr = listener.local_addr().ok();
     let mut server = Listener {
         listener,
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        addr,
+        options,
+        limit_connections: Arc::new(Semaphore::new(options.max_connections)),
+        last_saturation_warn: None,
         notify_shutdown,
+        notify_abort,
         shutdown_complete_tx,
     };
 
     tokio::select! {
-        res = server.run(app_manager_ref) => {
+        res = server.supervise(app_manager_ref) => {
             if let Err(err) = res {
                 error!("Errors on running urpc server. err: {:#?}", err);
             }
@@ -150,6 +371,7 @@ pub async fn run(listener: TcpListener, shutdown: impl Future, app_manager_ref:
     let Listener {
         shutdown_complete_tx,
         notify_shutdown,
+        notify_abort,
         ..
     } = server;
 
@@ -158,19 +380,66 @@ pub async fn run(listener: TcpListener, shutdown: impl Future, app_manager_ref:
     drop(notify_shutdown);
     drop(shutdown_complete_tx);
 
-    let _ = shutdown_complete_rx.recv().await;
+    // the in-flight commands are drained in the grace period, and then aborted forcibly
+    if tokio::time::timeout(options.shutdown_grace_period, shutdown_complete_rx.recv())
+        .await
+        .is_err()
+    {
+        warn!(
+            "The urpc connections are not drained in the shutdown grace period: {:?}, aborting them",
+            options.shutdown_grace_period
+        );
+        drop(notify_abort);
+        let _ = shutdown_complete_rx.recv().await;
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::app::AppManager;
-    use crate::config::Config;
+    use crate::app::test::{mock_config, mock_writing_context};
+    use crate::app::{AppManager, PartitionedUId, WritingViewContext};
+    use crate::chaos::{FaultMode, FaultOperation, FaultSpec, FaultTarget, FAULT_INJECTOR};
+    use crate::config::{Config, ReadCoalesceConfig, StorageType};
     use crate::config_reconfigure::ReconfigurableConfManager;
+    use crate::constant::StatusCode;
     use crate::decommission::DecommissionManager;
+    use crate::health_service::HealthService;
+    use crate::metric::{
+        URPC_COALESCED_READ_COUNTER, URPC_CONNECTION_IDLE_TIMEOUT_COUNTER,
+        URPC_LISTENER_FAILURE_COUNTER, URPC_LISTENER_RESTART_COUNTER,
+    };
     use crate::rpc::DefaultRpcService;
     use crate::runtime::manager::RuntimeManager;
     use crate::storage::StorageService;
+    use crate::store::Block;
+    use crate::urpc::command::GetLocalDataRequestCommand;
+    use crate::urpc::connection::Connection;
     use crate::urpc::frame::Frame;
+    use crate::urpc::read_coalescer::ReadCoalesceOptions;
+    use crate::urpc::server::{run, RestartTracker, UrpcServerOptions};
+    use bytes::Bytes;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn options(
+        max_connections: usize,
+        idle_timeout: Duration,
+        shutdown_grace_period: Duration,
+        read_coalesce: Option<ReadCoalesceOptions>,
+    ) -> UrpcServerOptions {
+        UrpcServerOptions {
+            max_connections,
+            idle_timeout,
+            shutdown_grace_period,
+            read_coalesce,
+            accept_max_backoff: Duration::from_secs(64),
+            listener_max_restarts: 10,
+            listener_restart_backoff: Duration::from_secs(5),
+            listener_healthy_reset: Duration::from_secs(600),
+        }
+    }
 
     #[tokio::test]
     #[ignore]
@@ -194,11 +463,491 @@ mod test {
             runtime_manager.clone(),
             app_manager_ref.clone(),
             &DecommissionManager::new(&app_manager_ref),
+            &HealthService::new(&app_manager_ref, &storage, &config.health_service_config),
         )?;
 
         Ok(())
     }
 
+    #[test]
+    fn get_local_data_round_trip_test() -> anyhow::Result<()> {
+        let app_id = "get_local_data_round_trip_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        // the huge partition is spilled to the localfile directly
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        let blocks = vec![
+            Block {
+                block_id: 0,
+                length: 12,
+                uncompress_length: 12,
+                crc: 0,
+                data: Bytes::from_static(b"hello world!"),
+                task_attempt_id: 0,
+            },
+            Block {
+                block_id: 1,
+                length: 12,
+                uncompress_length: 12,
+                crc: 0,
+                data: Bytes::from_static(b"hello china!"),
+                task_attempt_id: 0,
+            },
+        ];
+        runtime_manager.wait(app.insert(WritingViewContext::new_with_size(
+            uid.clone(),
+            blocks,
+            24,
+        )))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            storage.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(storage.get_memory_buffer_size(&uid))
+                    .unwrap()
+                    == 0
+        });
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let server_app_manager_ref = app_manager_ref.clone();
+        runtime_manager.default_runtime.spawn(async move {
+            let listener = TcpListener::from_std(listener).unwrap();
+            run(
+                listener,
+                options(
+                    40000,
+                    Duration::from_secs(600),
+                    Duration::from_secs(10),
+                    None,
+                ),
+                std::future::pending::<()>(),
+                server_app_manager_ref,
+            )
+            .await
+        });
+
+        let read = |request_id: i64, app_id: &str, offset: i64, length: i32| {
+            let request = GetLocalDataRequestCommand {
+                request_id,
+                app_id: app_id.to_string(),
+                shuffle_id: 1,
+                partition_id: 0,
+                partition_num_per_range: 1,
+                partition_num: 1,
+                offset,
+                length,
+                timestamp: 0,
+            };
+            runtime_manager.wait(async move {
+                let mut conn = Connection::new(TcpStream::connect(addr).await?);
+                conn.write_frame(&Frame::GetLocalData(request)).await?;
+                match conn.read_frame().await? {
+                    Some(Frame::GetLocalDataResponse(resp)) => Ok(resp),
+                    frame => Err(anyhow::anyhow!("unexpected frame: {:?}", frame)),
+                }
+            })
+        };
+
+        let resp = read(1, app_id, 0, 24)?;
+        assert_eq!(1, resp.request_id);
+        assert_eq!(StatusCode::SUCCESS as i32, resp.status_code);
+        assert_eq!(b"hello world!hello china!", resp.data.as_ref());
+
+        let resp = read(2, app_id, 12, 12)?;
+        assert_eq!(2, resp.request_id);
+        assert_eq!(b"hello china!", resp.data.as_ref());
+
+        let resp = read(3, "non_existent_app", 0, 24)?;
+        assert_eq!(StatusCode::NO_REGISTER as i32, resp.status_code);
+        assert!(resp.data.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn idle_connection_timeout_test() -> anyhow::Result<()> {
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(run(
+            listener,
+            options(
+                40000,
+                Duration::from_millis(500),
+                Duration::from_secs(10),
+                None,
+            ),
+            std::future::pending::<()>(),
+            app_manager_ref,
+        ));
+
+        // the client connects but sends nothing, which is closed by the server after the timeout
+        let timed_out = URPC_CONNECTION_IDLE_TIMEOUT_COUNTER.get();
+        let mut stream = TcpStream::connect(addr).await?;
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await??;
+        assert_eq!(0, read);
+        assert!(URPC_CONNECTION_IDLE_TIMEOUT_COUNTER.get() > timed_out);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn listener_restart_test() -> anyhow::Result<()> {
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+
+        // the socket is bound but never listened, so that every accept fails
+        let sock = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None)?;
+        sock.set_reuse_address(true)?;
+        sock.set_reuse_port(true)?;
+        sock.set_nonblocking(true)?;
+        sock.bind(&"127.0.0.1:0".parse::<SocketAddr>()?.into())?;
+        let broken: std::net::TcpListener = sock.into();
+        let addr = broken.local_addr()?;
+
+        let failures = URPC_LISTENER_FAILURE_COUNTER.get();
+        let restarts = URPC_LISTENER_RESTART_COUNTER.get();
+
+        let mut options = options(
+            40000,
+            Duration::from_millis(500),
+            Duration::from_secs(10),
+            None,
+        );
+        options.accept_max_backoff = Duration::ZERO;
+        options.listener_restart_backoff = Duration::from_millis(10);
+        tokio::spawn(run(
+            TcpListener::from_std(broken)?,
+            options,
+            std::future::pending::<()>(),
+            app_manager_ref,
+        ));
+
+        // the listener is rebound on the same address, and then serves the new connections
+        let mut stream = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match TcpStream::connect(addr).await {
+                    Ok(stream) => return stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await?;
+        assert!(URPC_LISTENER_FAILURE_COUNTER.get() > failures);
+        assert!(URPC_LISTENER_RESTART_COUNTER.get() > restarts);
+
+        // the connection accepted by the restarted listener is closed after the idle timeout
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await??;
+        assert_eq!(0, read);
+
+        Ok(())
+    }
+
+    #[test]
+    fn listener_restarts_reset_test() {
+        let mut tracker = RestartTracker::new(2, Duration::from_millis(200));
+
+        // the rapid failures exhaust the max restarts
+        for _ in 0..2 {
+            tracker.on_started();
+            tracker.on_failure();
+            assert!(tracker.try_restart());
+        }
+        tracker.on_started();
+        tracker.on_failure();
+        assert!(!tracker.try_restart());
+
+        // the restarts are reset after running healthy for the reset period
+        tracker.on_started();
+        std::thread::sleep(Duration::from_millis(300));
+        tracker.on_failure();
+        assert!(tracker.try_restart());
+        assert_eq!(1, tracker.restarts);
+    }
+
+    #[tokio::test]
+    async fn max_connections_test() -> anyhow::Result<()> {
+        let config = mock_config();
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(run(
+            listener,
+            options(2, Duration::from_secs(600), Duration::from_secs(10), None),
+            std::future::pending::<()>(),
+            app_manager_ref,
+        ));
+
+        let request = |request_id: i64| {
+            Frame::GetLocalData(GetLocalDataRequestCommand {
+                request_id,
+                app_id: "max_connections_test".to_string(),
+                shuffle_id: 1,
+                partition_id: 0,
+                partition_num_per_range: 1,
+                partition_num: 1,
+                offset: 0,
+                length: 1,
+                timestamp: 0,
+            })
+        };
+        async fn read_response(conn: &mut Connection) -> anyhow::Result<i64> {
+            match conn.read_frame().await? {
+                Some(Frame::GetLocalDataResponse(resp)) => Ok(resp.request_id),
+                frame => Err(anyhow::anyhow!("unexpected frame: {:?}", frame)),
+            }
+        }
+
+        // the first two connections are served
+        let mut conn_1 = Connection::new(TcpStream::connect(addr).await?);
+        conn_1.write_frame(&request(1)).await?;
+        assert_eq!(1, read_response(&mut conn_1).await?);
+        let mut conn_2 = Connection::new(TcpStream::connect(addr).await?);
+        conn_2.write_frame(&request(2)).await?;
+        assert_eq!(2, read_response(&mut conn_2).await?);
+
+        // the third one waits
+        let mut conn_3 = Connection::new(TcpStream::connect(addr).await?);
+        conn_3.write_frame(&request(3)).await?;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), read_response(&mut conn_3))
+                .await
+                .is_err()
+        );
+
+        // and then is served after one closes
+        drop(conn_1);
+        let request_id =
+            tokio::time::timeout(Duration::from_secs(5), read_response(&mut conn_3)).await??;
+        assert_eq!(3, request_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn graceful_drain_test() -> anyhow::Result<()> {
+        let app_id = "graceful_drain_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+        let disk_root = config.localfile_store.as_ref().unwrap().data_paths[0].clone();
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 12)))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            storage.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(storage.get_memory_buffer_size(&uid))
+                    .unwrap()
+                    == 0
+        });
+
+        // the local read is slowed down to keep the command in flight
+        FAULT_INJECTOR.enable(true);
+        FAULT_INJECTOR.register(FaultSpec {
+            target: FaultTarget {
+                disk_root: Some(disk_root),
+                store_type: Some(StorageType::LOCALFILE),
+                operation: Some(FaultOperation::READ),
+            },
+            mode: FaultMode::LATENCY_MS(1000),
+            probability: 1.0,
+            ttl_sec: 60,
+        })?;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = runtime_manager.default_runtime.spawn(async move {
+            let listener = TcpListener::from_std(listener).unwrap();
+            run(
+                listener,
+                options(
+                    40000,
+                    Duration::from_secs(600),
+                    Duration::from_secs(5),
+                    None,
+                ),
+                shutdown_rx,
+                app_manager_ref,
+            )
+            .await
+        });
+
+        let resp = runtime_manager.wait(async move {
+            let mut conn = Connection::new(TcpStream::connect(addr).await?);
+            conn.write_frame(&Frame::GetLocalData(GetLocalDataRequestCommand {
+                request_id: 1,
+                app_id: app_id.to_string(),
+                shuffle_id: 1,
+                partition_id: 0,
+                partition_num_per_range: 1,
+                partition_num: 1,
+                offset: 0,
+                length: 24,
+                timestamp: 0,
+            }))
+            .await?;
+
+            // shutdown in the middle of the command
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _ = shutdown_tx.send(());
+
+            match tokio::time::timeout(Duration::from_secs(5), conn.read_frame()).await?? {
+                Some(Frame::GetLocalDataResponse(resp)) => Ok(resp),
+                frame => Err(anyhow::anyhow!("unexpected frame: {:?}", frame)),
+            }
+        })?;
+        assert_eq!(1, resp.request_id);
+        assert_eq!(StatusCode::SUCCESS as i32, resp.status_code);
+        assert_eq!(24, resp.data.len());
+
+        // the server exits after draining, without waiting for the whole grace period
+        runtime_manager
+            .wait(async move { tokio::time::timeout(Duration::from_secs(3), server).await })??;
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_coalesce_test() -> anyhow::Result<()> {
+        let app_id = "read_coalesce_test";
+        let runtime_manager: RuntimeManager = Default::default();
+
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.hybrid_store.memory_spill_high_watermark = 1.0;
+        config
+            .hybrid_store
+            .huge_partition_memory_spill_to_hdfs_threshold_size = "10B".to_string();
+        config.app_config.partition_limit_enable = true;
+        config.app_config.partition_limit_threshold = "10B".to_string();
+
+        let reconf_manager = ReconfigurableConfManager::new(&config, None)?;
+        let storage = StorageService::init(&runtime_manager, &config);
+        let app_manager_ref =
+            AppManager::get_ref(runtime_manager.clone(), config, &storage, &reconf_manager);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let uid = PartitionedUId::from(app_id.to_string(), 1, 0);
+        runtime_manager.wait(app.insert(mock_writing_context(app_id, 1, 0, 2, 12)))?;
+        awaitility::at_most(Duration::from_secs(10)).until(|| {
+            storage.get_in_flight_size().unwrap() == 0
+                && runtime_manager
+                    .wait(storage.get_memory_buffer_size(&uid))
+                    .unwrap()
+                    == 0
+        });
+
+        let read_coalesce = ReadCoalesceOptions::parse(&ReadCoalesceConfig {
+            tiny_read_size: "16B".to_string(),
+            merged_read_size: "1K".to_string(),
+            window_ms: 10000,
+        })?;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        runtime_manager.default_runtime.spawn(async move {
+            let listener = TcpListener::from_std(listener).unwrap();
+            run(
+                listener,
+                options(
+                    40000,
+                    Duration::from_secs(600),
+                    Duration::from_secs(10),
+                    Some(read_coalesce),
+                ),
+                std::future::pending::<()>(),
+                app_manager_ref,
+            )
+            .await
+        });
+
+        // the 4 tiny consecutive reads are served by one merged disk read
+        let coalesced = URPC_COALESCED_READ_COUNTER.get();
+        let lengths = runtime_manager.wait(async move {
+            let mut conn = Connection::new(TcpStream::connect(addr).await?);
+            let mut lengths = vec![];
+            for idx in 0..4 {
+                conn.write_frame(&Frame::GetLocalData(GetLocalDataRequestCommand {
+                    request_id: idx,
+                    app_id: app_id.to_string(),
+                    shuffle_id: 1,
+                    partition_id: 0,
+                    partition_num_per_range: 1,
+                    partition_num: 1,
+                    offset: idx * 6,
+                    length: 6,
+                    timestamp: 0,
+                }))
+                .await?;
+                match conn.read_frame().await? {
+                    Some(Frame::GetLocalDataResponse(resp))
+                        if resp.status_code == StatusCode::SUCCESS as i32 =>
+                    {
+                        lengths.push(resp.data.len())
+                    }
+                    frame => return Err(anyhow::anyhow!("unexpected frame: {:?}", frame)),
+                }
+            }
+            Ok(lengths)
+        })?;
+        assert_eq!(vec![6, 6, 6, 6], lengths);
+        assert_eq!(3, URPC_COALESCED_READ_COUNTER.get() - coalesced);
+
+        Ok(())
+    }
+
     #[test]
     fn enum_test() {
         let frame = Frame::GetLocalData(Default::default());
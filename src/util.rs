@@ -19,11 +19,18 @@ use bytes::Bytes;
 use crc32fast::Hasher;
 
 use crate::config::Config;
+use crate::error::WorkerError;
+use crate::metric::TOTAL_READ_DEADLINE_EXCEEDED;
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use bytesize::ByteSize;
+use once_cell::sync::Lazy;
 
 const WORKER_IP: &str = "WORKER_IP";
 
@@ -83,6 +90,47 @@ pub fn now_timestamp_as_sec() -> u64 {
     timestamp
 }
 
+static MONOTONIC_START: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// The elapsed seconds since the process started, which is immune to the wall clock jump
+/// and should be used for the interval math.
+pub fn now_monotonic_as_sec() -> u64 {
+    MONOTONIC_START.elapsed().as_secs()
+}
+
+/// Fails fast if the deadline has been exceeded, which is unlimited if None.
+pub fn check_deadline(deadline: Option<Instant>) -> Result<(), WorkerError> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => {
+            TOTAL_READ_DEADLINE_EXCEEDED.inc();
+            Err(WorkerError::DEADLINE_EXCEEDED)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs the future until the deadline. Once exceeded, the future is dropped in place
+/// to release the partition locks and the in-flight io held by it.
+pub async fn run_until_deadline<T, F>(
+    deadline: Option<Instant>,
+    future: F,
+) -> Result<T, WorkerError>
+where
+    F: Future<Output = Result<T, WorkerError>>,
+{
+    check_deadline(deadline)?;
+    match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline.into(), future).await {
+            Ok(res) => res,
+            Err(_) => {
+                TOTAL_READ_DEADLINE_EXCEEDED.inc();
+                Err(WorkerError::DEADLINE_EXCEEDED)
+            }
+        },
+        _ => future.await,
+    }
+}
+
 pub fn is_port_used(port: u16) -> bool {
     match std::net::TcpListener::bind(SocketAddr::new(
         IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
@@ -94,14 +142,41 @@ pub fn is_port_used(port: u16) -> bool {
 }
 
 pub fn parse_raw_to_bytesize(s: &str) -> u64 {
-    s.parse::<ByteSize>().unwrap().0
+    try_parse_raw_to_bytesize(s).unwrap()
+}
+
+pub fn try_parse_raw_to_bytesize(s: &str) -> anyhow::Result<u64> {
+    s.parse::<ByteSize>()
+        .map(|size| size.0)
+        .map_err(|err| anyhow::anyhow!("Illegal byte size: [{}]. err: {}", s, err))
+}
+
+/// Writes into the temporary file and then renames it, so that the crash won't leave the
+/// broken file. Both the file and the parent dir are synced to make the rename durable.
+pub fn write_atomically(path: &str, content: &[u8]) -> std::io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    if let Some(parent) = Path::new(path)
+        .parent()
+        .filter(|x| !x.as_os_str().is_empty())
+    {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::util::{get_crc, is_port_used, now_timestamp_as_sec};
+    use crate::error::WorkerError;
+    use crate::util::{
+        get_crc, is_port_used, now_timestamp_as_sec, run_until_deadline, write_atomically,
+    };
     use bytes::Bytes;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_port() {
@@ -116,11 +191,41 @@ mod test {
         assert_eq!(false, is_port_used(port));
     }
 
+    #[test]
+    fn write_atomically_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("write_atomically_test")?;
+        let path = format!("{}/file", temp_dir.path().to_str().unwrap());
+        write_atomically(&path, b"hello")?;
+        write_atomically(&path, b"world")?;
+        assert_eq!(b"world".to_vec(), std::fs::read(&path)?);
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        Ok(())
+    }
+
     #[test]
     fn time_test() {
         println!("{}", now_timestamp_as_sec());
     }
 
+    #[tokio::test]
+    async fn deadline_test() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<_, WorkerError>(1)
+        };
+        let start = Instant::now();
+        let res = run_until_deadline(Some(Instant::now() + Duration::from_millis(10)), slow).await;
+        assert!(matches!(res, Err(WorkerError::DEADLINE_EXCEEDED)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the exceeded deadline fails fast without polling the future
+        let res = run_until_deadline(Some(Instant::now()), async { Ok::<_, WorkerError>(1) }).await;
+        assert!(matches!(res, Err(WorkerError::DEADLINE_EXCEEDED)));
+
+        let res = run_until_deadline(None, async { Ok::<_, WorkerError>(1) }).await;
+        assert_eq!(1, res.unwrap());
+    }
+
     #[test]
     fn crc_test() {
         let data = Bytes::from("hello world! hello china!");